@@ -0,0 +1,111 @@
+//! Microbenchmarks for BitVector construction
+//!
+//! Compares bit-by-bit construction (push / append_bits loops) against the
+//! word-wise bulk operations, and absolute-position reads against the BitReader
+//! cursor for sequential fixed-width decoding.
+
+use compression_benchmark_rs::bit_vector::BitVector;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// Number of bits in the benchmarked vectors
+const N_BITS: usize = 1 << 20;
+
+fn source_words() -> Vec<u64> {
+    (0..N_BITS as u64 / 64).map(|i| i.wrapping_mul(0x9E37_79B9_7F4A_7C15)).collect()
+}
+
+fn bench_append(c: &mut Criterion) {
+    let words = source_words();
+    let mut src = BitVector::new();
+    src.append_u64_slice(&words, N_BITS);
+
+    let mut group = c.benchmark_group("bit_vector_append");
+
+    group.bench_function("push", |b| {
+        b.iter(|| {
+            let mut bv = BitVector::with_capacity(N_BITS + 1);
+            bv.push(true);
+            for i in 0..N_BITS {
+                bv.push(unsafe { src.get_unchecked(i) });
+            }
+            black_box(bv)
+        })
+    });
+
+    group.bench_function("append_bits_13", |b| {
+        b.iter(|| {
+            let mut bv = BitVector::with_capacity(N_BITS + 1);
+            bv.push(true);
+            let mut offset = 0;
+            while offset + 13 <= N_BITS {
+                bv.append_bits(unsafe { src.get_bits_unchecked(offset, 13) }, 13);
+                offset += 13;
+            }
+            black_box(bv)
+        })
+    });
+
+    group.bench_function("append_u64_slice_unaligned", |b| {
+        b.iter(|| {
+            let mut bv = BitVector::with_capacity(N_BITS + 1);
+            bv.push(true);
+            bv.append_u64_slice(&words, N_BITS);
+            black_box(bv)
+        })
+    });
+
+    group.bench_function("extend_from_bitvector", |b| {
+        b.iter(|| {
+            let mut bv = BitVector::with_capacity(N_BITS + 1);
+            bv.push(true);
+            bv.extend_from_bitvector(&src);
+            black_box(bv)
+        })
+    });
+
+    group.bench_function("copy_bits_unaligned", |b| {
+        b.iter(|| {
+            let mut bv = BitVector::with_capacity(N_BITS);
+            bv.copy_bits(&src, 3, N_BITS - 3);
+            black_box(bv)
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_read(c: &mut Criterion) {
+    const WIDTH: usize = 13;
+    let words = source_words();
+    let mut src = BitVector::new();
+    src.append_u64_slice(&words, N_BITS);
+    let n_fields = N_BITS / WIDTH;
+
+    let mut group = c.benchmark_group("bit_vector_read_13");
+
+    group.bench_function("get_bits_unchecked", |b| {
+        b.iter(|| {
+            let mut sum = 0u64;
+            for i in 0..n_fields {
+                sum = sum.wrapping_add(unsafe { src.get_bits_unchecked(i * WIDTH, WIDTH) });
+            }
+            black_box(sum)
+        })
+    });
+
+    group.bench_function("bit_reader", |b| {
+        b.iter(|| {
+            let mut sum = 0u64;
+            let mut reader = src.reader(0);
+            for _ in 0..n_fields {
+                sum = sum.wrapping_add(unsafe { reader.read_unchecked(WIDTH) });
+            }
+            black_box(sum)
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_append, bench_read);
+criterion_main!(benches);
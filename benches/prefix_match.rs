@@ -0,0 +1,123 @@
+//! Microbenchmarks for common prefix length computation
+//!
+//! Compares the byte-by-byte loop previously used to compute shared prefixes against
+//! the word-wise fallback and the vectorized implementation, on single comparisons of
+//! growing prefix lengths, on front coding compression of strings sharing long
+//! prefixes (sorted paths below deep directories), and on longest prefix matching of
+//! these strings with the trie of OnPairBV, holding long tokens.
+
+use compression_benchmark_rs::compressor::front_coding::FrontCodingCompressor;
+use compression_benchmark_rs::compressor::prefix_match::{common_prefix_length, common_prefix_length_scalar};
+use compression_benchmark_rs::compressor::prefix_trie::PrefixTrie;
+use compression_benchmark_rs::compressor::Compressor;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/// Number of strings of the long-prefix dataset
+const N_STRINGS: usize = 100000;
+
+fn byte_loop(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(x, y)| x == y).count()
+}
+
+/// Returns two strings sharing a prefix of the given length, then differing
+fn string_pair(prefix_length: usize) -> (Vec<u8>, Vec<u8>) {
+    let a: Vec<u8> = (0..prefix_length + 16).map(|i| b'a' + (i % 26) as u8).collect();
+    let mut b = a.clone();
+    b[prefix_length] = b'#';
+    (a, b)
+}
+
+/// Returns sorted paths whose consecutive strings share about 200 bytes
+fn long_prefix_dataset() -> (Vec<u8>, Vec<usize>) {
+    let root = "/srv/storage/archive/2024/collections/long-running-experiment/replica-0001/".repeat(2);
+    let mut data = Vec::new();
+    let mut end_positions = vec![0];
+    for i in 0..N_STRINGS {
+        data.extend_from_slice(format!("{}{:04}/segment-{:08}.parquet", root, i / 1000, i).as_bytes());
+        end_positions.push(data.len());
+    }
+    (data, end_positions)
+}
+
+fn bench_common_prefix_length(c: &mut Criterion) {
+    let mut group = c.benchmark_group("common_prefix_length");
+
+    for prefix_length in [8, 64, 1024] {
+        let (a, b) = string_pair(prefix_length);
+        group.bench_with_input(BenchmarkId::new("byte_loop", prefix_length), &prefix_length, |bench, _| {
+            bench.iter(|| byte_loop(black_box(&a), black_box(&b)))
+        });
+        group.bench_with_input(BenchmarkId::new("scalar", prefix_length), &prefix_length, |bench, _| {
+            bench.iter(|| common_prefix_length_scalar(black_box(&a), black_box(&b)))
+        });
+        group.bench_with_input(BenchmarkId::new("simd", prefix_length), &prefix_length, |bench, _| {
+            bench.iter(|| common_prefix_length(black_box(&a), black_box(&b)))
+        });
+    }
+
+    group.finish();
+}
+
+fn bench_front_coding(c: &mut Criterion) {
+    let (data, end_positions) = long_prefix_dataset();
+    let strings: Vec<&[u8]> = end_positions.windows(2).map(|w| &data[w[0]..w[1]]).collect();
+
+    let mut group = c.benchmark_group("front_coding_long_prefixes");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+
+    group.bench_function("shared_prefixes_byte_loop", |b| {
+        b.iter(|| strings.windows(2).map(|w| byte_loop(w[1], w[0])).sum::<usize>())
+    });
+
+    group.bench_function("shared_prefixes_simd", |b| {
+        b.iter(|| strings.windows(2).map(|w| common_prefix_length(w[1], w[0])).sum::<usize>())
+    });
+
+    group.bench_function("compress", |b| {
+        b.iter(|| {
+            let mut compressor = FrontCodingCompressor::new(data.len(), N_STRINGS);
+            compressor.compress(&data, &end_positions);
+            black_box(compressor.space_used_bytes())
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_prefix_trie(c: &mut Criterion) {
+    let (data, end_positions) = long_prefix_dataset();
+    let strings: Vec<&[u8]> = end_positions.windows(2).map(|w| &data[w[0]..w[1]]).collect();
+
+    // Single bytes, then the directories and the first strings as long tokens
+    let mut trie = PrefixTrie::new();
+    for byte in 0..=255u8 {
+        trie.insert(&[byte], byte as usize);
+    }
+    for (id, string) in strings.iter().step_by(100).enumerate() {
+        let directory_end = string.iter().rposition(|&byte| byte == b'/').unwrap() + 1;
+        trie.insert(&string[..directory_end], 256 + 2 * id);
+        trie.insert(string, 256 + 2 * id + 1);
+    }
+
+    let mut group = c.benchmark_group("prefix_trie_long_tokens");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+
+    group.bench_function("parse", |b| {
+        b.iter(|| {
+            let mut n_tokens = 0;
+            for string in strings.iter() {
+                let mut pos = 0;
+                while pos < string.len() {
+                    pos += trie.find_longest_match(&string[pos..]).unwrap().1;
+                    n_tokens += 1;
+                }
+            }
+            black_box(n_tokens)
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_common_prefix_length, bench_front_coding, bench_prefix_trie);
+criterion_main!(benches);
@@ -0,0 +1,70 @@
+//! Build script generating the C header of the FFI layer
+//!
+//! The header is written to `include/compression_benchmark.h` when the `ffi`
+//! feature is enabled and the declarations changed; configuration lives in
+//! `cbindgen.toml`.
+//!
+//! With the `fsst-ffi` feature, the reference FSST library is linked from the
+//! directory in the `FSST_DIR` environment variable (a built checkout of
+//! github.com/cwida/fsst, holding `fsst.h` and `libfsst.a`), together with the shim
+//! exporting its decoder (see `src/compressor/fsst_ffi.rs`).
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "ffi")]
+    generate_header();
+    #[cfg(feature = "fsst-ffi")]
+    link_fsst();
+}
+
+#[cfg(feature = "ffi")]
+fn generate_header() {
+    println!("cargo:rerun-if-changed=src/ffi");
+    println!("cargo:rerun-if-changed=src/compressor/mod.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    // Only the FFI module is parsed, so that no other public constants leak into the header;
+    // the padding it re-exports from the compressors is defined from their source
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir)).expect("Failed to read cbindgen.toml");
+    let padding = buffer_padding(&format!("{}/src/compressor/mod.rs", crate_dir));
+    let bindings = cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(format!("{}/src/ffi/mod.rs", crate_dir))
+        .with_after_include(format!("\n/**\n * Extra bytes required at the end of every output buffer\n */\n#define CB_BUFFER_PADDING {}", padding))
+        .generate()
+        .expect("Failed to generate C header");
+
+    // The header is checked in: rewrite it only when the declarations change
+    let mut header = Vec::new();
+    bindings.write(&mut header);
+    let path = format!("{}/include/compression_benchmark.h", crate_dir);
+    if std::fs::read(&path).ok().as_deref() != Some(header.as_slice()) {
+        std::fs::write(&path, header).expect("Failed to write C header");
+    }
+}
+
+/// Reads the value of `BUFFER_PADDING` from the source of the compressor module
+#[cfg(feature = "ffi")]
+fn buffer_padding(path: &str) -> usize {
+    let source = std::fs::read_to_string(path).expect("Failed to read the compressor module");
+    source
+        .lines()
+        .find_map(|line| line.strip_prefix("pub const BUFFER_PADDING: usize = ")?.strip_suffix(';')?.parse().ok())
+        .expect("BUFFER_PADDING not found in the compressor module")
+}
+
+#[cfg(feature = "fsst-ffi")]
+fn link_fsst() {
+    println!("cargo:rerun-if-changed=src/compressor/fsst_shim.cpp");
+    println!("cargo:rerun-if-env-changed=FSST_DIR");
+
+    let fsst_dir = std::env::var("FSST_DIR").expect("The fsst-ffi feature requires FSST_DIR, the directory of a built FSST checkout");
+    cc::Build::new()
+        .cpp(true)
+        .flag_if_supported("-std=c++17")
+        .include(&fsst_dir)
+        .file("src/compressor/fsst_shim.cpp")
+        .compile("fsst_shim");
+    println!("cargo:rustc-link-search=native={}", fsst_dir);
+}
@@ -0,0 +1,148 @@
+//! Key-value service backed by compressed string stores
+//!
+//! Loads tab-separated `key<TAB>value` lines, compresses the keys and the values into two
+//! `CompressedStringStore`s, and answers commands read from standard input, one per line:
+//! - `get <key>`: prints the value of a key
+//! - `stats`: prints the number of entries and the memory used by the stores
+//! - `save <directory>`: saves the stores, to be served again with `--load`
+//!
+//! ```bash
+//! cargo run --release --example kv_service -- pairs.tsv --algorithm=onpair16
+//! ```
+
+use compression_benchmark_rs::benchmark_utils::take_option;
+use compression_benchmark_rs::store::{CompressedStringStore, StoreConfig};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+/// File names of the saved stores within a directory
+const KEYS_FILE: &str = "keys.store";
+const VALUES_FILE: &str = "values.store";
+
+/// Key-value map whose keys and values are held compressed
+struct KeyValueService {
+    keys: CompressedStringStore,
+    values: CompressedStringStore,      // Value of the i-th key at index i
+    index: HashMap<u64, Vec<usize>>,    // Indices of the keys by hash
+    buffer: Vec<u8>,                    // Reused output buffer
+}
+
+impl KeyValueService {
+    /// Builds the service from the lines of a tab-separated file
+    fn from_tsv(path: &Path, config: &StoreConfig) -> Result<Self, String> {
+        let content = fs::read(path).map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+        let pairs: Vec<(&[u8], &[u8])> = content
+            .split(|&b| b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| match line.iter().position(|&b| b == b'\t') {
+                Some(tab) => (&line[..tab], &line[tab + 1..]),
+                None => (line, &line[line.len()..]),
+            })
+            .collect();
+
+        let keys = CompressedStringStore::build(pairs.iter().map(|&(key, _)| key), config)?;
+        let values = CompressedStringStore::build(pairs.iter().map(|&(_, value)| value), config)?;
+        Ok(Self::new(keys, values))
+    }
+
+    /// Loads the service from stores saved by `save`
+    fn load(directory: &Path) -> io::Result<Self> {
+        let keys = CompressedStringStore::load(&directory.join(KEYS_FILE))?;
+        let values = CompressedStringStore::load(&directory.join(VALUES_FILE))?;
+        Ok(Self::new(keys, values))
+    }
+
+    /// Indexes the keys of the stores
+    fn new(mut keys: CompressedStringStore, values: CompressedStringStore) -> Self {
+        let mut index: HashMap<u64, Vec<usize>> = HashMap::new();
+        let mut buffer = Vec::new();
+        for i in 0..keys.len() {
+            keys.get(i, &mut buffer);
+            index.entry(hash(&buffer)).or_default().push(i);
+        }
+        KeyValueService { keys, values, index, buffer }
+    }
+
+    /// Returns the value of a key, the first one for duplicate keys
+    fn get(&mut self, key: &[u8]) -> Option<&[u8]> {
+        let candidates = self.index.get(&hash(key))?;
+        for &i in candidates {
+            // Keys colliding on the hash are told apart by comparing them
+            self.keys.get(i, &mut self.buffer);
+            if self.buffer == key {
+                self.values.get(i, &mut self.buffer);
+                return Some(&self.buffer);
+            }
+        }
+        None
+    }
+
+    /// Saves the stores to a directory
+    fn save(&self, directory: &Path) -> io::Result<()> {
+        fs::create_dir_all(directory)?;
+        self.keys.save(&directory.join(KEYS_FILE))?;
+        self.values.save(&directory.join(VALUES_FILE))
+    }
+}
+
+/// Hashes a key for the in-memory index
+fn hash(key: &[u8]) -> u64 {
+    xxhash_rust::xxh3::xxh3_64(key)
+}
+
+fn main() {
+    let mut args: Vec<String> = std::env::args().collect();
+    let algorithm = take_option(&mut args, "--algorithm");
+    let load = take_option(&mut args, "--load");
+    let mut config = StoreConfig::default();
+    if let Some(algorithm) = algorithm {
+        config.algorithm = algorithm;
+    }
+
+    let service = match (load, args.get(1)) {
+        (Some(directory), _) => KeyValueService::load(Path::new(&directory)).map_err(|e| e.to_string()),
+        (None, Some(path)) => KeyValueService::from_tsv(Path::new(path), &config),
+        (None, None) => {
+            eprintln!("Usage: {} <pairs.tsv> [--algorithm=NAME] | --load=DIRECTORY", args[0]);
+            eprintln!("  <pairs.tsv>       - One key<TAB>value pair per line");
+            eprintln!("  --algorithm=NAME  - Compression algorithm (default: {})", config.algorithm);
+            eprintln!("  --load=DIRECTORY  - Serve the stores saved in a directory");
+            std::process::exit(1);
+        }
+    };
+    let mut service = service.unwrap_or_else(|message| {
+        eprintln!("Error: {}", message);
+        std::process::exit(1);
+    });
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    for line in io::stdin().lock().lines() {
+        let line = line.expect("Failed to read command");
+        let (command, argument) = line.split_once(' ').unwrap_or((&line, ""));
+        match command {
+            "get" => match service.get(argument.as_bytes()) {
+                Some(value) => writeln!(out, "{}", String::from_utf8_lossy(value)),
+                None => writeln!(out, "(not found)"),
+            },
+            "stats" => writeln!(
+                out,
+                "{} entries compressed with {} ({}): {} bytes of keys and values in {} bytes",
+                service.keys.len(),
+                service.keys.name(),
+                service.keys.algorithm(),
+                service.keys.data_size() + service.values.data_size(),
+                service.keys.memory_usage() + service.values.memory_usage()
+            ),
+            "save" => match service.save(Path::new(argument)) {
+                Ok(()) => writeln!(out, "saved to {}", argument),
+                Err(e) => writeln!(out, "error: {}", e),
+            },
+            "" => continue,
+            _ => writeln!(out, "unknown command '{}': get <key> | stats | save <directory>", command),
+        }
+        .expect("Failed to write response");
+    }
+}
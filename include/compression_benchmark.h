@@ -0,0 +1,145 @@
+#ifndef COMPRESSION_BENCHMARK_H
+#define COMPRESSION_BENCHMARK_H
+
+/* Generated with cbindgen:0.26.0 */
+
+/* Generated by cbindgen from src/ffi; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Extra bytes required at the end of every output buffer
+ */
+#define CB_BUFFER_PADDING 1024
+
+/**
+ * Error codes returned by all FFI functions
+ */
+typedef enum CbStatus {
+  CB_STATUS_OK = 0,
+  CB_STATUS_NULL_POINTER = 1,
+  CB_STATUS_UNKNOWN_COMPRESSOR = 2,
+  CB_STATUS_INVALID_ARGUMENT = 3,
+  CB_STATUS_INVALID_STATE = 4,
+  CB_STATUS_INDEX_OUT_OF_BOUNDS = 5,
+  CB_STATUS_BUFFER_TOO_SMALL = 6,
+  CB_STATUS_PANIC = 7,
+} CbStatus;
+
+/**
+ * Opaque compressor handle
+ *
+ * Keeps the dataset sizes given at creation to validate the compressed dataset and
+ * indices; item lengths for buffer checks come from the compressor.
+ */
+typedef struct CbCompressor CbCompressor;
+
+/**
+ * Returns a static, NUL-terminated description of a status code
+ */
+const char *cb_status_message(enum CbStatus status);
+
+/**
+ * Creates a compressor handle
+ *
+ * # Arguments
+ * - `name`: NUL-terminated algorithm identifier (e.g., "onpair16")
+ * - `data_size`: Total size of the dataset in bytes
+ * - `n_elements`: Number of strings in the dataset
+ * - `out_handle`: Receives the new handle, to be released with `cb_compressor_destroy`
+ *
+ * # Safety
+ * `name` must point to a valid NUL-terminated string and `out_handle` to writable memory.
+ */
+enum CbStatus cb_compressor_create(const char *name,
+                                   size_t data_size,
+                                   size_t n_elements,
+                                   struct CbCompressor **out_handle);
+
+/**
+ * Compresses a dataset into the handle
+ *
+ * A handle compresses exactly one dataset, whose sizes must match those given at creation.
+ *
+ * # Arguments
+ * - `handle`: Compressor handle
+ * - `data`: Concatenated strings (`data_len` bytes)
+ * - `end_positions`: Boundary positions of the strings, starting with 0 (`n_end_positions` entries)
+ *
+ * # Safety
+ * `handle` must come from `cb_compressor_create`; `data` and `end_positions` must be
+ * valid for reads of the given lengths (`data` may be null for an empty dataset).
+ */
+enum CbStatus cb_compressor_compress(struct CbCompressor *handle,
+                                     const uint8_t *data,
+                                     size_t data_len,
+                                     const size_t *end_positions,
+                                     size_t n_end_positions);
+
+/**
+ * Reports the length of a string in bytes
+ *
+ * # Safety
+ * `handle` must come from `cb_compressor_create` and `out_len` must point to writable memory.
+ */
+enum CbStatus cb_compressor_item_len(const struct CbCompressor *handle,
+                                     size_t index,
+                                     size_t *out_len);
+
+/**
+ * Retrieves a single string by index
+ *
+ * # Arguments
+ * - `handle`: Compressor handle
+ * - `index`: Zero-based index of the string
+ * - `buffer`: Output buffer of `buffer_len` bytes (item length plus `CB_BUFFER_PADDING`)
+ * - `out_len`: Receives the number of bytes written
+ *
+ * # Safety
+ * `handle` must come from `cb_compressor_create`, `buffer` must be valid for writes of
+ * `buffer_len` bytes and `out_len` must point to writable memory.
+ */
+enum CbStatus cb_compressor_get_item_at(struct CbCompressor *handle,
+                                        size_t index,
+                                        uint8_t *buffer,
+                                        size_t buffer_len,
+                                        size_t *out_len);
+
+/**
+ * Decompresses the entire dataset
+ *
+ * # Arguments
+ * - `handle`: Compressor handle
+ * - `buffer`: Output buffer of `buffer_len` bytes (data size plus `CB_BUFFER_PADDING`)
+ * - `out_len`: Receives the number of bytes written
+ *
+ * # Safety
+ * `handle` must come from `cb_compressor_create`, `buffer` must be valid for writes of
+ * `buffer_len` bytes and `out_len` must point to writable memory.
+ */
+enum CbStatus cb_compressor_decompress(const struct CbCompressor *handle,
+                                       uint8_t *buffer,
+                                       size_t buffer_len,
+                                       size_t *out_len);
+
+/**
+ * Reports the memory used by the compressed representation in bytes
+ *
+ * # Safety
+ * `handle` must come from `cb_compressor_create` and `out_bytes` must point to writable memory.
+ */
+enum CbStatus cb_compressor_space_used(const struct CbCompressor *handle, size_t *out_bytes);
+
+/**
+ * Releases a compressor handle; null handles are ignored
+ *
+ * # Safety
+ * `handle` must come from `cb_compressor_create` and must not be used afterwards.
+ */
+void cb_compressor_destroy(struct CbCompressor *handle);
+
+#endif /* COMPRESSION_BENCHMARK_H */
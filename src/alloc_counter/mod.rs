@@ -0,0 +1,96 @@
+//! Allocation counting for garbage-free access paths
+//!
+//! Random access is expected to be allocation-free in steady state: compressors keep
+//! their scratch memory (block caches, decoded spans, batch schedules) in buffers
+//! allocated once and reused across accesses. An allocation on the access path costs
+//! a call into the allocator and, for large scratch buffers, page faults, which skew
+//! latency measurements and hide regressions.
+//!
+//! `CountingAllocator` wraps the system allocator and counts the allocations of the
+//! calling thread while counting is enabled (see `AllocationScope`). Counters are
+//! thread-local, so that parallel benchmark workers do not charge each other, and
+//! cost a thread-local check per allocation when disabled. Binaries measuring
+//! allocations install it as their `#[global_allocator]`; otherwise, no allocation
+//! is ever observed.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// System allocator counting the allocations of threads with counting enabled
+pub struct CountingAllocator;
+
+impl CountingAllocator {
+    /// Counts an allocation if the calling thread is counting
+    #[inline(always)]
+    fn record() {
+        // Thread-local storage may already be destroyed while a thread exits
+        let _ = COUNTING.try_with(|counting| {
+            if counting.get() {
+                ALLOCATIONS.with(|allocations| allocations.set(allocations.get() + 1));
+            }
+        });
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        Self::record();
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        Self::record();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // Growing or shrinking a buffer moves it in general, so it counts as an allocation
+        Self::record();
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+/// Counts the allocations of the calling thread while it is alive
+///
+/// Allocations are only observed if `CountingAllocator` is the global allocator (see
+/// `is_installed`). Scopes may be nested, each counting the allocations performed
+/// since it started; dropping a scope restores the counting state it found.
+pub struct AllocationScope {
+    start: usize,   // Allocations counted before the scope started
+    previous: bool, // Whether the thread was counting before the scope started
+}
+
+impl AllocationScope {
+    /// Starts counting the allocations of the calling thread
+    pub fn start() -> Self {
+        let previous = COUNTING.with(|counting| counting.replace(true));
+        AllocationScope { start: ALLOCATIONS.with(Cell::get), previous }
+    }
+
+    /// Returns the number of allocations (and reallocations) since the scope started
+    pub fn count(&self) -> usize {
+        ALLOCATIONS.with(Cell::get) - self.start
+    }
+}
+
+impl Drop for AllocationScope {
+    fn drop(&mut self) {
+        COUNTING.with(|counting| counting.set(self.previous));
+    }
+}
+
+/// Returns whether `CountingAllocator` is the global allocator, by counting a probe allocation
+pub fn is_installed() -> bool {
+    let scope = AllocationScope::start();
+    drop(std::hint::black_box(Box::new(0u64)));
+    scope.count() > 0
+}
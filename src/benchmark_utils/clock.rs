@@ -0,0 +1,199 @@
+//! Clocks timing the probes, with calibrated overhead and resolution
+//!
+//! Random access takes tens of nanoseconds, close to the cost of reading the clock
+//! itself, so that the timer matters: `Instant` reads `CLOCK_MONOTONIC` through the
+//! vDSO on most Linux kernels, but falls back to a system call on some virtual machines
+//! (e.g., with an unstable clock source), and is slewed by NTP. A `TimerSource` selects
+//! the clock of the timing probes (see `Timing`):
+//! - `instant`: `std::time::Instant`, portable (default)
+//! - `monotonic_raw`: `clock_gettime(CLOCK_MONOTONIC_RAW)`, never slewed (Linux only)
+//! - `tsc`: the time-stamp counter read with `rdtsc` after an `lfence`, without any
+//!   kernel involvement (x86_64 with an invariant TSC only)
+//!
+//! Each clock is calibrated once per process: the TSC frequency against `Instant`,
+//! then, for every source, the overhead of a reading (smallest interval between two
+//! consecutive readings) and its resolution (smallest nonzero interval). Readings are
+//! not corrected: the overhead is recorded with the results (see `TimerMetadata`), so
+//! that latencies measured on different machines or with different sources can be
+//! compared by subtracting it.
+
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Consecutive readings sampled to calibrate the overhead and resolution of a clock
+const CALIBRATION_READINGS: usize = 10_000;
+/// Duration over which the TSC frequency is measured against `Instant`
+#[cfg(target_arch = "x86_64")]
+const TSC_CALIBRATION_TIME: Duration = Duration::from_millis(20);
+
+/// Clock of the timing probes
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum TimerSource {
+    #[default]
+    Instant,        // `std::time::Instant`
+    MonotonicRaw,   // `clock_gettime(CLOCK_MONOTONIC_RAW)` (Linux only)
+    Tsc,            // Invariant time-stamp counter (x86_64 only)
+}
+
+impl TimerSource {
+    /// Returns the source identifier
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimerSource::Instant => "instant",
+            TimerSource::MonotonicRaw => "monotonic_raw",
+            TimerSource::Tsc => "tsc",
+        }
+    }
+
+    /// Parses a source from its identifier
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "instant" => Some(TimerSource::Instant),
+            "monotonic_raw" => Some(TimerSource::MonotonicRaw),
+            "tsc" => Some(TimerSource::Tsc),
+            _ => None,
+        }
+    }
+
+    /// Checks whether the source can be read on this machine
+    pub fn is_available(&self) -> bool {
+        match self {
+            TimerSource::Instant => true,
+            TimerSource::MonotonicRaw => cfg!(target_os = "linux"),
+            TimerSource::Tsc => has_invariant_tsc(),
+        }
+    }
+}
+
+/// Timer of a run, recorded with its results
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TimerMetadata {
+    pub source: String,         // Identifier of the source (see `TimerSource`)
+    pub overhead_ns: f64,       // Smallest interval between two consecutive readings, included in every timing
+    pub resolution_ns: f64,     // Smallest nonzero interval between two readings
+}
+
+/// Calibrated clock of a source
+pub struct Clock {
+    source: TimerSource,
+    epoch: Instant,             // Origin of the readings of `Instant`
+    ns_per_tick: f64,           // Nanoseconds per reading unit (1 except for the TSC)
+    overhead_ns: f64,           // Calibrated overhead of a reading
+    resolution_ns: f64,         // Calibrated resolution
+}
+
+impl Clock {
+    /// Returns the calibrated clock of a source, calibrating it on first use
+    ///
+    /// # Returns
+    /// The clock, or an error message if the source is unavailable on this machine
+    pub fn of(source: TimerSource) -> Result<&'static Clock, String> {
+        static CLOCKS: [OnceLock<Clock>; 3] = [OnceLock::new(), OnceLock::new(), OnceLock::new()];
+        if !source.is_available() {
+            return Err(format!("Timer '{}' is not available on this machine", source.name()));
+        }
+        Ok(CLOCKS[source as usize].get_or_init(|| Clock::calibrate(source)))
+    }
+
+    /// Returns the current reading of the clock, in clock units
+    #[inline(always)]
+    pub fn now(&self) -> u64 {
+        match self.source {
+            #[cfg(target_os = "linux")]
+            TimerSource::MonotonicRaw => read_monotonic_raw(),
+            #[cfg(target_arch = "x86_64")]
+            TimerSource::Tsc => read_tsc(),
+            // `Clock::of` rejects the sources unavailable on this platform, leaving `Instant`
+            _ => self.epoch.elapsed().as_nanos() as u64,
+        }
+    }
+
+    /// Returns the nanoseconds elapsed since a reading of the clock
+    #[inline(always)]
+    pub fn elapsed_ns(&self, start: u64) -> u128 {
+        let ticks = self.now().saturating_sub(start);
+        match self.source {
+            TimerSource::Tsc => (ticks as f64 * self.ns_per_tick) as u128,
+            _ => ticks as u128,
+        }
+    }
+
+    /// Returns the source of the clock
+    pub fn source(&self) -> TimerSource {
+        self.source
+    }
+
+    /// Returns the source and calibration of the clock, as recorded with the results
+    pub fn metadata(&self) -> TimerMetadata {
+        TimerMetadata { source: self.source.name().to_string(), overhead_ns: self.overhead_ns, resolution_ns: self.resolution_ns }
+    }
+
+    /// Measures the frequency (TSC only), the overhead and the resolution of a clock
+    fn calibrate(source: TimerSource) -> Clock {
+        let mut clock = Clock { source, epoch: Instant::now(), ns_per_tick: 1.0, overhead_ns: 0.0, resolution_ns: 0.0 };
+        #[cfg(target_arch = "x86_64")]
+        if source == TimerSource::Tsc {
+            let start_instant = Instant::now();
+            let start_ticks = read_tsc();
+            while start_instant.elapsed() < TSC_CALIBRATION_TIME {
+                std::hint::spin_loop();
+            }
+            let ticks = read_tsc() - start_ticks;
+            clock.ns_per_tick = start_instant.elapsed().as_nanos() as f64 / ticks.max(1) as f64;
+        }
+
+        let mut overhead = u64::MAX;
+        let mut resolution = u64::MAX;
+        for _ in 0..CALIBRATION_READINGS {
+            let first = clock.now();
+            let second = clock.now();
+            let interval = second.saturating_sub(first);
+            overhead = overhead.min(interval);
+            if interval > 0 {
+                resolution = resolution.min(interval);
+            }
+        }
+        clock.overhead_ns = overhead as f64 * clock.ns_per_tick;
+        clock.resolution_ns = if resolution == u64::MAX { 0.0 } else { resolution as f64 * clock.ns_per_tick };
+        debug!(timer = source.name(), overhead_ns = clock.overhead_ns, resolution_ns = clock.resolution_ns, "Timer calibrated");
+        clock
+    }
+}
+
+/// Reads `CLOCK_MONOTONIC_RAW`, in nanoseconds
+#[cfg(target_os = "linux")]
+#[inline(always)]
+fn read_monotonic_raw() -> u64 {
+    let mut time = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    // SAFETY: `time` is a valid timespec, and CLOCK_MONOTONIC_RAW exists on Linux
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC_RAW, &mut time) };
+    time.tv_sec as u64 * 1_000_000_000 + time.tv_nsec as u64
+}
+
+/// Reads the time-stamp counter, once the preceding instructions have completed
+///
+/// Also the timestamp of the stage timers (see `profiler::timestamp`), so that stage
+/// boundaries are not reordered around the timed work either.
+#[cfg(target_arch = "x86_64")]
+#[inline(always)]
+pub(crate) fn read_tsc() -> u64 {
+    // SAFETY: LFENCE and RDTSC are part of the x86_64 baseline
+    unsafe {
+        std::arch::x86_64::_mm_lfence();
+        std::arch::x86_64::_rdtsc()
+    }
+}
+
+/// Checks whether the time-stamp counter ticks at a constant rate in every power state
+#[cfg(target_arch = "x86_64")]
+fn has_invariant_tsc() -> bool {
+    use std::arch::x86_64::__cpuid;
+    __cpuid(0x8000_0000).eax >= 0x8000_0007 && __cpuid(0x8000_0007).edx & (1 << 8) != 0
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn has_invariant_tsc() -> bool {
+    false
+}
@@ -0,0 +1,96 @@
+//! Dataset files compressed on disk
+//!
+//! Corpora are usually distributed compressed, and JSON datasets of several GiB
+//! compress well, so that inflating them next to the originals before every benchmark
+//! wastes disk space and time. A dataset file ending with `.gz`, `.zst` or `.xz` is
+//! decompressed while it is read, without any intermediate file: the extension before
+//! the compression extension selects the format, so that `urls.json.zst` is a JSON
+//! dataset and `payloads.bin.gz` a binary one. Multi-member gzip and multi-stream xz
+//! files (as written by `pigz` and `xz -T`) are read in full.
+//!
+//! Decompression requires the `compressed-datasets` feature (enabled by default);
+//! without it, compressed dataset files are rejected with an error.
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+/// Compression of a dataset file, detected from its extension
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FileCompression {
+    None,
+    Gzip,       // `.gz`
+    Zstd,       // `.zst`
+    Xz,         // `.xz`
+}
+
+impl FileCompression {
+    /// Detects the compression of a file from its last extension
+    pub fn of(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("gz") => FileCompression::Gzip,
+            Some("zst") => FileCompression::Zstd,
+            Some("xz") => FileCompression::Xz,
+            _ => FileCompression::None,
+        }
+    }
+
+    /// Returns the identifier of the compression format
+    pub fn name(&self) -> &'static str {
+        match self {
+            FileCompression::None => "none",
+            FileCompression::Gzip => "gzip",
+            FileCompression::Zstd => "zstd",
+            FileCompression::Xz => "xz",
+        }
+    }
+}
+
+/// Returns the path of a dataset file without its compression extension (`urls.json.gz` → `urls.json`)
+///
+/// The extension of the returned path selects the dataset format.
+pub fn uncompressed_path(path: &Path) -> PathBuf {
+    match FileCompression::of(path) {
+        FileCompression::None => path.to_path_buf(),
+        _ => path.with_extension(""),
+    }
+}
+
+/// Checks whether a file is a JSON or binary dataset, possibly compressed
+pub fn is_dataset_file(path: &Path) -> bool {
+    uncompressed_path(path).extension().is_some_and(|extension| extension == "json" || extension == "bin")
+}
+
+/// Opens a dataset file, decompressing it while it is read
+///
+/// # Returns
+/// A buffered reader of the decompressed contents, or an error if the file cannot be
+/// opened or its compression is not supported by this build
+pub fn open_dataset_file(path: &Path) -> io::Result<Box<dyn Read>> {
+    let file = BufReader::new(File::open(path)?);
+    match FileCompression::of(path) {
+        FileCompression::None => Ok(Box::new(file)),
+        #[cfg(feature = "compressed-datasets")]
+        FileCompression::Gzip => Ok(Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(file)))),
+        #[cfg(feature = "compressed-datasets")]
+        FileCompression::Zstd => Ok(Box::new(BufReader::new(zstd::stream::read::Decoder::with_buffer(file)?))),
+        #[cfg(feature = "compressed-datasets")]
+        FileCompression::Xz => Ok(Box::new(BufReader::new(xz2::bufread::XzDecoder::new_multi_decoder(file)))),
+        #[cfg(not(feature = "compressed-datasets"))]
+        compression => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{} datasets require the compressed-datasets feature", compression.name()),
+        )),
+    }
+}
+
+/// Reads the decompressed contents of a dataset file
+pub fn read_dataset_file(path: &Path) -> io::Result<Vec<u8>> {
+    if FileCompression::of(path) == FileCompression::None {
+        return fs::read(path);
+    }
+    // The compressed size is a lower bound of the contents, saving the first reallocations
+    let mut content = Vec::with_capacity(fs::metadata(path)?.len() as usize);
+    open_dataset_file(path)?.read_to_end(&mut content)?;
+    Ok(content)
+}
@@ -0,0 +1,907 @@
+//! Benchmark engine: the measurement protocol shared by all benchmarked collections
+//!
+//! Compressors (see `benchmark_compressed`), record stores and shared-dictionary shards
+//! are measured by the same phases, so that their results are comparable: the engine
+//! repeats the work over the warm-up runs and the measured run, measures it with probes
+//! (see `probe`) while leaving verification unmeasured, enforces the phase deadline and
+//! the query budget, and panics on wrong output. Queries are checked as selected by
+//! `Verification`, sampled by default. Callers supply the work and its
+//! verification as closures over a state holding the collection and its buffers, so
+//! that verification can read what the work wrote.
+//!
+//! The access phases share one loop (see `replay`): random access, and the phases of
+//! compressors replaying the queries of its measured run (batches, access stages,
+//! zero-copy access, plain stores, hardware counters and scans).
+//!
+//! Each phase of `benchmark_compressed` has its function here, specialized for compressors
+//! (`compression_metrics`, `measure_full_decompression`, `measure_compressor_access`,
+//! `measure_replayed_access` and `validate_items`), so that it only sequences them.
+//!
+//! Times are measured in nanoseconds, and throughputs derived from them in MiB/s.
+
+use super::probe::{throughput, HardwareEvents, Memory, Probe, Timing};
+use crate::compressor::incremental::{CompressionSteps, Progress};
+use crate::compressor::{Compressor, ItemBoundaries, SpaceBreakdown, BUFFER_PADDING};
+use crate::phase_profile;
+use crate::profiler::{self, Stage, StageTimer};
+use super::{
+    AccessStageMetrics, AccessThroughputMetrics, BatchAccessMetrics, BenchmarkOptions, BudgetTracker, HardwareCounterMetrics,
+    LatencyHeatmap, PhaseDeadline, PlainAccessMetrics, ScanMetrics, ScratchWriter, ValidationMetrics, VerifyingWriter,
+    ZeroCopyAccessMetrics, SCAN_PROJECTION_SIZE, TIMEOUT_CHECK_INTERVAL,
+};
+use rustc_hash::FxHashMap;
+use std::ops::Range;
+use std::time::Instant;
+use tracing::{debug, info, info_span, warn};
+
+/// Runs of the random access phase (none by default)
+#[derive(Default)]
+pub struct AccessRuns {
+    pub average_access_times: Vec<u128>,    // Average latency of each run in nanoseconds, warm-up runs first
+    pub access_times: Vec<u128>,            // Latency of each query of the measured run in nanoseconds
+    pub retrieved_bytes: usize,             // Bytes retrieved by the measured run
+    pub allocations: Option<usize>,         // Allocations of the measured run (with a memory probe only)
+}
+
+impl AccessRuns {
+    /// Returns the number of queries of the measured run
+    pub fn n_queries(&self) -> usize {
+        self.access_times.len()
+    }
+
+    /// Returns the throughput of the measured run, in units and in bytes retrieved
+    pub fn throughput(&self) -> AccessThroughputMetrics {
+        let access_time = self.access_times.iter().sum::<u128>().max(1);
+        AccessThroughputMetrics {
+            item_rate: self.n_queries() as f64 / (access_time as f64 / 1e9),
+            byte_throughput: throughput(self.retrieved_bytes, access_time),
+        }
+    }
+}
+
+/// Measures the compression of a collection
+///
+/// # Arguments
+/// - `options`: Phase timeout
+/// - `compress`: Compresses the collection
+///
+/// # Returns
+/// Compression time in nanoseconds, or None if it exceeded the phase timeout
+pub fn measure_compression(options: &BenchmarkOptions, compress: impl FnOnce()) -> Option<u128> {
+    let mut timing = Timing::with_source(options.timer);
+    timing.start();
+    compress();
+    let compression_time = timing.stop();
+    let timed_out = options.phase_timeout.is_some_and(|timeout| compression_time > timeout.as_nanos());
+    (!timed_out).then_some(compression_time)
+}
+
+/// Measures an incremental compression of a collection (see `incremental`)
+///
+/// Unlike `measure_compression`, the deadline is checked between units of work, and
+/// the compression is abandoned as soon as it is exceeded rather than once it returns.
+/// The compressor is then left half-compressed, and must not be queried.
+///
+/// # Arguments
+/// - `options`: Phase timeout
+/// - `steps`: Units of work of the compression
+///
+/// # Returns
+/// Compression time in nanoseconds, or None if it exceeded the phase timeout
+pub fn measure_compression_steps(options: &BenchmarkOptions, mut steps: CompressionSteps) -> Option<u128> {
+    let deadline = PhaseDeadline::start(options);
+    let mut timing = Timing::with_source(options.timer);
+    timing.start();
+    while steps.step() == Progress::Continue {
+        if deadline.is_exceeded() {
+            debug!(n_steps = steps.n_steps(), "Compression abandoned after exceeding the phase timeout");
+            return None;
+        }
+    }
+    let compression_time = timing.stop();
+    (!deadline.is_exceeded()).then_some(compression_time)
+}
+
+/// Measures full decompression, after the warm-up runs
+///
+/// # Arguments
+/// - `state`: Collection and buffers
+/// - `name`: Name of the collection, reported on mismatches
+/// - `data_size`: Size of the decompressed data in bytes
+/// - `options`: Warm-up runs and phase timeout
+/// - `decompress`: Decompresses the collection (measured)
+/// - `verify`: Checks the output of the last decompression (unmeasured)
+///
+/// # Returns
+/// Throughput of each run in MiB/s, warm-up runs first, or None if the phase timed out
+pub fn measure_decompression<S>(
+    state: &mut S,
+    name: &str,
+    data_size: usize,
+    options: &BenchmarkOptions,
+    mut decompress: impl FnMut(&mut S),
+    mut verify: impl FnMut(&S) -> bool
+) -> Option<Vec<f64>> {
+    let deadline = PhaseDeadline::start(options);
+    let mut timing = Timing::with_source(options.timer);
+    let mut decompression_speeds = Vec::with_capacity(options.warmup_iterations + 1);
+    for _ in 0..=options.warmup_iterations {
+        if deadline.is_exceeded() {
+            return None;
+        }
+        timing.start();
+        decompress(state);
+        decompression_speeds.push(throughput(data_size, timing.stop()));
+
+        if !verify(state) {
+            panic!("Data mismatch during decompression for compressor: {}", name);
+        }
+    }
+    (!deadline.is_exceeded()).then_some(decompression_speeds)
+}
+
+/// Units of work replayed by an access phase, with the checks of its loop (see `replay`)
+pub struct Replay<'a> {
+    pub name: &'a str,          // Name of the collection, reported on mismatches
+    pub access: &'a str,        // Kind of access, reported on mismatches (e.g., "random access")
+    pub units: &'a [usize],     // Units of work of each run (e.g., queried indices)
+    pub check_interval: usize,  // Units between checks of the phase deadline
+    pub budgeted: bool,         // Whether runs stop once the query budget is exhausted (see `BudgetTracker`)
+}
+
+/// Replays units of work over the warm-up runs and the measured run
+///
+/// The loop shared by the access phases: runs check the phase deadline every
+/// `check_interval` units, and check the units selected by `options.verification`,
+/// unmeasured, panicking on wrong output.
+///
+/// # Arguments
+/// - `state`: Collection and buffers
+/// - `replay`: Units of work and checks of the phase
+/// - `options`: Warm-up runs, query budget, phase timeout and verification
+/// - `memory`: Probe counting the allocations of the measured run, if any
+/// - `start_run`: Prepares each run, unmeasured (e.g., resets stage timers or counters)
+/// - `step`: Performs a unit of work, returning the bytes it retrieved and its time in
+///   nanoseconds (0 for phases measuring runs with their own probes)
+/// - `verify`: Checks a unit, given the unit and the bytes retrieved (unmeasured)
+///
+/// # Returns
+/// The runs, with the time of each unit, or None if the phase timed out
+pub fn replay<S>(
+    state: &mut S,
+    replay: &Replay,
+    options: &BenchmarkOptions,
+    mut memory: Option<&mut Memory>,
+    mut start_run: impl FnMut(&mut S),
+    mut step: impl FnMut(&mut S, usize) -> (usize, u128),
+    mut verify: impl FnMut(&mut S, usize, usize) -> bool
+) -> Option<AccessRuns> {
+    let deadline = PhaseDeadline::start(options);
+    let mut runs = AccessRuns {
+        average_access_times: Vec::with_capacity(options.warmup_iterations + 1),
+        access_times: Vec::new(),
+        retrieved_bytes: 0,
+        allocations: None,
+    };
+    for iteration in 0..=options.warmup_iterations {
+        let is_measured = iteration == options.warmup_iterations;
+        let mut access_times: Vec<u128> = Vec::with_capacity(replay.units.len());
+        let mut budget = BudgetTracker::new(options);
+        let mut retrieved_bytes = 0;
+        start_run(state);
+        if let Some(memory) = memory.as_deref_mut().filter(|_| is_measured) {
+            memory.start();
+        }
+        for (i, &unit) in replay.units.iter().enumerate() {
+            if replay.budgeted && budget.is_exhausted() {
+                break;
+            }
+            if i % replay.check_interval == 0 && deadline.is_exceeded() {
+                return None;
+            }
+            let (size, access_time) = step(state, unit);
+            access_times.push(access_time);
+            budget.add(access_time);
+            retrieved_bytes += size;
+
+            if options.verification.checks(i) && !verify(state, unit, size) {
+                panic!("Data mismatch during {} for compressor: {}", replay.access, replay.name);
+            }
+        }
+        if let Some(memory) = memory.as_deref_mut().filter(|_| is_measured) {
+            runs.allocations = memory.stop();
+        }
+        runs.average_access_times.push(access_times.iter().sum::<u128>() / access_times.len().max(1) as u128);
+        runs.access_times = access_times;
+        runs.retrieved_bytes = retrieved_bytes;
+    }
+    Some(runs)
+}
+
+/// Measures the latency of random access queries, after the warm-up runs
+///
+/// Each run stops early once the query budget is exhausted (see `BudgetTracker`).
+///
+/// # Arguments
+/// - `state`: Collection and buffers
+/// - `name`: Name of the collection, reported on mismatches
+/// - `queries`: Indices queried by each run
+/// - `options`: Warm-up runs, query budget and phase timeout
+/// - `memory`: Probe counting the allocations of the measured run, if any (the warm-up
+///   runs may grow scratch buffers and caches, the measured run shows the steady state)
+/// - `access`: Retrieves a queried unit, returning its size in bytes (measured)
+/// - `verify`: Checks a retrieved unit, given its index and size (unmeasured, on the
+///   queries selected by `options.verification`)
+///
+/// # Returns
+/// The runs, or None if the phase timed out
+pub fn measure_random_access<S>(
+    state: &mut S,
+    name: &str,
+    queries: &[usize],
+    options: &BenchmarkOptions,
+    memory: Option<&mut Memory>,
+    mut access: impl FnMut(&mut S, usize) -> usize,
+    mut verify: impl FnMut(&S, usize, usize) -> bool
+) -> Option<AccessRuns> {
+    let mut timing = Timing::with_source(options.timer);
+    let replay_queries = Replay { name, access: "random access", units: queries, check_interval: TIMEOUT_CHECK_INTERVAL, budgeted: true };
+    replay(
+        state,
+        &replay_queries,
+        options,
+        memory,
+        |_| {},
+        |state, query| {
+            timing.start();
+            let size = access(state, query);
+            (size, timing.stop())
+        },
+        |state, query, size| verify(state, query, size)
+    )
+}
+
+/// Measures the latency of the queries of the measured random access run, issued in batches
+/// (see `Compressor::get_items_at`), after the warm-up runs
+///
+/// # Arguments
+/// - `compressor`: Compressor holding the dataset
+/// - `data`, `end_positions`: The dataset, to verify the batches
+/// - `queries`: Queries of the measured random access run
+/// - `batch_size`: Queries per batch
+/// - `options`: Warm-up runs, phase timeout and verification
+/// - `random_access_time`: Latency per query of the measured random access run in nanoseconds
+///
+/// # Returns
+/// The measurements, or None if the phase timed out
+pub fn measure_batch_access<T: Compressor>(
+    compressor: &mut T,
+    data: &[u8],
+    end_positions: &[usize],
+    queries: &[usize],
+    batch_size: usize,
+    options: &BenchmarkOptions,
+    random_access_time: u128
+) -> Option<BatchAccessMetrics> {
+    let batch_of = |batch_index: usize| &queries[batch_index * batch_size..((batch_index + 1) * batch_size).min(queries.len())];
+    let batches: Vec<usize> = (0..queries.len().div_ceil(batch_size)).collect();
+    let batch_buffer_size = batches
+        .iter()
+        .map(|&batch_index| batch_of(batch_index).iter().map(|&query| compressor.len_of_item(query)).sum::<usize>())
+        .max()
+        .unwrap_or(0);
+    let mut timing = Timing::with_source(options.timer);
+    let name = compressor.name().to_string();
+
+    let mut state = (compressor, vec![0u8; batch_buffer_size + BUFFER_PADDING], vec![0usize; batch_size]);
+    let runs = replay(
+        &mut state,
+        &Replay { name: &name, access: "batch access", units: &batches, check_interval: 1, budgeted: false },
+        options,
+        None,
+        |_| {},
+        |(compressor, batch_buffer, item_ends), batch_index| {
+            timing.start();
+            let size = compressor.get_items_at(batch_of(batch_index), batch_buffer, item_ends);
+            (size, timing.stop())
+        },
+        |(_, batch_buffer, item_ends), batch_index, _| {
+            let mut item_start = 0;
+            batch_of(batch_index).iter().zip(item_ends.iter()).all(|(&query, &item_end)| {
+                let is_correct = data[end_positions[query]..end_positions[query + 1]].eq(&batch_buffer[item_start..item_end]);
+                item_start = item_end;
+                is_correct
+            })
+        }
+    )?;
+
+    let average_access_time = runs.access_times.iter().sum::<u128>() / queries.len().max(1) as u128;
+    let speedup = random_access_time as f64 / average_access_time.max(1) as f64;
+    Some(BatchAccessMetrics { batch_size, average_access_time, speedup })
+}
+
+/// Attributes the latency of the queries of the measured random access run to the stages
+/// of access (see `Compressor::get_item_at_profiled`), after the warm-up runs
+///
+/// Ticks are converted to nanoseconds by timing the measured run with the wall clock.
+///
+/// # Arguments
+/// - `compressor`: Compressor holding the dataset
+/// - `buffer`: Output buffer, with room for any string
+/// - `data`, `end_positions`: The dataset, to verify the strings
+/// - `queries`: Queries of the measured random access run
+/// - `options`: Warm-up runs, phase timeout and verification
+///
+/// # Returns
+/// The latency of each stage per query, or None if the phase timed out
+pub fn measure_access_stages<T: Compressor>(
+    compressor: &mut T,
+    buffer: &mut [u8],
+    data: &[u8],
+    end_positions: &[usize],
+    queries: &[usize],
+    options: &BenchmarkOptions
+) -> Option<AccessStageMetrics> {
+    let name = compressor.name().to_string();
+    let mut run_start = (profiler::timestamp(), Instant::now());
+    let mut state = (compressor, buffer, StageTimer::new());
+    replay(
+        &mut state,
+        &Replay { name: &name, access: "profiled random access", units: queries, check_interval: TIMEOUT_CHECK_INTERVAL, budgeted: false },
+        options,
+        None,
+        |(_, _, timer)| {
+            timer.reset();
+            run_start = (profiler::timestamp(), Instant::now());
+        },
+        |(compressor, buffer, timer), query| {
+            timer.start();
+            (compressor.get_item_at_profiled(query, buffer, timer), 0)
+        },
+        |(_, buffer, _), query, size| data[end_positions[query]..end_positions[query + 1]].eq(&buffer[..size])
+    )?;
+
+    let elapsed_ticks = profiler::timestamp().wrapping_sub(run_start.0).max(1);
+    let nanos_per_tick = run_start.1.elapsed().as_nanos() as f64 / elapsed_ticks as f64;
+    let timer = &state.2;
+    let stage_time = |stage: Stage| timer.ticks(stage) as f64 * nanos_per_tick / queries.len().max(1) as f64;
+    Some(AccessStageMetrics {
+        lookup_time: stage_time(Stage::Lookup),
+        decode_time: stage_time(Stage::Decode),
+        copy_time: stage_time(Stage::Copy),
+    })
+}
+
+/// Measures the latency of the queries of the measured random access run without copies
+/// (see `Compressor::get_item_ref`), after the warm-up runs
+///
+/// # Arguments
+/// - `compressor`: Compressor holding the dataset, supporting zero-copy access
+/// - `data`, `end_positions`: The dataset, to verify the strings
+/// - `queries`: Queries of the measured random access run
+/// - `options`: Warm-up runs, phase timeout and verification
+/// - `random_access_time`: Latency per query of the measured random access run in nanoseconds
+///
+/// # Returns
+/// The measurements, or None if the phase timed out
+pub fn measure_zero_copy_access<T: Compressor>(
+    compressor: &mut T,
+    data: &[u8],
+    end_positions: &[usize],
+    queries: &[usize],
+    options: &BenchmarkOptions,
+    random_access_time: u128
+) -> Option<ZeroCopyAccessMetrics> {
+    let mut timing = Timing::with_source(options.timer);
+    let name = compressor.name().to_string();
+    let runs = replay(
+        compressor,
+        &Replay { name: &name, access: "zero-copy access", units: queries, check_interval: TIMEOUT_CHECK_INTERVAL, budgeted: false },
+        options,
+        None,
+        |_| {},
+        |compressor, query| {
+            timing.start();
+            let size = compressor.get_item_ref(query).map_or(0, |item| item.len());
+            (size, timing.stop())
+        },
+        |compressor, query, _| compressor.get_item_ref(query) == Some(&data[end_positions[query]..end_positions[query + 1]])
+    )?;
+
+    let average_access_time = *runs.average_access_times.last().unwrap();
+    let copy_overhead = random_access_time as f64 - average_access_time as f64;
+    Some(ZeroCopyAccessMetrics { average_access_time, copy_overhead })
+}
+
+/// Measures the latency of the queries of the measured random access run on uncompressed
+/// in-memory stores, after the warm-up runs
+///
+/// Each store is queried the same way as compressors: one timed lookup and copy to the
+/// buffer per query, under its own phase timeout.
+///
+/// # Arguments
+/// - `data`, `end_positions`: The dataset, loaded into the stores
+/// - `queries`: Queries of the measured random access run
+/// - `buffer`: Output buffer, with room for any string
+/// - `options`: Warm-up runs, phase timeout and verification
+/// - `random_access_time`: Latency per query of the measured random access run in nanoseconds
+///
+/// # Returns
+/// The measurements, or None if the phase timed out
+pub fn measure_plain_access(
+    data: &[u8],
+    end_positions: &[usize],
+    queries: &[usize],
+    buffer: &mut [u8],
+    options: &BenchmarkOptions,
+    random_access_time: u128
+) -> Option<PlainAccessMetrics> {
+    // Byte strings rather than `String`s, with the same layout, so that binary datasets qualify
+    let strings: Vec<Vec<u8>> = end_positions.windows(2).map(|w| data[w[0]..w[1]].to_vec()).collect();
+    let ranges: FxHashMap<u64, Range<usize>> = end_positions
+        .windows(2)
+        .enumerate()
+        .map(|(index, w)| (index as u64, w[0]..w[1]))
+        .collect();
+
+    let vec_access_time = measure_plain_store(data, end_positions, queries, buffer, options, &|query| &strings[query])?;
+    let hash_map_access_time = measure_plain_store(data, end_positions, queries, buffer, options, &|query| {
+        &data[ranges[&(query as u64)].clone()]
+    })?;
+    let slowdown = random_access_time as f64 / vec_access_time.max(1) as f64;
+    Some(PlainAccessMetrics { vec_access_time, hash_map_access_time, slowdown })
+}
+
+/// Measures the latency per query of a plain store, given its lookup
+fn measure_plain_store<'a>(
+    data: &[u8],
+    end_positions: &[usize],
+    queries: &[usize],
+    mut buffer: &mut [u8],
+    options: &BenchmarkOptions,
+    get: &dyn Fn(usize) -> &'a [u8]
+) -> Option<u128> {
+    let mut timing = Timing::with_source(options.timer);
+    let runs = replay(
+        &mut buffer,
+        &Replay { name: "plain store", access: "plain store access", units: queries, check_interval: TIMEOUT_CHECK_INTERVAL, budgeted: false },
+        options,
+        None,
+        |_| {},
+        |buffer, query| {
+            timing.start();
+            let item = get(std::hint::black_box(query));
+            buffer[..item.len()].copy_from_slice(item);
+            let access_time = timing.stop();
+            std::hint::black_box(&buffer);
+            (item.len(), access_time)
+        },
+        |buffer, query, size| data[end_positions[query]..end_positions[query + 1]].eq(&buffer[..size])
+    )?;
+    runs.average_access_times.last().copied()
+}
+
+/// Counts the hardware events of the queries of the measured random access run, after the warm-up runs
+///
+/// Retrieved strings are not verified, so that the counts cover random access only.
+///
+/// # Arguments
+/// - `compressor`: Compressor holding the dataset
+/// - `buffer`: Output buffer, with room for any string
+/// - `queries`: Queries of the measured random access run
+/// - `events`: Opened hardware counters
+/// - `options`: Warm-up runs and phase timeout
+///
+/// # Returns
+/// The events per query, or None if the phase timed out
+pub fn measure_hardware_counters<T: Compressor>(
+    compressor: &mut T,
+    buffer: &mut [u8],
+    queries: &[usize],
+    events: HardwareEvents,
+    options: &BenchmarkOptions
+) -> Option<HardwareCounterMetrics> {
+    let name = compressor.name().to_string();
+    let mut state = (compressor, buffer, events);
+    let runs = replay(
+        &mut state,
+        &Replay { name: &name, access: "random access", units: queries, check_interval: TIMEOUT_CHECK_INTERVAL, budgeted: false },
+        options,
+        None,
+        |(_, _, events)| events.start(),
+        |(compressor, buffer, _), query| (std::hint::black_box(compressor.get_item_at(query, buffer)), 0),
+        |_, _, _| true
+    );
+    // Counters are stopped even if the phase timed out
+    let counts = state.2.stop();
+    runs?;
+
+    let per_query = |count: Option<u64>| count.map(|count| count as f64 / queries.len().max(1) as f64);
+    Some(HardwareCounterMetrics {
+        instructions: per_query(counts.instructions),
+        llc_misses: per_query(counts.llc_misses),
+        branch_misses: per_query(counts.branch_misses),
+    })
+}
+
+/// Measures the throughput of scans starting from the queries of the measured random access
+/// run (see `Compressor::scan_range`), after the warm-up runs
+///
+/// One scan of `scan_length` strings starts every `scan_length` queries, clamped to end within
+/// the dataset, and projects the first `SCAN_PROJECTION_SIZE` bytes of each string.
+///
+/// # Arguments
+/// - `compressor`: Compressor holding the dataset
+/// - `data`, `end_positions`: The dataset, to verify the projections
+/// - `queries`: Queries of the measured random access run
+/// - `scan_length`: Strings per scan, clamped to the number of strings
+/// - `options`: Warm-up runs, phase timeout and verification
+/// - `random_access_time`: Latency per query of the measured random access run in nanoseconds
+///
+/// # Returns
+/// The measurements, or None if the phase timed out
+pub fn measure_scan<T: Compressor>(
+    compressor: &mut T,
+    data: &[u8],
+    end_positions: &[usize],
+    queries: &[usize],
+    scan_length: usize,
+    options: &BenchmarkOptions,
+    random_access_time: u128
+) -> Option<ScanMetrics> {
+    let n_items = end_positions.len() - 1;
+    let scan_length = scan_length.min(n_items);
+    let starts: Vec<usize> = queries.iter().step_by(scan_length).map(|&query| query.min(n_items - scan_length)).collect();
+    let mut timing = Timing::with_source(options.timer);
+    let name = compressor.name().to_string();
+
+    let mut state = (compressor, Vec::with_capacity(scan_length * SCAN_PROJECTION_SIZE));
+    let runs = replay(
+        &mut state,
+        &Replay { name: &name, access: "scan", units: &starts, check_interval: 1, budgeted: false },
+        options,
+        None,
+        |_| {},
+        |(compressor, projections), start| {
+            projections.clear();
+            timing.start();
+            compressor.scan_range(start, start + scan_length, |_, item| {
+                projections.extend_from_slice(&item[..item.len().min(SCAN_PROJECTION_SIZE)]);
+            });
+            (end_positions[start + scan_length] - end_positions[start], timing.stop())
+        },
+        |(_, projections), start, _| {
+            let expected = (start..start + scan_length).flat_map(|index| {
+                let item = &data[end_positions[index]..end_positions[index + 1]];
+                &item[..item.len().min(SCAN_PROJECTION_SIZE)]
+            });
+            projections.iter().copied().eq(expected.copied())
+        }
+    )?;
+
+    let scan_time: u128 = runs.access_times.iter().sum();
+    let n_scanned = starts.len() * scan_length;
+    let throughput = throughput(runs.retrieved_bytes, scan_time);
+    let item_time = scan_time as f64 / n_scanned as f64;
+    let speedup = random_access_time as f64 / item_time.max(f64::MIN_POSITIVE);
+    Some(ScanMetrics { scan_length, throughput, item_time, speedup })
+}
+
+/// Space and compression speed of a compressor holding the dataset
+pub struct CompressionMetrics {
+    pub compression_rate: f64,              // Space reduction factor
+    pub boundary_compression_rate: f64,     // Space reduction factor over the space breakdown
+    pub compression_speed: Option<f64>,     // Throughput in MiB/s (None if compression was not measured)
+    pub space_breakdown: SpaceBreakdown,    // Space usage split by category
+}
+
+/// Reports the space used by a compressor, and its compression speed if measured
+///
+/// # Arguments
+/// - `compressor`: Compressor holding the dataset
+/// - `data_size`: Size of the dataset in bytes
+/// - `options`: Phases measured
+/// - `compression_time`: Compression time in nanoseconds, ignored if compression was not measured
+pub fn compression_metrics<T: Compressor>(compressor: &T, data_size: usize, options: &BenchmarkOptions, compression_time: u128) -> CompressionMetrics {
+    let data_bytes = data_size as f64;
+    let compression_rate = data_bytes / compressor.space_used_bytes() as f64;
+    let space_breakdown = compressor.space_breakdown();
+    let compression_speed = options.phases.compress.then(|| throughput(data_size, compression_time));
+    // Both rates count every category of the space breakdown, per-string offsets included, so
+    // that they compare with compressors finding boundaries while decoding; the boundary rate
+    // is kept for results files predating the change
+    let boundary_compression_rate = data_bytes / space_breakdown.total().max(1) as f64;
+    info!(compression_rate, boundary_compression_rate, compression_speed, item_boundaries = compressor.item_boundaries().name(), "Compression completed");
+    if compressor.item_boundaries() == ItemBoundaries::Decoded && space_breakdown.item_offsets > 0 {
+        warn!(item_offsets = space_breakdown.item_offsets, "Compressor without per-string offsets reports item offsets");
+    }
+    CompressionMetrics { compression_rate, boundary_compression_rate, compression_speed, space_breakdown }
+}
+
+/// Measures the full decompression of a compressor, after the warm-up runs (see `measure_decompression`)
+///
+/// Streaming runs decompress into a writer discarding the data (see `ScratchWriter`), and
+/// are verified by a separate decompression (see `VerifyingWriter`).
+///
+/// # Arguments
+/// - `compressor`: Compressor holding the dataset
+/// - `buffer`: Output buffer, with room for the dataset unless streaming
+/// - `dataset_name`: Name of the dataset, for phase profiles
+/// - `data`: The dataset, to verify the output
+/// - `options`: Warm-up runs, phase timeout and streaming
+///
+/// # Returns
+/// Throughput of each run in MiB/s, warm-up runs first, or None if the phase timed out
+pub fn measure_full_decompression<T: Compressor>(
+    compressor: &mut T,
+    buffer: &mut [u8],
+    dataset_name: &str,
+    data: &[u8],
+    options: &BenchmarkOptions
+) -> Option<Vec<f64>> {
+    let _decompress_span = info_span!("decompress", warmup_iterations = options.warmup_iterations).entered();
+    let name = compressor.name().to_string();
+    let _decompress_profile = phase_profile::enter(dataset_name, &name, "decompress");
+    let mut state = (compressor, buffer);
+    let decompression_speeds = measure_decompression(
+        &mut state,
+        &name,
+        data.len(),
+        options,
+        |(compressor, buffer)| {
+            if options.streaming {
+                compressor.decompress_to(&mut ScratchWriter::new()).expect("Streaming decompression failed");
+            } else {
+                compressor.decompress(buffer);
+            }
+        },
+        |(compressor, buffer)| {
+            if options.streaming {
+                let mut verifier = VerifyingWriter::new(data);
+                compressor.decompress_to(&mut verifier).is_ok() && verifier.is_complete()
+            } else {
+                data.eq(&buffer[..data.len()])
+            }
+        }
+    )?;
+    info!(decompression_speed = decompression_speeds.last().unwrap(), "Decompression completed");
+    Some(decompression_speeds)
+}
+
+/// Random access phase of a compressor
+pub struct CompressorAccess {
+    pub runs: AccessRuns,                           // Runs of the phase
+    pub latency_heatmap: Option<LatencyHeatmap>,    // Latency of the measured run binned by length and block position (if requested)
+}
+
+/// Measures random access to a compressor, after the warm-up runs (see `measure_random_access`)
+///
+/// Retrieved strings are checked against the dataset, along with their reported length.
+///
+/// # Arguments
+/// - `compressor`: Compressor holding the dataset
+/// - `buffer`: Output buffer, with room for any string
+/// - `dataset_name`: Name of the dataset, for phase profiles
+/// - `data`, `end_positions`: The dataset, to verify the strings
+/// - `queries`: Indices queried by each run
+/// - `options`: Warm-up runs, query budget, phase timeout, allocation counting and latency heatmap
+///
+/// # Returns
+/// The runs, or None if the phase timed out
+pub fn measure_compressor_access<T: Compressor>(
+    compressor: &mut T,
+    buffer: &mut [u8],
+    dataset_name: &str,
+    data: &[u8],
+    end_positions: &[usize],
+    queries: &[usize],
+    options: &BenchmarkOptions
+) -> Option<CompressorAccess> {
+    let _random_access_span = info_span!("random_access", n_queries = queries.len(), warmup_iterations = options.warmup_iterations).entered();
+    let name = compressor.name().to_string();
+    let random_access_profile = phase_profile::enter(dataset_name, &name, "random_access");
+    let mut memory = options.count_allocations.then(Memory::new);
+    if memory.as_ref().is_some_and(|memory| !memory.is_available()) {
+        warn!("Counting allocator not installed, allocations are not counted");
+        memory = None;
+    }
+    let mut state = (&mut *compressor, buffer);
+    let runs = measure_random_access(
+        &mut state,
+        &name,
+        queries,
+        options,
+        memory.as_mut(),
+        |(compressor, buffer), query| compressor.get_item_at(query, buffer),
+        |(compressor, buffer), query, size| {
+            // Verify random access correctness, including the reported length
+            let expected = &data[end_positions[query]..end_positions[query + 1]];
+            size == compressor.len_of_item(query) && expected.eq(&buffer[..size])
+        }
+    )?;
+
+    let n_queries = runs.n_queries();
+    if let Some(allocations) = runs.allocations.filter(|&allocations| allocations > 0) {
+        warn!(allocations, n_queries, "Random access allocated in steady state");
+    }
+    let access_throughput = runs.throughput();
+    info!(
+        average_random_access_time = *runs.average_access_times.last().unwrap() as u64,
+        n_queries,
+        item_rate = access_throughput.item_rate,
+        byte_throughput = access_throughput.byte_throughput,
+        "Random access completed"
+    );
+    let latency_heatmap = options.latency_heatmap.then(|| LatencyHeatmap::build(compressor, &queries[..n_queries], &runs.access_times));
+    if let Some(heatmap) = &latency_heatmap {
+        let columns = heatmap.column_names();
+        for (row, &max_length) in heatmap.length_bounds.iter().enumerate() {
+            let access_times: Vec<u64> = heatmap.access_times[row].iter().map(|&time| time as u64).collect();
+            info!(max_length, ?columns, ?access_times, counts = ?heatmap.counts[row], "Latency heatmap row");
+        }
+    }
+    random_access_profile.finish();
+    Some(CompressorAccess { runs, latency_heatmap })
+}
+
+/// Phases of a compressor replaying the queries of its measured random access run (None if not requested)
+#[derive(Default)]
+pub struct ReplayedAccess {
+    pub batch_access: Option<BatchAccessMetrics>,
+    pub access_stages: Option<AccessStageMetrics>,
+    pub zero_copy_access: Option<ZeroCopyAccessMetrics>,
+    pub plain_access: Option<PlainAccessMetrics>,
+    pub hardware_counters: Option<HardwareCounterMetrics>,
+    pub scan: Option<ScanMetrics>,
+}
+
+/// Runs the phases replaying the queries of the measured random access run, as requested by `options`
+///
+/// Phases are skipped when the compressor or the system does not support them (zero-copy
+/// access, hardware counters), and all but access stages and hardware counters without queries.
+///
+/// # Arguments
+/// - `compressor`: Compressor holding the dataset
+/// - `buffer`: Output buffer, with room for any string
+/// - `data`, `end_positions`: The dataset, to verify the strings
+/// - `queries`: Queries of the measured random access run
+/// - `options`: Phases requested, warm-up runs, phase timeout and verification
+/// - `random_access_time`: Latency per query of the measured random access run in nanoseconds
+///
+/// # Returns
+/// The measurements, or the name of the phase that timed out
+pub fn measure_replayed_access<T: Compressor>(
+    compressor: &mut T,
+    buffer: &mut [u8],
+    data: &[u8],
+    end_positions: &[usize],
+    queries: &[usize],
+    options: &BenchmarkOptions,
+    random_access_time: u128
+) -> Result<ReplayedAccess, &'static str> {
+    let warmup_iterations = options.warmup_iterations;
+    let mut replayed = ReplayedAccess::default();
+
+    // Batch access latency measurement
+    if let Some(batch_size) = options.batch_size.filter(|_| !queries.is_empty()) {
+        let _batch_span = info_span!("batch_access", batch_size, warmup_iterations).entered();
+        let metrics = measure_batch_access(compressor, data, end_positions, queries, batch_size, options, random_access_time).ok_or("batch_access")?;
+        info!(average_access_time = metrics.average_access_time as u64, speedup = metrics.speedup, "Batch access completed");
+        replayed.batch_access = Some(metrics);
+    }
+
+    // Random access latency attribution
+    if options.profile_stages {
+        let _profile_span = info_span!("access_stages", warmup_iterations).entered();
+        let metrics = measure_access_stages(compressor, buffer, data, end_positions, queries, options).ok_or("access_stages")?;
+        info!(
+            lookup_time = metrics.lookup_time,
+            decode_time = metrics.decode_time,
+            copy_time = metrics.copy_time,
+            bottleneck = metrics.bottleneck().name(),
+            "Access stages profiled"
+        );
+        replayed.access_stages = Some(metrics);
+    }
+
+    // Zero-copy access latency measurement
+    if options.zero_copy && queries.first().is_some_and(|&query| compressor.get_item_ref(query).is_some()) {
+        let _zero_copy_span = info_span!("zero_copy_access", warmup_iterations).entered();
+        let metrics = measure_zero_copy_access(compressor, data, end_positions, queries, options, random_access_time).ok_or("zero_copy_access")?;
+        info!(average_access_time = metrics.average_access_time as u64, copy_overhead = metrics.copy_overhead, "Zero-copy access completed");
+        replayed.zero_copy_access = Some(metrics);
+    } else if options.zero_copy {
+        info!("Zero-copy access not supported, skipping");
+    }
+
+    // Random access to uncompressed in-memory stores
+    if options.plain_baselines && !queries.is_empty() {
+        let _plain_span = info_span!("plain_access", warmup_iterations).entered();
+        let metrics = measure_plain_access(data, end_positions, queries, buffer, options, random_access_time).ok_or("plain_access")?;
+        info!(
+            vec_access_time = metrics.vec_access_time as u64,
+            hash_map_access_time = metrics.hash_map_access_time as u64,
+            slowdown = metrics.slowdown,
+            "Plain store access completed"
+        );
+        replayed.plain_access = Some(metrics);
+    }
+
+    // Hardware events of random access
+    if options.perf_counters {
+        match HardwareEvents::open() {
+            Some(events) => {
+                let _counters_span = info_span!("hardware_counters", warmup_iterations).entered();
+                let metrics = measure_hardware_counters(compressor, buffer, queries, events, options).ok_or("hardware_counters")?;
+                info!(
+                    instructions = metrics.instructions,
+                    llc_misses = metrics.llc_misses,
+                    branch_misses = metrics.branch_misses,
+                    "Hardware counters read"
+                );
+                replayed.hardware_counters = Some(metrics);
+            }
+            None => warn!("Hardware counters unavailable, skipping"),
+        }
+    }
+
+    // Scan throughput measurement
+    if let Some(scan_length) = options.scan_length.filter(|_| !queries.is_empty()) {
+        let _scan_span = info_span!("scan", scan_length, warmup_iterations).entered();
+        let metrics = measure_scan(compressor, data, end_positions, queries, scan_length, options, random_access_time).ok_or("scan")?;
+        info!(throughput = metrics.throughput, item_time = metrics.item_time, speedup = metrics.speedup, "Scan completed");
+        replayed.scan = Some(metrics);
+    }
+    Ok(replayed)
+}
+
+/// Validates every `stride`-th string of a compressor one by one, unmeasured (see `ValidationMetrics`)
+///
+/// Panics on the first string differing from the dataset, or decoded as invalid UTF-8
+/// where the dataset string is valid.
+///
+/// # Arguments
+/// - `compressor`: Compressor holding the dataset
+/// - `buffer`: Output buffer, with room for any string
+/// - `data`, `end_positions`: The dataset, to verify the strings
+/// - `fraction`: Fraction of the strings validated
+/// - `options`: Phase timeout
+///
+/// # Returns
+/// The strings validated, or None if the phase timed out
+pub fn validate_items<T: Compressor>(
+    compressor: &mut T,
+    buffer: &mut [u8],
+    data: &[u8],
+    end_positions: &[usize],
+    fraction: f64,
+    options: &BenchmarkOptions
+) -> Option<ValidationMetrics> {
+    let _validation_span = info_span!("validation", fraction).entered();
+    let deadline = PhaseDeadline::start(options);
+    let n_items = end_positions.len() - 1;
+    let stride = (1.0 / fraction).round().max(1.0) as usize;
+    let mut n_validated = 0;
+    let mut n_utf8_items = 0;
+    for (i, index) in (0..n_items).step_by(stride).enumerate() {
+        if i % TIMEOUT_CHECK_INTERVAL == 0 && deadline.is_exceeded() {
+            return None;
+        }
+        let expected = &data[end_positions[index]..end_positions[index + 1]];
+        let size = compressor.get_item_at(index, buffer);
+        let item = &buffer[..size];
+        if std::str::from_utf8(expected).is_ok() {
+            if let Err(e) = std::str::from_utf8(item) {
+                panic!("Invalid UTF-8 at byte {} of string {} for compressor: {}", e.valid_up_to(), index, compressor.name());
+            }
+            n_utf8_items += 1;
+        }
+        if !expected.eq(item) {
+            panic!("Data mismatch at string {} during validation for compressor: {}", index, compressor.name());
+        }
+        n_validated += 1;
+    }
+
+    info!(stride, n_items = n_validated, n_utf8_items, "Validation completed");
+    Some(ValidationMetrics { stride, n_items: n_validated, n_utf8_items })
+}
@@ -3,19 +3,77 @@
 //! This module provides core infrastructure for systematic performance measurement
 //! of string compression algorithms, including:
 //! - Dataset loading and preprocessing
-//! - Random query generation for access pattern simulation  
+//! - Random query generation for access pattern simulation, under configurable
+//!   query-count policies, optionally seeded and stored in query files
 //! - Result aggregation and statistical analysis
-//! - CPU affinity management for reproducible measurements
+//! - CPU affinity and NUMA memory placement for reproducible measurements
+//!
+//! The measurement protocol itself (timing, verification, warm-up runs, budgets and
+//! timeouts) lives in `engine`, composing the probes of `probe`; the benchmark
+//! functions of this module and the binaries build on it, and all report a single
+//! `BenchmarkResult`.
+
+pub mod clock;
+pub mod dataset_file;
+pub mod engine;
+pub mod probe;
 
+pub use engine::{
+    compression_metrics, measure_access_stages, measure_batch_access, measure_compression, measure_compression_steps,
+    measure_compressor_access, measure_decompression, measure_full_decompression, measure_hardware_counters, measure_plain_access,
+    measure_random_access, measure_replayed_access, measure_scan, measure_zero_copy_access, validate_items, AccessRuns,
+    CompressionMetrics, CompressorAccess, ReplayedAccess,
+};
+pub use clock::{Clock, TimerMetadata, TimerSource};
+pub use dataset_file::{is_dataset_file, read_dataset_file, FileCompression};
+pub use probe::{throughput, HardwareEvents, Memory, Probe, Timing};
+
+use crate::compressor::block_stats::BlockStatsSummary;
+use crate::compressor::{BlockPosition, BlockSizeCalibration, BUFFER_PADDING, Compressor, DictionaryCompressor, LiteralTokenStats, SpaceBreakdown, STREAM_CHUNK_SIZE};
+use crate::compressor::dictionary_report::DictionaryStats;
+use crate::compressor::prefix_trie::MatcherStats;
+use crate::dataset_stats::DatasetStats;
+use crate::entropy::entropy_efficiency;
+use crate::phase_profile;
+use crate::profiler::Stage;
+use crate::store::{CompressedStringStore, StoreConfig};
+use crate::store::updatable::UpdatableStore;
 use prettytable::{row, Table};
 use serde::{Serialize, Deserialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{info, info_span, warn};
 #[cfg(target_os = "linux")]
 use libc::{self, cpu_set_t, CPU_SET, CPU_ZERO};
-use rand::{thread_rng, Rng};
+use rand::rngs::StdRng;
+use rand::{thread_rng, Rng, SeedableRng};
 use rand::distributions::Uniform;
+use rand::seq::{index, SliceRandom};
+
+/// Default number of random access queries per benchmark run
+pub const DEFAULT_N_QUERIES: usize = 1000000;
+/// Maximum number of queries generated for a time-budgeted run
+pub const MAX_BUDGET_QUERIES: usize = 10 * DEFAULT_N_QUERIES;
+/// Default number of rounds of updates (see `evaluate_updates`)
+pub const DEFAULT_UPDATE_ROUNDS: usize = 10;
+/// Queries issued between two checks of the phase timeout
+pub const TIMEOUT_CHECK_INTERVAL: usize = 1024;
+/// Length bins of the latency heatmap, as quantiles of the queried string lengths
+pub const HEATMAP_LENGTH_BINS: usize = 10;
+/// Bytes projected from each string by the scans of the scan phase (a prefix, e.g., a key)
+pub const SCAN_PROJECTION_SIZE: usize = 8;
+/// Queries between two checked queries of sampled verification (1% of the queries)
+pub const VERIFICATION_STRIDE: usize = 100;
+
+/// Default relative deviation from the median of the iterations flagging a run as an outlier (see `flag_outliers`)
+pub const DEFAULT_OUTLIER_TOLERANCE: f64 = 0.2;
+
+/// Minimum number of iterations of a combination for their median to flag outliers
+pub const MIN_OUTLIER_ITERATIONS: usize = 3;
 
 /// Performance metrics for a single algorithm-dataset combination
 #[derive(Serialize, Deserialize, Clone)]
@@ -23,27 +81,658 @@ pub struct BenchmarkResult {
     pub dataset_name: String,
     pub compressor_name: String,
     pub compression_rate: f64,              // Space reduction factor
-    pub compression_speed: f64,             // Throughput in MiB/s
+    pub compression_speed: Option<f64>,     // Throughput in MiB/s (null if compression was not measured, see `Phases`)
+    pub decompression_speed: Option<f64>,   // Throughput in MiB/s (null if decompression was not measured)
+    pub average_random_access_time: Option<u128>, // Latency in nanoseconds (null if random access was not measured)
+    #[serde(default)]
+    pub access_throughput: Option<AccessThroughputMetrics>, // Random access throughput in strings and in bytes (see `AccessThroughputMetrics`)
+    #[serde(default)]
+    pub space_breakdown: SpaceBreakdown,    // Space usage split by category
+    #[serde(default)]
+    pub boundary_compression_rate: Option<f64>, // Space reduction factor counting string boundaries, whether stored as offsets or decoded (see `ItemBoundaries`)
+    #[serde(default)]
+    pub dedup_ratio: Option<f64>,           // Reduction from deduplication alone (dedup wrappers only)
+    #[serde(default)]
+    pub first_run: Option<FirstRunMetrics>, // Cold-start measurements (runs with warm-up, measuring decompression and random access, only)
+    #[serde(default)]
+    pub entropy: Option<f64>,               // Empirical entropy of the dataset (bits per byte, order `entropy::DEFAULT_ORDER`, estimated above `entropy::EXACT_ENTROPY_LIMIT` bytes)
+    #[serde(default)]
+    pub length_code: Option<String>,        // Smallest universal code of the string lengths of the dataset (see `LengthIndex`)
+    #[serde(default)]
+    pub n_queries: Option<usize>,           // Random access queries issued in the measured run
+    #[serde(default)]
+    pub query_policy: Option<String>,       // Query-count policy (see `QueryPolicy`), with "+all" if every item was covered
+    #[serde(default)]
+    pub input_order: Option<String>,        // Order of the input strings (order comparisons only, see `dataset_order`)
+    #[serde(default)]
+    pub shared_dictionary: Option<SharedDictionaryMetrics>, // Dictionary shared across shards (shared-dictionary runs only)
+    #[serde(default)]
+    pub holdout: Option<HoldoutMetrics>,    // Compression of strings held out of training (holdout runs of dictionary compressors only)
+    #[serde(default)]
+    pub compression_split: Option<CompressionSplit>, // Compression time split between training and parsing (split runs and shared-dictionary runs only)
+    #[serde(default)]
+    pub block_size_calibration: Option<BlockSizeCalibration>, // Block size chosen for a latency budget (runs of block compressors with a budget only)
+    #[serde(default)]
+    pub block_stats: Option<BlockStatsSummary>, // Spread of the compression ratios of the blocks (runs of block compressors only, see `block_stats`)
+    #[serde(default)]
+    pub literal_tokens: Option<LiteralTokenStats>, // Single-byte tokens of the token stream (runs of OnPairBV only)
+    #[serde(default)]
+    pub matcher: Option<MatcherStats>,      // Memory of the longest prefix matcher (runs of OnPairBV only)
+    #[serde(default)]
+    pub dictionary: Option<DictionaryStats>, // Token lengths, coverage and entropy of the dictionary (runs of BPE and OnPairBV only)
+    #[serde(default)]
+    pub updates: Option<UpdateMetrics>,     // Re-compression and patching under updates (update runs only)
+    #[serde(default)]
+    pub batch_access: Option<BatchAccessMetrics>, // Batched random access (runs with a batch size only)
+    #[serde(default)]
+    pub access_stages: Option<AccessStageMetrics>, // Random access latency split by stage (profiled runs only)
+    #[serde(default)]
+    pub latency_heatmap: Option<LatencyHeatmap>, // Random access latency by string length and block position (heatmap runs only)
+    #[serde(default)]
+    pub scan: Option<ScanMetrics>,          // Scans of consecutive strings with projection (runs with a scan length only)
+    #[serde(default)]
+    pub validation: Option<ValidationMetrics>, // Strings validated one by one after the measured phases (validated runs only)
+    #[serde(default)]
+    pub zero_copy_access: Option<ZeroCopyAccessMetrics>, // Random access without copying (zero-copy runs of supporting compressors only)
+    #[serde(default)]
+    pub plain_access: Option<PlainAccessMetrics>, // Random access to uncompressed in-memory stores (runs with plain baselines only)
+    #[serde(default)]
+    pub hardware_counters: Option<HardwareCounterMetrics>, // Hardware events of random access (runs with counters available only)
+    #[serde(default)]
+    pub access_allocations: Option<usize>,  // Allocations of the measured random access run (allocation-counting runs only)
+    #[serde(default)]
+    pub placement: Option<Placement>,       // CPU cores and NUMA nodes of the run (pinned runs only)
+    #[serde(default)]
+    pub conditions: Option<RunConditions>,  // Time and temperature at the start and end of the run
+    #[serde(default)]
+    pub timed_out: Option<String>,          // Phase exceeding the phase timeout (timed-out runs only, without metrics)
+    #[serde(default)]
+    pub timer: Option<TimerMetadata>,       // Clock of the timings, with its calibrated overhead and resolution
+    #[serde(default)]
+    pub outlier: Option<Vec<String>>,       // Metrics deviating from the median of the iterations (flagged runs only, see `flag_outliers`)
+}
+
+impl BenchmarkResult {
+    /// Creates the status row of a run cancelled for exceeding the phase timeout
+    /// 
+    /// Metrics of the phases are None, as for skipped phases, and the compression rate
+    /// is zero: summaries skip timed-out rows.
+    /// 
+    /// # Arguments
+    /// - `dataset_name`: Name of the dataset being evaluated
+    /// - `compressor_name`: Name of the compressor, as reported in results
+    /// - `phase`: Phase that exceeded the timeout (e.g., "compress")
+    pub fn timed_out(dataset_name: String, compressor_name: String, phase: &str) -> Self {
+        BenchmarkResult {
+            dataset_name,
+            compressor_name,
+            compression_rate: 0.0,
+            compression_speed: None,
+            decompression_speed: None,
+            average_random_access_time: None,
+            access_throughput: None,
+            space_breakdown: SpaceBreakdown::default(),
+            boundary_compression_rate: None,
+            dedup_ratio: None,
+            first_run: None,
+            entropy: None,
+            length_code: None,
+            n_queries: None,
+            query_policy: None,
+            input_order: None,
+            shared_dictionary: None,
+            holdout: None,
+            compression_split: None,
+            block_size_calibration: None,
+            block_stats: None,
+            literal_tokens: None,
+            matcher: None,
+            dictionary: None,
+            updates: None,
+            batch_access: None,
+            access_stages: None,
+            latency_heatmap: None,
+            scan: None,
+            validation: None,
+            zero_copy_access: None,
+            plain_access: None,
+            hardware_counters: None,
+            access_allocations: None,
+            placement: None,
+            conditions: None,
+            timed_out: Some(phase.to_string()),
+            timer: None,
+            outlier: None,
+        }
+    }
+
+    /// Returns the entropy over the achieved bits per byte (see `entropy::entropy_efficiency`)
+    pub fn entropy_efficiency(&self) -> Option<f64> {
+        self.entropy.map(|entropy| entropy_efficiency(entropy, self.compression_rate))
+    }
+}
+
+/// Time and temperature at the start and end of a run
+///
+/// Timestamps locate each run in the schedule of the whole evaluation, and temperatures
+/// reveal thermal drift: on machines heating up over a long evaluation, throttling
+/// penalizes the runs measured last (see the `--interleave` option of `benchmark_all`).
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct RunConditions {
+    pub start_time: f64,                 // Unix time at the start of the run, in seconds
+    pub end_time: f64,                   // Unix time at the end of the run, in seconds
+    pub start_temperature: Option<f64>,  // Highest thermal zone temperature at the start, in °C (Linux only)
+    pub end_temperature: Option<f64>,    // Highest thermal zone temperature at the end, in °C (Linux only)
+}
+
+impl RunConditions {
+    /// Records the conditions at the start of a run
+    pub fn start() -> Self {
+        let now = unix_time();
+        RunConditions { start_time: now, end_time: now, start_temperature: read_temperature(), end_temperature: None }
+    }
+
+    /// Completes the conditions with those at the end of the run
+    pub fn finish(self) -> Self {
+        RunConditions { end_time: unix_time(), end_temperature: read_temperature(), ..self }
+    }
+}
+
+/// Returns the current Unix time in seconds
+fn unix_time() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0.0, |elapsed| elapsed.as_secs_f64())
+}
+
+/// Returns the highest temperature among the thermal zones, in °C
+///
+/// Reads the zones exposed in sysfs; returns None if there is none (e.g., in most
+/// virtual machines) or on other platforms.
+pub fn read_temperature() -> Option<f64> {
+    let entries = fs::read_dir("/sys/class/thermal").ok()?;
+    entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if !path.file_name()?.to_str()?.starts_with("thermal_zone") {
+                return None;
+            }
+            // Temperatures are reported in millidegrees Celsius
+            let millidegrees = fs::read_to_string(path.join("temp")).ok()?.trim().parse::<i64>().ok()?;
+            Some(millidegrees as f64 / 1000.0)
+        })
+        .reduce(f64::max)
+}
+
+/// Measurements of the first run of the repeatable phases, before any warm-up
+///
+/// The metrics of `BenchmarkResult` are measured after the warm-up iterations, so
+/// that cold-start costs (page faults, lazy allocations, cold caches and branch
+/// predictors) are reported separately instead of being averaged in. Compression
+/// is measured once, since it consumes the compressor instance.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct FirstRunMetrics {
     pub decompression_speed: f64,           // Throughput in MiB/s
     pub average_random_access_time: u128,   // Latency in nanoseconds
 }
 
-/// Loads and preprocesses JSON string datasets for benchmark evaluation
+/// Throughput of the measured random access run
+///
+/// Random access speed has two common readings: strings retrieved per second, which
+/// weighs every query equally, and bytes retrieved per second, which depends on the
+/// lengths of the strings queried. Uniform queries over-weight short strings in the
+/// latter; length-weighted queries (see `QueryDistribution::LengthWeighted`) query
+/// strings in proportion to their size, as a byte-oriented workload would.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct AccessThroughputMetrics {
+    pub item_rate: f64,                     // Strings (or records) retrieved per second
+    pub byte_throughput: f64,               // Bytes retrieved in MiB/s
+}
+
+/// Measurements of random access in batches (see `Compressor::get_items_at`)
+///
+/// The queries of the measured random access run are issued again in consecutive
+/// batches, so that compressors can share work between the strings of a batch
+/// (e.g., block compressors decompress each block once per batch).
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct BatchAccessMetrics {
+    pub batch_size: usize,                  // Queries per batch
+    pub average_access_time: u128,          // Latency per query in nanoseconds
+    pub speedup: f64,                       // Per-query random access time over batched access time
+}
+
+/// Measurements of scans of consecutive strings (see `Compressor::scan_range`)
+///
+/// Scans of `scan_length` strings start at the queries of the measured random access
+/// run, so that they visit about as many strings as the run queried, and project the
+/// first `SCAN_PROJECTION_SIZE` bytes of each string. Throughput counts the full length
+/// of the scanned strings, as for decompression.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct ScanMetrics {
+    pub scan_length: usize,                 // Strings per scan
+    pub throughput: f64,                    // Throughput in MiB/s
+    pub item_time: f64,                     // Latency per scanned string in nanoseconds
+    pub speedup: f64,                       // Per-query random access time over per-string scan time
+}
+
+/// Strings checked one by one against the dataset after the measured phases
+///
+/// Every `stride`-th string is retrieved by random access and compared byte by byte
+/// with the dataset; decoded strings must also be valid UTF-8 wherever the dataset
+/// string is, pinpointing the first invalid byte (e.g., a multi-byte character split
+/// across tokens) rather than the first differing one. Strings of binary datasets that
+/// are not valid UTF-8 are compared byte by byte only.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct ValidationMetrics {
+    pub stride: usize,                      // Strings between validated strings (1: all of them)
+    pub n_items: usize,                     // Strings validated
+    pub n_utf8_items: usize,                // Validated strings checked as valid UTF-8
+}
+
+/// Random access latency attributed to the stages of an access (see `profiler`)
+///
+/// Measured on a separate replay of the queries through the instrumented access path
+/// (`Compressor::get_item_at_profiled`), whose total is slightly higher than
+/// `BenchmarkResult::average_random_access_time` because of the instrumentation.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct AccessStageMetrics {
+    pub lookup_time: f64,                   // Latency per query spent locating strings in nanoseconds
+    pub decode_time: f64,                   // Latency per query spent decoding strings in nanoseconds
+    pub copy_time: f64,                     // Latency per query spent copying strings in nanoseconds
+}
+
+impl AccessStageMetrics {
+    /// Returns the stage with the highest latency
+    pub fn bottleneck(&self) -> Stage {
+        let times = [self.lookup_time, self.decode_time, self.copy_time];
+        Stage::ALL.into_iter().zip(times).max_by(|a, b| a.1.total_cmp(&b.1)).unwrap().0
+    }
+}
+
+/// Random access latency binned by string length and by position within block
+///
+/// Built from the per-query latencies of the measured random access run. Rows are the
+/// deciles of the lengths of the queried strings, merged where they coincide (e.g., on
+/// fixed-length strings); columns are the positions of the strings within their block
+/// (see `Compressor::block_position`), or a single column for compressors accessing
+/// every string independently. Cells without queries report a latency of 0.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LatencyHeatmap {
+    pub length_bounds: Vec<usize>,          // Largest string length of each row in bytes
+    pub positions: Vec<BlockPosition>,      // Block position of each column (empty: a single column, without blocks)
+    pub access_times: Vec<Vec<f64>>,        // Mean latency per row and column in nanoseconds
+    pub counts: Vec<Vec<usize>>,            // Queries per row and column
+}
+
+impl LatencyHeatmap {
+    /// Bins the latencies of queries by string length and block position
+    ///
+    /// # Arguments
+    /// - `compressor`: Compressor the queries were issued to
+    /// - `queries`: Indices of the queried strings
+    /// - `access_times`: Latency of each query in nanoseconds
+    pub fn build<T: Compressor>(compressor: &T, queries: &[usize], access_times: &[u128]) -> Self {
+        let lengths: Vec<usize> = queries.iter().map(|&query| compressor.len_of_item(query)).collect();
+        let mut sorted_lengths = lengths.clone();
+        sorted_lengths.sort_unstable();
+        let mut length_bounds: Vec<usize> = (1..=HEATMAP_LENGTH_BINS)
+            .filter_map(|bin| sorted_lengths.get((bin * sorted_lengths.len()).div_ceil(HEATMAP_LENGTH_BINS).saturating_sub(1)).copied())
+            .collect();
+        length_bounds.dedup();
+
+        let has_blocks = queries.first().is_some_and(|&query| compressor.block_position(query).is_some());
+        let positions = if has_blocks { BlockPosition::ALL.to_vec() } else { Vec::new() };
+        let mut total_times = vec![vec![0u128; positions.len().max(1)]; length_bounds.len()];
+        let mut counts = vec![vec![0usize; positions.len().max(1)]; length_bounds.len()];
+        for ((&query, &length), &access_time) in queries.iter().zip(lengths.iter()).zip(access_times) {
+            let row = length_bounds.partition_point(|&bound| bound < length);
+            let column = compressor.block_position(query).map_or(0, |position| position as usize);
+            total_times[row][column] += access_time;
+            counts[row][column] += 1;
+        }
+
+        let access_times = total_times
+            .iter()
+            .zip(counts.iter())
+            .map(|(times, counts)| times.iter().zip(counts).map(|(&time, &count)| time as f64 / count.max(1) as f64).collect())
+            .collect();
+        LatencyHeatmap { length_bounds, positions, access_times, counts }
+    }
+
+    /// Returns the column labels, "all" for compressors without blocks
+    pub fn column_names(&self) -> Vec<&'static str> {
+        if self.positions.is_empty() { vec!["all"] } else { self.positions.iter().map(BlockPosition::name).collect() }
+    }
+}
+
+/// Measurements of random access without copying (see `Compressor::get_item_ref`)
+///
+/// The queries of the measured random access run are issued again through the
+/// zero-copy access path, which returns strings in place (e.g., in the block cache).
+/// The difference with per-query random access quantifies the cost of copying
+/// strings to the caller's buffer.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct ZeroCopyAccessMetrics {
+    pub average_access_time: u128,          // Latency per query in nanoseconds
+    pub copy_overhead: f64,                 // Random access time minus zero-copy access time per query in nanoseconds
+}
+
+/// Random access latency of uncompressed in-memory stores, on the same queries
+///
+/// The queries of the measured random access run are issued again to a vector of
+/// strings (laid out as a `Vec<String>`) and to an `FxHashMap` from string IDs to
+/// ranges of the dataset, copying each string to a buffer as compressors do. They give
+/// the latency a plain in-memory store achieves on the same machine and dataset, in the
+/// context of which compressed access latencies can be read.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct PlainAccessMetrics {
+    pub vec_access_time: u128,              // Latency per query of the Vec<String> store in nanoseconds
+    pub hash_map_access_time: u128,         // Latency per query of the FxHashMap<u64, Range<usize>> store in nanoseconds
+    pub slowdown: f64,                      // Random access time over Vec<String> access time
+}
+
+/// Hardware events of random access, per query (see `perf`)
+///
+/// Counted on a separate replay of the queries of the measured random access run,
+/// without verification, in user space only. Events the machine does not expose are
+/// `None`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct HardwareCounterMetrics {
+    pub instructions: Option<f64>,          // Instructions retired per query
+    pub llc_misses: Option<f64>,            // Last-level cache misses per query
+    pub branch_misses: Option<f64>,         // Mispredicted branches per query
+}
+
+/// Cost of a dictionary trained once and shared by several shards
+///
+/// In shared-dictionary runs, the dictionary is charged to each shard in equal parts:
+/// `SpaceBreakdown::dictionary` and the compression rate of each shard account for
+/// `amortized_dictionary_size` bytes instead of the whole dictionary.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct SharedDictionaryMetrics {
+    pub n_shards: usize,                    // Number of shards sharing the dictionary
+    pub dictionary_size: usize,             // Size of the whole dictionary in bytes
+    pub amortized_dictionary_size: usize,   // Share of the dictionary charged to each shard in bytes
+    pub training_size: usize,               // Size of the strings sampled for training in bytes
+    pub training_time: f64,                 // Dictionary training time in seconds
+}
+
+/// Compression of strings unseen during dictionary training
+///
+/// Dictionary compressors train on the strings they compress, which overstates the
+/// compression of new strings. A random fraction of the strings is held out: the
+/// dictionary is trained on the others (the training set), and both sets are then
+/// compressed with it. Rates exclude the dictionary, shared by both sets, so that the
+/// gap between them measures how well the dictionary generalizes.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct HoldoutMetrics {
+    pub holdout_fraction: f64,              // Fraction of the strings held out
+    pub n_holdout: usize,                   // Number of strings held out
+    pub dictionary_size: usize,             // Size of the dictionary trained on the training set in bytes
+    pub in_sample_compression_rate: f64,    // Space reduction factor of the training set, excluding the dictionary
+    pub out_of_sample_compression_rate: f64, // Space reduction factor of the held-out strings, excluding the dictionary
+}
+
+/// Compression time of a dictionary compressor, split between training and parsing
+///
+/// A dictionary trained once can compress many shards (see `benchmark_shared`): the
+/// training cost amortizes across them, while the parsing cost is paid by every shard.
+/// The split gives the effective compression speed over any number of shards of the
+/// size of the parsed strings (see `effective_speed`).
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct CompressionSplit {
+    pub data_size: usize,                   // Size of the parsed strings in bytes
+    pub training_time: f64,                 // Dictionary training time in seconds
+    pub parse_time: f64,                    // Compression time with the trained dictionary in seconds
+}
+
+impl CompressionSplit {
+    /// Returns the compression speed in MiB/s of shards sharing one dictionary
+    ///
+    /// Each of the `n_shards` shards holds `data_size` bytes and is charged an equal
+    /// share of the training time.
+    pub fn effective_speed(&self, n_shards: usize) -> f64 {
+        let time = self.training_time + n_shards as f64 * self.parse_time;
+        throughput(self.data_size * n_shards, (time * 1e9) as u128)
+    }
+}
+
+/// Cost of keeping a compressed collection up to date as strings are replaced
+///
+/// In each round, a fraction of the strings is replaced with new values, and the
+/// collection is brought up to date in two ways: compressing all current strings
+/// again, or patching an `UpdatableStore` with a segment of the new values while
+/// tombstoning the replaced copies. Rounds accumulate, so that the space overhead of
+/// patching can be followed as updates accumulate.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UpdateMetrics {
+    pub update_fraction: f64,               // Fraction of the strings replaced in each round
+    pub rounds: Vec<UpdateRound>,           // Measurements after each round, starting with the initial compression
+}
+
+/// Measurements after a round of updates (see `UpdateMetrics`)
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct UpdateRound {
+    pub updated_items: usize,               // Strings replaced since the initial compression, counting repeats
+    pub data_size: usize,                   // Size of the current strings in bytes
+    pub recompression_time: f64,            // Time to compress all current strings again in seconds
+    pub recompressed_size: usize,           // Space used after compressing again in bytes
+    pub patch_time: f64,                    // Time to patch the previous round's store in seconds
+    pub patched_size: usize,                // Space used by the patched store, including string locations, in bytes
+    pub n_segments: usize,                  // Segments of the patched store holding live strings
+    pub dead_items: usize,                  // Replaced copies still stored by the patched store
+}
+
+/// Policy deciding the number of random access queries of a benchmark run
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum QueryPolicy {
+    Fixed(usize),       // Fixed number of queries, regardless of the dataset size
+    Coverage(f64),      // Queries per item (e.g., 0.5 queries half of the items on average)
+    TimeBudget(f64),    // Queries issued until their measured access time exceeds the budget (seconds)
+}
+
+impl Default for QueryPolicy {
+    fn default() -> Self {
+        QueryPolicy::Fixed(DEFAULT_N_QUERIES)
+    }
+}
+
+impl QueryPolicy {
+    /// Parses a policy from its command-line representation
+    ///
+    /// Accepted forms are `N` (fixed count), `coverage:F` (queries per item) and
+    /// `time:S` (time budget in seconds).
+    pub fn parse(s: &str) -> Option<Self> {
+        if let Some(fraction) = s.strip_prefix("coverage:") {
+            return fraction.parse().ok().filter(|f: &f64| *f > 0.0).map(QueryPolicy::Coverage);
+        }
+        if let Some(seconds) = s.strip_prefix("time:") {
+            return seconds.parse().ok().filter(|s: &f64| *s > 0.0).map(QueryPolicy::TimeBudget);
+        }
+        s.parse().ok().filter(|n: &usize| *n > 0).map(QueryPolicy::Fixed)
+    }
+
+    /// Returns the number of queries to generate for a dataset of `n_items` items
+    ///
+    /// Time-budgeted runs generate `MAX_BUDGET_QUERIES` queries, of which only those
+    /// fitting in the budget are issued.
+    pub fn n_queries(&self, n_items: usize) -> usize {
+        match *self {
+            QueryPolicy::Fixed(n_queries) => n_queries,
+            QueryPolicy::Coverage(fraction) => ((fraction * n_items as f64).ceil() as usize).max(1),
+            QueryPolicy::TimeBudget(_) => MAX_BUDGET_QUERIES,
+        }
+    }
+
+    /// Returns the budget on the measured access time, if any
+    pub fn time_budget(&self) -> Option<Duration> {
+        match *self {
+            QueryPolicy::TimeBudget(seconds) => Some(Duration::from_secs_f64(seconds)),
+            _ => None,
+        }
+    }
+}
+
+impl QueryPolicy {
+    /// Returns the description recorded in `BenchmarkResult::query_policy`
+    pub fn describe(&self, cover_all_items: bool) -> String {
+        if cover_all_items { format!("{}+all", self) } else { self.to_string() }
+    }
+}
+
+impl fmt::Display for QueryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QueryPolicy::Fixed(n_queries) => write!(f, "fixed:{}", n_queries),
+            QueryPolicy::Coverage(fraction) => write!(f, "coverage:{}", fraction),
+            QueryPolicy::TimeBudget(seconds) => write!(f, "time:{}", seconds),
+        }
+    }
+}
+
+/// Distribution of the random access queries over the strings of a dataset
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum QueryDistribution<'a> {
+    Uniform,                    // Every string equally likely
+    Zipf(f64),                  // Zipf-distributed over string IDs, with the given exponent
+    LengthWeighted(&'a [usize]), // Strings in proportion to their length, given the boundary positions
+}
+
+/// Phases measured by a benchmark run
+///
+/// The collection is compressed whether or not compression is measured: skipping a
+/// phase only leaves its metrics out of the result (null in results files), so that
+/// campaigns needing only compression rates, or only access latencies, skip the rest.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Phases {
+    pub compress: bool,     // Compression time
+    pub decompress: bool,   // Full decompression
+    pub access: bool,       // Random access, and the phases replaying its queries
+}
+
+impl Default for Phases {
+    fn default() -> Self {
+        Phases { compress: true, decompress: true, access: true }
+    }
+}
+
+impl Phases {
+    /// Parses a comma-separated list of phases (`compress`, `decompress`, `access`)
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut phases = Phases { compress: false, decompress: false, access: false };
+        for phase in s.split(',') {
+            match phase.trim() {
+                "compress" => phases.compress = true,
+                "decompress" => phases.decompress = true,
+                "access" => phases.access = true,
+                _ => return None,
+            }
+        }
+        Some(phases)
+    }
+}
+
+impl fmt::Display for Phases {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = [(self.compress, "compress"), (self.decompress, "decompress"), (self.access, "access")];
+        let selected: Vec<&str> = names.iter().filter(|(selected, _)| *selected).map(|&(_, name)| name).collect();
+        write!(f, "{}", selected.join(","))
+    }
+}
+
+/// Queries of the access phases whose output is checked against the dataset
+///
+/// Checks are unmeasured, but reading the expected string after each query evicts
+/// cache lines of the collection before the next one and doubles the duration of the
+/// access phases. Timing runs check one query in `VERIFICATION_STRIDE` by default,
+/// enough to catch systematic errors; verification runs (`differential_test`,
+/// `cbench verify`) check every string.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Verification {
+    Off,        // No query checked
+    #[default]
+    Sampled,    // One query in `VERIFICATION_STRIDE`
+    Full,       // Every query
+}
+
+impl Verification {
+    /// Returns the level identifier
+    pub fn name(&self) -> &'static str {
+        match self {
+            Verification::Off => "none",
+            Verification::Sampled => "sampled",
+            Verification::Full => "full",
+        }
+    }
+
+    /// Parses a level from its identifier
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "none" => Some(Verification::Off),
+            "sampled" => Some(Verification::Sampled),
+            "full" => Some(Verification::Full),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the output of the `i`-th query of a run is checked
+    #[inline(always)]
+    pub fn checks(&self, i: usize) -> bool {
+        match self {
+            Verification::Off => false,
+            Verification::Sampled => i.is_multiple_of(VERIFICATION_STRIDE),
+            Verification::Full => true,
+        }
+    }
+}
+
+/// Options of the measurement protocol (see `benchmark_with_options`)
+#[derive(Clone, Copy, Default, Debug)]
+pub struct BenchmarkOptions {
+    pub warmup_iterations: usize,       // Runs of each repeatable phase excluded from measurement
+    pub time_budget: Option<Duration>,  // Budget on the measured access time of each random access run
+    pub min_queries: usize,             // Queries issued regardless of the time budget
+    pub streaming: bool,                // Decompression to a writer, with bounded memory
+    pub batch_size: Option<usize>,      // Queries per batch of the batch access phase (None: skipped)
+    pub scan_length: Option<usize>,     // Strings per scan of the scan phase (None: skipped)
+    pub profile_stages: bool,           // Attribution of random access latency to access stages
+    pub phase_timeout: Option<Duration>, // Wall-clock limit of each phase, cancelling the run (None: unlimited)
+    pub zero_copy: bool,                // Random access without copying, for compressors supporting it
+    pub perf_counters: bool,            // Hardware events of random access, where the machine exposes them
+    pub count_allocations: bool,        // Allocations of the measured random access run, with the counting allocator installed
+    pub plain_baselines: bool,          // Random access to uncompressed in-memory stores, on the same queries
+    pub latency_heatmap: bool,          // Random access latency binned by string length and block position
+    pub dataset_stats: Option<DatasetStats>, // Statistics of the dataset, computed by the run if None (see `dataset_stats`)
+    pub phases: Phases,                 // Phases measured (all by default)
+    pub validation: Option<f64>,        // Fraction of the strings validated one by one, unmeasured (None: skipped)
+    pub verification: Verification,     // Queries of the access phases checked against the dataset, unmeasured
+    pub timer: TimerSource,             // Clock of the timing probes (see `clock`)
+}
+
+/// Loads and preprocesses string datasets for benchmark evaluation
+/// 
+/// Supports two formats, selected by file extension:
+/// - `.bin`: binary-safe length-prefixed items (see `load_binary_dataset`)
+/// - otherwise JSON: array of strings representing individual strings
+/// 
+/// Files ending with `.gz`, `.zst` or `.xz` are decompressed while they are read, the
+/// preceding extension selecting the format (see `dataset_file`).
 /// 
-/// Expects JSON format: array of strings representing individual strings.
 /// Returns flattened byte representation and positional metadata for efficient
 /// random access during benchmark execution.
 /// 
 /// # Arguments
-/// - `path`: Path to the JSON dataset file
+/// - `path`: Path to the dataset file
 ///
 /// # Returns
 /// - `Vec<u8>`: Concatenated string data as bytes
 /// - `Vec<usize>`: Boundary positions starting with 0, then cumulative string lengths.
 ///   String i is located at `data[end_positions[i]..end_positions[i+1]]`
 pub fn load_dataset(path: &Path) -> (Vec<u8>, Vec<usize>) {
-    let content = fs::read_to_string(path).unwrap();
-    let strings: Vec<String> = serde_json::from_str(&content).unwrap();
+    if is_binary_dataset(path) {
+        return load_binary_dataset(path);
+    }
+
+    let content = read_dataset_file(path).unwrap_or_else(|e| panic!("Failed to read '{}': {}", path.display(), e));
+    let strings: Vec<String> = serde_json::from_slice(&content).unwrap();
 
     let data: Vec<u8> = strings.iter().flat_map(|s| s.as_bytes()).copied().collect();
     let mut end_positions: Vec<usize> = Vec::new();
@@ -57,6 +746,154 @@ pub fn load_dataset(path: &Path) -> (Vec<u8>, Vec<usize>) {
     (data, end_positions)
 }
 
+/// Checks whether a dataset file uses the binary length-prefixed format
+/// 
+/// # Arguments
+/// - `path`: Path to the dataset file, possibly compressed (see `dataset_file`)
+pub fn is_binary_dataset(path: &Path) -> bool {
+    dataset_file::uncompressed_path(path).extension().map(|ext| ext == "bin").unwrap_or(false)
+}
+
+/// Loads a binary-safe dataset of arbitrary byte items
+/// 
+/// The file is a sequence of items, each encoded as a 32-bit little-endian length
+/// followed by that many raw bytes. Items are not required to be valid UTF-8.
+/// 
+/// # Arguments
+/// - `path`: Path to the binary dataset file, possibly compressed (see `dataset_file`)
+///
+/// # Returns
+/// Concatenated item data and boundary positions, as returned by `load_dataset`
+pub fn load_binary_dataset(path: &Path) -> (Vec<u8>, Vec<usize>) {
+    let content = read_dataset_file(path).unwrap_or_else(|e| panic!("Failed to read '{}': {}", path.display(), e));
+    let mut data: Vec<u8> = Vec::with_capacity(content.len());
+    let mut end_positions: Vec<usize> = vec![0];
+
+    let mut pos = 0;
+    while pos < content.len() {
+        let header: [u8; 4] = content
+            .get(pos..pos + 4)
+            .and_then(|h| h.try_into().ok())
+            .unwrap_or_else(|| panic!("Truncated item length at byte {} of '{}'", pos, path.display()));
+        let length = u32::from_le_bytes(header) as usize;
+        pos += 4;
+
+        let item = content
+            .get(pos..pos + length)
+            .unwrap_or_else(|| panic!("Truncated item at byte {} of '{}'", pos, path.display()));
+        data.extend_from_slice(item);
+        end_positions.push(data.len());
+        pos += length;
+    }
+
+    (data, end_positions)
+}
+
+/// Writes a dataset in the binary length-prefixed format
+/// 
+/// # Arguments
+/// - `path`: Path to the output file
+/// - `data`: Concatenated item data
+/// - `end_positions`: Boundary positions of the items, starting with 0
+pub fn write_binary_dataset(path: &Path, data: &[u8], end_positions: &[usize]) {
+    let mut content = Vec::with_capacity(data.len() + 4 * end_positions.len());
+    for window in end_positions.windows(2) {
+        let length = u32::try_from(window[1] - window[0]).expect("Item too large for the binary dataset format");
+        content.extend_from_slice(&length.to_le_bytes());
+        content.extend_from_slice(&data[window[0]..window[1]]);
+    }
+    fs::write(path, content).expect("Failed to write binary dataset");
+}
+
+/// Multi-column dataset where each record holds one string per column
+pub struct RecordDataset {
+    pub column_names: Vec<String>,              // Column names, in storage order
+    pub columns: Vec<(Vec<u8>, Vec<usize>)>,    // Concatenated data and boundary positions per column
+}
+
+impl RecordDataset {
+    /// Returns the number of records in the dataset
+    pub fn n_records(&self) -> usize {
+        self.columns.first().map_or(0, |(_, end_positions)| end_positions.len() - 1)
+    }
+
+    /// Returns the total size of all columns in bytes
+    pub fn data_size(&self) -> usize {
+        self.columns.iter().map(|(data, _)| data.len()).sum()
+    }
+
+    /// Returns the size of the largest record (all columns) in bytes
+    pub fn max_record_size(&self) -> usize {
+        (0..self.n_records())
+            .map(|i| self.columns.iter().map(|(_, end_positions)| end_positions[i + 1] - end_positions[i]).sum())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Checks whether a JSON dataset contains records instead of plain strings
+/// 
+/// Inspects the first element of the top-level array: record datasets are
+/// arrays of objects, plain datasets are arrays of strings. Only the first bytes of
+/// the file are read (and decompressed).
+///
+/// # Arguments
+/// - `path`: Path to the JSON dataset file, possibly compressed (see `dataset_file`)
+pub fn is_record_dataset(path: &Path) -> bool {
+    if is_binary_dataset(path) {
+        return false;
+    }
+    let reader = dataset_file::open_dataset_file(path).unwrap_or_else(|e| panic!("Failed to read '{}': {}", path.display(), e));
+    io::BufReader::new(reader)
+        .bytes()
+        .map(|b| b.unwrap_or_else(|e| panic!("Failed to read '{}': {}", path.display(), e)))
+        .filter(|b| !b.is_ascii_whitespace())
+        .take(2)
+        .eq([b'[', b'{'])
+}
+
+/// Loads a multi-column JSON dataset for benchmark evaluation
+/// 
+/// Expects JSON format: array of objects mapping column names to strings.
+/// Columns are taken from the union of the keys of all records, in sorted order;
+/// fields missing from a record are treated as empty strings.
+///
+/// # Arguments
+/// - `path`: Path to the JSON dataset file, possibly compressed (see `dataset_file`)
+///
+/// # Returns
+/// - `RecordDataset`: One concatenated column per field, with boundary positions
+///   in the same format returned by `load_dataset`
+pub fn load_record_dataset(path: &Path) -> RecordDataset {
+    let content = read_dataset_file(path).unwrap_or_else(|e| panic!("Failed to read '{}': {}", path.display(), e));
+    let records: Vec<BTreeMap<String, String>> = serde_json::from_slice(&content).unwrap();
+
+    let column_names: Vec<String> = records
+        .iter()
+        .flat_map(|record| record.keys().cloned())
+        .collect::<BTreeSet<String>>()
+        .into_iter()
+        .collect();
+
+    let columns = column_names
+        .iter()
+        .map(|name| {
+            let mut data = Vec::new();
+            let mut end_positions = Vec::with_capacity(records.len() + 1);
+            end_positions.push(0);
+            for record in records.iter() {
+                if let Some(value) = record.get(name) {
+                    data.extend_from_slice(value.as_bytes());
+                }
+                end_positions.push(data.len());
+            }
+            (data, end_positions)
+        })
+        .collect();
+
+    RecordDataset { column_names, columns }
+}
+
 /// Generates uniformly distributed random queries for access pattern simulation
 /// 
 /// Creates a representative workload for random access performance measurement.
@@ -69,185 +906,2119 @@ pub fn load_dataset(path: &Path) -> (Vec<u8>, Vec<usize>) {
 /// # Returns
 /// - `Vec<usize>`: Vector of random indices within the range [0, n)
 pub fn generate_random_queries(n: usize, n_queries: usize) -> Vec<usize> {
-    let mut rng = thread_rng();
+    random_queries(&mut thread_rng(), n, n_queries)
+}
+
+/// Draws uniformly distributed queries from a random number generator (see `generate_random_queries`)
+fn random_queries<R: Rng>(rng: &mut R, n: usize, n_queries: usize) -> Vec<usize> {
     let dist = Uniform::from(0..n);
     let mut queries = Vec::with_capacity(n_queries);
 
     for _ in 0..n_queries {
-        queries.push(rng.sample(&dist));
+        queries.push(rng.sample(dist));
     }
 
     queries
 }
 
-/// Reads benchmark results from a JSON file
+/// Generates Zipf-distributed random queries for skewed access patterns
 /// 
-/// Loads previously saved benchmark results for analysis or continuation of benchmarking.
-/// Returns empty vector if file doesn't exist or cannot be parsed.
+/// Item `i` is queried with probability proportional to `1 / (i + 1)^exponent`,
+/// so that popularity decreases with the item index.
 ///
 /// # Arguments
-/// - `file_path`: Path to the JSON results file
+/// - `n`: Total number of strings in dataset
+/// - `n_queries`: Number of random queries to generate
+/// - `exponent`: Skew of the distribution (0 is uniform)
 /// 
 /// # Returns
-/// - `Vec<BenchmarkResult>`: Loaded benchmark results
-pub fn read_benchmark_results(file_path: &str) -> Vec<BenchmarkResult> {
-    if Path::new(file_path).exists() {
-        let file_content = fs::read_to_string(file_path).expect("Failed to read file");
-        serde_json::from_str::<Vec<BenchmarkResult>>(&file_content).unwrap_or_else(|_| {
-            eprintln!("Error parsing results file '{}'. Starting fresh.", file_path);
-            Vec::new()
-        })
-    } else {
-        Vec::new()
+/// - `Vec<usize>`: Vector of random indices within the range [0, n)
+pub fn generate_zipf_queries(n: usize, n_queries: usize, exponent: f64) -> Vec<usize> {
+    zipf_queries(&mut thread_rng(), n, n_queries, exponent)
+}
+
+/// Draws Zipf-distributed queries from a random number generator (see `generate_zipf_queries`)
+fn zipf_queries<R: Rng>(rng: &mut R, n: usize, n_queries: usize, exponent: f64) -> Vec<usize> {
+    let mut cumulative_weights = Vec::with_capacity(n);
+    let mut total_weight = 0.0;
+    for i in 0..n {
+        total_weight += 1.0 / ((i + 1) as f64).powf(exponent);
+        cumulative_weights.push(total_weight);
     }
+
+    let dist = Uniform::from(0.0..total_weight);
+    (0..n_queries)
+        .map(|_| {
+            let target = rng.sample(dist);
+            cumulative_weights.partition_point(|&weight| weight <= target).min(n - 1)
+        })
+        .collect()
 }
 
-/// Appends a new benchmark result to the results file
+/// Generates random queries weighted by string length
 /// 
-/// Reads existing results, appends the new result, and writes back to file.
-/// Creates the file if it doesn't exist. Preserves all existing results.
+/// String `i` is queried with probability proportional to its length, as if a byte of
+/// the data were drawn uniformly, so that empty strings are never queried. Falls back to
+/// uniform queries if every string is empty.
 ///
 /// # Arguments
-/// - `result`: The new benchmark result to append
-/// - `output_path`: Path to the output JSON file 
-pub fn append_benchmark_result(result: &BenchmarkResult, output_path: &Path) {
-    let mut results: Vec<BenchmarkResult> = if output_path.exists() {
-        // Read existing results from the file if it exists
-        let data = fs::read_to_string(output_path).expect("Failed to read file");
-        serde_json::from_str(&data).expect("Failed to deserialize existing results")
-    } else {
-        // If the file doesn't exist, start with an empty vector
-        Vec::new()
-    };
+/// - `end_positions`: Boundary positions of the strings, starting with 0
+/// - `n_queries`: Number of random queries to generate
+/// 
+/// # Returns
+/// - `Vec<usize>`: Vector of random indices within the range [0, n)
+pub fn generate_length_weighted_queries(end_positions: &[usize], n_queries: usize) -> Vec<usize> {
+    length_weighted_queries(&mut thread_rng(), end_positions, n_queries)
+}
 
-    // Append the new result to the vector
-    results.push(result.clone());
+/// Draws length-weighted queries from a random number generator (see `generate_length_weighted_queries`)
+fn length_weighted_queries<R: Rng>(rng: &mut R, end_positions: &[usize], n_queries: usize) -> Vec<usize> {
+    let n = end_positions.len() - 1;
+    let data_size = end_positions[n];
+    if data_size == 0 {
+        return random_queries(rng, n, n_queries);
+    }
 
-    // Serialize the vector and write it back to the file
-    let json = serde_json::to_string_pretty(&results).expect("Failed to serialize results");
-    fs::write(output_path, json).expect("Failed to write results to file");
+    // Boundaries are the cumulative string lengths
+    let dist = Uniform::from(0..data_size);
+    (0..n_queries)
+        .map(|_| {
+            let target = rng.sample(dist);
+            end_positions[1..].partition_point(|&end| end <= target)
+        })
+        .collect()
 }
 
-/// Prints formatted benchmark results grouped by compressor
-/// 
-/// Groups results by compressor and dataset, calculates averages for each combination,
-/// then displays results in a tabular format with overall averages per compressor.
+/// Generates the random access queries of a query-count policy
 /// 
+/// With `cover_all_items`, every item is queried at least once: the queries start
+/// with a random permutation of all items, followed by queries drawn from the access
+/// distribution, raising the number of queries to `n` if needed. Issuing at least
+/// `n` queries (see `BenchmarkOptions::min_queries`) then touches every item, hence
+/// every block of block-based compressors.
+///
 /// # Arguments
-/// - `results`: Vector of benchmark results to display
-pub fn print_benchmark_results(results: &[BenchmarkResult]) {
-    // Group results by compressor and dataset name
-    let mut grouped_results: HashMap<(String, String), Vec<&BenchmarkResult>> = HashMap::new();
-    for result in results {
-        grouped_results
-            .entry((result.compressor_name.clone(), result.dataset_name.clone()))
-            .or_default()
-            .push(result);
-    }
+/// - `policy`: Query-count policy
+/// - `n`: Total number of strings in dataset
+/// - `distribution`: Distribution of the queries over the strings
+/// - `cover_all_items`: Whether every item must be queried at least once
+/// 
+/// # Returns
+/// - `Vec<usize>`: Vector of random indices within the range [0, n)
+pub fn generate_queries(policy: QueryPolicy, n: usize, distribution: QueryDistribution, cover_all_items: bool) -> Vec<usize> {
+    policy_queries(&mut thread_rng(), policy, n, distribution, cover_all_items)
+}
 
-    // A map to store results grouped by compressor name
-    let mut compressor_groups: HashMap<String, Vec<BenchmarkResult>> = HashMap::new();
-
-    // Calculate averaged results for each (compressor, dataset) pair
-    for ((compressor, dataset), group) in grouped_results {
-        let len = group.len() as f64;
-        let avg_compression_rate = group.iter().map(|r| r.compression_rate).sum::<f64>() / len;
-        let avg_compression_speed = group.iter().map(|r| r.compression_speed).sum::<f64>() / len;
-        let avg_decompression_speed = group.iter().map(|r| r.decompression_speed).sum::<f64>() / len;
-        let avg_average_random_access_time = group.iter().map(|r| r.average_random_access_time).sum::<u128>() / group.len() as u128;
-
-        // Store the averaged result
-        let averaged_result = BenchmarkResult {
-            dataset_name: dataset,
-            compressor_name: compressor.clone(),
-            compression_rate: avg_compression_rate,
-            compression_speed: avg_compression_speed,
-            decompression_speed: avg_decompression_speed,
-            average_random_access_time: avg_average_random_access_time,
-        };
+/// Generates the random access queries of a query-count policy, reproducibly
+/// 
+/// Same as `generate_queries`, with all random choices derived from `seed`: the same
+/// seed, policy and dataset size yield the same queries on every run (see `bundle`).
+pub fn generate_seeded_queries(policy: QueryPolicy, n: usize, distribution: QueryDistribution, cover_all_items: bool, seed: u64) -> Vec<usize> {
+    policy_queries(&mut StdRng::seed_from_u64(seed), policy, n, distribution, cover_all_items)
+}
 
-        compressor_groups
-            .entry(compressor)
-            .or_default()
-            .push(averaged_result);
+/// Draws the queries of a query-count policy from a random number generator (see `generate_queries`)
+fn policy_queries<R: Rng>(rng: &mut R, policy: QueryPolicy, n: usize, distribution: QueryDistribution, cover_all_items: bool) -> Vec<usize> {
+    let n_queries = policy.n_queries(n);
+    let mut queries: Vec<usize> = Vec::with_capacity(n_queries.max(n));
+    if cover_all_items {
+        queries.extend(0..n);
+        queries.shuffle(rng);
     }
 
-    // Print results grouped by compressor
-    for (compressor, results) in compressor_groups {
-        let mut sorted_results = results;
-        // Sort results by dataset name
-        sorted_results.sort_by(|a, b| a.dataset_name.cmp(&b.dataset_name));
+    let n_drawn = n_queries.saturating_sub(queries.len());
+    queries.extend(match distribution {
+        QueryDistribution::Uniform => random_queries(rng, n, n_drawn),
+        QueryDistribution::Zipf(exponent) => zipf_queries(rng, n, n_drawn, exponent),
+        QueryDistribution::LengthWeighted(end_positions) => length_weighted_queries(rng, end_positions, n_drawn),
+    });
+    queries
+}
 
-        // Create a new table for each compressor
-        let mut table = Table::new();
-        table.add_row(row![
-            "Dataset",
-            "Comp. Rate",
-            "Comp. Speed (MiB/s)",
-            "Decomp. Speed (MiB/s)",
-            "Avg. Random Access Time (ns)"
-        ]);
+/// Writes random access queries to a file, as 64-bit little-endian indices
+/// 
+/// # Arguments
+/// - `path`: Path to the output file
+/// - `queries`: Indices of the queried strings, in issue order
+pub fn write_query_file(path: &Path, queries: &[usize]) -> io::Result<()> {
+    let content: Vec<u8> = queries.iter().flat_map(|&query| (query as u64).to_le_bytes()).collect();
+    fs::write(path, content)
+}
 
-        // Add rows for each averaged result
-        for result in &sorted_results {
-            table.add_row(row![
+/// Reads random access queries written by `write_query_file`
+/// 
+/// # Arguments
+/// - `path`: Path to the query file
+/// - `n`: Total number of strings in the dataset queried
+/// 
+/// # Returns
+/// The queries, or a description of why the file cannot be read or does not fit the dataset
+pub fn load_query_file(path: &Path, n: usize) -> Result<Vec<usize>, String> {
+    let content = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if content.len() % 8 != 0 {
+        return Err(format!("{} is truncated: {} bytes is not a whole number of queries", path.display(), content.len()));
+    }
+    let queries: Vec<usize> = content.chunks_exact(8).map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()) as usize).collect();
+    match queries.iter().position(|&query| query >= n) {
+        Some(position) => Err(format!("Query {} of {} is out of range for a dataset of {} strings", position, path.display(), n)),
+        None => Ok(queries),
+    }
+}
+
+/// Core benchmark function implementing the measurement protocol
+/// 
+/// Executes the complete evaluation pipeline:
+/// 1. Compression phase with timing measurement
+/// 2. Full decompression with validation and timing  
+/// 3. Random access evaluation over the given queries
+/// 4. Data integrity verification at each step
+/// 
+/// # Arguments
+/// - `compressor`: The compression algorithm instance to benchmark
+/// - `dataset_name`: Name of the dataset being evaluated
+/// - `data`: Raw byte data from the dataset
+/// - `end_positions`: Boundary positions for individual strings in the data
+/// - `queries`: Vector of random indices for access pattern simulation
+///
+/// # Returns
+/// - `BenchmarkResult`: Aggregated performance metrics for statistical analysis.
+pub fn benchmark<T: Compressor>(
+    compressor: &mut T, 
+    dataset_name: String, 
+    data: &[u8], 
+    end_positions: &[usize], 
+    queries: &[usize]
+) -> BenchmarkResult {
+    benchmark_with_warmup(compressor, dataset_name, data, end_positions, queries, 0)
+}
+
+/// Benchmark function separating first-run and steady-state measurements
+/// 
+/// Follows the same measurement protocol as `benchmark`, where full decompression
+/// and the random access queries are repeated `warmup_iterations` times before the
+/// measured run. The first run is reported in `BenchmarkResult::first_run`.
+/// 
+/// # Arguments
+/// - `compressor`: The compression algorithm instance to benchmark
+/// - `dataset_name`: Name of the dataset being evaluated
+/// - `data`: Raw byte data from the dataset
+/// - `end_positions`: Boundary positions for individual strings in the data
+/// - `queries`: Vector of random indices for access pattern simulation
+/// - `warmup_iterations`: Runs of each repeatable phase excluded from measurement
+///
+/// # Returns
+/// - `BenchmarkResult`: Aggregated performance metrics for statistical analysis.
+pub fn benchmark_with_warmup<T: Compressor>(
+    compressor: &mut T, 
+    dataset_name: String, 
+    data: &[u8], 
+    end_positions: &[usize], 
+    queries: &[usize],
+    warmup_iterations: usize
+) -> BenchmarkResult {
+    let options = BenchmarkOptions { warmup_iterations, ..Default::default() };
+    benchmark_with_options(compressor, dataset_name, data, end_positions, queries, &options)
+}
+
+/// Benchmark function with configurable protocol options
+/// 
+/// Follows the same measurement protocol as `benchmark_with_warmup`. With a time
+/// budget, each random access run stops once the measured access time exceeds the
+/// budget (but not before `min_queries` queries), and the number of queries of the
+/// measured run is reported in `BenchmarkResult::n_queries`.
+/// 
+/// In streaming mode, decompression is measured through `Compressor::decompress_to`
+/// into a writer consuming and discarding the data (see `ScratchWriter`), and verified
+/// in a separate unmeasured pass; no buffer of the size of the dataset is allocated.
+/// Results are reported under the compressor name suffixed with "(streaming)".
+/// 
+/// Phases left out of `options.phases` are not measured, and their metrics are left
+/// out of the result; the dataset is compressed all the same, without a timeout.
+/// 
+/// With `options.validation`, a fraction of the strings is retrieved and checked one
+/// by one after the measured phases, including their UTF-8 validity (see
+/// `ValidationMetrics`); full decompression is verified regardless, and the queries of
+/// the access phases as selected by `options.verification`.
+/// 
+/// # Arguments
+/// - `compressor`: The compression algorithm instance to benchmark
+/// - `dataset_name`: Name of the dataset being evaluated
+/// - `data`: Raw byte data from the dataset
+/// - `end_positions`: Boundary positions for individual strings in the data
+/// - `queries`: Vector of random indices for access pattern simulation
+/// - `options`: Warm-up runs and query budget
+///
+/// # Returns
+/// - `BenchmarkResult`: Aggregated performance metrics for statistical analysis.
+pub fn benchmark_with_options<T: Compressor>(
+    compressor: &mut T, 
+    dataset_name: String, 
+    data: &[u8], 
+    end_positions: &[usize], 
+    queries: &[usize],
+    options: &BenchmarkOptions
+) -> BenchmarkResult {
+    let _span = info_span!("benchmark", compressor = compressor.name(), dataset = %dataset_name).entered();
+    let conditions = RunConditions::start();
+
+    // Phase 1: Compression measurement
+    let compress_span = info_span!("compress").entered();
+    let compress_profile = phase_profile::enter(&dataset_name, compressor.name(), "compress");
+    let compression_time = if options.phases.compress {
+        measure_compression_steps(options, compressor.compress_steps(data, end_positions))
+    } else {
+        compressor.compress(data, end_positions);
+        Some(0)
+    };
+    compress_profile.finish();
+    compress_span.exit();
+    let mut result = match compression_time {
+        Some(compression_time) => benchmark_compressed(compressor, dataset_name, data, end_positions, queries, options, compression_time),
+        None => timed_out_result(dataset_name, compressor.name(), options, "compress"),
+    };
+    result.conditions = Some(conditions.finish());
+    result
+}
+
+/// Measures a compressor that has already compressed the dataset
+///
+/// Runs the decompression and random access phases of `benchmark_with_options`,
+/// for callers compressing the dataset in their own way (e.g., with a shared
+/// dictionary). Must be called within the caller's benchmark span.
+///
+/// # Arguments
+/// - `compressor`: The compressor holding the compressed dataset
+/// - `dataset_name`: Name of the dataset being evaluated
+/// - `data`: Raw byte data from the dataset
+/// - `end_positions`: Boundary positions for individual strings in the data
+/// - `queries`: Vector of random indices for access pattern simulation
+/// - `options`: Warm-up runs and query budget
+/// - `compression_time`: Measured compression time in nanoseconds (see `measure_compression`),
+///   ignored if compression is not among `options.phases`
+///
+/// # Returns
+/// - `BenchmarkResult`: Aggregated performance metrics for statistical analysis.
+pub fn benchmark_compressed<T: Compressor>(
+    compressor: &mut T,
+    dataset_name: String,
+    data: &[u8],
+    end_positions: &[usize],
+    queries: &[usize],
+    options: &BenchmarkOptions,
+    compression_time: u128
+) -> BenchmarkResult {
+    let warmup_iterations = options.warmup_iterations;
+    let CompressionMetrics { compression_rate, boundary_compression_rate, compression_speed, space_breakdown } =
+        compression_metrics(compressor, data.len(), options, compression_time);
+
+    // Streaming decompression only needs room for the longest string, retrieved by random access
+    let buffer_size = if options.streaming { compressor.max_item_len() } else { data.len() };
+    let mut buffer: Vec<u8> = vec![0; buffer_size + BUFFER_PADDING];
+
+    // Phase 2: Decompression measurement with validation, after the warm-up runs
+    let decompression_speeds = if options.phases.decompress {
+        let Some(decompression_speeds) = measure_full_decompression(compressor, &mut buffer, &dataset_name, data, options) else {
+            return timed_out_result(dataset_name, compressor.name(), options, "decompress");
+        };
+        Some(decompression_speeds)
+    } else {
+        None
+    };
+
+    // Phases replaying the queries of the random access run are skipped along with it
+    let without_access = BenchmarkOptions {
+        batch_size: None,
+        scan_length: None,
+        profile_stages: false,
+        zero_copy: false,
+        perf_counters: false,
+        count_allocations: false,
+        plain_baselines: false,
+        latency_heatmap: false,
+        ..*options
+    };
+    let options = if options.phases.access { options } else { &without_access };
+
+    // Phase 3: Random access latency measurement, after the warm-up runs
+    let random_access = if options.phases.access {
+        measure_compressor_access(compressor, &mut buffer, &dataset_name, data, end_positions, queries, options)
+    } else {
+        Some(CompressorAccess { runs: AccessRuns::default(), latency_heatmap: None })
+    };
+    let Some(CompressorAccess { runs: access_runs, latency_heatmap }) = random_access else {
+        return timed_out_result(dataset_name, compressor.name(), options, "random_access");
+    };
+    let n_queries = access_runs.n_queries();
+    let access_throughput = options.phases.access.then(|| access_runs.throughput());
+    let AccessRuns { average_access_times: average_random_access_times, allocations: access_allocations, .. } = access_runs;
+
+    // Phases 4 to 9 replay the queries of the measured random access run: batches, access
+    // stages, zero-copy access, plain stores, hardware counters and scans
+    let random_access_time = average_random_access_times.last().copied().unwrap_or(0);
+    let replayed = measure_replayed_access(compressor, &mut buffer, data, end_positions, &queries[..n_queries], options, random_access_time);
+    let ReplayedAccess { batch_access, access_stages, zero_copy_access, plain_access, hardware_counters, scan } = match replayed {
+        Ok(replayed) => replayed,
+        Err(phase) => return timed_out_result(dataset_name, compressor.name(), options, phase),
+    };
+
+    // Phase 10: Validation of sampled strings one by one, unmeasured
+    let validation = if let Some(fraction) = options.validation {
+        let Some(metrics) = validate_items(compressor, &mut buffer, data, end_positions, fraction, options) else {
+            return timed_out_result(dataset_name, compressor.name(), options, "validation");
+        };
+        Some(metrics)
+    } else {
+        None
+    };
+
+    // Entropy bound of the dataset, outside of the measured phases, unless cached
+    let stats = options
+        .dataset_stats
+        .filter(|stats| stats.matches(data, end_positions))
+        .unwrap_or_else(|| DatasetStats::compute(data, end_positions));
+    let entropy = stats.entropy;
+    info!(entropy, efficiency = entropy_efficiency(entropy, compression_rate), "Entropy computed");
+    info!(length_code = stats.length_code.name(), bits_per_item = stats.bits_per_length, "Length code selected");
+
+    BenchmarkResult {
+        dataset_name,
+        compressor_name: result_name(compressor.name(), options),
+        compression_rate,
+        compression_speed,
+        decompression_speed: decompression_speeds.as_ref().map(|speeds| *speeds.last().unwrap()),
+        average_random_access_time: average_random_access_times.last().copied(),
+        access_throughput,
+        space_breakdown,
+        boundary_compression_rate: Some(boundary_compression_rate),
+        dedup_ratio: None,
+        first_run: first_run_metrics(warmup_iterations, decompression_speeds.as_deref(), &average_random_access_times),
+        entropy: Some(entropy),
+        length_code: Some(stats.length_code.name().to_string()),
+        n_queries: options.phases.access.then_some(n_queries),
+        query_policy: None,
+        input_order: None,
+        shared_dictionary: None,
+        holdout: None,
+        compression_split: None,
+        block_size_calibration: None,
+        block_stats: None,
+        literal_tokens: None,
+        matcher: None,
+        dictionary: None,
+        updates: None,
+        batch_access,
+        access_stages,
+        latency_heatmap,
+        scan,
+        validation,
+        zero_copy_access,
+        plain_access,
+        hardware_counters,
+        access_allocations,
+        placement: None,
+        conditions: None,
+        timed_out: None,
+        timer: Clock::of(options.timer).ok().map(Clock::metadata),
+        outlier: None,
+    }
+}
+
+/// Returns the first-run measurements of a run with warm-up, if it measured both
+/// decompression and random access
+///
+/// # Arguments
+/// - `warmup_iterations`: Runs of each repeatable phase excluded from measurement
+/// - `decompression_speeds`: Throughput of each decompression run, if measured
+/// - `average_access_times`: Average latency of each random access run (empty if not measured)
+pub fn first_run_metrics(warmup_iterations: usize, decompression_speeds: Option<&[f64]>, average_access_times: &[u128]) -> Option<FirstRunMetrics> {
+    match (decompression_speeds, average_access_times.first()) {
+        (Some(speeds), Some(&access_time)) if warmup_iterations > 0 => Some(FirstRunMetrics {
+            decompression_speed: speeds[0],
+            average_random_access_time: access_time,
+        }),
+        _ => None,
+    }
+}
+
+/// Returns the status row of a run cancelled during a phase, logging the cancellation
+pub fn timed_out_result(dataset_name: String, compressor_name: &str, options: &BenchmarkOptions, phase: &str) -> BenchmarkResult {
+    warn!(phase, timeout = options.phase_timeout.map_or(0.0, |timeout| timeout.as_secs_f64()), "Phase timed out, cancelling the run");
+    BenchmarkResult::timed_out(dataset_name, result_name(compressor_name, options), phase)
+}
+
+/// Returns the compressor name reported in results, marking streaming runs
+pub fn result_name(compressor_name: &str, options: &BenchmarkOptions) -> String {
+    if options.streaming { format!("{} (streaming)", compressor_name) } else { compressor_name.to_string() }
+}
+
+/// Measures how a dictionary compresses strings held out of its training
+///
+/// Splits the strings at random (see `split_holdout`), trains a dictionary on the
+/// training set with the parameters of `trainer`, and compresses both sets with it.
+/// Both compressed sets are verified by full decompression.
+///
+/// # Arguments
+/// - `trainer`: Compressor whose parameters train the dictionary (left untouched)
+/// - `data`: Concatenated strings
+/// - `end_positions`: Boundary positions of the strings
+/// - `holdout_fraction`: Fraction of the strings held out, in (0, 1)
+///
+/// # Returns
+/// - `Option<HoldoutMetrics>`: The metrics, or None if the dataset has fewer than two strings
+pub fn evaluate_holdout<T: DictionaryCompressor>(
+    trainer: &T,
+    data: &[u8],
+    end_positions: &[usize],
+    holdout_fraction: f64
+) -> Option<HoldoutMetrics> {
+    let ((train_data, train_end_positions), (holdout_data, holdout_end_positions)) =
+        split_holdout(data, end_positions, holdout_fraction)?;
+    let n_holdout = holdout_end_positions.len() - 1;
+    let _span = info_span!("holdout", n_holdout, holdout_fraction).entered();
+
+    let dictionary = trainer.train_dictionary(&train_data, &train_end_positions);
+    let mut dictionary_size = 0;
+    let mut compression_rate = |data: &[u8], end_positions: &[usize]| {
+        let mut compressor = T::new(data.len(), end_positions.len() - 1);
+        compressor.compress_with_dictionary(data, end_positions, &dictionary);
+        let mut buffer = vec![0; data.len() + BUFFER_PADDING];
+        let size = compressor.decompress(&mut buffer);
+        if data != &buffer[..size] {
+            panic!("Data mismatch during holdout decompression for compressor: {}", compressor.name());
+        }
+        dictionary_size = compressor.space_breakdown().dictionary;
+        data.len() as f64 / (compressor.space_used_bytes() - dictionary_size).max(1) as f64
+    };
+    let in_sample_compression_rate = compression_rate(&train_data, &train_end_positions);
+    let out_of_sample_compression_rate = compression_rate(&holdout_data, &holdout_end_positions);
+    info!(in_sample_compression_rate, out_of_sample_compression_rate, "Holdout evaluated");
+
+    Some(HoldoutMetrics { holdout_fraction, n_holdout, dictionary_size, in_sample_compression_rate, out_of_sample_compression_rate })
+}
+
+/// Measures the compression time of a dictionary compressor, split between training and parsing
+///
+/// Trains a dictionary on all strings, then compresses them with it, timing both
+/// steps separately (see `CompressionSplit`). The output is checked, unmeasured.
+///
+/// # Arguments
+/// - `trainer`: Compressor whose configuration trains the dictionary
+/// - `data`: Concatenated strings
+/// - `end_positions`: Boundary positions of the strings, starting with 0
+pub fn measure_compression_split<T: DictionaryCompressor>(trainer: &T, data: &[u8], end_positions: &[usize]) -> CompressionSplit {
+    let _span = info_span!("split").entered();
+    let mut timing = Timing::new();
+    timing.start();
+    let dictionary = trainer.train_dictionary(data, end_positions);
+    let training_time = timing.stop() as f64 / 1e9;
+
+    let mut compressor = T::new(data.len(), end_positions.len() - 1);
+    timing.start();
+    compressor.compress_with_dictionary(data, end_positions, &dictionary);
+    let parse_time = timing.stop() as f64 / 1e9;
+
+    let mut buffer = vec![0; data.len() + BUFFER_PADDING];
+    let size = compressor.decompress(&mut buffer);
+    if data != &buffer[..size] {
+        panic!("Data mismatch during split compression for compressor: {}", compressor.name());
+    }
+    info!(training_time, parse_time, "Compression split between training and parsing");
+    CompressionSplit { data_size: data.len(), training_time, parse_time }
+}
+
+/// Concatenated strings of a subset and their boundary positions, starting with 0
+pub type StringSet = (Vec<u8>, Vec<usize>);
+
+/// Splits strings into a training set and a random held-out set
+///
+/// At least one string is held out and one kept for training; strings keep their
+/// order within each set.
+///
+/// # Returns
+/// - `Option<(StringSet, StringSet)>`: Training and held-out sets, or None if there are
+///   fewer than two strings
+pub fn split_holdout(data: &[u8], end_positions: &[usize], holdout_fraction: f64) -> Option<(StringSet, StringSet)> {
+    let n_items = end_positions.len() - 1;
+    if n_items < 2 {
+        return None;
+    }
+    let n_holdout = ((n_items as f64 * holdout_fraction).round() as usize).clamp(1, n_items - 1);
+    let mut held_out = vec![false; n_items];
+    for item in index::sample(&mut thread_rng(), n_items, n_holdout) {
+        held_out[item] = true;
+    }
+
+    let mut train = (Vec::with_capacity(data.len()), vec![0]);
+    let mut holdout = (Vec::new(), vec![0]);
+    for (item, &is_held_out) in held_out.iter().enumerate() {
+        let (set_data, set_end_positions) = if is_held_out { &mut holdout } else { &mut train };
+        set_data.extend_from_slice(&data[end_positions[item]..end_positions[item + 1]]);
+        set_end_positions.push(set_data.len());
+    }
+    Some((train, holdout))
+}
+
+/// Measures re-compression against patching as strings are replaced
+///
+/// Runs `n_rounds` rounds, each replacing `update_fraction` of the strings, picked at
+/// random, with strings drawn at random from the dataset (so that new values follow the
+/// distribution of the data). After each round, the current strings are compressed
+/// again from scratch, and the store of the previous round is patched (see
+/// `UpdateMetrics`). The patched store is verified against the current strings at the end.
+///
+/// # Arguments
+/// - `algorithm`: Compressor identifier (see `registry::available_compressors`)
+/// - `data`: Concatenated strings
+/// - `end_positions`: Boundary positions of the strings
+/// - `update_fraction`: Fraction of the strings replaced in each round, in (0, 1]
+/// - `n_rounds`: Number of rounds of updates
+///
+/// # Returns
+/// - `Result<UpdateMetrics, String>`: The metrics, or an error if the algorithm cannot
+///   compress the strings
+pub fn evaluate_updates(
+    algorithm: &str,
+    data: &[u8],
+    end_positions: &[usize],
+    update_fraction: f64,
+    n_rounds: usize
+) -> Result<UpdateMetrics, String> {
+    let n_items = end_positions.len() - 1;
+    let n_updates = ((n_items as f64 * update_fraction).round() as usize).clamp(1, n_items.max(1));
+    let _span = info_span!("updates", update_fraction, n_rounds).entered();
+    let config = StoreConfig { algorithm: algorithm.to_string(), sorted: false };
+    let items: Vec<&[u8]> = end_positions.windows(2).map(|w| &data[w[0]..w[1]]).collect();
+    let mut current = items.clone();
+    let data_size = |current: &[&[u8]]| current.iter().map(|item| item.len()).sum::<usize>();
+
+    let start_compression = Instant::now();
+    let compressed = CompressedStringStore::build(items.iter().copied(), &config)?;
+    let compression_time = start_compression.elapsed().as_secs_f64();
+    let mut store = UpdatableStore::build(items.iter().copied(), &config)?;
+    let mut rounds = vec![UpdateRound {
+        updated_items: 0,
+        data_size: data.len(),
+        recompression_time: compression_time,
+        recompressed_size: compressed.memory_usage(),
+        patch_time: 0.0,
+        patched_size: store.memory_usage(),
+        n_segments: store.n_segments(),
+        dead_items: 0,
+    }];
+
+    // Empty datasets have no string to replace
+    let n_rounds = if n_items > 0 { n_rounds } else { 0 };
+    let mut rng = thread_rng();
+    for round in 1..=n_rounds {
+        let updates: Vec<(usize, &[u8])> = index::sample(&mut rng, n_items, n_updates)
+            .into_iter()
+            .map(|index| (index, items[rng.gen_range(0..n_items)]))
+            .collect();
+        for &(index, value) in updates.iter() {
+            current[index] = value;
+        }
+
+        let start_patch = Instant::now();
+        store.replace(updates.iter().copied())?;
+        let patch_time = start_patch.elapsed().as_secs_f64();
+
+        let start_recompression = Instant::now();
+        let recompressed = CompressedStringStore::build(current.iter().copied(), &config)?;
+        let recompression_time = start_recompression.elapsed().as_secs_f64();
+
+        let metrics = UpdateRound {
+            updated_items: round * n_updates,
+            data_size: data_size(&current),
+            recompression_time,
+            recompressed_size: recompressed.memory_usage(),
+            patch_time,
+            patched_size: store.memory_usage(),
+            n_segments: store.n_segments(),
+            dead_items: store.dead_items(),
+        };
+        info!(
+            round,
+            recompression_time,
+            patch_time,
+            space_overhead = metrics.patched_size as f64 / metrics.recompressed_size.max(1) as f64,
+            "Update round completed"
+        );
+        rounds.push(metrics);
+    }
+
+    let mut buffer = Vec::new();
+    for (index, &item) in current.iter().enumerate() {
+        store.get(index, &mut buffer);
+        if buffer != item {
+            panic!("Data mismatch in patched store at index {} for algorithm: {}", index, algorithm);
+        }
+    }
+
+    Ok(UpdateMetrics { update_fraction, rounds })
+}
+
+
+/// Writer consuming data into a fixed scratch buffer, discarding it
+/// 
+/// Stands in for a consumer reading the stream (e.g., a file or a socket), so that
+/// compressors handing out their storage directly are not measured as free.
+pub struct ScratchWriter {
+    scratch: Vec<u8>,
+}
+
+impl ScratchWriter {
+    /// Creates a writer with a scratch buffer of `STREAM_CHUNK_SIZE` bytes
+    pub fn new() -> Self {
+        ScratchWriter { scratch: vec![0; STREAM_CHUNK_SIZE] }
+    }
+}
+
+impl Default for ScratchWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Write for ScratchWriter {
+    #[inline(always)]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for chunk in buf.chunks(self.scratch.len()) {
+            self.scratch[..chunk.len()].copy_from_slice(chunk);
+            // Keeps the copy from being optimized away
+            std::hint::black_box(&mut self.scratch);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writer comparing the written data against the expected content
+pub struct VerifyingWriter<'a> {
+    expected: &'a [u8],
+    position: usize,    // Bytes verified so far
+}
+
+impl<'a> VerifyingWriter<'a> {
+    /// Creates a writer expecting exactly `expected`
+    pub fn new(expected: &'a [u8]) -> Self {
+        VerifyingWriter { expected, position: 0 }
+    }
+
+    /// Returns whether all the expected content has been written
+    pub fn is_complete(&self) -> bool {
+        self.position == self.expected.len()
+    }
+}
+
+impl Write for VerifyingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let end = self.position + buf.len();
+        if self.expected.get(self.position..end) != Some(buf) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Data mismatch at byte {}", self.position)));
+        }
+        self.position = end;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Tracks the measured access time of a random access run against its budget
+pub struct BudgetTracker {
+    budget: Option<u128>,   // Budget in nanoseconds
+    min_queries: usize,
+    elapsed: u128,          // Measured access time so far, in nanoseconds
+    n_queries: usize,
+}
+
+impl BudgetTracker {
+    /// Starts tracking a run under the budget of the given options
+    pub fn new(options: &BenchmarkOptions) -> Self {
+        BudgetTracker {
+            budget: options.time_budget.map(|budget| budget.as_nanos()),
+            min_queries: options.min_queries,
+            elapsed: 0,
+            n_queries: 0,
+        }
+    }
+
+    /// Records the measured time of a query, in nanoseconds
+    #[inline(always)]
+    pub fn add(&mut self, time: u128) {
+        self.elapsed += time;
+        self.n_queries += 1;
+    }
+
+    /// Returns whether the run must stop before the next query
+    #[inline(always)]
+    pub fn is_exhausted(&self) -> bool {
+        self.budget.is_some_and(|budget| self.elapsed >= budget && self.n_queries >= self.min_queries.max(1))
+    }
+}
+
+/// Wall-clock deadline of a benchmark phase (see `BenchmarkOptions::phase_timeout`)
+/// 
+/// Phases check the deadline between operations: a single operation (e.g., a unit
+/// of work of an incremental compression, see `incremental`) cannot be interrupted,
+/// and is detected once it returns.
+pub struct PhaseDeadline {
+    start: Instant,
+    timeout: Option<Duration>,
+}
+
+impl PhaseDeadline {
+    /// Starts a phase under the timeout of the given options
+    pub fn start(options: &BenchmarkOptions) -> Self {
+        PhaseDeadline { start: Instant::now(), timeout: options.phase_timeout }
+    }
+
+    /// Returns whether the phase has run longer than the timeout
+    #[inline(always)]
+    pub fn is_exceeded(&self) -> bool {
+        self.timeout.is_some_and(|timeout| self.start.elapsed() > timeout)
+    }
+}
+
+/// Removes a valueless flag from the command-line arguments
+///
+/// # Arguments
+/// - `args`: Command-line arguments
+/// - `name`: Flag name, including the leading dashes (e.g., `--cover-all`)
+///
+/// # Returns
+/// Whether the flag was present
+pub fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    let n_args = args.len();
+    args.retain(|arg| arg != name);
+    args.len() != n_args
+}
+
+/// Removes a `<name>=<value>` option from the command-line arguments
+/// 
+/// Options may appear anywhere among the positional arguments.
+///
+/// # Arguments
+/// - `args`: Command-line arguments
+/// - `name`: Option name, including the leading dashes (e.g., `--warmup`)
+///
+/// # Returns
+/// The value of the last occurrence of the option, or `None` if absent
+pub fn take_option(args: &mut Vec<String>, name: &str) -> Option<String> {
+    let prefix = format!("{}=", name);
+    let mut value = None;
+    args.retain(|arg| match arg.strip_prefix(&prefix) {
+        Some(v) => {
+            value = Some(v.to_string());
+            false
+        }
+        None => true,
+    });
+    value
+}
+
+
+/// Version of the results file schema written by this crate
+///
+/// - 1: JSON array of results, as written before results files were versioned
+/// - 2: JSON object holding the schema version and the array of results, or JSON Lines
+///   (`.jsonl` files) with a version header line followed by one result per line
+///
+/// Older files are migrated when read (see `load_benchmark_results`), and rewritten in
+/// the current schema when results are appended to them.
+pub const RESULTS_SCHEMA_VERSION: u32 = 2;
+
+/// Versioned results file, as written in the current schema
+#[derive(Serialize)]
+struct ResultsFile<'a> {
+    schema_version: u32,
+    results: &'a [BenchmarkResult],
+}
+
+/// Header line of a JSON Lines results file
+#[derive(Serialize, Deserialize)]
+struct ResultsHeader {
+    schema_version: u32,
+}
+
+/// Returns whether results are stored as JSON Lines, from the file extension
+pub fn is_jsonl_results(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| extension == "jsonl")
+}
+
+/// Reads benchmark results from a JSON or JSON Lines file, migrating older schemas
+///
+/// # Arguments
+/// - `path`: Path to the results file (`.jsonl` for JSON Lines)
+///
+/// # Returns
+/// - `Result<Vec<BenchmarkResult>, String>`: Results in file order, or a description of
+///   why the file cannot be read
+pub fn load_benchmark_results(path: &Path) -> Result<Vec<BenchmarkResult>, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let results = if is_jsonl_results(path) { parse_results_jsonl(&content) } else { parse_results_json(&content) };
+    results.map_err(|message| format!("Failed to parse {}: {}", path.display(), message))
+}
+
+/// Parses a JSON results file: a bare array (schema 1) or a versioned object
+fn parse_results_json(content: &str) -> Result<Vec<BenchmarkResult>, String> {
+    let value: serde_json::Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    match value {
+        serde_json::Value::Array(results) => migrate_results(1, results),
+        serde_json::Value::Object(mut object) => {
+            let schema_version = object
+                .get("schema_version")
+                .and_then(serde_json::Value::as_u64)
+                .ok_or("missing schema_version")?;
+            match object.remove("results") {
+                Some(serde_json::Value::Array(results)) => migrate_results(schema_version as u32, results),
+                _ => Err("missing results array".to_string()),
+            }
+        }
+        _ => Err("expected an array of results or a versioned results object".to_string()),
+    }
+}
+
+/// Parses a JSON Lines results file
+///
+/// Header lines may appear anywhere (e.g., in files concatenated across campaigns), each
+/// setting the schema of the results that follow. Truncated lines, left by processes
+/// killed while appending, are skipped with a warning.
+fn parse_results_jsonl(content: &str) -> Result<Vec<BenchmarkResult>, String> {
+    let mut results = Vec::new();
+    let mut schema_version = RESULTS_SCHEMA_VERSION;
+    let mut pending = Vec::new();
+    for (line_number, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value: serde_json::Value = match serde_json::from_str(line) {
+            Ok(value) => value,
+            Err(e) if e.is_eof() => {
+                warn!(line = line_number + 1, "Skipping truncated line of results file");
+                continue;
+            }
+            Err(e) => return Err(format!("line {}: {}", line_number + 1, e)),
+        };
+        if value.get("dataset_name").is_none() {
+            let header: ResultsHeader = serde_json::from_value(value).map_err(|e| format!("line {}: {}", line_number + 1, e))?;
+            results.extend(migrate_results(schema_version, std::mem::take(&mut pending))?);
+            schema_version = header.schema_version;
+        } else {
+            pending.push(value);
+        }
+    }
+    results.extend(migrate_results(schema_version, pending)?);
+    Ok(results)
+}
+
+/// Converts results of a schema version to the current schema
+///
+/// Schema 2 only versions the file itself: results of schema 1 are read unchanged, with
+/// the metrics they predate taking their default (absent) values.
+fn migrate_results(schema_version: u32, results: Vec<serde_json::Value>) -> Result<Vec<BenchmarkResult>, String> {
+    if schema_version == 0 || schema_version > RESULTS_SCHEMA_VERSION {
+        return Err(format!("unsupported schema version {} (supported: 1 to {})", schema_version, RESULTS_SCHEMA_VERSION));
+    }
+    results
+        .into_iter()
+        .enumerate()
+        .map(|(index, result)| serde_json::from_value(result).map_err(|e| format!("result {}: {}", index, e)))
+        .collect()
+}
+
+/// Reads benchmark results from a results file
+/// 
+/// Loads previously saved benchmark results for analysis or continuation of benchmarking.
+/// Returns an empty vector if the file doesn't exist, and panics if it cannot be parsed,
+/// so that existing results are never overwritten.
+///
+/// # Arguments
+/// - `file_path`: Path to the JSON or JSON Lines results file
+/// 
+/// # Returns
+/// - `Vec<BenchmarkResult>`: Loaded benchmark results
+pub fn read_benchmark_results(file_path: &str) -> Vec<BenchmarkResult> {
+    let path = Path::new(file_path);
+    if path.exists() {
+        load_benchmark_results(path).unwrap_or_else(|message| panic!("{}", message))
+    } else {
+        Vec::new()
+    }
+}
+
+/// Appends a new benchmark result to the results file
+/// 
+/// JSON Lines files get one more line, written in a single append, so that the cost of
+/// appending does not grow with the number of results. JSON files are read, extended
+/// and atomically rewritten in the current schema. Creates the file if it doesn't exist,
+/// and panics if existing results cannot be parsed.
+///
+/// # Arguments
+/// - `result`: The new benchmark result to append
+/// - `output_path`: Path to the output JSON or JSON Lines file
+pub fn append_benchmark_result(result: &BenchmarkResult, output_path: &Path) {
+    if is_jsonl_results(output_path) {
+        let mut file = fs::OpenOptions::new().read(true).create(true).append(true).open(output_path).expect("Failed to open results file");
+        let mut lines = Vec::new();
+        if file.metadata().expect("Failed to read results file metadata").len() == 0 {
+            serde_json::to_writer(&mut lines, &ResultsHeader { schema_version: RESULTS_SCHEMA_VERSION }).unwrap();
+            lines.push(b'\n');
+        } else {
+            // Terminate a truncated last line, so that it does not corrupt the new one
+            let mut last_byte = [0u8];
+            file.seek(SeekFrom::End(-1)).and_then(|_| file.read_exact(&mut last_byte)).expect("Failed to read results file");
+            if last_byte[0] != b'\n' {
+                lines.push(b'\n');
+            }
+        }
+        serde_json::to_writer(&mut lines, result).expect("Failed to serialize result");
+        lines.push(b'\n');
+        file.write_all(&lines).expect("Failed to append result to file");
+        return;
+    }
+
+    let mut results = read_benchmark_results(output_path.to_str().unwrap());
+    results.push(result.clone());
+    write_benchmark_results(&results, output_path);
+}
+
+/// Writes benchmark results to a JSON or JSON Lines file, replacing its content
+/// 
+/// Unlike `append_benchmark_result`, the file is written once, so that results
+/// collected by parallel workers can be persisted without racing on the file.
+/// The file is replaced atomically (see `write_file_atomically`).
+///
+/// # Arguments
+/// - `results`: Benchmark results to write
+/// - `output_path`: Path to the output JSON or JSON Lines file
+pub fn write_benchmark_results(results: &[BenchmarkResult], output_path: &Path) {
+    let content = if is_jsonl_results(output_path) {
+        let mut lines = serde_json::to_vec(&ResultsHeader { schema_version: RESULTS_SCHEMA_VERSION }).unwrap();
+        lines.push(b'\n');
+        for result in results {
+            serde_json::to_writer(&mut lines, result).expect("Failed to serialize results");
+            lines.push(b'\n');
+        }
+        lines
+    } else {
+        let file = ResultsFile { schema_version: RESULTS_SCHEMA_VERSION, results };
+        serde_json::to_vec_pretty(&file).expect("Failed to serialize results")
+    };
+    write_file_atomically(output_path, &content).expect("Failed to write results to file");
+}
+
+/// Replaces the content of a file atomically
+///
+/// The content is written to a temporary file next to the destination, flushed to disk,
+/// then renamed over the destination, so that readers and interrupted runs see either
+/// the old or the new content, never a partially written file.
+pub fn write_file_atomically(path: &Path, content: &[u8]) -> io::Result<()> {
+    let mut temporary_path = path.as_os_str().to_owned();
+    temporary_path.push(format!(".{}.tmp", std::process::id()));
+    let temporary_path = Path::new(&temporary_path);
+
+    let written = fs::File::create(temporary_path).and_then(|mut file| {
+        file.write_all(content)?;
+        file.sync_all()
+    });
+    let result = written.and_then(|_| fs::rename(temporary_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(temporary_path);
+    }
+    result
+}
+
+/// Summary statistics of a metric over repeated iterations
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug)]
+pub struct MetricSummary {
+    pub mean: f64,
+    pub stddev: f64,    // Sample standard deviation
+    pub ci95: f64,      // Half-width of the 95% confidence interval of the mean
+}
+
+impl MetricSummary {
+    /// Computes mean, sample standard deviation and 95% confidence interval
+    /// 
+    /// The confidence interval uses the Student's t distribution, which is
+    /// appropriate for the small number of iterations of a benchmark.
+    /// 
+    /// # Arguments
+    /// - `samples`: Measured values of the metric, one per iteration
+    pub fn from_samples(samples: &[f64]) -> Self {
+        let n = samples.len();
+        if n == 0 {
+            return Self::default();
+        }
+
+        let mean = samples.iter().sum::<f64>() / n as f64;
+        if n == 1 {
+            return MetricSummary { mean, stddev: 0.0, ci95: 0.0 };
+        }
+
+        let variance = samples.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / (n - 1) as f64;
+        let stddev = variance.sqrt();
+        let ci95 = student_t_975(n - 1) * stddev / (n as f64).sqrt();
+
+        MetricSummary { mean, stddev, ci95 }
+    }
+
+    /// Returns the coefficient of variation (standard deviation relative to the mean)
+    pub fn coefficient_of_variation(&self) -> f64 {
+        if self.mean == 0.0 {
+            0.0
+        } else {
+            self.stddev / self.mean.abs()
+        }
+    }
+}
+
+/// Returns the 0.975 quantile of the Student's t distribution
+/// 
+/// # Arguments
+/// - `degrees_of_freedom`: Number of samples minus one (at least 1)
+fn student_t_975(degrees_of_freedom: usize) -> f64 {
+    const TABLE: [f64; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228,
+        2.201, 2.179, 2.160, 2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086,
+        2.080, 2.074, 2.069, 2.064, 2.060, 2.056, 2.052, 2.048, 2.045, 2.042,
+    ];
+    TABLE.get(degrees_of_freedom.max(1) - 1).copied().unwrap_or(1.960)
+}
+
+/// Aggregated metrics of all iterations of an algorithm-dataset combination
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BenchmarkSummary {
+    pub dataset_name: String,
+    pub compressor_name: String,
+    pub n_iterations: usize,                // Iterations aggregated, outliers excluded
+    #[serde(default)]
+    pub n_outliers: usize,                  // Iterations flagged as outliers (see `flag_outliers`)
+    pub compression_rate: MetricSummary,
+    #[serde(default)]
+    pub compression_speed: Option<MetricSummary>, // Present if every iteration measured it (see `Phases`)
+    #[serde(default)]
+    pub decompression_speed: Option<MetricSummary>, // Present if every iteration measured it
+    #[serde(default)]
+    pub average_random_access_time: Option<MetricSummary>, // Present if every iteration measured it
+    #[serde(default)]
+    pub access_throughput: Option<AccessThroughputSummary>, // Present if every iteration measured it
+    pub space_breakdown: SpaceBreakdown,    // Averaged over iterations
+    #[serde(default)]
+    pub dedup_ratio: Option<f64>,           // Deterministic across iterations
+    #[serde(default)]
+    pub first_run: Option<FirstRunSummary>, // Present if every iteration ran a warm-up
+    #[serde(default)]
+    pub entropy: Option<f64>,               // Deterministic across iterations
+    #[serde(default)]
+    pub length_code: Option<String>,        // Deterministic across iterations
+    #[serde(default)]
+    pub batch_access: Option<BatchAccessSummary>, // Present if every iteration measured batch access
+    #[serde(default)]
+    pub scan: Option<ScanSummary>,          // Present if every iteration measured scans
+    #[serde(default)]
+    pub access_stages: Option<AccessStageSummary>, // Present if every iteration profiled access stages
+    #[serde(default)]
+    pub zero_copy_access: Option<ZeroCopyAccessSummary>, // Present if every iteration measured zero-copy access
+    #[serde(default)]
+    pub plain_access: Option<PlainAccessSummary>, // Present if every iteration measured plain stores
+    #[serde(default)]
+    pub hardware_counters: Option<HardwareCounterSummary>, // Present if every iteration read hardware counters
+    #[serde(default)]
+    pub access_allocations: Option<usize>,  // Most allocations of a measured random access run, if every iteration counted them
+    #[serde(default)]
+    pub placement: Option<Placement>,       // Placement of the first iteration
+    #[serde(default)]
+    pub holdout: Option<HoldoutSummary>,    // Present if every iteration held out strings
+}
+
+impl BenchmarkSummary {
+    /// Returns the entropy over the mean achieved bits per byte (see `entropy::entropy_efficiency`)
+    pub fn entropy_efficiency(&self) -> Option<f64> {
+        self.entropy.map(|entropy| entropy_efficiency(entropy, self.compression_rate.mean))
+    }
+}
+
+/// Aggregated first-run measurements of all iterations
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct FirstRunSummary {
+    pub decompression_speed: MetricSummary,
+    pub average_random_access_time: MetricSummary,
+}
+
+/// Aggregated batch access measurements of all iterations
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct BatchAccessSummary {
+    pub batch_size: usize,
+    pub average_access_time: MetricSummary,
+    pub speedup: MetricSummary,
+}
+
+/// Aggregated random access throughputs of all iterations
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct AccessThroughputSummary {
+    pub item_rate: MetricSummary,
+    pub byte_throughput: MetricSummary,
+}
+
+/// Aggregated scan measurements of all iterations
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct ScanSummary {
+    pub scan_length: usize,
+    pub throughput: MetricSummary,
+    pub item_time: MetricSummary,
+    pub speedup: MetricSummary,
+}
+
+/// Aggregated access stage attributions of all iterations
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct AccessStageSummary {
+    pub lookup_time: MetricSummary,
+    pub decode_time: MetricSummary,
+    pub copy_time: MetricSummary,
+}
+
+/// Aggregated zero-copy access measurements of all iterations
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct ZeroCopyAccessSummary {
+    pub average_access_time: MetricSummary,
+    pub copy_overhead: MetricSummary,
+}
+
+/// Aggregated plain store access measurements of all iterations
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct PlainAccessSummary {
+    pub vec_access_time: MetricSummary,
+    pub hash_map_access_time: MetricSummary,
+    pub slowdown: MetricSummary,
+}
+
+/// Aggregated hardware events of all iterations, per query
+///
+/// Each event is present if it was counted in every iteration.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct HardwareCounterSummary {
+    pub instructions: Option<MetricSummary>,
+    pub llc_misses: Option<MetricSummary>,
+    pub branch_misses: Option<MetricSummary>,
+}
+
+/// Aggregated holdout measurements of all iterations (each with its own random split)
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct HoldoutSummary {
+    pub in_sample_compression_rate: MetricSummary,
+    pub out_of_sample_compression_rate: MetricSummary,
+}
+
+/// Aggregates benchmark results by compressor and dataset
+/// 
+/// Timed-out runs carry no metrics and are skipped (see `BenchmarkResult::timed_out`),
+/// and runs flagged as outliers are counted but not aggregated (see `flag_outliers`).
+/// 
+/// # Arguments
+/// - `results`: Results of individual iterations
+/// 
+/// # Returns
+/// - `Vec<BenchmarkSummary>`: One summary per (compressor, dataset) pair, sorted by
+///   compressor and dataset name
+pub fn summarize_benchmark_results(results: &[BenchmarkResult]) -> Vec<BenchmarkSummary> {
+    // Group results by compressor and dataset name
+    let mut grouped_results: BTreeMap<(String, String), Vec<&BenchmarkResult>> = BTreeMap::new();
+    for result in results.iter().filter(|r| r.timed_out.is_none()) {
+        grouped_results
+            .entry((result.compressor_name.clone(), result.dataset_name.clone()))
+            .or_default()
+            .push(result);
+    }
+
+    grouped_results
+        .into_iter()
+        .map(|((compressor, dataset), mut group)| {
+            let n_runs = group.len();
+            group.retain(|r| r.outlier.is_none());
+            (compressor, dataset, group, n_runs)
+        })
+        .filter(|(_, _, group, _)| !group.is_empty())
+        .map(|(compressor, dataset, group, n_runs)| {
+            let summarize = |metric: fn(&BenchmarkResult) -> f64| {
+                MetricSummary::from_samples(&group.iter().map(|r| metric(r)).collect::<Vec<f64>>())
+            };
+            let summarize_measured = |metric: fn(&BenchmarkResult) -> Option<f64>| {
+                group.iter().map(|r| metric(r)).collect::<Option<Vec<f64>>>().map(|samples| MetricSummary::from_samples(&samples))
+            };
+            let len = group.len();
+
+            BenchmarkSummary {
+                dataset_name: dataset,
+                compressor_name: compressor,
+                n_iterations: len,
+                n_outliers: n_runs - len,
+                compression_rate: summarize(|r| r.compression_rate),
+                compression_speed: summarize_measured(|r| r.compression_speed),
+                decompression_speed: summarize_measured(|r| r.decompression_speed),
+                average_random_access_time: summarize_measured(|r| r.average_random_access_time.map(|time| time as f64)),
+                access_throughput: group
+                    .iter()
+                    .map(|r| r.access_throughput)
+                    .collect::<Option<Vec<AccessThroughputMetrics>>>()
+                    .map(|throughputs| AccessThroughputSummary {
+                        item_rate: MetricSummary::from_samples(&throughputs.iter().map(|t| t.item_rate).collect::<Vec<f64>>()),
+                        byte_throughput: MetricSummary::from_samples(
+                            &throughputs.iter().map(|t| t.byte_throughput).collect::<Vec<f64>>()
+                        ),
+                    }),
+                space_breakdown: SpaceBreakdown {
+                    payload: group.iter().map(|r| r.space_breakdown.payload).sum::<usize>() / len,
+                    dictionary: group.iter().map(|r| r.space_breakdown.dictionary).sum::<usize>() / len,
+                    item_offsets: group.iter().map(|r| r.space_breakdown.item_offsets).sum::<usize>() / len,
+                    block_metadata: group.iter().map(|r| r.space_breakdown.block_metadata).sum::<usize>() / len,
+                    id_map: group.iter().map(|r| r.space_breakdown.id_map).sum::<usize>() / len,
+                    checksums: group.iter().map(|r| r.space_breakdown.checksums).sum::<usize>() / len,
+                },
+                dedup_ratio: group[0].dedup_ratio,
+                entropy: group[0].entropy,
+                length_code: group[0].length_code.clone(),
+                first_run: group
+                    .iter()
+                    .map(|r| r.first_run)
+                    .collect::<Option<Vec<FirstRunMetrics>>>()
+                    .map(|first_runs| FirstRunSummary {
+                        decompression_speed: MetricSummary::from_samples(
+                            &first_runs.iter().map(|f| f.decompression_speed).collect::<Vec<f64>>()
+                        ),
+                        average_random_access_time: MetricSummary::from_samples(
+                            &first_runs.iter().map(|f| f.average_random_access_time as f64).collect::<Vec<f64>>()
+                        ),
+                    }),
+                batch_access: group
+                    .iter()
+                    .map(|r| r.batch_access)
+                    .collect::<Option<Vec<BatchAccessMetrics>>>()
+                    .map(|batches| BatchAccessSummary {
+                        batch_size: batches[0].batch_size,
+                        average_access_time: MetricSummary::from_samples(
+                            &batches.iter().map(|b| b.average_access_time as f64).collect::<Vec<f64>>()
+                        ),
+                        speedup: MetricSummary::from_samples(&batches.iter().map(|b| b.speedup).collect::<Vec<f64>>()),
+                    }),
+                scan: group
+                    .iter()
+                    .map(|r| r.scan)
+                    .collect::<Option<Vec<ScanMetrics>>>()
+                    .map(|scans| ScanSummary {
+                        scan_length: scans[0].scan_length,
+                        throughput: MetricSummary::from_samples(&scans.iter().map(|s| s.throughput).collect::<Vec<f64>>()),
+                        item_time: MetricSummary::from_samples(&scans.iter().map(|s| s.item_time).collect::<Vec<f64>>()),
+                        speedup: MetricSummary::from_samples(&scans.iter().map(|s| s.speedup).collect::<Vec<f64>>()),
+                    }),
+                access_stages: group
+                    .iter()
+                    .map(|r| r.access_stages)
+                    .collect::<Option<Vec<AccessStageMetrics>>>()
+                    .map(|stages| AccessStageSummary {
+                        lookup_time: MetricSummary::from_samples(&stages.iter().map(|s| s.lookup_time).collect::<Vec<f64>>()),
+                        decode_time: MetricSummary::from_samples(&stages.iter().map(|s| s.decode_time).collect::<Vec<f64>>()),
+                        copy_time: MetricSummary::from_samples(&stages.iter().map(|s| s.copy_time).collect::<Vec<f64>>()),
+                    }),
+                zero_copy_access: group
+                    .iter()
+                    .map(|r| r.zero_copy_access)
+                    .collect::<Option<Vec<ZeroCopyAccessMetrics>>>()
+                    .map(|accesses| ZeroCopyAccessSummary {
+                        average_access_time: MetricSummary::from_samples(
+                            &accesses.iter().map(|z| z.average_access_time as f64).collect::<Vec<f64>>()
+                        ),
+                        copy_overhead: MetricSummary::from_samples(&accesses.iter().map(|z| z.copy_overhead).collect::<Vec<f64>>()),
+                    }),
+                plain_access: group
+                    .iter()
+                    .map(|r| r.plain_access)
+                    .collect::<Option<Vec<PlainAccessMetrics>>>()
+                    .map(|accesses| PlainAccessSummary {
+                        vec_access_time: MetricSummary::from_samples(
+                            &accesses.iter().map(|p| p.vec_access_time as f64).collect::<Vec<f64>>()
+                        ),
+                        hash_map_access_time: MetricSummary::from_samples(
+                            &accesses.iter().map(|p| p.hash_map_access_time as f64).collect::<Vec<f64>>()
+                        ),
+                        slowdown: MetricSummary::from_samples(&accesses.iter().map(|p| p.slowdown).collect::<Vec<f64>>()),
+                    }),
+                hardware_counters: group
+                    .iter()
+                    .map(|r| r.hardware_counters)
+                    .collect::<Option<Vec<HardwareCounterMetrics>>>()
+                    .map(|counters| {
+                        let summarize = |event: fn(&HardwareCounterMetrics) -> Option<f64>| {
+                            counters
+                                .iter()
+                                .map(event)
+                                .collect::<Option<Vec<f64>>>()
+                                .map(|samples| MetricSummary::from_samples(&samples))
+                        };
+                        HardwareCounterSummary {
+                            instructions: summarize(|h| h.instructions),
+                            llc_misses: summarize(|h| h.llc_misses),
+                            branch_misses: summarize(|h| h.branch_misses),
+                        }
+                    }),
+                access_allocations: group
+                    .iter()
+                    .map(|r| r.access_allocations)
+                    .collect::<Option<Vec<usize>>>()
+                    .and_then(|allocations| allocations.into_iter().max()),
+                placement: group[0].placement.clone(),
+                holdout: group
+                    .iter()
+                    .map(|r| r.holdout)
+                    .collect::<Option<Vec<HoldoutMetrics>>>()
+                    .map(|holdouts| HoldoutSummary {
+                        in_sample_compression_rate: MetricSummary::from_samples(
+                            &holdouts.iter().map(|h| h.in_sample_compression_rate).collect::<Vec<f64>>()
+                        ),
+                        out_of_sample_compression_rate: MetricSummary::from_samples(
+                            &holdouts.iter().map(|h| h.out_of_sample_compression_rate).collect::<Vec<f64>>()
+                        ),
+                    }),
+            }
+        })
+        .collect()
+}
+
+/// Checks whether repeated measurements have stabilized
+/// 
+/// Results are grouped by compressor and dataset; the measurements are considered
+/// stable when the coefficient of variation of every measured metric in every group
+/// is below the target.
+/// 
+/// # Arguments
+/// - `results`: Results of the iterations performed so far
+/// - `target_cv`: Maximum accepted coefficient of variation (e.g., 0.02 for 2%)
+pub fn is_converged(results: &[BenchmarkResult], target_cv: f64) -> bool {
+    summarize_benchmark_results(results).iter().all(|summary| {
+        [
+            Some(summary.compression_rate),
+            summary.compression_speed,
+            summary.decompression_speed,
+            summary.average_random_access_time,
+        ]
+        .iter()
+        .flatten()
+        .all(|metric| metric.coefficient_of_variation() <= target_cv)
+    })
+}
+
+/// Named timing of a result, compared across the iterations of a run
+type OutlierTiming = (&'static str, fn(&BenchmarkResult) -> Option<f64>);
+
+/// Flags the runs whose timings deviate from the median of their iterations
+///
+/// Interference (e.g., another process, frequency scaling) slows down single runs,
+/// which averages would otherwise absorb. Results are grouped by compressor and
+/// dataset; in groups of at least `MIN_OUTLIER_ITERATIONS` runs, a run is flagged if
+/// its compression speed, decompression speed or random access time deviates from the
+/// median of the group by more than `tolerance` (relative to the median), and the
+/// flags of the other runs are cleared. The median is the lower median, a measured
+/// value, so that at least one run of every group remains unflagged. Timed-out runs
+/// are neither flagged nor counted.
+///
+/// # Arguments
+/// - `results`: Results of the iterations, flagged in place (see `BenchmarkResult::outlier`)
+/// - `tolerance`: Maximum accepted relative deviation from the median (e.g., 0.2 for 20%)
+///
+/// # Returns
+/// Number of runs flagged
+pub fn flag_outliers(results: &mut [BenchmarkResult], tolerance: f64) -> usize {
+    let timings: [OutlierTiming; 3] = [
+        ("compression_speed", |r| r.compression_speed),
+        ("decompression_speed", |r| r.decompression_speed),
+        ("average_random_access_time", |r| r.average_random_access_time.map(|time| time as f64)),
+    ];
+
+    let mut grouped_results: BTreeMap<(String, String), Vec<usize>> = BTreeMap::new();
+    for (index, result) in results.iter().enumerate().filter(|(_, r)| r.timed_out.is_none()) {
+        grouped_results
+            .entry((result.compressor_name.clone(), result.dataset_name.clone()))
+            .or_default()
+            .push(index);
+    }
+
+    let mut n_flagged = 0;
+    for group in grouped_results.into_values() {
+        let mut deviating: Vec<Vec<String>> = vec![Vec::new(); group.len()];
+        if group.len() >= MIN_OUTLIER_ITERATIONS {
+            for (metric, timing) in timings {
+                let Some(mut samples) = group.iter().map(|&index| timing(&results[index])).collect::<Option<Vec<f64>>>() else {
+                    continue;
+                };
+                samples.sort_by(f64::total_cmp);
+                let median = samples[(samples.len() - 1) / 2];
+                if median <= 0.0 {
+                    continue;
+                }
+                for (position, &index) in group.iter().enumerate() {
+                    let deviation = (timing(&results[index]).unwrap() - median).abs() / median;
+                    if deviation > tolerance {
+                        deviating[position].push(metric.to_string());
+                    }
+                }
+            }
+        }
+        for (position, &index) in group.iter().enumerate() {
+            let metrics = std::mem::take(&mut deviating[position]);
+            if !metrics.is_empty() {
+                n_flagged += 1;
+            }
+            results[index].outlier = (!metrics.is_empty()).then_some(metrics);
+        }
+    }
+    n_flagged
+}
+
+/// Writes aggregated benchmark statistics to a JSON file
+/// 
+/// # Arguments
+/// - `summaries`: Aggregated statistics to write
+/// - `output_path`: Path to the output JSON file
+pub fn write_benchmark_summary(summaries: &[BenchmarkSummary], output_path: &Path) {
+    let json = serde_json::to_string_pretty(summaries).expect("Failed to serialize summary");
+    write_file_atomically(output_path, json.as_bytes()).expect("Failed to write summary to file");
+}
+
+/// Prints formatted benchmark results grouped by compressor
+/// 
+/// Groups results by compressor and dataset, calculates mean and 95% confidence
+/// interval for each combination, then displays results in a tabular format with
+/// overall averages per compressor.
+/// 
+/// # Arguments
+/// - `results`: Vector of benchmark results to display
+pub fn print_benchmark_results(results: &[BenchmarkResult]) {
+    // A map to store summaries grouped by compressor name
+    let mut compressor_groups: BTreeMap<String, Vec<BenchmarkSummary>> = BTreeMap::new();
+    for summary in summarize_benchmark_results(results) {
+        compressor_groups
+            .entry(summary.compressor_name.clone())
+            .or_default()
+            .push(summary);
+    }
+
+    // Print results grouped by compressor
+    for (compressor, sorted_results) in compressor_groups {
+        // Create a new table for each compressor
+        let mut table = Table::new();
+        table.add_row(row![
+            "Dataset",
+            "Iterations",
+            "Comp. Rate",
+            "Comp. Speed (MiB/s)",
+            "Decomp. Speed (MiB/s)",
+            "Avg. Random Access Time (ns)",
+            "Entropy Eff."
+        ]);
+
+        // Add rows for each summarized result, as mean ± 95% confidence interval
+        for result in &sorted_results {
+            table.add_row(row![
                 &result.dataset_name,
-                format!("{:.3}", result.compression_rate),
-                format!("{:.2}", result.compression_speed),
-                format!("{:.2}", result.decompression_speed),
-                format!("{}", result.average_random_access_time),
+                format!("{}", result.n_iterations),
+                format!("{:.3}", result.compression_rate.mean),
+                format_metric(result.compression_speed, 2),
+                format_metric(result.decompression_speed, 2),
+                format_metric(result.average_random_access_time, 0),
+                result.entropy_efficiency().map_or("-".to_string(), |efficiency| format!("{:.3}", efficiency)),
             ]);
         }
 
         // Calculate overall averages for this compressor
         let len = sorted_results.len() as f64;
         let overall_avg_compression_rate =
-            sorted_results.iter().map(|r| r.compression_rate).sum::<f64>() / len;
-        let overall_avg_compression_speed =
-            sorted_results.iter().map(|r| r.compression_speed).sum::<f64>() / len;
-        let overall_avg_decompression_speed =
-            sorted_results.iter().map(|r| r.decompression_speed).sum::<f64>() / len;
-        let overall_avg_random_access_time =
-            sorted_results.iter().map(|r| r.average_random_access_time).sum::<u128>() / sorted_results.len() as u128;
+            sorted_results.iter().map(|r| r.compression_rate.mean).sum::<f64>() / len;
+        // Averages of the metrics measured on some datasets only are over these datasets
+        let overall_average = |metric: fn(&BenchmarkSummary) -> Option<MetricSummary>, precision: usize| {
+            let means: Vec<f64> = sorted_results.iter().filter_map(metric).map(|summary| summary.mean).collect();
+            if means.is_empty() {
+                "-".to_string()
+            } else {
+                format!("{:.*}", precision, means.iter().sum::<f64>() / means.len() as f64)
+            }
+        };
+        let efficiencies: Vec<f64> = sorted_results.iter().filter_map(|r| r.entropy_efficiency()).collect();
+        let overall_avg_entropy_efficiency = (!efficiencies.is_empty())
+            .then(|| efficiencies.iter().sum::<f64>() / efficiencies.len() as f64);
 
         // Add overall averages row
         table.add_row(row![
             "AVERAGE",
+            "",
             format!("{:.3}", overall_avg_compression_rate),
-            format!("{:.2}", overall_avg_compression_speed),
-            format!("{:.2}", overall_avg_decompression_speed),
-            format!("{}", overall_avg_random_access_time),
+            overall_average(|r| r.compression_speed, 2),
+            overall_average(|r| r.decompression_speed, 2),
+            overall_average(|r| r.average_random_access_time, 0),
+            overall_avg_entropy_efficiency.map_or("-".to_string(), |efficiency| format!("{:.3}", efficiency)),
         ]);
 
         // Print the table for this compressor
         println!("\nResults for Compressor: {}", compressor);
         table.printstd();
+
+        print_space_breakdown(&sorted_results);
+        if sorted_results.iter().any(|r| r.access_throughput.is_some()) {
+            print_access_throughput(&sorted_results);
+        }
+        if sorted_results.iter().any(|r| r.first_run.is_some()) {
+            print_first_run(&sorted_results);
+        }
+        if sorted_results.iter().any(|r| r.batch_access.is_some()) {
+            print_batch_access(&sorted_results);
+        }
+        if sorted_results.iter().any(|r| r.scan.is_some()) {
+            print_scan(&sorted_results);
+        }
+        if sorted_results.iter().any(|r| r.access_stages.is_some()) {
+            print_access_stages(&sorted_results);
+        }
+        if sorted_results.iter().any(|r| r.zero_copy_access.is_some()) {
+            print_zero_copy_access(&sorted_results);
+        }
+        if sorted_results.iter().any(|r| r.plain_access.is_some()) {
+            print_plain_access(&sorted_results);
+        }
+        if sorted_results.iter().any(|r| r.hardware_counters.is_some()) {
+            print_hardware_counters(&sorted_results);
+        }
+        if sorted_results.iter().any(|r| r.holdout.is_some()) {
+            print_holdout(&sorted_results);
+        }
+    }
+
+    if results.iter().any(|r| r.timed_out.is_some()) {
+        print_timed_out(results);
+    }
+}
+
+/// Formats a summarized metric as mean ± 95% confidence interval, or "-" if it was not measured
+/// 
+/// # Arguments
+/// - `metric`: Summarized metric, if measured
+/// - `precision`: Decimal places of the mean and of the interval
+fn format_metric(metric: Option<MetricSummary>, precision: usize) -> String {
+    metric.map_or("-".to_string(), |metric| format!("{:.*} ± {:.*}", precision, metric.mean, precision, metric.ci95))
+}
+
+/// Prints the runs cancelled for exceeding the phase timeout
+/// 
+/// # Arguments
+/// - `results`: Results of individual iterations, including timed-out runs
+fn print_timed_out(results: &[BenchmarkResult]) {
+    let mut table = Table::new();
+    table.add_row(row!["Compressor", "Dataset", "Phase"]);
+    for result in results {
+        if let Some(phase) = &result.timed_out {
+            table.add_row(row![&result.compressor_name, &result.dataset_name, phase]);
+        }
+    }
+
+    println!("\nTimed out runs:");
+    table.printstd();
+}
+
+/// Prints first-run measurements next to the steady-state ones
+/// 
+/// # Arguments
+/// - `results`: Summarized benchmark results of a single compressor
+fn print_first_run(results: &[BenchmarkSummary]) {
+    let mut table = Table::new();
+    table.add_row(row![
+        "Dataset",
+        "First Decomp. Speed (MiB/s)",
+        "Steady Decomp. Speed (MiB/s)",
+        "First Random Access Time (ns)",
+        "Steady Random Access Time (ns)"
+    ]);
+
+    for result in results {
+        if let Some(first_run) = &result.first_run {
+            table.add_row(row![
+                &result.dataset_name,
+                format!("{:.2} ± {:.2}", first_run.decompression_speed.mean, first_run.decompression_speed.ci95),
+                format_metric(result.decompression_speed, 2),
+                format!("{:.0} ± {:.0}", first_run.average_random_access_time.mean, first_run.average_random_access_time.ci95),
+                format_metric(result.average_random_access_time, 0),
+            ]);
+        }
+    }
+
+    println!("First run vs steady state:");
+    table.printstd();
+}
+
+/// Prints batch access measurements next to per-query random access
+/// 
+/// # Arguments
+/// - `results`: Summarized benchmark results of a single compressor
+fn print_batch_access(results: &[BenchmarkSummary]) {
+    let mut table = Table::new();
+    table.add_row(row![
+        "Dataset",
+        "Batch Size",
+        "Random Access Time (ns)",
+        "Batch Access Time (ns)",
+        "Speedup"
+    ]);
+
+    for result in results {
+        if let Some(batch_access) = &result.batch_access {
+            table.add_row(row![
+                &result.dataset_name,
+                format!("{}", batch_access.batch_size),
+                format_metric(result.average_random_access_time, 0),
+                format!("{:.0} ± {:.0}", batch_access.average_access_time.mean, batch_access.average_access_time.ci95),
+                format!("{:.2} ± {:.2}", batch_access.speedup.mean, batch_access.speedup.ci95),
+            ]);
+        }
+    }
+
+    println!("Batch access vs per-query access:");
+    table.printstd();
+}
+
+/// Prints random access throughput, in strings and in bytes per second
+/// 
+/// # Arguments
+/// - `results`: Summarized benchmark results of a single compressor
+fn print_access_throughput(results: &[BenchmarkSummary]) {
+    let mut table = Table::new();
+    table.add_row(row![
+        "Dataset",
+        "Random Access Time (ns)",
+        "Strings per Second",
+        "Bytes Throughput (MiB/s)"
+    ]);
+
+    for result in results {
+        if let Some(access_throughput) = &result.access_throughput {
+            table.add_row(row![
+                &result.dataset_name,
+                format_metric(result.average_random_access_time, 0),
+                format!("{:.0} ± {:.0}", access_throughput.item_rate.mean, access_throughput.item_rate.ci95),
+                format!("{:.2} ± {:.2}", access_throughput.byte_throughput.mean, access_throughput.byte_throughput.ci95),
+            ]);
+        }
+    }
+
+    println!("Random access throughput:");
+    table.printstd();
+}
+
+/// Prints scan measurements next to per-query random access
+/// 
+/// # Arguments
+/// - `results`: Summarized benchmark results of a single compressor
+fn print_scan(results: &[BenchmarkSummary]) {
+    let mut table = Table::new();
+    table.add_row(row![
+        "Dataset",
+        "Scan Length",
+        "Scan Throughput (MiB/s)",
+        "Random Access Time (ns)",
+        "Scan Time per String (ns)",
+        "Speedup"
+    ]);
+
+    for result in results {
+        if let Some(scan) = &result.scan {
+            table.add_row(row![
+                &result.dataset_name,
+                format!("{}", scan.scan_length),
+                format!("{:.2} ± {:.2}", scan.throughput.mean, scan.throughput.ci95),
+                format_metric(result.average_random_access_time, 0),
+                format!("{:.1} ± {:.1}", scan.item_time.mean, scan.item_time.ci95),
+                format!("{:.2} ± {:.2}", scan.speedup.mean, scan.speedup.ci95),
+            ]);
+        }
+    }
+
+    println!("Scan vs per-query access:");
+    table.printstd();
+}
+
+/// Prints the random access latency of each stage, along with the bottleneck stage
+/// 
+/// # Arguments
+/// - `results`: Summarized benchmark results of a single compressor
+fn print_access_stages(results: &[BenchmarkSummary]) {
+    let mut table = Table::new();
+    table.add_row(row![
+        "Dataset",
+        "Lookup (ns)",
+        "Decode (ns)",
+        "Copy (ns)",
+        "Bottleneck"
+    ]);
+
+    for result in results {
+        if let Some(stages) = &result.access_stages {
+            let means = AccessStageMetrics {
+                lookup_time: stages.lookup_time.mean,
+                decode_time: stages.decode_time.mean,
+                copy_time: stages.copy_time.mean,
+            };
+            table.add_row(row![
+                &result.dataset_name,
+                format!("{:.0} ± {:.0}", stages.lookup_time.mean, stages.lookup_time.ci95),
+                format!("{:.0} ± {:.0}", stages.decode_time.mean, stages.decode_time.ci95),
+                format!("{:.0} ± {:.0}", stages.copy_time.mean, stages.copy_time.ci95),
+                means.bottleneck().name(),
+            ]);
+        }
+    }
+
+    println!("Random access latency by stage:");
+    table.printstd();
+}
+
+/// Prints zero-copy access measurements next to per-query random access
+/// 
+/// # Arguments
+/// - `results`: Summarized benchmark results of a single compressor
+fn print_zero_copy_access(results: &[BenchmarkSummary]) {
+    let mut table = Table::new();
+    table.add_row(row![
+        "Dataset",
+        "Random Access Time (ns)",
+        "Zero-Copy Access Time (ns)",
+        "Copy Overhead (ns)"
+    ]);
+
+    for result in results {
+        if let Some(zero_copy_access) = &result.zero_copy_access {
+            table.add_row(row![
+                &result.dataset_name,
+                format_metric(result.average_random_access_time, 0),
+                format!("{:.0} ± {:.0}", zero_copy_access.average_access_time.mean, zero_copy_access.average_access_time.ci95),
+                format!("{:.0} ± {:.0}", zero_copy_access.copy_overhead.mean, zero_copy_access.copy_overhead.ci95),
+            ]);
+        }
+    }
+
+    println!("Zero-copy access vs per-query access:");
+    table.printstd();
+}
+
+/// Prints plain store access measurements next to per-query random access
+/// 
+/// # Arguments
+/// - `results`: Summarized benchmark results of a single compressor
+fn print_plain_access(results: &[BenchmarkSummary]) {
+    let mut table = Table::new();
+    table.add_row(row![
+        "Dataset",
+        "Random Access Time (ns)",
+        "Vec Access Time (ns)",
+        "HashMap Access Time (ns)",
+        "Slowdown"
+    ]);
+
+    for result in results {
+        if let Some(plain_access) = &result.plain_access {
+            table.add_row(row![
+                &result.dataset_name,
+                format_metric(result.average_random_access_time, 0),
+                format!("{:.0} ± {:.0}", plain_access.vec_access_time.mean, plain_access.vec_access_time.ci95),
+                format!("{:.0} ± {:.0}", plain_access.hash_map_access_time.mean, plain_access.hash_map_access_time.ci95),
+                format!("{:.2} ± {:.2}", plain_access.slowdown.mean, plain_access.slowdown.ci95),
+            ]);
+        }
+    }
+
+    println!("Random access vs uncompressed in-memory stores:");
+    table.printstd();
+}
+
+/// Prints the hardware events of random access per query
+/// 
+/// # Arguments
+/// - `results`: Summarized benchmark results of a single compressor
+fn print_hardware_counters(results: &[BenchmarkSummary]) {
+    let mut table = Table::new();
+    table.add_row(row!["Dataset", "Instructions", "LLC Misses", "Branch Misses"]);
+
+    let format_event = |event: Option<MetricSummary>| {
+        event.map_or("-".to_string(), |event| format!("{:.1} ± {:.1}", event.mean, event.ci95))
+    };
+    for result in results {
+        if let Some(counters) = &result.hardware_counters {
+            table.add_row(row![
+                &result.dataset_name,
+                format_event(counters.instructions),
+                format_event(counters.llc_misses),
+                format_event(counters.branch_misses),
+            ]);
+        }
+    }
+
+    println!("Hardware events per random access:");
+    table.printstd();
+}
+
+/// Prints in-sample and out-of-sample compression rates side by side
+/// 
+/// # Arguments
+/// - `results`: Summarized benchmark results of a single compressor
+fn print_holdout(results: &[BenchmarkSummary]) {
+    let mut table = Table::new();
+    table.add_row(row!["Dataset", "In-Sample Rate", "Out-of-Sample Rate", "Out/In"]);
+    for result in results {
+        if let Some(holdout) = &result.holdout {
+            table.add_row(row![
+                &result.dataset_name,
+                format!("{:.3} ± {:.3}", holdout.in_sample_compression_rate.mean, holdout.in_sample_compression_rate.ci95),
+                format!("{:.3} ± {:.3}", holdout.out_of_sample_compression_rate.mean, holdout.out_of_sample_compression_rate.ci95),
+                format!("{:.3}", holdout.out_of_sample_compression_rate.mean / holdout.in_sample_compression_rate.mean),
+            ]);
+        }
+    }
+
+    println!("Holdout evaluation (rates excluding the dictionary):");
+    table.printstd();
+}
+
+/// Prints the space breakdown of averaged results as percentages of the total
+/// 
+/// Each row shows how the compressed representation of a dataset is split
+/// between payload, dictionary, item offsets, block metadata, ID mapping and checksums,
+/// along with the deduplication ratio of deduplicating wrappers.
+/// 
+/// # Arguments
+/// - `results`: Summarized benchmark results of a single compressor
+fn print_space_breakdown(results: &[BenchmarkSummary]) {
+    let mut table = Table::new();
+    table.add_row(row![
+        "Dataset",
+        "Total (bytes)",
+        "Payload (%)",
+        "Dictionary (%)",
+        "Item Offsets (%)",
+        "Block Metadata (%)",
+        "ID Map (%)",
+        "Checksums (%)",
+        "Dedup Ratio"
+    ]);
+
+    for result in results {
+        let breakdown = &result.space_breakdown;
+        let total = breakdown.total().max(1) as f64;
+        table.add_row(row![
+            &result.dataset_name,
+            format!("{}", breakdown.total()),
+            format!("{:.1}", 100.0 * breakdown.payload as f64 / total),
+            format!("{:.1}", 100.0 * breakdown.dictionary as f64 / total),
+            format!("{:.1}", 100.0 * breakdown.item_offsets as f64 / total),
+            format!("{:.1}", 100.0 * breakdown.block_metadata as f64 / total),
+            format!("{:.1}", 100.0 * breakdown.id_map as f64 / total),
+            format!("{:.1}", 100.0 * breakdown.checksums as f64 / total),
+            result.dedup_ratio.map_or("-".to_string(), |ratio| format!("{:.3}", ratio)),
+        ]);
+    }
+
+    println!("Space breakdown:");
+    table.printstd();
+}
+
+/// CPU cores and NUMA nodes of a pinned run
+///
+/// Recorded in the results so that measurements on multi-socket machines can be
+/// compared: latency and throughput depend on whether the data lives on the NUMA node
+/// of the cores accessing it.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Placement {
+    pub cores: Vec<usize>,      // CPU cores the run was pinned to
+    pub numa_nodes: Vec<usize>, // NUMA nodes of these cores (empty if the topology is unavailable)
+    pub memory_bound: bool,     // Whether allocations were bound to `numa_nodes`
+}
+
+/// Parses a list of CPU cores, such as "0-3,8"
+///
+/// Cores are returned in the order given, without duplicates.
+///
+/// # Returns
+/// - `Option<Vec<usize>>`: The cores, or None if the list is empty or malformed
+pub fn parse_core_list(list: &str) -> Option<Vec<usize>> {
+    let mut cores = Vec::new();
+    for range in list.split(',') {
+        let (first, last) = match range.split_once('-') {
+            Some((first, last)) => (first.trim().parse::<usize>().ok()?, last.trim().parse::<usize>().ok()?),
+            None => {
+                let core = range.trim().parse::<usize>().ok()?;
+                (core, core)
+            }
+        };
+        if first > last {
+            return None;
+        }
+        for core in first..=last {
+            if !cores.contains(&core) {
+                cores.push(core);
+            }
+        }
+    }
+    Some(cores)
+}
+
+/// Formats a list of CPU cores, merging consecutive cores into ranges (see `parse_core_list`)
+pub fn format_core_list(cores: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &core in cores {
+        match ranges.last_mut() {
+            Some((_, last)) if *last + 1 == core => *last = core,
+            _ => ranges.push((core, core)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|&(first, last)| if first == last { first.to_string() } else { format!("{}-{}", first, last) })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Pins the calling thread to a set of cores, optionally binding its memory to their NUMA nodes
+///
+/// Failures are logged and leave the thread unpinned (or its memory unbound).
+///
+/// # Arguments
+/// - `cores`: CPU cores to bind to
+/// - `bind_memory`: Whether to restrict later allocations to the NUMA nodes of the cores
+///
+/// # Returns
+/// - `Option<Placement>`: The placement applied, or None if the thread could not be pinned
+pub fn apply_placement(cores: &[usize], bind_memory: bool) -> Option<Placement> {
+    if !try_set_affinity(cores) {
+        warn!("Failed to set CPU affinity to cores {}. Continuing without core pinning.", format_core_list(cores));
+        return None;
     }
+    let numa_nodes = numa_nodes_of(cores);
+    let memory_bound = bind_memory && {
+        let bound = !numa_nodes.is_empty() && try_bind_memory(&numa_nodes);
+        if !bound {
+            warn!("Failed to bind memory to the NUMA nodes of cores {}. Continuing with the default allocation policy.", format_core_list(cores));
+        }
+        bound
+    };
+    info!(cores = %format_core_list(cores), numa_nodes = ?numa_nodes, memory_bound, "Placement applied");
+    Some(Placement { cores: cores.to_vec(), numa_nodes, memory_bound })
 }
 
 /// Attempts to set CPU affinity for reproducible measurements
 /// 
-/// Tries to bind the current process to a specific CPU core to reduce
-/// measurement variance. Only supported on Linux systems.
+/// Tries to bind the calling thread to a set of CPU cores to reduce
+/// measurement variance. Threads spawned afterwards inherit the binding.
+/// Only supported on Linux systems.
 /// 
 /// # Arguments
-/// - `core_id`: The CPU core ID to bind to
+/// - `cores`: The CPU core IDs to bind to
 /// 
 /// # Returns
 /// - `bool`: True if CPU affinity was successfully set, false otherwise
 #[cfg(target_os = "linux")]
-pub fn try_set_affinity(core_id: usize) -> bool {
+pub fn try_set_affinity(cores: &[usize]) -> bool {
+    if cores.is_empty() || cores.iter().any(|&core| core >= libc::CPU_SETSIZE as usize) {
+        return false;
+    }
     unsafe {
         let mut cpuset: cpu_set_t = std::mem::zeroed();
         CPU_ZERO(&mut cpuset);
-        CPU_SET(core_id, &mut cpuset);
+        for &core in cores {
+            CPU_SET(core, &mut cpuset);
+        }
         
         libc::sched_setaffinity(0, std::mem::size_of::<cpu_set_t>(), &cpuset) == 0
     }
 }
 
 #[cfg(not(target_os = "linux"))]
-pub fn try_set_affinity(_core_id: usize) -> bool {
+pub fn try_set_affinity(_cores: &[usize]) -> bool {
     // CPU affinity is not supported on this platform
     false
 }
+
+/// Returns the NUMA nodes of a set of cores, in increasing order
+///
+/// Reads the topology from sysfs; returns no node if it is unavailable.
+#[cfg(target_os = "linux")]
+pub fn numa_nodes_of(cores: &[usize]) -> Vec<usize> {
+    let Ok(entries) = fs::read_dir("/sys/devices/system/node") else {
+        return Vec::new();
+    };
+    let mut nodes: Vec<usize> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let node = entry.file_name().to_str()?.strip_prefix("node")?.parse::<usize>().ok()?;
+            // Memory-only nodes have an empty list of cores
+            let node_cores = parse_core_list(fs::read_to_string(entry.path().join("cpulist")).ok()?.trim())?;
+            node_cores.iter().any(|core| cores.contains(core)).then_some(node)
+        })
+        .collect();
+    nodes.sort_unstable();
+    nodes
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn numa_nodes_of(_cores: &[usize]) -> Vec<usize> {
+    // NUMA topology is not available on this platform
+    Vec::new()
+}
+
+/// Binds the memory allocations of the calling thread to a set of NUMA nodes
+///
+/// Sets the `MPOL_BIND` policy with `set_mempolicy`, without depending on libnuma.
+/// Only pages allocated afterwards are affected, so the binding must precede loading
+/// the dataset.
+///
+/// # Returns
+/// - `bool`: True if the memory policy was successfully set, false otherwise
+#[cfg(target_os = "linux")]
+pub fn try_bind_memory(nodes: &[usize]) -> bool {
+    const MPOL_BIND: libc::c_int = 2;
+    const WORD_BITS: usize = libc::c_ulong::BITS as usize;
+    let Some(&max_node) = nodes.iter().max() else {
+        return false;
+    };
+    let mut node_mask: Vec<libc::c_ulong> = vec![0; max_node / WORD_BITS + 1];
+    for &node in nodes {
+        node_mask[node / WORD_BITS] |= 1 << (node % WORD_BITS);
+    }
+    // The kernel reads one bit less than the given maximum
+    let max_nodes = (node_mask.len() * WORD_BITS + 1) as libc::c_ulong;
+    unsafe { libc::syscall(libc::SYS_set_mempolicy, MPOL_BIND, node_mask.as_ptr(), max_nodes) == 0 }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn try_bind_memory(_nodes: &[usize]) -> bool {
+    // Memory policies are not supported on this platform
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_datasets_keep_arbitrary_bytes() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut data = Vec::new();
+        let mut end_positions = vec![0];
+        for length in [0, 1, 255, 0, 4096, 17] {
+            data.extend((0..length).map(|_| rng.gen::<u8>()));
+            end_positions.push(data.len());
+        }
+        let path = std::env::temp_dir().join(format!("binary_dataset_{}.bin", std::process::id()));
+        write_binary_dataset(&path, &data, &end_positions);
+        let loaded = load_dataset(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, (data, end_positions));
+    }
+}
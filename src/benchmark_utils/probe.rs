@@ -0,0 +1,136 @@
+//! Measurement probes
+//!
+//! A probe measures a span of work between `start` and `stop`: its wall-clock time,
+//! the allocations it performs, or the hardware events it causes. The phases of the
+//! benchmark engine (see `engine`) compose the probes they need, so that every binary
+//! measures the same way and reports in the same units: times in nanoseconds, and
+//! throughputs in MiB/s (see `throughput`).
+
+use super::clock::{Clock, TimerSource};
+use crate::alloc_counter::{self, AllocationScope};
+use crate::perf::{HardwareCounts, PerfCounters};
+
+/// Measurement of a span of work
+pub trait Probe {
+    /// Measured quantity of a span
+    type Reading;
+
+    /// Starts measuring a span
+    fn start(&mut self);
+
+    /// Stops measuring, returning the measurement of the span since `start`
+    fn stop(&mut self) -> Self::Reading;
+}
+
+/// Wall-clock time of a span, in nanoseconds, read from a calibrated clock (see `clock`)
+pub struct Timing {
+    clock: &'static Clock,
+    start: u64,     // Reading of the clock at the start of the span
+}
+
+impl Timing {
+    /// Creates a timing probe reading `Instant`
+    pub fn new() -> Self {
+        Self::with_source(TimerSource::Instant)
+    }
+
+    /// Creates a timing probe reading the clock of a source
+    ///
+    /// Panics if the source is unavailable on this machine: binaries check it when
+    /// parsing their options (see `Clock::of`).
+    pub fn with_source(source: TimerSource) -> Self {
+        let clock = Clock::of(source).unwrap_or_else(|message| panic!("{}", message));
+        Timing { clock, start: clock.now() }
+    }
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Probe for Timing {
+    type Reading = u128;
+
+    #[inline(always)]
+    fn start(&mut self) {
+        self.start = self.clock.now();
+    }
+
+    #[inline(always)]
+    fn stop(&mut self) -> u128 {
+        self.clock.elapsed_ns(self.start)
+    }
+}
+
+/// Allocations of the calling thread during a span
+///
+/// Readings are `None` if the counting allocator is not installed (see `alloc_counter`).
+pub struct Memory {
+    installed: bool,                    // Whether the counting allocator is the global allocator
+    scope: Option<AllocationScope>,     // Scope of the current span
+}
+
+impl Memory {
+    /// Creates a memory probe
+    pub fn new() -> Self {
+        Memory { installed: alloc_counter::is_installed(), scope: None }
+    }
+
+    /// Returns whether allocations are observed
+    pub fn is_available(&self) -> bool {
+        self.installed
+    }
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Probe for Memory {
+    type Reading = Option<usize>;
+
+    fn start(&mut self) {
+        self.scope = self.installed.then(AllocationScope::start);
+    }
+
+    fn stop(&mut self) -> Option<usize> {
+        self.scope.take().map(|scope| scope.count())
+    }
+}
+
+/// Hardware events of the calling thread during a span (see `perf`)
+pub struct HardwareEvents {
+    counters: PerfCounters,
+}
+
+impl HardwareEvents {
+    /// Opens the hardware counters
+    ///
+    /// # Returns
+    /// The probe, or `None` if no event can be counted on this machine
+    pub fn open() -> Option<Self> {
+        PerfCounters::open().map(|counters| HardwareEvents { counters })
+    }
+}
+
+impl Probe for HardwareEvents {
+    type Reading = HardwareCounts;
+
+    fn start(&mut self) {
+        self.counters.enable();
+    }
+
+    fn stop(&mut self) -> HardwareCounts {
+        self.counters.disable();
+        self.counters.read()
+    }
+}
+
+/// Returns the throughput of processing `bytes` bytes in `time` nanoseconds, in MiB/s
+pub fn throughput(bytes: usize, time: u128) -> f64 {
+    (bytes as f64 / (1024.0 * 1024.0)) / (time.max(1) as f64 / 1e9)
+}
@@ -0,0 +1,382 @@
+//! Asynchronous random access benchmark over disk-resident compressed data
+//!
+//! This binary writes the compressed payload of a dataset to a temporary file and
+//! retrieves random strings from it through io_uring, keeping a fixed number of reads
+//! in flight (the queue depth). Each completed read is decompressed and its string
+//! extracted while the other reads are pending, so that decompression overlaps storage
+//! latency; the benchmark reports throughput and latency percentiles per queue depth.
+//!
+//! Supported algorithms keep their payload addressable by file offset:
+//! - `raw`: each string is read directly
+//! - `lz4_blocks`, `zstd_blocks`, `zstd_dict_blocks`, `tiered`: the block holding each
+//!   string is read and decompressed (no block cache is kept across queries)
+//!
+//! Reads bypass the page cache with `O_DIRECT` where the file system supports it. The
+//! file location follows `std::env::temp_dir` (i.e., the `TMPDIR` environment variable),
+//! which should point to the storage device under test. Linux only.
+
+use compression_benchmark_rs::benchmark_utils::*;
+#[cfg(feature = "tiered")]
+use compression_benchmark_rs::compressor::{BlockCompressor, Compressor, BUFFER_PADDING};
+#[cfg(feature = "tiered")]
+use compression_benchmark_rs::compressor::tiered::{BlockCodec, TieredCompressor};
+use compression_benchmark_rs::logging;
+use io_uring::{opcode, types, IoUring};
+use prettytable::{row, Table};
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::ops::Range;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tracing::{error, info, warn};
+
+/// Queue depths evaluated by default
+const DEFAULT_QUEUE_DEPTHS: [usize; 7] = [1, 2, 4, 8, 16, 32, 64];
+/// Largest supported queue depth
+const MAX_QUEUE_DEPTH: usize = 4096;
+/// Default number of random access queries per queue depth
+const DEFAULT_N_QUERIES: usize = 100000;
+/// Alignment required by `O_DIRECT` reads (offset, length and buffer address)
+const DIRECT_IO_ALIGNMENT: usize = 4096;
+
+/// Measurements at one queue depth
+#[derive(Serialize)]
+struct QueueDepthResult {
+    compressor_name: String,
+    dataset_name: String,
+    queue_depth: usize,
+    n_queries: usize,
+    throughput: f64,        // Queries per second
+    mean_latency: f64,      // Microseconds, from submission to extracted string
+    p50_latency: f64,       // Microseconds
+    p99_latency: f64,       // Microseconds
+    p999_latency: f64,      // Microseconds
+}
+
+/// Compressed representation whose payload is read from disk
+enum DiskStore {
+    Raw,                                // Strings stored as-is
+    #[cfg(feature = "tiered")]
+    Blocks(Box<TieredCompressor>),      // Independently compressed blocks
+}
+
+impl DiskStore {
+    /// Compresses the dataset with the given algorithm, if supported
+    #[cfg_attr(not(feature = "tiered"), allow(unused_variables))]
+    fn create(name: &str, data: &[u8], end_positions: &[usize]) -> Option<Self> {
+        #[cfg(feature = "tiered")]
+        let n_items = end_positions.len() - 1;
+        #[cfg(feature = "tiered")]
+        let compressor = match name {
+            "lz4_blocks" => Some(TieredCompressor::with_codec(data.len(), n_items, BlockCodec::Lz4)),
+            "zstd_blocks" => Some(TieredCompressor::with_codec(data.len(), n_items, BlockCodec::Zstd)),
+            "zstd_dict_blocks" => Some(TieredCompressor::with_shared_dictionary(data.len(), n_items)),
+            "tiered" => Some(TieredCompressor::new(data.len(), n_items)),
+            _ => None,
+        };
+        #[cfg(feature = "tiered")]
+        if let Some(mut compressor) = compressor {
+            Compressor::compress(&mut compressor, data, end_positions);
+            return Some(DiskStore::Blocks(Box::new(compressor)));
+        }
+
+        (name == "raw").then_some(DiskStore::Raw)
+    }
+
+    /// Returns the name of the algorithm
+    fn name(&self) -> &str {
+        match self {
+            DiskStore::Raw => "Raw",
+            #[cfg(feature = "tiered")]
+            DiskStore::Blocks(compressor) => Compressor::name(compressor.as_ref()),
+        }
+    }
+
+    /// Returns the bytes written to disk
+    fn payload<'a>(&'a self, data: &'a [u8]) -> &'a [u8] {
+        match self {
+            DiskStore::Raw => data,
+            #[cfg(feature = "tiered")]
+            DiskStore::Blocks(compressor) => compressor.get_compressed_data(),
+        }
+    }
+
+    /// Returns the range of the payload to read to retrieve a string
+    fn read_range(&self, index: usize, end_positions: &[usize]) -> Range<usize> {
+        match self {
+            DiskStore::Raw => end_positions[index]..end_positions[index + 1],
+            #[cfg(feature = "tiered")]
+            DiskStore::Blocks(compressor) => {
+                compressor.get_compressed_block_range(compressor.get_block_index(index))
+            }
+        }
+    }
+
+    /// Returns the largest range read to retrieve a string, and the largest decompressed unit
+    fn max_unit_sizes(&self, end_positions: &[usize]) -> (usize, usize) {
+        match self {
+            DiskStore::Raw => {
+                let max_item_size = end_positions.windows(2).map(|w| w[1] - w[0]).max().unwrap_or(0);
+                (max_item_size, 0)
+            }
+            #[cfg(feature = "tiered")]
+            DiskStore::Blocks(compressor) => {
+                let max_compressed_size = compressor
+                    .get_blocks_metadata()
+                    .iter()
+                    .map(|block| block.end_position - block.start_position)
+                    .max()
+                    .unwrap_or(0);
+                (max_compressed_size, compressor.get_max_block_size())
+            }
+        }
+    }
+
+    /// Extracts a string from the bytes read for it
+    ///
+    /// # Arguments
+    /// - `index`: Index of the string
+    /// - `unit`: Payload bytes of `read_range(index)`
+    /// - `scratch`: Buffer receiving the decompressed block, sized by `max_unit_sizes`
+    #[cfg_attr(not(feature = "tiered"), allow(unused_variables))]
+    fn extract<'a>(&self, index: usize, unit: &'a [u8], scratch: &'a mut [u8]) -> &'a [u8] {
+        match self {
+            DiskStore::Raw => unit,
+            #[cfg(feature = "tiered")]
+            DiskStore::Blocks(compressor) => {
+                let block_index = compressor.get_block_index(index);
+                let block = &compressor.get_blocks_metadata()[block_index];
+                let uncompressed_size = block.uncompressed_size as usize;
+                compressor.decompress_block_with_codec(unit, uncompressed_size, block.codec, scratch);
+                let (start, end) = compressor.get_item_delimiters(block_index, index);
+                &scratch[start..end]
+            }
+        }
+    }
+}
+
+/// Temporary file holding the payload, removed when dropped
+struct PayloadFile {
+    path: PathBuf,
+    file: File,
+}
+
+impl PayloadFile {
+    /// Writes the payload, padded to the direct I/O alignment, and opens it for direct reads
+    fn create(payload: &[u8]) -> Self {
+        let path = std::env::temp_dir().join(format!("async_io_{}.bin", std::process::id()));
+        let mut content = payload.to_vec();
+        content.resize(payload.len().next_multiple_of(DIRECT_IO_ALIGNMENT), 0);
+        fs::write(&path, &content).expect("Failed to write payload file");
+
+        let file = match OpenOptions::new().read(true).custom_flags(libc::O_DIRECT).open(&path) {
+            Ok(file) => file,
+            Err(_) => {
+                warn!(path = %path.display(), "O_DIRECT not supported. Falling back to buffered reads.");
+                File::open(&path).expect("Failed to open payload file")
+            }
+        };
+        PayloadFile { path, file }
+    }
+}
+
+impl Drop for PayloadFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Read in flight, with its aligned buffer
+struct Slot {
+    buffer: Vec<u8>,            // Over-allocated for alignment
+    base: usize,                // Start of the aligned region of `buffer`
+    index: usize,               // String being retrieved
+    unit: Range<usize>,         // Payload range of the string, relative to the aligned region
+    submitted: Instant,
+}
+
+/// Runs the queries with `queue_depth` reads in flight
+///
+/// # Returns
+/// Elapsed time in seconds and the latency of each query in microseconds
+fn run_queue_depth(
+    store: &DiskStore,
+    file: &PayloadFile,
+    data: &[u8],
+    end_positions: &[usize],
+    queries: &[usize],
+    queue_depth: usize,
+) -> (f64, Vec<f64>) {
+    let mut ring = IoUring::new(queue_depth as u32).unwrap_or_else(|e| {
+        error!("Failed to create io_uring instance: {}", e);
+        std::process::exit(1);
+    });
+    let (max_read_size, max_unit_size) = store.max_unit_sizes(end_positions);
+    let mut slots: Vec<Slot> = (0..queue_depth)
+        .map(|_| {
+            // A range spans at most two partially covered aligned blocks, plus alignment slack
+            let buffer = vec![0; max_read_size + 3 * DIRECT_IO_ALIGNMENT];
+            let base = buffer.as_ptr().align_offset(DIRECT_IO_ALIGNMENT);
+            Slot { buffer, base, index: 0, unit: 0..0, submitted: Instant::now() }
+        })
+        .collect();
+    let mut scratch = vec![0; max_unit_size + BUFFER_PADDING];
+    let mut latencies = Vec::with_capacity(queries.len());
+    let fd = types::Fd(file.file.as_raw_fd());
+
+    let start = Instant::now();
+    let mut next_query = 0;
+    let mut free_slots: Vec<usize> = (0..queue_depth).rev().collect();
+    while latencies.len() < queries.len() {
+        while next_query < queries.len() {
+            let Some(slot_id) = free_slots.pop() else { break };
+            let slot = &mut slots[slot_id];
+            let index = queries[next_query];
+            let range = store.read_range(index, end_positions);
+            let aligned_start = range.start / DIRECT_IO_ALIGNMENT * DIRECT_IO_ALIGNMENT;
+            let aligned_end = range.end.next_multiple_of(DIRECT_IO_ALIGNMENT).max(aligned_start + DIRECT_IO_ALIGNMENT);
+            slot.index = index;
+            slot.unit = range.start - aligned_start..range.end - aligned_start;
+            slot.submitted = Instant::now();
+
+            let entry = opcode::Read::new(fd, slot.buffer[slot.base..].as_mut_ptr(), (aligned_end - aligned_start) as u32)
+                .offset(aligned_start as u64)
+                .build()
+                .user_data(slot_id as u64);
+            // SAFETY: the buffer is owned by the slot, which is not reused before the read completes
+            unsafe { ring.submission().push(&entry).expect("Submission queue is full") };
+            next_query += 1;
+        }
+
+        ring.submit_and_wait(1).expect("Failed to submit reads");
+        let completed: Vec<(usize, i32)> = ring.completion().map(|cqe| (cqe.user_data() as usize, cqe.result())).collect();
+        for (slot_id, result) in completed {
+            let slot = &slots[slot_id];
+            assert!(result >= 0, "Read failed: {}", std::io::Error::from_raw_os_error(-result));
+            assert!(result as usize >= slot.unit.end, "Short read of string {}", slot.index);
+
+            let unit = &slot.buffer[slot.base + slot.unit.start..slot.base + slot.unit.end];
+            let item = store.extract(slot.index, unit, &mut scratch);
+            latencies.push(slot.submitted.elapsed().as_secs_f64() * 1e6);
+
+            let expected = &data[end_positions[slot.index]..end_positions[slot.index + 1]];
+            if item != expected {
+                panic!("Retrieved string {} does not match the original for compressor: {}", slot.index, store.name());
+            }
+            free_slots.push(slot_id);
+        }
+    }
+    (start.elapsed().as_secs_f64(), latencies)
+}
+
+/// Returns the value at the given quantile of sorted samples
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    sorted[((sorted.len() - 1) as f64 * q).round() as usize]
+}
+
+/// Parses a comma-separated list of queue depths
+fn parse_queue_depths(value: &str) -> Option<Vec<usize>> {
+    value
+        .split(',')
+        .map(|depth| depth.trim().parse::<usize>().ok().filter(|depth| (1..=MAX_QUEUE_DEPTH).contains(depth)))
+        .collect()
+}
+
+/// Asynchronous I/O benchmark entry point
+fn main() {
+    let mut args: Vec<String> = std::env::args().collect();
+    logging::init_from_args(&mut args).unwrap_or_else(|message| {
+        eprintln!("Error: {}", message);
+        std::process::exit(1);
+    });
+    let queue_depths = take_option(&mut args, "--queue-depths").map_or(DEFAULT_QUEUE_DEPTHS.to_vec(), |value| {
+        parse_queue_depths(&value).unwrap_or_else(|| {
+            error!("Invalid queue depths '{}'. Must be a comma-separated list of numbers from 1 to {}.", value, MAX_QUEUE_DEPTH);
+            std::process::exit(1);
+        })
+    });
+    let n_queries = take_option(&mut args, "--queries").map_or(DEFAULT_N_QUERIES, |value| {
+        value.parse::<usize>().ok().filter(|&n| n > 0).unwrap_or_else(|| {
+            error!("Invalid number of queries '{}'. Must be a positive number.", value);
+            std::process::exit(1);
+        })
+    });
+
+    if args.len() < 3 {
+        eprintln!("Usage: {} <dataset_path> <compressor_name> [output_file] [--queue-depths=D] [--queries=N] [--log-level=L] [--log-format=F]", args[0]);
+        eprintln!("  <compressor_name> - raw | lz4_blocks | zstd_blocks | zstd_dict_blocks | tiered");
+        eprintln!("  --queue-depths=D  - Comma-separated reads in flight (default: 1,2,4,8,16,32,64)");
+        eprintln!("  --queries=N       - Random access queries per queue depth (default: {})", DEFAULT_N_QUERIES);
+        eprintln!("  --log-level=L     - trace | debug | info | warn | error (default: info)");
+        eprintln!("  --log-format=F    - text | json (default: text)");
+        std::process::exit(1);
+    }
+
+    let dataset_path = Path::new(&args[1]);
+    let dataset_name = dataset_path.file_stem().unwrap().to_string_lossy().to_string();
+    let (data, end_positions) = load_dataset(dataset_path);
+    let n_items = end_positions.len() - 1;
+    if n_items == 0 {
+        error!(dataset = %dataset_path.display(), "Dataset has no strings");
+        std::process::exit(1);
+    }
+
+    let store = DiskStore::create(&args[2], &data, &end_positions).unwrap_or_else(|| {
+        error!("Compressor '{}' is not supported by the asynchronous I/O benchmark", args[2]);
+        std::process::exit(1);
+    });
+    let file = PayloadFile::create(store.payload(&data));
+    info!(
+        compressor = store.name(),
+        dataset = %dataset_name,
+        payload_size = store.payload(&data).len(),
+        path = %file.path.display(),
+        "Payload written to disk"
+    );
+
+    let mut results = Vec::with_capacity(queue_depths.len());
+    for &queue_depth in &queue_depths {
+        let queries = generate_random_queries(n_items, n_queries);
+        let (elapsed, mut latencies) = run_queue_depth(&store, &file, &data, &end_positions, &queries, queue_depth);
+        latencies.sort_unstable_by(f64::total_cmp);
+        let result = QueueDepthResult {
+            compressor_name: store.name().to_string(),
+            dataset_name: dataset_name.clone(),
+            queue_depth,
+            n_queries,
+            throughput: n_queries as f64 / elapsed,
+            mean_latency: latencies.iter().sum::<f64>() / latencies.len() as f64,
+            p50_latency: quantile(&latencies, 0.5),
+            p99_latency: quantile(&latencies, 0.99),
+            p999_latency: quantile(&latencies, 0.999),
+        };
+        info!(queue_depth, throughput = result.throughput, p99_latency = result.p99_latency, "Queue depth evaluated");
+        results.push(result);
+    }
+
+    let mut table = Table::new();
+    table.add_row(row!["Queue Depth", "Queries/s", "Mean (µs)", "P50 (µs)", "P99 (µs)", "P99.9 (µs)"]);
+    for result in &results {
+        table.add_row(row![
+            result.queue_depth,
+            format!("{:.0}", result.throughput),
+            format!("{:.1}", result.mean_latency),
+            format!("{:.1}", result.p50_latency),
+            format!("{:.1}", result.p99_latency),
+            format!("{:.1}", result.p999_latency)
+        ]);
+    }
+    println!("{} on {}", store.name(), dataset_name);
+    table.printstd();
+
+    if let Some(output_file) = args.get(3) {
+        let json = serde_json::to_string_pretty(&results).expect("Failed to serialize results");
+        fs::write(output_file, json).expect("Failed to write results");
+        info!(path = %output_file, "Results written");
+    }
+}
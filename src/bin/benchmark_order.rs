@@ -0,0 +1,169 @@
+//! Input order sensitivity benchmark
+//!
+//! This binary compresses the same dataset in several orders of its strings (original,
+//! sorted, shuffled and clustered, see `dataset_order`) with each listed compressor, and
+//! reports how compression rate, throughput and random access latency change with the
+//! order. Every run follows the protocol of `benchmark_individual`, once per compressor
+//! and order; random access queries are drawn once from the seed, and mapped to the
+//! positions of the queried strings in each order, so that every order serves the same
+//! strings. Results are annotated with their order (`input_order`), and a comparison
+//! table reports each metric relative to the original order.
+
+use compression_benchmark_rs::benchmark_utils::*;
+use compression_benchmark_rs::compressor::registry::{self, AnyCompressor};
+use compression_benchmark_rs::dataset_order::{self, InputOrder};
+use compression_benchmark_rs::dataset_stats::DatasetStats;
+use compression_benchmark_rs::logging;
+use prettytable::{row, Table};
+use std::path::Path;
+use tracing::{error, info, info_span};
+
+/// Default seed of the queries and of the random orders
+const DEFAULT_SEED: u64 = 42;
+
+/// Order sensitivity entry point
+fn main() {
+    let mut args: Vec<String> = std::env::args().collect();
+    logging::init_from_args(&mut args).unwrap_or_else(|message| {
+        eprintln!("Error: {}", message);
+        std::process::exit(1);
+    });
+    let orders = take_option(&mut args, "--orders").map_or(InputOrder::ALL.to_vec(), |value| {
+        value.split(',').map(InputOrder::parse).collect::<Option<Vec<InputOrder>>>().unwrap_or_else(|| {
+            error!("Invalid orders '{}'. Must be a list of original, sorted, shuffled or clustered.", value);
+            std::process::exit(1);
+        })
+    });
+    let seed = take_option(&mut args, "--seed").map_or(DEFAULT_SEED, |value| {
+        value.parse::<u64>().unwrap_or_else(|_| {
+            error!("Invalid seed '{}'. Must be a valid number.", value);
+            std::process::exit(1);
+        })
+    });
+    let query_policy = take_option(&mut args, "--queries").map_or(QueryPolicy::default(), |value| {
+        QueryPolicy::parse(&value).unwrap_or_else(|| {
+            error!("Invalid query policy '{}'. Must be N, coverage:F or time:S.", value);
+            std::process::exit(1);
+        })
+    });
+
+    if args.len() < 3 {
+        eprintln!("Usage: {} <dataset_path> <compressor_names> [output_file] [--orders=O] [--seed=N] [--queries=P] [--log-level=L] [--log-format=F]", args[0]);
+        eprintln!("  <compressor_names> - Comma-separated compressors, e.g. bpe,onpair_bv,zstd_blocks");
+        eprintln!("  --orders=O         - Comma-separated orders among original, sorted, shuffled, clustered (default: all)");
+        eprintln!("  --seed=N           - Seed of the queries and of the shuffled and clustered orders (default: {})", DEFAULT_SEED);
+        eprintln!("  --queries=P        - N queries, coverage:F (F queries per item) or time:S (S seconds) (default: {})", DEFAULT_N_QUERIES);
+        eprintln!("  --log-level=L      - trace | debug | info | warn | error (default: info)");
+        eprintln!("  --log-format=F     - text | json (default: text)");
+        std::process::exit(1);
+    }
+
+    let dataset_path = Path::new(&args[1]);
+    let compressor_names: Vec<&str> = args[2].split(',').collect();
+    let output_file = args.get(3).map(Path::new);
+
+    if !dataset_path.is_file() {
+        error!("Dataset path '{}' is not a file.", dataset_path.display());
+        std::process::exit(1);
+    }
+    if is_record_dataset(dataset_path) {
+        error!("Record datasets are not supported: their records cannot be reordered as strings.");
+        std::process::exit(1);
+    }
+    for &compressor_name in compressor_names.iter() {
+        if !registry::lookup(compressor_name).is_some_and(|entry| entry.available) {
+            error!("{}.", registry::unavailable_message(compressor_name));
+            std::process::exit(1);
+        }
+    }
+
+    // Queries address strings in the original order
+    let dataset_name = dataset_path.file_name().unwrap().to_str().unwrap().to_string();
+    let (data, end_positions) = load_dataset(dataset_path);
+    let n_elements = end_positions.len() - 1;
+    let queries = generate_seeded_queries(query_policy, n_elements, QueryDistribution::Uniform, false, seed);
+    let policy_description = query_policy.describe(false);
+
+    let mut results: Vec<BenchmarkResult> = Vec::new();
+    for &order in orders.iter() {
+        let _order_span = info_span!("order", order = order.name()).entered();
+        let permutation = dataset_order::permutation(&data, &end_positions, order, seed);
+        let (ordered_data, ordered_end_positions) = dataset_order::permute(&data, &end_positions, &permutation);
+        let ordered_queries = dataset_order::permute_queries(&queries, &permutation);
+        let options = BenchmarkOptions {
+            time_budget: query_policy.time_budget(),
+            dataset_stats: Some(DatasetStats::compute(&ordered_data, &ordered_end_positions)),
+            ..BenchmarkOptions::default()
+        };
+
+        for &compressor_name in compressor_names.iter() {
+            let Some(mut compressor) = AnyCompressor::create(compressor_name, ordered_data.len(), n_elements) else {
+                error!("{}.", registry::unavailable_message(compressor_name));
+                std::process::exit(1);
+            };
+            if let Err(message) = compressor.capacity().check(&ordered_end_positions) {
+                error!("Compressor '{}' does not support this dataset: {}.", compressor_name, message);
+                std::process::exit(1);
+            }
+
+            let conditions = RunConditions::start();
+            let mut result = compressor.benchmark_with_options(dataset_name.clone(), &ordered_data, &ordered_end_positions, &ordered_queries, &options);
+            result.conditions = Some(conditions.finish());
+            result.query_policy = Some(policy_description.clone());
+            result.input_order = Some(order.name().to_string());
+            info!(compressor = compressor_name, compression_rate = result.compression_rate, "Order evaluated");
+            results.push(result);
+        }
+    }
+
+    print_order_comparison(&results);
+
+    if let Some(output_file) = output_file {
+        write_benchmark_results(&results, output_file);
+    }
+}
+
+/// Prints the metrics of each compressor in every order, relative to the original order
+///
+/// Ratios compare each order to the original one (above 1: higher in this order), and
+/// are omitted if the original order was not evaluated.
+fn print_order_comparison(results: &[BenchmarkResult]) {
+    let mut compressor_names: Vec<&str> = results.iter().map(|result| result.compressor_name.as_str()).collect();
+    compressor_names.sort_unstable();
+    compressor_names.dedup();
+
+    for compressor_name in compressor_names {
+        let runs: Vec<&BenchmarkResult> = results.iter().filter(|result| result.compressor_name == compressor_name).collect();
+        let original = runs.iter().find(|result| result.input_order.as_deref() == Some(InputOrder::Original.name()));
+        let relative = |value: Option<f64>, baseline: Option<f64>| match (value, baseline) {
+            (Some(value), Some(baseline)) if baseline > 0.0 => format!("{:.3}", value / baseline),
+            _ => "-".to_string(),
+        };
+
+        let mut table = Table::new();
+        table.add_row(row![
+            "Order",
+            "Comp. Rate",
+            "vs. Original",
+            "Comp. Speed (MiB/s)",
+            "Decomp. Speed (MiB/s)",
+            "Avg. Random Access Time (ns)",
+            "vs. Original"
+        ]);
+        for run in runs.iter() {
+            let access_time = run.average_random_access_time.map(|time| time as f64);
+            table.add_row(row![
+                run.input_order.as_deref().unwrap_or("-"),
+                format!("{:.3}", run.compression_rate),
+                relative(Some(run.compression_rate), original.map(|original| original.compression_rate)),
+                run.compression_speed.map_or("-".to_string(), |speed| format!("{:.2}", speed)),
+                run.decompression_speed.map_or("-".to_string(), |speed| format!("{:.2}", speed)),
+                run.average_random_access_time.map_or("-".to_string(), |time| format!("{}", time)),
+                relative(access_time, original.and_then(|original| original.average_random_access_time).map(|time| time as f64)),
+            ]);
+        }
+
+        println!("\nCompressor: {}", compressor_name);
+        table.printstd();
+    }
+}
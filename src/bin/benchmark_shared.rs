@@ -0,0 +1,277 @@
+//! Shared-dictionary benchmark over dataset shards
+//!
+//! This binary models distributed stores deploying one dictionary fleet-wide: every
+//! dataset of a directory is a shard, a single dictionary is trained over random
+//! samples of all shards, and each shard is then compressed independently with it.
+//! Each shard is measured with the protocol of `benchmark_individual` (compression,
+//! decompression and random access), charging it an equal share of the dictionary.
+//! The dictionary size, its amortized cost per shard and the training cost are
+//! reported in `BenchmarkResult::shared_dictionary`, and the training and parse times
+//! of each shard in `BenchmarkResult::compression_split`.
+//!
+//! Dictionary-based compressors supporting shared dictionaries are `bpe` and `onpair_bv`
+//! (see `DictionaryCompressor`). Record datasets are skipped.
+
+use compression_benchmark_rs::benchmark_utils::*;
+use compression_benchmark_rs::compressor::bpe::BPECompressor;
+#[cfg(feature = "onpair")]
+use compression_benchmark_rs::compressor::onpair_bv::OnPairBVCompressor;
+use compression_benchmark_rs::compressor::DictionaryCompressor;
+use compression_benchmark_rs::dataset_stats;
+use compression_benchmark_rs::logging;
+use rand::seq::index;
+use rand::thread_rng;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{error, info, info_span, warn};
+
+/// Default fraction of the strings of each shard sampled for training
+const DEFAULT_SAMPLE_FRACTION: f64 = 0.1;
+
+/// Dataset shard, with its strings and boundary positions
+struct Shard {
+    name: String,
+    data: Vec<u8>,
+    end_positions: Vec<usize>,
+}
+
+/// Shared-dictionary benchmark entry point
+fn main() {
+    let mut args: Vec<String> = std::env::args().collect();
+    logging::init_from_args(&mut args).unwrap_or_else(|message| {
+        eprintln!("Error: {}", message);
+        std::process::exit(1);
+    });
+    let sample_fraction = take_option(&mut args, "--sample").map_or(DEFAULT_SAMPLE_FRACTION, |value| {
+        value.parse::<f64>().ok().filter(|fraction| *fraction > 0.0 && *fraction <= 1.0).unwrap_or_else(|| {
+            error!("Invalid sample fraction '{}'. Must be a number in (0, 1].", value);
+            std::process::exit(1);
+        })
+    });
+    let warmup_iterations = take_option(&mut args, "--warmup").map_or(0, |value| {
+        value.parse::<usize>().unwrap_or_else(|_| {
+            error!("Invalid warm-up iterations '{}'. Must be a valid number.", value);
+            std::process::exit(1);
+        })
+    });
+    let query_policy = take_option(&mut args, "--queries").map_or(QueryPolicy::default(), |value| {
+        QueryPolicy::parse(&value).unwrap_or_else(|| {
+            error!("Invalid query policy '{}'. Must be N, coverage:F or time:S.", value);
+            std::process::exit(1);
+        })
+    });
+    let cover_all_items = take_flag(&mut args, "--cover-all");
+    let numa_bind = take_flag(&mut args, "--numa-bind");
+
+    if args.len() < 4 {
+        eprintln!("Usage: {} <dataset_directory> <compressor_name> <output_file> [cores] [--sample=F] [--warmup=N] [--queries=P] [--cover-all] [--numa-bind] [--log-level=L] [--log-format=F]", args[0]);
+        eprintln!("  <compressor_name> - bpe{}", if cfg!(feature = "onpair") { " | onpair_bv" } else { "" });
+        eprintln!("  [cores]           - Optional CPU cores for pinning, e.g. 2 or 0-3,8");
+        eprintln!("  --sample=F        - Fraction of the strings of each shard sampled for training (default: {})", DEFAULT_SAMPLE_FRACTION);
+        eprintln!("  --warmup=N        - Unmeasured runs of decompression and random access (default: 0)");
+        eprintln!("  --queries=P       - N queries, coverage:F (F queries per item) or time:S (S seconds) (default: {})", DEFAULT_N_QUERIES);
+        eprintln!("  --cover-all       - Query every item at least once");
+        eprintln!("  --numa-bind       - Bind memory to the NUMA nodes of the pinned cores (Linux only)");
+        eprintln!("  --log-level=L     - trace | debug | info | warn | error (default: info)");
+        eprintln!("  --log-format=F    - text | json (default: text)");
+        std::process::exit(1);
+    }
+
+    let directory = Path::new(&args[1]);
+    let compressor_name = &args[2];
+    let output_file = Path::new(&args[3]);
+    let cores = if args.len() > 4 {
+        Some(parse_core_list(&args[4]).unwrap_or_else(|| {
+            error!("Invalid cores '{}'. Must be a core ID or a list such as 0-3,8.", args[4]);
+            std::process::exit(1);
+        }))
+    } else {
+        None
+    };
+
+    if !directory.is_dir() {
+        error!("{} is not a valid directory.", directory.display());
+        std::process::exit(1);
+    }
+
+    if numa_bind && cores.is_none() {
+        warn!("--numa-bind requires pinned cores, ignoring it");
+    }
+    let placement = cores.and_then(|cores| apply_placement(&cores, numa_bind));
+
+    // Every plain JSON or binary dataset of the directory, compressed or not, is a shard (cached statistics excluded)
+    let mut paths: Vec<PathBuf> = fs::read_dir(directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_file() && is_dataset_file(path))
+        .filter(|path| !dataset_stats::is_sidecar(path))
+        .collect();
+    paths.sort();
+
+    let mut shards: Vec<Shard> = Vec::new();
+    for path in paths {
+        if is_record_dataset(&path) {
+            warn!(dataset = %path.display(), "Skipping record dataset");
+            continue;
+        }
+        let (data, end_positions) = load_dataset(&path);
+        let name = path.file_name().unwrap().to_str().unwrap().to_string();
+        shards.push(Shard { name, data, end_positions });
+    }
+    if shards.is_empty() {
+        error!("No dataset found in {}.", directory.display());
+        std::process::exit(1);
+    }
+
+    let options = |n_items: usize| BenchmarkOptions {
+        warmup_iterations,
+        time_budget: query_policy.time_budget(),
+        min_queries: if cover_all_items { n_items } else { 0 },
+        streaming: false,
+        batch_size: None,
+        scan_length: None,
+        profile_stages: false,
+        phase_timeout: None,
+        zero_copy: false,
+        perf_counters: false,
+        count_allocations: false,
+        plain_baselines: false,
+        latency_heatmap: false,
+        dataset_stats: None,
+        phases: Phases::default(),
+        validation: None,
+        verification: Verification::default(),
+        timer: TimerSource::default(),
+    };
+    let workload = |shard: &Shard| {
+        let n_items = shard.end_positions.len() - 1;
+        (generate_queries(query_policy, n_items, QueryDistribution::Uniform, cover_all_items), options(n_items))
+    };
+
+    let mut results = match compressor_name.as_str() {
+        "bpe" => benchmark_shards::<BPECompressor>(&shards, sample_fraction, workload),
+        #[cfg(feature = "onpair")]
+        "onpair_bv" => benchmark_shards::<OnPairBVCompressor>(&shards, sample_fraction, workload),
+        _ => {
+            error!("Compressor '{}' does not support shared dictionaries.", compressor_name);
+            std::process::exit(1);
+        }
+    };
+
+    let policy_description = query_policy.describe(cover_all_items);
+    for result in results.iter_mut() {
+        result.query_policy = Some(policy_description.clone());
+        result.placement = placement.clone();
+    }
+
+    print_benchmark_results(&results);
+    write_benchmark_results(&results, output_file);
+}
+
+/// Trains a dictionary over samples of all shards, then benchmarks each shard with it
+///
+/// # Arguments
+/// - `shards`: Datasets sharing the dictionary
+/// - `sample_fraction`: Fraction of the strings of each shard sampled for training
+/// - `workload`: Random access queries and benchmark options of a shard
+///
+/// # Returns
+/// One result per shard, charged an equal share of the dictionary
+fn benchmark_shards<T: DictionaryCompressor>(
+    shards: &[Shard],
+    sample_fraction: f64,
+    workload: impl Fn(&Shard) -> (Vec<usize>, BenchmarkOptions),
+) -> Vec<BenchmarkResult> {
+    let (sample_data, sample_end_positions) = sample_shards(shards, sample_fraction);
+    let n_samples = sample_end_positions.len() - 1;
+
+    let train_span = info_span!("train", n_shards = shards.len(), training_size = sample_data.len()).entered();
+    let trainer = T::new(sample_data.len(), n_samples);
+    let mut timing = Timing::new();
+    timing.start();
+    let dictionary = trainer.train_dictionary(&sample_data, &sample_end_positions);
+    let training_time = timing.stop() as f64 / 1e9;
+    info!(training_time, "Dictionary trained");
+    train_span.exit();
+
+    let mut results = Vec::with_capacity(shards.len());
+    for shard in shards {
+        let (queries, options) = workload(shard);
+        let mut compressor = T::new(shard.data.len(), shard.end_positions.len() - 1);
+        let _span = info_span!("benchmark", compressor = compressor.name(), dataset = %shard.name).entered();
+        let conditions = RunConditions::start();
+
+        let compress_span = info_span!("compress").entered();
+        let compression_time = measure_compression(&options, || {
+            compressor.compress_with_dictionary(&shard.data, &shard.end_positions, &dictionary)
+        });
+        compress_span.exit();
+        let Some(compression_time) = compression_time else {
+            let mut result = timed_out_result(shard.name.clone(), compressor.name(), &options, "compress");
+            result.conditions = Some(conditions.finish());
+            results.push(result);
+            continue;
+        };
+
+        let mut result = benchmark_compressed(
+            &mut compressor, shard.name.clone(), &shard.data, &shard.end_positions, &queries, &options, compression_time
+        );
+        result.conditions = Some(conditions.finish());
+
+        // Each shard holds a copy of the whole dictionary, but is charged its share only
+        let dictionary_size = result.space_breakdown.dictionary;
+        let amortized_dictionary_size = dictionary_size / shards.len();
+        let space_used = compressor.space_used_bytes() - dictionary_size + amortized_dictionary_size;
+        result.compressor_name = format!("{} (shared dictionary)", result.compressor_name);
+        result.compression_rate = shard.data.len() as f64 / space_used as f64;
+        result.space_breakdown.dictionary = amortized_dictionary_size;
+        result.compression_split = Some(CompressionSplit {
+            data_size: shard.data.len(),
+            training_time,
+            parse_time: compression_time as f64 / 1e9,
+        });
+        result.shared_dictionary = Some(SharedDictionaryMetrics {
+            n_shards: shards.len(),
+            dictionary_size,
+            amortized_dictionary_size,
+            training_size: sample_data.len(),
+            training_time,
+        });
+        info!(compression_rate = result.compression_rate, dictionary_size, amortized_dictionary_size, "Shard compressed with shared dictionary");
+        results.push(result);
+    }
+    results
+}
+
+/// Concatenates a random sample of the strings of every shard
+///
+/// At least one string is sampled from each non-empty shard, and sampled strings
+/// keep their order within the shard.
+///
+/// # Arguments
+/// - `shards`: Datasets to sample
+/// - `fraction`: Fraction of the strings of each shard to sample
+///
+/// # Returns
+/// Sampled strings and their boundary positions
+fn sample_shards(shards: &[Shard], fraction: f64) -> (Vec<u8>, Vec<usize>) {
+    let mut rng = thread_rng();
+    let mut data: Vec<u8> = Vec::new();
+    let mut end_positions: Vec<usize> = vec![0];
+
+    for shard in shards {
+        let n_items = shard.end_positions.len() - 1;
+        if n_items == 0 {
+            continue;
+        }
+        let n_samples = ((n_items as f64 * fraction).round() as usize).clamp(1, n_items);
+        let mut sampled = index::sample(&mut rng, n_items, n_samples).into_vec();
+        sampled.sort_unstable();
+
+        for item in sampled {
+            data.extend_from_slice(&shard.data[shard.end_positions[item]..shard.end_positions[item + 1]]);
+            end_positions.push(data.len());
+        }
+    }
+    (data, end_positions)
+}
@@ -0,0 +1,170 @@
+//! Experiment bundle driver
+//!
+//! Creates and runs self-contained experiment bundles (see `bundle`), so that a
+//! benchmark can be shared and run again exactly:
+//! - `create` draws the random access queries of a dataset from a seed (`--seed=N`,
+//!   random by default), writes them to a new bundle with a reference to the dataset,
+//!   the algorithm and the options of `benchmark_individual`, then runs the bundle once
+//! - `run` checks that the dataset matches the reference of the bundle, then runs
+//!   `benchmark_individual` on it with the options and queries of the bundle, `--runs=N`
+//!   times; `--dataset=PATH` uses a copy of the dataset at another path
+//!
+//! Results of every run are appended to the results file of the bundle. Queries are
+//! drawn with the query options of `benchmark_individual` (`--queries`, `--cover-all`,
+//! `--zipf` and `--length-weighted`), which then only set the time budget of the runs.
+
+use compression_benchmark_rs::benchmark_utils::*;
+use compression_benchmark_rs::bundle::{DatasetReference, ExperimentBundle};
+use compression_benchmark_rs::logging;
+use rand::{thread_rng, Rng};
+use std::path::Path;
+use std::process::Command;
+use tracing::{error, info};
+
+/// Path to individual benchmark executable
+const BENCHMARK_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/target/release/benchmark_individual");
+
+/// Bundle driver entry point
+fn main() {
+    let mut args: Vec<String> = std::env::args().collect();
+    logging::init_from_args(&mut args).unwrap_or_else(|message| {
+        eprintln!("Error: {}", message);
+        std::process::exit(1);
+    });
+
+    match args.get(1).map(String::as_str) {
+        Some("create") if args.iter().filter(|arg| !arg.starts_with("--")).count() == 5 => create(args),
+        Some("run") if args.len() >= 3 => run(args),
+        _ => {
+            eprintln!("Usage: {} create <bundle_dir> <dataset_path> <compressor_name> [--seed=N] [options]", args[0]);
+            eprintln!("       {} run <bundle_dir> [cores] [--dataset=PATH] [--runs=N]", args[0]);
+            eprintln!("  [options]         - Options of benchmark_individual, e.g. --warmup=3 --queries=coverage:1");
+            eprintln!("  [cores]           - Optional CPU cores for pinning, e.g. 2 or 0-3,8");
+            eprintln!("  --seed=N          - Seed of the queries (default: random, recorded in the bundle)");
+            eprintln!("  --dataset=PATH    - Copy of the dataset of the bundle at another path");
+            eprintln!("  --runs=N          - Runs of the bundle (default: 1)");
+            eprintln!("  --log-level=L     - trace | debug | info | warn | error (default: info)");
+            eprintln!("  --log-format=F    - text | json (default: text)");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Creates a bundle and runs it once (`create` command)
+fn create(mut args: Vec<String>) {
+    let seed = take_option(&mut args, "--seed").map_or_else(|| thread_rng().gen(), |value| {
+        value.parse::<u64>().unwrap_or_else(|_| {
+            error!("Invalid seed '{}'. Must be a non-negative integer.", value);
+            std::process::exit(1);
+        })
+    });
+    let (options, args): (Vec<String>, Vec<String>) = args.into_iter().partition(|arg| arg.starts_with("--"));
+    if let Some(option) = options.iter().find(|option| option.starts_with("--query-file=")) {
+        error!("Unsupported option '{}': bundles draw their own queries", option);
+        std::process::exit(1);
+    }
+    let bundle_dir = Path::new(&args[2]);
+    let dataset_path = Path::new(&args[3]);
+    let compressor_name = &args[4];
+
+    // Queries are drawn with the query options of the runs
+    let mut workload = options.clone();
+    let query_policy = take_option(&mut workload, "--queries").map_or(QueryPolicy::default(), |value| {
+        QueryPolicy::parse(&value).unwrap_or_else(|| {
+            error!("Invalid query policy '{}'. Must be N, coverage:F or time:S.", value);
+            std::process::exit(1);
+        })
+    });
+    let cover_all_items = take_flag(&mut workload, "--cover-all");
+    let zipf_exponent = take_option(&mut workload, "--zipf").map(|value| {
+        value.parse::<f64>().ok().filter(|exponent| *exponent >= 0.0).unwrap_or_else(|| {
+            error!("Invalid Zipf exponent '{}'. Must be a non-negative number.", value);
+            std::process::exit(1);
+        })
+    });
+
+    let length_weighted = take_flag(&mut workload, "--length-weighted");
+
+    let dataset = DatasetReference::of(dataset_path);
+    // Record datasets are queried by record, with uniform queries only
+    let is_record_dataset = is_record_dataset(dataset_path);
+    let end_positions = if length_weighted && !is_record_dataset { load_dataset(dataset_path).1 } else { Vec::new() };
+    let distribution = match zipf_exponent {
+        _ if is_record_dataset => QueryDistribution::Uniform,
+        Some(exponent) => QueryDistribution::Zipf(exponent),
+        None if length_weighted => QueryDistribution::LengthWeighted(&end_positions),
+        None => QueryDistribution::Uniform,
+    };
+    let queries = generate_seeded_queries(query_policy, dataset.n_items, distribution, cover_all_items, seed);
+    let bundle = ExperimentBundle::create(bundle_dir, dataset, compressor_name, options, seed, &queries).unwrap_or_else(|message| {
+        error!("{}", message);
+        std::process::exit(1);
+    });
+    info!(bundle = %bundle_dir.display(), seed, n_queries = queries.len(), "Bundle created");
+
+    if !run_bundle(&bundle, dataset_path, None) {
+        std::process::exit(1);
+    }
+}
+
+/// Runs an existing bundle (`run` command)
+fn run(mut args: Vec<String>) {
+    let dataset_override = take_option(&mut args, "--dataset");
+    let runs = take_option(&mut args, "--runs").map_or(1, |value| {
+        value.parse::<usize>().ok().filter(|runs| *runs > 0).unwrap_or_else(|| {
+            error!("Invalid number of runs '{}'. Must be a positive number.", value);
+            std::process::exit(1);
+        })
+    });
+    let cores = args.get(3).map(|cores| {
+        parse_core_list(cores).unwrap_or_else(|| {
+            error!("Invalid cores '{}'. Must be a core ID or a list such as 0-3,8.", cores);
+            std::process::exit(1);
+        })
+    });
+
+    let bundle = ExperimentBundle::open(Path::new(&args[2])).unwrap_or_else(|message| {
+        error!("{}", message);
+        std::process::exit(1);
+    });
+    let dataset_path = dataset_override.unwrap_or_else(|| bundle.manifest.dataset.path.clone());
+    let dataset_path = Path::new(&dataset_path);
+    if let Err(message) = bundle.manifest.dataset.check(dataset_path) {
+        error!("{}", message);
+        std::process::exit(1);
+    }
+
+    for run in 1..=runs {
+        info!(run, runs, "Running bundle");
+        if !run_bundle(&bundle, dataset_path, cores.as_deref()) {
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Runs `benchmark_individual` with the options and queries of a bundle
+///
+/// # Arguments
+/// - `bundle`: Bundle to run
+/// - `dataset_path`: Path to the dataset, matching the reference of the bundle
+/// - `cores`: CPU cores for pinning, if any
+///
+/// # Returns
+/// Whether the benchmark succeeded
+fn run_bundle(bundle: &ExperimentBundle, dataset_path: &Path, cores: Option<&[usize]>) -> bool {
+    let mut cmd = Command::new(BENCHMARK_PATH);
+    cmd.arg(dataset_path)
+       .arg(&bundle.manifest.compressor)
+       .arg(bundle.results_path());
+    if let Some(cores) = cores {
+        cmd.arg(format_core_list(cores));
+    }
+    cmd.args(&bundle.manifest.options)
+       .arg(format!("--query-file={}", bundle.queries_path().display()));
+
+    let status = cmd.status().expect("Failed to execute benchmark");
+    if !status.success() {
+        error!(compressor = bundle.manifest.compressor.as_str(), "Benchmark failed");
+    }
+    status.success()
+}
@@ -0,0 +1,557 @@
+//! Unified command-line entry point of the benchmark suite
+//!
+//! `cbench <command> [arguments]` groups the tools of the suite under subcommands
+//! sharing the registry of algorithms (see `registry`), the dataset loaders and the
+//! logging options (`--log-level=L`, `--log-format=F`):
+//! - `bench`: benchmarks every algorithm on every dataset of a directory (see
+//!   `cli::benchmark_all`, accepting the same arguments), each iteration in a `cbench
+//!   bench-one` process
+//! - `bench-one`: benchmarks an algorithm on a dataset (see `cli::benchmark_individual`,
+//!   accepting the same arguments)
+//! - `estimate`: estimates the compression rate of algorithms by compressing a
+//!   stratified sample of the strings (see `sampling`), without random access runs
+//! - `compress`: compresses a dataset into a store file (see `CompressedStringStore::save`)
+//! - `simulate`: predicts the random access latency of algorithms on a dataset profile
+//!   with a model calibrated on a sample of a dataset (see `cost_model`), and compares
+//!   the predictions with measured results
+//! - `dictionary`: exports the trained dictionary of an algorithm with the frequencies
+//!   and adjacencies of its tokens in the compressed dataset (see `DictionaryReport`)
+//! - `analyze`: reports the properties of a dataset relevant to compression (string
+//!   lengths, duplicates, empirical entropy, smallest length code)
+//! - `verify`: checks that an algorithm, or a saved store, returns every string of a
+//!   dataset unchanged
+//! - `amortize`: reports the compression speed of shards sharing a trained dictionary,
+//!   from results with separate training and parse times (see `CompressionSplit`)
+//!
+//! Command lines are parsed with clap, which prints the arguments of a command with
+//! `cbench help <command>` and exits with status 2 on invalid command lines. Otherwise,
+//! every command exits with `EXIT_SUCCESS` on success, `EXIT_FAILURE` on errors and
+//! `EXIT_MISMATCH` when a verification fails; the benchmark drivers exit as their binaries.
+
+use clap::{Args, Parser, Subcommand};
+use compression_benchmark_rs::alloc_counter::CountingAllocator;
+use compression_benchmark_rs::benchmark_utils::*;
+use compression_benchmark_rs::cli::{benchmark_all, benchmark_individual};
+use compression_benchmark_rs::compressor::registry::{self, AnyCompressor};
+use compression_benchmark_rs::compressor::dictionary_report::pairs_csv_path;
+use compression_benchmark_rs::compressor::sampling;
+use compression_benchmark_rs::cost_model::{self, AccessStructure, DatasetProfile};
+use compression_benchmark_rs::dataset_stats::DatasetStats;
+use compression_benchmark_rs::logging::{self, LogFormat, DEFAULT_LOG_LEVEL};
+use compression_benchmark_rs::store::{CompressedStringStore, StoreConfig};
+use prettytable::{row, Cell, Row, Table};
+use rustc_hash::FxHashSet;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+use tracing::{error, info, warn, Level};
+
+/// Counts allocations of the random access phase of `bench-one` (`--count-allocations`)
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Exit status of successful commands
+const EXIT_SUCCESS: i32 = 0;
+/// Exit status of commands failing on an error (unreadable dataset, unavailable algorithm)
+const EXIT_FAILURE: i32 = 1;
+/// Exit status of verifications finding a string that differs from the dataset
+const EXIT_MISMATCH: i32 = 3;
+/// Fraction of the strings compressed by `estimate` by default
+const DEFAULT_ESTIMATE_SAMPLE: f64 = 0.01;
+/// Strings compressed by `estimate` at least, unless the dataset is smaller
+const MIN_ESTIMATE_ITEMS: usize = 10000;
+/// Fraction of the strings compressed by `simulate` to calibrate the models by default
+const DEFAULT_CALIBRATION_SAMPLE: f64 = 0.1;
+/// Random access queries of the calibration of each model
+const CALIBRATION_QUERIES: usize = 100000;
+
+/// Shard counts reported by `amortize`, besides the one given with --shards
+const AMORTIZED_SHARDS: [usize; 3] = [1, 10, 100];
+
+/// Benchmark suite for string compression with random access
+#[derive(Parser)]
+#[command(name = "cbench")]
+struct Cli {
+    #[command(subcommand)]
+    command: Commands,
+    /// Maximum level of the emitted events
+    #[arg(long, global = true, value_parser = ["trace", "debug", "info", "warn", "error"])]
+    log_level: Option<String>,
+    /// Format of the log records on stderr
+    #[arg(long, global = true, value_parser = ["text", "json"])]
+    log_format: Option<String>,
+}
+
+/// Subcommands, with their arguments
+#[derive(Subcommand)]
+enum Commands {
+    /// Benchmark every algorithm on every dataset of a directory (as benchmark_all)
+    Bench(DriverArgs),
+    /// Benchmark an algorithm on a dataset (as benchmark_individual)
+    BenchOne(DriverArgs),
+    /// Estimate compression rates on a sample of the strings
+    Estimate {
+        /// Dataset file, JSON or binary, possibly compressed
+        dataset_path: PathBuf,
+        /// Algorithms to estimate (default: all available)
+        #[arg(value_delimiter = ',')]
+        compressors: Vec<String>,
+        /// Fraction of the strings compressed, at least 10000 strings
+        #[arg(long, default_value_t = DEFAULT_ESTIMATE_SAMPLE, value_parser = parse_fraction)]
+        sample: f64,
+    },
+    /// Compress a dataset into a store file
+    Compress {
+        /// Dataset file, JSON or binary, possibly compressed
+        dataset_path: PathBuf,
+        compressor: String,
+        output_file: PathBuf,
+        /// Sort and deduplicate the strings (order-preserving dictionary)
+        #[arg(long)]
+        sorted: bool,
+    },
+    /// Predict random access latency with a calibrated cost model
+    Simulate {
+        /// Dataset file, JSON or binary, possibly compressed
+        dataset_path: PathBuf,
+        /// Algorithms to model (default: all available)
+        #[arg(value_delimiter = ',')]
+        compressors: Vec<String>,
+        /// Fraction of the strings compressed for calibration, at least 10000 strings
+        #[arg(long, default_value_t = DEFAULT_CALIBRATION_SAMPLE, value_parser = parse_fraction)]
+        sample: f64,
+        /// Hypothetical dataset, e.g. n_items=1000000,mean_length=24 (default: the dataset)
+        #[arg(long, value_parser = parse_profile)]
+        profile: Option<DatasetProfile>,
+        /// Measured results of the dataset to compare the predictions with
+        #[arg(long)]
+        results: Option<PathBuf>,
+    },
+    /// Export the trained dictionary and token statistics to JSON or CSV
+    Dictionary {
+        /// Dataset file, JSON or binary, possibly compressed
+        dataset_path: PathBuf,
+        /// Algorithm with a trainable dictionary (bpe or an onpair_bv variant)
+        compressor: String,
+        /// JSON file, or CSV file of the tokens with the adjacencies in <name>.pairs.csv
+        output_file: PathBuf,
+    },
+    /// Report string lengths, duplicates and entropy of a dataset
+    Analyze {
+        /// Dataset file, JSON or binary, possibly compressed
+        dataset_path: PathBuf,
+    },
+    /// Check that every string is returned unchanged
+    Verify {
+        /// Dataset file, JSON or binary, possibly compressed
+        dataset_path: PathBuf,
+        /// Algorithm to compress the dataset with, or a store saved by 'compress'
+        target: String,
+    },
+    /// Report compression speeds amortizing dictionary training over shards
+    Amortize {
+        /// Results of benchmark_shared, or of benchmark_individual with --split-compression
+        results_file: PathBuf,
+        /// Also report the speed over N shards (always reported: 1, 10 and 100)
+        #[arg(long)]
+        shards: Option<NonZeroUsize>,
+    },
+}
+
+/// Arguments of a benchmark driver, parsed by the driver itself
+#[derive(Args)]
+struct DriverArgs {
+    /// Arguments and options of the driver (run without arguments to list them)
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true, value_name = "ARGS")]
+    args: Vec<String>,
+}
+
+/// Unified entry point
+fn main() {
+    let cli = Cli::parse();
+    let status = match cli.command {
+        Commands::Bench(driver) => {
+            benchmark_all::run(driver_args("bench", driver, &cli.log_level, &cli.log_format), individual_benchmark);
+            EXIT_SUCCESS
+        }
+        Commands::BenchOne(driver) => {
+            benchmark_individual::run(driver_args("bench-one", driver, &cli.log_level, &cli.log_format));
+            EXIT_SUCCESS
+        }
+        command => {
+            let level = cli.log_level.map_or(DEFAULT_LOG_LEVEL, |level| level.parse::<Level>().unwrap());
+            let format = cli.log_format.map_or(LogFormat::Text, |format| LogFormat::parse(&format).unwrap());
+            logging::init(level, format);
+            match command {
+                Commands::Estimate { dataset_path, compressors, sample } => estimate(&dataset_path, &compressors, sample),
+                Commands::Compress { dataset_path, compressor, output_file, sorted } => compress(&dataset_path, compressor, &output_file, sorted),
+                Commands::Simulate { dataset_path, compressors, sample, profile, results } => simulate(&dataset_path, &compressors, sample, profile, results.as_deref()),
+                Commands::Dictionary { dataset_path, compressor, output_file } => dictionary(&dataset_path, &compressor, &output_file),
+                Commands::Analyze { dataset_path } => analyze(&dataset_path),
+                Commands::Verify { dataset_path, target } => verify(&dataset_path, target),
+                Commands::Amortize { results_file, shards } => amortize(&results_file, shards.map(NonZeroUsize::get)),
+                Commands::Bench(_) | Commands::BenchOne(_) => unreachable!(),
+            }
+        }
+    };
+    std::process::exit(status);
+}
+
+/// Returns the command-line arguments of a benchmark driver, with the logging options
+/// given before the subcommand
+fn driver_args(command: &str, driver: DriverArgs, log_level: &Option<String>, log_format: &Option<String>) -> Vec<String> {
+    let mut args = vec![format!("cbench {}", command)];
+    args.extend(driver.args);
+    args.extend(log_level.iter().map(|level| format!("--log-level={}", level)));
+    args.extend(log_format.iter().map(|format| format!("--log-format={}", format)));
+    args
+}
+
+/// Returns the command running an individual benchmark of `bench`: `cbench bench-one`
+fn individual_benchmark() -> Command {
+    let mut command = Command::new(std::env::current_exe().expect("Failed to locate the cbench executable"));
+    command.arg("bench-one");
+    command
+}
+
+/// Parses a sample fraction in (0, 1]
+fn parse_fraction(value: &str) -> Result<f64, String> {
+    value.parse::<f64>().ok().filter(|fraction| *fraction > 0.0 && *fraction <= 1.0).ok_or_else(|| "must be a number in (0, 1]".to_string())
+}
+
+/// Parses a dataset profile (see `DatasetProfile::parse`)
+fn parse_profile(value: &str) -> Result<DatasetProfile, String> {
+    DatasetProfile::parse(value).ok_or_else(|| "must be n_items=N,mean_length=L".to_string())
+}
+
+/// Returns the algorithms of a command, all available ones if none is given
+fn compressors_or_all(compressors: &[String]) -> Vec<&str> {
+    if compressors.is_empty() {
+        registry::available_compressors()
+    } else {
+        compressors.iter().map(String::as_str).collect()
+    }
+}
+
+/// Loads a string dataset, rejecting multi-column datasets
+///
+/// # Returns
+/// Dataset name, concatenated strings and their boundary positions, or the exit status
+fn load_string_dataset(dataset_path: &Path) -> Result<(String, Vec<u8>, Vec<usize>), i32> {
+    if !dataset_path.is_file() {
+        error!("Dataset path '{}' is not a file.", dataset_path.display());
+        return Err(EXIT_FAILURE);
+    }
+    if is_record_dataset(dataset_path) {
+        error!("Multi-column datasets are supported by 'bench' and 'bench-one' only.");
+        return Err(EXIT_FAILURE);
+    }
+    let dataset_name = dataset_path.file_name().unwrap().to_str().unwrap().to_string();
+    let (data, end_positions) = load_dataset(dataset_path);
+    Ok((dataset_name, data, end_positions))
+}
+
+/// Draws a stratified sample of the strings, of at least `MIN_ESTIMATE_ITEMS` strings
+///
+/// Strings are sampled evenly across the dataset, and kept in dataset order.
+///
+/// # Returns
+/// Concatenated sampled strings and their boundary positions
+fn draw_sample(data: &[u8], end_positions: &[usize], sample_rate: f64) -> (Vec<u8>, Vec<usize>) {
+    let n_items = end_positions.len() - 1;
+    let n_sampled = ((n_items as f64 * sample_rate).ceil() as usize).max(MIN_ESTIMATE_ITEMS).min(n_items);
+    let mut sampled = sampling::sample_stratified_strings(n_items, n_sampled);
+    sampled.sort_unstable();
+    let mut sample_data = Vec::new();
+    let mut sample_end_positions = vec![0];
+    for &index in sampled.iter() {
+        sample_data.extend_from_slice(&data[end_positions[index]..end_positions[index + 1]]);
+        sample_end_positions.push(sample_data.len());
+    }
+    (sample_data, sample_end_positions)
+}
+
+/// Estimates compression rates on a stratified sample of the strings
+fn estimate(dataset_path: &Path, compressors: &[String], sample_rate: f64) -> i32 {
+    let (dataset_name, data, end_positions) = match load_string_dataset(dataset_path) {
+        Ok(dataset) => dataset,
+        Err(status) => return status,
+    };
+
+    let (sample_data, sample_end_positions) = draw_sample(&data, &end_positions, sample_rate);
+    let n_sampled = sample_end_positions.len() - 1;
+    info!(dataset = dataset_name, n_sampled, sample_size = sample_data.len(), "Sample drawn");
+
+    let mut table = Table::new();
+    table.add_row(row!["Compressor", "Compression Rate", "Compression Speed (MiB/s)"]);
+    for name in compressors_or_all(compressors) {
+        let Some(mut compressor) = AnyCompressor::create(name, sample_data.len(), n_sampled) else {
+            warn!(compressor = name, reason = %registry::unavailable_message(name), "Skipping compressor");
+            continue;
+        };
+        if let Err(message) = compressor.capacity().check(&sample_end_positions) {
+            warn!(compressor = name, reason = %message, "Skipping compressor");
+            continue;
+        }
+        let start = Instant::now();
+        compressor.compress(&sample_data, &sample_end_positions);
+        let compression_speed = sample_data.len() as f64 / (1024.0 * 1024.0) / start.elapsed().as_secs_f64();
+        let compression_rate = sample_data.len() as f64 / compressor.space_used_bytes().max(1) as f64;
+        table.add_row(row![compressor.name(), format!("{:.3}", compression_rate), format!("{:.2}", compression_speed)]);
+    }
+    table.printstd();
+    EXIT_SUCCESS
+}
+
+/// Predicts random access latencies with cost models calibrated on a sample of the strings
+fn simulate(dataset_path: &Path, compressors: &[String], sample_rate: f64, profile: Option<DatasetProfile>, results_path: Option<&Path>) -> i32 {
+    let (dataset_name, data, end_positions) = match load_string_dataset(dataset_path) {
+        Ok(dataset) => dataset,
+        Err(status) => return status,
+    };
+    // Measurements describe the dataset itself: they validate predictions on its profile only
+    let results = match (results_path, profile) {
+        (Some(path), None) => match load_benchmark_results(path) {
+            Ok(results) => results,
+            Err(message) => {
+                error!("{}", message);
+                return EXIT_FAILURE;
+            }
+        },
+        (Some(_), Some(_)) => {
+            warn!("--results validates predictions on the profile of the dataset only, ignoring it");
+            Vec::new()
+        }
+        _ => Vec::new(),
+    };
+    let profile = profile.unwrap_or_else(|| DatasetProfile::of(&end_positions));
+
+    let (sample_data, sample_end_positions) = draw_sample(&data, &end_positions, sample_rate);
+    let n_sampled = sample_end_positions.len() - 1;
+    let queries = generate_random_queries(n_sampled, CALIBRATION_QUERIES);
+    info!(dataset = dataset_name, n_sampled, sample_size = sample_data.len(), n_items = profile.n_items, mean_length = profile.mean_length, "Calibration sample drawn");
+
+    let mut table = Table::new();
+    table.add_row(row!["Compressor", "Structure", "Fixed Time (ns)", "Time per Unit (ns)", "Units", "Predicted Time (ns)", "Measured Time (ns)", "Error (%)"]);
+    for name in compressors_or_all(compressors) {
+        let Some(mut compressor) = AnyCompressor::create(name, sample_data.len(), n_sampled) else {
+            warn!(compressor = name, reason = %registry::unavailable_message(name), "Skipping compressor");
+            continue;
+        };
+        if let Err(message) = compressor.capacity().check(&sample_end_positions) {
+            warn!(compressor = name, reason = %message, "Skipping compressor");
+            continue;
+        }
+        compressor.compress(&sample_data, &sample_end_positions);
+        let model = compressor.calibrate_cost_model(AccessStructure::of(name), &sample_data, &sample_end_positions, &queries);
+        let predicted = model.predict(&profile);
+
+        let measured: Vec<f64> = results
+            .iter()
+            .filter(|r| r.timed_out.is_none() && r.dataset_name == dataset_name && r.compressor_name == compressor.name())
+            .filter_map(|r| r.average_random_access_time.map(|time| time as f64))
+            .collect();
+        let measured = (!measured.is_empty()).then(|| measured.iter().sum::<f64>() / measured.len() as f64);
+        info!(compressor = name, structure = model.structure.name(), fixed_time = model.fixed_time, unit_time = model.unit_time, predicted, measured, "Cost model calibrated");
+        table.add_row(row![
+            compressor.name(),
+            model.structure.name(),
+            format!("{:.1}", model.fixed_time),
+            format!("{:.4}", model.unit_time),
+            format!("{:.1}", model.units(&profile)),
+            format!("{:.0}", predicted),
+            measured.map_or("-".to_string(), |measured| format!("{:.0}", measured)),
+            measured.map_or("-".to_string(), |measured| format!("{:+.1}", 100.0 * cost_model::relative_error(predicted, measured))),
+        ]);
+    }
+    table.printstd();
+    EXIT_SUCCESS
+}
+
+/// Compresses a dataset into a store file
+fn compress(dataset_path: &Path, algorithm: String, output_path: &Path, sorted: bool) -> i32 {
+    let (dataset_name, data, end_positions) = match load_string_dataset(dataset_path) {
+        Ok(dataset) => dataset,
+        Err(status) => return status,
+    };
+
+    let config = StoreConfig { algorithm, sorted };
+    let start = Instant::now();
+    let store = match CompressedStringStore::build(end_positions.windows(2).map(|w| &data[w[0]..w[1]]), &config) {
+        Ok(store) => store,
+        Err(message) => {
+            error!("{}", message);
+            return EXIT_FAILURE;
+        }
+    };
+    let compression_time = start.elapsed().as_secs_f64();
+    if let Err(e) = store.save(output_path) {
+        error!("Failed to write '{}': {}", output_path.display(), e);
+        return EXIT_FAILURE;
+    }
+    info!(
+        dataset = dataset_name,
+        compressor = store.name(),
+        n_items = store.len(),
+        compression_rate = data.len() as f64 / store.memory_usage().max(1) as f64,
+        compression_time,
+        output = %output_path.display(),
+        "Store saved"
+    );
+    EXIT_SUCCESS
+}
+
+/// Exports the trained dictionary of an algorithm and the usage of its tokens
+fn dictionary(dataset_path: &Path, name: &str, output_path: &Path) -> i32 {
+    let (dataset_name, data, end_positions) = match load_string_dataset(dataset_path) {
+        Ok(dataset) => dataset,
+        Err(status) => return status,
+    };
+
+    let Some(mut compressor) = AnyCompressor::create(name, data.len(), end_positions.len() - 1) else {
+        error!("{}", registry::unavailable_message(name));
+        return EXIT_FAILURE;
+    };
+    if let Err(message) = compressor.capacity().check(&end_positions) {
+        error!("{}", message);
+        return EXIT_FAILURE;
+    }
+    compressor.compress(&data, &end_positions);
+    let Some(report) = compressor.dictionary_report() else {
+        error!("Compressor '{}' has no trainable dictionary", name);
+        return EXIT_FAILURE;
+    };
+
+    if let Err(e) = report.write(output_path) {
+        error!("Failed to write '{}': {}", output_path.display(), e);
+        return EXIT_FAILURE;
+    }
+    let is_csv = output_path.extension().is_some_and(|extension| extension == "csv");
+    let stats = report.stats();
+    info!(
+        dataset = dataset_name,
+        compressor = report.compressor_name,
+        n_dictionary_tokens = report.tokens.len(),
+        n_tokens = report.n_tokens,
+        n_adjacencies = report.adjacencies.len(),
+        bytes_per_token = stats.bytes_per_token,
+        entropy_bits = stats.entropy_bits,
+        output = %output_path.display(),
+        pairs_output = is_csv.then(|| pairs_csv_path(output_path).display().to_string()),
+        "Dictionary exported"
+    );
+    EXIT_SUCCESS
+}
+
+/// Reports the properties of a dataset relevant to compression
+fn analyze(dataset_path: &Path) -> i32 {
+    let (dataset_name, data, end_positions) = match load_string_dataset(dataset_path) {
+        Ok(dataset) => dataset,
+        Err(status) => return status,
+    };
+
+    let n_items = end_positions.len() - 1;
+    let mut lengths: Vec<usize> = end_positions.windows(2).map(|w| w[1] - w[0]).collect();
+    lengths.sort_unstable();
+    let distinct: FxHashSet<&[u8]> = end_positions.windows(2).map(|w| &data[w[0]..w[1]]).collect();
+    let stats = DatasetStats::load_or_compute(dataset_path, &data, &end_positions);
+
+    let mut table = Table::new();
+    table.add_row(row!["Property", "Value"]);
+    table.add_row(row!["Dataset", dataset_name]);
+    table.add_row(row!["Strings", n_items]);
+    table.add_row(row!["Size (bytes)", data.len()]);
+    if n_items > 0 {
+        table.add_row(row!["Mean length", format!("{:.2}", data.len() as f64 / n_items as f64)]);
+        table.add_row(row!["Length (min / median / max)", format!("{} / {} / {}", lengths[0], lengths[n_items / 2], lengths[n_items - 1])]);
+        table.add_row(row!["Distinct strings", format!("{} ({:.1}%)", distinct.len(), 100.0 * distinct.len() as f64 / n_items as f64)]);
+    }
+    table.add_row(row!["Entropy (bits per byte)", format!("{:.3}", stats.entropy)]);
+    table.add_row(row!["Length code", format!("{} ({:.2} bits per string)", stats.length_code.name(), stats.bits_per_length)]);
+    table.printstd();
+    EXIT_SUCCESS
+}
+
+/// Reports the compression speed of shards sharing a dictionary, from train/parse splits
+fn amortize(results_path: &Path, extra_shards: Option<usize>) -> i32 {
+    let results = match load_benchmark_results(results_path) {
+        Ok(results) => results,
+        Err(message) => {
+            error!("{}", message);
+            return EXIT_FAILURE;
+        }
+    };
+    let splits: Vec<(&BenchmarkResult, CompressionSplit)> = results
+        .iter()
+        .filter_map(|result| result.compression_split.map(|split| (result, split)))
+        .collect();
+    if splits.is_empty() {
+        error!("No result with separate training and parse times (run benchmark_shared, or benchmark_individual with --split-compression).");
+        return EXIT_FAILURE;
+    }
+
+    let mut shard_counts = AMORTIZED_SHARDS.to_vec();
+    if let Some(n_shards) = extra_shards.filter(|n_shards| !shard_counts.contains(n_shards)) {
+        shard_counts.push(n_shards);
+    }
+    let mut header: Vec<Cell> = ["Compressor", "Dataset", "Training (s)", "Parsing (s)"].iter().map(|title| Cell::new(title)).collect();
+    header.extend(shard_counts.iter().map(|n_shards| Cell::new(&format!("{} Shard{} (MiB/s)", n_shards, if *n_shards == 1 { "" } else { "s" }))));
+    let mut table = Table::new();
+    table.add_row(Row::new(header));
+    for (result, split) in splits {
+        let mut cells = vec![
+            Cell::new(&result.compressor_name),
+            Cell::new(&result.dataset_name),
+            Cell::new(&format!("{:.3}", split.training_time)),
+            Cell::new(&format!("{:.3}", split.parse_time)),
+        ];
+        cells.extend(shard_counts.iter().map(|&n_shards| Cell::new(&format!("{:.2}", split.effective_speed(n_shards)))));
+        table.add_row(Row::new(cells));
+    }
+    table.printstd();
+    EXIT_SUCCESS
+}
+
+/// Checks that an algorithm, or a saved store, returns every string of a dataset unchanged
+fn verify(dataset_path: &Path, target: String) -> i32 {
+    let (dataset_name, data, end_positions) = match load_string_dataset(dataset_path) {
+        Ok(dataset) => dataset,
+        Err(status) => return status,
+    };
+
+    let store = if Path::new(&target).is_file() {
+        CompressedStringStore::load(Path::new(&target)).map_err(|e| format!("Failed to load '{}': {}", target, e))
+    } else {
+        let config = StoreConfig { algorithm: target, sorted: false };
+        CompressedStringStore::build(end_positions.windows(2).map(|w| &data[w[0]..w[1]]), &config)
+    };
+    let mut store = match store {
+        Ok(store) => store,
+        Err(message) => {
+            error!("{}", message);
+            return EXIT_FAILURE;
+        }
+    };
+
+    // Sorted stores hold the distinct strings in order, not the strings by index
+    let mut expected: Vec<&[u8]> = end_positions.windows(2).map(|w| &data[w[0]..w[1]]).collect();
+    if store.is_sorted() {
+        expected.sort_unstable();
+        expected.dedup();
+    }
+    if store.len() != expected.len() {
+        error!(expected = expected.len(), found = store.len(), "Store holds a different number of strings");
+        return EXIT_MISMATCH;
+    }
+    let mut buffer = Vec::new();
+    for (index, item) in expected.iter().enumerate() {
+        store.get(index, &mut buffer);
+        if buffer != *item || store.item_len(index) != item.len() {
+            error!(index, compressor = store.name(), "String differs from the dataset");
+            return EXIT_MISMATCH;
+        }
+    }
+    info!(dataset = dataset_name, compressor = store.name(), n_items = expected.len(), "All strings verified");
+    EXIT_SUCCESS
+}
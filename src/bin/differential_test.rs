@@ -0,0 +1,184 @@
+//! Differential testing of compressors
+//!
+//! This binary compresses each dataset with every listed compressor and checks that
+//! full decompression and random access to every string return the same bytes as the
+//! `raw` baseline, itself checked against the dataset first (see `differential`).
+//! Each divergence is logged with its location and the bytes around it, compressors
+//! returning the same bytes are grouped into agreement classes, and the first string
+//! on which two divergent classes disagree is logged too. Nothing is timed.
+//!
+//! The process exits with status 1 if any compressor diverges on any dataset.
+
+use compression_benchmark_rs::benchmark_utils::*;
+use compression_benchmark_rs::compressor::registry::{self, AnyCompressor};
+use compression_benchmark_rs::dataset_stats;
+use compression_benchmark_rs::differential::{self, CompressorCheck};
+use compression_benchmark_rs::logging;
+use prettytable::{row, Table};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{error, info, info_span, warn};
+
+/// Compressor whose output is the reference
+const REFERENCE: &str = "raw";
+
+/// Differential testing entry point
+fn main() {
+    let mut args: Vec<String> = std::env::args().collect();
+    logging::init_from_args(&mut args).unwrap_or_else(|message| {
+        eprintln!("Error: {}", message);
+        std::process::exit(1);
+    });
+
+    if args.len() < 2 {
+        eprintln!("Usage: {} <dataset_path|dataset_directory> [compressor_names] [--log-level=L] [--log-format=F]", args[0]);
+        eprintln!("  [compressor_names] - Comma-separated compressors (default: all available)");
+        eprintln!("  --log-level=L      - trace | debug | info | warn | error (default: info)");
+        eprintln!("  --log-format=F     - text | json (default: text)");
+        std::process::exit(1);
+    }
+
+    let path = Path::new(&args[1]);
+    let datasets: Vec<PathBuf> = if path.is_dir() {
+        let mut datasets: Vec<PathBuf> = fs::read_dir(path)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.is_file() && is_dataset_file(path))
+            .filter(|path| !dataset_stats::is_sidecar(path))
+            .collect();
+        datasets.sort();
+        datasets
+    } else if path.is_file() {
+        vec![path.to_path_buf()]
+    } else {
+        error!("Dataset path '{}' is neither a file nor a directory.", path.display());
+        std::process::exit(1);
+    };
+    let compressor_names: Vec<&str> = match args.get(2) {
+        Some(names) => names.split(',').collect(),
+        None => registry::available_compressors().into_iter().filter(|&name| name != REFERENCE).collect(),
+    };
+    for &compressor_name in compressor_names.iter() {
+        if !registry::lookup(compressor_name).is_some_and(|entry| entry.available) {
+            error!("{}.", registry::unavailable_message(compressor_name));
+            std::process::exit(1);
+        }
+    }
+
+    let mut passed = true;
+    for dataset_path in datasets.iter() {
+        if is_record_dataset(dataset_path) {
+            warn!(dataset = %dataset_path.display(), "Record datasets are not supported, skipping");
+            continue;
+        }
+        passed &= test_dataset(dataset_path, &compressor_names);
+    }
+
+    if !passed {
+        std::process::exit(1);
+    }
+}
+
+/// Checks every compressor on a dataset against the reference
+///
+/// # Returns
+/// Whether every compressor returned every string of the dataset unchanged
+fn test_dataset(dataset_path: &Path, compressor_names: &[&str]) -> bool {
+    let dataset_name = dataset_path.file_name().unwrap().to_str().unwrap().to_string();
+    let _span = info_span!("dataset", dataset = %dataset_name).entered();
+    let (data, end_positions) = load_dataset(dataset_path);
+
+    // The reference output must match the dataset itself
+    let Some(reference_check) = compress_and_check(REFERENCE, &data, &end_positions, &data) else {
+        error!("Reference compressor cannot hold this dataset.");
+        return false;
+    };
+    if !reference_check.passed() {
+        log_divergences(&reference_check);
+        error!("Reference output differs from the dataset, skipping the dataset.");
+        return false;
+    }
+    // Matching the dataset, the reference output is the dataset itself
+    let reference = &data;
+
+    let mut checks: Vec<CompressorCheck> = vec![reference_check];
+    for &compressor_name in compressor_names {
+        let Some(check) = compress_and_check(compressor_name, &data, &end_positions, reference) else {
+            continue;
+        };
+        if check.passed() {
+            info!(compressor = check.compressor_name.as_str(), "All strings match the reference");
+        } else {
+            log_divergences(&check);
+        }
+        checks.push(check);
+    }
+
+    // Divergent classes disagreeing with each other point at distinct bugs
+    let classes = differential::agreement_classes(&checks);
+    let divergent: Vec<&Vec<usize>> = classes.iter().filter(|class| !checks[class[0]].passed()).collect();
+    for (i, a) in divergent.iter().enumerate() {
+        for b in divergent[i + 1..].iter() {
+            if let Some(item) = differential::first_disagreement(&checks[a[0]], &checks[b[0]]) {
+                warn!(a = checks[a[0]].compressor_name.as_str(), b = checks[b[0]].compressor_name.as_str(), item, "Divergent compressors disagree");
+            }
+        }
+    }
+
+    print_checks(&dataset_name, &checks, &classes);
+    checks.iter().all(CompressorCheck::passed)
+}
+
+/// Compresses a dataset with a compressor and checks its output against the reference
+///
+/// # Returns
+/// The outcome of the check, or None if the compressor cannot hold the dataset
+fn compress_and_check(compressor_name: &str, data: &[u8], end_positions: &[usize], reference: &[u8]) -> Option<CompressorCheck> {
+    let n_elements = end_positions.len() - 1;
+    let mut compressor = AnyCompressor::create(compressor_name, data.len(), n_elements)?;
+    if let Err(message) = compressor.capacity().check(end_positions) {
+        warn!(compressor = compressor_name, reason = %message, "Compressor does not support this dataset, skipping");
+        return None;
+    }
+    let _span = info_span!("check", compressor = compressor.name()).entered();
+    compressor.compress(data, end_positions);
+    Some(differential::check(&mut compressor, reference, end_positions))
+}
+
+/// Logs the first divergences of a compressor
+fn log_divergences(check: &CompressorCheck) {
+    let divergences = check.decompression.iter().chain(check.first_item_divergence.iter());
+    for divergence in divergences {
+        error!(
+            compressor = check.compressor_name.as_str(),
+            location = %divergence.location(),
+            expected_length = divergence.expected_length,
+            found_length = divergence.found_length,
+            expected = %divergence.expected_context,
+            found = %divergence.found_context,
+            "Output diverges from the reference"
+        );
+    }
+    if check.divergent_items > 0 {
+        error!(compressor = check.compressor_name.as_str(), divergent_items = check.divergent_items, "Strings returned differently by random access");
+    }
+}
+
+/// Prints the outcome of the checks of a dataset, with the compressors agreeing with each one
+fn print_checks(dataset_name: &str, checks: &[CompressorCheck], classes: &[Vec<usize>]) {
+    let mut table = Table::new();
+    table.add_row(row!["Compressor", "Decompression", "Random Access", "Agrees With"]);
+    for (index, check) in checks.iter().enumerate() {
+        let decompression = check.decompression.as_ref().map_or("ok".to_string(), |divergence| divergence.location());
+        let random_access = match &check.first_item_divergence {
+            Some(divergence) => format!("{} strings (first: {})", check.divergent_items, divergence.location()),
+            None => "ok".to_string(),
+        };
+        let class = classes.iter().find(|class| class.contains(&index)).unwrap();
+        let agreeing: Vec<&str> = class.iter().filter(|&&other| other != index).map(|&other| checks[other].compressor_name.as_str()).collect();
+        table.add_row(row![check.compressor_name, decompression, random_access, if agreeing.is_empty() { "-".to_string() } else { agreeing.join(", ") }]);
+    }
+
+    println!("\nDataset: {}", dataset_name);
+    table.printstd();
+}
@@ -0,0 +1,348 @@
+//! Downloader of public string-collection corpora
+//!
+//! This binary fetches the public datasets declared in `CORPORA` (domain lists,
+//! Wikipedia titles, system logs), extracts one string per line (or per CSV row), and
+//! writes them in a format accepted by the benchmarks: a JSON array of strings, or a
+//! length-prefixed `.bin` file if some string is not valid UTF-8.
+//!
+//! Some upstream files are regenerated periodically (e.g., the latest Wikipedia dump),
+//! so the manifest does not pin their checksums. Instead, the SHA-256 of every
+//! downloaded file and of every prepared dataset is recorded in a lock file next to
+//! the datasets (`LOCK_FILE`). Later fetches fail if the upstream content changed
+//! (`--update-lock` accepts the new content), and `--verify` checks the prepared
+//! datasets against the lock without downloading anything. Publishing the lock file
+//! with benchmark results makes them reproducible.
+//!
+//! Requires the `fetch` feature.
+
+use compression_benchmark_rs::benchmark_utils::*;
+use compression_benchmark_rs::logging;
+use flate2::read::GzDecoder;
+use prettytable::{row, Table};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{error, info, info_span, warn};
+
+/// Name of the lock file recording the checksums of fetched corpora
+const LOCK_FILE: &str = "corpora.lock.json";
+/// Timeout of each download, including the transfer
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Compression of a downloaded file
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Encoding {
+    Plain,  // Uncompressed text
+    Gzip,   // Gzip-compressed text
+}
+
+/// Layout of the strings in a downloaded file
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Layout {
+    Lines { skip: usize },                  // One string per line, after `skip` header lines
+    CsvColumn { column: usize, skip: usize }, // One field of each row (unquoted CSV), after `skip` header lines
+}
+
+/// Public corpus declared in the manifest
+struct Corpus {
+    name: &'static str,             // Dataset name, used as output file name
+    description: &'static str,
+    url: &'static str,
+    encoding: Encoding,
+    layout: Layout,
+}
+
+/// Manifest of the available corpora
+const CORPORA: [Corpus; 6] = [
+    Corpus {
+        name: "wikipedia_titles",
+        description: "Titles of the main namespace of the English Wikipedia (latest dump)",
+        url: "https://dumps.wikimedia.org/enwiki/latest/enwiki-latest-all-titles-in-ns0.gz",
+        encoding: Encoding::Gzip,
+        layout: Layout::Lines { skip: 1 },
+    },
+    Corpus {
+        name: "majestic_domains",
+        description: "Domains of the Majestic Million ranking",
+        url: "https://downloads.majestic.com/majestic_million.csv",
+        encoding: Encoding::Plain,
+        layout: Layout::CsvColumn { column: 2, skip: 1 },
+    },
+    Corpus {
+        name: "apache_log",
+        description: "Apache error log sample (Loghub)",
+        url: "https://raw.githubusercontent.com/logpai/loghub/master/Apache/Apache_2k.log",
+        encoding: Encoding::Plain,
+        layout: Layout::Lines { skip: 0 },
+    },
+    Corpus {
+        name: "hdfs_log",
+        description: "Hadoop HDFS log sample (Loghub)",
+        url: "https://raw.githubusercontent.com/logpai/loghub/master/HDFS/HDFS_2k.log",
+        encoding: Encoding::Plain,
+        layout: Layout::Lines { skip: 0 },
+    },
+    Corpus {
+        name: "linux_log",
+        description: "Linux syslog sample (Loghub)",
+        url: "https://raw.githubusercontent.com/logpai/loghub/master/Linux/Linux_2k.log",
+        encoding: Encoding::Plain,
+        layout: Layout::Lines { skip: 0 },
+    },
+    Corpus {
+        name: "openssh_log",
+        description: "OpenSSH server log sample (Loghub)",
+        url: "https://raw.githubusercontent.com/logpai/loghub/master/OpenSSH/OpenSSH_2k.log",
+        encoding: Encoding::Plain,
+        layout: Layout::Lines { skip: 0 },
+    },
+];
+
+/// Checksums and statistics of a fetched corpus
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct LockEntry {
+    url: String,
+    source_sha256: String,      // Checksum of the downloaded file
+    dataset_file: String,       // Name of the prepared dataset in the output directory
+    dataset_sha256: String,     // Checksum of the prepared dataset
+    n_items: usize,
+    data_size: usize,           // Total size of the strings in bytes
+}
+
+/// Corpus downloader entry point
+fn main() {
+    let mut args: Vec<String> = std::env::args().collect();
+    logging::init_from_args(&mut args).unwrap_or_else(|message| {
+        eprintln!("Error: {}", message);
+        std::process::exit(1);
+    });
+    let list = take_flag(&mut args, "--list");
+    let verify = take_flag(&mut args, "--verify");
+    let update_lock = take_flag(&mut args, "--update-lock");
+
+    if list {
+        print_manifest();
+        return;
+    }
+
+    if args.len() < 2 {
+        let names: Vec<&str> = CORPORA.iter().map(|corpus| corpus.name).collect();
+        eprintln!("Usage: {} <output_directory> [corpus...] [--list] [--verify] [--update-lock] [--log-level=L] [--log-format=F]", args[0]);
+        eprintln!("  [corpus...]       - {} (default: all)", names.join(" | "));
+        eprintln!("  --list            - Print the manifest of the available corpora");
+        eprintln!("  --verify          - Check prepared datasets against the lock file, without downloading");
+        eprintln!("  --update-lock     - Accept upstream files whose checksum differs from the lock file");
+        eprintln!("  --log-level=L     - trace | debug | info | warn | error (default: info)");
+        eprintln!("  --log-format=F    - text | json (default: text)");
+        std::process::exit(1);
+    }
+
+    let output_directory = PathBuf::from(&args[1]);
+    let corpora: Vec<&Corpus> = if args.len() > 2 {
+        args[2..]
+            .iter()
+            .map(|name| {
+                CORPORA.iter().find(|corpus| corpus.name == name).unwrap_or_else(|| {
+                    error!("Unknown corpus '{}'. Run with --list to see the available corpora.", name);
+                    std::process::exit(1);
+                })
+            })
+            .collect()
+    } else {
+        CORPORA.iter().collect()
+    };
+
+    fs::create_dir_all(&output_directory).expect("Failed to create output directory");
+    let lock_path = output_directory.join(LOCK_FILE);
+    let mut lock = read_lock(&lock_path);
+
+    let mut n_failures = 0;
+    for corpus in corpora {
+        let _span = info_span!("corpus", name = corpus.name).entered();
+        let outcome = if verify {
+            verify_corpus(corpus, &output_directory, &lock)
+        } else {
+            fetch_corpus(corpus, &output_directory, lock.get(corpus.name), update_lock).map(|entry| {
+                lock.insert(corpus.name.to_string(), entry);
+                let json = serde_json::to_string_pretty(&lock).expect("Failed to serialize lock file");
+                fs::write(&lock_path, json).expect("Failed to write lock file");
+            })
+        };
+        if let Err(message) = outcome {
+            error!("{}", message);
+            n_failures += 1;
+        }
+    }
+
+    if n_failures > 0 {
+        error!(n_failures, "Some corpora could not be {}", if verify { "verified" } else { "fetched" });
+        std::process::exit(1);
+    }
+}
+
+/// Downloads a corpus, checks it against the lock file and writes the prepared dataset
+///
+/// # Arguments
+/// - `corpus`: Corpus to fetch
+/// - `output_directory`: Directory receiving the dataset
+/// - `locked`: Lock entry of a previous fetch, if any
+/// - `update_lock`: Whether to accept a source whose checksum differs from the lock entry
+///
+/// # Returns
+/// The lock entry of the fetched corpus, or a description of the failure
+fn fetch_corpus(corpus: &Corpus, output_directory: &Path, locked: Option<&LockEntry>, update_lock: bool) -> Result<LockEntry, String> {
+    info!(url = corpus.url, "Downloading");
+    let source = download(corpus.url)?;
+    let source_sha256 = sha256(&source);
+
+    if let Some(locked) = locked {
+        if locked.source_sha256 != source_sha256 {
+            if !update_lock {
+                return Err(format!(
+                    "Upstream content of '{}' changed since it was locked (expected SHA-256 {}, got {}). Rerun with --update-lock to accept it.",
+                    corpus.name, locked.source_sha256, source_sha256
+                ));
+            }
+            warn!(expected = %locked.source_sha256, actual = %source_sha256, "Upstream content changed, updating the lock file");
+        }
+    }
+
+    let text = match corpus.encoding {
+        Encoding::Plain => source,
+        Encoding::Gzip => {
+            let mut text = Vec::new();
+            GzDecoder::new(source.as_slice())
+                .read_to_end(&mut text)
+                .map_err(|e| format!("Failed to decompress '{}': {}", corpus.url, e))?;
+            text
+        }
+    };
+    let (data, end_positions) = extract_strings(&text, corpus.layout);
+    let n_items = end_positions.len() - 1;
+    if n_items == 0 {
+        return Err(format!("No strings found in '{}'.", corpus.url));
+    }
+
+    // Strings that are not valid UTF-8 are kept byte for byte in the binary format
+    let dataset_file = if std::str::from_utf8(&data).is_ok() {
+        let strings: Vec<&str> = end_positions
+            .windows(2)
+            .map(|window| std::str::from_utf8(&data[window[0]..window[1]]).unwrap())
+            .collect();
+        let path = output_directory.join(format!("{}.json", corpus.name));
+        fs::write(&path, serde_json::to_vec(&strings).expect("Failed to serialize dataset")).expect("Failed to write dataset");
+        path
+    } else {
+        let path = output_directory.join(format!("{}.bin", corpus.name));
+        write_binary_dataset(&path, &data, &end_positions);
+        path
+    };
+
+    let dataset_sha256 = sha256(&fs::read(&dataset_file).expect("Failed to read dataset"));
+    info!(dataset = %dataset_file.display(), n_items, data_size = data.len(), "Corpus prepared");
+    Ok(LockEntry {
+        url: corpus.url.to_string(),
+        source_sha256,
+        dataset_file: dataset_file.file_name().unwrap().to_str().unwrap().to_string(),
+        dataset_sha256,
+        n_items,
+        data_size: data.len(),
+    })
+}
+
+/// Checks a prepared dataset against its lock entry
+///
+/// # Returns
+/// Nothing if the dataset matches, or a description of the mismatch
+fn verify_corpus(corpus: &Corpus, output_directory: &Path, lock: &BTreeMap<String, LockEntry>) -> Result<(), String> {
+    let entry = lock.get(corpus.name).ok_or_else(|| format!("Corpus '{}' is not in the lock file.", corpus.name))?;
+    let path = output_directory.join(&entry.dataset_file);
+    let content = fs::read(&path).map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+    let dataset_sha256 = sha256(&content);
+    if dataset_sha256 != entry.dataset_sha256 {
+        return Err(format!(
+            "Dataset '{}' does not match the lock file (expected SHA-256 {}, got {}).",
+            path.display(), entry.dataset_sha256, dataset_sha256
+        ));
+    }
+    info!(dataset = %path.display(), "Dataset verified");
+    Ok(())
+}
+
+/// Extracts the strings of a downloaded text file
+///
+/// Lines end with `\n` or `\r\n`; empty strings are skipped.
+///
+/// # Arguments
+/// - `text`: Content of the file
+/// - `layout`: Position of the strings in the file
+///
+/// # Returns
+/// Concatenated strings and their boundary positions, as returned by `load_dataset`
+fn extract_strings(text: &[u8], layout: Layout) -> (Vec<u8>, Vec<usize>) {
+    let skip = match layout {
+        Layout::Lines { skip } | Layout::CsvColumn { skip, .. } => skip,
+    };
+    let mut data: Vec<u8> = Vec::with_capacity(text.len());
+    let mut end_positions: Vec<usize> = vec![0];
+
+    for line in text.split(|&b| b == b'\n').skip(skip) {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        let item = match layout {
+            Layout::Lines { .. } => line,
+            Layout::CsvColumn { column, .. } => line.split(|&b| b == b',').nth(column).unwrap_or(&[]),
+        };
+        if item.is_empty() {
+            continue;
+        }
+        data.extend_from_slice(item);
+        end_positions.push(data.len());
+    }
+    (data, end_positions)
+}
+
+/// Downloads a file into memory
+fn download(url: &str) -> Result<Vec<u8>, String> {
+    let response = ureq::AgentBuilder::new()
+        .timeout(DOWNLOAD_TIMEOUT)
+        .build()
+        .get(url)
+        .call()
+        .map_err(|e| format!("Failed to download '{}': {}", url, e))?;
+    let mut content = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut content)
+        .map_err(|e| format!("Failed to download '{}': {}", url, e))?;
+    Ok(content)
+}
+
+/// Returns the SHA-256 digest of the content as a hexadecimal string
+fn sha256(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Reads the lock file, or returns an empty lock if it does not exist
+fn read_lock(path: &Path) -> BTreeMap<String, LockEntry> {
+    match fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            error!("Invalid lock file '{}': {}", path.display(), e);
+            std::process::exit(1);
+        }),
+        Err(_) => BTreeMap::new(),
+    }
+}
+
+/// Prints the manifest of the available corpora
+fn print_manifest() {
+    let mut table = Table::new();
+    table.add_row(row!["Corpus", "Description", "URL"]);
+    for corpus in CORPORA.iter() {
+        table.add_row(row![corpus.name, corpus.description, corpus.url]);
+    }
+    table.printstd();
+}
@@ -0,0 +1,66 @@
+//! Generator of pathological stress datasets
+//!
+//! This binary writes one dataset per class of pathological strings, so that the
+//! benchmarks report the behavior of each compressor on each class separately:
+//! - `stress_long.bin`: a few items of tens of MiB of text (`--long-size`), exercising
+//!   items far larger than a block and training over huge strings
+//! - `stress_runs.bin`: items of a single repeated byte, of every power-of-two length
+//!   up to `MAX_RUN_LENGTH`, on which pair merging produces ever longer tokens
+//! - `stress_unique.bin`: a de Bruijn sequence of order 2 over all byte values, cut into
+//!   items: no substring of two or more bytes occurs twice in the whole dataset, so that
+//!   dictionary-based compressors find nothing to merge
+//!
+//! Datasets use the binary length-prefixed format, and are deterministic for a given
+//! set of options. The generators are in the `stress` module.
+
+use compression_benchmark_rs::benchmark_utils::*;
+use compression_benchmark_rs::logging;
+use compression_benchmark_rs::stress::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::fs;
+use std::path::Path;
+use tracing::{error, info};
+
+/// Default size of the long items in MiB
+const DEFAULT_LONG_SIZE: usize = 16;
+/// Seed of the random generator
+const SEED: u64 = 42;
+
+/// Stress dataset generator entry point
+fn main() {
+    let mut args: Vec<String> = std::env::args().collect();
+    logging::init_from_args(&mut args).unwrap_or_else(|message| {
+        eprintln!("Error: {}", message);
+        std::process::exit(1);
+    });
+    let long_size = take_option(&mut args, "--long-size").map_or(DEFAULT_LONG_SIZE, |value| {
+        value.parse::<usize>().ok().filter(|size| (1..4096).contains(size)).unwrap_or_else(|| {
+            error!("Invalid long item size '{}'. Must be a number of MiB below 4096.", value);
+            std::process::exit(1);
+        })
+    });
+
+    if args.len() < 2 {
+        eprintln!("Usage: {} <output_directory> [--long-size=M] [--log-level=L] [--log-format=F]", args[0]);
+        eprintln!("  --long-size=M     - Size of the long items in MiB (default: {})", DEFAULT_LONG_SIZE);
+        eprintln!("  --log-level=L     - trace | debug | info | warn | error (default: info)");
+        eprintln!("  --log-format=F    - text | json (default: text)");
+        std::process::exit(1);
+    }
+
+    let output_directory = Path::new(&args[1]);
+    fs::create_dir_all(output_directory).expect("Failed to create output directory");
+
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let datasets = [
+        ("stress_long.bin", long_items(&mut rng, long_size << 20)),
+        ("stress_runs.bin", run_items(MAX_RUN_LENGTH)),
+        ("stress_unique.bin", unique_items()),
+    ];
+    for (file_name, (data, end_positions)) in datasets {
+        let path = output_directory.join(file_name);
+        write_binary_dataset(&path, &data, &end_positions);
+        info!(dataset = %path.display(), n_items = end_positions.len() - 1, data_size = data.len(), "Stress dataset written");
+    }
+}
@@ -0,0 +1,187 @@
+//! Parameter tuning driver for compression algorithms
+//!
+//! This binary sweeps the tunable parameters of a compression algorithm over a
+//! declared grid on a single dataset. Each configuration is evaluated with an
+//! abbreviated benchmark, and the best configuration under the requested objective
+//! is reported. Tunable parameters are:
+//! - `onpair_bv`: bits per token and merge threshold, on top of the other training
+//!   parameters given with `--onpair-config` (see `OnPairConfig::parse`)
+//! - `bpe`: maximum number of dictionary tokens
+//!
+//! With `--budgets=B,...`, the dictionary size budget (in bytes, including token
+//! boundaries) is swept instead, tracing the compression rate as a function of the
+//! budget: BPE stops merging at the budget, and OnPairBV evicts its least-used
+//! provisional tokens before stopping (see `OnPairConfig::max_dictionary_size`).
+//!
+//! All evaluated configurations can optionally be written to a JSON file.
+
+use compression_benchmark_rs::benchmark_utils::*;
+use compression_benchmark_rs::compressor::bpe::{BPECompressor, BPEConfig};
+#[cfg(feature = "onpair")]
+use compression_benchmark_rs::compressor::onpair_bv::{OnPairBVCompressor, OnPairConfig};
+use compression_benchmark_rs::logging;
+use compression_benchmark_rs::tuning::{Objective, Trial};
+use prettytable::{row, Table};
+use std::fs;
+use std::path::Path;
+use tracing::{error, info};
+
+/// Number of random access queries per evaluated configuration
+const N_QUERIES: usize = 100000;
+/// Bits per token evaluated for OnPairBV
+#[cfg(feature = "onpair")]
+const ONPAIR_BV_BITS_PER_TOKEN: [usize; 7] = [10, 11, 12, 13, 14, 15, 16];
+/// Merge thresholds evaluated for OnPairBV (None: derived from data size)
+#[cfg(feature = "onpair")]
+const ONPAIR_BV_THRESHOLDS: [Option<usize>; 6] = [None, Some(2), Some(4), Some(8), Some(16), Some(32)];
+/// Dictionary capacities evaluated for BPE
+const BPE_MAX_TOKENS: [usize; 6] = [512, 1024, 4096, 8192, 16384, 65536];
+
+/// Tuning entry point
+fn main() {
+    let mut args: Vec<String> = std::env::args().collect();
+    logging::init_from_args(&mut args).unwrap_or_else(|message| {
+        eprintln!("Error: {}", message);
+        std::process::exit(1);
+    });
+    #[cfg(feature = "onpair")]
+    let onpair_config = take_option(&mut args, "--onpair-config").map_or(OnPairConfig::default(), |value| {
+        OnPairConfig::parse(&value).unwrap_or_else(|| {
+            error!("Invalid OnPair configuration '{}'. Must be a list of threshold=N|auto, max_length=N, bits=N, sample=F, sampling=random|stratified, dictionary=plain|front_coded or max_dictionary=N.", value);
+            std::process::exit(1);
+        })
+    });
+    let budgets = take_option(&mut args, "--budgets").map(|value| {
+        value.split(',').map(|budget| budget.parse::<usize>().ok().filter(|&budget| budget > 0)).collect::<Option<Vec<usize>>>().unwrap_or_else(|| {
+            error!("Invalid budgets '{}'. Must be a list of positive numbers of bytes.", value);
+            std::process::exit(1);
+        })
+    });
+
+    if args.len() < 4 {
+        eprintln!("Usage: {} <dataset_path> <compressor_name> <objective> [output_file] [--onpair-config=C] [--budgets=B] [--log-level=L] [--log-format=F]", args[0]);
+        eprintln!("  <compressor_name> - onpair_bv | bpe");
+        eprintln!("  <objective>       - max_ratio | min_latency | max_speed |");
+        eprintln!("                      min_latency:ratio>=X | max_ratio:latency<=N");
+        eprintln!("  --onpair-config=C - Fixed OnPairBV parameters, e.g. max_length=16,sample=0.5");
+        eprintln!("  --budgets=B       - Sweep dictionary size budgets in bytes instead, e.g. 4096,16384,65536");
+        eprintln!("  --log-level=L     - trace | debug | info | warn | error (default: info)");
+        eprintln!("  --log-format=F    - text | json (default: text)");
+        std::process::exit(1);
+    }
+
+    let dataset_path = Path::new(&args[1]);
+    let compressor_name = &args[2];
+    let objective = Objective::parse(&args[3]).unwrap_or_else(|| {
+        error!("Invalid objective '{}'.", args[3]);
+        std::process::exit(1);
+    });
+    let output_file = args.get(4);
+
+    if !dataset_path.is_file() {
+        error!("Dataset path '{}' is not a file.", dataset_path.display());
+        std::process::exit(1);
+    }
+
+    // Load dataset
+    let dataset_name = dataset_path.file_name().unwrap().to_str().unwrap().to_string();
+    let (data, end_positions) = load_dataset(dataset_path);
+    let n_elements = end_positions.len() - 1;
+    let queries = generate_random_queries(n_elements, N_QUERIES);
+
+    // Evaluate every configuration of the grid
+    let mut trials: Vec<Trial> = Vec::new();
+    match compressor_name.as_str() {
+        #[cfg(feature = "onpair")]
+        "onpair_bv" if budgets.is_some() => {
+            for &budget in budgets.iter().flatten() {
+                let config = OnPairConfig { max_dictionary_size: Some(budget), ..onpair_config };
+                let mut compressor = OnPairBVCompressor::with_config(data.len(), n_elements, config);
+                let parameters = format!("max_dictionary_size={}", budget);
+                info!(%parameters, "Evaluating configuration");
+                let result = benchmark(&mut compressor, dataset_name.clone(), &data, &end_positions, &queries);
+                trials.push(Trial { parameters, result });
+            }
+        }
+        #[cfg(feature = "onpair")]
+        "onpair_bv" => {
+            for &bits_per_token in ONPAIR_BV_BITS_PER_TOKEN.iter() {
+                for &threshold in ONPAIR_BV_THRESHOLDS.iter() {
+                    let config = OnPairConfig { bits_per_token, threshold, ..onpair_config };
+                    let mut compressor = OnPairBVCompressor::with_config(data.len(), n_elements, config);
+                    let parameters = format!(
+                        "bits_per_token={}, threshold={}",
+                        bits_per_token,
+                        threshold.map_or("auto".to_string(), |t| t.to_string())
+                    );
+                    info!(%parameters, "Evaluating configuration");
+                    let result = benchmark(&mut compressor, dataset_name.clone(), &data, &end_positions, &queries);
+                    trials.push(Trial { parameters, result });
+                }
+            }
+        }
+        "bpe" if budgets.is_some() => {
+            for &budget in budgets.iter().flatten() {
+                let config = BPEConfig { max_dictionary_size: Some(budget), ..BPEConfig::default() };
+                let mut compressor = BPECompressor::with_config(data.len(), n_elements, config);
+                let parameters = format!("max_dictionary_size={}", budget);
+                info!(%parameters, "Evaluating configuration");
+                let result = benchmark(&mut compressor, dataset_name.clone(), &data, &end_positions, &queries);
+                trials.push(Trial { parameters, result });
+            }
+        }
+        "bpe" => {
+            for &max_tokens in BPE_MAX_TOKENS.iter() {
+                let mut compressor = BPECompressor::with_max_tokens(data.len(), n_elements, max_tokens);
+                let parameters = format!("max_tokens={}", max_tokens);
+                info!(%parameters, "Evaluating configuration");
+                let result = benchmark(&mut compressor, dataset_name.clone(), &data, &end_positions, &queries);
+                trials.push(Trial { parameters, result });
+            }
+        }
+        _ => {
+            error!("Compressor '{}' has no tunable parameters.", compressor_name);
+            std::process::exit(1);
+        }
+    }
+
+    print_trials(&trials, &objective);
+
+    match objective.best(&trials) {
+        Some(best) => println!("\nBest configuration: {}", best.parameters),
+        None => println!("\nNo configuration satisfies the objective constraints."),
+    }
+
+    if let Some(output_file) = output_file {
+        let json = serde_json::to_string_pretty(&trials).expect("Failed to serialize trials");
+        fs::write(output_file, json).expect("Failed to write trials to file");
+    }
+}
+
+/// Prints all evaluated configurations, marking those violating the objective constraints
+fn print_trials(trials: &[Trial], objective: &Objective) {
+    let mut table = Table::new();
+    table.add_row(row![
+        "Parameters",
+        "Feasible",
+        "Comp. Rate",
+        "Dictionary (bytes)",
+        "Comp. Speed (MiB/s)",
+        "Decomp. Speed (MiB/s)",
+        "Avg. Random Access Time (ns)"
+    ]);
+
+    for trial in trials {
+        table.add_row(row![
+            &trial.parameters,
+            if objective.is_feasible(&trial.result) { "yes" } else { "no" },
+            format!("{:.3}", trial.result.compression_rate),
+            trial.result.space_breakdown.dictionary,
+            trial.result.compression_speed.map_or("-".to_string(), |speed| format!("{:.2}", speed)),
+            trial.result.decompression_speed.map_or("-".to_string(), |speed| format!("{:.2}", speed)),
+            trial.result.average_random_access_time.map_or("-".to_string(), |time| format!("{}", time)),
+        ]);
+    }
+
+    table.printstd();
+}
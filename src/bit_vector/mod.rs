@@ -1,5 +1,13 @@
 use serde::{Deserialize, Serialize};
-use std::arch::x86_64::_popcnt64;
+use std::io::{self, Read, Write};
+
+mod roaring;
+pub use roaring::RoaringBitmap;
+
+/// Magic bytes opening the portable serialization of a binary vector
+const SERIALIZATION_MAGIC: [u8; 4] = *b"BVEC";
+/// Version of the portable serialization written by `write_to`
+const SERIALIZATION_VERSION: u32 = 1;
 
 #[derive(Default, Clone, Serialize, Deserialize, Eq, PartialEq)]
 pub struct BitVector {
@@ -15,7 +23,7 @@ impl BitVector {
 
     /// Creates an empty binary vector with at least a capacity of ```n_bits```.
     pub fn with_capacity(n_bits: usize) -> Self {
-        let capacity = (n_bits + 63) / 64;
+        let capacity = n_bits.div_ceil(64);
         Self {
             data: Vec::with_capacity(capacity),
             ..Self::default()
@@ -33,7 +41,7 @@ impl BitVector {
     #[inline]
     pub fn extend_with_zeroes(&mut self, n: usize) {
         self.position += n;
-        let new_size = (self.position + 63) / 64;
+        let new_size = self.position.div_ceil(64);
         self.data.resize_with(new_size, Default::default);
     }
 
@@ -48,9 +56,9 @@ impl BitVector {
     #[inline]
     pub fn extend_with_ones(&mut self, n: usize) {
         self.position += n;
-        let new_size = (self.position + 63) / 64;
+        let new_size = self.position.div_ceil(64);
         self.data.resize_with(new_size, || u64::MAX); // Fill with u64::MAX
-        if self.position % 64 != 0 {
+        if !self.position.is_multiple_of(64) {
             let remaining_bits = self.position % 64;
             self.data[new_size - 1] = (1u64 << remaining_bits) - 1; // Set only the last bits to 1
         }
@@ -81,6 +89,10 @@ impl BitVector {
         Some(self.data[word] >> pos_in_word & 1_u64 == 1)
     }
 
+    /// Returns the bit at position `index`, without bounds checking
+    ///
+    /// # Safety
+    /// `index` must be smaller than the length of the vector.
     #[inline(always)]
     pub unsafe fn get_unchecked(&self, index: usize) -> bool {
         debug_assert!(index < self.position);
@@ -119,6 +131,61 @@ impl BitVector {
         }
     }
 
+    /// Appends the first ```len``` bits of ```words```, least significant bit first.
+    ///
+    /// Words are appended whole when the vector is word-aligned, and shifted
+    /// into place otherwise.
+    pub fn append_u64_slice(&mut self, words: &[u64], len: usize) {
+        assert!(len <= words.len() * 64);
+        if len == 0 {
+            return;
+        }
+        let n_words = len.div_ceil(64);
+        let tail_bits = len & 63;
+        let tail_mask = if tail_bits == 0 { u64::MAX } else { (1u64 << tail_bits) - 1 };
+
+        if self.position & 63 == 0 {
+            self.data.extend_from_slice(&words[..n_words]);
+            *self.data.last_mut().unwrap() &= tail_mask;
+            self.position += len;
+            return;
+        }
+
+        self.data.reserve(n_words);
+        for &word in words[..n_words - 1].iter() {
+            self.append_bits(word, 64);
+        }
+        let last_len = len - (n_words - 1) * 64;
+        self.append_bits(words[n_words - 1] & tail_mask, last_len);
+    }
+
+    /// Appends all the bits of ```other```.
+    pub fn extend_from_bitvector(&mut self, other: &BitVector) {
+        self.append_u64_slice(&other.data, other.position);
+    }
+
+    /// Appends ```len``` bits of ```src``` starting at bit position ```src_offset```.
+    pub fn copy_bits(&mut self, src: &BitVector, src_offset: usize, len: usize) {
+        assert!(src_offset + len <= src.position);
+        if src_offset & 63 == 0 {
+            self.append_u64_slice(&src.data[src_offset / 64..], len);
+            return;
+        }
+
+        self.data.reserve(len.div_ceil(64));
+        let mut offset = src_offset;
+        let end = src_offset + len;
+        while offset + 64 <= end {
+            let bits = unsafe { src.get_bits_unchecked(offset, 64) };
+            self.append_bits(bits, 64);
+            offset += 64;
+        }
+        if offset < end {
+            let bits = unsafe { src.get_bits_unchecked(offset, end - offset) };
+            self.append_bits(bits, end - offset);
+        }
+    }
+
     #[inline(always)]
     pub fn get_bits(&self, index: usize, len: usize) -> Option<u64> {
         if (len > 64) | (index + len > self.position) {
@@ -137,6 +204,10 @@ impl BitVector {
         Some(((self.data[block] >> shift) | (self.data[block + 1] << (64 - shift))) & mask)
     }
 
+    /// Returns the `len` bits starting at position `index`, without bounds checking
+    ///
+    /// # Safety
+    /// `len` must be at most 64, and `index + len` at most the length of the vector.
     #[inline(always)]
     pub unsafe fn get_bits_unchecked(&self, index: usize, len: usize) -> u64 {
         debug_assert!(len <= 64);
@@ -158,52 +229,63 @@ impl BitVector {
         }
     }
 
+    /// Returns the position of the first set bit after ```pos```, if any.
+    ///
+    /// Any ```pos``` is accepted, including positions at or past the end of the vector.
     #[inline(always)]
     pub fn next_one(&self, pos: usize) -> Option<usize> {
-        let mut next_pos = pos + 1;
-        let mut word_pos = next_pos >> 6;
-        if word_pos >= self.data.len() {
-            return None;
-        }
-        let mut buffer = self.data[word_pos] >> (next_pos % 64);
-
-        while buffer == 0 {
-            next_pos += 64 - (next_pos % 64);
-            word_pos = next_pos >> 6;
-            if word_pos >= self.data.len() {
-                return None;
-            }
-            buffer = self.data[word_pos];
-        }
-        let pos_in_word: usize = buffer.trailing_zeros() as usize;
-        next_pos += pos_in_word;
-
-        Some(next_pos)
+        UnaryIter::new(self, pos.checked_add(1)?).next1()
     }
 
+    /// Returns the position of the last set bit before ```pos```, if any.
+    ///
+    /// Any ```pos``` is accepted: positions past the end of the vector search from its last bit.
     #[inline(always)]
     pub fn prev_one(&self, pos: usize) -> Option<usize> {
-        if pos == 0 {
+        let end = pos.min(self.position);
+        if end == 0 {
             return None;
         }
 
-        let mut prev_pos = pos - 1;
-        let mut word_pos = prev_pos >> 6;
-        let mut buffer = self.data[word_pos] << (63 - (prev_pos % 64));
-
-        while buffer == 0 {
+        // Keep the bits of the last word up to position end - 1 only
+        let mut word_pos = (end - 1) >> 6;
+        let mut word = self.data[word_pos] & (u64::MAX >> (63 - (end - 1) % 64));
+        while word == 0 {
             if word_pos == 0 {
                 return None;
             }
             word_pos -= 1;
-            prev_pos = (word_pos + 1) * 64 - 1;
-            buffer = self.data[word_pos];
+            word = self.data[word_pos];
         }
 
-        let pos_in_word: usize = buffer.leading_zeros() as usize;
-        prev_pos -= pos_in_word;
+        Some(word_pos * 64 + 63 - word.leading_zeros() as usize)
+    }
 
-        Some(prev_pos)
+    /// Returns the position of the set bit of the given ```rank``` (0-based), if any.
+    pub fn select1(&self, rank: usize) -> Option<usize> {
+        UnaryIter::new(self, 0).select1(rank)
+    }
+
+    /// Returns the position of the unset bit of the given ```rank``` (0-based), if any.
+    pub fn select0(&self, rank: usize) -> Option<usize> {
+        UnaryIter::new(self, 0).select0(rank)
+    }
+
+    /// Returns the bits from ```pos``` to the end of its word, shifted to the lowest
+    /// positions and complemented if ```ones``` is false, so that set bits mark the
+    /// bits looked for. Bits past the end of the vector are cleared.
+    #[inline(always)]
+    fn word_from(&self, pos: usize, ones: bool) -> u64 {
+        debug_assert!(pos < self.position);
+
+        let word = self.data[pos >> 6];
+        let word = if ones { word } else { !word } >> (pos % 64);
+        let remaining = self.position - pos;
+        if remaining < 64 {
+            word & ((1u64 << remaining) - 1)
+        } else {
+            word
+        }
     }
 
     /// Shrinks the underlying vector of 64bit words to fit.
@@ -221,134 +303,558 @@ impl BitVector {
         self.position
     }
 
-    pub fn ones(&self, pos: usize) -> UnaryIterOnes {
+    /// Returns the number of set bits.
+    pub fn ones_count(&self) -> usize {
+        self.words().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Returns the number of unset bits.
+    pub fn zeros_count(&self) -> usize {
+        self.position - self.ones_count()
+    }
+
+    /// Returns the fraction of set bits, 0 for an empty vector.
+    pub fn density(&self) -> f64 {
+        if self.position == 0 {
+            return 0.0;
+        }
+        self.ones_count() as f64 / self.position as f64
+    }
+
+    /// Returns an iterator over the 64-bit words holding the bits, least significant bit first.
+    ///
+    /// The last word has its bits past the end of the vector cleared.
+    pub fn words(&self) -> Words<'_> {
+        Words { words: self.data[..self.position.div_ceil(64)].iter(), tail_bits: self.position % 64 }
+    }
+
+    /// Returns an iterator over the positions of set bits, starting at ```pos```.
+    pub fn ones(&self, pos: usize) -> UnaryIterOnes<'_> {
         UnaryIterOnes::new(self, pos)
     }
 
-    pub fn zeroes(&self, pos: usize) -> UnaryIterZeroes {
+    /// Returns an iterator over the positions of unset bits, starting at ```pos```.
+    pub fn zeroes(&self, pos: usize) -> UnaryIterZeroes<'_> {
         UnaryIterZeroes::new(self, pos)
     }
+
+    /// Returns a cursor reading consecutive bit fields starting at ```pos```.
+    pub fn reader(&self, pos: usize) -> BitReader<'_> {
+        BitReader::new(self, pos)
+    }
+
+    /// Writes the vector in a portable format, independent of the platform.
+    ///
+    /// The format is the magic bytes `BVEC`, the format version (u32), the number
+    /// of bits (u64) and the words holding them (u64 each, least significant bit
+    /// first), all little-endian. Bits past the end of the vector are written as 0.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&SERIALIZATION_MAGIC)?;
+        writer.write_all(&SERIALIZATION_VERSION.to_le_bytes())?;
+        writer.write_all(&(self.position as u64).to_le_bytes())?;
+        let n_words = self.position.div_ceil(64);
+        for (i, &word) in self.data[..n_words].iter().enumerate() {
+            let tail_bits = self.position - i * 64;
+            let word = if tail_bits < 64 { word & ((1u64 << tail_bits) - 1) } else { word };
+            writer.write_all(&word.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Reads a vector written by ```write_to```, on any platform.
+    ///
+    /// Fails with ```InvalidData``` on a wrong magic, an unsupported version, a bit
+    /// length not addressable on this platform, or set bits past the end.
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != SERIALIZATION_MAGIC {
+            return Err(invalid("Not a serialized binary vector".to_string()));
+        }
+        let mut version = [0u8; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != SERIALIZATION_VERSION {
+            return Err(invalid(format!("Unsupported binary vector format version {}", version)));
+        }
+        let mut n_bits = [0u8; 8];
+        reader.read_exact(&mut n_bits)?;
+        let n_bits = u64::from_le_bytes(n_bits);
+        let position = usize::try_from(n_bits).map_err(|_| invalid(format!("Binary vector of {} bits exceeds the address space", n_bits)))?;
+
+        // Words are read one at a time, so that a corrupted length fails at the end of the input
+        let n_words = position.div_ceil(64);
+        let mut data = Vec::new();
+        let mut word = [0u8; 8];
+        for _ in 0..n_words {
+            reader.read_exact(&mut word)?;
+            data.push(u64::from_le_bytes(word));
+        }
+        if position % 64 != 0 && data[n_words - 1] >> (position % 64) != 0 {
+            return Err(invalid("Set bits past the end of the binary vector".to_string()));
+        }
+        data.shrink_to_fit();
+        Ok(Self { data, position })
+    }
+
+    /// Returns the vector in the portable format of ```write_to```.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(16 + self.position.div_ceil(64) * 8);
+        self.write_to(&mut bytes).expect("Writing to a vector cannot fail");
+        bytes
+    }
+
+    /// Decodes a vector from the portable format of ```write_to```, rejecting trailing bytes.
+    pub fn from_bytes(mut bytes: &[u8]) -> io::Result<Self> {
+        let bv = Self::read_from(&mut bytes)?;
+        if !bytes.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} trailing bytes after the binary vector", bytes.len())));
+        }
+        Ok(bv)
+    }
 }
 
-// Iterator for enumerating bit positions
-pub struct UnaryIter<'a> {
-    bv: &'a BitVector,
+/// Bit vector supporting the navigation between set bits, dense or compressed
+///
+/// Implemented by `BitVector` (one bit per position) and `RoaringBitmap` (see
+/// `roaring`), so that algorithms tracking positions may pick the representation
+/// fitting the density of their bits.
+pub trait BitSet {
+    /// Creates a vector with `n_bits` set to 1
+    fn with_ones(n_bits: usize) -> Self;
+
+    /// Returns whether the bit at `index` is set, false past the end of the vector
+    fn contains(&self, index: usize) -> bool;
+
+    /// Sets the bit at `index` to `bit`
+    fn set(&mut self, index: usize, bit: bool);
+
+    /// Returns the position of the first set bit after `pos`, if any
+    fn next_one(&self, pos: usize) -> Option<usize>;
+
+    /// Returns the position of the last set bit before `pos`, if any
+    fn prev_one(&self, pos: usize) -> Option<usize>;
+
+    /// Returns the number of set bits
+    fn ones_count(&self) -> usize;
+
+    /// Returns the fraction of set bits, 0 for an empty vector
+    fn density(&self) -> f64;
+
+    /// Returns the memory taken by the vector in bytes
+    fn size_bytes(&self) -> usize;
+}
+
+impl BitSet for BitVector {
+    fn with_ones(n_bits: usize) -> Self {
+        BitVector::with_ones(n_bits)
+    }
+
+    #[inline(always)]
+    fn contains(&self, index: usize) -> bool {
+        self.get(index) == Some(true)
+    }
+
+    #[inline(always)]
+    fn set(&mut self, index: usize, bit: bool) {
+        BitVector::set(self, index, bit)
+    }
+
+    #[inline(always)]
+    fn next_one(&self, pos: usize) -> Option<usize> {
+        BitVector::next_one(self, pos)
+    }
+
+    #[inline(always)]
+    fn prev_one(&self, pos: usize) -> Option<usize> {
+        BitVector::prev_one(self, pos)
+    }
+
+    fn ones_count(&self) -> usize {
+        BitVector::ones_count(self)
+    }
+
+    fn density(&self) -> f64 {
+        BitVector::density(self)
+    }
+
+    fn size_bytes(&self) -> usize {
+        self.data.capacity() * std::mem::size_of::<u64>()
+    }
+}
+
+/// Iterator over the words of a binary vector, with the bits past its end cleared
+pub struct Words<'a> {
+    words: std::slice::Iter<'a, u64>,
+    tail_bits: usize,   // Bits of the vector in its last word (0: the last word is full)
+}
+
+impl Iterator for Words<'_> {
+    type Item = u64;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<u64> {
+        let word = *self.words.next()?;
+        if self.tail_bits != 0 && self.words.len() == 0 {
+            return Some(word & ((1u64 << self.tail_bits) - 1));
+        }
+        Some(word)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.words.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Words<'_> {}
+
+/// Cursor for reading consecutive bit fields
+///
+/// Keeps the current word and in-word offset, so sequential reads avoid
+/// recomputing them from an absolute bit position on every call.
+pub struct BitReader<'a> {
+    data: &'a [u64],
     pos: usize,
+    end: usize,
     word_pos: usize,
-    buffer: u64,
+    shift: usize,
 }
 
-impl<'a> UnaryIter<'a> {
-    // Creates the iterator from the given bit position
-    pub fn new(bv: &BitVector, pos: usize) -> UnaryIter {
-        let word_pos = pos >> 6;
-        let buffer = if word_pos < bv.data.len() {
-            bv.data[word_pos] >> (pos % 64)
-        } else {
-            0
-        };
-
-        UnaryIter {
-            bv,
+impl<'a> BitReader<'a> {
+    /// Creates the cursor at the given bit position
+    pub fn new(bv: &'a BitVector, pos: usize) -> BitReader<'a> {
+        BitReader {
+            data: &bv.data,
             pos,
-            word_pos,
-            buffer,
+            end: bv.position,
+            word_pos: pos >> 6,
+            shift: pos & 63,
         }
     }
 
-    // iterates over positions of set bits
+    /// Reads the next ```len``` bits, or returns None if fewer bits are left.
     #[inline(always)]
-    pub fn next1(&mut self) -> Option<usize> {
-        while self.buffer == 0 {
-            self.pos += 64 - (self.pos % 64);
-            self.word_pos = self.pos >> 6;
-            if self.word_pos >= self.bv.data.len() {
-                return None;
-            }
-            self.buffer = self.bv.data[self.word_pos];
+    pub fn read(&mut self, len: usize) -> Option<u64> {
+        if (len > 64) | (self.pos + len > self.end) {
+            return None;
         }
-        let pos_in_word: usize = self.buffer.trailing_zeros() as usize;
-        self.pos += pos_in_word + 1;
-        self.word_pos = self.pos >> 6;
-        self.buffer = if self.word_pos < self.bv.data.len() {
-            self.bv.data[self.word_pos] >> (self.pos % 64)
-        } else {
-            0
-        };
-        Some(self.pos - 1)
+        if len == 0 {
+            return Some(0);
+        }
+        Some(unsafe { self.read_unchecked(len) })
     }
 
-    // iterates over positions of bits set to zero
+    /// Reads the next ```len``` bits without bounds checking.
+    ///
+    /// # Safety
+    /// ```len``` must be in 1..=64 and at least ```len``` bits must be left.
+    #[inline(always)]
+    pub unsafe fn read_unchecked(&mut self, len: usize) -> u64 {
+        debug_assert!(len > 0 && len <= 64);
+        debug_assert!(self.pos + len <= self.end);
+
+        let mask = u64::MAX >> (64 - len);
+        let mut value = *self.data.get_unchecked(self.word_pos) >> self.shift;
+        if self.shift + len > 64 {
+            value |= *self.data.get_unchecked(self.word_pos + 1) << (64 - self.shift);
+        }
+
+        self.pos += len;
+        self.shift += len;
+        if self.shift >= 64 {
+            self.shift -= 64;
+            self.word_pos += 1;
+        }
+
+        value & mask
+    }
+
+    /// Returns the current bit position.
+    #[inline(always)]
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+}
+
+/// Cursor enumerating the positions of set or unset bits
+///
+/// Scans whole words from any starting position, aligned or not, and never returns
+/// positions past the end of the vector; starting at or past the end yields nothing.
+pub struct UnaryIter<'a> {
+    bv: &'a BitVector,
+    pos: usize,     // Next position to scan
+}
+
+impl<'a> UnaryIter<'a> {
+    /// Creates the cursor at the given bit position
+    pub fn new(bv: &BitVector, pos: usize) -> UnaryIter<'_> {
+        UnaryIter { bv, pos }
+    }
+
+    /// Returns the position of the next set bit, advancing past it
+    #[inline(always)]
+    pub fn next1(&mut self) -> Option<usize> {
+        self.select(0, true)
+    }
+
+    /// Returns the position of the next unset bit, advancing past it
     #[inline(always)]
     pub fn next0(&mut self) -> Option<usize> {
-        let mut buffer = !self.buffer;
-        loop {
-            let w;
-            unsafe {
-                w = _popcnt64(buffer as i64) as usize - (self.pos % 64);
-            }
-            if w != 0 {
-                break;
-            }
-            self.pos += 64 - (self.pos % 64);
-            self.word_pos = self.pos >> 6;
-            if self.word_pos >= self.bv.data.len() {
-                return None;
+        self.select(0, false)
+    }
+
+    /// Skips ```rank``` set bits and returns the position of the next one, advancing past it
+    #[inline(always)]
+    pub fn select1(&mut self, rank: usize) -> Option<usize> {
+        self.select(rank, true)
+    }
+
+    /// Skips ```rank``` unset bits and returns the position of the next one, advancing past it
+    #[inline(always)]
+    pub fn select0(&mut self, rank: usize) -> Option<usize> {
+        self.select(rank, false)
+    }
+
+    /// Skips ```rank``` bits equal to ```ones``` and returns the position of the next one
+    ///
+    /// Whole words are skipped by population count. When no such bit is left,
+    /// the cursor moves to the end of the vector.
+    #[inline(always)]
+    fn select(&mut self, mut rank: usize, ones: bool) -> Option<usize> {
+        while self.pos < self.bv.position {
+            let mut word = self.bv.word_from(self.pos, ones);
+            let count = word.count_ones() as usize;
+            if rank < count {
+                // Clear the lowest rank set bits of the word
+                for _ in 0..rank {
+                    word &= word - 1;
+                }
+                let found = self.pos + word.trailing_zeros() as usize;
+                self.pos = found + 1;
+                return Some(found);
             }
-            buffer = !self.bv.data[self.word_pos];
+            rank -= count;
+            self.pos = (self.pos + 64 - self.pos % 64).min(self.bv.position);
         }
-        let pos_in_word: usize = buffer.trailing_zeros() as usize;
-        self.pos += pos_in_word + 1;
-        self.word_pos = self.pos >> 6;
-        self.buffer = if self.word_pos < self.bv.data.len() {
-            self.bv.data[self.word_pos] >> (self.pos % 64)
-        } else {
-            0
-        };
-
-        Some(self.pos - 1).filter(|&x| x < self.bv.position)
+        self.pos = self.pos.max(self.bv.position);
+        None
     }
 
+    /// Returns the next position to scan
     #[inline(always)]
     pub fn pos(&self) -> usize {
         self.pos
     }
 }
 
+/// Iterator over the positions of set bits
 pub struct UnaryIterOnes<'a> {
     iter: UnaryIter<'a>,
 }
+
 impl<'a> UnaryIterOnes<'a> {
-    pub fn new(bv: &BitVector, pos: usize) -> UnaryIterOnes {
+    pub fn new(bv: &BitVector, pos: usize) -> UnaryIterOnes<'_> {
         let iter = UnaryIter::new(bv, pos);
         UnaryIterOnes { iter }
     }
 }
+
 impl<'a> Iterator for UnaryIterOnes<'a> {
     type Item = usize;
 
-    // iterates over positions of set bits
     #[inline(always)]
     fn next(&mut self) -> Option<usize> {
         self.iter.next1()
     }
+
+    #[inline(always)]
+    fn nth(&mut self, n: usize) -> Option<usize> {
+        self.iter.select1(n)
+    }
 }
 
+/// Iterator over the positions of unset bits
 pub struct UnaryIterZeroes<'a> {
     iter: UnaryIter<'a>,
 }
+
 impl<'a> UnaryIterZeroes<'a> {
-    pub fn new(bv: &BitVector, pos: usize) -> UnaryIterZeroes {
+    pub fn new(bv: &BitVector, pos: usize) -> UnaryIterZeroes<'_> {
         let iter = UnaryIter::new(bv, pos);
         UnaryIterZeroes { iter }
     }
 }
+
 impl<'a> Iterator for UnaryIterZeroes<'a> {
     type Item = usize;
 
-    // iterates over positions of set bits
     #[inline(always)]
     fn next(&mut self) -> Option<usize> {
         self.iter.next0()
     }
+
+    #[inline(always)]
+    fn nth(&mut self, n: usize) -> Option<usize> {
+        self.iter.select0(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// Random vectors of lengths around word boundaries and of every density, with their bits
+    fn random_vectors() -> Vec<(BitVector, Vec<bool>)> {
+        let mut rng = StdRng::seed_from_u64(1099);
+        let mut vectors = Vec::new();
+        for n_bits in [0, 1, 2, 63, 64, 65, 127, 128, 129, 200, 1000] {
+            for density in [0.0, 0.02, 0.5, 0.98, 1.0] {
+                let bits: Vec<bool> = (0..n_bits).map(|_| rng.gen_bool(density)).collect();
+                let mut bv = BitVector::new();
+                for &bit in &bits {
+                    bv.push(bit);
+                }
+                vectors.push((bv, bits));
+            }
+        }
+        vectors
+    }
+
+    #[test]
+    fn next_one_and_prev_one_match_a_linear_scan() {
+        for (bv, bits) in random_vectors() {
+            for pos in (0..bits.len() + 130).chain([usize::MAX - 1, usize::MAX]) {
+                let next = (pos.saturating_add(1)..bits.len()).find(|&i| bits[i]);
+                let prev = (0..pos.min(bits.len())).rev().find(|&i| bits[i]);
+                assert_eq!(bv.next_one(pos), next, "next_one({}) over {} bits", pos, bits.len());
+                assert_eq!(bv.prev_one(pos), prev, "prev_one({}) over {} bits", pos, bits.len());
+            }
+        }
+    }
+
+    #[test]
+    fn select_matches_a_linear_scan() {
+        for (bv, bits) in random_vectors() {
+            let ones: Vec<usize> = (0..bits.len()).filter(|&i| bits[i]).collect();
+            let zeroes: Vec<usize> = (0..bits.len()).filter(|&i| !bits[i]).collect();
+            for rank in 0..=bits.len() + 1 {
+                assert_eq!(bv.select1(rank), ones.get(rank).copied(), "select1({}) over {} bits", rank, bits.len());
+                assert_eq!(bv.select0(rank), zeroes.get(rank).copied(), "select0({}) over {} bits", rank, bits.len());
+            }
+        }
+    }
+
+    #[test]
+    fn unary_iterators_from_any_position_match_a_linear_scan() {
+        for (bv, bits) in random_vectors() {
+            for start in 0..=bits.len() + 65 {
+                let ones: Vec<usize> = (start..bits.len()).filter(|&i| bits[i]).collect();
+                let zeroes: Vec<usize> = (start..bits.len()).filter(|&i| !bits[i]).collect();
+                assert_eq!(bv.ones(start).collect::<Vec<_>>(), ones, "ones({}) over {} bits", start, bits.len());
+                assert_eq!(bv.zeroes(start).collect::<Vec<_>>(), zeroes, "zeroes({}) over {} bits", start, bits.len());
+                for n in [0, 1, 5, 70] {
+                    assert_eq!(bv.ones(start).nth(n), ones.get(n).copied(), "ones({}).nth({})", start, n);
+                    assert_eq!(bv.zeroes(start).nth(n), zeroes.get(n).copied(), "zeroes({}).nth({})", start, n);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unary_iter_alternates_between_ones_and_zeroes() {
+        for (bv, bits) in random_vectors() {
+            let mut iter = UnaryIter::new(&bv, 0);
+            let mut pos = 0;
+            for step in 0.. {
+                let ones = step % 2 == 0;
+                let expected = (pos..bits.len()).find(|&i| bits[i] == ones);
+                let found = if ones { iter.next1() } else { iter.next0() };
+                assert_eq!(found, expected, "step {} over {} bits", step, bits.len());
+                match found {
+                    Some(found) => pos = found + 1,
+                    None => break,
+                }
+                assert_eq!(iter.pos(), pos);
+            }
+            assert_eq!(iter.pos(), bits.len().max(pos));
+        }
+    }
+
+    /// Bits of the fixtures: every third bit, and the last one
+    fn fixture_bits() -> Vec<bool> {
+        (0..130).map(|i| i % 3 == 0 || i == 129).collect()
+    }
+
+    #[test]
+    fn portable_fixture_reads_and_writes_byte_for_byte() {
+        // Little-endian header and words, as written on any platform
+        let fixture: &[u8] = include_bytes!("../../tests/fixtures/bit_vector_130.bvec");
+        let bv = BitVector::from_bytes(fixture).unwrap();
+        let bits = fixture_bits();
+        assert_eq!(bv.len(), bits.len());
+        assert!((0..bits.len()).all(|i| bv.get(i) == Some(bits[i])));
+        assert_eq!(bv.to_bytes(), fixture);
+    }
+
+    #[test]
+    fn native_big_endian_layout_is_rejected() {
+        // The same vector with header and words in big-endian order, as a naive dump of the
+        // words would store it on a big-endian platform
+        let fixture: &[u8] = include_bytes!("../../tests/fixtures/bit_vector_130_native_be.bvec");
+        let error = BitVector::from_bytes(fixture).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // With a portable header, the swapped last word sets bits past the end
+        let mut swapped = include_bytes!("../../tests/fixtures/bit_vector_130.bvec").to_vec();
+        for word in swapped[16..].chunks_exact_mut(8) {
+            word.reverse();
+        }
+        let error = BitVector::from_bytes(&swapped).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn serialization_round_trips_and_masks_bits_past_the_end() {
+        for (bv, bits) in random_vectors() {
+            let bytes = bv.to_bytes();
+            assert_eq!(bytes.len(), 16 + bits.len().div_ceil(64) * 8);
+            assert!(BitVector::from_bytes(&bytes).unwrap() == bv, "round trip over {} bits", bits.len());
+            assert!(BitVector::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        }
+    }
+
+    #[test]
+    fn counts_and_words_match_the_bits() {
+        for (bv, bits) in random_vectors() {
+            let n_ones = bits.iter().filter(|&&bit| bit).count();
+            assert_eq!(bv.ones_count(), n_ones);
+            assert_eq!(bv.zeros_count(), bits.len() - n_ones);
+            let density = if bits.is_empty() { 0.0 } else { n_ones as f64 / bits.len() as f64 };
+            assert_eq!(bv.density(), density);
+
+            let words: Vec<u64> = bv.words().collect();
+            assert_eq!(words.len(), bits.len().div_ceil(64));
+            assert_eq!(bv.words().len(), words.len());
+            for (i, &bit) in bits.iter().enumerate() {
+                assert_eq!((words[i / 64] >> (i % 64)) & 1 == 1, bit, "bit {} of {}", i, bits.len());
+            }
+        }
+    }
+
+    #[test]
+    fn words_mask_the_bits_past_the_end() {
+        // Stale bits past the end, as left by a shorter vector reusing the words
+        for n_bits in [1, 63, 64, 65, 100, 128] {
+            let bv = BitVector { data: vec![u64::MAX; 3], position: n_bits };
+            let words: Vec<u64> = bv.words().collect();
+            assert_eq!(words.len(), n_bits.div_ceil(64));
+            assert!(words.iter().map(|word| word.count_ones() as usize).sum::<usize>() == n_bits);
+            assert_eq!(bv.ones_count(), n_bits);
+            assert_eq!(bv.zeros_count(), 0);
+            assert_eq!(bv.density(), 1.0);
+        }
+    }
 }
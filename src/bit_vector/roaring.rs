@@ -0,0 +1,387 @@
+//! Compressed bit vector for sets of positions thinning out over time
+//!
+//! BPE training marks the positions starting a token in a bit vector over the whole
+//! corpus: every position at first, fewer and fewer as merges proceed, yet a plain
+//! `BitVector` keeps taking one bit per input byte. `RoaringBitmap` splits the positions
+//! into chunks of 2^16 bits, each stored in the container suited to its density, as in
+//! Roaring bitmaps: a chunk with every bit set takes no memory, a dense chunk is a plain
+//! bitmap of 8 KiB, and a sparse chunk lists the offsets of its set bits in 2 bytes
+//! each. Containers are converted as bits are set and cleared; a bitmap becomes an array
+//! only once half of the array limit is reached, so that clearing and setting bits
+//! around the limit does not convert the chunk back and forth.
+
+use super::BitSet;
+
+/// Bits of the offset of a position within its chunk
+const CHUNK_BITS: usize = 16;
+/// Positions per chunk
+const CHUNK_SIZE: usize = 1 << CHUNK_BITS;
+/// Words of a bitmap container
+const CHUNK_WORDS: usize = CHUNK_SIZE / 64;
+/// Maximum set bits of an array container, beyond which a bitmap takes less space
+const ARRAY_LIMIT: usize = CHUNK_SIZE / 16;
+
+/// Set bits of a chunk
+#[derive(Clone)]
+enum Container {
+    Full,                                   // Every bit of the chunk is set
+    Array(Vec<u16>),                        // Offsets of the set bits, in increasing order
+    Bitmap(Box<[u64; CHUNK_WORDS]>, usize), // Bits of the chunk, and number of set bits
+}
+
+/// Bit vector storing each chunk of 2^16 bits in a full, array or bitmap container
+#[derive(Clone)]
+pub struct RoaringBitmap {
+    chunks: Vec<Container>,
+    len: usize,
+}
+
+impl RoaringBitmap {
+    /// Creates a bit vector with `n_bits` set to 1, taking no memory per chunk
+    pub fn with_ones(n_bits: usize) -> Self {
+        RoaringBitmap { chunks: vec![Container::Full; n_bits.div_ceil(CHUNK_SIZE)], len: n_bits }
+    }
+
+    /// Creates a bit vector with `n_bits` set to 0, taking no memory per chunk
+    pub fn with_zeroes(n_bits: usize) -> Self {
+        RoaringBitmap { chunks: vec![Container::Array(Vec::new()); n_bits.div_ceil(CHUNK_SIZE)], len: n_bits }
+    }
+
+    /// Returns the bit at `index`, None past the end of the vector
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index >= self.len {
+            return None;
+        }
+        let offset = index % CHUNK_SIZE;
+        Some(match &self.chunks[index >> CHUNK_BITS] {
+            Container::Full => true,
+            Container::Array(offsets) => offsets.binary_search(&(offset as u16)).is_ok(),
+            Container::Bitmap(words, _) => words[offset >> 6] >> (offset % 64) & 1 == 1,
+        })
+    }
+
+    /// Sets the bit at `index` to `bit`, converting its chunk to the container fitting its new density
+    ///
+    /// Panics if `index` is past the end of the vector.
+    pub fn set(&mut self, index: usize, bit: bool) {
+        assert!(index < self.len, "index {} out of bounds for a bit vector of {} bits", index, self.len);
+        let chunk = index >> CHUNK_BITS;
+        let chunk_len = self.chunk_len(chunk);
+        let offset = index % CHUNK_SIZE;
+        let container = &mut self.chunks[chunk];
+        match container {
+            Container::Full if bit => return,
+            Container::Full => {
+                *container = Container::full_bitmap(chunk_len);
+                container.set_in_place(offset, false);
+            }
+            _ => container.set_in_place(offset, bit),
+        }
+        container.normalize(chunk_len);
+    }
+
+    /// Returns the position of the first set bit after `pos`, if any
+    ///
+    /// Any `pos` is accepted, including positions at or past the end of the vector.
+    #[inline]
+    pub fn next_one(&self, pos: usize) -> Option<usize> {
+        let start = pos.checked_add(1)?;
+        if start >= self.len {
+            return None;
+        }
+        let mut chunk = start >> CHUNK_BITS;
+        let mut offset = start % CHUNK_SIZE;
+        while chunk < self.chunks.len() {
+            if let Some(found) = self.chunks[chunk].next_from(offset, self.chunk_len(chunk)) {
+                return Some((chunk << CHUNK_BITS) + found);
+            }
+            chunk += 1;
+            offset = 0;
+        }
+        None
+    }
+
+    /// Returns the position of the last set bit before `pos`, if any
+    ///
+    /// Any `pos` is accepted: positions past the end of the vector search from its last bit.
+    #[inline]
+    pub fn prev_one(&self, pos: usize) -> Option<usize> {
+        let end = pos.min(self.len);
+        if end == 0 {
+            return None;
+        }
+        let mut chunk = (end - 1) >> CHUNK_BITS;
+        let mut offset = (end - 1) % CHUNK_SIZE;
+        loop {
+            if let Some(found) = self.chunks[chunk].prev_to(offset) {
+                return Some((chunk << CHUNK_BITS) + found);
+            }
+            if chunk == 0 {
+                return None;
+            }
+            chunk -= 1;
+            offset = CHUNK_SIZE - 1;
+        }
+    }
+
+    /// Checks if the vector is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of bits in the vector
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the number of set bits
+    pub fn ones_count(&self) -> usize {
+        self.chunks.iter().enumerate().map(|(chunk, container)| match container {
+            Container::Full => self.chunk_len(chunk),
+            Container::Array(offsets) => offsets.len(),
+            Container::Bitmap(_, n_ones) => *n_ones,
+        }).sum()
+    }
+
+    /// Returns the fraction of set bits, 0 for an empty vector
+    pub fn density(&self) -> f64 {
+        if self.len == 0 {
+            return 0.0;
+        }
+        self.ones_count() as f64 / self.len as f64
+    }
+
+    /// Returns the memory taken by the vector in bytes, containers included
+    pub fn size_bytes(&self) -> usize {
+        let containers = self.chunks.iter().map(|container| match container {
+            Container::Full => 0,
+            Container::Array(offsets) => offsets.capacity() * std::mem::size_of::<u16>(),
+            Container::Bitmap(..) => CHUNK_WORDS * std::mem::size_of::<u64>(),
+        });
+        self.chunks.capacity() * std::mem::size_of::<Container>() + containers.sum::<usize>()
+    }
+
+    /// Returns the number of bits of a chunk (fewer than `CHUNK_SIZE` for a partial last chunk)
+    #[inline(always)]
+    fn chunk_len(&self, chunk: usize) -> usize {
+        (self.len - (chunk << CHUNK_BITS)).min(CHUNK_SIZE)
+    }
+}
+
+impl Container {
+    /// Returns a bitmap container with the first `chunk_len` bits set
+    fn full_bitmap(chunk_len: usize) -> Self {
+        let mut words = Box::new([0u64; CHUNK_WORDS]);
+        words[..chunk_len / 64].fill(u64::MAX);
+        if !chunk_len.is_multiple_of(64) {
+            words[chunk_len / 64] = (1u64 << (chunk_len % 64)) - 1;
+        }
+        Container::Bitmap(words, chunk_len)
+    }
+
+    /// Sets a bit of an array or bitmap container, keeping its kind
+    fn set_in_place(&mut self, offset: usize, bit: bool) {
+        match self {
+            Container::Full => unreachable!("full containers are converted before clearing bits"),
+            Container::Array(offsets) => match (offsets.binary_search(&(offset as u16)), bit) {
+                (Err(position), true) => offsets.insert(position, offset as u16),
+                (Ok(position), false) => {
+                    offsets.remove(position);
+                }
+                _ => {}
+            },
+            Container::Bitmap(words, n_ones) => {
+                let word = &mut words[offset >> 6];
+                let mask = 1u64 << (offset % 64);
+                if (*word & mask != 0) != bit {
+                    *word ^= mask;
+                    if bit { *n_ones += 1 } else { *n_ones -= 1 }
+                }
+            }
+        }
+    }
+
+    /// Converts the container to the kind fitting its number of set bits
+    fn normalize(&mut self, chunk_len: usize) {
+        match self {
+            Container::Full => {}
+            Container::Array(offsets) if offsets.len() == chunk_len => *self = Container::Full,
+            Container::Array(offsets) if offsets.len() > ARRAY_LIMIT => {
+                let mut words = Box::new([0u64; CHUNK_WORDS]);
+                for &offset in offsets.iter() {
+                    words[offset as usize >> 6] |= 1u64 << (offset % 64);
+                }
+                *self = Container::Bitmap(words, offsets.len());
+            }
+            Container::Array(_) => {}
+            Container::Bitmap(_, n_ones) if *n_ones == chunk_len => *self = Container::Full,
+            Container::Bitmap(words, n_ones) if *n_ones <= ARRAY_LIMIT / 2 => {
+                let mut offsets = Vec::with_capacity(*n_ones);
+                for (word_index, &word) in words.iter().enumerate() {
+                    let mut word = word;
+                    while word != 0 {
+                        offsets.push((word_index * 64 + word.trailing_zeros() as usize) as u16);
+                        word &= word - 1;
+                    }
+                }
+                *self = Container::Array(offsets);
+            }
+            Container::Bitmap(..) => {}
+        }
+    }
+
+    /// Returns the offset of the first set bit at or after `offset`, if any
+    #[inline]
+    fn next_from(&self, offset: usize, chunk_len: usize) -> Option<usize> {
+        match self {
+            Container::Full => (offset < chunk_len).then_some(offset),
+            Container::Array(offsets) => {
+                let position = offsets.partition_point(|&o| (o as usize) < offset);
+                offsets.get(position).map(|&o| o as usize)
+            }
+            Container::Bitmap(words, _) => {
+                let mut word_index = offset >> 6;
+                let mut word = words[word_index] & (u64::MAX << (offset % 64));
+                while word == 0 {
+                    word_index += 1;
+                    if word_index == CHUNK_WORDS {
+                        return None;
+                    }
+                    word = words[word_index];
+                }
+                Some(word_index * 64 + word.trailing_zeros() as usize)
+            }
+        }
+    }
+
+    /// Returns the offset of the last set bit at or before `offset`, if any
+    #[inline]
+    fn prev_to(&self, offset: usize) -> Option<usize> {
+        match self {
+            Container::Full => Some(offset),
+            Container::Array(offsets) => {
+                let position = offsets.partition_point(|&o| (o as usize) <= offset);
+                position.checked_sub(1).map(|position| offsets[position] as usize)
+            }
+            Container::Bitmap(words, _) => {
+                let mut word_index = offset >> 6;
+                let mut word = words[word_index] & (u64::MAX >> (63 - offset % 64));
+                while word == 0 {
+                    if word_index == 0 {
+                        return None;
+                    }
+                    word_index -= 1;
+                    word = words[word_index];
+                }
+                Some(word_index * 64 + 63 - word.leading_zeros() as usize)
+            }
+        }
+    }
+}
+
+impl BitSet for RoaringBitmap {
+    fn with_ones(n_bits: usize) -> Self {
+        RoaringBitmap::with_ones(n_bits)
+    }
+
+    #[inline(always)]
+    fn contains(&self, index: usize) -> bool {
+        self.get(index) == Some(true)
+    }
+
+    #[inline(always)]
+    fn set(&mut self, index: usize, bit: bool) {
+        RoaringBitmap::set(self, index, bit)
+    }
+
+    #[inline(always)]
+    fn next_one(&self, pos: usize) -> Option<usize> {
+        RoaringBitmap::next_one(self, pos)
+    }
+
+    #[inline(always)]
+    fn prev_one(&self, pos: usize) -> Option<usize> {
+        RoaringBitmap::prev_one(self, pos)
+    }
+
+    fn ones_count(&self) -> usize {
+        RoaringBitmap::ones_count(self)
+    }
+
+    fn density(&self) -> f64 {
+        RoaringBitmap::density(self)
+    }
+
+    fn size_bytes(&self) -> usize {
+        RoaringBitmap::size_bytes(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bit_vector::BitVector;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// Checks that `get`, `next_one` and `prev_one` agree with a plain `BitVector` at every position
+    fn assert_same_bits(roaring: &RoaringBitmap, bv: &BitVector) {
+        assert_eq!(roaring.len(), bv.len());
+        assert_eq!(roaring.ones_count(), (0..bv.len()).filter(|&i| bv.get(i) == Some(true)).count());
+        for pos in 0..=bv.len() + 1 {
+            assert_eq!(roaring.get(pos), bv.get(pos), "get({})", pos);
+            assert_eq!(roaring.next_one(pos), bv.next_one(pos), "next_one({})", pos);
+            assert_eq!(roaring.prev_one(pos), bv.prev_one(pos), "prev_one({})", pos);
+        }
+    }
+
+    #[test]
+    fn queries_agree_with_bit_vector() {
+        // A full chunk, a chunk thinned to an array, a dense bitmap chunk, and a partial last chunk
+        let n_bits = 3 * CHUNK_SIZE + 1000;
+        let mut roaring = RoaringBitmap::with_ones(n_bits);
+        let mut bv = BitVector::with_ones(n_bits);
+        let mut rng = StdRng::seed_from_u64(1167);
+        fn set(roaring: &mut RoaringBitmap, bv: &mut BitVector, index: usize, bit: bool) {
+            roaring.set(index, bit);
+            bv.set(index, bit);
+        }
+
+        for index in CHUNK_SIZE..2 * CHUNK_SIZE {
+            if rng.gen_ratio(99, 100) {
+                set(&mut roaring, &mut bv, index, false);
+            }
+        }
+        for index in 2 * CHUNK_SIZE..n_bits {
+            if rng.gen_ratio(1, 3) {
+                set(&mut roaring, &mut bv, index, false);
+            }
+        }
+        assert_same_bits(&roaring, &bv);
+
+        // Set bits back, around the array limit and up to a full chunk again
+        for index in CHUNK_SIZE..2 * CHUNK_SIZE {
+            if rng.gen_ratio(1, 10) {
+                set(&mut roaring, &mut bv, index, true);
+            }
+        }
+        for index in 3 * CHUNK_SIZE..n_bits {
+            set(&mut roaring, &mut bv, index, true);
+        }
+        assert_same_bits(&roaring, &bv);
+    }
+
+    #[test]
+    fn queries_agree_with_bit_vector_when_empty() {
+        let n_bits = CHUNK_SIZE + 1;
+        let mut roaring = RoaringBitmap::with_ones(n_bits);
+        let mut bv = BitVector::with_ones(n_bits);
+        for index in 0..n_bits {
+            roaring.set(index, false);
+            bv.set(index, false);
+        }
+        assert_same_bits(&roaring, &bv);
+        assert_same_bits(&RoaringBitmap::with_zeroes(0), &BitVector::with_zeroes(0));
+    }
+}
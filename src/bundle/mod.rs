@@ -0,0 +1,179 @@
+//! Self-contained experiment bundles
+//!
+//! A benchmark run depends on more than the dataset and the algorithm: the random access
+//! queries, the seed they were drawn from and the options of the run all shape the
+//! results. A bundle is a directory packaging all of them with the results, so that an
+//! experiment can be shared and run again exactly, by other researchers or on other
+//! machines:
+//! - `bundle.json`: the manifest (see `BundleManifest`), referencing the dataset by path
+//!   and by a hash of its contents, so that a copy of the dataset elsewhere can be used
+//!   and checked
+//! - `queries.bin`: the queries, drawn once when the bundle is created (see
+//!   `write_query_file`) and issued unchanged by every run
+//! - `results.jsonl`: the results of every run of the bundle, appended as JSON Lines
+//!
+//! Bundles are plain directories: archive them with `tar` or `zip` to share them. The
+//! `bundle` binary creates and runs them.
+
+use crate::benchmark_utils::{is_record_dataset, load_dataset, load_query_file, load_record_dataset, write_query_file};
+use crate::dataset_stats::dataset_hash;
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use xxhash_rust::xxh3::Xxh3;
+
+/// Version of the manifest format, bumped whenever its fields change
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+/// Manifest file of a bundle
+pub const MANIFEST_FILE: &str = "bundle.json";
+/// Query file of a bundle
+pub const QUERIES_FILE: &str = "queries.bin";
+/// Results file of a bundle
+pub const RESULTS_FILE: &str = "results.jsonl";
+
+/// Dataset of a bundle, identified by its contents
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DatasetReference {
+    pub path: String,       // Path of the dataset when the bundle was created
+    pub n_items: usize,     // Strings (or records) of the dataset, the range of the queries
+    pub data_size: usize,   // Total size of the strings in bytes
+    pub hash: u64,          // Hash of the strings and their boundaries (see `dataset_hash`)
+}
+
+impl DatasetReference {
+    /// Loads a dataset and computes its reference
+    ///
+    /// Record datasets are hashed column by column, in column order.
+    pub fn of(path: &Path) -> Self {
+        let (n_items, data_size, hash) = if is_record_dataset(path) {
+            let records = load_record_dataset(path);
+            let mut hasher = Xxh3::new();
+            for (data, end_positions) in records.columns.iter() {
+                hasher.update(&dataset_hash(data, end_positions).to_le_bytes());
+            }
+            (records.n_records(), records.data_size(), hasher.digest())
+        } else {
+            let (data, end_positions) = load_dataset(path);
+            (end_positions.len() - 1, data.len(), dataset_hash(&data, &end_positions))
+        };
+        DatasetReference { path: path.display().to_string(), n_items, data_size, hash }
+    }
+
+    /// Checks that a dataset matches the reference
+    ///
+    /// # Returns
+    /// A description of the first mismatch, if any
+    pub fn check(&self, path: &Path) -> Result<(), String> {
+        let found = DatasetReference::of(path);
+        if found.n_items != self.n_items || found.data_size != self.data_size {
+            return Err(format!(
+                "{} holds {} items of {} bytes, the bundle expects {} items of {} bytes",
+                path.display(), found.n_items, found.data_size, self.n_items, self.data_size
+            ));
+        }
+        if found.hash != self.hash {
+            return Err(format!("{} has different contents than the dataset of the bundle", path.display()));
+        }
+        Ok(())
+    }
+}
+
+/// Manifest of a bundle: everything needed to run its experiment again
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BundleManifest {
+    pub format_version: u32,
+    pub created_at: f64,            // Unix time of the creation of the bundle, in seconds
+    pub crate_version: String,      // Version of the benchmark suite creating the bundle
+    pub dataset: DatasetReference,
+    pub compressor: String,         // Algorithm identifier (see `registry`), possibly wrapped
+    pub options: Vec<String>,       // Options of `benchmark_individual`, as given on the command line
+    pub seed: u64,                  // Seed the queries were drawn from (see `generate_seeded_queries`)
+    pub n_queries: usize,
+}
+
+/// Experiment bundle stored in a directory
+pub struct ExperimentBundle {
+    pub directory: PathBuf,
+    pub manifest: BundleManifest,
+}
+
+impl ExperimentBundle {
+    /// Creates a bundle, writing its manifest and queries
+    ///
+    /// # Arguments
+    /// - `directory`: Directory of the bundle, created if needed; must not hold a bundle yet
+    /// - `dataset`: Reference of the dataset
+    /// - `compressor`: Algorithm identifier
+    /// - `options`: Options of `benchmark_individual`
+    /// - `seed`: Seed the queries were drawn from
+    /// - `queries`: Random access queries, issued by every run
+    ///
+    /// # Returns
+    /// The bundle, or a description of why it cannot be written
+    pub fn create(
+        directory: &Path,
+        dataset: DatasetReference,
+        compressor: &str,
+        options: Vec<String>,
+        seed: u64,
+        queries: &[usize]
+    ) -> Result<Self, String> {
+        if directory.join(MANIFEST_FILE).exists() {
+            return Err(format!("{} already holds a bundle", directory.display()));
+        }
+        fs::create_dir_all(directory).map_err(|e| format!("Failed to create {}: {}", directory.display(), e))?;
+
+        let manifest = BundleManifest {
+            format_version: BUNDLE_FORMAT_VERSION,
+            created_at: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0.0, |elapsed| elapsed.as_secs_f64()),
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            dataset,
+            compressor: compressor.to_string(),
+            options,
+            seed,
+            n_queries: queries.len(),
+        };
+        let bundle = ExperimentBundle { directory: directory.to_path_buf(), manifest };
+        write_query_file(&bundle.queries_path(), queries)
+            .map_err(|e| format!("Failed to write {}: {}", bundle.queries_path().display(), e))?;
+        let content = serde_json::to_vec_pretty(&bundle.manifest).expect("Failed to serialize bundle manifest");
+        fs::write(directory.join(MANIFEST_FILE), content).map_err(|e| format!("Failed to write bundle manifest: {}", e))?;
+        Ok(bundle)
+    }
+
+    /// Opens a bundle, checking its manifest and queries
+    ///
+    /// # Returns
+    /// The bundle, or a description of why it cannot be read
+    pub fn open(directory: &Path) -> Result<Self, String> {
+        let manifest_path = directory.join(MANIFEST_FILE);
+        let content = fs::read_to_string(&manifest_path).map_err(|e| format!("Failed to read {}: {}", manifest_path.display(), e))?;
+        let manifest: BundleManifest = serde_json::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", manifest_path.display(), e))?;
+        if manifest.format_version != BUNDLE_FORMAT_VERSION {
+            return Err(format!("Unsupported bundle format version {} (supported: {})", manifest.format_version, BUNDLE_FORMAT_VERSION));
+        }
+
+        let bundle = ExperimentBundle { directory: directory.to_path_buf(), manifest };
+        let n_queries = bundle.queries()?.len();
+        if n_queries != bundle.manifest.n_queries {
+            return Err(format!("{} holds {} queries, the manifest expects {}", QUERIES_FILE, n_queries, bundle.manifest.n_queries));
+        }
+        Ok(bundle)
+    }
+
+    /// Reads the queries of the bundle
+    pub fn queries(&self) -> Result<Vec<usize>, String> {
+        load_query_file(&self.queries_path(), self.manifest.dataset.n_items)
+    }
+
+    /// Returns the path of the query file
+    pub fn queries_path(&self) -> PathBuf {
+        self.directory.join(QUERIES_FILE)
+    }
+
+    /// Returns the path of the results file
+    pub fn results_path(&self) -> PathBuf {
+        self.directory.join(RESULTS_FILE)
+    }
+}
@@ -0,0 +1,627 @@
+//! Comprehensive benchmark for string compression algorithms
+//! 
+//! Entry point of the `benchmark_all` binary and of `cbench bench`, orchestrating
+//! systematic evaluation of compression algorithms for string collections with
+//! random access requirements. The benchmark suite measures:
+//! - Compression ratio and throughput
+//! - Decompression throughput  
+//! - Random access latency
+//!
+//! Each algorithm is evaluated repeatedly until the coefficient of variation of every
+//! metric drops below TARGET_CV, between MIN_ITERATIONS and MAX_ITERATIONS runs.
+//! Individual runs are persisted in JSON format (or appended as JSON Lines with `--jsonl`,
+//! for long campaigns), together with a summary reporting
+//! mean, standard deviation and 95% confidence interval of each metric. Compressors
+//! are then ranked on each dataset and overall by a score normalized against a baseline
+//! (`--baseline=NAME`, default `Raw`; see `ranking`).
+//! With `--warmup=N`, every iteration also reports first-run measurements, and
+//! `--queries=P` and `--cover-all` set the random access workload, `--streaming`
+//! measures decompression with bounded memory, `--batch=N` also measures random
+//! access in batches of N queries, `--scan=N` measures scans of N consecutive
+//! strings, `--profile-stages` splits random access latency
+//! by stage, `--zero-copy` measures random access without copying and
+//! `--perf-counters` counts hardware events of random access and `--count-allocations`
+//! counts allocations of random access, `--plain-baselines` measures uncompressed stores on
+//! the same queries, `--latency-heatmap` bins random access latency by string length
+//! and block position, and `--holdout=F` reports the compression of
+//! strings held out of dictionary training, and `--verify=L` selects the queries checked
+//! against the dataset and `--timer=T` the clock of the timings (see `benchmark_individual`).
+//! Runs can be pinned to a list of cores (e.g., `0-3,8`), and `--numa-bind` also binds
+//! their memory to the NUMA nodes of these cores; the placement is recorded in the results.
+//! With `--phase-timeout=S`, a run with a phase longer than S seconds is cancelled: a
+//! timed-out status row is recorded instead of its measurements, and the evaluation
+//! continues with the next combination. Processes that cannot cancel themselves (e.g.,
+//! stuck in a unit of work of compression, see `incremental`) are killed after
+//! KILL_TIMEOUT_PHASES times the timeout.
+//!
+//! By default, every iteration runs in a separate `benchmark_individual` process.
+//! With `--jobs=N`, datasets are instead benchmarked in-process on N parallel threads,
+//! one dataset per thread at a time, reducing the total wall time at the cost of
+//! measurements sharing the machine. Record datasets always use separate processes.
+//!
+//! By default, all iterations of a compressor on a dataset run before the next
+//! combination. With `--interleave`, iterations follow a round-robin schedule across
+//! compressors and datasets (across compressors only within a `--jobs` worker), so that
+//! thermal throttling builds up evenly instead of penalizing the combinations measured
+//! last. Every run records its start and end time and temperature, and the temperature
+//! range over the evaluation is logged.
+//!
+//! Runs disturbed by interference are flagged as outliers once a combination has
+//! MIN_OUTLIER_ITERATIONS iterations: a run whose compression speed, decompression speed
+//! or random access time deviates from the median of its iterations by more than
+//! `--outliers=X` percent (default 20) is marked in the results file with the deviating
+//! metrics, and excluded from the summary and from the convergence check (see
+//! `flag_outliers`). With `--rerun-outliers`, flagged runs also do not count toward
+//! MIN_ITERATIONS, so that each one is replaced by another iteration, up to MAX_ITERATIONS.
+
+use crate::benchmark_utils::*;
+use crate::compressor::registry::{self, AnyCompressor};
+use crate::dataset_stats;
+use crate::logging;
+use crate::ranking::{self, DEFAULT_BASELINE};
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+/// Compression algorithms under evaluation
+const COMPRESSORS: [&str; 6] = ["raw", "raw_mmap", "raw_direct", "onpair", "onpair16", "bpe"];
+/// Output file for aggregated benchmark results
+const OUTPUT_FILE: &str = "benchmark_results.json";
+/// Output file for aggregated benchmark results, as JSON Lines (`--jsonl`)
+const OUTPUT_FILE_JSONL: &str = "benchmark_results.jsonl";
+/// Output file for per-metric statistics across iterations
+const SUMMARY_FILE: &str = "benchmark_summary.json";
+/// Output file for per-dataset rankings and the overall leaderboard
+const RANKING_FILE: &str = "benchmark_ranking.json";
+/// Minimum number of iterations per algorithm-dataset combination
+const MIN_ITERATIONS: usize = 3;
+/// Maximum number of iterations per algorithm-dataset combination
+const MAX_ITERATIONS: usize = 15;
+/// Target coefficient of variation at which iterations stop
+const TARGET_CV: f64 = 0.02;
+/// Phase timeouts after which a benchmark process is killed: one per phase (compression,
+/// decompression, random, batch, profiled, zero-copy and plain store access, hardware counters,
+/// holdout evaluation), plus one for loading the dataset
+const KILL_TIMEOUT_PHASES: u32 = 10;
+/// Interval between checks of a running benchmark process
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Main benchmark orchestrator
+/// 
+/// Executes comprehensive evaluation of compression algorithms across all datasets
+/// in the specified directory. For each dataset-algorithm pair, performs independent
+/// measurements until they stabilize, to ensure statistical significance. Exits the
+/// process on invalid arguments.
+///
+/// # Arguments
+/// - `args`: Command-line arguments, starting with the program name shown in the usage
+/// - `individual_benchmark`: Returns the command running an individual benchmark (see
+///   `benchmark_individual::run`), to which its arguments are appended
+pub fn run(mut args: Vec<String>, individual_benchmark: fn() -> Command) {
+    // Logging and workload options are forwarded to the individual benchmarks
+    let forwarded_options: Vec<String> = args
+        .iter()
+        .filter(|arg| arg.starts_with("--log-") || arg.starts_with("--queries=") || *arg == "--cover-all" || *arg == "--streaming" || arg.starts_with("--batch=") || arg.starts_with("--scan=") || *arg == "--profile-stages" || *arg == "--zero-copy" || *arg == "--perf-counters" || *arg == "--count-allocations" || *arg == "--plain-baselines" || *arg == "--latency-heatmap" || arg.starts_with("--holdout=") || arg.starts_with("--verify=") || arg.starts_with("--timer=") || *arg == "--numa-bind" || arg.starts_with("--phase-timeout="))
+        .cloned()
+        .collect();
+    logging::init_from_args(&mut args).unwrap_or_else(|message| {
+        eprintln!("Error: {}", message);
+        std::process::exit(1);
+    });
+    let warmup_iterations = take_option(&mut args, "--warmup").map(|value| {
+        value.parse::<usize>().unwrap_or_else(|_| {
+            error!("Invalid warm-up iterations '{}'. Must be a valid number.", value);
+            std::process::exit(1);
+        })
+    });
+    let query_policy = take_option(&mut args, "--queries").map_or(QueryPolicy::default(), |value| {
+        QueryPolicy::parse(&value).unwrap_or_else(|| {
+            error!("Invalid query policy '{}'. Must be N, coverage:F or time:S.", value);
+            std::process::exit(1);
+        })
+    });
+    let cover_all_items = take_flag(&mut args, "--cover-all");
+    let streaming = take_flag(&mut args, "--streaming");
+    let batch_size = take_option(&mut args, "--batch").map(|value| {
+        value.parse::<usize>().ok().filter(|batch_size| *batch_size > 0).unwrap_or_else(|| {
+            error!("Invalid batch size '{}'. Must be a positive number.", value);
+            std::process::exit(1);
+        })
+    });
+    let scan_length = take_option(&mut args, "--scan").map(|value| {
+        value.parse::<usize>().ok().filter(|scan_length| *scan_length > 0).unwrap_or_else(|| {
+            error!("Invalid scan length '{}'. Must be a positive number.", value);
+            std::process::exit(1);
+        })
+    });
+    let profile_stages = take_flag(&mut args, "--profile-stages");
+    let zero_copy = take_flag(&mut args, "--zero-copy");
+    let perf_counters = take_flag(&mut args, "--perf-counters");
+    let count_allocations = take_flag(&mut args, "--count-allocations");
+    let plain_baselines = take_flag(&mut args, "--plain-baselines");
+    let latency_heatmap = take_flag(&mut args, "--latency-heatmap");
+    let holdout_fraction = take_option(&mut args, "--holdout").map(|value| {
+        value.parse::<f64>().ok().filter(|fraction| *fraction > 0.0 && *fraction < 1.0).unwrap_or_else(|| {
+            error!("Invalid holdout fraction '{}'. Must be a number in (0, 1).", value);
+            std::process::exit(1);
+        })
+    });
+    let verification = take_option(&mut args, "--verify").map_or(Verification::default(), |value| {
+        Verification::parse(&value).unwrap_or_else(|| {
+            error!("Invalid verification level '{}'. Must be none, sampled or full.", value);
+            std::process::exit(1);
+        })
+    });
+    let timer = take_option(&mut args, "--timer").map_or(TimerSource::default(), |value| {
+        let source = TimerSource::parse(&value).unwrap_or_else(|| {
+            error!("Invalid timer '{}'. Must be instant, monotonic_raw or tsc.", value);
+            std::process::exit(1);
+        });
+        if let Err(message) = Clock::of(source) {
+            error!("{}.", message);
+            std::process::exit(1);
+        }
+        source
+    });
+    let numa_bind = take_flag(&mut args, "--numa-bind");
+    let interleave = take_flag(&mut args, "--interleave");
+    let outlier_tolerance = take_option(&mut args, "--outliers").map_or(DEFAULT_OUTLIER_TOLERANCE, |value| {
+        value.parse::<f64>().ok().filter(|percent| *percent > 0.0).map(|percent| percent / 100.0).unwrap_or_else(|| {
+            error!("Invalid outlier tolerance '{}'. Must be a positive percentage.", value);
+            std::process::exit(1);
+        })
+    });
+    let outliers = Outliers { tolerance: outlier_tolerance, rerun: take_flag(&mut args, "--rerun-outliers") };
+    let output_file = if take_flag(&mut args, "--jsonl") { OUTPUT_FILE_JSONL } else { OUTPUT_FILE };
+    let phase_timeout = take_option(&mut args, "--phase-timeout").map(|value| {
+        value.parse::<f64>().ok().filter(|seconds| *seconds > 0.0).map(Duration::from_secs_f64).unwrap_or_else(|| {
+            error!("Invalid phase timeout '{}'. Must be a positive number of seconds.", value);
+            std::process::exit(1);
+        })
+    });
+    let baseline = take_option(&mut args, "--baseline").unwrap_or_else(|| DEFAULT_BASELINE.to_string());
+    let jobs = take_option(&mut args, "--jobs").map_or(1, |value| {
+        value.parse::<usize>().ok().filter(|jobs| *jobs > 0).unwrap_or_else(|| {
+            error!("Invalid number of jobs '{}'. Must be a positive number.", value);
+            std::process::exit(1);
+        })
+    });
+
+    // Validate command-line interface
+    if args.len() < 2 {
+        eprintln!("Usage: {} <directory> [cores] [--warmup=N] [--queries=P] [--cover-all] [--streaming] [--batch=N] [--scan=N] [--profile-stages] [--zero-copy] [--perf-counters] [--count-allocations] [--plain-baselines] [--latency-heatmap] [--holdout=F] [--verify=L] [--timer=T] [--numa-bind] [--phase-timeout=S] [--baseline=NAME] [--jobs=N] [--interleave] [--outliers=X] [--rerun-outliers] [--jsonl] [--log-level=L] [--log-format=F]", args[0]);
+        eprintln!("  <directory>       - Directory containing JSON or binary (.bin) dataset files, possibly compressed (.gz, .zst, .xz)");
+        eprintln!("  [cores]           - Optional CPU cores for pinning, e.g. 2 or 0-3,8 (with --jobs, one per worker, or the first of N consecutive cores)");
+        eprintln!("  --warmup=N        - Unmeasured runs of decompression and random access (default: 0)");
+        eprintln!("  --queries=P       - N queries, coverage:F (F queries per item) or time:S (S seconds) (default: {})", DEFAULT_N_QUERIES);
+        eprintln!("  --cover-all       - Query every item at least once");
+        eprintln!("  --streaming       - Streaming decompression with bounded memory");
+        eprintln!("  --batch=N         - Also measure random access in batches of N queries");
+        eprintln!("  --scan=N          - Also measure scans of N consecutive strings projecting a prefix");
+        eprintln!("  --profile-stages  - Also split random access latency into lookup, decode and copy");
+        eprintln!("  --zero-copy       - Also measure random access without copying, where supported");
+        eprintln!("  --perf-counters   - Also count hardware events of random access (Linux only)");
+        eprintln!("  --count-allocations - Count allocations of the measured random access run");
+        eprintln!("  --plain-baselines - Also measure random access to uncompressed Vec and FxHashMap stores");
+        eprintln!("  --latency-heatmap - Also bin random access latency by string length and position in block");
+        eprintln!("  --holdout=F       - Also train dictionaries without a fraction F of the strings and compress them");
+        eprintln!("  --verify=L        - Queries checked against the dataset: none | sampled (1%) | full (default: sampled)");
+        eprintln!("  --timer=T         - Clock of the timings: instant | monotonic_raw (Linux) | tsc (x86_64) (default: instant)");
+        eprintln!("  --numa-bind       - Bind memory to the NUMA nodes of the pinned cores (Linux only)");
+        eprintln!("  --phase-timeout=S - Cancel a run if a phase takes longer than S seconds (default: unlimited)");
+        eprintln!("  --baseline=NAME   - Compressor against which rankings are normalized (default: {})", DEFAULT_BASELINE);
+        eprintln!("  --jobs=N          - Datasets benchmarked in parallel, in-process (default: 1, one process per run)");
+        eprintln!("  --interleave      - Round-robin iterations across compressors and datasets, spreading thermal drift");
+        eprintln!("  --outliers=X      - Flag runs deviating more than X% from the median of their iterations (default: {})", DEFAULT_OUTLIER_TOLERANCE * 100.0);
+        eprintln!("  --rerun-outliers  - Replace runs flagged as outliers with extra iterations");
+        eprintln!("  --jsonl           - Append runs to {} instead of rewriting {}", OUTPUT_FILE_JSONL, OUTPUT_FILE);
+        eprintln!("  --log-level=L     - trace | debug | info | warn | error (default: info)");
+        eprintln!("  --log-format=F    - text | json (default: text)");
+        std::process::exit(1);
+    }
+
+    let directory = &args[1];
+    // Optional CPU core affinity for consistent performance measurements
+    let cores = if args.len() > 2 {
+        Some(parse_core_list(&args[2]).unwrap_or_else(|| {
+            error!("Invalid cores '{}'. Must be a core ID or a list such as 0-3,8.", args[2]);
+            std::process::exit(1);
+        }))
+    } else {
+        None
+    };
+    if let Some(cores) = cores.as_ref().filter(|cores| jobs > 1 && cores.len() > 1 && cores.len() < jobs) {
+        error!("{} cores given for {} jobs. Must give one core per job, or a single first core.", cores.len(), jobs);
+        std::process::exit(1);
+    }
+    if numa_bind && cores.is_none() {
+        warn!("--numa-bind requires pinned cores, ignoring it");
+    }
+
+    // Validate dataset directory
+    let dir = Path::new(directory);
+    if !dir.exists() || !dir.is_dir() {
+        error!("{} is not a valid directory.", directory);
+        std::process::exit(1);
+    }
+
+    // Initialize clean results file for this benchmark run
+    if Path::new(output_file).exists() {
+        fs::remove_file(output_file).expect("Failed to remove existing results file");
+    }
+
+    // Collect JSON and binary dataset files, compressed or not, skipping cached statistics
+    let datasets: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_file() && is_dataset_file(path))
+        .filter(|path| !dataset_stats::is_sidecar(path))
+        .collect();
+
+    // Systematic evaluation across all datasets and compression algorithms
+    let (parallel_datasets, process_datasets): (Vec<PathBuf>, Vec<PathBuf>) =
+        datasets.into_iter().partition(|path| jobs > 1 && !is_record_dataset(path));
+    let workload = Workload { query_policy, cover_all_items, streaming, batch_size, scan_length, profile_stages, zero_copy, perf_counters, count_allocations, plain_baselines, latency_heatmap, holdout_fraction, verification, timer, phase_timeout, warmup_iterations: warmup_iterations.unwrap_or(0), interleave, outliers };
+    if !process_datasets.is_empty() {
+        benchmark_in_processes(&process_datasets, output_file, cores.as_deref(), &workload, &forwarded_options, individual_benchmark);
+    }
+    if !parallel_datasets.is_empty() {
+        let mut results = read_benchmark_results(output_file);
+        results.extend(benchmark_in_threads(&parallel_datasets, jobs, cores.as_deref(), numa_bind, &workload));
+        write_benchmark_results(&results, Path::new(output_file));
+    }
+
+    // Flag the outliers of every combination in the results file
+    let mut results = read_benchmark_results(output_file);
+    let n_outliers = flag_outliers(&mut results, outliers.tolerance);
+    if n_outliers > 0 {
+        warn!(n_outliers, tolerance = outliers.tolerance, "Runs flagged as outliers, excluded from the summary");
+        write_benchmark_results(&results, Path::new(output_file));
+    }
+
+    // Generate comprehensive benchmark report
+    log_temperature_range(&results);
+    let summaries = summarize_benchmark_results(&results);
+    let ranking = ranking::rank_summaries(&summaries, &baseline);
+    write_benchmark_summary(&summaries, Path::new(SUMMARY_FILE));
+    ranking::write_ranking_report(&ranking, Path::new(RANKING_FILE));
+    print_benchmark_results(&results);
+    ranking::print_ranking_report(&ranking);
+}
+/// Benchmarks every compressor on datasets, one `benchmark_individual` process per iteration
+/// 
+/// # Arguments
+/// - `dataset_paths`: Paths to the dataset files
+/// - `output_file`: Results file the individual benchmarks append to
+/// - `cores`: Optional CPU cores for pinning (NUMA binding is among the forwarded options)
+/// - `workload`: Workload of the iterations: warm-up runs and phase timeout (also bounding
+///   the lifetime of the processes) are forwarded, the schedule and outlier detection apply here
+/// - `forwarded_options`: Logging and workload options forwarded to the individual benchmarks
+/// - `individual_benchmark`: Returns the command running an individual benchmark
+fn benchmark_in_processes(
+    dataset_paths: &[PathBuf],
+    output_file: &str,
+    cores: Option<&[usize]>,
+    workload: &Workload,
+    forwarded_options: &[String],
+    individual_benchmark: fn() -> Command
+) {
+    // Every available compressor on every dataset, dataset by dataset
+    let mut combinations = Vec::new();
+    for path in dataset_paths {
+        for &compressor in COMPRESSORS.iter() {
+            if !registry::lookup(compressor).is_some_and(|entry| entry.available) {
+                warn!(compressor, reason = %registry::unavailable_message(compressor), "Skipping compressor");
+                continue;
+            }
+            combinations.push((path.to_str().unwrap(), compressor));
+        }
+    }
+    // Results of the iterations of each combination
+    let mut combination_results: Vec<Vec<BenchmarkResult>> = vec![Vec::new(); combinations.len()];
+
+    run_schedule(combinations.len(), workload.interleave, |combination, n_iterations| {
+        let (dataset_path, compressor) = combinations[combination];
+        if n_iterations == 0 {
+            info!(dataset = dataset_path, compressor, "Benchmarking compressor");
+        }
+        let first_result = read_benchmark_results(output_file).len();
+
+        // Execute individual benchmark with specified parameters
+        let mut cmd = individual_benchmark();
+        cmd.arg(dataset_path)
+           .arg(compressor)
+           .arg(output_file);
+        
+        // Apply CPU core affinity if specified
+        if let Some(cores) = cores {
+            cmd.arg(format_core_list(cores));
+        }
+        if workload.warmup_iterations > 0 {
+            cmd.arg(format!("--warmup={}", workload.warmup_iterations));
+        }
+        cmd.args(forwarded_options);
+        
+        let child = cmd.spawn().expect("Failed to execute benchmark");
+        let status = wait_with_timeout(child, workload.phase_timeout.map(|timeout| timeout * KILL_TIMEOUT_PHASES));
+
+        let Some(status) = status else {
+            error!(dataset = dataset_path, compressor, "Benchmark process timed out and was killed");
+            let dataset_name = Path::new(dataset_path).file_name().unwrap().to_str().unwrap().to_string();
+            let compressor_name = AnyCompressor::create(compressor, 0, 0).map_or(compressor.to_string(), |c| c.name().to_string());
+            append_benchmark_result(&BenchmarkResult::timed_out(dataset_name, compressor_name, "process"), Path::new(output_file));
+            return false;
+        };
+        if !status.success() {
+            error!(dataset = dataset_path, compressor, "Benchmark failed");
+            return false;
+        }
+
+        // A timed-out run would time out again: move on to the next combination
+        let results = read_benchmark_results(output_file);
+        let new_results = results.get(first_result..).unwrap_or(&[]);
+        if new_results.iter().any(|result| result.timed_out.is_some()) {
+            return false;
+        }
+
+        let results = &mut combination_results[combination];
+        results.extend_from_slice(new_results);
+        let converged = is_complete(results, workload.outliers);
+        if converged || n_iterations + 1 == MAX_ITERATIONS {
+            let n_outliers = results.iter().filter(|result| result.outlier.is_some()).count();
+            info!(dataset = dataset_path, compressor, n_iterations = n_iterations + 1, n_outliers, "Measurements completed");
+        }
+        !converged
+    });
+}
+
+/// Runs iterations of benchmark combinations, up to MAX_ITERATIONS each
+/// 
+/// By default, all iterations of a combination run before those of the next one. With
+/// interleaving, iterations follow a round-robin schedule (one iteration of every
+/// combination, then the next round), so that slow drifts of the machine (e.g.,
+/// thermal throttling as it heats up) spread evenly across combinations instead of
+/// penalizing those measured last.
+/// 
+/// # Arguments
+/// - `n_combinations`: Number of combinations, identified by their index
+/// - `interleave`: Whether iterations follow a round-robin schedule
+/// - `run_iteration`: Runs an iteration of a combination, given the number of its previous
+///   iterations; returns whether the combination needs more iterations
+fn run_schedule(n_combinations: usize, interleave: bool, mut run_iteration: impl FnMut(usize, usize) -> bool) {
+    if interleave {
+        let mut pending: Vec<usize> = (0..n_combinations).collect();
+        for n_iterations in 0..MAX_ITERATIONS {
+            pending.retain(|&combination| run_iteration(combination, n_iterations));
+        }
+    } else {
+        for combination in 0..n_combinations {
+            for n_iterations in 0..MAX_ITERATIONS {
+                if !run_iteration(combination, n_iterations) {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Outlier detection across the iterations of a combination (see `flag_outliers`)
+#[derive(Clone, Copy)]
+struct Outliers {
+    tolerance: f64, // Relative deviation from the median flagging a run
+    rerun: bool,    // Whether flagged runs are replaced by extra iterations
+}
+
+/// Flags the outliers among the iterations of a combination and checks whether the others have converged
+///
+/// Flagged runs are excluded from the convergence check; when re-run, they do not count
+/// toward MIN_ITERATIONS either, so that each one calls for another iteration.
+///
+/// # Arguments
+/// - `results`: Results of the iterations of the combination performed so far
+/// - `outliers`: Outlier detection policy
+fn is_complete(results: &mut [BenchmarkResult], outliers: Outliers) -> bool {
+    let n_flagged = flag_outliers(results, outliers.tolerance);
+    let n_counted = if outliers.rerun { results.len() - n_flagged } else { results.len() };
+    n_counted >= MIN_ITERATIONS && is_converged(results, TARGET_CV)
+}
+
+/// Workload of the iterations, in-process or forwarded to the individual benchmarks
+struct Workload {
+    query_policy: QueryPolicy,
+    cover_all_items: bool,
+    streaming: bool,
+    batch_size: Option<usize>,
+    scan_length: Option<usize>,
+    profile_stages: bool,
+    zero_copy: bool,
+    perf_counters: bool,
+    count_allocations: bool,
+    plain_baselines: bool,
+    latency_heatmap: bool,
+    holdout_fraction: Option<f64>,
+    verification: Verification,
+    timer: TimerSource,
+    phase_timeout: Option<Duration>,
+    warmup_iterations: usize,
+    interleave: bool,
+    outliers: Outliers,
+}
+
+/// Benchmarks datasets on parallel threads within this process
+/// 
+/// Workers take datasets from a shared queue, so that at most `jobs` datasets are
+/// loaded at a time. Results are collected in memory and returned once all workers
+/// have finished, leaving the results file to a single writer.
+/// 
+/// # Arguments
+/// - `datasets`: Paths to the dataset files (strings only, not records)
+/// - `jobs`: Number of worker threads
+/// - `cores`: Optional CPU cores, one per worker, or the core of the first worker if the
+///   others use the following cores
+/// - `numa_bind`: Whether each worker binds its memory to the NUMA node of its core
+/// - `workload`: Query policy and warm-up runs of every iteration
+fn benchmark_in_threads(datasets: &[PathBuf], jobs: usize, cores: Option<&[usize]>, numa_bind: bool, workload: &Workload) -> Vec<BenchmarkResult> {
+    let next_dataset = AtomicUsize::new(0);
+
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs.min(datasets.len()))
+            .map(|worker| {
+                let next_dataset = &next_dataset;
+                scope.spawn(move || {
+                    let core = cores.map(|cores| if cores.len() == 1 { cores[0] + worker } else { cores[worker] });
+                    let placement = core.and_then(|core| apply_placement(&[core], numa_bind));
+
+                    let mut results = Vec::new();
+                    while let Some(path) = datasets.get(next_dataset.fetch_add(1, Ordering::Relaxed)) {
+                        results.extend(benchmark_dataset(path, workload, placement.as_ref()));
+                    }
+                    results
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("Benchmark worker panicked"))
+            .collect()
+    })
+}
+
+/// Benchmarks every compressor on a dataset within the calling thread
+/// 
+/// Follows the same iteration policy as the process-based mode: every iteration uses
+/// a fresh compressor instance and fresh random queries. A failed iteration (e.g., a
+/// data mismatch) stops the iterations of that compressor only.
+/// 
+/// # Arguments
+/// - `path`: Path to the dataset file
+/// - `workload`: Query policy and warm-up runs of every iteration
+/// - `placement`: CPU core and NUMA node of the calling thread, recorded in the results
+fn benchmark_dataset(path: &Path, workload: &Workload, placement: Option<&Placement>) -> Vec<BenchmarkResult> {
+    info!(dataset = %path.display(), "Processing dataset");
+    let dataset_name = path.file_name().unwrap().to_str().unwrap().to_string();
+    let (data, end_positions) = load_dataset(path);
+    let n_elements = end_positions.len() - 1;
+    let options = BenchmarkOptions {
+        warmup_iterations: workload.warmup_iterations,
+        time_budget: workload.query_policy.time_budget(),
+        min_queries: if workload.cover_all_items { n_elements } else { 0 },
+        streaming: workload.streaming,
+        batch_size: workload.batch_size,
+        scan_length: workload.scan_length,
+        profile_stages: workload.profile_stages,
+        phase_timeout: workload.phase_timeout,
+        zero_copy: workload.zero_copy,
+        perf_counters: workload.perf_counters,
+        count_allocations: workload.count_allocations,
+        plain_baselines: workload.plain_baselines,
+        latency_heatmap: workload.latency_heatmap,
+        dataset_stats: Some(dataset_stats::DatasetStats::load_or_compute(path, &data, &end_positions)),
+        phases: Phases::default(),
+        validation: None,
+        verification: workload.verification,
+        timer: workload.timer,
+    };
+    let compressors: Vec<&str> = COMPRESSORS
+        .iter()
+        .copied()
+        .filter(|&compressor_name| {
+            let available = registry::lookup(compressor_name).is_some_and(|entry| entry.available);
+            if !available {
+                warn!(compressor = compressor_name, reason = %registry::unavailable_message(compressor_name), "Skipping compressor");
+            }
+            available
+        })
+        .collect();
+    // Results of the iterations of each compressor
+    let mut compressor_results: Vec<Vec<BenchmarkResult>> = vec![Vec::new(); compressors.len()];
+
+    run_schedule(compressors.len(), workload.interleave, |compressor_index, n_iterations| {
+        let compressor_name = compressors[compressor_index];
+        if n_iterations == 0 {
+            info!(compressor = compressor_name, dataset = %dataset_name, "Benchmarking compressor");
+        }
+        let queries = generate_queries(workload.query_policy, n_elements, QueryDistribution::Uniform, workload.cover_all_items);
+        let mut compressor = AnyCompressor::create(compressor_name, data.len(), n_elements).unwrap();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut result = compressor.benchmark_with_options(dataset_name.clone(), &data, &end_positions, &queries, &options);
+            if let Some(fraction) = workload.holdout_fraction.filter(|_| result.timed_out.is_none()) {
+                result.holdout = compressor.evaluate_holdout(&data, &end_positions, fraction);
+            }
+            result
+        }));
+
+        let results = &mut compressor_results[compressor_index];
+        match result {
+            Ok(mut result) => {
+                result.query_policy = Some(workload.query_policy.describe(workload.cover_all_items));
+                result.placement = placement.cloned();
+                let timed_out = result.timed_out.is_some();
+                results.push(result);
+                if timed_out {
+                    return false;
+                }
+            }
+            Err(_) => {
+                error!(dataset = %dataset_name, compressor = compressor_name, "Benchmark failed");
+                return false;
+            }
+        }
+
+        let converged = is_complete(results, workload.outliers);
+        if converged || n_iterations + 1 == MAX_ITERATIONS {
+            let n_outliers = results.iter().filter(|result| result.outlier.is_some()).count();
+            info!(compressor = compressor_name, dataset = %dataset_name, n_iterations = n_iterations + 1, n_outliers, "Measurements completed");
+        }
+        !converged
+    });
+    compressor_results.into_iter().flatten().collect()
+}
+
+/// Logs the range of the temperatures recorded at the start and end of the runs, if any
+fn log_temperature_range(results: &[BenchmarkResult]) {
+    let temperatures: Vec<f64> = results
+        .iter()
+        .filter_map(|result| result.conditions)
+        .flat_map(|conditions| [conditions.start_temperature, conditions.end_temperature])
+        .flatten()
+        .collect();
+    if temperatures.is_empty() {
+        return;
+    }
+    let min_temperature = temperatures.iter().copied().fold(f64::INFINITY, f64::min);
+    let max_temperature = temperatures.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    info!(min_temperature, max_temperature, "Temperature range over the evaluation");
+}
+
+/// Waits for a benchmark process, killing it if it runs longer than the limit
+/// 
+/// # Arguments
+/// - `child`: Running benchmark process
+/// - `limit`: Maximum lifetime of the process (None: unlimited)
+/// 
+/// # Returns
+/// Exit status of the process, or None if it was killed
+fn wait_with_timeout(mut child: Child, limit: Option<Duration>) -> Option<ExitStatus> {
+    let Some(limit) = limit else {
+        return Some(child.wait().expect("Failed to wait for benchmark"));
+    };
+    let start = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait().expect("Failed to wait for benchmark") {
+            return Some(status);
+        }
+        if start.elapsed() > limit {
+            let _ = child.kill();
+            let _ = child.wait();
+            return None;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
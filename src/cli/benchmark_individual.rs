@@ -0,0 +1,987 @@
+//! Individual benchmark executor for compression algorithm evaluation
+//!
+//! Entry point of the `benchmark_individual` binary and of `cbench bench-one`,
+//! performing isolated performance measurement of a single compression algorithm on a
+//! single dataset. Metrics collected include:
+//! - Compression ratio and throughput (MiB/s)
+//! - Decompression throughput (MiB/s) 
+//! - Random access latency (ns)
+//!
+//! Datasets of multi-column records are evaluated per column and as a whole, using both
+//! a column-store and a row-store layout with record-level random access.
+//! Each compressor also has a sorted variant (`<name>_sorted`), storing strings in
+//! lexicographic order, and a deduplicating variant (`<name>_dedup`), storing each
+//! distinct string once; both resolve accesses through an ID map. Verified variants
+//! (`<name>_verified`, `<name>_verified_xxh3`) check every retrieved string against a
+//! checksum, measuring the overhead of integrity verification.
+//!
+//! Progress and diagnostics are logged to stderr (`--log-level`, `--log-format`; see
+//! `compression_benchmark_rs::logging`).
+//! With `--zipf=S`, random access queries follow a Zipf distribution over item IDs,
+//! which the tiered compressor (`tiered`) also receives as its access hint.
+//! With `--length-weighted`, random access queries pick strings in proportion to their
+//! length, so that every byte of the data is equally likely to be read (string datasets
+//! only). Random access throughput is reported both in strings and in bytes per second
+//! (see `AccessThroughputMetrics`), whatever the distribution of the queries.
+//! With `--warmup=N`, decompression and random access are repeated N times before
+//! being measured, and the first run is reported separately from the steady state.
+//! The number of random access queries follows a policy (`--queries`: a fixed count,
+//! a number of queries per item, or a time budget); with `--cover-all`, every item is
+//! queried at least once. The policy is recorded in the results.
+//! With `--query-file=PATH`, the queries are read from a file written by
+//! `write_query_file` instead of being drawn (e.g., the queries of an experiment bundle;
+//! see `bundle`), while the policy still sets the time budget.
+//! With `--streaming`, full decompression writes to a sink through
+//! `Compressor::decompress_to` with bounded memory, instead of a buffer of the size
+//! of the dataset.
+//! With `--batch=N`, the queries are also issued in batches of N through
+//! `Compressor::get_items_at`, and the speedup over per-query access is reported
+//! (string datasets only).
+//! With `--scan=N`, scans of N consecutive strings starting at the queries are issued
+//! through `Compressor::scan_range`, projecting a prefix of each string, and the scan
+//! throughput is reported (string datasets only).
+//! With `--profile-stages`, the queries are replayed through the instrumented access
+//! path, reporting the latency spent locating, decoding and copying strings (string
+//! datasets only).
+//! With `--profile`, the call stacks of the compression, decompression and random
+//! access phases are sampled, and a flamegraph and a pprof profile of each phase are
+//! written to a `profiles` directory next to the output file (see `phase_profile`;
+//! requires the `pprof` feature). Sampling slows the phases down.
+//! With `--zero-copy`, the queries are replayed through `Compressor::get_item_ref`,
+//! which returns strings in place, reporting the cost of copying them to the caller's
+//! buffer (string datasets and compressors supporting zero-copy access only).
+//! With `--perf-counters`, the queries are replayed with hardware counters enabled,
+//! reporting instructions, last-level cache misses and branch mispredictions per
+//! query (string datasets on Linux machines exposing the counters only).
+//! With `--count-allocations`, the allocations of the measured random access run are
+//! counted (see `alloc_counter`) and reported; access paths are expected to be
+//! allocation-free after the warm-up runs (string datasets only).
+//! With `--plain-baselines`, the queries are also issued to uncompressed in-memory
+//! stores (a vector of strings and an `FxHashMap` from string IDs to ranges), putting
+//! compressed access latencies in the context of a plain store on the same machine and
+//! dataset (string datasets only).
+//! With `--latency-heatmap`, the latencies of the measured random access run are binned
+//! by decile of string length and by position of the string within its block (first,
+//! middle or last; see `LatencyHeatmap`), reporting a small matrix per run (string
+//! datasets only).
+//! With `--onpair-config=K=V,...`, OnPairBV variants train with the given parameters
+//! (merge threshold, maximum token length, bits per token, sampled fraction, dictionary
+//! coding; see `OnPairConfig::parse`), enabling parameter studies without recompiling
+//! (plain string datasets and unwrapped compressors only).
+//! With `--holdout=F`, dictionary compressors (`bpe`, `onpair_bv`) also train a dictionary
+//! without a random fraction F of the strings, and the compression rates of the training
+//! and held-out strings are reported side by side (see `evaluate_holdout`; plain string
+//! datasets and unwrapped compressors only).
+//! With `--split-compression`, dictionary compressors also time dictionary training and
+//! parsing separately (see `CompressionSplit`), so that `cbench amortize` can report the
+//! compression speed of shards sharing a dictionary (plain string datasets and
+//! unwrapped compressors only).
+//! With `--updates=F`, a fraction F of the strings is replaced in each of
+//! `--update-rounds=N` rounds (default: 10), and the cost of compressing all strings
+//! again is compared after each round with patching a store that appends the new values
+//! as a segment and tombstones the replaced copies (see `evaluate_updates`); the space
+//! of both after each round is recorded as a time series (plain string datasets and
+//! unwrapped compressors only).
+//! With `--reorder-blocks`, block compressors (`lz4_blocks`, `zstd_blocks`,
+//! `zstd_dict_blocks`, `tiered`) lay out their compressed blocks so that blocks
+//! accessed one after the other by the queries are adjacent in memory; the queries are
+//! then replayed on this layout, and the result is recorded under a separate
+//! compressor name for comparison with the default layout (plain string datasets and
+//! unwrapped compressors only).
+//! With `--block-latency=NS`, block compressors choose their block size for the dataset:
+//! the largest size whose calibrated single-block decompression time stays within NS
+//! nanoseconds is used, and recorded in the result (see `TieredCompressor::set_latency_budget`;
+//! plain string datasets and unwrapped compressors only).
+//! With `--offset-sampling=K`, token-stream compressors (`bpe`, `onpair_bv` and its
+//! variants) store the token offset of every K-th string only, with the token counts
+//! of the strings in between: the space of the offsets is reported under the item
+//! offsets, and the latency of scanning from the closest sample by random access
+//! (see `BPECompressor::set_offset_sample_rate`; plain string datasets and unwrapped
+//! compressors only).
+//! With `--item-sampling=M`, the same compressors store the token holding every M-th
+//! byte of the strings longer than M bytes, so that range reads within long strings
+//! (`get_item_range`) start from the closest sampled token: the space of the samples is
+//! reported under the item offsets (see `BPECompressor::set_item_sample_interval`;
+//! plain string datasets and unwrapped compressors only).
+//! Block compressors report the spread of the compression ratios of their blocks
+//! (`block_stats`), and with `--block-stats=FILE` the compressed and uncompressed size
+//! and item count of every block are written to a CSV file, to find incompressible
+//! blocks dominating the space (see `BlockCompressor::get_block_stats`; plain string
+//! datasets and unwrapped compressors only).
+//! With `--validate=F`, a fraction F of the strings is retrieved and checked one by
+//! one after the measured phases, including the UTF-8 validity of text strings, to
+//! catch corruption the queries did not reach (see `ValidationMetrics`).
+//! With `--verify=L`, the output of the queries of the access phases is checked against
+//! the dataset for none of them, one in 100 (`sampled`, the default) or all of them
+//! (`full`); checks are never timed, but checking every query evicts the collection
+//! from the cache between queries (see `Verification`).
+//! With `--timer=T`, timings read `CLOCK_MONOTONIC_RAW` (`monotonic_raw`, Linux only) or
+//! the invariant time-stamp counter (`tsc`, x86_64 only) instead of `Instant`; the clock,
+//! its calibrated reading overhead and its resolution are recorded under `timer` (see
+//! `clock`).
+//! With `--phase-timeout=S`, a phase running longer than S seconds cancels the run,
+//! and a timed-out status row is recorded instead of the measurements. Phases are
+//! checked between operations, so an overlong compression is detected once it ends.
+//! With `--phases=P,...`, only the listed phases (`compress`, `decompress`, `access`)
+//! are measured, and the metrics of the others are null in the results; skipping
+//! `access` also skips the phases replaying its queries (batches, scans, stage
+//! profiling, zero-copy and plain store access, hardware counters, latency heatmap).
+//! The dataset is compressed all the same, so that campaigns needing only compression
+//! rates, or only access latencies, save the time of the other phases.
+//! With `--from-store`, the dataset path is a store saved by
+//! `CompressedStringStore::save` (see `store`): its strings are the dataset, its
+//! algorithm must be the compressor of the run, and the compressor of the loaded store
+//! is measured as is, without measuring compression (plain string datasets and
+//! unwrapped compressors only).
+//!
+//! Results are appended to a JSON file for aggregation by the main benchmark harness.
+//! CPU core affinity can be specified for consistent measurements in controlled environments,
+//! as a single core or a list (e.g., `0-3,8`); with `--numa-bind`, memory is also bound to
+//! the NUMA nodes of these cores. The cores and NUMA nodes are recorded in the results.
+
+use crate::benchmark_utils::*;
+use crate::compressor::block_stats::{write_block_stats_csv, BlockStatsSummary};
+use crate::compressor::bpe::BPECompressor;
+#[cfg(feature = "onpair")]
+use crate::compressor::onpair_bv::{OnPairBVCompressor, OnPairConfig};
+use crate::compressor::{Compressor, BUFFER_PADDING};
+use crate::compressor::checksum::{ChecksumAlgorithm, VerifiedCompressor};
+use crate::compressor::dedup::DedupCompressor;
+use crate::compressor::fm_index::FMIndexCompressor;
+use crate::compressor::front_coding::FrontCodingCompressor;
+use crate::compressor::fsst::FSSTCompressor;
+use crate::compressor::lzw::LZWCompressor;
+use crate::compressor::terminated::TerminatedBPECompressor;
+use crate::compressor::id_map::SortedCompressor;
+use crate::compressor::length_index::LengthIndex;
+use crate::compressor::raw::RawCompressor;
+#[cfg(feature = "onpair")]
+use crate::compressor::onpair16::OnPair16Compressor;
+#[cfg(feature = "onpair")]
+use crate::compressor::onpair::OnPairCompressor;
+use crate::compressor::registry::{self, AnyCompressor};
+#[cfg(feature = "tiered")]
+use crate::compressor::tiered::{AccessHint, TieredCompressor};
+use crate::compressor::record::{interleave_columns, RecordLayout, RecordStore};
+use crate::dataset_stats::DatasetStats;
+use crate::entropy::dataset_entropy;
+use crate::logging;
+use crate::phase_profile;
+use crate::store::CompressedStringStore;
+use std::path::Path;
+use std::time::Duration;
+use tracing::{error, info, info_span, warn};
+
+/// Directory of the phase profiles (`--profile`), next to the output file
+const PROFILE_DIRECTORY: &str = "profiles";
+
+/// Name suffixes selecting a wrapper around a compressor (e.g., "onpair16_sorted")
+const WRAPPER_SUFFIXES: [(&str, Wrapper); 4] = [
+    ("_sorted", Wrapper::Sorted),
+    ("_dedup", Wrapper::Dedup),
+    ("_verified", Wrapper::Verified(ChecksumAlgorithm::Crc32c)),
+    ("_verified_xxh3", Wrapper::Verified(ChecksumAlgorithm::XxHash3)),
+];
+
+/// Wrappers applied on top of a compressor
+#[derive(Clone, Copy)]
+enum Wrapper {
+    Sorted,                         // Strings stored in lexicographic order
+    Dedup,                          // Distinct strings stored once
+    Verified(ChecksumAlgorithm),    // Retrieved strings verified against checksums
+}
+
+/// Individual benchmark execution entry point
+///
+/// Exits the process on invalid arguments and failed runs. Allocations are counted
+/// (`--count-allocations`) only if the binary installs `alloc_counter::CountingAllocator`.
+///
+/// # Arguments
+/// - `args`: Command-line arguments, starting with the program name shown in the usage
+pub fn run(mut args: Vec<String>) {
+    logging::init_from_args(&mut args).unwrap_or_else(|message| {
+        eprintln!("Error: {}", message);
+        std::process::exit(1);
+    });
+    let warmup_iterations = take_option(&mut args, "--warmup").map_or(0, |value| {
+        value.parse::<usize>().unwrap_or_else(|_| {
+            error!("Invalid warm-up iterations '{}'. Must be a valid number.", value);
+            std::process::exit(1);
+        })
+    });
+    let query_policy = take_option(&mut args, "--queries").map_or(QueryPolicy::default(), |value| {
+        QueryPolicy::parse(&value).unwrap_or_else(|| {
+            error!("Invalid query policy '{}'. Must be N, coverage:F or time:S.", value);
+            std::process::exit(1);
+        })
+    });
+    let cover_all_items = take_flag(&mut args, "--cover-all");
+    let query_file = take_option(&mut args, "--query-file");
+    let streaming = take_flag(&mut args, "--streaming");
+    let batch_size = take_option(&mut args, "--batch").map(|value| {
+        value.parse::<usize>().ok().filter(|batch_size| *batch_size > 0).unwrap_or_else(|| {
+            error!("Invalid batch size '{}'. Must be a positive number.", value);
+            std::process::exit(1);
+        })
+    });
+    let scan_length = take_option(&mut args, "--scan").map(|value| {
+        value.parse::<usize>().ok().filter(|scan_length| *scan_length > 0).unwrap_or_else(|| {
+            error!("Invalid scan length '{}'. Must be a positive number.", value);
+            std::process::exit(1);
+        })
+    });
+    let profile_stages = take_flag(&mut args, "--profile-stages");
+    let profile_phases = take_flag(&mut args, "--profile");
+    let zero_copy = take_flag(&mut args, "--zero-copy");
+    let perf_counters = take_flag(&mut args, "--perf-counters");
+    let count_allocations = take_flag(&mut args, "--count-allocations");
+    let plain_baselines = take_flag(&mut args, "--plain-baselines");
+    let latency_heatmap = take_flag(&mut args, "--latency-heatmap");
+    let holdout_fraction = take_option(&mut args, "--holdout").map(|value| {
+        value.parse::<f64>().ok().filter(|fraction| *fraction > 0.0 && *fraction < 1.0).unwrap_or_else(|| {
+            error!("Invalid holdout fraction '{}'. Must be a number in (0, 1).", value);
+            std::process::exit(1);
+        })
+    });
+    let split_compression = take_flag(&mut args, "--split-compression");
+    let update_fraction = take_option(&mut args, "--updates").map(|value| {
+        value.parse::<f64>().ok().filter(|fraction| *fraction > 0.0 && *fraction <= 1.0).unwrap_or_else(|| {
+            error!("Invalid update fraction '{}'. Must be a number in (0, 1].", value);
+            std::process::exit(1);
+        })
+    });
+    let update_rounds = take_option(&mut args, "--update-rounds").map_or(DEFAULT_UPDATE_ROUNDS, |value| {
+        value.parse::<usize>().ok().filter(|rounds| *rounds > 0).unwrap_or_else(|| {
+            error!("Invalid number of update rounds '{}'. Must be a positive number.", value);
+            std::process::exit(1);
+        })
+    });
+    let reorder_blocks = take_flag(&mut args, "--reorder-blocks");
+    let block_latency = take_option(&mut args, "--block-latency").map(|value| {
+        value.parse::<f64>().ok().filter(|latency| latency.is_finite() && *latency > 0.0).unwrap_or_else(|| {
+            error!("Invalid block latency budget '{}'. Must be a positive number of nanoseconds.", value);
+            std::process::exit(1);
+        })
+    });
+    let offset_sampling = take_option(&mut args, "--offset-sampling").map(|value| {
+        value.parse::<usize>().ok().filter(|sample_rate| *sample_rate > 0).unwrap_or_else(|| {
+            error!("Invalid offset sampling '{}'. Must be a positive number of strings.", value);
+            std::process::exit(1);
+        })
+    });
+    let item_sampling = take_option(&mut args, "--item-sampling").map(|value| {
+        value.parse::<usize>().ok().filter(|sample_interval| *sample_interval > 0).unwrap_or_else(|| {
+            error!("Invalid item sampling '{}'. Must be a positive number of bytes.", value);
+            std::process::exit(1);
+        })
+    });
+    let block_stats_file = take_option(&mut args, "--block-stats");
+    let validation = take_option(&mut args, "--validate").map(|value| {
+        value.parse::<f64>().ok().filter(|fraction| *fraction > 0.0 && *fraction <= 1.0).unwrap_or_else(|| {
+            error!("Invalid validation fraction '{}'. Must be a number in (0, 1].", value);
+            std::process::exit(1);
+        })
+    });
+    let verification = take_option(&mut args, "--verify").map_or(Verification::default(), |value| {
+        Verification::parse(&value).unwrap_or_else(|| {
+            error!("Invalid verification level '{}'. Must be none, sampled or full.", value);
+            std::process::exit(1);
+        })
+    });
+    let timer = take_option(&mut args, "--timer").map_or(TimerSource::default(), |value| {
+        let source = TimerSource::parse(&value).unwrap_or_else(|| {
+            error!("Invalid timer '{}'. Must be instant, monotonic_raw or tsc.", value);
+            std::process::exit(1);
+        });
+        if let Err(message) = Clock::of(source) {
+            error!("{}.", message);
+            std::process::exit(1);
+        }
+        source
+    });
+    let numa_bind = take_flag(&mut args, "--numa-bind");
+    let phase_timeout = take_option(&mut args, "--phase-timeout").map(|value| {
+        value.parse::<f64>().ok().filter(|seconds| *seconds > 0.0).map(Duration::from_secs_f64).unwrap_or_else(|| {
+            error!("Invalid phase timeout '{}'. Must be a positive number of seconds.", value);
+            std::process::exit(1);
+        })
+    });
+    #[cfg(feature = "onpair")]
+    let onpair_config = take_option(&mut args, "--onpair-config").map(|value| {
+        OnPairConfig::parse(&value).unwrap_or_else(|| {
+            error!("Invalid OnPair configuration '{}'. Must be a list of threshold=N|auto, max_length=N, bits=N, sample=F, sampling=random|stratified, dictionary=plain|front_coded or max_dictionary=N.", value);
+            std::process::exit(1);
+        })
+    });
+    let zipf_exponent = take_option(&mut args, "--zipf").map(|value| {
+        value.parse::<f64>().ok().filter(|exponent| *exponent >= 0.0).unwrap_or_else(|| {
+            error!("Invalid Zipf exponent '{}'. Must be a non-negative number.", value);
+            std::process::exit(1);
+        })
+    });
+    let length_weighted = take_flag(&mut args, "--length-weighted");
+    if length_weighted && zipf_exponent.is_some() {
+        error!("--length-weighted and --zipf are mutually exclusive.");
+        std::process::exit(1);
+    }
+    let phases = take_option(&mut args, "--phases").map_or(Phases::default(), |value| {
+        Phases::parse(&value).unwrap_or_else(|| {
+            error!("Invalid phases '{}'. Must be a list of compress, decompress and access.", value);
+            std::process::exit(1);
+        })
+    });
+    let from_store = take_flag(&mut args, "--from-store");
+
+    if args.len() < 4 {
+        eprintln!("Usage: {} <dataset_path> <compressor_name> <output_file> [cores] [--warmup=N] [--queries=P] [--cover-all] [--query-file=PATH] [--streaming] [--batch=N] [--scan=N] [--profile-stages] [--profile] [--zero-copy] [--perf-counters] [--count-allocations] [--plain-baselines] [--latency-heatmap] [--holdout=F] [--split-compression] [--updates=F] [--update-rounds=N] [--reorder-blocks] [--block-latency=NS] [--offset-sampling=K] [--item-sampling=M] [--block-stats=FILE] [--validate=F] [--verify=L] [--timer=T] [--numa-bind] [--phase-timeout=S] [--onpair-config=C] [--zipf=S] [--length-weighted] [--phases=P] [--from-store] [--log-level=L] [--log-format=F]", args[0]);
+        eprintln!("  <compressor_name> - {}", registry::available_compressors().join(" | "));
+        eprintln!("                      (append _sorted, _dedup, _verified or _verified_xxh3 for a wrapped variant)");
+        eprintln!("  [cores]           - Optional CPU cores for pinning, e.g. 2 or 0-3,8");
+        eprintln!("  --warmup=N        - Unmeasured runs of decompression and random access (default: 0)");
+        eprintln!("  --queries=P       - N queries, coverage:F (F queries per item) or time:S (S seconds) (default: {})", DEFAULT_N_QUERIES);
+        eprintln!("  --cover-all       - Query every item at least once");
+        eprintln!("  --query-file=PATH - Issue the queries stored in PATH instead of drawing them");
+        eprintln!("  --streaming       - Streaming decompression with bounded memory");
+        eprintln!("  --batch=N         - Also measure random access in batches of N queries");
+        eprintln!("  --scan=N          - Also measure scans of N consecutive strings projecting a prefix");
+        eprintln!("  --profile-stages  - Also split random access latency into lookup, decode and copy");
+        eprintln!("  --profile         - Write a flamegraph and a pprof profile of each phase next to the output file");
+        eprintln!("  --zero-copy       - Also measure random access without copying, where supported");
+        eprintln!("  --perf-counters   - Also count hardware events of random access (Linux only)");
+        eprintln!("  --count-allocations - Count allocations of the measured random access run");
+        eprintln!("  --plain-baselines - Also measure random access to uncompressed Vec and FxHashMap stores");
+        eprintln!("  --latency-heatmap - Also bin random access latency by string length and position in block");
+        eprintln!("  --holdout=F       - Also train dictionaries without a fraction F of the strings and compress them");
+        eprintln!("  --split-compression - Also time dictionary training and parsing separately");
+        eprintln!("  --updates=F       - Also compare re-compression with patching when replacing a fraction F of the strings");
+        eprintln!("  --update-rounds=N - Rounds of updates of --updates (default: {})", DEFAULT_UPDATE_ROUNDS);
+        eprintln!("  --reorder-blocks  - Lay out compressed blocks by co-access in the queries");
+        eprintln!("  --block-latency=NS - Largest block size decompressing within NS nanoseconds, for block compressors");
+        eprintln!("  --offset-sampling=K - Store the token offset of every K-th string only, for token-stream compressors");
+        eprintln!("  --item-sampling=M - Sample the token holding every M-th byte of long strings, for token-stream compressors");
+        eprintln!("  --block-stats=FILE - Write the sizes and item count of every block to a CSV file, for block compressors");
+        eprintln!("  --validate=F      - Check a fraction F of the strings one by one, including UTF-8 validity");
+        eprintln!("  --verify=L        - Queries checked against the dataset: none | sampled (1%) | full (default: sampled)");
+        eprintln!("  --timer=T         - Clock of the timings: instant | monotonic_raw (Linux) | tsc (x86_64) (default: instant)");
+        eprintln!("  --numa-bind       - Bind memory to the NUMA nodes of the pinned cores (Linux only)");
+        eprintln!("  --phase-timeout=S - Cancel the run if a phase takes longer than S seconds (default: unlimited)");
+        eprintln!("  --onpair-config=C - OnPairBV parameters, e.g. threshold=8,max_length=16,bits=12,sample=0.5");
+        eprintln!("  --zipf=S          - Zipf-distributed queries with exponent S (default: uniform)");
+        eprintln!("  --length-weighted - Queries weighted by string length (default: uniform)");
+        eprintln!("  --phases=P        - Phases measured, a list of compress, decompress and access (default: all)");
+        eprintln!("  --from-store      - The dataset is a saved store, measured without compressing the dataset");
+        eprintln!("  --log-level=L     - trace | debug | info | warn | error (default: info)");
+        eprintln!("  --log-format=F    - text | json (default: text)");
+        std::process::exit(1);
+    }
+
+    let dataset_path = &args[1];
+    let compressor_name = &args[2];
+    let output_file = &args[3];
+    let cores = if args.len() > 4 {
+        Some(parse_core_list(&args[4]).unwrap_or_else(|| {
+            error!("Invalid cores '{}'. Must be a core ID or a list such as 0-3,8.", args[4]);
+            std::process::exit(1);
+        }))
+    } else {
+        None
+    };
+    if profile_phases {
+        let profile_directory = Path::new(output_file).parent().unwrap_or(Path::new("")).join(PROFILE_DIRECTORY);
+        phase_profile::enable(&profile_directory).unwrap_or_else(|message| {
+            error!("{}.", message);
+            std::process::exit(1);
+        });
+    }
+
+    // Check if dataset path exists and is a file
+    let dataset_path = Path::new(dataset_path);
+    if !dataset_path.exists() {
+        error!("Dataset path '{}' does not exist.", dataset_path.display());
+        std::process::exit(1);
+    }
+    if !dataset_path.is_file() {
+        error!("Dataset path '{}' is not a file.", dataset_path.display());
+        std::process::exit(1);
+    }
+
+    // Set CPU affinity (and NUMA memory binding) if specified, before loading the dataset
+    if numa_bind && cores.is_none() {
+        warn!("--numa-bind requires pinned cores, ignoring it");
+    }
+    let placement = cores.and_then(|cores| apply_placement(&cores, numa_bind));
+    
+    // Load dataset
+    let dataset_name = dataset_path.file_name().unwrap().to_str().unwrap().to_string();
+    let options = |n_items: usize| BenchmarkOptions {
+        warmup_iterations,
+        time_budget: query_policy.time_budget(),
+        min_queries: if cover_all_items { n_items } else { 0 },
+        streaming,
+        batch_size,
+        scan_length,
+        profile_stages,
+        phase_timeout,
+        zero_copy,
+        perf_counters,
+        count_allocations,
+        plain_baselines,
+        latency_heatmap,
+        dataset_stats: None,
+        phases,
+        validation,
+        verification,
+        timer,
+    };
+    let policy_description = query_policy.describe(cover_all_items);
+
+    // Saved stores are measured with the compressor they load
+    if from_store {
+        let store = CompressedStringStore::load(dataset_path).unwrap_or_else(|e| {
+            error!("Failed to load store '{}': {}", dataset_path.display(), e);
+            std::process::exit(1);
+        });
+        if store.algorithm() != compressor_name {
+            error!("Store '{}' was compressed with '{}', not '{}'.", dataset_path.display(), store.algorithm(), compressor_name);
+            std::process::exit(1);
+        }
+        if phases.compress {
+            info!("Stores are compressed when loaded, compression is not measured");
+        }
+        let (data, end_positions) = store.strings();
+        let n_elements = end_positions.len() - 1;
+        let queries = match &query_file {
+            Some(path) => read_queries(path, n_elements),
+            None => {
+                let distribution = match zipf_exponent {
+                    Some(exponent) => QueryDistribution::Zipf(exponent),
+                    None if length_weighted => QueryDistribution::LengthWeighted(&end_positions),
+                    None => QueryDistribution::Uniform,
+                };
+                generate_queries(query_policy, n_elements, distribution, cover_all_items)
+            }
+        };
+        let mut compressor = store.into_compressor();
+        let mut result = compressor.benchmark_compressed(dataset_name, &data, &end_positions, &queries, &options(n_elements));
+        result.query_policy = Some(policy_description);
+        result.placement = placement;
+        append_benchmark_result(&result, Path::new(output_file));
+        return;
+    }
+
+    // Multi-column datasets are evaluated per column and per record layout
+    if is_record_dataset(dataset_path) {
+        #[cfg(feature = "onpair")]
+        if onpair_config.is_some() {
+            warn!("--onpair-config applies to unwrapped compressors on string datasets only, ignoring it");
+        }
+        if holdout_fraction.is_some() {
+            warn!("--holdout applies to unwrapped compressors on string datasets only, ignoring it");
+        }
+        if split_compression {
+            warn!("--split-compression applies to unwrapped compressors on string datasets only, ignoring it");
+        }
+        if update_fraction.is_some() {
+            warn!("--updates applies to unwrapped compressors on string datasets only, ignoring it");
+        }
+        if reorder_blocks {
+            warn!("--reorder-blocks applies to unwrapped compressors on string datasets only, ignoring it");
+        }
+        if block_latency.is_some() {
+            warn!("--block-latency applies to unwrapped compressors on string datasets only, ignoring it");
+        }
+        if offset_sampling.is_some() {
+            warn!("--offset-sampling applies to unwrapped compressors on string datasets only, ignoring it");
+        }
+        if item_sampling.is_some() {
+            warn!("--item-sampling applies to unwrapped compressors on string datasets only, ignoring it");
+        }
+        if block_stats_file.is_some() {
+            warn!("--block-stats applies to unwrapped compressors on string datasets only, ignoring it");
+        }
+        if validation.is_some() {
+            warn!("--validate applies to string datasets only, ignoring it");
+        }
+        if length_weighted {
+            warn!("--length-weighted applies to string datasets only, ignoring it");
+        }
+        let records = load_record_dataset(dataset_path);
+        let output_file = Path::new(output_file);
+        let queries = match &query_file {
+            Some(path) => read_queries(path, records.n_records()),
+            None => generate_queries(query_policy, records.n_records(), QueryDistribution::Uniform, cover_all_items),
+        };
+        let options = options(records.n_records());
+        match compressor_name.as_str() {
+            "raw" => benchmark_record_dataset::<RawCompressor>(&dataset_name, &records, output_file, &queries, &options, &policy_description, placement.as_ref()),
+            "bpe" => benchmark_record_dataset::<BPECompressor>(&dataset_name, &records, output_file, &queries, &options, &policy_description, placement.as_ref()),
+            "bpe_terminated" => benchmark_record_dataset::<TerminatedBPECompressor>(&dataset_name, &records, output_file, &queries, &options, &policy_description, placement.as_ref()),
+            "fm_index" => benchmark_record_dataset::<FMIndexCompressor>(&dataset_name, &records, output_file, &queries, &options, &policy_description, placement.as_ref()),
+            "front_coding" => benchmark_record_dataset::<FrontCodingCompressor>(&dataset_name, &records, output_file, &queries, &options, &policy_description, placement.as_ref()),
+            "fsst_native" => benchmark_record_dataset::<FSSTCompressor>(&dataset_name, &records, output_file, &queries, &options, &policy_description, placement.as_ref()),
+            "lzw" => benchmark_record_dataset::<LZWCompressor>(&dataset_name, &records, output_file, &queries, &options, &policy_description, placement.as_ref()),
+            #[cfg(feature = "onpair")]
+            "onpair" => benchmark_record_dataset::<OnPairCompressor>(&dataset_name, &records, output_file, &queries, &options, &policy_description, placement.as_ref()),
+            #[cfg(feature = "onpair")]
+            "onpair16" => benchmark_record_dataset::<OnPair16Compressor>(&dataset_name, &records, output_file, &queries, &options, &policy_description, placement.as_ref()),
+            #[cfg(feature = "onpair")]
+            "onpair_bv" => benchmark_record_dataset::<OnPairBVCompressor>(&dataset_name, &records, output_file, &queries, &options, &policy_description, placement.as_ref()),
+            #[cfg(feature = "tiered")]
+            "tiered" => benchmark_record_dataset::<TieredCompressor>(&dataset_name, &records, output_file, &queries, &options, &policy_description, placement.as_ref()),
+            _ => {
+                error!("{}", registry::unavailable_message(compressor_name));
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let (data, end_positions) = load_dataset(dataset_path);
+    let n_elements = end_positions.len() - 1;
+    let queries = match &query_file {
+        Some(path) => read_queries(path, n_elements),
+        None => {
+            let distribution = match zipf_exponent {
+                Some(exponent) => QueryDistribution::Zipf(exponent),
+                None if length_weighted => QueryDistribution::LengthWeighted(&end_positions),
+                None => QueryDistribution::Uniform,
+            };
+            generate_queries(query_policy, n_elements, distribution, cover_all_items)
+        }
+    };
+    let options = BenchmarkOptions {
+        dataset_stats: Some(DatasetStats::load_or_compute(dataset_path, &data, &end_positions)),
+        ..options(n_elements)
+    };
+
+    // Wrapped variants, selected by a name suffix
+    let wrapped = WRAPPER_SUFFIXES
+        .iter()
+        .find_map(|&(suffix, wrapper)| compressor_name.strip_suffix(suffix).map(|base_name| (base_name, wrapper)));
+    if let Some((base_name, wrapper)) = wrapped {
+        #[cfg(feature = "onpair")]
+        if onpair_config.is_some() {
+            warn!("--onpair-config applies to unwrapped compressors on string datasets only, ignoring it");
+        }
+        if holdout_fraction.is_some() {
+            warn!("--holdout applies to unwrapped compressors on string datasets only, ignoring it");
+        }
+        if split_compression {
+            warn!("--split-compression applies to unwrapped compressors on string datasets only, ignoring it");
+        }
+        if update_fraction.is_some() {
+            warn!("--updates applies to unwrapped compressors on string datasets only, ignoring it");
+        }
+        if reorder_blocks {
+            warn!("--reorder-blocks applies to unwrapped compressors on string datasets only, ignoring it");
+        }
+        if block_latency.is_some() {
+            warn!("--block-latency applies to unwrapped compressors on string datasets only, ignoring it");
+        }
+        if offset_sampling.is_some() {
+            warn!("--offset-sampling applies to unwrapped compressors on string datasets only, ignoring it");
+        }
+        if item_sampling.is_some() {
+            warn!("--item-sampling applies to unwrapped compressors on string datasets only, ignoring it");
+        }
+        if block_stats_file.is_some() {
+            warn!("--block-stats applies to unwrapped compressors on string datasets only, ignoring it");
+        }
+        let mut result = match base_name {
+            "raw" => benchmark_wrapped::<RawCompressor>(wrapper, dataset_name, &data, &end_positions, &queries, &options),
+            "bpe" => benchmark_wrapped::<BPECompressor>(wrapper, dataset_name, &data, &end_positions, &queries, &options),
+            "bpe_terminated" => benchmark_wrapped::<TerminatedBPECompressor>(wrapper, dataset_name, &data, &end_positions, &queries, &options),
+            "fm_index" => benchmark_wrapped::<FMIndexCompressor>(wrapper, dataset_name, &data, &end_positions, &queries, &options),
+            "front_coding" => benchmark_wrapped::<FrontCodingCompressor>(wrapper, dataset_name, &data, &end_positions, &queries, &options),
+            "fsst_native" => benchmark_wrapped::<FSSTCompressor>(wrapper, dataset_name, &data, &end_positions, &queries, &options),
+            "lzw" => benchmark_wrapped::<LZWCompressor>(wrapper, dataset_name, &data, &end_positions, &queries, &options),
+            #[cfg(feature = "onpair")]
+            "onpair" => benchmark_wrapped::<OnPairCompressor>(wrapper, dataset_name, &data, &end_positions, &queries, &options),
+            #[cfg(feature = "onpair")]
+            "onpair16" => benchmark_wrapped::<OnPair16Compressor>(wrapper, dataset_name, &data, &end_positions, &queries, &options),
+            #[cfg(feature = "onpair")]
+            "onpair_bv" => benchmark_wrapped::<OnPairBVCompressor>(wrapper, dataset_name, &data, &end_positions, &queries, &options),
+            #[cfg(feature = "tiered")]
+            "tiered" => benchmark_wrapped::<TieredCompressor>(wrapper, dataset_name, &data, &end_positions, &queries, &options),
+            _ => {
+                error!("{}", registry::unavailable_message(base_name));
+                std::process::exit(1);
+            }
+        };
+        result.query_policy = Some(policy_description);
+        result.placement = placement;
+        append_benchmark_result(&result, Path::new(output_file));
+        return;
+    }
+
+    // Initialize the compressor; the tiered compressor takes the query trace as access hint
+    let compressor = match compressor_name.as_str() {
+        #[cfg(feature = "tiered")]
+        "tiered" => Some(AnyCompressor::Tiered(
+            TieredCompressor::with_access_hint(data.len(), n_elements, AccessHint::Trace(queries.clone()))
+        )),
+        _ => AnyCompressor::create(compressor_name, data.len(), n_elements),
+    };
+    let mut compressor = compressor.unwrap_or_else(|| {
+        error!("{}", registry::unavailable_message(compressor_name));
+        std::process::exit(1);
+    });
+    #[cfg(feature = "onpair")]
+    if let Some(config) = onpair_config {
+        if !compressor.set_onpair_config(config) {
+            warn!(compressor = compressor_name.as_str(), "Compressor has no configurable OnPair parameters, ignoring --onpair-config");
+        }
+    }
+    if reorder_blocks {
+        #[cfg(feature = "tiered")]
+        let accepted = compressor.set_layout_trace(queries.clone());
+        #[cfg(not(feature = "tiered"))]
+        let accepted = false;
+        if !accepted {
+            warn!(compressor = compressor_name.as_str(), "Compressor has no blocks to reorder, ignoring --reorder-blocks");
+        }
+    }
+    if let Some(latency_budget) = block_latency {
+        #[cfg(feature = "tiered")]
+        let accepted = compressor.set_latency_budget(latency_budget);
+        #[cfg(not(feature = "tiered"))]
+        let accepted = { let _ = latency_budget; false };
+        if !accepted {
+            warn!(compressor = compressor_name.as_str(), "Compressor has no block size, ignoring --block-latency");
+        }
+    }
+    if let Some(sample_rate) = offset_sampling {
+        if !compressor.set_offset_sample_rate(sample_rate) {
+            warn!(compressor = compressor_name.as_str(), "Compressor has no token offsets to sample, ignoring --offset-sampling");
+        }
+    }
+    if let Some(sample_interval) = item_sampling {
+        if !compressor.set_item_sample_interval(sample_interval) {
+            warn!(compressor = compressor_name.as_str(), "Compressor has no token stream to sample, ignoring --item-sampling");
+        }
+    }
+    if let Err(message) = compressor.capacity().check(&end_positions) {
+        error!("Compressor '{}' does not support this dataset: {}.", compressor_name, message);
+        std::process::exit(1);
+    }
+
+    let mut result = compressor.benchmark_with_options(dataset_name, &data, &end_positions, &queries, &options);
+    result.query_policy = Some(policy_description);
+    result.block_size_calibration = compressor.block_size_calibration();
+    result.literal_tokens = compressor.literal_token_stats().filter(|_| result.timed_out.is_none());
+    result.matcher = compressor.matcher_stats().filter(|_| result.timed_out.is_none());
+    if result.timed_out.is_none() {
+        result.dictionary = compressor.dictionary_stats();
+    }
+    if let Some(stats) = &result.dictionary {
+        info!(n_used_tokens = stats.n_used_tokens, bytes_per_token = stats.bytes_per_token, entropy_bits = stats.entropy_bits, "Dictionary summarized");
+    }
+    match compressor.block_stats().filter(|_| result.timed_out.is_none()) {
+        Some(block_stats) => {
+            result.block_stats = BlockStatsSummary::from_blocks(&block_stats);
+            if let Some(summary) = result.block_stats {
+                info!(n_blocks = summary.n_blocks, min_ratio = summary.min_ratio, median_ratio = summary.median_ratio, max_ratio = summary.max_ratio, "Block ratios summarized");
+            }
+            if let Some(block_stats_file) = &block_stats_file {
+                write_block_stats_csv(&block_stats, Path::new(block_stats_file)).unwrap_or_else(|e| {
+                    error!("Failed to write block statistics to '{}': {}", block_stats_file, e);
+                    std::process::exit(1);
+                });
+            }
+        }
+        None if block_stats_file.is_some() && result.timed_out.is_none() => {
+            warn!(compressor = compressor_name.as_str(), "Compressor has no blocks, ignoring --block-stats");
+        }
+        None => {}
+    }
+    if let Some(fraction) = holdout_fraction.filter(|_| result.timed_out.is_none()) {
+        result.holdout = compressor.evaluate_holdout(&data, &end_positions, fraction);
+        if result.holdout.is_none() {
+            warn!(compressor = compressor_name.as_str(), "Compressor has no trainable dictionary or too few strings, ignoring --holdout");
+        }
+    }
+    if split_compression && result.timed_out.is_none() {
+        result.compression_split = compressor.measure_compression_split(&data, &end_positions);
+        if result.compression_split.is_none() {
+            warn!(compressor = compressor_name.as_str(), "Compressor has no trainable dictionary, ignoring --split-compression");
+        }
+    }
+    if let Some(fraction) = update_fraction.filter(|_| result.timed_out.is_none()) {
+        match evaluate_updates(compressor_name, &data, &end_positions, fraction, update_rounds) {
+            Ok(updates) => result.updates = Some(updates),
+            Err(message) => warn!(compressor = compressor_name.as_str(), "Update evaluation failed, ignoring --updates: {}", message),
+        }
+    }
+    result.placement = placement;
+
+    // Append the result to the file
+    append_benchmark_result(&result, Path::new(output_file));
+}
+
+/// Reads the queries of `--query-file`, exiting on an unreadable file or out-of-range queries
+/// 
+/// # Arguments
+/// - `path`: Path to the query file
+/// - `n`: Total number of strings (or records) in the dataset
+fn read_queries(path: &str, n: usize) -> Vec<usize> {
+    let queries = load_query_file(Path::new(path), n).unwrap_or_else(|message| {
+        error!("{}", message);
+        std::process::exit(1);
+    });
+    info!(path, n_queries = queries.len(), "Queries read from file");
+    queries
+}
+
+/// Benchmarks a compressor on top of a wrapper
+/// 
+/// # Arguments
+/// - `wrapper`: Wrapper applied to the compressor
+/// - `dataset_name`: Name of the dataset being evaluated
+/// - `data`: Concatenated strings
+/// - `end_positions`: Boundary positions of the strings
+/// - `queries`: Vector of random indices for access pattern simulation
+/// - `options`: Warm-up runs and query budget
+fn benchmark_wrapped<T: Compressor>(
+    wrapper: Wrapper,
+    dataset_name: String,
+    data: &[u8],
+    end_positions: &[usize],
+    queries: &[usize],
+    options: &BenchmarkOptions
+) -> BenchmarkResult {
+    let n_elements = end_positions.len() - 1;
+    match wrapper {
+        Wrapper::Sorted => {
+            let mut compressor = SortedCompressor::<T>::new(data.len(), n_elements);
+            benchmark_with_options(&mut compressor, dataset_name, data, end_positions, queries, options)
+        }
+        Wrapper::Dedup => {
+            let mut compressor = DedupCompressor::<T>::new(data.len(), n_elements);
+            let mut result = benchmark_with_options(&mut compressor, dataset_name, data, end_positions, queries, options);
+            result.dedup_ratio = Some(compressor.dedup_ratio());
+            result
+        }
+        Wrapper::Verified(algorithm) => {
+            let mut compressor = VerifiedCompressor::<T>::with_algorithm(data.len(), n_elements, algorithm);
+            benchmark_with_options(&mut compressor, dataset_name, data, end_positions, queries, options)
+        }
+    }
+}
+
+/// Benchmarks a compressor on a multi-column dataset
+/// 
+/// Evaluates each column as an independent dataset, then both record layouts
+/// (column store and row store) with record-level random access. Each evaluation
+/// is appended to the output file as a separate result.
+/// 
+/// # Arguments
+/// - `dataset_name`: Name of the dataset being evaluated
+/// - `records`: Multi-column dataset
+/// - `output_file`: Path to the output JSON file
+/// - `queries`: Vector of random record indices for access pattern simulation
+/// - `options`: Warm-up runs and query budget
+/// - `policy_description`: Query-count policy recorded in the results
+/// - `placement`: CPU cores and NUMA nodes recorded in the results
+fn benchmark_record_dataset<T: Compressor>(
+    dataset_name: &str,
+    records: &RecordDataset,
+    output_file: &Path,
+    queries: &[usize],
+    options: &BenchmarkOptions,
+    policy_description: &str,
+    placement: Option<&Placement>
+) {
+    // Each column evaluated independently
+    for (column_name, (data, end_positions)) in records.column_names.iter().zip(records.columns.iter()) {
+        let mut compressor = T::new(data.len(), end_positions.len() - 1);
+        let column_dataset_name = format!("{}[{}]", dataset_name, column_name);
+        let mut result = benchmark_with_options(&mut compressor, column_dataset_name, data, end_positions, queries, options);
+        result.query_policy = Some(policy_description.to_string());
+        result.placement = placement.cloned();
+        append_benchmark_result(&result, output_file);
+    }
+
+    // All columns together, with record-level access
+    for layout in [RecordLayout::Columns, RecordLayout::Rows] {
+        let mut store = RecordStore::<T>::new(layout, &records.columns);
+        let layout_dataset_name = format!("{}[{}]", dataset_name, layout.name());
+        let conditions = RunConditions::start();
+        let mut result = benchmark_records(&mut store, layout_dataset_name, records, queries, options);
+        result.conditions = Some(conditions.finish());
+        result.query_policy = Some(policy_description.to_string());
+        result.placement = placement.cloned();
+        append_benchmark_result(&result, output_file);
+    }
+}
+
+/// Benchmark function for record stores
+/// 
+/// Follows the same measurement protocol as `benchmark_with_options`, where random
+/// access retrieves all the columns of the queried record.
+/// 
+/// # Arguments
+/// - `store`: The record store to benchmark
+/// - `dataset_name`: Name of the dataset being evaluated
+/// - `records`: Multi-column dataset
+/// - `queries`: Vector of random record indices for access pattern simulation
+/// - `options`: Warm-up runs and query budget
+///
+/// # Returns
+/// - `BenchmarkResult`: Aggregated performance metrics for statistical analysis.
+fn benchmark_records<T: Compressor>(
+    store: &mut RecordStore<T>,
+    dataset_name: String,
+    records: &RecordDataset,
+    queries: &[usize],
+    options: &BenchmarkOptions
+) -> BenchmarkResult {
+    let warmup_iterations = options.warmup_iterations;
+    let _span = info_span!("benchmark", compressor = store.name(), dataset = %dataset_name).entered();
+    let data_size = records.data_size();
+    let buffer_size = if options.streaming { records.max_record_size() } else { data_size };
+    let mut buffer: Vec<u8> = vec![0; buffer_size + BUFFER_PADDING];
+    let mut field_ends: Vec<usize> = vec![0; store.n_columns()];
+    let data_bytes = data_size as f64;
+    let name = store.name().to_string();
+
+    // Phase 1: Compression measurement (the store is compressed even if compression is not measured)
+    let compress_span = info_span!("compress").entered();
+    let compress_profile = phase_profile::enter(&dataset_name, &name, "compress");
+    let compression_time = if options.phases.compress {
+        measure_compression(options, || store.compress(&records.columns))
+    } else {
+        store.compress(&records.columns);
+        Some(0)
+    };
+    compress_profile.finish();
+    let Some(compression_time) = compression_time else {
+        return timed_out_result(dataset_name, store.name(), options, "compress");
+    };
+    let compression_rate = data_bytes / store.space_used_bytes() as f64;
+    let compression_speed = options.phases.compress.then(|| throughput(data_size, compression_time));
+    let space_breakdown = store.space_breakdown();
+    info!(compression_rate, compression_speed, "Compression completed");
+    compress_span.exit();
+
+    // Phase 2: Decompression measurement with validation, after the warm-up runs
+    let decompression_speeds = if options.phases.decompress {
+        let expected = match store.layout() {
+            RecordLayout::Columns => records.columns.iter().flat_map(|(data, _)| data.iter().copied()).collect(),
+            RecordLayout::Rows => interleave_columns(&records.columns).0,
+        };
+        let _decompress_span = info_span!("decompress", warmup_iterations).entered();
+        let _decompress_profile = phase_profile::enter(&dataset_name, &name, "decompress");
+        let mut state = (&mut *store, &mut buffer);
+        let decompression_speeds = measure_decompression(
+            &mut state,
+            &name,
+            data_size,
+            options,
+            |(store, buffer)| {
+                if options.streaming {
+                    store.decompress_to(&mut ScratchWriter::new()).expect("Streaming decompression failed");
+                } else {
+                    store.decompress(buffer);
+                }
+            },
+            |(store, buffer)| {
+                // Verify decompression correctness according to the store layout
+                if options.streaming {
+                    let mut verifier = VerifyingWriter::new(&expected);
+                    store.decompress_to(&mut verifier).is_ok() && verifier.is_complete()
+                } else {
+                    expected.eq(&buffer[..data_size])
+                }
+            }
+        );
+        let Some(decompression_speeds) = decompression_speeds else {
+            return timed_out_result(dataset_name, store.name(), options, "decompress");
+        };
+        info!(decompression_speed = decompression_speeds.last().unwrap(), "Decompression completed");
+        Some(decompression_speeds)
+    } else {
+        None
+    };
+
+    // Phase 3: Record access latency measurement, after the warm-up runs
+    let access_runs = if options.phases.access {
+        let _random_access_span = info_span!("random_access", n_queries = queries.len(), warmup_iterations).entered();
+        let _random_access_profile = phase_profile::enter(&dataset_name, &name, "random_access");
+        let mut state = (&mut *store, &mut buffer, &mut field_ends);
+        let access_runs = measure_random_access(
+            &mut state,
+            &name,
+            queries,
+            options,
+            None,
+            |(store, buffer, field_ends), query| store.get_record_at(query, buffer, field_ends),
+            |(_, buffer, field_ends), query, _| {
+                // Verify record access correctness, field by field
+                let mut field_start = 0;
+                records.columns.iter().zip(field_ends.iter()).all(|((data, end_positions), &field_end)| {
+                    let expected_field = &data[end_positions[query]..end_positions[query + 1]];
+                    let is_correct = expected_field.eq(&buffer[field_start..field_end]);
+                    field_start = field_end;
+                    is_correct
+                })
+            }
+        );
+        let Some(access_runs) = access_runs else {
+            return timed_out_result(dataset_name, store.name(), options, "random_access");
+        };
+        info!(average_random_access_time = *access_runs.average_access_times.last().unwrap() as u64, n_queries = access_runs.n_queries(), "Random access completed");
+        Some(access_runs)
+    } else {
+        None
+    };
+    let n_queries = access_runs.as_ref().map(|runs| runs.n_queries());
+    let access_throughput = access_runs.as_ref().map(|runs| runs.throughput());
+    let average_random_access_times = access_runs.map_or_else(Vec::new, |runs| runs.average_access_times);
+
+    // Contexts do not cross fields, so the entropy does not depend on the layout
+    let (fields, field_end_positions) = interleave_columns(&records.columns);
+    let entropy = dataset_entropy(&fields, &field_end_positions);
+    let length_index = LengthIndex::new(&field_end_positions);
+
+    BenchmarkResult {
+        dataset_name,
+        compressor_name: result_name(store.name(), options),
+        compression_rate,
+        compression_speed,
+        decompression_speed: decompression_speeds.as_ref().map(|speeds| *speeds.last().unwrap()),
+        average_random_access_time: average_random_access_times.last().copied(),
+        access_throughput,
+        space_breakdown,
+        boundary_compression_rate: None,
+        dedup_ratio: None,
+        first_run: first_run_metrics(warmup_iterations, decompression_speeds.as_deref(), &average_random_access_times),
+        entropy: Some(entropy),
+        length_code: Some(length_index.code().name().to_string()),
+        n_queries,
+        query_policy: None,
+        input_order: None,
+        shared_dictionary: None,
+        batch_access: None,
+        access_stages: None,
+        latency_heatmap: None,
+        scan: None,
+        validation: None,
+        zero_copy_access: None,
+        plain_access: None,
+        hardware_counters: None,
+        access_allocations: None,
+        holdout: None,
+        compression_split: None,
+        block_size_calibration: None,
+        block_stats: None,
+        literal_tokens: None,
+        matcher: None,
+        dictionary: None,
+        updates: None,
+        placement: None,
+        conditions: None,
+        timed_out: None,
+        timer: Clock::of(options.timer).ok().map(Clock::metadata),
+        outlier: None,
+    }
+}
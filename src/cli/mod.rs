@@ -0,0 +1,8 @@
+//! Command-line entry points of the benchmark drivers
+//!
+//! The drivers parse their own arguments, so that the standalone binaries
+//! (`benchmark_all`, `benchmark_individual`) and the subcommands of `cbench` (`bench`,
+//! `bench-one`) run the same code with the same options.
+
+pub mod benchmark_all;
+pub mod benchmark_individual;
@@ -0,0 +1,72 @@
+//! Per-block statistics of block compressors
+//!
+//! The compression rate of a block compressor averages over all of its blocks, hiding
+//! datasets where a few incompressible blocks (e.g., embedded binary data or random
+//! identifiers) dominate the space. `BlockStats` reports the compressed and uncompressed
+//! size and the item count of each block after compression (see
+//! `BlockCompressor::get_block_stats`), exported as CSV for offline analysis, and
+//! `BlockStatsSummary` the spread of the block compression ratios reported in results.
+
+use crate::benchmark_utils::write_file_atomically;
+use serde::{Serialize, Deserialize};
+use std::io;
+use std::path::Path;
+
+/// Sizes and item count of a compressed block
+#[derive(Clone, Copy, Debug)]
+pub struct BlockStats {
+    pub compressed_size: usize,     // Size of the compressed data of the block (shared by duplicate blocks)
+    pub uncompressed_size: usize,   // Size of the strings of the block
+    pub n_items: usize,             // Strings in the block
+}
+
+impl BlockStats {
+    /// Returns the compression ratio of the block (uncompressed over compressed size)
+    pub fn compression_ratio(&self) -> f64 {
+        self.uncompressed_size as f64 / self.compressed_size.max(1) as f64
+    }
+}
+
+/// Spread of the compression ratios of the blocks
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct BlockStatsSummary {
+    pub n_blocks: usize,
+    pub min_ratio: f64,             // Compression ratio of the least compressible block
+    pub median_ratio: f64,          // Median compression ratio of the blocks
+    pub max_ratio: f64,             // Compression ratio of the most compressible block
+}
+
+impl BlockStatsSummary {
+    /// Summarizes the compression ratios of blocks
+    ///
+    /// # Returns
+    /// The summary, or None if there is no block
+    pub fn from_blocks(blocks: &[BlockStats]) -> Option<Self> {
+        let mut ratios: Vec<f64> = blocks.iter().map(BlockStats::compression_ratio).collect();
+        if ratios.is_empty() {
+            return None;
+        }
+        ratios.sort_unstable_by(f64::total_cmp);
+        let middle = ratios.len() / 2;
+        let median_ratio = if ratios.len().is_multiple_of(2) { (ratios[middle - 1] + ratios[middle]) / 2.0 } else { ratios[middle] };
+
+        Some(BlockStatsSummary {
+            n_blocks: ratios.len(),
+            min_ratio: ratios[0],
+            median_ratio,
+            max_ratio: ratios[ratios.len() - 1],
+        })
+    }
+}
+
+/// Writes the statistics of blocks to a CSV file, one row per block in block order
+pub fn write_block_stats_csv(blocks: &[BlockStats], path: &Path) -> io::Result<()> {
+    let mut csv = String::from("block,compressed_size,uncompressed_size,n_items,compression_ratio\n");
+    for (index, block) in blocks.iter().enumerate() {
+        csv.push_str(&format!(
+            "{},{},{},{},{:.4}\n",
+            index, block.compressed_size, block.uncompressed_size, block.n_items, block.compression_ratio()
+        ));
+    }
+    write_file_atomically(path, csv.as_bytes())
+}
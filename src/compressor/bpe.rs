@@ -3,61 +3,709 @@
 //! Classic BPE algorithm adapted for string collections with random access support.
 //! Iteratively merges the most frequent byte pairs to build a compression dictionary,
 //! providing a well-established baseline for comparison with OnPair algorithms.
+//!
+//! The token offsets of the strings can be sampled (see `set_offset_sample_rate`),
+//! trading access latency for the space of one offset per string. Token positions
+//! within long strings can be sampled too (see `set_item_sample_interval`), so that
+//! range reads start from the closest sampled token.
+//!
+//! Training indexes the positions of every pair in memory by default. It can instead
+//! train on a sample of the strings, or count the pairs of all strings exactly on disk
+//! in passes over the strings (see `BPETraining`), so that the compression lost to
+//! sampling can be measured against exact training within the same memory bound.
 
-use super::Compressor;
-use crate::bit_vector::BitVector;
+use super::dictionary_report::{DictionaryReport, DictionaryStats};
+use super::intra_item_index::IntraItemIndex;
+use super::length_index::{self, LengthIndex};
+use super::pair_counting::ExternalPairCounter;
+use super::sampling::{self, Sampling};
+use super::{max_len_of_items, to_u32_offset, Capacity, Compressor, DictionaryCompressor, ItemBoundaries, SpaceBreakdown, StreamBuffer, MAX_TOKEN_LENGTH, MAX_U32_OFFSET};
+use crate::bit_vector::{BitSet, BitVector, RoaringBitmap};
+use crate::profiler::{Stage, StageTimer};
+use std::borrow::Cow;
+use std::cmp::Reverse;
 use std::collections::BinaryHeap;
+use std::io::{self, Write};
 use rustc_hash::{FxHashMap, FxHashSet};
+use tracing::{debug, info_span, trace};
 
 /// Optimization constant for memory copy operations
-const FAST_ACCESS_SIZE: usize = 16;
+pub(crate) const FAST_ACCESS_SIZE: usize = 16;
+
+/// Maximum dictionary size supported by 16-bit token IDs
+pub const MAX_TOKENS: usize = 1 << 16;
+
+/// Merges between two progress reports of the training (at trace level)
+const MERGE_PROGRESS_INTERVAL: usize = 1024;
+
+/// Default fraction of the strings trained on by sampled training (see `BPETraining::Sampled`)
+pub const DEFAULT_SAMPLE_RATE: f64 = 0.1;
+
+/// Default buffer of the external pair counter in bytes (see `BPETraining::External`)
+pub const DEFAULT_COUNTING_MEMORY: usize = 64 << 20;
+
+/// Default number of merges per counting pass (see `BPETraining::External`)
+pub const DEFAULT_MERGES_PER_PASS: usize = 256;
+
+/// Candidate pairs kept by a counting pass per merge, as pairs sharing a token with a
+/// pair merged in the same pass are deferred
+const CANDIDATES_PER_MERGE: usize = 4;
+
+/// Training data size in bytes from which token starts are tracked in a `RoaringBitmap`
+/// rather than a `BitVector`: chunks of merged positions take less than a bit per byte,
+/// while smaller inputs keep the faster dense vector
+pub const COMPRESSED_STARTS_MIN_SIZE: usize = 1 << 30;
 
 /// Type alias for byte pairs in the merging process
 type Pair = (u16, u16);
 
+/// Merge budget and convergence criteria of the BPE training
+/// 
+/// Merging stops at the first criterion met; the default configuration merges
+/// until the dictionary is full or no adjacent pair is left, counting the pairs of
+/// every string in memory.
+#[derive(Debug, Clone, Copy)]
+pub struct BPEConfig {
+    /// Maximum number of dictionary tokens, between 256 and `MAX_TOKENS`
+    pub max_tokens: usize,
+    /// Minimum frequency of the most frequent pair to keep merging (at least 1)
+    pub min_frequency: u32,
+    /// Maximum dictionary size in bytes, including token boundaries
+    pub max_dictionary_size: Option<usize>,
+    /// Minimum net bytes saved by merging the most frequent pair, relative to the current compressed size
+    pub min_gain: Option<f64>,
+    /// Counting of the pairs during training
+    pub training: BPETraining,
+}
+
+impl Default for BPEConfig {
+    fn default() -> Self {
+        BPEConfig {
+            max_tokens: MAX_TOKENS,
+            min_frequency: 1,
+            max_dictionary_size: None,
+            min_gain: None,
+            training: BPETraining::InMemory,
+        }
+    }
+}
+
+/// Counting of the pairs of adjacent tokens during the BPE training
+///
+/// In memory, the positions of every pair are indexed, taking memory proportional to
+/// the training data. Sampled training indexes the pairs of a fraction of the strings,
+/// then replays the merges on all strings, trading compression for memory. External
+/// training counts the pairs of all strings exactly with a bounded buffer spilled to
+/// disk (see `pair_counting`), merging several pairs per counting pass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BPETraining {
+    /// Pair positions of all strings indexed in memory
+    InMemory,
+    /// Pair positions of a sample of the strings indexed in memory
+    Sampled {
+        /// Fraction of the strings trained on, in (0, 1]
+        sample_rate: f64,
+        /// Strategy picking the sampled strings
+        sampling: Sampling,
+    },
+    /// Pairs of all strings counted on disk, in passes over the strings
+    External {
+        /// Pair keys buffered before spilling a sorted run to disk, in bytes
+        memory_budget: usize,
+        /// Maximum number of pairs merged after each counting pass, at least 1
+        merges_per_pass: usize,
+    },
+}
+
+/// Criterion that stopped the BPE training
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// No pair of adjacent tokens is left to merge
+    Exhausted,
+    /// The dictionary holds `max_tokens` tokens
+    MaxTokens,
+    /// The most frequent pair occurs less than `min_frequency` times
+    MinFrequency,
+    /// The next token would exceed `max_dictionary_size`
+    DictionarySize,
+    /// The next merge would save less than `min_gain`
+    MinGain,
+}
+
+/// Outcome of a BPE training
+#[derive(Debug, Clone, Copy)]
+pub struct TrainingReport {
+    pub iterations: usize,      // Merges executed
+    pub stop_reason: StopReason,
+    pub merged_fraction: f64,   // Fraction of the input positions merged into a preceding token
+}
+
 /// BPE compressor implementing classic byte pair encoding
 /// 
 /// Provides a reference implementation of traditional BPE for performance comparison.
 /// Builds dictionary through iterative merging of most frequent adjacent token pairs,
 /// maintaining compatibility with random access requirements.
 pub struct BPECompressor {
+    config: BPEConfig,                      // Merge budget and convergence criteria
+    training_report: Option<TrainingReport>, // Outcome of the last training, if any
+    offset_sample_rate: Option<usize>,      // Distance between sampled string offsets, if sampled
+    item_sample_interval: Option<usize>,    // Decoded bytes between sampled tokens within strings, if sampled
+    name: String,                           // Display name, reflecting the sampling
     compressed_data: Vec<u16>,              // Token ID sequences (2 bytes per token)
     item_end_positions: Vec<usize>,         // Compressed string boundaries
+    sampled_offsets: Option<LengthIndex>,   // Sampled string boundaries, replacing the plain ones if configured
+    item_samples: Option<IntraItemIndex>,   // Sampled token positions within long strings, if configured
     dictionary: Vec<u8>,                    // Token definitions (variable length)
     dictionary_end_positions: Vec<u32>,     // Token boundary positions in dictionary
 }
 
 impl Compressor for BPECompressor {
     fn new(data_size: usize, n_elements: usize) -> Self {
-        BPECompressor {
+        Self::with_max_tokens(data_size, n_elements, MAX_TOKENS)
+    }
+
+    fn compress(&mut self, data: &[u8], end_positions: &[usize]) {
+        match self.config.training {
+            BPETraining::InMemory if data.len() >= COMPRESSED_STARTS_MIN_SIZE => self.compress_in_memory::<RoaringBitmap>(data, end_positions),
+            BPETraining::InMemory => self.compress_in_memory::<BitVector>(data, end_positions),
+            BPETraining::Sampled { .. } => {
+                // Merges learned on the sample are replayed on every string
+                let mut trainer = Self::with_config(0, 0, self.config);
+                let merges = info_span!("train").in_scope(|| trainer.train_merges(data, end_positions));
+                trainer.log_training();
+                self.training_report = trainer.training_report;
+                self.compress_with_dictionary(data, end_positions, &BPEDictionary::new(trainer, merges));
+            }
+            BPETraining::External { .. } => {
+                // The tokens left by the last merging pass are the compressed strings
+                let (tokens, item_end_positions, _) = info_span!("train").in_scope(|| self.train_external(data, end_positions));
+                self.log_training();
+                self.compressed_data = tokens;
+                self.item_end_positions = item_end_positions;
+                self.sample_item_tokens();
+                self.sample_offsets();
+            }
+        }
+    }
+
+    fn decompress(&self, buffer: &mut [u8]) -> usize {
+        let dict_ptr = self.dictionary.as_ptr();
+        let end_positions_ptr = self.dictionary_end_positions.as_ptr();
+        let mut size = 0;
+
+        for &token_id in self.compressed_data.iter(){
+            unsafe {
+                let dict_start = *end_positions_ptr.add(token_id as usize) as usize;
+                let dict_end = *end_positions_ptr.add(token_id as usize + 1) as usize;
+                let length = dict_end - dict_start;
+
+                let mut src = dict_ptr.add(dict_start);
+                let mut dst = buffer.as_mut_ptr().add(size);
+                std::ptr::copy_nonoverlapping(src, dst, FAST_ACCESS_SIZE);
+
+                if length > FAST_ACCESS_SIZE {
+                    src = src.add(FAST_ACCESS_SIZE); 
+                    dst = dst.add(FAST_ACCESS_SIZE);
+                    std::ptr::copy_nonoverlapping(src, dst, length - FAST_ACCESS_SIZE);
+                }
+
+                size += length;
+            }
+        }
+
+        size
+    }
+
+    fn decompress_to<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let mut stream = StreamBuffer::new(writer);
+        for &token_id in self.compressed_data.iter() {
+            let dict_start = self.dictionary_end_positions[token_id as usize] as usize;
+            let dict_end = self.dictionary_end_positions[token_id as usize + 1] as usize;
+            let length = dict_end - dict_start;
+            let buffer = stream.reserve(length)?;
+
+            unsafe {
+                let mut src = self.dictionary.as_ptr().add(dict_start);
+                let mut dst = buffer.as_mut_ptr();
+                std::ptr::copy_nonoverlapping(src, dst, FAST_ACCESS_SIZE);
+
+                if length > FAST_ACCESS_SIZE {
+                    src = src.add(FAST_ACCESS_SIZE); 
+                    dst = dst.add(FAST_ACCESS_SIZE);
+                    std::ptr::copy_nonoverlapping(src, dst, length - FAST_ACCESS_SIZE);
+                }
+            }
+            stream.commit(length);
+        }
+        stream.finish()
+    }
+
+    fn get_item_at(&mut self, index: usize, buffer: &mut [u8]) -> usize {
+        let (item_start, item_end) = self.item_bounds(index);
+        let dict_ptr = self.dictionary.as_ptr();
+        let end_positions_ptr = self.dictionary_end_positions.as_ptr();
+        let mut size = 0;
+
+        for &token_id in self.compressed_data[item_start..item_end].iter() {
+            unsafe {
+                let dict_start = *end_positions_ptr.add(token_id as usize) as usize;
+                let dict_end = *end_positions_ptr.add(token_id as usize + 1) as usize;
+                let length = dict_end - dict_start;
+
+                let mut src = dict_ptr.add(dict_start);
+                let mut dst = buffer.as_mut_ptr().add(size);
+                std::ptr::copy_nonoverlapping(src, dst, FAST_ACCESS_SIZE);
+
+                if length > FAST_ACCESS_SIZE {
+                    src = src.add(FAST_ACCESS_SIZE); 
+                    dst = dst.add(FAST_ACCESS_SIZE);
+                    std::ptr::copy_nonoverlapping(src, dst, length - FAST_ACCESS_SIZE);
+                }
+
+                size += length;
+            }
+        }
+
+        size
+    }
+
+    fn get_item_at_profiled(&mut self, index: usize, buffer: &mut [u8], timer: &mut StageTimer) -> usize {
+        let (item_start, item_end) = self.item_bounds(index);
+        timer.lap(Stage::Lookup);
+
+        // Tokens are resolved to dictionary spans first, then copied
+        timer.spans.clear();
+        for &token_id in self.compressed_data[item_start..item_end].iter() {
+            let dict_start = self.dictionary_end_positions[token_id as usize] as usize;
+            let dict_end = self.dictionary_end_positions[token_id as usize + 1] as usize;
+            timer.spans.push((dict_start, dict_end - dict_start));
+        }
+        timer.lap(Stage::Decode);
+
+        let size = copy_spans(&self.dictionary, &timer.spans, buffer);
+        timer.lap(Stage::Copy);
+        size
+    }
+
+    fn get_item_range(&mut self, index: usize, start: usize, end: usize, buffer: &mut [u8]) -> usize {
+        let (item_start, item_end) = self.item_bounds(index);
+        let (token_position, mut offset) = match &self.item_samples {
+            Some(item_samples) => item_samples.seek(index, start),
+            None => (0, 0),
+        };
+
+        // Tokens ending before the range are skipped without copying their bytes
+        let mut position = item_start + token_position;
+        while position < item_end {
+            let length = self.token_length(self.compressed_data[position]);
+            if offset + length > start {
+                break;
+            }
+            offset += length;
+            position += 1;
+        }
+
+        // Tokens are copied from the one holding the first byte of the range
+        let mut size = 0;
+        while position < item_end && offset + size < end {
+            let token_id = self.compressed_data[position] as usize;
+            let dict_start = self.dictionary_end_positions[token_id] as usize;
+            let dict_end = self.dictionary_end_positions[token_id + 1] as usize;
+            size += copy_spans(&self.dictionary, &[(dict_start, dict_end - dict_start)], &mut buffer[size..]);
+            position += 1;
+        }
+
+        // Bytes of the first token preceding the range are dropped
+        buffer.copy_within(start - offset..end - offset, 0);
+        end - start
+    }
+
+    fn len_of_item(&self, index: usize) -> usize {
+        // Sums the lengths of the item tokens, without copying their bytes
+        let (item_start, item_end) = self.item_bounds(index);
+        self.compressed_data[item_start..item_end]
+            .iter()
+            .map(|&token_id| {
+                let token_id = token_id as usize;
+                (self.dictionary_end_positions[token_id + 1] - self.dictionary_end_positions[token_id]) as usize
+            })
+            .sum()
+    }
+
+    fn max_item_len(&self) -> usize {
+        max_len_of_items(self, self.item_end_positions().len().saturating_sub(1))
+    }
+
+    /// Reports the limits of BPE: training data positions are stored as 32-bit indices
+    fn capacity(&self) -> Capacity {
+        Capacity { max_data_size: MAX_U32_OFFSET, ..Capacity::UNLIMITED }
+    }
+
+    fn space_used_bytes(&self) -> usize {
+        self.space_breakdown().total()
+    }
+
+    fn space_breakdown(&self) -> SpaceBreakdown {
+        SpaceBreakdown {
+            payload: self.compressed_data.len() * std::mem::size_of::<u16>(),
+            dictionary: self.dictionary.len() + self.dictionary_end_positions.len() * std::mem::size_of::<u32>(),
+            item_offsets: self.item_offsets_size() + self.item_samples.as_ref().map_or(0, IntraItemIndex::size_bytes),
+            block_metadata: 0,
+            id_map: 0,
+            checksums: 0,
+        }
+    }
+
+    fn item_boundaries(&self) -> ItemBoundaries {
+        match self.sampled_offsets {
+            Some(_) => ItemBoundaries::Sampled,
+            None => ItemBoundaries::Offsets,
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Dictionary trained by BPE, with the merges to replay on new data
+pub struct BPEDictionary {
+    dictionary: Vec<u8>,                    // Token definitions (variable length)
+    dictionary_end_positions: Vec<u32>,     // Token boundary positions in dictionary
+    merges: FxHashMap<Pair, u16>,           // Token formed by each merged pair (lower IDs merged first)
+}
+
+impl BPEDictionary {
+    /// Takes the dictionary out of a trained compressor, with its merges in merge order
+    fn new(trainer: BPECompressor, merges: Vec<Pair>) -> Self {
+        BPEDictionary {
+            dictionary: trainer.dictionary,
+            dictionary_end_positions: trainer.dictionary_end_positions,
+            merges: merges.into_iter().enumerate().map(|(i, pair)| (pair, (256 + i) as u16)).collect(),
+        }
+    }
+}
+
+impl DictionaryCompressor for BPECompressor {
+    type Dictionary = BPEDictionary;
+
+    fn train_dictionary(&self, data: &[u8], end_positions: &[usize]) -> BPEDictionary {
+        let mut trainer = Self::with_config(0, 0, self.config);
+        let merges = trainer.train_merges(data, end_positions);
+        BPEDictionary::new(trainer, merges)
+    }
+
+    fn compress_with_dictionary(&mut self, data: &[u8], end_positions: &[usize], dictionary: &BPEDictionary) {
+        self.dictionary = dictionary.dictionary.clone();
+        self.dictionary_end_positions = dictionary.dictionary_end_positions.clone();
+        let _parse_span = info_span!("parse").entered();
+
+        // Merges are replayed on each string in training order, left to right as in training
+        let mut tokens: Vec<u16> = Vec::new();
+        self.item_end_positions.push(0);
+        for window in end_positions.windows(2) {
+            tokens.clear();
+            tokens.extend(data[window[0]..window[1]].iter().map(|&b| b as u16));
+
+            loop {
+                let next_merge = tokens
+                    .windows(2)
+                    .filter_map(|pair| dictionary.merges.get(&(pair[0], pair[1])).map(|&id| (id, (pair[0], pair[1]))))
+                    .min();
+                let Some((merged_id, pair)) = next_merge else { break };
+
+                let mut read = 0;
+                let mut write = 0;
+                while read < tokens.len() {
+                    if read + 1 < tokens.len() && (tokens[read], tokens[read + 1]) == pair {
+                        tokens[write] = merged_id;
+                        read += 2;
+                    } else {
+                        tokens[write] = tokens[read];
+                        read += 1;
+                    }
+                    write += 1;
+                }
+                tokens.truncate(write);
+            }
+
+            self.compressed_data.extend_from_slice(&tokens);
+            self.item_end_positions.push(self.compressed_data.len());
+        }
+        self.sample_item_tokens();
+        self.sample_offsets();
+    }
+
+    fn dictionary_report(&self) -> DictionaryReport {
+        let item_end_positions = self.item_end_positions();
+        let items = item_end_positions
+            .windows(2)
+            .map(|w| self.compressed_data[w[0]..w[1]].iter().map(|&token_id| token_id as usize));
+        DictionaryReport::build(self.name(), &self.dictionary, &self.dictionary_end_positions, items)
+    }
+
+    fn dictionary_stats(&self) -> DictionaryStats {
+        let item_end_positions = self.item_end_positions();
+        let items = item_end_positions
+            .windows(2)
+            .map(|w| self.compressed_data[w[0]..w[1]].iter().map(|&token_id| token_id as usize));
+        DictionaryStats::build(&self.dictionary_end_positions, items)
+    }
+}
+
+impl BPECompressor {
+    /// Creates a compressor with a custom dictionary capacity
+    /// 
+    /// # Arguments
+    /// - `data_size`: Total size of input data in bytes
+    /// - `n_elements`: Number of individual strings in the dataset
+    /// - `max_tokens`: Maximum number of dictionary tokens, between 256 and `MAX_TOKENS`
+    pub fn with_max_tokens(data_size: usize, n_elements: usize, max_tokens: usize) -> Self {
+        Self::with_config(data_size, n_elements, BPEConfig { max_tokens, ..BPEConfig::default() })
+    }
+
+    /// Creates a compressor with a custom merge budget and convergence criteria
+    /// 
+    /// # Arguments
+    /// - `data_size`: Total size of input data in bytes
+    /// - `n_elements`: Number of individual strings in the dataset
+    /// - `config`: Merge budget and convergence criteria of the training
+    pub fn with_config(data_size: usize, n_elements: usize, config: BPEConfig) -> Self {
+        assert!((256..=MAX_TOKENS).contains(&config.max_tokens), "max_tokens must be between 256 and {}", MAX_TOKENS);
+        assert!(config.min_frequency >= 1, "min_frequency must be at least 1");
+        match config.training {
+            BPETraining::InMemory => {}
+            BPETraining::Sampled { sample_rate, .. } => {
+                assert!(sample_rate > 0.0 && sample_rate <= 1.0, "sample_rate must be in (0, 1]");
+            }
+            BPETraining::External { merges_per_pass, .. } => {
+                assert!(merges_per_pass >= 1, "merges_per_pass must be at least 1");
+            }
+        }
+
+        let mut compressor = BPECompressor {
+            config,
+            training_report: None,
+            offset_sample_rate: None,
+            item_sample_interval: None,
+            name: String::from("BPE"),
             compressed_data: Vec::with_capacity(data_size),
             item_end_positions: Vec::with_capacity(n_elements),
+            sampled_offsets: None,
+            item_samples: None,
             dictionary: Vec::new(),
             dictionary_end_positions: Vec::new(),
+        };
+        compressor.name = compressor.describe();
+        compressor
+    }
+
+    /// Samples the token offsets of the strings
+    /// 
+    /// The offset of every `sample_rate`-th string is stored, with the token count of
+    /// the strings in between (see `length_index`): an access scans up to
+    /// `sample_rate - 1` counts from the closest sample. Larger rates save space at the
+    /// cost of latency. Takes effect on the next compression.
+    /// 
+    /// # Arguments
+    /// - `sample_rate`: Distance between sampled offsets, at least 1
+    pub fn set_offset_sample_rate(&mut self, sample_rate: usize) {
+        assert!(sample_rate >= 1, "sample_rate must be at least 1");
+        self.offset_sample_rate = Some(sample_rate);
+        self.name = self.describe();
+    }
+
+    /// Samples the token positions within long strings
+    /// 
+    /// For every string longer than `sample_interval` bytes, the token holding every
+    /// `sample_interval`-th byte is stored with its offset (see `intra_item_index`):
+    /// `get_item_range` then sums the lengths of the tokens spanning at most
+    /// `sample_interval` bytes before the range, instead of all tokens from the start
+    /// of the string. Takes effect on the next compression.
+    /// 
+    /// # Arguments
+    /// - `sample_interval`: Decoded bytes between sampled tokens, at least 1
+    pub fn set_item_sample_interval(&mut self, sample_interval: usize) {
+        assert!(sample_interval >= 1, "sample_interval must be at least 1");
+        self.item_sample_interval = Some(sample_interval);
+        self.name = self.describe();
+    }
+
+    /// Returns the display name of the current configuration
+    fn describe(&self) -> String {
+        let mut options = Vec::new();
+        match self.config.training {
+            BPETraining::InMemory => {}
+            BPETraining::Sampled { sample_rate, .. } => options.push(format!("sampled training {}", sample_rate)),
+            BPETraining::External { .. } => options.push(String::from("external counting")),
+        }
+        if let Some(sample_rate) = self.offset_sample_rate {
+            options.push(format!("sampled offsets {}", sample_rate));
+        }
+        if let Some(sample_interval) = self.item_sample_interval {
+            options.push(format!("item samples {}", sample_interval));
+        }
+
+        if options.is_empty() {
+            String::from("BPE")
+        } else {
+            format!("BPE ({})", options.join(", "))
         }
     }
 
-    fn compress(&mut self, data: &[u8], end_positions: &[usize]) {
-        // Initialize the dictionary with single-byte tokens
-        self.dictionary_end_positions.push(0);
-        for i in 0..256 {
-            let token = vec![i as u8];
-            self.dictionary.extend(&token);
-            self.dictionary_end_positions.push(self.dictionary.len() as u32);
+    /// Takes the compressed representation out of a compressor
+    /// 
+    /// # Returns
+    /// Token IDs of all strings, boundary positions of the strings in the token IDs
+    /// (starting with 0), token definitions and token boundary positions in them
+    pub(crate) fn into_parts(self) -> (Vec<u16>, Vec<usize>, Vec<u8>, Vec<u32>) {
+        let item_end_positions = self.item_end_positions().into_owned();
+        (self.compressed_data, item_end_positions, self.dictionary, self.dictionary_end_positions)
+    }
+
+    /// Returns the positions of the first and past the last token of a string
+    #[inline(always)]
+    fn item_bounds(&self, index: usize) -> (usize, usize) {
+        match &self.sampled_offsets {
+            Some(sampled_offsets) => sampled_offsets.bounds(index),
+            None => (self.item_end_positions[index], self.item_end_positions[index + 1]),
+        }
+    }
+
+    /// Returns the token boundary positions of all strings, starting with 0
+    fn item_end_positions(&self) -> Cow<'_, [usize]> {
+        match &self.sampled_offsets {
+            Some(sampled_offsets) => Cow::Owned(sampled_offsets.end_positions()),
+            None => Cow::Borrowed(&self.item_end_positions),
+        }
+    }
+
+    /// Returns the size of the string offsets in bytes, sampled or plain
+    fn item_offsets_size(&self) -> usize {
+        match &self.sampled_offsets {
+            Some(sampled_offsets) => sampled_offsets.size_bytes(),
+            None => self.item_end_positions.len() * std::mem::size_of::<usize>(),
+        }
+    }
+
+    /// Samples the token positions within long strings, if configured
+    /// 
+    /// Runs before `sample_offsets`, while the plain string offsets are available.
+    fn sample_item_tokens(&mut self) {
+        let compressor = &*self;
+        let item_samples = self.item_sample_interval.map(|sample_interval| {
+            let items = compressor.item_end_positions.windows(2).map(move |w| {
+                compressor.compressed_data[w[0]..w[1]].iter().map(move |&token_id| compressor.token_length(token_id))
+            });
+            IntraItemIndex::build(sample_interval, items)
+        });
+        self.item_samples = item_samples;
+    }
+
+    /// Replaces the plain string offsets with sampled ones, if configured
+    fn sample_offsets(&mut self) {
+        self.sampled_offsets = self
+            .offset_sample_rate
+            .map(|sample_rate| length_index::sample_item_offsets(&mut self.item_end_positions, sample_rate));
+    }
+
+    /// Returns the number of merges executed by the last training and why it stopped
+    /// 
+    /// `None` if the compressor has not been trained yet.
+    pub fn training_report(&self) -> Option<TrainingReport> {
+        self.training_report
+    }
+
+    /// Logs the dictionary built by the last training (at debug level)
+    fn log_training(&self) {
+        let report = self.training_report.unwrap();
+        debug!(
+            n_tokens = self.dictionary_end_positions.len() - 1,
+            dictionary_size = self.dictionary.len(),
+            iterations = report.iterations,
+            stop_reason = ?report.stop_reason,
+            merged_fraction = report.merged_fraction,
+            "Dictionary built"
+        );
+    }
+
+    /// Builds the dictionary with the configured training (see `BPETraining`)
+    ///
+    /// # Returns
+    /// The merged pairs in merge order (pair `i` forms token `256 + i`)
+    fn train_merges(&mut self, data: &[u8], end_positions: &[usize]) -> Vec<Pair> {
+        match self.config.training {
+            BPETraining::InMemory => self.train_in_memory(data, end_positions),
+            BPETraining::Sampled { sample_rate, sampling } => {
+                let indices = sampling::sample_strings(end_positions.len() - 1, sample_rate, sampling);
+                let (sample_data, sample_end_positions) = gather_strings(data, end_positions, &indices);
+                debug!(n_sampled = indices.len(), sample_size = sample_data.len(), "Training sample drawn");
+                self.train_in_memory(&sample_data, &sample_end_positions)
+            }
+            BPETraining::External { .. } => self.train_external(data, end_positions).2,
+        }
+    }
+
+    /// Trains in memory and stores the tokens left by the last merge as the compressed strings
+    fn compress_in_memory<B: BitSet>(&mut self, data: &[u8], end_positions: &[usize]) {
+        let (token_ids, bv, _) = info_span!("train").in_scope(|| self.train::<B, FrequencyBuckets>(data, end_positions));
+        self.log_training();
+
+        // Store the compressed data
+        let _parse_span = info_span!("parse").entered();
+        let mut i = 0;
+        for &end_position in end_positions.iter() {
+            while i < end_position {
+                if bv.contains(i) {
+                    self.compressed_data.push(token_ids[i]);
+                }
+                i += 1;
+            }
+            self.item_end_positions.push(self.compressed_data.len());
         }
+        self.sample_item_tokens();
+        self.sample_offsets();
+    }
+
+    /// Builds the dictionary in memory, picking the bit vector of token starts by the data size
+    ///
+    /// # Returns
+    /// The merged pairs in merge order (pair `i` forms token `256 + i`)
+    fn train_in_memory(&mut self, data: &[u8], end_positions: &[usize]) -> Vec<Pair> {
+        if data.len() >= COMPRESSED_STARTS_MIN_SIZE {
+            self.train::<RoaringBitmap, FrequencyBuckets>(data, end_positions).2
+        } else {
+            self.train::<BitVector, FrequencyBuckets>(data, end_positions).2
+        }
+    }
+
+    /// Builds the dictionary by merging the most frequent pairs of adjacent tokens
+    /// 
+    /// Pairs forming tokens longer than `MAX_TOKEN_LENGTH` bytes are never merged.
+    /// Token starts are tracked in a bit vector of type `B`, a `RoaringBitmap` for inputs of
+    /// at least `COMPRESSED_STARTS_MIN_SIZE` bytes, whose memory is logged once merging stops.
+    /// The most frequent pair is selected by a `PairSelection` of type `S`, `FrequencyBuckets`
+    /// outside tests.
+    /// 
+    /// # Returns
+    /// Token ID of each input position, a bit vector marking with ones the positions
+    /// starting a token, and the merged pairs in merge order (pair `i` forms token `256 + i`)
+    fn train<B: BitSet, S: PairSelection>(&mut self, data: &[u8], end_positions: &[usize]) -> (Vec<u16>, B, Vec<Pair>) {
+        self.capacity().assert_fits(end_positions, self.name());
+        self.init_dictionary();
 
         // Initialize Token IDs
         let mut token_ids: Vec<u16> = data.iter().map(|&b| b as u16).collect();
 
         // A bitvector indicates with zeroes the positions of merged bytes
-        let mut bv = BitVector::with_ones(data.len());
+        let mut bv = B::with_ones(data.len());
 
         // Strings end positions are used to avoid merging pairs across different strings
         let end_positions_set: FxHashSet<usize> = end_positions.iter().skip(1).copied().collect();
 
         // Initialize pair positions  
         let mut pair_pos: FxHashMap<Pair, FxHashSet<u32>> = FxHashMap::default();
-        for i in 0..data.len()-1 {
+        for i in 0..data.len().saturating_sub(1) {
             if end_positions_set.contains(&(i+1)) {
                 continue;
             }
@@ -65,32 +713,49 @@ impl Compressor for BPECompressor {
             let t2 = token_ids[i+1];
             pair_pos
                 .entry((t1, t2))
-                .or_insert(FxHashSet::default())
+                .or_default()
                 .insert(i as u32);
         }
 
-        // Initialize heap tracking the most frequent pairs
-        let mut top_pairs: BinaryHeap<(u32, Pair)> = BinaryHeap::new();
+        // Initialize buckets tracking the most frequent pairs
+        let max_frequency = pair_pos.values().map(|pos_set| pos_set.len()).max().unwrap_or(0);
+        let mut top_pairs = S::new(max_frequency);
         for (pair, pos_set) in pair_pos.iter() {
-            top_pairs.push((pos_set.len() as u32, *pair));
+            top_pairs.insert(*pair, pos_set.len());
         }
 
         // Merge pairs
+        let mut merges: Vec<Pair> = Vec::new();
         let mut next_id = 256;
-        while !top_pairs.is_empty(){
-            // Get the most frequent pair
-            let (freq, top_pair) = top_pairs.pop().unwrap();
-            let current_freq = pair_pos[&top_pair].len() as u32;
-            
-            // Check if the frequency is up-to-date
-            if freq != current_freq {
-                top_pairs.push((current_freq, top_pair));
-                continue; 
+        let mut n_tokens = data.len();
+        let stop_reason = loop {
+            if next_id as usize >= self.config.max_tokens {
+                break StopReason::MaxTokens;
             }
 
+            // Get the most frequent pair
+            let Some((current_freq, top_pair)) = top_pairs.pop_max(|pair| pair_pos[&pair].len()) else {
+                break StopReason::Exhausted;
+            };
+            let current_freq = current_freq as u32;
+
             // Stop if the most frequent pair has frequency 0
             if current_freq == 0 {
-                break;
+                break StopReason::Exhausted;
+            }
+            if current_freq < self.config.min_frequency {
+                break StopReason::MinFrequency;
+            }
+
+            // Pairs forming overlong tokens are dropped, as their length never changes
+            let (t1, t2) = top_pair;
+            let token_length = self.token_length(t1) + self.token_length(t2);
+            if token_length > MAX_TOKEN_LENGTH {
+                continue;
+            }
+
+            if let Some(stop_reason) = self.check_budget(current_freq as usize, token_length, n_tokens) {
+                break stop_reason;
             }
 
             // Get the positions of the top pair
@@ -98,22 +763,10 @@ impl Compressor for BPECompressor {
             positions.sort();
 
             // Let t1 and t2 be the tokens to merge
-            let (t1, t2) = top_pair;
+            merges.push(top_pair);
 
             // Add the new token to the dictionary
-            let t1_data = self.dictionary[
-                self.dictionary_end_positions[t1 as usize] as usize
-                ..
-                self.dictionary_end_positions[t1 as usize + 1] as usize
-            ].to_vec();
-            let t2_data = self.dictionary[
-                self.dictionary_end_positions[t2 as usize] as usize
-                ..
-                self.dictionary_end_positions[t2 as usize + 1] as usize
-            ].to_vec();
-            self.dictionary.extend(&t1_data);
-            self.dictionary.extend(&t2_data);
-            self.dictionary_end_positions.push(self.dictionary.len() as u32);
+            self.push_token(t1, t2);
 
             // Keep track of new pairs that will form after merging
             let mut new_pairs: FxHashSet<Pair> = FxHashSet::default();
@@ -121,7 +774,7 @@ impl Compressor for BPECompressor {
             // Update occurrences of the top pair
             for &position in positions.iter() {
                 // If position was already merged, skip
-                if unsafe { !bv.get_unchecked(position as usize) } {
+                if !bv.contains(position as usize) {
                     continue;
                 }
 
@@ -132,23 +785,23 @@ impl Compressor for BPECompressor {
                 let t3_pos = bv.next_one(t2_pos); // t3_pos is None if t2 is the last token
 
                 // Update (t0, t1) and (t0, next_id)  
-                if t0_pos.is_some() && !end_positions_set.contains(&t1_pos) {
-                    let t0 = token_ids[t0_pos.unwrap()];
+                if let Some(t0_pos) = t0_pos.filter(|_| !end_positions_set.contains(&t1_pos)) {
+                    let t0 = token_ids[t0_pos];
                     // Update (t0, t1)
                     if (t0, t1) != top_pair {
-                        pair_pos.get_mut(&(t0, t1)).unwrap().remove(&(t0_pos.unwrap() as u32));
+                        pair_pos.get_mut(&(t0, t1)).unwrap().remove(&(t0_pos as u32));
                     }
                     // Update (t0, next_id)
                     new_pairs.insert((t0, next_id));
                     pair_pos
                             .entry((t0, next_id))
-                            .or_insert(FxHashSet::default())
-                            .insert(t0_pos.unwrap() as u32);
+                            .or_default()
+                            .insert(t0_pos as u32);
                 }
 
                 // Update (t2, t3) and (next_id, t3)
-                if t3_pos.is_some() && !end_positions_set.contains(&t3_pos.unwrap()){
-                    let t3 = token_ids[t3_pos.unwrap()];
+                if let Some(t3_pos) = t3_pos.filter(|t3_pos| !end_positions_set.contains(t3_pos)) {
+                    let t3 = token_ids[t3_pos];
                     // Update (t2, t3)
                     if (t2, t3) != top_pair {
                         pair_pos.get_mut(&(t2, t3)).unwrap().remove(&(t2_pos as u32));
@@ -157,111 +810,388 @@ impl Compressor for BPECompressor {
                     new_pairs.insert((next_id, t3));
                     pair_pos
                             .entry((next_id, t3))
-                            .or_insert(FxHashSet::default())
+                            .or_default()
                             .insert(t1_pos as u32);
                 }
     
                 // set t2_pos to 0 to merge t1 and t2
-                bv.set(t2_pos as usize, false);
+                bv.set(t2_pos, false);
+                n_tokens -= 1;
     
                 // Update token_ids
                 token_ids[t1_pos] = next_id;
             }
 
-            // Update the top_pairs heap with new pairs.
-            // We don't need to update old pairs because they are already in the heap and their frequency can only decrease; 
-            // the check at the beginning of the merge loop ensures we operate with up-to-date frequencies.
+            // Add the new pairs to the buckets, once their occurrences are all known.
+            // Old pairs stay in their bucket: their frequency can only decrease, and
+            // `pop_max` moves them down when they reach the top bucket.
             for &new_pair in new_pairs.iter() {
-                let freq = pair_pos[&new_pair].len() as u32;
-                top_pairs.push((freq, new_pair));
+                top_pairs.insert(new_pair, pair_pos[&new_pair].len());
             }
     
+            if merges.len().is_multiple_of(MERGE_PROGRESS_INTERVAL) {
+                trace!(merges = merges.len(), merged_fraction = 1.0 - bv.density(), "Merge progress");
+            }
+
             // If the dictionary is full, stop merging
             if next_id == u16::MAX {
-                break; 
+                break StopReason::MaxTokens;
             }
 
             next_id += 1;
-        }
+        };
 
-        // Store the compressed data
-        let mut i = 0;
-        for &end_position in end_positions.iter() {
-            while i < end_position {
-                if unsafe{ bv.get_unchecked(i) } {
-                    self.compressed_data.push(token_ids[i]);
-                }
-                i += 1;
-            }
-            self.item_end_positions.push(self.compressed_data.len());
-        }
+        // Every token starts at one set bit
+        debug_assert_eq!(bv.ones_count(), n_tokens);
+        let merged_fraction = if data.is_empty() { 0.0 } else { 1.0 - bv.density() };
+        debug!(token_starts_bytes = bv.size_bytes(), "Token starts tracked");
+        self.training_report = Some(TrainingReport { iterations: merges.len(), stop_reason, merged_fraction });
+
+        (token_ids, bv, merges)
     }
 
-    fn decompress(&self, buffer: &mut [u8]) -> usize {
-        let dict_ptr = self.dictionary.as_ptr();
-        let end_positions_ptr = self.dictionary_end_positions.as_ptr();
-        let mut size = 0;
+    /// Builds the dictionary by merging the most frequent pairs, counted exactly on disk
+    ///
+    /// Each pass counts the pairs of adjacent tokens of all strings with an
+    /// `ExternalPairCounter`, then merges up to `merges_per_pass` of the most frequent
+    /// pairs in a second scan of the strings. Pairs merged in the same pass share no
+    /// token, so that merging one leaves the counts of the others exact; with one merge
+    /// per pass, each merge picks the most frequent pair of the current tokens. Pairs
+    /// forming tokens longer than `MAX_TOKEN_LENGTH` bytes are never merged.
+    ///
+    /// # Returns
+    /// Token IDs of all strings, boundary positions of the strings in them (starting
+    /// with 0), and the merged pairs in merge order (pair `i` forms token `256 + i`)
+    fn train_external(&mut self, data: &[u8], end_positions: &[usize]) -> (Vec<u16>, Vec<usize>, Vec<Pair>) {
+        let BPETraining::External { memory_budget, merges_per_pass } = self.config.training else {
+            unreachable!("external training requires BPETraining::External");
+        };
+        self.capacity().assert_fits(end_positions, self.name());
+        self.init_dictionary();
 
-        for &token_id in self.compressed_data.iter(){
-            unsafe {
-                let dict_start = *end_positions_ptr.add(token_id as usize) as usize;
-                let dict_end = *end_positions_ptr.add(token_id as usize + 1) as usize;
-                let length = dict_end - dict_start;
+        let mut tokens: Vec<u16> = data.iter().map(|&b| b as u16).collect();
+        let mut item_end_positions = end_positions.to_vec();
+        let mut merges: Vec<Pair> = Vec::new();
+        let mut n_passes = 0;
+        let stop_reason = loop {
+            if 256 + merges.len() >= self.config.max_tokens {
+                break StopReason::MaxTokens;
+            }
+            n_passes += 1;
 
-                let mut src = dict_ptr.add(dict_start);
-                let mut dst = buffer.as_mut_ptr().add(size);
-                std::ptr::copy_nonoverlapping(src, dst, FAST_ACCESS_SIZE);
+            // First pass: count the pairs within strings, keeping the most frequent ones
+            let mut counter = ExternalPairCounter::new(memory_budget);
+            for window in item_end_positions.windows(2) {
+                for pair in tokens[window[0]..window[1]].windows(2) {
+                    counter.push((pair[0], pair[1])).expect("Failed to spill BPE pair counts");
+                }
+            }
+            let n_runs = counter.n_runs();
+            let n_candidates = merges_per_pass * CANDIDATES_PER_MERGE;
+            let mut candidates: BinaryHeap<Reverse<(usize, Pair)>> = BinaryHeap::with_capacity(n_candidates + 1);
+            let mut max_frequency = 0;
+            for entry in counter.counts().expect("Failed to read BPE pair counts") {
+                let (pair, frequency) = entry.expect("Failed to read BPE pair counts");
+                if self.token_length(pair.0) + self.token_length(pair.1) > MAX_TOKEN_LENGTH {
+                    continue;
+                }
+                max_frequency = max_frequency.max(frequency);
+                candidates.push(Reverse((frequency, pair)));
+                if candidates.len() > n_candidates {
+                    candidates.pop();
+                }
+            }
+            trace!(pass = n_passes, runs = n_runs, max_frequency, "Pairs counted");
+            if max_frequency == 0 {
+                break StopReason::Exhausted;
+            }
 
-                if length > FAST_ACCESS_SIZE {
-                    src = src.add(FAST_ACCESS_SIZE); 
-                    dst = dst.add(FAST_ACCESS_SIZE);
-                    std::ptr::copy_nonoverlapping(src, dst, length - FAST_ACCESS_SIZE);
+            // Select the most frequent pairs sharing no token, ties broken towards the largest pair
+            let mut candidates: Vec<(usize, Pair)> = candidates.into_iter().map(|Reverse(candidate)| candidate).collect();
+            candidates.sort_unstable_by(|a, b| b.cmp(a));
+            let mut pass_merges: FxHashMap<Pair, u16> = FxHashMap::default();
+            let mut merged_tokens: FxHashSet<u16> = FxHashSet::default();
+            let mut pass_stop = None;
+            for (frequency, (t1, t2)) in candidates {
+                if pass_merges.len() == merges_per_pass {
+                    break;
+                }
+                if 256 + merges.len() >= self.config.max_tokens {
+                    pass_stop = Some(StopReason::MaxTokens);
+                    break;
+                }
+                if frequency < self.config.min_frequency as usize {
+                    pass_stop = Some(StopReason::MinFrequency);
+                    break;
+                }
+                if merged_tokens.contains(&t1) || merged_tokens.contains(&t2) {
+                    continue;
+                }
+                let token_length = self.token_length(t1) + self.token_length(t2);
+                if let Some(stop_reason) = self.check_budget(frequency, token_length, tokens.len()) {
+                    pass_stop = Some(stop_reason);
+                    break;
                 }
 
-                size += length;
+                merged_tokens.insert(t1);
+                merged_tokens.insert(t2);
+                pass_merges.insert((t1, t2), (256 + merges.len()) as u16);
+                merges.push((t1, t2));
+                self.push_token(t1, t2);
+            }
+
+            // Second pass: merge the selected pairs, left to right within each string
+            let mut write = 0;
+            let mut read = 0;
+            for end_position in item_end_positions.iter_mut().skip(1) {
+                let end = *end_position;
+                while read < end {
+                    let merged = if read + 1 < end { pass_merges.get(&(tokens[read], tokens[read + 1])) } else { None };
+                    match merged {
+                        Some(&token_id) => {
+                            tokens[write] = token_id;
+                            read += 2;
+                        }
+                        None => {
+                            tokens[write] = tokens[read];
+                            read += 1;
+                        }
+                    }
+                    write += 1;
+                }
+                *end_position = write;
             }
+            tokens.truncate(write);
+            trace!(pass = n_passes, merges = merges.len(), n_tokens = tokens.len(), "Pairs merged");
+
+            if let Some(stop_reason) = pass_stop {
+                break stop_reason;
+            }
+        };
+
+        debug!(passes = n_passes, "External training done");
+        let merged_fraction = if data.is_empty() { 0.0 } else { 1.0 - tokens.len() as f64 / data.len() as f64 };
+        self.training_report = Some(TrainingReport { iterations: merges.len(), stop_reason, merged_fraction });
+        tokens.shrink_to_fit();
+        (tokens, item_end_positions, merges)
+    }
+
+    /// Initializes the dictionary with the single-byte tokens
+    fn init_dictionary(&mut self) {
+        self.dictionary_end_positions.push(0);
+        for i in 0..256 {
+            let token = vec![i as u8];
+            self.dictionary.extend(&token);
+            self.dictionary_end_positions.push(to_u32_offset(self.dictionary.len(), "BPE dictionary"));
         }
+    }
 
-        size
+    /// Adds the token merging two tokens to the dictionary
+    fn push_token(&mut self, t1: u16, t2: u16) {
+        for token_id in [t1, t2] {
+            let start = self.dictionary_end_positions[token_id as usize] as usize;
+            let end = self.dictionary_end_positions[token_id as usize + 1] as usize;
+            self.dictionary.extend_from_within(start..end);
+        }
+        self.dictionary_end_positions.push(to_u32_offset(self.dictionary.len(), "BPE dictionary"));
     }
 
-    fn get_item_at(&mut self, index: usize, buffer: &mut [u8]) -> usize {
-        let item_start = self.item_end_positions[index];
-        let item_end = self.item_end_positions[index + 1];
-        let dict_ptr = self.dictionary.as_ptr();
-        let end_positions_ptr = self.dictionary_end_positions.as_ptr();
-        let mut size = 0;
+    /// Checks the dictionary budget and the minimum gain of a merge
+    ///
+    /// # Arguments
+    /// - `frequency`: Occurrences of the merged pair
+    /// - `token_length`: Length in bytes of the token formed by the pair
+    /// - `n_tokens`: Number of tokens of the strings before the merge
+    ///
+    /// # Returns
+    /// The criterion stopping the training, None if the merge is within budget
+    fn check_budget(&self, frequency: usize, token_length: usize, n_tokens: usize) -> Option<StopReason> {
+        // The new token costs its bytes and a boundary, each occurrence saves one token ID
+        let token_cost = token_length + std::mem::size_of::<u32>();
+        let dictionary_size = self.dictionary.len() + self.dictionary_end_positions.len() * std::mem::size_of::<u32>();
+        if self.config.max_dictionary_size.is_some_and(|budget| dictionary_size + token_cost > budget) {
+            return Some(StopReason::DictionarySize);
+        }
+        if let Some(min_gain) = self.config.min_gain {
+            let compressed_size = n_tokens * std::mem::size_of::<u16>() + dictionary_size;
+            let saved = (frequency * std::mem::size_of::<u16>()) as f64 - token_cost as f64;
+            if saved < min_gain * compressed_size as f64 {
+                return Some(StopReason::MinGain);
+            }
+        }
+        None
+    }
 
-        for &token_id in self.compressed_data[item_start..item_end].iter() {
-            unsafe {
-                let dict_start = *end_positions_ptr.add(token_id as usize) as usize;
-                let dict_end = *end_positions_ptr.add(token_id as usize + 1) as usize;
-                let length = dict_end - dict_start;
+    /// Returns the length in bytes of a dictionary token
+    fn token_length(&self, token_id: u16) -> usize {
+        (self.dictionary_end_positions[token_id as usize + 1] - self.dictionary_end_positions[token_id as usize]) as usize
+    }
+}
 
-                let mut src = dict_ptr.add(dict_start);
-                let mut dst = buffer.as_mut_ptr().add(size);
-                std::ptr::copy_nonoverlapping(src, dst, FAST_ACCESS_SIZE);
+/// Concatenates the strings picked by a sampler, for sampled training
+///
+/// # Returns
+/// Concatenated strings and their boundary positions, starting with 0
+fn gather_strings(data: &[u8], end_positions: &[usize], indices: &[usize]) -> (Vec<u8>, Vec<usize>) {
+    let mut sample_data = Vec::new();
+    let mut sample_end_positions = Vec::with_capacity(indices.len() + 1);
+    sample_end_positions.push(0);
+    for &index in indices {
+        sample_data.extend_from_slice(&data[end_positions[index]..end_positions[index + 1]]);
+        sample_end_positions.push(sample_data.len());
+    }
+    (sample_data, sample_end_positions)
+}
 
-                if length > FAST_ACCESS_SIZE {
-                    src = src.add(FAST_ACCESS_SIZE); 
-                    dst = dst.add(FAST_ACCESS_SIZE);
-                    std::ptr::copy_nonoverlapping(src, dst, length - FAST_ACCESS_SIZE);
+/// Selection of the most frequent pair of tokens, the next one to merge
+///
+/// Once tracked, the frequency of a pair only decreases, as the pairs created by a merge
+/// all contain the new token: selections may apply decrements lazily, checking the
+/// current frequency of a pair when it is selected.
+trait PairSelection {
+    /// Creates an empty selection for frequencies up to `max_frequency`
+    fn new(max_frequency: usize) -> Self;
+
+    /// Adds a pair occurring `frequency` times, at most the frequency of the last popped pair
+    fn insert(&mut self, pair: Pair, frequency: usize);
+
+    /// Removes and returns the most frequent pair with its frequency, None if no pair is left
+    ///
+    /// Ties are broken towards the largest pair.
+    ///
+    /// # Arguments
+    /// - `frequency`: Returns the current frequency of a tracked pair
+    fn pop_max(&mut self, frequency: impl Fn(Pair) -> usize) -> Option<(usize, Pair)>;
+}
+
+/// Pairs of tokens bucketed by frequency, for selecting the most frequent pair
+///
+/// Bucket `f` holds pairs that occurred `f` times when last placed, as in RePair
+/// implementations. Decrements are applied lazily (see `PairSelection`), moving
+/// outdated pairs down when they reach the highest non-empty bucket, so that merges
+/// do not touch the buckets of the pairs they update. Each bucket is a max-heap of
+/// pairs, breaking ties towards the largest pair as a single max-heap of
+/// `(frequency, pair)` entries would, with far fewer entries per heap.
+struct FrequencyBuckets {
+    buckets: Vec<BinaryHeap<Pair>>,
+    top: usize,                 // No bucket above holds a pair
+}
+
+impl PairSelection for FrequencyBuckets {
+    fn new(max_frequency: usize) -> Self {
+        FrequencyBuckets { buckets: vec![BinaryHeap::new(); max_frequency + 1], top: 0 }
+    }
+
+    fn insert(&mut self, pair: Pair, frequency: usize) {
+        self.buckets[frequency].push(pair);
+        self.top = self.top.max(frequency);
+    }
+
+    fn pop_max(&mut self, frequency: impl Fn(Pair) -> usize) -> Option<(usize, Pair)> {
+        loop {
+            while self.buckets[self.top].is_empty() {
+                if self.top == 0 {
+                    return None;
                 }
+                self.top -= 1;
+            }
+            let pair = self.buckets[self.top].pop().unwrap();
+            let current = frequency(pair);
+            if current == self.top {
+                return Some((current, pair));
+            }
+            // Apply the pending decrements of an outdated pair
+            self.buckets[current].push(pair);
+        }
+    }
+}
 
-                size += length;
+/// Copies dictionary spans consecutively to the buffer, as in `get_item_at`
+///
+/// # Arguments
+/// - `dictionary`: Token definitions
+/// - `spans`: Start and length of each token in the dictionary
+/// - `buffer`: Output buffer, with `FAST_ACCESS_SIZE` bytes of padding
+///
+/// # Returns
+/// Number of bytes written to the buffer
+pub(crate) fn copy_spans(dictionary: &[u8], spans: &[(usize, usize)], buffer: &mut [u8]) -> usize {
+    let mut size = 0;
+    for &(dict_start, length) in spans {
+        unsafe {
+            let mut src = dictionary.as_ptr().add(dict_start);
+            let mut dst = buffer.as_mut_ptr().add(size);
+            std::ptr::copy_nonoverlapping(src, dst, FAST_ACCESS_SIZE);
+
+            if length > FAST_ACCESS_SIZE {
+                src = src.add(FAST_ACCESS_SIZE);
+                dst = dst.add(FAST_ACCESS_SIZE);
+                std::ptr::copy_nonoverlapping(src, dst, length - FAST_ACCESS_SIZE);
             }
         }
+        size += length;
+    }
+    size
+}
 
-        size
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// Pairs in a single max-heap of `(frequency, pair)` entries, re-pushing outdated entries
+    /// when popped: the selection used before `FrequencyBuckets`
+    struct LazyHeap {
+        heap: BinaryHeap<(usize, Pair)>,
     }
 
-    fn space_used_bytes(&self) -> usize {
-        (self.compressed_data.len() * std::mem::size_of::<u16>()) 
-        + self.dictionary.len() 
-        + (self.dictionary_end_positions.len() * std::mem::size_of::<u32>())
+    impl PairSelection for LazyHeap {
+        fn new(_max_frequency: usize) -> Self {
+            LazyHeap { heap: BinaryHeap::new() }
+        }
+
+        fn insert(&mut self, pair: Pair, frequency: usize) {
+            self.heap.push((frequency, pair));
+        }
+
+        fn pop_max(&mut self, frequency: impl Fn(Pair) -> usize) -> Option<(usize, Pair)> {
+            while let Some((tracked, pair)) = self.heap.pop() {
+                let current = frequency(pair);
+                if current == tracked {
+                    return Some((current, pair));
+                }
+                self.heap.push((current, pair));
+            }
+            None
+        }
     }
 
-    fn name(&self) -> &str {
-        "BPE"
+    /// Returns strings over a 4-byte alphabet, whose pairs often occur equally often
+    fn small_alphabet_strings() -> (Vec<u8>, Vec<usize>) {
+        let mut rng = StdRng::seed_from_u64(1129);
+        let mut data = Vec::new();
+        let mut end_positions = vec![0];
+        for _ in 0..3000 {
+            let length = rng.gen_range(0..24);
+            data.extend((0..length).map(|_| b"abcd"[rng.gen_range(0..4)]));
+            end_positions.push(data.len());
+        }
+        (data, end_positions)
+    }
+
+    #[test]
+    fn frequency_buckets_merge_the_pairs_of_a_single_heap() {
+        let (data, end_positions) = small_alphabet_strings();
+        let mut buckets = BPECompressor::new(data.len(), end_positions.len() - 1);
+        let (bucket_tokens, _, bucket_merges) = buckets.train::<BitVector, FrequencyBuckets>(&data, &end_positions);
+        let mut heap = BPECompressor::new(data.len(), end_positions.len() - 1);
+        let (heap_tokens, _, heap_merges) = heap.train::<BitVector, LazyHeap>(&data, &end_positions);
+
+        assert!(bucket_merges.len() > 100, "too few merges to compare: {}", bucket_merges.len());
+        assert_eq!(bucket_merges, heap_merges);
+        assert_eq!(bucket_tokens, heap_tokens);
+        assert_eq!(buckets.dictionary, heap.dictionary);
+        assert_eq!(buckets.dictionary_end_positions, heap.dictionary_end_positions);
     }
 }
@@ -0,0 +1,244 @@
+//! Checksums and integrity verification for compressed data
+//!
+//! Provides 32-bit checksums (CRC32C or truncated XXH3) and a wrapper verifying the
+//! output of any compressor: every retrieved string is checked against a per-item
+//! checksum, and full decompression against a whole-corpus checksum. Mismatches
+//! panic, so that corrupted data fails loudly. Benchmarking a compressor with and
+//! without the wrapper measures the verification overhead.
+
+use super::{BlockPosition, Capacity, Compressor, ItemBoundaries, SpaceBreakdown};
+use crate::profiler::{Stage, StageTimer};
+use std::io::{self, Write};
+use xxhash_rust::xxh3::Xxh3;
+
+/// Checksum function used for integrity verification
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChecksumAlgorithm {
+    Crc32c,     // CRC32C (hardware-accelerated where available)
+    XxHash3,    // XXH3 64-bit, truncated to 32 bits
+}
+
+impl ChecksumAlgorithm {
+    /// Computes the checksum of a byte slice
+    #[inline(always)]
+    pub fn checksum(&self, data: &[u8]) -> u32 {
+        match self {
+            ChecksumAlgorithm::Crc32c => crc32c::crc32c(data),
+            ChecksumAlgorithm::XxHash3 => xxhash_rust::xxh3::xxh3_64(data) as u32,
+        }
+    }
+
+    /// Starts an incremental checksum computation
+    pub fn hasher(&self) -> ChecksumHasher {
+        match self {
+            ChecksumAlgorithm::Crc32c => ChecksumHasher::Crc32c(0),
+            ChecksumAlgorithm::XxHash3 => ChecksumHasher::XxHash3(Box::new(Xxh3::new())),
+        }
+    }
+
+    /// Returns the identifier of the algorithm used in compressor names
+    pub fn name(&self) -> &str {
+        match self {
+            ChecksumAlgorithm::Crc32c => "crc32c",
+            ChecksumAlgorithm::XxHash3 => "xxh3",
+        }
+    }
+}
+
+/// Incremental checksum over consecutive slices, equal to the checksum of their concatenation
+pub enum ChecksumHasher {
+    Crc32c(u32),
+    XxHash3(Box<Xxh3>),
+}
+
+impl ChecksumHasher {
+    /// Appends a slice to the checksummed data
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            ChecksumHasher::Crc32c(crc) => *crc = crc32c::crc32c_append(*crc, data),
+            ChecksumHasher::XxHash3(hasher) => hasher.update(data),
+        }
+    }
+
+    /// Returns the checksum of the data appended so far
+    pub fn finish(&self) -> u32 {
+        match self {
+            ChecksumHasher::Crc32c(crc) => *crc,
+            ChecksumHasher::XxHash3(hasher) => hasher.digest() as u32,
+        }
+    }
+}
+
+/// Writer forwarding data to an inner writer while checksumming it
+struct ChecksumWriter<'a, W: Write> {
+    inner: &'a mut W,
+    hasher: ChecksumHasher,
+}
+
+impl<W: Write> Write for ChecksumWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Wrapper verifying the strings returned by a compressor
+pub struct VerifiedCompressor<T: Compressor> {
+    inner: T,
+    algorithm: ChecksumAlgorithm,
+    item_checksums: Vec<u32>,   // Checksum of each string
+    corpus_checksum: u32,       // Checksum of the concatenated strings
+    name: String,
+}
+
+impl<T: Compressor> VerifiedCompressor<T> {
+    /// Creates a verifying wrapper using the given checksum function
+    ///
+    /// # Arguments
+    /// - `data_size`: Total size of input data in bytes
+    /// - `n_elements`: Number of individual strings in the dataset
+    /// - `algorithm`: Checksum function
+    pub fn with_algorithm(data_size: usize, n_elements: usize, algorithm: ChecksumAlgorithm) -> Self {
+        let inner = T::new(data_size, n_elements);
+        let name = format!("{} (verified, {})", inner.name(), algorithm.name());
+
+        VerifiedCompressor {
+            inner,
+            algorithm,
+            item_checksums: Vec::with_capacity(n_elements),
+            corpus_checksum: 0,
+            name,
+        }
+    }
+
+    /// Returns the space used by the item and corpus checksums
+    fn checksums_size(&self) -> usize {
+        std::mem::size_of_val(self.item_checksums.as_slice()) + std::mem::size_of::<u32>()
+    }
+}
+
+impl<T: Compressor> Compressor for VerifiedCompressor<T> {
+    fn new(data_size: usize, n_elements: usize) -> Self {
+        Self::with_algorithm(data_size, n_elements, ChecksumAlgorithm::Crc32c)
+    }
+
+    fn compress(&mut self, data: &[u8], end_positions: &[usize]) {
+        self.item_checksums = end_positions
+            .windows(2)
+            .map(|w| self.algorithm.checksum(&data[w[0]..w[1]]))
+            .collect();
+        self.corpus_checksum = self.algorithm.checksum(data);
+        self.inner.compress(data, end_positions);
+    }
+
+    fn decompress(&self, buffer: &mut [u8]) -> usize {
+        let size = self.inner.decompress(buffer);
+        if self.algorithm.checksum(&buffer[..size]) != self.corpus_checksum {
+            panic!("Checksum mismatch during decompression for compressor: {}", self.name);
+        }
+        size
+    }
+
+    fn decompress_to<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let mut checksum_writer = ChecksumWriter { inner: writer, hasher: self.algorithm.hasher() };
+        let size = self.inner.decompress_to(&mut checksum_writer)?;
+        if checksum_writer.hasher.finish() != self.corpus_checksum {
+            panic!("Checksum mismatch during decompression for compressor: {}", self.name);
+        }
+        Ok(size)
+    }
+
+    #[inline(always)]
+    fn get_item_at(&mut self, index: usize, buffer: &mut [u8]) -> usize {
+        let size = self.inner.get_item_at(index, buffer);
+        if self.algorithm.checksum(&buffer[..size]) != self.item_checksums[index] {
+            panic!("Checksum mismatch for item {} in compressor: {}", index, self.name);
+        }
+        size
+    }
+
+    fn get_item_at_profiled(&mut self, index: usize, buffer: &mut [u8], timer: &mut StageTimer) -> usize {
+        let size = self.inner.get_item_at_profiled(index, buffer, timer);
+        if self.algorithm.checksum(&buffer[..size]) != self.item_checksums[index] {
+            panic!("Checksum mismatch for item {} in compressor: {}", index, self.name);
+        }
+        timer.lap(Stage::Decode);
+        size
+    }
+
+    #[inline(always)]
+    fn get_item_ref(&mut self, index: usize) -> Option<&[u8]> {
+        let item = self.inner.get_item_ref(index)?;
+        if self.algorithm.checksum(item) != self.item_checksums[index] {
+            panic!("Checksum mismatch for item {} in compressor: {}", index, self.name);
+        }
+        Some(item)
+    }
+
+    fn get_items_at(&mut self, indices: &[usize], buffer: &mut [u8], item_ends: &mut [usize]) -> usize {
+        let size = self.inner.get_items_at(indices, buffer, item_ends);
+        let mut item_start = 0;
+        for (&index, &item_end) in indices.iter().zip(item_ends.iter()) {
+            if self.algorithm.checksum(&buffer[item_start..item_end]) != self.item_checksums[index] {
+                panic!("Checksum mismatch for item {} in compressor: {}", index, self.name);
+            }
+            item_start = item_end;
+        }
+        size
+    }
+
+    fn scan_range<P: FnMut(usize, &[u8])>(&mut self, start_index: usize, end_index: usize, mut projector: P) {
+        let (item_checksums, algorithm, name) = (&self.item_checksums, &self.algorithm, &self.name);
+        self.inner.scan_range(start_index, end_index, |index, item| {
+            if algorithm.checksum(item) != item_checksums[index] {
+                panic!("Checksum mismatch for item {} in compressor: {}", index, name);
+            }
+            projector(index, item);
+        });
+    }
+
+    fn len_of_item(&self, index: usize) -> usize {
+        self.inner.len_of_item(index)
+    }
+
+    fn max_item_len(&self) -> usize {
+        self.inner.max_item_len()
+    }
+
+    fn offset_to_item(&self, offset: usize) -> Option<(usize, usize)> {
+        self.inner.offset_to_item(offset)
+    }
+
+    fn block_position(&self, index: usize) -> Option<BlockPosition> {
+        self.inner.block_position(index)
+    }
+
+    fn item_boundaries(&self) -> ItemBoundaries {
+        self.inner.item_boundaries()
+    }
+
+    fn capacity(&self) -> Capacity {
+        self.inner.capacity()
+    }
+
+    fn space_used_bytes(&self) -> usize {
+        self.inner.space_used_bytes() + self.checksums_size()
+    }
+
+    fn space_breakdown(&self) -> SpaceBreakdown {
+        let inner = self.inner.space_breakdown();
+        SpaceBreakdown {
+            checksums: inner.checksums + self.checksums_size(),
+            ..inner
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
@@ -0,0 +1,193 @@
+//! Front-coded token dictionaries (nested coding)
+//!
+//! On small datasets, or with wide token IDs, the dictionary of a token compressor takes
+//! a large share of the compressed size. A `CodedDictionary` stores the tokens
+//! front-coded in ID order (see `front_coding`) with their lengths: merged tokens extend
+//! tokens merged shortly before them, so that neighbouring IDs often share prefixes.
+//! Accessing a token decodes its bucket up to it, so that a small direct-mapped cache
+//! keeps recently decoded short tokens: accesses concentrate on few frequent tokens,
+//! which then skip bucket decoding. Full decompression expands the whole dictionary once
+//! instead (see `decode_all`).
+//!
+//! Like block caches, the decode cache is transient and excluded from the space used.
+
+use super::front_coding::FrontCodingCompressor;
+use super::token_offsets::{Offset, TokenOffsets};
+use super::{Compressor, MAX_TOKEN_LENGTH};
+
+/// Tokens per front coding bucket, kept small to bound the decoding of a cache miss
+const DICTIONARY_BUCKET_SIZE: usize = 8;
+/// Slots of the decode cache
+const CACHE_SLOTS: usize = 256;
+/// Longest cached token in bytes; longer tokens are decoded on every access
+const CACHED_TOKEN_LENGTH: usize = 32;
+/// Tag of an empty cache slot
+const EMPTY_SLOT: usize = usize::MAX;
+
+/// Storage of a token dictionary
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DictionaryCoding {
+    Plain,      // Concatenated tokens, copied directly
+    FrontCoded, // Front-coded tokens, decoded through a cache (see `CodedDictionary`)
+}
+
+impl DictionaryCoding {
+    /// Returns the coding identifier
+    pub fn name(&self) -> &'static str {
+        match self {
+            DictionaryCoding::Plain => "plain",
+            DictionaryCoding::FrontCoded => "front_coded",
+        }
+    }
+
+    /// Parses a coding from its identifier
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "plain" => Some(DictionaryCoding::Plain),
+            "front_coded" => Some(DictionaryCoding::FrontCoded),
+            _ => None,
+        }
+    }
+}
+
+/// Front-coded token dictionary with a decode cache of hot tokens
+pub struct CodedDictionary {
+    tokens: FrontCodingCompressor,          // Tokens in ID order
+    token_lengths: Vec<u16>,                // Length of each token in bytes
+    cache_tags: Vec<usize>,                 // Token held by each cache slot, or EMPTY_SLOT
+    cache: Vec<u8>,                         // Decoded tokens, CACHED_TOKEN_LENGTH bytes per slot
+    scratch: Vec<u8>,                       // Decoding space of a token and its bucket predecessors
+}
+
+impl CodedDictionary {
+    /// Front-codes a dictionary
+    ///
+    /// # Arguments
+    /// - `dictionary`: Token definitions, concatenated (trailing padding is ignored)
+    /// - `dictionary_end_positions`: Token boundary positions in `dictionary`, starting with 0
+    pub fn new<O: Offset>(dictionary: &[u8], dictionary_end_positions: &[O]) -> Self {
+        let end_positions: Vec<usize> = dictionary_end_positions.iter().map(|&position| position.position()).collect();
+        let n_tokens = end_positions.len() - 1;
+        let mut tokens = FrontCodingCompressor::with_bucket_size(end_positions[n_tokens], n_tokens, DICTIONARY_BUCKET_SIZE);
+        tokens.compress(dictionary, &end_positions);
+        let token_lengths = end_positions
+            .windows(2)
+            .map(|w| {
+                assert!(w[1] - w[0] <= MAX_TOKEN_LENGTH, "Token longer than MAX_TOKEN_LENGTH");
+                (w[1] - w[0]) as u16
+            })
+            .collect();
+
+        CodedDictionary {
+            tokens,
+            token_lengths,
+            cache_tags: vec![EMPTY_SLOT; CACHE_SLOTS],
+            cache: vec![0; CACHE_SLOTS * CACHED_TOKEN_LENGTH],
+            scratch: vec![0; MAX_TOKEN_LENGTH],
+        }
+    }
+
+    /// Returns the number of tokens
+    pub fn n_tokens(&self) -> usize {
+        self.token_lengths.len()
+    }
+
+    /// Returns the length of a token in bytes, without decoding it
+    #[inline(always)]
+    pub fn token_length(&self, token_id: usize) -> usize {
+        self.token_lengths[token_id] as usize
+    }
+
+    /// Copies a token to the output, through the decode cache
+    ///
+    /// # Returns
+    /// Length of the token in bytes
+    #[inline]
+    pub fn copy_token(&mut self, token_id: usize, dst: &mut [u8]) -> usize {
+        let length = self.token_lengths[token_id] as usize;
+        if length > CACHED_TOKEN_LENGTH {
+            self.tokens.get_item_at(token_id, &mut self.scratch);
+            dst[..length].copy_from_slice(&self.scratch[..length]);
+            return length;
+        }
+
+        let slot = token_id % CACHE_SLOTS;
+        let cached = &mut self.cache[slot * CACHED_TOKEN_LENGTH..(slot + 1) * CACHED_TOKEN_LENGTH];
+        if self.cache_tags[slot] != token_id {
+            self.tokens.get_item_at(token_id, &mut self.scratch);
+            cached[..length].copy_from_slice(&self.scratch[..length]);
+            self.cache_tags[slot] = token_id;
+        }
+        dst[..length].copy_from_slice(&cached[..length]);
+        length
+    }
+
+    /// Decodes the whole dictionary
+    ///
+    /// # Returns
+    /// Token definitions, concatenated, and their boundary positions starting with 0
+    pub fn decode_all(&self) -> (Vec<u8>, TokenOffsets) {
+        let mut dictionary_end_positions = TokenOffsets::with_capacity(self.n_tokens() + 1);
+        dictionary_end_positions.push(0);
+        let mut size = 0;
+        for &length in &self.token_lengths {
+            size += length as usize;
+            dictionary_end_positions.push(size);
+        }
+
+        let mut dictionary = vec![0; size];
+        let decoded_size = self.tokens.decompress(&mut dictionary);
+        debug_assert_eq!(decoded_size, dictionary.len());
+        (dictionary, dictionary_end_positions)
+    }
+
+    /// Returns the size of the coded tokens and their lengths in bytes
+    pub fn space_used_bytes(&self) -> usize {
+        self.tokens.space_used_bytes() + self.token_lengths.len() * std::mem::size_of::<u16>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::token_offsets::with_offsets;
+
+    /// Single bytes, then merged tokens extending the previous ones: short, longer than the
+    /// cached tokens, and up to `MAX_TOKEN_LENGTH`, with more tokens than cache slots
+    fn tokens() -> Vec<Vec<u8>> {
+        let mut tokens: Vec<Vec<u8>> = (0..=255u8).map(|b| vec![b]).collect();
+        for i in 0..300usize {
+            let length = [2, 5, CACHED_TOKEN_LENGTH, CACHED_TOKEN_LENGTH + 1, 100, MAX_TOKEN_LENGTH][i % 6];
+            let previous = tokens.last().unwrap();
+            let mut token: Vec<u8> = previous.iter().copied().chain(std::iter::repeat(i as u8)).take(length).collect();
+            *token.last_mut().unwrap() ^= 0x5a;
+            tokens.push(token);
+        }
+        tokens
+    }
+
+    #[test]
+    fn every_token_decodes_to_its_definition() {
+        let tokens = tokens();
+        let dictionary = tokens.concat();
+        let mut end_positions = TokenOffsets::with_capacity(tokens.len() + 1);
+        end_positions.push(0);
+        for token in &tokens {
+            end_positions.push(end_positions.get(end_positions.len() - 1) + token.len());
+        }
+
+        let mut coded = with_offsets!(&end_positions, offsets => CodedDictionary::new(&dictionary, offsets));
+        assert_eq!(coded.n_tokens(), tokens.len());
+        assert_eq!(coded.decode_all(), (dictionary.clone(), end_positions.clone()));
+        let wide_offsets: Vec<u64> = (0..end_positions.len()).map(|i| end_positions.get(i) as u64).collect();
+        assert_eq!(CodedDictionary::new(&dictionary, &wide_offsets).decode_all(), (dictionary.clone(), end_positions));
+
+        // Forward then backward, so that tokens sharing a cache slot evict each other
+        let mut buffer = vec![0u8; MAX_TOKEN_LENGTH];
+        for token_id in (0..tokens.len()).chain((0..tokens.len()).rev()) {
+            assert_eq!(coded.token_length(token_id), tokens[token_id].len());
+            let length = coded.copy_token(token_id, &mut buffer);
+            assert_eq!(&buffer[..length], tokens[token_id].as_slice(), "token {}", token_id);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+//! Deduplication wrapper for any compressor
+//!
+//! Exact duplicate strings are detected by hashing, and each distinct string is
+//! stored once, in order of first occurrence, by the inner compressor. Every item
+//! references its distinct string through an `IdMap`.
+
+use super::id_map::RemappedCompressor;
+use super::{BlockPosition, Capacity, Compressor, SpaceBreakdown};
+use crate::profiler::StageTimer;
+use rustc_hash::FxHashMap;
+use std::io::{self, Write};
+
+/// Wrapper storing each distinct string once
+pub struct DedupCompressor<T: Compressor> {
+    remapped: RemappedCompressor<T>,
+    dedup_ratio: f64,   // Input size over the size of the distinct strings
+    name: String,
+}
+
+impl<T: Compressor> DedupCompressor<T> {
+    /// Returns the input size divided by the size of the distinct strings
+    ///
+    /// Measures the reduction achieved by deduplication alone, before compression.
+    pub fn dedup_ratio(&self) -> f64 {
+        self.dedup_ratio
+    }
+}
+
+impl<T: Compressor> Compressor for DedupCompressor<T> {
+    fn new(data_size: usize, n_elements: usize) -> Self {
+        let remapped = RemappedCompressor::new(data_size, n_elements);
+        let name = format!("{} (dedup)", remapped.inner_name());
+        DedupCompressor { remapped, dedup_ratio: 1.0, name }
+    }
+
+    fn compress(&mut self, data: &[u8], end_positions: &[usize]) {
+        let n_elements = end_positions.len() - 1;
+
+        // Assign internal IDs to distinct strings in order of first occurrence
+        let mut distinct: FxHashMap<&[u8], usize> = FxHashMap::default();
+        let mut stored = Vec::new();
+        let mut internal_ids = Vec::with_capacity(n_elements);
+        let mut distinct_size = 0;
+        for i in 0..n_elements {
+            let item = &data[end_positions[i]..end_positions[i + 1]];
+            let internal_id = *distinct.entry(item).or_insert_with(|| {
+                stored.push(i);
+                distinct_size += item.len();
+                stored.len() - 1
+            });
+            internal_ids.push(internal_id);
+        }
+
+        self.dedup_ratio = data.len() as f64 / distinct_size.max(1) as f64;
+        self.remapped.compress_remapped(data, end_positions, &stored, &internal_ids);
+    }
+
+    fn decompress(&self, buffer: &mut [u8]) -> usize {
+        self.remapped.decompress(buffer)
+    }
+
+    fn decompress_to<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        self.remapped.decompress_to(writer)
+    }
+
+    #[inline(always)]
+    fn get_item_at(&mut self, index: usize, buffer: &mut [u8]) -> usize {
+        self.remapped.get_item_at(index, buffer)
+    }
+
+    fn get_item_at_profiled(&mut self, index: usize, buffer: &mut [u8], timer: &mut StageTimer) -> usize {
+        self.remapped.get_item_at_profiled(index, buffer, timer)
+    }
+
+    #[inline(always)]
+    fn get_item_ref(&mut self, index: usize) -> Option<&[u8]> {
+        self.remapped.get_item_ref(index)
+    }
+
+    fn get_items_at(&mut self, indices: &[usize], buffer: &mut [u8], item_ends: &mut [usize]) -> usize {
+        self.remapped.get_items_at(indices, buffer, item_ends)
+    }
+
+    fn len_of_item(&self, index: usize) -> usize {
+        self.remapped.len_of_item(index)
+    }
+
+    fn max_item_len(&self) -> usize {
+        self.remapped.max_item_len()
+    }
+
+    fn block_position(&self, index: usize) -> Option<BlockPosition> {
+        self.remapped.block_position(index)
+    }
+
+    fn capacity(&self) -> Capacity {
+        self.remapped.capacity()
+    }
+
+    fn space_used_bytes(&self) -> usize {
+        self.remapped.space_used_bytes()
+    }
+
+    fn space_breakdown(&self) -> SpaceBreakdown {
+        self.remapped.space_breakdown()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
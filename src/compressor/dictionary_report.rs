@@ -0,0 +1,264 @@
+//! Export of trained dictionaries for offline analysis
+//!
+//! A `DictionaryReport` lists the tokens of a trained dictionary with their bytes,
+//! lengths and frequencies in the compressed strings, together with the number of
+//! times each pair of tokens occurs consecutively within a string. Written as JSON or
+//! CSV after compression, reports show what a dictionary learned and allow comparing
+//! dictionaries across datasets or parameter settings.
+//!
+//! Token bytes are written with ASCII escapes (`std::ascii::escape_default`), so that
+//! binary tokens survive both formats.
+//!
+//! `DictionaryStats` condenses the same usage into a few figures recorded with the
+//! benchmark results: token lengths, the share of the corpus covered by each class of
+//! token lengths, and the order-0 entropy of the token stream. Two dictionaries of the
+//! same size reach different ratios by covering the corpus with longer tokens, or by
+//! using their tokens more unevenly, which an entropy coder of the IDs would exploit.
+
+use crate::benchmark_utils::write_file_atomically;
+use super::token_offsets::Offset;
+use rustc_hash::FxHashMap;
+use serde::{Serialize, Deserialize};
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Extension replacing `.csv` to name the adjacency file of a CSV export
+pub const PAIRS_CSV_EXTENSION: &str = ".pairs.csv";
+/// Longest token of each length class but the last one, unbounded (see `LengthClassCoverage`)
+pub const LENGTH_CLASS_BOUNDS: [usize; 5] = [1, 2, 4, 8, 16];
+
+/// Token of a trained dictionary, with its usage
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TokenEntry {
+    pub id: usize,
+    pub token: String,                      // Token bytes, ASCII-escaped
+    pub length: usize,                      // Token length in bytes
+    pub frequency: usize,                   // Occurrences in the compressed strings
+}
+
+/// Pair of tokens occurring consecutively within strings
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct TokenAdjacency {
+    pub first: usize,                       // ID of the preceding token
+    pub second: usize,                      // ID of the following token
+    pub count: usize,                       // Occurrences of the pair
+}
+
+/// Trained dictionary of a compressor and its usage in the compressed strings
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DictionaryReport {
+    pub compressor_name: String,
+    pub n_items: usize,                     // Compressed strings
+    pub n_tokens: usize,                    // Tokens in the compressed strings
+    pub tokens: Vec<TokenEntry>,            // All tokens of the dictionary, by ID
+    pub adjacencies: Vec<TokenAdjacency>,   // Pairs of consecutive tokens, by decreasing count
+}
+
+/// Usage of the tokens whose length falls in a class
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LengthClassCoverage {
+    pub lengths: String,                    // Token lengths of the class (e.g., "3-4", "17+")
+    pub n_dictionary_tokens: usize,         // Tokens of the dictionary in the class
+    pub token_fraction: f64,                // Fraction of the token stream made of tokens of the class
+    pub byte_fraction: f64,                 // Fraction of the corpus bytes covered by tokens of the class
+}
+
+/// Summary of a dictionary and of its usage in the compressed strings
+///
+/// Recorded with the results of dictionary compressors (see `DictionaryCompressor::dictionary_stats`).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DictionaryStats {
+    pub n_dictionary_tokens: usize,         // Tokens of the dictionary
+    pub n_used_tokens: usize,               // Tokens of the dictionary occurring in the compressed strings
+    pub n_tokens: usize,                    // Tokens in the compressed strings
+    pub average_token_length: f64,          // Average length of the tokens of the dictionary
+    pub max_token_length: usize,            // Length of the longest token of the dictionary
+    pub bytes_per_token: f64,               // Average length of the tokens in the compressed strings
+    pub coverage: Vec<LengthClassCoverage>, // Usage by token length, by increasing lengths (see `LENGTH_CLASS_BOUNDS`)
+    pub entropy_bits: f64,                  // Order-0 entropy of the token stream, in bits per token
+}
+
+impl DictionaryStats {
+    /// Counts the usage of the tokens of a dictionary in the compressed strings
+    ///
+    /// Unlike `DictionaryReport::build`, neither copies the tokens nor counts adjacencies.
+    ///
+    /// # Arguments
+    /// - `dictionary_end_positions`: Token boundary positions in the dictionary, starting with 0
+    /// - `items`: Token IDs of each compressed string
+    pub fn build<O, I, T>(dictionary_end_positions: &[O], items: I) -> Self
+    where
+        O: Offset,
+        I: IntoIterator<Item = T>,
+        T: IntoIterator<Item = usize>,
+    {
+        let mut frequencies = vec![0usize; dictionary_end_positions.len() - 1];
+        for token_id in items.into_iter().flatten() {
+            frequencies[token_id] += 1;
+        }
+        let lengths = dictionary_end_positions.windows(2).map(|w| w[1].position() - w[0].position());
+        Self::from_usage(lengths.zip(frequencies))
+    }
+
+    /// Summarizes the length and frequency of every token of a dictionary
+    fn from_usage(usage: impl IntoIterator<Item = (usize, usize)>) -> Self {
+        let n_classes = LENGTH_CLASS_BOUNDS.len() + 1;
+        let mut class_tokens = vec![0usize; n_classes];
+        let mut class_occurrences = vec![0usize; n_classes];
+        let mut class_bytes = vec![0usize; n_classes];
+        let mut frequencies = Vec::new();
+        let (mut n_dictionary_tokens, mut dictionary_bytes, mut max_token_length) = (0, 0, 0);
+        for (length, frequency) in usage {
+            let class = LENGTH_CLASS_BOUNDS.partition_point(|&bound| bound < length);
+            class_tokens[class] += 1;
+            class_occurrences[class] += frequency;
+            class_bytes[class] += frequency * length;
+            n_dictionary_tokens += 1;
+            dictionary_bytes += length;
+            max_token_length = max_token_length.max(length);
+            frequencies.push(frequency);
+        }
+
+        let n_tokens: usize = class_occurrences.iter().sum();
+        let corpus_bytes: usize = class_bytes.iter().sum();
+        let fraction = |count: usize, total: usize| if total == 0 { 0.0 } else { count as f64 / total as f64 };
+        let coverage = (0..n_classes)
+            .map(|class| LengthClassCoverage {
+                lengths: length_class_name(class),
+                n_dictionary_tokens: class_tokens[class],
+                token_fraction: fraction(class_occurrences[class], n_tokens),
+                byte_fraction: fraction(class_bytes[class], corpus_bytes),
+            })
+            .collect();
+        let entropy_bits = frequencies
+            .iter()
+            .filter(|&&frequency| frequency > 0)
+            .map(|&frequency| {
+                let p = frequency as f64 / n_tokens as f64;
+                -p * p.log2()
+            })
+            .sum();
+
+        DictionaryStats {
+            n_dictionary_tokens,
+            n_used_tokens: frequencies.iter().filter(|&&frequency| frequency > 0).count(),
+            n_tokens,
+            average_token_length: fraction(dictionary_bytes, n_dictionary_tokens),
+            max_token_length,
+            bytes_per_token: fraction(corpus_bytes, n_tokens),
+            coverage,
+            entropy_bits,
+        }
+    }
+}
+
+impl DictionaryReport {
+    /// Counts the usage of the tokens of a dictionary in the compressed strings
+    ///
+    /// # Arguments
+    /// - `compressor_name`: Name of the compressor, as reported in results
+    /// - `dictionary`: Token definitions, concatenated
+    /// - `dictionary_end_positions`: Token boundary positions in `dictionary`, starting with 0
+    /// - `items`: Token IDs of each compressed string
+    pub fn build<O, I, T>(compressor_name: &str, dictionary: &[u8], dictionary_end_positions: &[O], items: I) -> Self
+    where
+        O: Offset,
+        I: IntoIterator<Item = T>,
+        T: IntoIterator<Item = usize>,
+    {
+        let mut frequencies = vec![0usize; dictionary_end_positions.len() - 1];
+        let mut pair_counts: FxHashMap<(usize, usize), usize> = FxHashMap::default();
+        let mut n_items = 0;
+        for item in items {
+            let mut previous = None;
+            for token_id in item {
+                frequencies[token_id] += 1;
+                if let Some(previous) = previous {
+                    *pair_counts.entry((previous, token_id)).or_insert(0) += 1;
+                }
+                previous = Some(token_id);
+            }
+            n_items += 1;
+        }
+
+        let tokens = frequencies
+            .iter()
+            .enumerate()
+            .map(|(id, &frequency)| {
+                let bytes = &dictionary[dictionary_end_positions[id].position()..dictionary_end_positions[id + 1].position()];
+                TokenEntry { id, token: escape(bytes), length: bytes.len(), frequency }
+            })
+            .collect();
+        let mut adjacencies: Vec<TokenAdjacency> = pair_counts
+            .into_iter()
+            .map(|((first, second), count)| TokenAdjacency { first, second, count })
+            .collect();
+        adjacencies.sort_unstable_by(|a, b| b.count.cmp(&a.count).then((a.first, a.second).cmp(&(b.first, b.second))));
+
+        DictionaryReport {
+            compressor_name: compressor_name.to_string(),
+            n_items,
+            n_tokens: frequencies.iter().sum(),
+            tokens,
+            adjacencies,
+        }
+    }
+
+    /// Summarizes the dictionary and its usage (see `DictionaryStats`)
+    pub fn stats(&self) -> DictionaryStats {
+        DictionaryStats::from_usage(self.tokens.iter().map(|entry| (entry.length, entry.frequency)))
+    }
+
+    /// Writes the report to a JSON file
+    pub fn write_json(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        write_file_atomically(path, json.as_bytes())
+    }
+
+    /// Writes the tokens to a CSV file, and the adjacencies next to it (see `pairs_csv_path`)
+    pub fn write_csv(&self, path: &Path) -> io::Result<()> {
+        let mut tokens = String::from("id,token,length,frequency\n");
+        for entry in &self.tokens {
+            tokens.push_str(&format!("{},\"{}\",{},{}\n", entry.id, entry.token.replace('"', "\"\""), entry.length, entry.frequency));
+        }
+        write_file_atomically(path, tokens.as_bytes())?;
+
+        let mut pairs = String::from("first,second,count\n");
+        for adjacency in &self.adjacencies {
+            pairs.push_str(&format!("{},{},{}\n", adjacency.first, adjacency.second, adjacency.count));
+        }
+        write_file_atomically(&pairs_csv_path(path), pairs.as_bytes())
+    }
+
+    /// Writes the report as CSV if the path ends with `.csv`, as JSON otherwise
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        if path.extension().is_some_and(|extension| extension == "csv") {
+            self.write_csv(path)
+        } else {
+            self.write_json(path)
+        }
+    }
+}
+
+/// Returns the path of the adjacency file of a CSV export (`tokens.csv` → `tokens.pairs.csv`)
+pub fn pairs_csv_path(path: &Path) -> PathBuf {
+    let mut pairs_path = OsString::from(path.with_extension("").as_os_str());
+    pairs_path.push(PAIRS_CSV_EXTENSION);
+    PathBuf::from(pairs_path)
+}
+
+/// Returns the token lengths of a class (e.g., "3-4", "17+")
+fn length_class_name(class: usize) -> String {
+    let min_length = class.checked_sub(1).map_or(1, |previous| LENGTH_CLASS_BOUNDS[previous] + 1);
+    match LENGTH_CLASS_BOUNDS.get(class) {
+        Some(&max_length) if max_length == min_length => min_length.to_string(),
+        Some(&max_length) => format!("{}-{}", min_length, max_length),
+        None => format!("{}+", min_length),
+    }
+}
+
+/// Escapes the bytes of a token as printable ASCII
+fn escape(bytes: &[u8]) -> String {
+    bytes.iter().flat_map(|&byte| std::ascii::escape_default(byte)).map(char::from).collect()
+}
@@ -0,0 +1,484 @@
+//! FM-index self-index (experimental)
+//!
+//! Self-index over the concatenated strings: the Burrows-Wheeler transform (BWT) of the
+//! corpus is stored in a wavelet matrix supporting rank queries, together with samples
+//! of the suffix array and of its inverse every `sample_rate` text positions. Strings are
+//! extracted by walking the LF mapping backwards from the nearest sampled position after
+//! them, and the occurrences of any pattern are counted and located without decompressing
+//! (`count`, `locate`), so that self-indexes can be compared with dictionary coders on
+//! workloads needing both random access and pattern search.
+//!
+//! The wavelet matrix stores the transform uncompressed (8 bits per byte, plus rank
+//! directories), so that the index is larger than the data: it measures the cost of
+//! search support rather than competing on compression rate. The suffix array is built
+//! by prefix doubling, in O(n log^2 n) time.
+
+use super::length_index::LengthIndex;
+use super::{max_len_of_items, Capacity, Compressor, SpaceBreakdown, StreamBuffer, MAX_U32_OFFSET, STREAM_CHUNK_SIZE};
+use std::io::{self, Write};
+use tracing::{debug, info_span};
+
+/// Default distance between sampled text positions
+pub const DEFAULT_SAMPLE_RATE: usize = 32;
+/// Words of a rank directory block
+const BLOCK_WORDS: usize = 8;
+
+/// Bit vector with constant-time rank support
+struct RankedBits {
+    words: Vec<u64>,        // Bits, least significant first
+    block_ranks: Vec<u64>,  // Ones before each block of BLOCK_WORDS words
+}
+
+impl RankedBits {
+    /// Builds the bit vector of `n_bits` bits, the i-th one being `bit(i)`
+    fn from_fn(n_bits: usize, bit: impl Fn(usize) -> bool) -> Self {
+        let mut words = vec![0u64; n_bits.div_ceil(64)];
+        for i in (0..n_bits).filter(|&i| bit(i)) {
+            words[i / 64] |= 1 << (i % 64);
+        }
+
+        let mut block_ranks = Vec::with_capacity(words.len() / BLOCK_WORDS + 1);
+        let mut ones = 0;
+        for block in words.chunks(BLOCK_WORDS) {
+            block_ranks.push(ones);
+            ones += block.iter().map(|word| word.count_ones() as u64).sum::<u64>();
+        }
+        block_ranks.push(ones);
+
+        RankedBits { words, block_ranks }
+    }
+
+    /// Returns the bit at position `i`
+    #[inline(always)]
+    fn get(&self, i: usize) -> bool {
+        (self.words[i / 64] >> (i % 64)) & 1 == 1
+    }
+
+    /// Returns the number of ones before position `i`
+    #[inline(always)]
+    fn rank1(&self, i: usize) -> usize {
+        let word = i / 64;
+        let block = word / BLOCK_WORDS;
+        let mut rank = self.block_ranks[block] as usize;
+        for w in &self.words[block * BLOCK_WORDS..word] {
+            rank += w.count_ones() as usize;
+        }
+        if !i.is_multiple_of(64) {
+            rank += (self.words[word] & ((1 << (i % 64)) - 1)).count_ones() as usize;
+        }
+        rank
+    }
+
+    /// Returns the number of zeroes before position `i`
+    #[inline(always)]
+    fn rank0(&self, i: usize) -> usize {
+        i - self.rank1(i)
+    }
+
+    /// Returns the space used in bytes
+    fn size_bytes(&self) -> usize {
+        (self.words.len() + self.block_ranks.len()) * std::mem::size_of::<u64>()
+    }
+}
+
+/// Sequence of bytes supporting access and rank, one bit level per bit of the bytes
+struct WaveletMatrix {
+    levels: Vec<RankedBits>,    // Bit levels, most significant bit first
+    zeros: Vec<usize>,          // Zeroes of each level
+}
+
+impl WaveletMatrix {
+    /// Builds the wavelet matrix of a byte sequence
+    fn new(symbols: &[u8]) -> Self {
+        let mut levels = Vec::with_capacity(8);
+        let mut zeros = Vec::with_capacity(8);
+        let mut current = symbols.to_vec();
+        for level in 0..8 {
+            let shift = 7 - level;
+            let bits = RankedBits::from_fn(current.len(), |i| (current[i] >> shift) & 1 == 1);
+            zeros.push(bits.rank0(current.len()));
+            levels.push(bits);
+
+            // Stable partition by the bit of the level, zeroes first
+            let (mut next, ones): (Vec<u8>, Vec<u8>) = current.iter().partition(|&&c| (c >> shift) & 1 == 0);
+            next.extend(ones);
+            current = next;
+        }
+        WaveletMatrix { levels, zeros }
+    }
+
+    /// Returns the number of occurrences of `symbol` before position `i`
+    #[inline(always)]
+    fn rank(&self, symbol: u8, i: usize) -> usize {
+        let mut position = i;
+        let mut start = 0;
+        for (level, (bits, &zeros)) in self.levels.iter().zip(self.zeros.iter()).enumerate() {
+            if (symbol >> (7 - level)) & 1 == 0 {
+                position = bits.rank0(position);
+                start = bits.rank0(start);
+            } else {
+                position = zeros + bits.rank1(position);
+                start = zeros + bits.rank1(start);
+            }
+        }
+        position - start
+    }
+
+    /// Returns the symbol at position `i`, and its occurrences before position `i`
+    #[inline(always)]
+    fn access_rank(&self, i: usize) -> (u8, usize) {
+        let mut symbol = 0u8;
+        let mut position = i;
+        let mut start = 0;
+        for (bits, &zeros) in self.levels.iter().zip(self.zeros.iter()) {
+            let bit = bits.get(position);
+            symbol = (symbol << 1) | bit as u8;
+            if bit {
+                position = zeros + bits.rank1(position);
+                start = zeros + bits.rank1(start);
+            } else {
+                position = bits.rank0(position);
+                start = bits.rank0(start);
+            }
+        }
+        (symbol, position - start)
+    }
+
+    /// Returns the space used in bytes
+    fn size_bytes(&self) -> usize {
+        self.levels.iter().map(RankedBits::size_bytes).sum::<usize>() + self.zeros.len() * std::mem::size_of::<usize>()
+    }
+}
+
+/// FM-index over the concatenated strings
+///
+/// Rows are the suffixes of the corpus followed by a terminator, smaller than any byte,
+/// in lexicographic order: row 0 is the terminator alone.
+pub struct FMIndexCompressor {
+    sample_rate: usize,                 // Distance between sampled text positions
+    data_size: usize,                   // Corpus length, excluding the terminator
+    bwt: WaveletMatrix,                 // BWT of the rows, the terminator replaced by byte 0
+    terminator_row: usize,              // Row whose BWT symbol is the terminator
+    symbol_starts: Vec<usize>,          // First row of the suffixes starting with each byte
+    sampled_rows: RankedBits,           // Rows whose suffix starts at a sampled text position
+    sa_samples: Vec<u32>,               // Text position of each sampled row, in row order
+    isa_samples: Vec<u32>,              // Row of each sampled text position
+    item_bounds: LengthIndex,           // String boundaries in the corpus
+}
+
+impl Compressor for FMIndexCompressor {
+    fn new(data_size: usize, n_elements: usize) -> Self {
+        Self::with_sample_rate(data_size, n_elements, DEFAULT_SAMPLE_RATE)
+    }
+
+    fn compress(&mut self, data: &[u8], end_positions: &[usize]) {
+        self.capacity().assert_fits(end_positions, self.name());
+        let sa = info_span!("suffix_array").in_scope(|| suffix_array(data));
+
+        let _index_span = info_span!("index").entered();
+        self.data_size = data.len();
+        self.terminator_row = sa.iter().position(|&position| position == 0).unwrap();
+        let bwt: Vec<u8> = sa.iter().map(|&position| if position == 0 { 0 } else { data[position as usize - 1] }).collect();
+        self.bwt = WaveletMatrix::new(&bwt);
+
+        let mut counts = [0usize; 256];
+        for &b in data {
+            counts[b as usize] += 1;
+        }
+        self.symbol_starts = Vec::with_capacity(256);
+        let mut start = 1;
+        for count in counts {
+            self.symbol_starts.push(start);
+            start += count;
+        }
+
+        let sample_rate = self.sample_rate;
+        self.sampled_rows = RankedBits::from_fn(sa.len(), |row| (sa[row] as usize).is_multiple_of(sample_rate));
+        self.sa_samples = sa.iter().copied().filter(|&position| (position as usize).is_multiple_of(sample_rate)).collect();
+        self.isa_samples = vec![0; data.len() / sample_rate + 1];
+        for (row, &position) in sa.iter().enumerate() {
+            if (position as usize).is_multiple_of(sample_rate) {
+                self.isa_samples[position as usize / sample_rate] = row as u32;
+            }
+        }
+
+        self.item_bounds = LengthIndex::new(end_positions);
+        debug!(n_samples = self.sa_samples.len(), index_size = self.space_used_bytes(), "Index built");
+    }
+
+    fn decompress(&self, buffer: &mut [u8]) -> usize {
+        // Row 0 is the terminator: walking back from it yields the corpus in reverse
+        let mut row = 0;
+        for position in (0..self.data_size).rev() {
+            let (symbol, previous_row) = self.lf(row);
+            buffer[position] = symbol;
+            row = previous_row;
+        }
+        self.data_size
+    }
+
+    fn decompress_to<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        // The corpus is extracted backwards, so chunks are extracted one at a time from their end
+        let mut stream = StreamBuffer::new(writer);
+        for start in (0..self.data_size).step_by(STREAM_CHUNK_SIZE) {
+            let end = (start + STREAM_CHUNK_SIZE).min(self.data_size);
+            self.extract(start, end, stream.reserve(end - start)?);
+            stream.commit(end - start);
+        }
+        stream.finish()
+    }
+
+    fn get_item_at(&mut self, index: usize, buffer: &mut [u8]) -> usize {
+        let (start, end) = self.item_bounds.bounds(index);
+        self.extract(start, end, buffer);
+        end - start
+    }
+
+    fn len_of_item(&self, index: usize) -> usize {
+        self.item_bounds.len_of(index)
+    }
+
+    fn max_item_len(&self) -> usize {
+        max_len_of_items(self, self.item_bounds.len())
+    }
+
+    fn offset_to_item(&self, offset: usize) -> Option<(usize, usize)> {
+        self.item_bounds.offset_to_item(offset)
+    }
+
+    /// Reports the limits of the 32-bit suffix array samples, which also address the terminator
+    fn capacity(&self) -> Capacity {
+        Capacity { max_data_size: MAX_U32_OFFSET - 1, ..Capacity::UNLIMITED }
+    }
+
+    fn space_used_bytes(&self) -> usize {
+        self.space_breakdown().total()
+    }
+
+    fn space_breakdown(&self) -> SpaceBreakdown {
+        SpaceBreakdown {
+            payload: self.bwt.size_bytes()
+                + self.symbol_starts.len() * std::mem::size_of::<usize>()
+                + self.sampled_rows.size_bytes()
+                + (self.sa_samples.len() + self.isa_samples.len()) * std::mem::size_of::<u32>(),
+            item_offsets: self.item_bounds.size_bytes(),
+            ..SpaceBreakdown::default()
+        }
+    }
+
+    fn name(&self) -> &str {
+        "FM-index"
+    }
+}
+
+impl FMIndexCompressor {
+    /// Creates an index with a custom sampling of the suffix array
+    ///
+    /// # Arguments
+    /// - `data_size`: Total size of input data in bytes
+    /// - `n_elements`: Number of individual strings in the dataset
+    /// - `sample_rate`: Distance between sampled text positions: larger rates save space,
+    ///   at the cost of up to `sample_rate` extra steps per extracted string or located occurrence
+    pub fn with_sample_rate(_data_size: usize, _n_elements: usize, sample_rate: usize) -> Self {
+        assert!(sample_rate >= 1, "sample_rate must be at least 1");
+
+        FMIndexCompressor {
+            sample_rate,
+            data_size: 0,
+            bwt: WaveletMatrix::new(&[]),
+            terminator_row: 0,
+            symbol_starts: Vec::new(),
+            sampled_rows: RankedBits::from_fn(0, |_| false),
+            sa_samples: Vec::new(),
+            isa_samples: Vec::new(),
+            item_bounds: LengthIndex::new(&[]),
+        }
+    }
+
+    /// Counts the occurrences of a pattern in the corpus
+    ///
+    /// The corpus is the concatenation of the strings, so that occurrences spanning
+    /// consecutive strings are counted too (see `locate` for occurrences within strings).
+    ///
+    /// # Returns
+    /// Number of occurrences, 0 for an empty pattern
+    pub fn count(&self, pattern: &[u8]) -> usize {
+        self.row_range(pattern).map_or(0, |(first, last)| last - first)
+    }
+
+    /// Locates the occurrences of a pattern within the strings
+    ///
+    /// # Returns
+    /// Index of the string and offset in the string of each occurrence, sorted; empty
+    /// for an empty pattern
+    pub fn locate(&self, pattern: &[u8]) -> Vec<(usize, usize)> {
+        let Some((first, last)) = self.row_range(pattern) else {
+            return Vec::new();
+        };
+
+        let mut occurrences: Vec<(usize, usize)> = (first..last)
+            .filter_map(|row| {
+                let position = self.locate_row(row);
+                let index = self.item_bounds.find(position);
+                let (start, end) = self.item_bounds.bounds(index);
+                (position + pattern.len() <= end).then(|| (index, position - start))
+            })
+            .collect();
+        occurrences.sort_unstable();
+        occurrences
+    }
+
+    /// Returns the range of rows prefixed by a pattern (backward search)
+    fn row_range(&self, pattern: &[u8]) -> Option<(usize, usize)> {
+        if pattern.is_empty() || self.data_size == 0 {
+            return None;
+        }
+
+        let mut first = 0;
+        let mut last = self.data_size + 1;
+        for &symbol in pattern.iter().rev() {
+            let start = self.symbol_starts[symbol as usize];
+            first = start + self.rank(symbol, first);
+            last = start + self.rank(symbol, last);
+            if first >= last {
+                return None;
+            }
+        }
+        Some((first, last))
+    }
+
+    /// Returns the occurrences of a byte in the BWT before a row
+    #[inline(always)]
+    fn rank(&self, symbol: u8, row: usize) -> usize {
+        self.bwt.rank(symbol, row) - (symbol == 0 && self.terminator_row < row) as usize
+    }
+
+    /// Maps the row of the suffix at position `p` to the byte at `p - 1` and the row of the suffix at `p - 1`
+    ///
+    /// Must not be called on the terminator row (position 0).
+    #[inline(always)]
+    fn lf(&self, row: usize) -> (u8, usize) {
+        let (symbol, rank) = self.bwt.access_rank(row);
+        let rank = rank - (symbol == 0 && self.terminator_row < row) as usize;
+        (symbol, self.symbol_starts[symbol as usize] + rank)
+    }
+
+    /// Returns the text position of the suffix of a row
+    fn locate_row(&self, mut row: usize) -> usize {
+        let mut steps = 0;
+        while !self.sampled_rows.get(row) {
+            row = self.lf(row).1;
+            steps += 1;
+        }
+        self.sa_samples[self.sampled_rows.rank1(row)] as usize + steps
+    }
+
+    /// Extracts the bytes of the corpus in `start..end` into `buffer`
+    fn extract(&self, start: usize, end: usize, buffer: &mut [u8]) {
+        // Start from the nearest sampled position at or after the end, or the terminator
+        let mut position = end.next_multiple_of(self.sample_rate).min(self.data_size);
+        let mut row = if position == self.data_size { 0 } else { self.isa_samples[position / self.sample_rate] as usize };
+        while position > start {
+            let (symbol, previous_row) = self.lf(row);
+            position -= 1;
+            if position < end {
+                buffer[position - start] = symbol;
+            }
+            row = previous_row;
+        }
+    }
+}
+
+/// Builds the suffix array of the data followed by a terminator, by prefix doubling
+///
+/// # Returns
+/// Start position of each suffix in lexicographic order, the terminator (position
+/// `data.len()`) first
+fn suffix_array(data: &[u8]) -> Vec<u32> {
+    let n = data.len() + 1;
+    // Rank of each suffix by its first byte, the terminator ranking lowest
+    let mut rank: Vec<u32> = data.iter().map(|&b| b as u32 + 1).chain(std::iter::once(0)).collect();
+    let mut sa: Vec<u32> = (0..n as u32).collect();
+    let mut length = 1;
+
+    loop {
+        // Sort by the ranks of the first `length` bytes, then of the next `length` bytes
+        let key = |i: u32| {
+            let i = i as usize;
+            let next = if i + length < n { rank[i + length] as u64 + 1 } else { 0 };
+            ((rank[i] as u64) << 32) | next
+        };
+        sa.sort_by_cached_key(|&i| key(i));
+
+        let mut next_rank = vec![0u32; n];
+        let mut current = 0;
+        for w in 1..n {
+            if key(sa[w]) != key(sa[w - 1]) {
+                current += 1;
+            }
+            next_rank[sa[w] as usize] = current;
+        }
+        rank = next_rank;
+
+        if current as usize == n - 1 {
+            return sa;
+        }
+        length *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naive_count(data: &[u8], pattern: &[u8]) -> usize {
+        if pattern.is_empty() { 0 } else { data.windows(pattern.len()).filter(|window| *window == pattern).count() }
+    }
+
+    fn naive_locate(strings: &[&[u8]], pattern: &[u8]) -> Vec<(usize, usize)> {
+        if pattern.is_empty() {
+            return Vec::new();
+        }
+        strings
+            .iter()
+            .enumerate()
+            .flat_map(|(index, item)| {
+                item.windows(pattern.len()).enumerate().filter(|(_, window)| *window == pattern).map(move |(offset, _)| (index, offset))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn count_and_locate_match_a_naive_search() {
+        // Repeats, overlapping occurrences, zero bytes (sorting with the terminator) and an empty string
+        let strings: [&[u8]; 7] = [b"banana", b"ananas", b"", b"bandana\0ban", b"aaaa", b"\0\0nab", b"cabana"];
+        let data: Vec<u8> = strings.concat();
+        let mut end_positions = vec![0];
+        for item in strings {
+            end_positions.push(end_positions.last().unwrap() + item.len());
+        }
+        let patterns: [&[u8]; 13] = [
+            b"a", b"an", b"ana", b"banana", b"nab", b"aa", b"\0", b"\0ban", b"sb",  // Present, "sb" only across strings
+            b"xyz", b"bananas", b"nn",                                             // Absent
+            b"",                                                                    // Empty
+        ];
+
+        for sample_rate in [1, 3, DEFAULT_SAMPLE_RATE] {
+            let mut index = FMIndexCompressor::with_sample_rate(data.len(), strings.len(), sample_rate);
+            index.compress(&data, &end_positions);
+            for pattern in patterns {
+                assert_eq!(index.count(pattern), naive_count(&data, pattern), "count {:?} at rate {}", pattern, sample_rate);
+                assert_eq!(index.locate(pattern), naive_locate(&strings, pattern), "locate {:?} at rate {}", pattern, sample_rate);
+            }
+        }
+    }
+
+    #[test]
+    fn empty_corpus_has_no_occurrences() {
+        let mut index = FMIndexCompressor::new(0, 1);
+        index.compress(&[], &[0, 0]);
+        assert_eq!(index.count(b"a"), 0);
+        assert!(index.locate(b"a").is_empty());
+        assert!(index.locate(b"").is_empty());
+    }
+}
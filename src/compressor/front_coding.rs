@@ -0,0 +1,369 @@
+//! Front coding in buckets, with order-preserving search
+//!
+//! Strings are grouped in buckets of `bucket_size` consecutive strings. The first string
+//! of each bucket (its header) is stored in full, and every following one as the length
+//! of the prefix it shares with its predecessor plus the remaining suffix, lengths being
+//! variable-byte integers. Accessing a string decodes its bucket up to it.
+//!
+//! On sorted strings, consecutive strings share long prefixes, and the collection acts
+//! as an order-preserving string dictionary, as used by databases to encode columns:
+//! `locate` maps a string to its index (its rank), and `range` maps bounds to the range
+//! of indices of the strings between them, so that predicates on strings are evaluated
+//! on indices. Both binary search the bucket headers, then scan a single bucket.
+
+use super::prefix_match::common_prefix_length;
+use super::{max_len_of_items, BlockPosition, Compressor, ItemBoundaries, SpaceBreakdown, StreamBuffer};
+use std::cmp::Ordering;
+use std::io::{self, Write};
+use std::ops::Range;
+
+/// Default number of strings per bucket
+pub const DEFAULT_BUCKET_SIZE: usize = 16;
+
+/// Front-coded string collection
+pub struct FrontCodingCompressor {
+    bucket_size: usize,             // Strings per bucket (the last bucket may hold fewer)
+    data: Vec<u8>,                  // Encoded buckets
+    bucket_offsets: Vec<usize>,     // Start of each bucket in `data`, plus the end of the last one
+    n_items: usize,
+    is_sorted: bool,                // Whether the strings are sorted, enabling `locate` and `range`
+}
+
+impl Compressor for FrontCodingCompressor {
+    fn new(data_size: usize, n_elements: usize) -> Self {
+        Self::with_bucket_size(data_size, n_elements, DEFAULT_BUCKET_SIZE)
+    }
+
+    fn compress(&mut self, data: &[u8], end_positions: &[usize]) {
+        self.n_items = end_positions.len() - 1;
+        self.is_sorted = end_positions.windows(3).all(|w| data[w[0]..w[1]] <= data[w[1]..w[2]]);
+
+        let mut previous: &[u8] = &[];
+        for (index, window) in end_positions.windows(2).enumerate() {
+            let item = &data[window[0]..window[1]];
+            if index % self.bucket_size == 0 {
+                self.bucket_offsets.push(self.data.len());
+                write_varint(&mut self.data, item.len());
+                self.data.extend_from_slice(item);
+            } else {
+                let shared = common_prefix_length(item, previous);
+                write_varint(&mut self.data, shared);
+                write_varint(&mut self.data, item.len() - shared);
+                self.data.extend_from_slice(&item[shared..]);
+            }
+            previous = item;
+        }
+        self.bucket_offsets.push(self.data.len());
+    }
+
+    fn decompress(&self, buffer: &mut [u8]) -> usize {
+        let mut size = 0;
+        for bucket in 0..self.n_buckets() {
+            size += self.decode_bucket(bucket, &mut buffer[size..]);
+        }
+        size
+    }
+
+    fn decompress_to<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        // Strings of a bucket are decoded in place of their predecessor: a bucket is
+        // decoded into scratch space, then flushed
+        let mut stream = StreamBuffer::new(writer);
+        let mut item = Vec::new();
+        for bucket in 0..self.n_buckets() {
+            let mut reader = self.reader(bucket);
+            while let Some((shared, suffix)) = reader.next() {
+                item.truncate(shared);
+                item.extend_from_slice(suffix);
+                stream.reserve(item.len())?[..item.len()].copy_from_slice(&item);
+                stream.commit(item.len());
+            }
+        }
+        stream.finish()
+    }
+
+    fn get_item_at(&mut self, index: usize, buffer: &mut [u8]) -> usize {
+        let bucket = index / self.bucket_size;
+        let mut reader = self.reader(bucket);
+        let mut length = 0;
+        for _ in 0..=index % self.bucket_size {
+            let (shared, suffix) = reader.next().unwrap();
+            buffer[shared..shared + suffix.len()].copy_from_slice(suffix);
+            length = shared + suffix.len();
+        }
+        length
+    }
+
+    fn scan_range<P: FnMut(usize, &[u8])>(&mut self, start_index: usize, end_index: usize, mut projector: P) {
+        // Buckets are decoded once, each string in place of its predecessor; strings of
+        // the first bucket preceding the range are decoded but not projected
+        let mut item = Vec::new();
+        let mut index = start_index - start_index % self.bucket_size;
+        while index < end_index {
+            let mut reader = self.reader(index / self.bucket_size);
+            while let Some((shared, suffix)) = reader.next() {
+                item.truncate(shared);
+                item.extend_from_slice(suffix);
+                if index >= start_index {
+                    projector(index, &item);
+                }
+                index += 1;
+                if index == end_index {
+                    break;
+                }
+            }
+        }
+    }
+
+    fn len_of_item(&self, index: usize) -> usize {
+        let bucket = index / self.bucket_size;
+        let mut reader = self.reader(bucket);
+        let mut length = 0;
+        for _ in 0..=index % self.bucket_size {
+            let (shared, suffix) = reader.next().unwrap();
+            length = shared + suffix.len();
+        }
+        length
+    }
+
+    fn max_item_len(&self) -> usize {
+        max_len_of_items(self, self.n_items)
+    }
+
+    fn block_position(&self, index: usize) -> Option<BlockPosition> {
+        let first_item = index - index % self.bucket_size;
+        Some(BlockPosition::of(index - first_item, self.bucket_size.min(self.n_items - first_item)))
+    }
+
+    /// Reports boundaries found while decoding: strings are located by bucket, then by their encoded lengths
+    fn item_boundaries(&self) -> ItemBoundaries {
+        ItemBoundaries::Decoded
+    }
+
+    fn space_used_bytes(&self) -> usize {
+        self.data.len() + self.bucket_offsets.len() * std::mem::size_of::<usize>()
+    }
+
+    fn space_breakdown(&self) -> SpaceBreakdown {
+        SpaceBreakdown {
+            payload: self.data.len(),
+            block_metadata: self.bucket_offsets.len() * std::mem::size_of::<usize>(),
+            ..SpaceBreakdown::default()
+        }
+    }
+
+    fn name(&self) -> &str {
+        "Front coding"
+    }
+}
+
+impl FrontCodingCompressor {
+    /// Creates a compressor with a custom bucket size
+    ///
+    /// # Arguments
+    /// - `data_size`: Total size of input data in bytes
+    /// - `n_elements`: Number of individual strings in the dataset
+    /// - `bucket_size`: Strings per bucket: larger buckets share more prefixes, at the cost
+    ///   of decoding more strings per access
+    pub fn with_bucket_size(data_size: usize, n_elements: usize, bucket_size: usize) -> Self {
+        assert!(bucket_size >= 1, "bucket_size must be at least 1");
+
+        FrontCodingCompressor {
+            bucket_size,
+            data: Vec::with_capacity(data_size),
+            bucket_offsets: Vec::with_capacity(n_elements / bucket_size + 2),
+            n_items: 0,
+            is_sorted: true,
+        }
+    }
+
+    /// Returns whether the compressed strings are sorted, as required by `locate` and `range`
+    pub fn is_sorted(&self) -> bool {
+        self.is_sorted
+    }
+
+    /// Returns the index of a string
+    ///
+    /// # Returns
+    /// The index of the first occurrence of the string, or `None` if it is not stored
+    pub fn locate(&self, string: &[u8]) -> Option<usize> {
+        let (index, found) = self.lower_bound(string);
+        found.then_some(index)
+    }
+
+    /// Returns the indices of the strings `s` such that `lower <= s < upper`
+    pub fn range(&self, lower: &[u8], upper: &[u8]) -> Range<usize> {
+        let start = self.lower_bound(lower).0;
+        let end = self.lower_bound(upper).0.max(start);
+        start..end
+    }
+
+    /// Returns the number of strings smaller than `string`, and whether the next one equals it
+    ///
+    /// Binary searches the last bucket whose header is smaller than the string, then
+    /// scans that bucket.
+    pub fn lower_bound(&self, string: &[u8]) -> (usize, bool) {
+        assert!(self.is_sorted, "Order-preserving search requires sorted strings");
+
+        let n_buckets = self.n_buckets();
+        let (mut low, mut high) = (0, n_buckets);
+        while low < high {
+            let middle = (low + high) / 2;
+            if self.header(middle) < string {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        let bucket = low;
+        if bucket == 0 {
+            return (0, n_buckets > 0 && self.header(0) == string);
+        }
+
+        // Every string of the buckets after the previous one is at least `string`
+        let bucket = bucket - 1;
+        let mut reader = self.reader(bucket);
+        let mut item = Vec::new();
+        let mut index = bucket * self.bucket_size;
+        while let Some((shared, suffix)) = reader.next() {
+            item.truncate(shared);
+            item.extend_from_slice(suffix);
+            match item.as_slice().cmp(string) {
+                Ordering::Less => index += 1,
+                Ordering::Equal => return (index, true),
+                Ordering::Greater => return (index, false),
+            }
+        }
+        (index, index < self.n_items && self.header(bucket + 1) == string)
+    }
+
+    /// Returns the number of buckets
+    fn n_buckets(&self) -> usize {
+        self.bucket_offsets.len().saturating_sub(1)
+    }
+
+    /// Returns a reader of the strings of a bucket
+    fn reader(&self, bucket: usize) -> BucketReader<'_> {
+        BucketReader::new(&self.data[self.bucket_offsets[bucket]..self.bucket_offsets[bucket + 1]])
+    }
+
+    /// Returns the first string of a bucket, stored in full
+    fn header(&self, bucket: usize) -> &[u8] {
+        let mut position = self.bucket_offsets[bucket];
+        let length = read_varint(&self.data, &mut position);
+        &self.data[position..position + length]
+    }
+
+    /// Decodes the strings of a bucket into a buffer, returning their total length
+    fn decode_bucket(&self, bucket: usize, buffer: &mut [u8]) -> usize {
+        let mut reader = self.reader(bucket);
+        let mut size = 0;
+        let mut previous_start = 0;
+        while let Some((shared, suffix)) = reader.next() {
+            // The shared prefix is copied from the predecessor, just written
+            buffer.copy_within(previous_start..previous_start + shared, size);
+            buffer[size + shared..size + shared + suffix.len()].copy_from_slice(suffix);
+            previous_start = size;
+            size += shared + suffix.len();
+        }
+        size
+    }
+}
+
+/// Reader of the strings of a bucket, as (shared prefix length, suffix) pairs
+struct BucketReader<'a> {
+    bucket: &'a [u8],
+    position: usize,
+    is_header: bool,
+}
+
+impl<'a> BucketReader<'a> {
+    fn new(bucket: &'a [u8]) -> Self {
+        BucketReader { bucket, position: 0, is_header: true }
+    }
+
+    /// Returns the next string of the bucket, or `None` at its end
+    fn next(&mut self) -> Option<(usize, &'a [u8])> {
+        if self.position == self.bucket.len() {
+            return None;
+        }
+        let shared = if self.is_header { 0 } else { read_varint(self.bucket, &mut self.position) };
+        self.is_header = false;
+        let length = read_varint(self.bucket, &mut self.position);
+        let suffix = &self.bucket[self.position..self.position + length];
+        self.position += length;
+        Some((shared, suffix))
+    }
+}
+
+/// Appends a variable-byte integer (7 bits per byte, least significant first)
+fn write_varint(output: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        output.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
+
+/// Reads a variable-byte integer, advancing the position past it
+#[inline(always)]
+fn read_varint(input: &[u8], position: &mut usize) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = input[*position];
+        *position += 1;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte < 0x80 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sorted strings with duplicates, one of them across a boundary of buckets of 4 strings
+    const STRINGS: [&[u8]; 11] = [b"", b"app", b"apple", b"apply", b"apply", b"banana", b"band", b"bandana", b"can", b"cane", b"zebra"];
+
+    fn compress(strings: &[&[u8]], bucket_size: usize) -> FrontCodingCompressor {
+        let data = strings.concat();
+        let mut end_positions = vec![0];
+        for item in strings {
+            end_positions.push(end_positions.last().unwrap() + item.len());
+        }
+        let mut compressor = FrontCodingCompressor::with_bucket_size(data.len(), strings.len(), bucket_size);
+        compressor.compress(&data, &end_positions);
+        compressor
+    }
+
+    #[test]
+    fn locate_and_range_match_a_binary_search() {
+        // Misses before, between and after the strings, and right before and after bucket headers
+        let keys: [&[u8]; 16] = [
+            b"", b"a", b"ap", b"app", b"appl", b"apply", b"applz", b"b", b"banana", b"bandan", b"can", b"canal", b"cane", b"d", b"zebra", b"zz",
+        ];
+        for bucket_size in [1, 2, 4, DEFAULT_BUCKET_SIZE] {
+            let compressor = compress(&STRINGS, bucket_size);
+            assert!(compressor.is_sorted());
+            for key in keys.iter().copied().chain(STRINGS) {
+                let start = STRINGS.partition_point(|item| *item < key);
+                let expected = (start < STRINGS.len() && STRINGS[start] == key).then_some(start);
+                assert_eq!(compressor.locate(key), expected, "{:?} in buckets of {}", key, bucket_size);
+                for upper in keys {
+                    let end = STRINGS.partition_point(|item| *item < upper).max(start);
+                    assert_eq!(compressor.range(key, upper), start..end, "{:?}..{:?} in buckets of {}", key, upper, bucket_size);
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "requires sorted strings")]
+    fn locate_rejects_unsorted_strings() {
+        let compressor = compress(&[b"pear", b"apple"], DEFAULT_BUCKET_SIZE);
+        assert!(!compressor.is_sorted());
+        compressor.locate(b"pear");
+    }
+}
@@ -0,0 +1,379 @@
+//! Fast Static Symbol Table (FSST) compression, native implementation
+//!
+//! FSST (Boncz, Neumann and Leis, VLDB 2020) replaces substrings of up to 8 bytes by
+//! one-byte codes of a static table of at most 255 symbols, code 255 escaping a literal
+//! byte. Strings are compressed independently, so that random access decodes a single
+//! string with one table lookup and one 8-byte copy per code.
+//!
+//! The table is trained in `GENERATIONS` generations on a sample of about 16 KiB, as in
+//! the reference implementation: each generation compresses the sample with the current
+//! table, counts the codes and the pairs of consecutive codes, and keeps the 255
+//! symbols (single codes, escaped bytes or concatenations of two codes, cut to 8 bytes)
+//! with the highest gain, their length times their frequency. Encoding finds the symbol
+//! at each position through a table indexed by the next two bytes (symbols of one or two
+//! bytes) and a lossy hash table on the next three bytes (longer symbols, one per slot):
+//! a symbol colliding with a symbol of higher gain is dropped from the table.
+//!
+//! This is a reimplementation in Rust; `fsst_ffi` binds the reference library, so that
+//! the two entries separate the cost of the binding from algorithmic differences.
+
+use crate::compressor::{max_len_of_items, Compressor, SpaceBreakdown, StreamBuffer};
+use crate::compressor::sampling::sample_stratified_strings;
+use crate::profiler::{Stage, StageTimer};
+use rustc_hash::FxHashMap;
+use std::io::{self, Write};
+
+/// Maximum number of symbols of a table
+const MAX_SYMBOLS: usize = 255;
+/// Code preceding an escaped literal byte
+const ESCAPE_CODE: u8 = 255;
+/// Maximum symbol length in bytes
+pub(crate) const MAX_SYMBOL_LENGTH: usize = 8;
+/// Target size of the training sample in bytes
+const SAMPLE_TARGET: usize = 1 << 14;
+/// Longest prefix of a string included in the training sample
+const SAMPLE_CHUNK: usize = 512;
+/// Training generations, each rebuilding the table from the counts of the previous one
+const GENERATIONS: usize = 5;
+/// Bits of the hash of the first three bytes of a symbol
+const HASH_BITS: u32 = 10;
+/// Tag of an empty hash slot
+const EMPTY_SLOT: u16 = u16::MAX;
+/// Codes counted by training: the symbols, then the escaped literal bytes
+const COUNTED_CODES: usize = MAX_SYMBOLS + 256;
+/// Size of the header of a serialized table (version, terminator flag and length histogram)
+const TABLE_HEADER_SIZE: usize = 17;
+
+/// Symbol table, with the lookup structures of encoding
+struct SymbolTable {
+    symbols: [u64; 256],    // Symbol of each code, little-endian and zero-padded
+    lengths: [u8; 256],     // Length of each symbol in bytes (0 for unused codes)
+    n_symbols: usize,
+    short_codes: Vec<u16>,  // Longest symbol of at most 2 bytes matching each 2-byte prefix (length << 8 | code)
+    byte_codes: [u16; 256], // Symbol of 1 byte matching each byte, or an escape (length << 8 | code)
+    long_codes: Vec<u16>,   // Code of the symbol of 3 bytes or more in each hash slot, or EMPTY_SLOT
+}
+
+impl SymbolTable {
+    /// Builds a table from candidate symbols, by decreasing gain
+    ///
+    /// Symbols of 3 bytes or more colliding with a previous one in the hash table are
+    /// skipped, and the table holds at most `MAX_SYMBOLS` symbols.
+    fn new(candidates: &[(u64, u8)]) -> Self {
+        let mut table = SymbolTable {
+            symbols: [0; 256],
+            lengths: [0; 256],
+            n_symbols: 0,
+            short_codes: Vec::new(),
+            byte_codes: [1 << 8 | ESCAPE_CODE as u16; 256],
+            long_codes: vec![EMPTY_SLOT; 1 << HASH_BITS],
+        };
+        for &(symbol, length) in candidates {
+            if table.n_symbols == MAX_SYMBOLS {
+                break;
+            }
+            let code = table.n_symbols;
+            if length >= 3 {
+                let slot = hash_slot(symbol);
+                if table.long_codes[slot] != EMPTY_SLOT {
+                    continue;
+                }
+                table.long_codes[slot] = code as u16;
+            }
+            table.symbols[code] = symbol;
+            table.lengths[code] = length;
+            table.n_symbols += 1;
+        }
+
+        for code in 0..table.n_symbols {
+            if table.lengths[code] == 1 {
+                table.byte_codes[table.symbols[code] as usize] = 1 << 8 | code as u16;
+            }
+        }
+        table.short_codes = (0..1 << 16).map(|prefix: usize| table.byte_codes[prefix & 0xFF]).collect();
+        for code in 0..table.n_symbols {
+            if table.lengths[code] == 2 {
+                table.short_codes[table.symbols[code] as usize] = 2 << 8 | code as u16;
+            }
+        }
+        table
+    }
+
+    /// Trains a table on a sample of the strings
+    fn train(data: &[u8], end_positions: &[usize]) -> Self {
+        let sample = training_sample(data, end_positions);
+        let mut table = SymbolTable::new(&[]);
+        let mut counts = vec![0u32; COUNTED_CODES];
+        let mut pair_counts = vec![0u32; COUNTED_CODES * COUNTED_CODES];
+        for _ in 0..GENERATIONS {
+            counts.fill(0);
+            pair_counts.fill(0);
+            for item in sample.iter() {
+                let mut position = 0;
+                let mut previous = None;
+                while position < item.len() {
+                    let (code, length) = table.find(&item[position..]);
+                    let counted = if code == ESCAPE_CODE { MAX_SYMBOLS + item[position] as usize } else { code as usize };
+                    counts[counted] += 1;
+                    if let Some(previous) = previous {
+                        pair_counts[previous * COUNTED_CODES + counted] += 1;
+                    }
+                    previous = Some(counted);
+                    position += length;
+                }
+            }
+            table = SymbolTable::new(&table.candidates(&counts, &pair_counts));
+        }
+        table
+    }
+
+    /// Ranks the candidate symbols of the next generation by decreasing gain
+    fn candidates(&self, counts: &[u32], pair_counts: &[u32]) -> Vec<(u64, u8)> {
+        let mut gains: FxHashMap<(u64, u8), u64> = FxHashMap::default();
+        for first in 0..COUNTED_CODES {
+            if counts[first] == 0 {
+                continue;
+            }
+            let (first_symbol, first_length) = self.counted_symbol(first);
+            *gains.entry((first_symbol, first_length)).or_default() += counts[first] as u64 * first_length as u64;
+            if first_length as usize == MAX_SYMBOL_LENGTH {
+                continue;
+            }
+            for second in 0..COUNTED_CODES {
+                let count = pair_counts[first * COUNTED_CODES + second];
+                if count == 0 {
+                    continue;
+                }
+                let (second_symbol, second_length) = self.counted_symbol(second);
+                let length = (first_length + second_length).min(MAX_SYMBOL_LENGTH as u8);
+                let symbol = (first_symbol | second_symbol << (8 * first_length as u32)) & length_mask(length as usize);
+                *gains.entry((symbol, length)).or_default() += count as u64 * length as u64;
+            }
+        }
+
+        let mut candidates: Vec<((u64, u8), u64)> = gains.into_iter().collect();
+        candidates.sort_unstable_by(|(a, a_gain), (b, b_gain)| b_gain.cmp(a_gain).then(a.cmp(b)));
+        candidates.into_iter().map(|(symbol, _)| symbol).collect()
+    }
+
+    /// Returns the symbol and length of a counted code (symbol or escaped byte)
+    fn counted_symbol(&self, counted: usize) -> (u64, u8) {
+        if counted < MAX_SYMBOLS {
+            (self.symbols[counted], self.lengths[counted])
+        } else {
+            ((counted - MAX_SYMBOLS) as u64, 1)
+        }
+    }
+
+    /// Finds the code encoding the start of the input, and the number of bytes it covers
+    #[inline(always)]
+    fn find(&self, input: &[u8]) -> (u8, usize) {
+        let word = load_word(input);
+        if input.len() >= 3 {
+            let code = self.long_codes[hash_slot(word)];
+            if code != EMPTY_SLOT {
+                let length = self.lengths[code as usize] as usize;
+                if length <= input.len() && word & length_mask(length) == self.symbols[code as usize] {
+                    return (code as u8, length);
+                }
+            }
+        }
+        let entry = if input.len() >= 2 { self.short_codes[(word & 0xFFFF) as usize] } else { self.byte_codes[(word & 0xFF) as usize] };
+        (entry as u8, (entry >> 8) as usize)
+    }
+
+    /// Appends the codes of a string to `output`, escaped bytes following their escape code
+    fn encode(&self, item: &[u8], output: &mut Vec<u8>) {
+        let mut position = 0;
+        while position < item.len() {
+            let (code, length) = self.find(&item[position..]);
+            output.push(code);
+            if code == ESCAPE_CODE {
+                output.push(item[position]);
+            }
+            position += length;
+        }
+    }
+
+    /// Returns the size of the table serialized as by the reference implementation
+    fn serialized_size(&self) -> usize {
+        TABLE_HEADER_SIZE + self.lengths[..self.n_symbols].iter().map(|&length| length as usize).sum::<usize>()
+    }
+}
+
+/// FSST compressor with a table trained on a sample of the strings
+pub struct FSSTCompressor {
+    table: SymbolTable,
+    compressed_data: Vec<u8>,           // Codes of all strings, concatenated
+    item_end_positions: Vec<usize>,     // Boundaries of the strings in `compressed_data`, starting with 0
+}
+
+impl Compressor for FSSTCompressor {
+    fn new(data_size: usize, n_elements: usize) -> Self {
+        FSSTCompressor {
+            table: SymbolTable::new(&[]),
+            compressed_data: Vec::with_capacity(data_size),
+            item_end_positions: Vec::with_capacity(n_elements + 1),
+        }
+    }
+
+    fn compress(&mut self, data: &[u8], end_positions: &[usize]) {
+        self.table = SymbolTable::train(data, end_positions);
+        self.item_end_positions.push(0);
+        for window in end_positions.windows(2) {
+            self.table.encode(&data[window[0]..window[1]], &mut self.compressed_data);
+            self.item_end_positions.push(self.compressed_data.len());
+        }
+        self.compressed_data.shrink_to_fit();
+    }
+
+    fn decompress(&self, buffer: &mut [u8]) -> usize {
+        // Escapes never span strings: the concatenated codes decode to the concatenated strings
+        self.decode(&self.compressed_data, buffer)
+    }
+
+    fn decompress_to<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let mut stream = StreamBuffer::new(writer);
+        for window in self.item_end_positions.windows(2) {
+            let codes = &self.compressed_data[window[0]..window[1]];
+            let size = self.decode(codes, stream.reserve(codes.len() * MAX_SYMBOL_LENGTH)?);
+            stream.commit(size);
+        }
+        stream.finish()
+    }
+
+    #[inline(always)]
+    fn get_item_at(&mut self, index: usize, buffer: &mut [u8]) -> usize {
+        let codes = &self.compressed_data[self.item_end_positions[index]..self.item_end_positions[index + 1]];
+        self.decode(codes, buffer)
+    }
+
+    fn get_item_at_profiled(&mut self, index: usize, buffer: &mut [u8], timer: &mut StageTimer) -> usize {
+        let codes = &self.compressed_data[self.item_end_positions[index]..self.item_end_positions[index + 1]];
+        timer.lap(Stage::Lookup);
+
+        // Symbols are copied to the buffer as they are decoded
+        let size = self.decode(codes, buffer);
+        timer.lap(Stage::Decode);
+        size
+    }
+
+    fn len_of_item(&self, index: usize) -> usize {
+        let codes = &self.compressed_data[self.item_end_positions[index]..self.item_end_positions[index + 1]];
+        decoded_length(&self.table.lengths, codes)
+    }
+
+    fn max_item_len(&self) -> usize {
+        max_len_of_items(self, self.item_end_positions.len().saturating_sub(1))
+    }
+
+    fn space_used_bytes(&self) -> usize {
+        self.space_breakdown().total()
+    }
+
+    fn space_breakdown(&self) -> SpaceBreakdown {
+        SpaceBreakdown {
+            payload: self.compressed_data.len(),
+            dictionary: self.table.serialized_size(),
+            item_offsets: self.item_end_positions.len() * std::mem::size_of::<usize>(),
+            ..SpaceBreakdown::default()
+        }
+    }
+
+    fn name(&self) -> &str {
+        "FSST (native)"
+    }
+}
+
+impl FSSTCompressor {
+    /// Decodes codes to `buffer`, which needs `MAX_SYMBOL_LENGTH` bytes of slack
+    ///
+    /// # Returns
+    /// Number of bytes decoded
+    #[inline(always)]
+    fn decode(&self, codes: &[u8], buffer: &mut [u8]) -> usize {
+        let mut size = 0;
+        let mut position = 0;
+        while position < codes.len() {
+            let code = codes[position];
+            if code == ESCAPE_CODE {
+                buffer[size] = codes[position + 1];
+                size += 1;
+                position += 2;
+            } else {
+                // Symbols are copied whole, then the write position advances by their length
+                buffer[size..size + MAX_SYMBOL_LENGTH].copy_from_slice(&self.table.symbols[code as usize].to_le_bytes());
+                size += self.table.lengths[code as usize] as usize;
+                position += 1;
+            }
+        }
+        size
+    }
+}
+
+/// Returns the decoded length of codes, from the symbol lengths of the table
+///
+/// # Arguments
+/// - `lengths`: Length of the symbol of each code
+/// - `codes`: Codes of a string, escaped bytes following their escape code
+pub(crate) fn decoded_length(lengths: &[u8], codes: &[u8]) -> usize {
+    let mut length = 0;
+    let mut position = 0;
+    while position < codes.len() {
+        if codes[position] == ESCAPE_CODE {
+            length += 1;
+            position += 2;
+        } else {
+            length += lengths[codes[position] as usize] as usize;
+            position += 1;
+        }
+    }
+    length
+}
+
+/// Picks the training sample: prefixes of stratified strings, up to `SAMPLE_TARGET` bytes
+fn training_sample<'a>(data: &'a [u8], end_positions: &[usize]) -> Vec<&'a [u8]> {
+    let n_items = end_positions.len() - 1;
+    if data.len() <= SAMPLE_TARGET {
+        return end_positions.windows(2).map(|w| &data[w[0]..w[1]]).collect();
+    }
+
+    let mean_length = (data.len() / n_items.max(1)).clamp(1, SAMPLE_CHUNK);
+    let mut sample = Vec::new();
+    let mut sample_size = 0;
+    for index in sample_stratified_strings(n_items, SAMPLE_TARGET / mean_length + 1) {
+        let start = end_positions[index];
+        let end = end_positions[index + 1].min(start + SAMPLE_CHUNK);
+        sample.push(&data[start..end]);
+        sample_size += end - start;
+        if sample_size >= SAMPLE_TARGET {
+            break;
+        }
+    }
+    sample
+}
+
+/// Reads up to 8 bytes of the input as a little-endian word, zero-padded
+#[inline(always)]
+fn load_word(input: &[u8]) -> u64 {
+    match input.get(..8) {
+        Some(bytes) => u64::from_le_bytes(bytes.try_into().unwrap()),
+        None => {
+            let mut bytes = [0u8; 8];
+            bytes[..input.len()].copy_from_slice(input);
+            u64::from_le_bytes(bytes)
+        }
+    }
+}
+
+/// Returns the mask of the first `length` bytes of a word
+#[inline(always)]
+fn length_mask(length: usize) -> u64 {
+    u64::MAX >> (64 - 8 * length as u32)
+}
+
+/// Returns the hash slot of the first three bytes of a word
+#[inline(always)]
+fn hash_slot(word: u64) -> usize {
+    ((word & 0xFF_FFFF).wrapping_mul(0x9E37_79B9_7F4A_7C15) >> (64 - HASH_BITS)) as usize
+}
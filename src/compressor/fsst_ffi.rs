@@ -0,0 +1,195 @@
+//! FSST through the C API of the reference library
+//!
+//! Binds `libfsst` from the reference implementation (github.com/cwida/fsst), built
+//! separately: `build.rs` links it from the directory in the `FSST_DIR` environment
+//! variable, and compiles `fsst_shim.cpp` to export `fsst_decompress`, which the
+//! library defines inline in its header. Strings are compressed by the library with a
+//! table trained on its own sample, and decoded one by one through the shim.
+//!
+//! Side by side with `fsst` (the native implementation), this entry separates the
+//! cost of the binding and of implementation choices from those of the algorithm.
+
+use crate::compressor::{max_len_of_items, Compressor, SpaceBreakdown, StreamBuffer};
+use crate::compressor::fsst::{decoded_length, MAX_SYMBOL_LENGTH};
+use crate::profiler::{Stage, StageTimer};
+use std::io::{self, Write};
+
+/// Maximum size of a serialized table (`FSST_MAXHEADER`)
+const MAX_HEADER_SIZE: usize = 2066;
+
+/// Decoding table of the library (`fsst_decoder_t`)
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct FsstDecoder {
+    version: u64,
+    zero_terminated: u8,
+    len: [u8; 255],
+    symbol: [u64; 255],
+}
+
+/// Encoder of the library (`fsst_encoder_t`), opaque
+#[repr(C)]
+struct FsstEncoder {
+    _private: [u8; 0],
+}
+
+#[link(name = "fsst")]
+extern "C" {
+    fn fsst_create(n: usize, len_in: *const usize, str_in: *const *const u8, zero_terminated: i32) -> *mut FsstEncoder;
+    fn fsst_compress(
+        encoder: *mut FsstEncoder,
+        n_strings: usize,
+        len_in: *const usize,
+        str_in: *const *const u8,
+        output_size: usize,
+        output: *mut u8,
+        len_out: *mut usize,
+        str_out: *mut *mut u8,
+    ) -> usize;
+    fn fsst_export(encoder: *mut FsstEncoder, buffer: *mut u8) -> u32;
+    fn fsst_decoder(encoder: *mut FsstEncoder) -> FsstDecoder;
+    fn fsst_destroy(encoder: *mut FsstEncoder);
+}
+
+extern "C" {
+    // Exported by fsst_shim.cpp
+    fn fsst_shim_decompress(decoder: *const FsstDecoder, len_in: usize, str_in: *const u8, size: usize, output: *mut u8) -> usize;
+}
+
+/// FSST compressor backed by the reference library
+pub struct FSSTFfiCompressor {
+    decoder: FsstDecoder,
+    table_size: usize,                  // Size of the table serialized by `fsst_export`
+    compressed_data: Vec<u8>,           // Codes of all strings, concatenated
+    item_end_positions: Vec<usize>,     // Boundaries of the strings in `compressed_data`, starting with 0
+}
+
+impl Compressor for FSSTFfiCompressor {
+    fn new(_data_size: usize, n_elements: usize) -> Self {
+        FSSTFfiCompressor {
+            decoder: FsstDecoder { version: 0, zero_terminated: 0, len: [0; 255], symbol: [0; 255] },
+            table_size: 0,
+            compressed_data: Vec::new(),
+            item_end_positions: Vec::with_capacity(n_elements + 1),
+        }
+    }
+
+    fn compress(&mut self, data: &[u8], end_positions: &[usize]) {
+        self.item_end_positions.push(0);
+        let n_items = end_positions.len() - 1;
+        if n_items == 0 {
+            return;
+        }
+
+        let lengths: Vec<usize> = end_positions.windows(2).map(|w| w[1] - w[0]).collect();
+        let strings: Vec<*const u8> = end_positions[..n_items].iter().map(|&start| data[start..].as_ptr()).collect();
+        // Worst case of the library: every byte escaped, plus 7 bytes of slack
+        self.compressed_data = vec![0; 2 * data.len() + 7];
+        let mut output_lengths = vec![0usize; n_items];
+        let mut output_strings = vec![std::ptr::null_mut::<u8>(); n_items];
+
+        let mut table = [0u8; MAX_HEADER_SIZE];
+        let n_compressed = unsafe {
+            let encoder = fsst_create(n_items, lengths.as_ptr(), strings.as_ptr(), 0);
+            let n_compressed = fsst_compress(
+                encoder,
+                n_items,
+                lengths.as_ptr(),
+                strings.as_ptr(),
+                self.compressed_data.len(),
+                self.compressed_data.as_mut_ptr(),
+                output_lengths.as_mut_ptr(),
+                output_strings.as_mut_ptr(),
+            );
+            self.table_size = fsst_export(encoder, table.as_mut_ptr()) as usize;
+            self.decoder = fsst_decoder(encoder);
+            fsst_destroy(encoder);
+            n_compressed
+        };
+        assert_eq!(n_compressed, n_items, "FSST output buffer too small");
+
+        // Strings are written one after the other, from the start of the buffer
+        let base = self.compressed_data.as_ptr() as usize;
+        for (&string, &length) in output_strings.iter().zip(output_lengths.iter()) {
+            self.item_end_positions.push(string as usize - base + length);
+        }
+        self.compressed_data.truncate(*self.item_end_positions.last().unwrap());
+        self.compressed_data.shrink_to_fit();
+    }
+
+    fn decompress(&self, buffer: &mut [u8]) -> usize {
+        let mut size = 0;
+        for index in 0..self.item_end_positions.len() - 1 {
+            size += self.decode(index, &mut buffer[size..]);
+        }
+        size
+    }
+
+    fn decompress_to<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let mut stream = StreamBuffer::new(writer);
+        for index in 0..self.item_end_positions.len() - 1 {
+            let n_codes = self.item_end_positions[index + 1] - self.item_end_positions[index];
+            let size = self.decode(index, stream.reserve(n_codes * MAX_SYMBOL_LENGTH)?);
+            stream.commit(size);
+        }
+        stream.finish()
+    }
+
+    #[inline(always)]
+    fn get_item_at(&mut self, index: usize, buffer: &mut [u8]) -> usize {
+        self.decode(index, buffer)
+    }
+
+    fn get_item_at_profiled(&mut self, index: usize, buffer: &mut [u8], timer: &mut StageTimer) -> usize {
+        let start = self.item_end_positions[index];
+        let end = self.item_end_positions[index + 1];
+        timer.lap(Stage::Lookup);
+
+        // The library copies symbols to the buffer as it decodes them
+        let size = unsafe {
+            fsst_shim_decompress(&self.decoder, end - start, self.compressed_data[start..].as_ptr(), buffer.len(), buffer.as_mut_ptr())
+        };
+        timer.lap(Stage::Decode);
+        size
+    }
+
+    fn len_of_item(&self, index: usize) -> usize {
+        let codes = &self.compressed_data[self.item_end_positions[index]..self.item_end_positions[index + 1]];
+        decoded_length(&self.decoder.len, codes)
+    }
+
+    fn max_item_len(&self) -> usize {
+        max_len_of_items(self, self.item_end_positions.len().saturating_sub(1))
+    }
+
+    fn space_used_bytes(&self) -> usize {
+        self.space_breakdown().total()
+    }
+
+    fn space_breakdown(&self) -> SpaceBreakdown {
+        SpaceBreakdown {
+            payload: self.compressed_data.len(),
+            dictionary: self.table_size,
+            item_offsets: self.item_end_positions.len() * std::mem::size_of::<usize>(),
+            ..SpaceBreakdown::default()
+        }
+    }
+
+    fn name(&self) -> &str {
+        "FSST (FFI)"
+    }
+}
+
+impl FSSTFfiCompressor {
+    /// Decodes a string to `buffer` through the library
+    ///
+    /// # Returns
+    /// Number of bytes decoded
+    #[inline(always)]
+    fn decode(&self, index: usize, buffer: &mut [u8]) -> usize {
+        let start = self.item_end_positions[index];
+        let end = self.item_end_positions[index + 1];
+        // The library bounds its writes by the size of the buffer
+        unsafe { fsst_shim_decompress(&self.decoder, end - start, self.compressed_data[start..].as_ptr(), buffer.len(), buffer.as_mut_ptr()) }
+    }
+}
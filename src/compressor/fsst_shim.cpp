@@ -0,0 +1,9 @@
+// Exports the decoder of the reference FSST library (see fsst_ffi.rs)
+//
+// fsst_decompress is defined inline in fsst.h, so that libfsst has no symbol for it.
+
+#include "fsst.h"
+
+extern "C" size_t fsst_shim_decompress(const fsst_decoder_t *decoder, size_t len_in, const unsigned char *str_in, size_t size, unsigned char *output) {
+    return fsst_decompress(decoder, len_in, str_in, size, output);
+}
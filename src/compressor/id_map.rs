@@ -0,0 +1,354 @@
+//! Stable external IDs for reordered string collections
+//!
+//! Strings may be stored in an order different from the input one (e.g., sorted to
+//! cluster similar strings), or duplicates may be stored once. `IdMap` resolves the
+//! external ID of a string (its input position) to its internal ID (its storage
+//! position), and `RemappedCompressor` applies it on top of any compressor, so that
+//! random access measurements include the cost of remapping.
+
+use crate::bit_vector::BitVector;
+use super::{AccessScratch, BlockPosition, Capacity, Compressor, SpaceBreakdown, StreamBuffer};
+use crate::profiler::{Stage, StageTimer};
+use std::io::{self, Write};
+
+/// Padding required by compressors writing past the end of the output buffer
+const DECOMPRESSION_PADDING: usize = 1024;
+
+/// Mapping from external IDs to internal IDs
+///
+/// Internal IDs are bit-packed with the minimum fixed width able to represent the
+/// largest one. Several external IDs may map to the same internal ID.
+#[derive(Default)]
+pub struct IdMap {
+    ids: BitVector,     // Internal ID of each external ID, `width` bits each
+    width: usize,       // Bits per internal ID
+    len: usize,         // Number of external IDs
+}
+
+impl IdMap {
+    /// Creates a mapping from the internal ID of each external ID
+    ///
+    /// # Arguments
+    /// - `internal_ids`: Internal ID of each external ID, indexed by external ID
+    pub fn new(internal_ids: &[usize]) -> Self {
+        let max_id = internal_ids.iter().copied().max().unwrap_or(0);
+        let width = ((usize::BITS - max_id.leading_zeros()) as usize).max(1);
+
+        let mut ids = BitVector::with_capacity(internal_ids.len() * width);
+        for &id in internal_ids {
+            ids.append_bits(id as u64, width);
+        }
+
+        IdMap { ids, width, len: internal_ids.len() }
+    }
+
+    /// Returns the internal ID of an external ID
+    #[inline(always)]
+    pub fn get(&self, external_id: usize) -> usize {
+        assert!(external_id < self.len, "External ID out of bounds");
+        unsafe { self.ids.get_bits_unchecked(external_id * self.width, self.width) as usize }
+    }
+
+    /// Returns the number of external IDs
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the mapping is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of bits per internal ID
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Reports the memory used by the mapping in bytes
+    pub fn space_used_bytes(&self) -> usize {
+        self.ids.len() / 8
+    }
+}
+
+/// Compressor storing strings in an order different from the input one
+///
+/// Each stored string is identified by its internal ID, and accesses go through an
+/// `IdMap`. Full decompression restores the input order using the bit-packed lengths
+/// of the stored strings. Wrappers decide which strings are stored and in which order.
+pub struct RemappedCompressor<T: Compressor> {
+    inner: T,
+    id_map: IdMap,
+    lengths: BitVector,     // Length of each stored string (internal order), `length_width` bits each
+    length_width: usize,
+    stored_size: usize,     // Total size of the stored strings
+    access_scratch: AccessScratch,  // Internal IDs of a batch, reused across batches
+}
+
+impl<T: Compressor> RemappedCompressor<T> {
+    /// Creates a remapped compressor
+    ///
+    /// The inner compressor is created again at compression time, sized for the
+    /// stored strings only.
+    ///
+    /// # Arguments
+    /// - `data_size`: Total size of input data in bytes
+    /// - `n_elements`: Number of individual strings in the dataset
+    pub fn new(data_size: usize, n_elements: usize) -> Self {
+        RemappedCompressor {
+            inner: T::new(data_size, n_elements),
+            id_map: IdMap::default(),
+            lengths: BitVector::new(),
+            length_width: 0,
+            stored_size: 0,
+            access_scratch: AccessScratch::default(),
+        }
+    }
+
+    /// Compresses a subset of the input strings in the given order
+    ///
+    /// # Arguments
+    /// - `data`: Concatenated input strings
+    /// - `end_positions`: Boundary positions of the input strings
+    /// - `stored`: External ID of each stored string, in internal order
+    /// - `internal_ids`: Internal ID of each external ID
+    pub fn compress_remapped(&mut self, data: &[u8], end_positions: &[usize], stored: &[usize], internal_ids: &[usize]) {
+        let item = |i: usize| &data[end_positions[i]..end_positions[i + 1]];
+
+        let max_length = stored.iter().map(|&i| item(i).len()).max().unwrap_or(0);
+        self.length_width = ((usize::BITS - max_length.leading_zeros()) as usize).max(1);
+        self.lengths = BitVector::with_capacity(stored.len() * self.length_width);
+
+        let mut stored_data = Vec::with_capacity(data.len());
+        let mut stored_end_positions = Vec::with_capacity(stored.len() + 1);
+        stored_end_positions.push(0);
+        for &external_id in stored {
+            stored_data.extend_from_slice(item(external_id));
+            stored_end_positions.push(stored_data.len());
+            self.lengths.append_bits(item(external_id).len() as u64, self.length_width);
+        }
+
+        // Size the inner compressor for the stored strings only
+        self.inner = T::new(stored_data.len(), stored.len());
+        self.inner.compress(&stored_data, &stored_end_positions);
+        self.id_map = IdMap::new(internal_ids);
+        self.stored_size = stored_data.len();
+    }
+
+    /// Decompresses all strings in input order (see `Compressor::decompress`)
+    pub fn decompress(&self, buffer: &mut [u8]) -> usize {
+        let mut stored_data = vec![0u8; self.stored_size + DECOMPRESSION_PADDING];
+        self.inner.decompress(&mut stored_data);
+
+        // Start position of each stored string
+        let n_stored = self.lengths.len() / self.length_width.max(1);
+        let mut start_positions = Vec::with_capacity(n_stored + 1);
+        let mut position = 0;
+        for internal_id in 0..n_stored {
+            start_positions.push(position);
+            position += self.length_of(internal_id);
+        }
+
+        let mut size = 0;
+        for external_id in 0..self.id_map.len() {
+            let internal_id = self.id_map.get(external_id);
+            let start = start_positions[internal_id];
+            let length = self.length_of(internal_id);
+            buffer[size..size + length].copy_from_slice(&stored_data[start..start + length]);
+            size += length;
+        }
+        size
+    }
+
+    /// Writes all strings in input order to a writer (see `Compressor::decompress_to`)
+    ///
+    /// Strings are reordered from the stored ones, which are decompressed in memory
+    /// first: memory is bounded by the size of the stored strings rather than by
+    /// the chunk size.
+    pub fn decompress_to<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let mut stored_data = vec![0u8; self.stored_size + DECOMPRESSION_PADDING];
+        self.inner.decompress(&mut stored_data);
+
+        // Start position of each stored string
+        let n_stored = self.lengths.len() / self.length_width.max(1);
+        let mut start_positions = Vec::with_capacity(n_stored);
+        let mut position = 0;
+        for internal_id in 0..n_stored {
+            start_positions.push(position);
+            position += self.length_of(internal_id);
+        }
+
+        let mut stream = StreamBuffer::new(writer);
+        for external_id in 0..self.id_map.len() {
+            let internal_id = self.id_map.get(external_id);
+            let start = start_positions[internal_id];
+            let length = self.length_of(internal_id);
+            stream.reserve(length)?[..length].copy_from_slice(&stored_data[start..start + length]);
+            stream.commit(length);
+        }
+        stream.finish()
+    }
+
+    /// Retrieves a string by external ID (see `Compressor::get_item_at`)
+    #[inline(always)]
+    pub fn get_item_at(&mut self, index: usize, buffer: &mut [u8]) -> usize {
+        let internal_id = self.id_map.get(index);
+        self.inner.get_item_at(internal_id, buffer)
+    }
+
+    /// Retrieves a string by external ID, charging the ID mapping to lookup (see `Compressor::get_item_at_profiled`)
+    pub fn get_item_at_profiled(&mut self, index: usize, buffer: &mut [u8], timer: &mut StageTimer) -> usize {
+        let internal_id = self.id_map.get(index);
+        timer.lap(Stage::Lookup);
+        self.inner.get_item_at_profiled(internal_id, buffer, timer)
+    }
+
+    /// Retrieves a string by external ID without copying it (see `Compressor::get_item_ref`)
+    #[inline(always)]
+    pub fn get_item_ref(&mut self, index: usize) -> Option<&[u8]> {
+        let internal_id = self.id_map.get(index);
+        self.inner.get_item_ref(internal_id)
+    }
+
+    /// Retrieves a batch of strings by external ID (see `Compressor::get_items_at`)
+    pub fn get_items_at(&mut self, indices: &[usize], buffer: &mut [u8], item_ends: &mut [usize]) -> usize {
+        let mut internal_ids = std::mem::take(&mut self.access_scratch.ids);
+        internal_ids.clear();
+        internal_ids.extend(indices.iter().map(|&index| self.id_map.get(index)));
+        let size = self.inner.get_items_at(&internal_ids, buffer, item_ends);
+        self.access_scratch.ids = internal_ids;
+        size
+    }
+
+    /// Reports the length of a string by external ID, from the stored lengths
+    pub fn len_of_item(&self, index: usize) -> usize {
+        self.length_of(self.id_map.get(index))
+    }
+
+    /// Reports the length of the longest stored string (see `Compressor::max_item_len`)
+    pub fn max_item_len(&self) -> usize {
+        self.inner.max_item_len()
+    }
+
+    /// Reports the position of a string within the inner compressor's block, by external ID
+    pub fn block_position(&self, index: usize) -> Option<BlockPosition> {
+        self.inner.block_position(self.id_map.get(index))
+    }
+
+    /// Reports the limits of the inner compressor (see `Compressor::capacity`)
+    pub fn capacity(&self) -> Capacity {
+        self.inner.capacity()
+    }
+
+    /// Reports total memory usage, including the ID map and the stored lengths
+    pub fn space_used_bytes(&self) -> usize {
+        self.inner.space_used_bytes() + self.id_map.space_used_bytes() + self.lengths.len() / 8
+    }
+
+    /// Reports memory usage split by category; stored lengths count as item offsets
+    pub fn space_breakdown(&self) -> SpaceBreakdown {
+        let inner = self.inner.space_breakdown();
+        SpaceBreakdown {
+            item_offsets: inner.item_offsets + self.lengths.len() / 8,
+            id_map: inner.id_map + self.id_map.space_used_bytes(),
+            ..inner
+        }
+    }
+
+    /// Returns the name of the inner compressor
+    pub fn inner_name(&self) -> &str {
+        self.inner.name()
+    }
+
+    /// Returns the length of a stored string
+    #[inline(always)]
+    fn length_of(&self, internal_id: usize) -> usize {
+        unsafe { self.lengths.get_bits_unchecked(internal_id * self.length_width, self.length_width) as usize }
+    }
+}
+
+/// Wrapper storing strings in lexicographic order
+///
+/// Sorting clusters strings sharing prefixes, which may benefit dictionary-based
+/// compressors, at the cost of an ID map lookup per access.
+pub struct SortedCompressor<T: Compressor> {
+    remapped: RemappedCompressor<T>,
+    name: String,
+}
+
+impl<T: Compressor> Compressor for SortedCompressor<T> {
+    fn new(data_size: usize, n_elements: usize) -> Self {
+        let remapped = RemappedCompressor::new(data_size, n_elements);
+        let name = format!("{} (sorted)", remapped.inner_name());
+        SortedCompressor { remapped, name }
+    }
+
+    fn compress(&mut self, data: &[u8], end_positions: &[usize]) {
+        let n_elements = end_positions.len() - 1;
+        let item = |i: usize| &data[end_positions[i]..end_positions[i + 1]];
+
+        // Stable sort keeps duplicates in input order
+        let mut order: Vec<usize> = (0..n_elements).collect();
+        order.sort_by(|&a, &b| item(a).cmp(item(b)));
+
+        let mut internal_ids = vec![0; n_elements];
+        for (internal_id, &external_id) in order.iter().enumerate() {
+            internal_ids[external_id] = internal_id;
+        }
+
+        self.remapped.compress_remapped(data, end_positions, &order, &internal_ids);
+    }
+
+    fn decompress(&self, buffer: &mut [u8]) -> usize {
+        self.remapped.decompress(buffer)
+    }
+
+    fn decompress_to<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        self.remapped.decompress_to(writer)
+    }
+
+    #[inline(always)]
+    fn get_item_at(&mut self, index: usize, buffer: &mut [u8]) -> usize {
+        self.remapped.get_item_at(index, buffer)
+    }
+
+    fn get_item_at_profiled(&mut self, index: usize, buffer: &mut [u8], timer: &mut StageTimer) -> usize {
+        self.remapped.get_item_at_profiled(index, buffer, timer)
+    }
+
+    #[inline(always)]
+    fn get_item_ref(&mut self, index: usize) -> Option<&[u8]> {
+        self.remapped.get_item_ref(index)
+    }
+
+    fn get_items_at(&mut self, indices: &[usize], buffer: &mut [u8], item_ends: &mut [usize]) -> usize {
+        self.remapped.get_items_at(indices, buffer, item_ends)
+    }
+
+    fn len_of_item(&self, index: usize) -> usize {
+        self.remapped.len_of_item(index)
+    }
+
+    fn max_item_len(&self) -> usize {
+        self.remapped.max_item_len()
+    }
+
+    fn block_position(&self, index: usize) -> Option<BlockPosition> {
+        self.remapped.block_position(index)
+    }
+
+    fn capacity(&self) -> Capacity {
+        self.remapped.capacity()
+    }
+
+    fn space_used_bytes(&self) -> usize {
+        self.remapped.space_used_bytes()
+    }
+
+    fn space_breakdown(&self) -> SpaceBreakdown {
+        self.remapped.space_breakdown()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
@@ -0,0 +1,92 @@
+//! Incremental compression with yield points
+//!
+//! `Compressor::compress` blocks its thread until the whole dataset is compressed,
+//! for minutes on large datasets. `Compressor::compress_steps` splits the same work
+//! into units run one at a time by `CompressionSteps::step`, so that callers can
+//! interleave compression with other work (e.g., a service answering requests on the
+//! same thread, or an async task yielding to its executor between units) and stop a
+//! compression exceeding a deadline without killing its process.
+//!
+//! Once a unit reports `Progress::Done`, the compressed representation is identical
+//! to the one built by `compress`. A compression stopped before leaves the compressor
+//! in an unspecified state: it must be compressed again, or dropped.
+//!
+//! Units are only as fine as each compressor allows. By default, the whole compression
+//! is a single unit; block compressors compress a bounded number of blocks per unit,
+//! and OnPairBV trains its dictionary in one unit (the longest one), then parses a
+//! bounded number of strings per unit.
+
+/// Outcome of a unit of work of an incremental compression
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Progress {
+    Continue,   // Units of work remain
+    Done,       // The dataset is compressed
+}
+
+/// Remaining units of work of an incremental compression (see `Compressor::compress_steps`)
+///
+/// Also iterates over the units, running one unit per item and yielding the number
+/// of units run so far, until the compression is done.
+pub struct CompressionSteps<'a> {
+    step: Box<dyn FnMut() -> Progress + 'a>,    // Runs the next unit of work
+    n_steps: usize,                             // Units of work run so far
+    done: bool,                                 // Whether the last unit has run
+}
+
+impl<'a> CompressionSteps<'a> {
+    /// Creates an incremental compression from its units of work
+    ///
+    /// # Arguments
+    /// - `step`: Runs the next unit of work, reporting whether units remain; never
+    ///   called again once it reports `Progress::Done`
+    pub fn new(step: impl FnMut() -> Progress + 'a) -> Self {
+        CompressionSteps { step: Box::new(step), n_steps: 0, done: false }
+    }
+
+    /// Creates an incremental compression running the whole compression as a single unit
+    pub fn single(compress: impl FnOnce() + 'a) -> Self {
+        let mut compress = Some(compress);
+        Self::new(move || {
+            if let Some(compress) = compress.take() {
+                compress();
+            }
+            Progress::Done
+        })
+    }
+
+    /// Runs the next unit of work
+    ///
+    /// # Returns
+    /// Whether units of work remain (`Progress::Done` without running anything once done)
+    pub fn step(&mut self) -> Progress {
+        if self.done {
+            return Progress::Done;
+        }
+        let progress = (self.step)();
+        self.n_steps += 1;
+        self.done = progress == Progress::Done;
+        progress
+    }
+
+    /// Returns the number of units of work run so far
+    pub fn n_steps(&self) -> usize {
+        self.n_steps
+    }
+
+    /// Runs the remaining units of work
+    pub fn finish(mut self) {
+        while self.step() == Progress::Continue {}
+    }
+}
+
+impl Iterator for CompressionSteps<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.done {
+            return None;
+        }
+        self.step();
+        Some(self.n_steps)
+    }
+}
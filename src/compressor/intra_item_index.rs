@@ -0,0 +1,91 @@
+//! Sampled token positions within long strings
+//!
+//! Token-stream compressors locate a byte of a string by summing the lengths of the
+//! tokens preceding it, from the start of the string: range reads deep into long
+//! strings (e.g., documents) pay for the whole prefix. `IntraItemIndex` stores, for
+//! every string longer than the sample interval `m`, the token holding every `m`-th
+//! decoded byte, with the offset of its first byte: a range read starts from the
+//! closest sample before it, and sums at most the lengths of the tokens spanning `m`
+//! bytes. Strings no longer than `m` get no sample, and cost nothing.
+
+use super::token_offsets::TokenOffsets;
+
+/// Sampled token positions of the strings longer than the sample interval
+pub struct IntraItemIndex {
+    sample_interval: usize,         // Decoded bytes between samples
+    items: Vec<usize>,              // Indices of the sampled strings, in increasing order
+    first_samples: Vec<usize>,      // Position of the first sample of each sampled string in the sample arrays, then the total
+    token_positions: TokenOffsets,  // Position of the sampled token within its string, widened past 4 GiB
+    token_offsets: TokenOffsets,    // Offset of the first byte of the sampled token within its string
+}
+
+impl IntraItemIndex {
+    /// Samples the token positions of all strings
+    ///
+    /// # Arguments
+    /// - `sample_interval`: Decoded bytes between samples, at least 1
+    /// - `item_token_lengths`: Lengths of the tokens of each string, in string order
+    pub fn build<I, T>(sample_interval: usize, item_token_lengths: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: IntoIterator<Item = usize>,
+    {
+        assert!(sample_interval >= 1, "sample_interval must be at least 1");
+        let mut items = Vec::new();
+        let mut first_samples = vec![0];
+        let mut token_positions = TokenOffsets::default();
+        let mut token_offsets = TokenOffsets::default();
+
+        for (index, token_lengths) in item_token_lengths.into_iter().enumerate() {
+            let n_samples = token_positions.len();
+            let mut offset = 0;
+            for (position, length) in token_lengths.into_iter().enumerate() {
+                // The token holds every sampled byte from its first one to its last one
+                let mut next_sample = (token_positions.len() - n_samples + 1) * sample_interval;
+                while next_sample < offset + length {
+                    token_positions.push(position);
+                    token_offsets.push(offset);
+                    next_sample += sample_interval;
+                }
+                offset += length;
+            }
+            if token_positions.len() > n_samples {
+                items.push(index);
+                first_samples.push(token_positions.len());
+            }
+        }
+
+        IntraItemIndex { sample_interval, items, first_samples, token_positions, token_offsets }
+    }
+
+    /// Returns the closest sample at or before a byte of a string
+    ///
+    /// # Arguments
+    /// - `index`: Zero-based index of the string
+    /// - `offset`: Byte offset within the string
+    ///
+    /// # Returns
+    /// Position of the sampled token within the string and offset of its first byte,
+    /// (0, 0) if no sample precedes the byte
+    #[inline(always)]
+    pub fn seek(&self, index: usize, offset: usize) -> (usize, usize) {
+        let sample = offset / self.sample_interval;
+        if sample == 0 {
+            return (0, 0);
+        }
+        let Ok(item) = self.items.binary_search(&index) else {
+            return (0, 0);
+        };
+        // Samples past the end of the string only follow out-of-range offsets
+        let position = (self.first_samples[item] + sample - 1).min(self.first_samples[item + 1] - 1);
+        (self.token_positions.get(position), self.token_offsets.get(position))
+    }
+
+    /// Returns the space used by the index in bytes
+    pub fn size_bytes(&self) -> usize {
+        std::mem::size_of_val(self.items.as_slice())
+            + std::mem::size_of_val(self.first_samples.as_slice())
+            + self.token_positions.size_bytes()
+            + self.token_offsets.size_bytes()
+    }
+}
@@ -0,0 +1,428 @@
+//! Compact string boundaries with universal codes
+//!
+//! Compressors locate strings by their end positions, usually stored as one machine
+//! word per string. `LengthIndex` stores the string lengths instead, each encoded with
+//! a universal code, together with the absolute end position of every `sample_rate`-th
+//! string: a boundary is recovered by decoding the lengths following the closest
+//! anchor. Length distributions differ widely across datasets (short identifiers, long
+//! log lines, heavy tails), so the index computes the exact size of every supported
+//! code on the dataset and keeps the smallest one, then decodes the encoded lengths
+//! back to validate the choice.
+
+use crate::bit_vector::{BitReader, BitVector};
+use serde::{Serialize, Deserialize};
+use tracing::{debug, info};
+
+/// Default distance between sampled absolute end positions
+pub const DEFAULT_SAMPLE_RATE: usize = 64;
+
+/// Fibonacci numbers 1, 2, 3, 5, ... below 2^64, the weights of Fibonacci codewords
+const FIBONACCI: [u64; 92] = fibonacci_numbers();
+
+const fn fibonacci_numbers() -> [u64; 92] {
+    let mut numbers = [0u64; 92];
+    numbers[0] = 1;
+    numbers[1] = 2;
+    let mut i = 2;
+    while i < numbers.len() {
+        numbers[i] = numbers[i - 1] + numbers[i - 2];
+        i += 1;
+    }
+    numbers
+}
+
+/// Universal code of string lengths
+///
+/// Codes apply to the length plus one, since they cannot represent 0.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum LengthCode {
+    Gamma,      // Elias gamma: unary bit length, then the bits below the leading one
+    Delta,      // Elias delta: gamma-coded bit length, then the bits below the leading one
+    Fibonacci,  // Zeckendorf representation, terminated by two consecutive ones
+    VByte,      // 7 bits per byte, with a continuation bit
+}
+
+impl LengthCode {
+    /// All supported codes, in order of preference on ties
+    pub const ALL: [LengthCode; 4] = [LengthCode::VByte, LengthCode::Gamma, LengthCode::Delta, LengthCode::Fibonacci];
+
+    /// Returns the code identifier
+    pub fn name(&self) -> &'static str {
+        match self {
+            LengthCode::Gamma => "gamma",
+            LengthCode::Delta => "delta",
+            LengthCode::Fibonacci => "fibonacci",
+            LengthCode::VByte => "vbyte",
+        }
+    }
+
+    /// Returns the number of bits of the codeword of a positive value
+    pub fn encoded_bits(&self, value: u64) -> usize {
+        debug_assert!(value > 0);
+        let n_bits = bit_length(value);
+        match self {
+            LengthCode::Gamma => 2 * n_bits - 1,
+            LengthCode::Delta => n_bits - 1 + LengthCode::Gamma.encoded_bits(n_bits as u64),
+            LengthCode::Fibonacci => FIBONACCI.partition_point(|&weight| weight <= value) + 1,
+            LengthCode::VByte => 8 * n_bits.div_ceil(7),
+        }
+    }
+
+    /// Appends the codeword of a positive value
+    fn encode(&self, bits: &mut BitVector, value: u64) {
+        debug_assert!(value > 0);
+        let n_bits = bit_length(value);
+        match self {
+            LengthCode::Gamma => {
+                bits.extend_with_zeroes(n_bits - 1);
+                bits.push(true);
+                bits.append_bits(value & low_mask(n_bits - 1), n_bits - 1);
+            }
+            LengthCode::Delta => {
+                LengthCode::Gamma.encode(bits, n_bits as u64);
+                bits.append_bits(value & low_mask(n_bits - 1), n_bits - 1);
+            }
+            LengthCode::Fibonacci => {
+                // Greedy Zeckendorf representation, least significant weight first
+                let n_weights = FIBONACCI.partition_point(|&weight| weight <= value);
+                let mut codeword = vec![false; n_weights];
+                let mut remainder = value;
+                for i in (0..n_weights).rev() {
+                    if FIBONACCI[i] <= remainder {
+                        codeword[i] = true;
+                        remainder -= FIBONACCI[i];
+                    }
+                }
+                for bit in codeword {
+                    bits.push(bit);
+                }
+                bits.push(true);
+            }
+            LengthCode::VByte => {
+                let mut remainder = value;
+                while remainder >= 0x80 {
+                    bits.append_bits((remainder & 0x7f) | 0x80, 8);
+                    remainder >>= 7;
+                }
+                bits.append_bits(remainder, 8);
+            }
+        }
+    }
+
+    /// Reads the next codeword
+    #[inline(always)]
+    fn decode(&self, reader: &mut BitReader) -> u64 {
+        match self {
+            LengthCode::Gamma => {
+                let mut n_bits = 1;
+                while reader.read(1).unwrap() == 0 {
+                    n_bits += 1;
+                }
+                (1 << (n_bits - 1)) | reader.read(n_bits - 1).unwrap()
+            }
+            LengthCode::Delta => {
+                let n_bits = LengthCode::Gamma.decode(reader) as usize;
+                (1 << (n_bits - 1)) | reader.read(n_bits - 1).unwrap()
+            }
+            LengthCode::Fibonacci => {
+                let mut value = 0;
+                let mut previous = false;
+                for &weight in FIBONACCI.iter() {
+                    let bit = reader.read(1).unwrap() == 1;
+                    if bit && previous {
+                        return value;
+                    }
+                    if bit {
+                        value += weight;
+                    }
+                    previous = bit;
+                }
+                // The terminating one follows the largest weight
+                assert!(reader.read(1).unwrap() == 1, "Corrupted Fibonacci codeword");
+                value
+            }
+            LengthCode::VByte => {
+                let mut value = 0;
+                let mut shift = 0;
+                loop {
+                    let byte = reader.read(8).unwrap();
+                    value |= (byte & 0x7f) << shift;
+                    if byte < 0x80 {
+                        return value;
+                    }
+                    shift += 7;
+                }
+            }
+        }
+    }
+}
+
+/// String boundaries stored as encoded lengths with sampled absolute positions
+pub struct LengthIndex {
+    code: LengthCode,
+    sample_rate: usize,
+    bits: BitVector,            // Codeword of each length plus one
+    anchors: Vec<usize>,        // End position before every `sample_rate`-th string, plus the total size
+    anchor_offsets: Vec<usize>, // Position in `bits` of the codeword of every `sample_rate`-th string
+    n_items: usize,
+}
+
+impl LengthIndex {
+    /// Encodes the boundaries of a collection with the smallest code
+    ///
+    /// # Arguments
+    /// - `end_positions`: Boundary positions of the strings, starting with 0
+    pub fn new(end_positions: &[usize]) -> Self {
+        Self::with_sample_rate(end_positions, DEFAULT_SAMPLE_RATE)
+    }
+
+    /// Encodes the boundaries with a custom sampling (see `new`)
+    ///
+    /// # Arguments
+    /// - `end_positions`: Boundary positions of the strings, starting with 0
+    /// - `sample_rate`: Distance between sampled absolute end positions: larger rates save
+    ///   space, at the cost of decoding up to `sample_rate - 1` more lengths per access
+    pub fn with_sample_rate(end_positions: &[usize], sample_rate: usize) -> Self {
+        let sizes: Vec<(LengthCode, usize)> = LengthCode::ALL
+            .iter()
+            .map(|&code| (code, Self::lengths(end_positions).map(|value| code.encoded_bits(value)).sum()))
+            .collect();
+        let &(code, _) = sizes.iter().min_by_key(|&&(_, size)| size).unwrap();
+        debug!(?sizes, chosen = code.name(), "Length code selected");
+
+        let index = Self::with_code(end_positions, code, sample_rate);
+        index.validate(end_positions);
+        index
+    }
+
+    /// Encodes the boundaries with the given code (see `with_sample_rate`)
+    pub fn with_code(end_positions: &[usize], code: LengthCode, sample_rate: usize) -> Self {
+        assert!(sample_rate >= 1, "sample_rate must be at least 1");
+        let n_items = end_positions.len().saturating_sub(1);
+
+        let mut bits = BitVector::new();
+        let mut anchors = Vec::with_capacity(n_items / sample_rate + 2);
+        let mut anchor_offsets = Vec::with_capacity(n_items / sample_rate + 1);
+        for (index, value) in Self::lengths(end_positions).enumerate() {
+            if index.is_multiple_of(sample_rate) {
+                anchors.push(end_positions[index]);
+                anchor_offsets.push(bits.len());
+            }
+            code.encode(&mut bits, value);
+        }
+        anchors.push(end_positions.last().copied().unwrap_or(0));
+        bits.shrink_to_fit();
+
+        LengthIndex { code, sample_rate, bits, anchors, anchor_offsets, n_items }
+    }
+
+    /// Returns the values encoded for the strings, their lengths plus one
+    fn lengths(end_positions: &[usize]) -> impl Iterator<Item = u64> + '_ {
+        end_positions.windows(2).map(|w| (w[1] - w[0]) as u64 + 1)
+    }
+
+    /// Checks that every boundary decodes to its original value, panicking otherwise
+    fn validate(&self, end_positions: &[usize]) {
+        let mut reader = self.bits.reader(0);
+        let mut end = end_positions.first().copied().unwrap_or(0);
+        for (index, &expected) in end_positions.iter().enumerate().skip(1) {
+            end += self.read_length(&mut reader);
+            if end != expected {
+                panic!("Length index with {} code decodes boundary {} as {} instead of {}", self.code.name(), index, end, expected);
+            }
+        }
+    }
+
+    /// Reads the length of the next string
+    ///
+    /// Subtracts one before converting, so that boundaries up to `usize::MAX` do not overflow.
+    #[inline(always)]
+    fn read_length(&self, reader: &mut BitReader) -> usize {
+        (self.code.decode(reader) - 1) as usize
+    }
+
+    /// Returns the start and end positions of a string
+    #[inline]
+    pub fn bounds(&self, index: usize) -> (usize, usize) {
+        debug_assert!(index < self.n_items);
+        let anchor = index / self.sample_rate;
+        let mut reader = self.bits.reader(self.anchor_offsets[anchor]);
+        let mut start = self.anchors[anchor];
+        for _ in anchor * self.sample_rate..index {
+            start += self.read_length(&mut reader);
+        }
+        (start, start + self.read_length(&mut reader))
+    }
+
+    /// Returns the length of a string
+    #[inline]
+    pub fn len_of(&self, index: usize) -> usize {
+        let (start, end) = self.bounds(index);
+        end - start
+    }
+
+    /// Returns the index of the string holding a position, below the total size
+    ///
+    /// Binary searches the anchors, then decodes lengths from the closest one. Empty
+    /// strings hold no position and are skipped.
+    pub fn find(&self, position: usize) -> usize {
+        debug_assert!(position < *self.anchors.last().unwrap());
+        let anchor = self.anchors[..self.anchor_offsets.len()].partition_point(|&start| start <= position) - 1;
+        let mut reader = self.bits.reader(self.anchor_offsets[anchor]);
+        let mut index = anchor * self.sample_rate;
+        let mut end = self.anchors[anchor];
+        loop {
+            end += self.read_length(&mut reader);
+            if position < end {
+                return index;
+            }
+            index += 1;
+        }
+    }
+
+    /// Returns the string holding a position and the offset within it, or None past the end
+    pub fn offset_to_item(&self, position: usize) -> Option<(usize, usize)> {
+        if position >= *self.anchors.last().unwrap() {
+            return None;
+        }
+        let index = self.find(position);
+        Some((index, position - self.bounds(index).0))
+    }
+
+    /// Decodes all boundaries in order
+    ///
+    /// # Returns
+    /// Boundary positions of the strings, starting with 0
+    pub fn end_positions(&self) -> Vec<usize> {
+        let mut end_positions = Vec::with_capacity(self.n_items + 1);
+        let mut reader = self.bits.reader(0);
+        let mut end = self.anchors[0];
+        end_positions.push(end);
+        for _ in 0..self.n_items {
+            end += self.read_length(&mut reader);
+            end_positions.push(end);
+        }
+        end_positions
+    }
+
+    /// Returns the distance between sampled absolute end positions
+    pub fn sample_rate(&self) -> usize {
+        self.sample_rate
+    }
+
+    /// Returns the code chosen for the lengths
+    pub fn code(&self) -> LengthCode {
+        self.code
+    }
+
+    /// Returns the number of strings
+    pub fn len(&self) -> usize {
+        self.n_items
+    }
+
+    /// Returns whether the index holds no string
+    pub fn is_empty(&self) -> bool {
+        self.n_items == 0
+    }
+
+    /// Returns the average number of bits per string, excluding anchors
+    pub fn bits_per_item(&self) -> f64 {
+        if self.n_items == 0 {
+            return 0.0;
+        }
+        self.bits.len() as f64 / self.n_items as f64
+    }
+
+    /// Reports the memory used by the index in bytes
+    pub fn size_bytes(&self) -> usize {
+        self.bits.len().div_ceil(8) + (self.anchors.len() + self.anchor_offsets.len()) * std::mem::size_of::<usize>()
+    }
+}
+
+/// Replaces the token offsets of the strings of a token-stream compressor with sampled offsets
+///
+/// Token-stream compressors (BPE, OnPairBV) store the end position of every string in
+/// their token stream; sampling keeps the position of every `sample_rate`-th string and
+/// the token count of the others, so that an access scans up to `sample_rate - 1` counts
+/// from the closest sample. Logs the size of the offsets before and after sampling.
+///
+/// # Arguments
+/// - `item_end_positions`: Token boundary positions of the strings, starting with 0 (emptied)
+/// - `sample_rate`: Distance between sampled offsets
+pub(crate) fn sample_item_offsets(item_end_positions: &mut Vec<usize>, sample_rate: usize) -> LengthIndex {
+    let plain_size = item_end_positions.len() * std::mem::size_of::<usize>();
+    let sampled_offsets = LengthIndex::with_sample_rate(item_end_positions, sample_rate);
+    let sampled_size = sampled_offsets.size_bytes();
+    info!(
+        sample_rate,
+        plain_size,
+        sampled_size,
+        code = sampled_offsets.code().name(),
+        reduction = 1.0 - sampled_size as f64 / plain_size.max(1) as f64,
+        "Item offsets sampled"
+    );
+    *item_end_positions = Vec::new();
+    sampled_offsets
+}
+
+/// Returns the number of bits of a positive value, up to its leading one
+#[inline(always)]
+fn bit_length(value: u64) -> usize {
+    (u64::BITS - value.leading_zeros()) as usize
+}
+
+/// Returns a mask of the `n` least significant bits, for `n` below 64
+#[inline(always)]
+fn low_mask(n: usize) -> u64 {
+    (1 << n) - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_code_round_trips_empty_and_maximum_lengths() {
+        // Codewords of up to 64 bits, with the last boundary at `usize::MAX`
+        let max_length = usize::MAX - 16;
+        let lengths = [0, 3, 0, 0, 1, max_length, 0, 7, 5, 0];
+        let mut end_positions = vec![0];
+        for length in lengths {
+            end_positions.push(end_positions.last().unwrap() + length);
+        }
+
+        for code in LengthCode::ALL {
+            for sample_rate in [1, 2, 3, DEFAULT_SAMPLE_RATE] {
+                let index = LengthIndex::with_code(&end_positions, code, sample_rate);
+                assert_eq!(index.len(), lengths.len());
+                assert_eq!(index.end_positions(), end_positions, "{} code at rate {}", code.name(), sample_rate);
+                for (i, &length) in lengths.iter().enumerate() {
+                    assert_eq!(index.bounds(i), (end_positions[i], end_positions[i + 1]));
+                    assert_eq!(index.len_of(i), length);
+                }
+                // Positions are held by the next non-empty string
+                assert_eq!(index.offset_to_item(0), Some((1, 0)));
+                assert_eq!(index.offset_to_item(3), Some((4, 0)));
+                assert_eq!(index.offset_to_item(4), Some((5, 0)));
+                assert_eq!(index.offset_to_item(4 + max_length / 2), Some((5, max_length / 2)));
+                assert_eq!(index.offset_to_item(4 + max_length), Some((7, 0)));
+                assert_eq!(index.offset_to_item(*end_positions.last().unwrap() - 1), Some((8, 4)));
+                assert_eq!(index.offset_to_item(*end_positions.last().unwrap()), None);
+            }
+        }
+        // The smallest code decodes back to the same boundaries
+        assert_eq!(LengthIndex::new(&end_positions).end_positions(), end_positions);
+    }
+
+    #[test]
+    fn empty_collections_hold_no_string() {
+        for end_positions in [vec![], vec![0]] {
+            let index = LengthIndex::new(&end_positions);
+            assert!(index.is_empty());
+            assert_eq!(index.end_positions(), [0]);
+            assert_eq!(index.offset_to_item(0), None);
+            assert_eq!(index.bits_per_item(), 0.0);
+        }
+    }
+}
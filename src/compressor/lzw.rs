@@ -0,0 +1,383 @@
+//! LZW compression with per-string or shared dictionaries
+//!
+//! LZW (Welch, 1984), a variant of LZ78 (Ziv and Lempel, 1978), parses the input into
+//! the longest phrases of a dictionary, and extends the dictionary with each phrase
+//! followed by the next byte. Unlike LZ77 (the block codecs of `tiered`), phrases refer
+//! to dictionary entries rather than to the preceding bytes, and unlike pair merging
+//! (BPE, OnPair), the dictionary grows in a single pass over the input.
+//!
+//! Two dictionaries are supported (see `LZWDictionary`):
+//! - Per string: each string is parsed from the initial dictionary of the 256 bytes,
+//!   grown as in classic LZW, and decoding grows it again. Random access decodes a single
+//!   string without shared state, the phrases of the string being pointers to its own
+//!   decoded bytes, but short strings hold few phrases to refer to.
+//! - Shared: the dictionary grows over the strings, in order, until it holds `MAX_CODES`
+//!   phrases, then it is frozen and every string is parsed into its longest phrases.
+//!   Phrases are stored as pointers to their prefix phrase plus their last byte, and
+//!   random access decodes each phrase by following the pointers from its last byte.
+//!
+//! Phrases never span strings and hold at most `MAX_TOKEN_LENGTH` bytes. Codes are
+//! bit-packed, with the width of the largest code of the shared dictionary, or with the
+//! width of the largest code available when written for per-string dictionaries,
+//! starting at 8 bits.
+
+use super::{item_at_offset, max_len_of_items, Compressor, SpaceBreakdown, StreamBuffer, MAX_TOKEN_LENGTH};
+use crate::bit_vector::{BitReader, BitVector};
+use crate::profiler::{Stage, StageTimer};
+use rustc_hash::FxHashMap;
+use std::io::{self, Write};
+use tracing::debug;
+
+/// Phrases of the initial dictionary, one per byte
+const N_LITERALS: usize = 256;
+/// Maximum number of phrases of a dictionary (16-bit codes)
+const MAX_CODES: usize = 1 << 16;
+/// Space of a stored phrase in bytes: prefix code (2), last byte (1) and length (2)
+const PHRASE_SIZE: usize = 5;
+
+/// Dictionary referenced by the codes of a string
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LZWDictionary {
+    PerItem,    // Grown from the bytes for each string, as in classic LZW
+    Shared,     // Grown over all strings until full, then frozen
+}
+
+/// Dictionary of phrases, each a pointer to its prefix phrase plus a byte
+struct PhraseTree {
+    children: FxHashMap<(u16, u8), u16>,    // Phrase extending a phrase by a byte (parsing only)
+    parents: Vec<u16>,                      // Prefix phrase of each phrase (unused for single bytes)
+    last_bytes: Vec<u8>,                    // Last byte of each phrase
+    lengths: Vec<u16>,                      // Length of each phrase in bytes
+}
+
+impl PhraseTree {
+    /// Creates the initial dictionary of the 256 single-byte phrases
+    fn new() -> Self {
+        PhraseTree {
+            children: FxHashMap::default(),
+            parents: vec![0; N_LITERALS],
+            last_bytes: (0..N_LITERALS).map(|byte| byte as u8).collect(),
+            lengths: vec![1; N_LITERALS],
+        }
+    }
+
+    /// Grows a dictionary over the strings, as LZW parsing them in order, until it is full
+    fn grow(data: &[u8], end_positions: &[usize]) -> Self {
+        let mut tree = PhraseTree::new();
+        for window in end_positions.windows(2) {
+            let item = &data[window[0]..window[1]];
+            let mut position = 0;
+            while position < item.len() && tree.len() < MAX_CODES {
+                let (code, length) = tree.longest_match(&item[position..]);
+                if position + length < item.len() && length < MAX_TOKEN_LENGTH {
+                    tree.push(code, item[position + length]);
+                }
+                position += length;
+            }
+        }
+        tree
+    }
+
+    /// Returns the number of phrases, single bytes included
+    fn len(&self) -> usize {
+        self.lengths.len()
+    }
+
+    /// Drops the phrases grown from the initial dictionary
+    fn reset(&mut self) {
+        self.children.clear();
+        self.parents.truncate(N_LITERALS);
+        self.last_bytes.truncate(N_LITERALS);
+        self.lengths.truncate(N_LITERALS);
+    }
+
+    /// Adds the phrase extending a phrase by a byte, with the next code
+    fn push(&mut self, prefix: u16, byte: u8) {
+        self.children.insert((prefix, byte), self.len() as u16);
+        self.parents.push(prefix);
+        self.last_bytes.push(byte);
+        self.lengths.push(self.lengths[prefix as usize] + 1);
+    }
+
+    /// Finds the longest phrase starting the input, which must not be empty
+    ///
+    /// # Returns
+    /// Code of the phrase and its length in bytes
+    #[inline(always)]
+    fn longest_match(&self, input: &[u8]) -> (u16, usize) {
+        let mut code = input[0] as u16;
+        let mut length = 1;
+        while let Some(&child) = input.get(length).and_then(|&byte| self.children.get(&(code, byte))) {
+            code = child;
+            length += 1;
+        }
+        (code, length)
+    }
+
+    /// Decodes a phrase to `buffer`, following the prefix pointers from its last byte
+    ///
+    /// # Returns
+    /// Length of the phrase in bytes
+    #[inline(always)]
+    fn decode(&self, code: usize, buffer: &mut [u8]) -> usize {
+        let length = self.lengths[code] as usize;
+        let mut code = code;
+        for position in (1..length).rev() {
+            buffer[position] = self.last_bytes[code];
+            code = self.parents[code] as usize;
+        }
+        buffer[0] = code as u8;
+        length
+    }
+
+    /// Returns the space of the phrases grown from the initial dictionary in bytes
+    fn size_bytes(&self) -> usize {
+        (self.len() - N_LITERALS) * PHRASE_SIZE
+    }
+}
+
+/// LZW compressor, with a dictionary per string or shared by all strings
+pub struct LZWCompressor {
+    dictionary: LZWDictionary,
+    phrases: PhraseTree,                // Shared dictionary (the single bytes only with per-string dictionaries)
+    code_bits: usize,                   // Width of the codes of the shared dictionary
+    compressed_data: BitVector,         // Codes of all strings, concatenated
+    item_end_positions: Vec<usize>,     // Boundaries of the strings in `compressed_data` in bits, starting with 0
+    data_end_positions: Vec<usize>,     // Boundaries of the uncompressed strings, starting with 0
+    decoded_phrases: Vec<(usize, usize)>, // Start and length of the phrases of the string being decoded (per-string dictionaries)
+}
+
+impl Compressor for LZWCompressor {
+    fn new(data_size: usize, n_elements: usize) -> Self {
+        Self::with_dictionary(data_size, n_elements, LZWDictionary::Shared)
+    }
+
+    fn compress(&mut self, data: &[u8], end_positions: &[usize]) {
+        if self.dictionary == LZWDictionary::Shared {
+            self.phrases = PhraseTree::grow(data, end_positions);
+            self.code_bits = code_width(self.phrases.len());
+            debug!(n_phrases = self.phrases.len(), code_bits = self.code_bits, "Dictionary grown");
+        }
+
+        self.item_end_positions.push(0);
+        for window in end_positions.windows(2) {
+            let item = &data[window[0]..window[1]];
+            match self.dictionary {
+                LZWDictionary::PerItem => self.encode_per_item(item),
+                LZWDictionary::Shared => self.encode_shared(item),
+            }
+            self.item_end_positions.push(self.compressed_data.len());
+        }
+        self.data_end_positions = end_positions.to_vec();
+
+        // The parsing index is not needed to decode
+        self.phrases.children = FxHashMap::default();
+        if self.dictionary == LZWDictionary::PerItem {
+            self.phrases.reset();
+        }
+        self.compressed_data.shrink_to_fit();
+    }
+
+    fn decompress(&self, buffer: &mut [u8]) -> usize {
+        let mut decoded_phrases = Vec::new();
+        let mut size = 0;
+        for index in 0..self.item_end_positions.len().saturating_sub(1) {
+            size += self.decode_item(index, &mut buffer[size..], &mut decoded_phrases);
+        }
+        size
+    }
+
+    fn decompress_to<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let mut stream = StreamBuffer::new(writer);
+        let mut decoded_phrases = Vec::new();
+        for index in 0..self.item_end_positions.len().saturating_sub(1) {
+            let size = self.decode_item(index, stream.reserve(self.len_of_item(index))?, &mut decoded_phrases);
+            stream.commit(size);
+        }
+        stream.finish()
+    }
+
+    #[inline(always)]
+    fn get_item_at(&mut self, index: usize, buffer: &mut [u8]) -> usize {
+        let mut decoded_phrases = std::mem::take(&mut self.decoded_phrases);
+        let size = self.decode_item(index, buffer, &mut decoded_phrases);
+        self.decoded_phrases = decoded_phrases;
+        size
+    }
+
+    fn get_item_at_profiled(&mut self, index: usize, buffer: &mut [u8], timer: &mut StageTimer) -> usize {
+        let mut decoded_phrases = std::mem::take(&mut self.decoded_phrases);
+        timer.lap(Stage::Lookup);
+
+        // Phrases are written to the buffer as they are decoded
+        let size = self.decode_item(index, buffer, &mut decoded_phrases);
+        self.decoded_phrases = decoded_phrases;
+        timer.lap(Stage::Decode);
+        size
+    }
+
+    fn len_of_item(&self, index: usize) -> usize {
+        self.data_end_positions[index + 1] - self.data_end_positions[index]
+    }
+
+    fn max_item_len(&self) -> usize {
+        max_len_of_items(self, self.item_end_positions.len().saturating_sub(1))
+    }
+
+    fn offset_to_item(&self, offset: usize) -> Option<(usize, usize)> {
+        item_at_offset(&self.data_end_positions, offset)
+    }
+
+    fn space_used_bytes(&self) -> usize {
+        self.space_breakdown().total()
+    }
+
+    fn space_breakdown(&self) -> SpaceBreakdown {
+        SpaceBreakdown {
+            payload: self.compressed_data.len().div_ceil(8),
+            dictionary: self.phrases.size_bytes(),
+            item_offsets: (self.item_end_positions.len() + self.data_end_positions.len()) * std::mem::size_of::<usize>(),
+            ..SpaceBreakdown::default()
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self.dictionary {
+            LZWDictionary::PerItem => "LZW (per string)",
+            LZWDictionary::Shared => "LZW (shared)",
+        }
+    }
+}
+
+impl LZWCompressor {
+    /// Creates a compressor with a dictionary per string or shared by all strings
+    ///
+    /// # Arguments
+    /// - `data_size`: Total size of input data in bytes
+    /// - `n_elements`: Number of individual strings in the dataset
+    /// - `dictionary`: Dictionary referenced by the codes of each string
+    pub fn with_dictionary(data_size: usize, n_elements: usize, dictionary: LZWDictionary) -> Self {
+        LZWCompressor {
+            dictionary,
+            phrases: PhraseTree::new(),
+            code_bits: code_width(N_LITERALS),
+            compressed_data: BitVector::with_capacity(data_size * 8),
+            item_end_positions: Vec::with_capacity(n_elements + 1),
+            data_end_positions: Vec::new(),
+            decoded_phrases: Vec::new(),
+        }
+    }
+
+    /// Appends the codes of a string parsed with the frozen shared dictionary
+    fn encode_shared(&mut self, item: &[u8]) {
+        let mut position = 0;
+        while position < item.len() {
+            let (code, length) = self.phrases.longest_match(&item[position..]);
+            self.compressed_data.append_bits(code as u64, self.code_bits);
+            position += length;
+        }
+    }
+
+    /// Appends the codes of a string parsed with its own dictionary, grown as it is parsed
+    ///
+    /// Each code has the width of the largest code available when it is written: the
+    /// phrases added so far, and the phrase pending on the previous code that the
+    /// decoder adds once it knows the next byte.
+    fn encode_per_item(&mut self, item: &[u8]) {
+        self.phrases.reset();
+        let mut position = 0;
+        while position < item.len() {
+            let (code, length) = self.phrases.longest_match(&item[position..]);
+            self.compressed_data.append_bits(code as u64, code_width(self.phrases.len()));
+            if position + length < item.len() && self.phrases.len() < MAX_CODES && length < MAX_TOKEN_LENGTH {
+                self.phrases.push(code, item[position + length]);
+            }
+            position += length;
+        }
+    }
+
+    /// Decodes a string to `buffer`
+    ///
+    /// # Arguments
+    /// - `index`: Zero-based index of the string
+    /// - `buffer`: Output buffer for the string
+    /// - `decoded_phrases`: Scratch space for the phrases of per-string dictionaries
+    ///
+    /// # Returns
+    /// Number of bytes decoded
+    #[inline(always)]
+    fn decode_item(&self, index: usize, buffer: &mut [u8], decoded_phrases: &mut Vec<(usize, usize)>) -> usize {
+        let mut reader = self.compressed_data.reader(self.item_end_positions[index]);
+        let end = self.item_end_positions[index + 1];
+        match self.dictionary {
+            LZWDictionary::PerItem => decode_per_item(&mut reader, end, buffer, decoded_phrases),
+            LZWDictionary::Shared => {
+                let mut size = 0;
+                while reader.pos() < end {
+                    // Codes lie within the stream, ending at `end`
+                    let code = unsafe { reader.read_unchecked(self.code_bits) } as usize;
+                    size += self.phrases.decode(code, &mut buffer[size..]);
+                }
+                size
+            }
+        }
+    }
+}
+
+/// Decodes the codes of a string parsed with its own dictionary, growing it again
+///
+/// Each phrase added to the dictionary is the previous phrase followed by the first byte
+/// of the next one: both are decoded next to each other, so the phrase is stored as a
+/// pointer to its bytes in `buffer`. A code may refer to the phrase pending on the
+/// previous code (the previous phrase followed by its own first byte), copied from the
+/// previous phrase one byte at a time.
+///
+/// # Arguments
+/// - `reader`: Cursor on the first code of the string
+/// - `end`: Bit position past the last code of the string
+/// - `buffer`: Output buffer for the string
+/// - `decoded_phrases`: Receives the start and length of the phrases added to the dictionary
+///
+/// # Returns
+/// Number of bytes decoded
+#[inline(always)]
+fn decode_per_item(reader: &mut BitReader, end: usize, buffer: &mut [u8], decoded_phrases: &mut Vec<(usize, usize)>) -> usize {
+    decoded_phrases.clear();
+    let mut size = 0;
+    let mut previous: Option<(usize, usize)> = None;
+    while reader.pos() < end {
+        let n_codes = N_LITERALS + decoded_phrases.len();
+        let pending = previous.filter(|&(_, length)| n_codes < MAX_CODES && length < MAX_TOKEN_LENGTH);
+        // Codes lie within the stream, ending at `end`
+        let code = unsafe { reader.read_unchecked(code_width(n_codes + pending.is_some() as usize)) } as usize;
+
+        let length = if code < N_LITERALS {
+            buffer[size] = code as u8;
+            1
+        } else if code < n_codes {
+            let (start, length) = decoded_phrases[code - N_LITERALS];
+            buffer.copy_within(start..start + length, size);
+            length
+        } else {
+            // The pending phrase overlaps its own first byte
+            let (start, length) = pending.unwrap();
+            for offset in 0..=length {
+                buffer[size + offset] = buffer[start + offset];
+            }
+            length + 1
+        };
+
+        if let Some((start, length)) = pending {
+            decoded_phrases.push((start, length + 1));
+        }
+        previous = Some((size, length));
+        size += length;
+    }
+    size
+}
+
+/// Returns the width in bits of the codes of a dictionary of `n_codes` phrases
+#[inline(always)]
+fn code_width(n_codes: usize) -> usize {
+    (usize::BITS - (n_codes - 1).leading_zeros()) as usize
+}
@@ -6,9 +6,233 @@
 
 pub mod raw;
 pub mod bpe;
+#[cfg(feature = "onpair")]
 pub mod onpair;
+#[cfg(feature = "onpair")]
 pub mod onpair16;
+#[cfg(feature = "onpair")]
 pub mod onpair_bv;
+pub mod record;
+pub mod id_map;
+pub mod dedup;
+pub mod checksum;
+pub mod length_index;
+pub mod intra_item_index;
+pub mod fm_index;
+pub mod front_coding;
+pub mod fsst;
+#[cfg(feature = "fsst-ffi")]
+pub mod fsst_ffi;
+pub mod lzw;
+pub mod terminated;
+pub mod prefix_match;
+pub mod prefix_trie;
+pub mod sampling;
+pub mod pair_counting;
+pub mod dictionary_report;
+pub mod block_stats;
+pub mod coded_dictionary;
+pub mod token_offsets;
+pub mod offset_coding;
+pub mod incremental;
+#[cfg(feature = "tiered")]
+pub mod tiered;
+pub mod registry;
+
+use crate::profiler::{Stage, StageTimer};
+use block_stats::BlockStats;
+use checksum::ChecksumAlgorithm;
+use dictionary_report::{DictionaryReport, DictionaryStats};
+use incremental::{CompressionSteps, Progress};
+use serde::{Serialize, Deserialize};
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::cmp::Reverse;
+use std::io::{self, Write};
+use std::ops::Range;
+use tracing::{debug, info};
+
+/// Breakdown of the space used by a compressed representation
+///
+/// Splits the memory footprint into the categories that make up a compressed
+/// string collection. Transient structures such as block caches are excluded.
+#[derive(Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq)]
+#[serde(default)]
+pub struct SpaceBreakdown {
+    pub payload: usize,         // Compressed data (token streams or compressed blocks)
+    pub dictionary: usize,      // Dictionary entries and their boundary positions
+    pub item_offsets: usize,    // Per-item boundary positions, and sampled token positions within items
+    pub block_metadata: usize,  // Per-block metadata (block-based compressors only)
+    pub id_map: usize,          // External to internal ID mapping (reordering wrappers only)
+    pub checksums: usize,       // Integrity checksums (verifying wrappers only)
+}
+
+impl SpaceBreakdown {
+    /// Returns the sum of all space categories in bytes
+    pub fn total(&self) -> usize {
+        self.payload + self.dictionary + self.item_offsets + self.block_metadata + self.id_map + self.checksums
+    }
+}
+
+impl std::ops::Add for SpaceBreakdown {
+    type Output = SpaceBreakdown;
+
+    fn add(self, other: SpaceBreakdown) -> SpaceBreakdown {
+        SpaceBreakdown {
+            payload: self.payload + other.payload,
+            dictionary: self.dictionary + other.dictionary,
+            item_offsets: self.item_offsets + other.item_offsets,
+            block_metadata: self.block_metadata + other.block_metadata,
+            id_map: self.id_map + other.id_map,
+            checksums: self.checksums + other.checksums,
+        }
+    }
+}
+
+impl std::iter::Sum for SpaceBreakdown {
+    fn sum<I: Iterator<Item = SpaceBreakdown>>(iter: I) -> SpaceBreakdown {
+        iter.fold(SpaceBreakdown::default(), |acc, breakdown| acc + breakdown)
+    }
+}
+
+/// How a compressor locates the boundaries of its strings
+///
+/// Most compressors store the end position of every string in an offset array.
+/// Others store no per-string offsets and find boundaries while decoding (terminator
+/// symbols, or lengths embedded in the compressed stream), at the cost of decoding
+/// from a sampled anchor: their space reports no item offsets. In between, token-stream
+/// compressors can sample their offsets, storing the offset of every `k`-th string and
+/// the token count of the strings in between (see `length_index`).
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ItemBoundaries {
+    Offsets,    // One stored offset per string
+    Decoded,    // Found while decoding, without per-string offsets
+    Sampled,    // Sampled offsets, with per-string counts scanned from the closest sample
+}
+
+impl ItemBoundaries {
+    /// Returns the identifier of the boundary storage
+    pub fn name(&self) -> &'static str {
+        match self {
+            ItemBoundaries::Offsets => "offsets",
+            ItemBoundaries::Decoded => "decoded",
+            ItemBoundaries::Sampled => "sampled",
+        }
+    }
+}
+
+/// Position of a string within the block (or bucket) storing it
+///
+/// Block-based access decodes a block up to or beyond the requested string, so its
+/// latency may depend on where the string lies in the block. A block holding a single
+/// string reports it as first.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockPosition {
+    First,
+    Middle,
+    Last,
+}
+
+impl BlockPosition {
+    /// All positions, in block order
+    pub const ALL: [BlockPosition; 3] = [BlockPosition::First, BlockPosition::Middle, BlockPosition::Last];
+
+    /// Classifies the string at an offset within a block of `block_size` strings
+    pub fn of(offset: usize, block_size: usize) -> Self {
+        debug_assert!(offset < block_size);
+        if offset == 0 {
+            BlockPosition::First
+        } else if offset + 1 == block_size {
+            BlockPosition::Last
+        } else {
+            BlockPosition::Middle
+        }
+    }
+
+    /// Returns the position identifier
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlockPosition::First => "first",
+            BlockPosition::Middle => "middle",
+            BlockPosition::Last => "last",
+        }
+    }
+}
+
+/// Largest dataset, string or dictionary size addressable by 32-bit offsets (4 GiB)
+pub const MAX_U32_OFFSET: usize = u32::MAX as usize;
+
+/// Maximum length of the tokens merged by dictionary-based compressors (BPE, OnPairBV)
+///
+/// Without a bound, runs of a repeated byte merge into tokens doubling in length up to
+/// the whole run: the dictionary grows as large as the runs, and dictionary lookups
+/// grow with the longest run.
+pub const MAX_TOKEN_LENGTH: usize = 1024;
+
+/// Limits on the datasets a compressor can represent
+///
+/// Compact structures store positions with 32-bit offsets (e.g., dictionary boundaries,
+/// string lengths), or sizes with 32-bit integers (block sizes). Datasets exceeding a
+/// limit are rejected before compression instead of being silently corrupted; limits
+/// on structures whose size depends on the data (e.g., dictionaries) are checked while
+/// building them.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Capacity {
+    pub max_data_size: usize,   // Total size of the strings in bytes
+    pub max_item_size: usize,   // Size of a single string in bytes
+}
+
+impl Capacity {
+    /// No limit beyond the address space
+    pub const UNLIMITED: Capacity = Capacity { max_data_size: usize::MAX, max_item_size: usize::MAX };
+
+    /// Checks a dataset against the limits
+    /// 
+    /// # Arguments
+    /// - `end_positions`: Boundary positions of the strings, starting with 0
+    /// 
+    /// # Returns
+    /// A description of the first limit exceeded, if any
+    pub fn check(&self, end_positions: &[usize]) -> Result<(), String> {
+        let data_size = *end_positions.last().unwrap_or(&0);
+        if data_size > self.max_data_size {
+            return Err(format!("dataset of {} bytes exceeds the maximum of {} bytes", data_size, self.max_data_size));
+        }
+        let max_item_size = end_positions.windows(2).map(|w| w[1] - w[0]).max().unwrap_or(0);
+        if max_item_size > self.max_item_size {
+            return Err(format!("string of {} bytes exceeds the maximum of {} bytes", max_item_size, self.max_item_size));
+        }
+        Ok(())
+    }
+
+    /// Panics with the compressor's name if a dataset exceeds the limits
+    pub fn assert_fits(&self, end_positions: &[usize], compressor_name: &str) {
+        if let Err(message) = self.check(end_positions) {
+            panic!("Unsupported dataset for compressor {}: {}", compressor_name, message);
+        }
+    }
+}
+
+/// Returns the length of the longest of the first `n_items` strings of a compressor
+/// 
+/// Scans `len_of_item`, for compressors keeping neither the lengths nor their maximum
+/// (see `Compressor::max_item_len`).
+pub(crate) fn max_len_of_items<T: Compressor + ?Sized>(compressor: &T, n_items: usize) -> usize {
+    (0..n_items).map(|index| compressor.len_of_item(index)).max().unwrap_or(0)
+}
+
+/// Converts a position to a 32-bit offset, panicking if it exceeds `MAX_U32_OFFSET`
+/// 
+/// # Arguments
+/// - `position`: Position to convert
+/// - `structure`: Name of the structure holding the offset, for the error message
+#[inline(always)]
+pub(crate) fn to_u32_offset(position: usize, structure: &str) -> u32 {
+    u32::try_from(position).unwrap_or_else(|_| {
+        panic!("{} exceeds {} bytes ({} bytes), the maximum addressable by 32-bit offsets", structure, MAX_U32_OFFSET, position)
+    })
+}
 
 /// Core trait defining the compression algorithm interface
 /// 
@@ -17,7 +241,13 @@ pub mod onpair_bv;
 /// - Compression/decompression of string collections
 /// - Access to individual strings by index
 /// - Space usage reporting for compression ratio calculation
-pub trait Compressor {
+///
+/// Compressors must be `Send + Sync`, so that independent instances can be built and
+/// queried on parallel threads (e.g., one thread per dataset). Mutable state used
+/// during random access, such as block caches, must be owned by the instance: shared
+/// state across instances (globals, `Rc`, cells shared between compressors) is not
+/// allowed.
+pub trait Compressor: Send + Sync {
     /// Creates a new compressor instance with pre-allocated buffers
     /// 
     /// Create instances of compression algorithms with appropriate buffer sizes based 
@@ -35,6 +265,19 @@ pub trait Compressor {
     /// - `end_positions`: Boundary positions for individual strings (cumulative lengths)
     fn compress(&mut self, data: &[u8], end_positions: &[usize]);
 
+    /// Splits the compression of the input dataset into units of work (see `incremental`)
+    ///
+    /// Running the units until one reports `Progress::Done` compresses the dataset
+    /// as `compress` does. The default implementation runs the whole compression as
+    /// a single unit; compressors with long resumable phases override it.
+    ///
+    /// # Arguments
+    /// - `data`: Concatenated string data as byte array
+    /// - `end_positions`: Boundary positions for individual strings (cumulative lengths)
+    fn compress_steps<'a>(&'a mut self, data: &'a [u8], end_positions: &'a [usize]) -> CompressionSteps<'a> {
+        CompressionSteps::single(move || self.compress(data, end_positions))
+    }
+
     /// Decompresses the entire dataset to provided buffer
     /// 
     /// # Arguments
@@ -44,12 +287,28 @@ pub trait Compressor {
     /// Number of bytes written to the output buffer
     fn decompress(&self, buffer: &mut [u8]) -> usize;
 
+    /// Decompresses the entire dataset to a writer, with bounded memory
+    /// 
+    /// Unlike `decompress`, no buffer of the size of the dataset is required:
+    /// output is flushed to the writer in chunks of about `STREAM_CHUNK_SIZE` bytes
+    /// (or block by block for block-based compressors).
+    /// 
+    /// # Arguments
+    /// - `writer`: Destination of the decompressed data
+    /// 
+    /// # Returns
+    /// Number of bytes written, or the first error returned by the writer
+    fn decompress_to<W: Write>(&self, writer: &mut W) -> io::Result<usize>;
+
     /// Retrieves a single string by index
     /// 
     /// Core operation for access latency measurement. Provides direct access
     /// to individual strings without decompressing the entire dataset.
     /// Writes the requested string to the provided buffer.
     /// 
+    /// The buffer is not checked: it must hold the string plus `BUFFER_PADDING` bytes
+    /// (see `max_item_len` to size it once for any string, and `try_get_item_at`).
+    /// 
     /// # Arguments
     /// - `index`: Zero-based index of the string to retrieve
     /// - `buffer`: Output buffer for the decompressed string
@@ -58,12 +317,232 @@ pub trait Compressor {
     /// Number of bytes written to the buffer
     fn get_item_at(&mut self, index: usize, buffer: &mut [u8]) -> usize;
 
+    /// Retrieves a single string by index, checking the buffer first
+    /// 
+    /// Checked counterpart of `get_item_at`, for callers outside measured loops: the
+    /// length of the string is looked up before decoding it (see `len_of_item`).
+    /// 
+    /// # Arguments
+    /// - `index`: Zero-based index of the string to retrieve
+    /// - `buffer`: Output buffer for the decompressed string
+    /// 
+    /// # Returns
+    /// Number of bytes written to the buffer, or a description of the missing room if
+    /// the buffer cannot hold the string plus `BUFFER_PADDING` bytes
+    fn try_get_item_at(&mut self, index: usize, buffer: &mut [u8]) -> Result<usize, String> {
+        let required_size = self.len_of_item(index) + BUFFER_PADDING;
+        if buffer.len() < required_size {
+            return Err(format!("buffer of {} bytes cannot hold string {} ({} bytes required, including {} bytes of padding)", buffer.len(), index, required_size, BUFFER_PADDING));
+        }
+        Ok(self.get_item_at(index, buffer))
+    }
+
+    /// Retrieves a single string by index, attributing its latency to access stages
+    /// 
+    /// Instrumented counterpart of `get_item_at` (see `profiler`): the caller starts
+    /// the timer, and the compressor laps it after locating the string (`Stage::Lookup`),
+    /// decoding it (`Stage::Decode`) and copying it to the buffer (`Stage::Copy`). Stages
+    /// interleaved in `get_item_at` run one after the other here. The default
+    /// implementation charges the whole access to decoding, for compressors whose access
+    /// path cannot be split (e.g., those implemented by external libraries).
+    /// 
+    /// # Arguments
+    /// - `index`: Zero-based index of the string to retrieve
+    /// - `buffer`: Output buffer, with the same padding as for `get_item_at`
+    /// - `timer`: Timer charged with the ticks of each stage
+    /// 
+    /// # Returns
+    /// Number of bytes written to the buffer
+    fn get_item_at_profiled(&mut self, index: usize, buffer: &mut [u8], timer: &mut StageTimer) -> usize {
+        let size = self.get_item_at(index, buffer);
+        timer.lap(Stage::Decode);
+        size
+    }
+
+    /// Retrieves a single string by index without copying it
+    /// 
+    /// Zero-copy counterpart of `get_item_at`: returns the string as a slice of the
+    /// compressor's own storage (e.g., the uncompressed data, or the cached block
+    /// holding the string), valid until the next access. The default implementation
+    /// returns None, for compressors that decode strings directly into the caller's
+    /// buffer; callers then fall back to `get_item_at`.
+    /// 
+    /// # Arguments
+    /// - `index`: Zero-based index of the string to retrieve
+    /// 
+    /// # Returns
+    /// The string, or None if the compressor does not support zero-copy access
+    fn get_item_ref(&mut self, _index: usize) -> Option<&[u8]> {
+        None
+    }
+
+    /// Retrieves a batch of strings by index
+    /// 
+    /// Strings are written consecutively in the order of `indices`, and the end
+    /// position of each of them in the buffer is stored in `item_ends`. The default
+    /// implementation retrieves them one at a time through `get_item_at`; compressors
+    /// able to share work between the strings of a batch should override it.
+    /// 
+    /// # Arguments
+    /// - `indices`: Zero-based indices of the strings to retrieve, in output order
+    /// - `buffer`: Output buffer for all strings, plus the padding required by `get_item_at`
+    /// - `item_ends`: Receives the end position of each string (at least `indices.len()` entries)
+    /// 
+    /// # Returns
+    /// Number of bytes written to the buffer
+    fn get_items_at(&mut self, indices: &[usize], buffer: &mut [u8], item_ends: &mut [usize]) -> usize {
+        let mut size = 0;
+        for (&index, item_end) in indices.iter().zip(item_ends.iter_mut()) {
+            size += self.get_item_at(index, &mut buffer[size..]);
+            *item_end = size;
+        }
+        size
+    }
+
+    /// Retrieves a byte range of a single string
+    ///
+    /// Range reads within long strings (e.g., documents) need only part of the string.
+    /// The default implementation decodes the whole string into a scratch buffer and
+    /// copies the range; token-stream compressors override it to skip the tokens
+    /// preceding the range without copying them, from the closest sampled token if
+    /// configured (see `intra_item_index`).
+    ///
+    /// # Arguments
+    /// - `index`: Zero-based index of the string
+    /// - `start`: Offset of the first byte of the range within the string
+    /// - `end`: Offset past the last byte of the range, at least `start` and at most the
+    ///   length of the string
+    /// - `buffer`: Output buffer, with room for `end - start` bytes plus `2 * MAX_TOKEN_LENGTH`
+    ///   (the tokens straddling the range bounds) and the padding required by `get_item_at`
+    ///
+    /// # Returns
+    /// Number of bytes written to the buffer (`end - start`)
+    fn get_item_range(&mut self, index: usize, start: usize, end: usize, buffer: &mut [u8]) -> usize {
+        let mut item = vec![0; self.len_of_item(index) + BUFFER_PADDING];
+        self.get_item_at(index, &mut item);
+        buffer[..end - start].copy_from_slice(&item[start..end]);
+        end - start
+    }
+
+    /// Visits the strings of an index range in order, projecting each of them
+    /// 
+    /// Scans read many consecutive strings but often need only part of each (e.g., a
+    /// prefix or a fixed-width field): the projector receives every string in place,
+    /// without the range being materialized. The default implementation retrieves the
+    /// strings one at a time through `get_item_at` into a reused buffer; compressors
+    /// decoding neighbouring strings together (blocks, buckets, token streams) should
+    /// override it to decode the range sequentially.
+    /// 
+    /// # Arguments
+    /// - `start_index`: Index of the first string to visit
+    /// - `end_index`: Index past the last string to visit (at most the number of strings)
+    /// - `projector`: Called with the index and the content of each string, which is only
+    ///   valid during the call
+    fn scan_range<P: FnMut(usize, &[u8])>(&mut self, start_index: usize, end_index: usize, mut projector: P) {
+        let mut buffer = Vec::new();
+        for index in start_index..end_index {
+            // Same slack as streaming decompression, for compressors copying past the end
+            let required_size = self.len_of_item(index) + BUFFER_PADDING;
+            if buffer.len() < required_size {
+                buffer.resize(required_size, 0);
+            }
+            let size = self.get_item_at(index, &mut buffer);
+            projector(index, &buffer[..size]);
+        }
+    }
+
+    /// Reports the length of a single string, without decompressing it
+    /// 
+    /// Allows callers to size output buffers exactly before calling `get_item_at`,
+    /// and benchmarks to validate retrieved sizes cheaply.
+    /// 
+    /// # Arguments
+    /// - `index`: Zero-based index of the string
+    /// 
+    /// # Returns
+    /// Length of the string in bytes
+    fn len_of_item(&self, index: usize) -> usize;
+
+    /// Reports the length of the longest string
+    /// 
+    /// Allows callers to size a single output buffer for `get_item_at` on any string
+    /// (plus `BUFFER_PADDING` bytes). Compressors not keeping it may scan the lengths of
+    /// all strings: callers should keep the result rather than calling it per access.
+    /// 
+    /// # Returns
+    /// Length of the longest string in bytes, 0 if there is none
+    fn max_item_len(&self) -> usize;
+
+    /// Finds the string holding a byte offset of the uncompressed data
+    /// 
+    /// Offsets address the concatenation of the strings in input order, so that
+    /// integrations addressing data by byte ranges (e.g., range reads) can map them to
+    /// strings. Compressors keeping the boundaries of the uncompressed strings override
+    /// it with a binary search; the default implementation returns None, for compressors
+    /// knowing string lengths only (see `store::CompressedStringStore::offset_to_item`).
+    /// 
+    /// # Arguments
+    /// - `offset`: Byte offset in the uncompressed data
+    /// 
+    /// # Returns
+    /// Index of the string and offset within it (empty strings hold no offset), or None
+    /// if the offset is past the end of the data or the lookup is not supported
+    fn offset_to_item(&self, _offset: usize) -> Option<(usize, usize)> {
+        None
+    }
+
+    /// Reports how the compressor locates the boundaries of its strings
+    /// 
+    /// The default implementation reports an offset array. Compressors opting out of
+    /// per-string offsets report `ItemBoundaries::Decoded`, and must then report no
+    /// `item_offsets` in `space_breakdown`: the benchmark warns otherwise.
+    fn item_boundaries(&self) -> ItemBoundaries {
+        ItemBoundaries::Offsets
+    }
+
+    /// Reports the position of a string within the block storing it
+    /// 
+    /// The default implementation returns None, for compressors accessing every string
+    /// independently. Compressors grouping strings into blocks or buckets override it.
+    /// 
+    /// # Arguments
+    /// - `index`: Zero-based index of the string
+    fn block_position(&self, _index: usize) -> Option<BlockPosition> {
+        None
+    }
+
+    /// Reports the limits on the datasets the compressor can represent
+    /// 
+    /// The default implementation reports no limit. Compressors storing 32-bit
+    /// positions or sizes should override it, and check their input against it
+    /// when compressing (see `Capacity::assert_fits`).
+    fn capacity(&self) -> Capacity {
+        Capacity::UNLIMITED
+    }
+
     /// Reports total memory usage of the compressed representation
     /// 
+    /// Compressors overriding `space_breakdown` must return its total, so that the
+    /// compression rate and the breakdown of a result agree.
+    /// 
     /// # Returns
     /// Total bytes used by compressed data and metadata structures
     fn space_used_bytes(&self) -> usize;
 
+    /// Reports memory usage split by category
+    /// 
+    /// The default implementation attributes all reported space to the payload.
+    /// Compressors with access to their internal layout should override it.
+    /// 
+    /// # Returns
+    /// Space usage of payload, dictionary, item offsets and block metadata
+    fn space_breakdown(&self) -> SpaceBreakdown {
+        SpaceBreakdown {
+            payload: self.space_used_bytes(),
+            ..SpaceBreakdown::default()
+        }
+    }
+
     /// Returns the human-readable name of the compression algorithm
     /// 
     /// # Returns
@@ -71,6 +550,98 @@ pub trait Compressor {
     fn name(&self) -> &str;
 }
 
+/// Size of the chunks flushed to the writer by streaming decompression
+pub const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+/// Slack after the written data required by decompression and access routines (e.g., 16-byte copies)
+pub const BUFFER_PADDING: usize = 1024;
+
+/// Chunk buffer flushing decompressed data to a writer
+/// 
+/// Decompression routines reserve space for the bytes they are about to write,
+/// with `BUFFER_PADDING` bytes of slack, and commit the bytes actually written.
+/// The buffer is flushed whenever a reservation does not fit in the current chunk,
+/// so that memory is bounded by the chunk size plus the largest reservation.
+pub struct StreamBuffer<'a, W: Write> {
+    writer: &'a mut W,
+    buffer: Vec<u8>,
+    len: usize,         // Bytes pending in the buffer
+    written: usize,     // Bytes flushed to the writer
+}
+
+impl<'a, W: Write> StreamBuffer<'a, W> {
+    /// Creates a chunk buffer over a writer
+    pub fn new(writer: &'a mut W) -> Self {
+        StreamBuffer { writer, buffer: vec![0; STREAM_CHUNK_SIZE + BUFFER_PADDING], len: 0, written: 0 }
+    }
+
+    /// Returns a buffer for writing up to `size` bytes (plus padding), flushing if needed
+    #[inline(always)]
+    pub fn reserve(&mut self, size: usize) -> io::Result<&mut [u8]> {
+        if self.len + size + BUFFER_PADDING > self.buffer.len() {
+            self.flush()?;
+            if size + BUFFER_PADDING > self.buffer.len() {
+                self.buffer.resize(size + BUFFER_PADDING, 0);
+            }
+        }
+        Ok(&mut self.buffer[self.len..])
+    }
+
+    /// Marks `size` bytes of the last reservation as written
+    #[inline(always)]
+    pub fn commit(&mut self, size: usize) {
+        self.len += size;
+    }
+
+    /// Writes the pending bytes to the writer
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.write_all(&self.buffer[..self.len])?;
+        self.written += self.len;
+        self.len = 0;
+        Ok(())
+    }
+
+    /// Flushes the pending bytes and returns the total number of bytes written
+    pub fn finish(mut self) -> io::Result<usize> {
+        self.flush()?;
+        self.writer.flush()?;
+        Ok(self.written)
+    }
+}
+
+/// Finds the string holding a byte offset, by binary search on string boundaries
+/// 
+/// # Arguments
+/// - `end_positions`: Boundary positions of the strings, starting with 0
+/// - `offset`: Byte offset in the concatenated strings
+/// 
+/// # Returns
+/// Index of the string and offset within it, or None past the end of the data
+pub(crate) fn item_at_offset(end_positions: &[usize], offset: usize) -> Option<(usize, usize)> {
+    if offset >= *end_positions.last()? {
+        return None;
+    }
+    // The last string starting at or before the offset, skipping empty strings
+    let index = end_positions.partition_point(|&end| end <= offset) - 1;
+    Some((index, offset - end_positions[index]))
+}
+
+/// Prefetches the given bytes into the cache, one cache line at a time
+/// 
+/// Does nothing on architectures without a prefetch intrinsic.
+#[inline(always)]
+fn prefetch_bytes(data: &[u8]) {
+    #[cfg(target_arch = "x86_64")]
+    for offset in (0..data.len()).step_by(64) {
+        // SAFETY: the address is within `data`, and prefetching never faults
+        unsafe {
+            use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+            _mm_prefetch::<_MM_HINT_T0>(data.as_ptr().add(offset) as *const i8);
+        }
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = data;
+}
+
 #[allow(dead_code)]
 /// Default block size for block-based compression algorithms
 /// Set to 64 KB as a reasonable balance between compression efficiency and memory usage.
@@ -80,10 +651,117 @@ const DEFAULT_BLOCK_SIZE: usize = 64 * 1024;
 /// 
 /// Stores essential information needed for block boundary management
 /// and random access within compressed datasets divided into fixed-size blocks.
+/// Blocks identical to an earlier block reference its compressed data instead of
+/// storing a copy, so compressed ranges of different blocks may coincide.
 pub struct BlockMetadata {
+    pub start_position: usize,  // Start position of this block in compressed data
     pub end_position: usize,    // End position of this block in compressed data
     pub num_items_psum: usize,  // Cumulative number of items up to this block
     pub uncompressed_size: i32, // Uncompressed size of this block
+    pub checksum: u32,          // Checksum of the uncompressed block (0 if disabled)
+    pub codec: u8,              // Codec used for this block (multi-codec compressors only, 0 otherwise)
+}
+
+/// Block size chosen to bound the decompression time of a single block
+///
+/// Recorded by block compressors given a latency budget (see
+/// `TieredCompressor::set_latency_budget`), which calibrate block sizes on sampled
+/// blocks before compressing.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct BlockSizeCalibration {
+    pub latency_budget: f64,                // Decompression time allowed per block in nanoseconds
+    pub block_size: usize,                  // Largest calibrated block size within the budget in bytes
+    pub block_latency: f64,                 // Mean decompression time of sampled blocks of that size in nanoseconds
+    pub within_budget: bool,                // Whether the block size meets the budget (the smallest size is used otherwise)
+}
+
+/// Single-byte tokens in the token stream of a dictionary compressor
+///
+/// Recorded by OnPairBV after compressing. Strings poorly covered by the dictionary
+/// (e.g., noisy data) parse into many single-byte tokens, which can be inlined in the
+/// token stream instead of referencing the dictionary (see
+/// `OnPairBVCompressor::with_literal_inlining`).
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct LiteralTokenStats {
+    pub n_tokens: usize,            // Tokens of all compressed strings
+    pub single_byte_tokens: usize,  // Tokens of a single byte
+    pub inlined: bool,              // Whether single-byte tokens are inlined in the token stream
+    pub dictionary_tokens: usize,   // Tokens of the dictionary, single-byte tokens included
+}
+
+impl LiteralTokenStats {
+    /// Returns the fraction of the tokens made of a single byte
+    pub fn single_byte_fraction(&self) -> f64 {
+        if self.n_tokens == 0 { 0.0 } else { self.single_byte_tokens as f64 / self.n_tokens as f64 }
+    }
+}
+
+/// Space saved by storing identical blocks once (see `BlockCompressor::get_block_dedup_stats`)
+#[derive(Clone, Copy, Default, Debug)]
+pub struct BlockDedupStats {
+    pub duplicate_blocks: usize,    // Blocks referencing the compressed data of an earlier block
+    pub saved_bytes: usize,         // Compressed size of the duplicate blocks
+}
+
+/// Policy deciding where block-based compressors cut blocks
+/// 
+/// Cutting purely by size makes the number of items per block depend on item
+/// lengths, and so does the depth of the search for items within blocks.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlockPacking {
+    Bytes,          // Cut before exceeding the block size
+    Items(usize),   // Cut every given number (at least 1) of items, regardless of size
+    Hybrid(usize),  // Cut before exceeding the block size or the given number (at least 1) of items
+}
+
+impl BlockPacking {
+    /// Returns whether the current block must be closed before adding an item
+    /// 
+    /// Under `Bytes` and `Hybrid`, items larger than the block size (jumbo items) get a
+    /// block of their own: the size limit closes the block before and after them.
+    /// Under `Items`, jumbo items are packed like any other, so that every block but
+    /// the last holds exactly the given number of items.
+    /// 
+    /// # Arguments
+    /// - `block_size`: Target block size in bytes
+    /// - `current_block_size`: Size of the current block in bytes
+    /// - `num_items_in_block`: Number of items in the current block
+    /// - `item_size`: Size of the item to add
+    #[inline(always)]
+    pub fn should_cut(&self, block_size: usize, current_block_size: usize, num_items_in_block: usize, item_size: usize) -> bool {
+        let exceeds_size = current_block_size + item_size > block_size;
+        match *self {
+            BlockPacking::Bytes => exceeds_size,
+            BlockPacking::Items(max_items) => num_items_in_block >= max_items,
+            BlockPacking::Hybrid(max_items) => exceeds_size || num_items_in_block >= max_items,
+        }
+    }
+}
+
+/// Scratch buffers of batch access, kept by compressors and reused across calls
+///
+/// Access paths must not allocate in steady state (see `alloc_counter`): a buffer is
+/// taken out of the scratch for the duration of a call, cleared and put back with its
+/// capacity, so that allocations only happen while it grows to the largest batch.
+#[derive(Default)]
+pub struct AccessScratch {
+    pub block_order: Vec<(usize, usize)>,   // (block, position in the batch) of each query
+    pub ids: Vec<usize>,                    // Indices of a batch after remapping
+}
+
+/// Position of a block compression between calls (see `BlockCompressor::compress_blocks`)
+///
+/// Holds the items of the current, not yet compressed block, and the index of the
+/// blocks compressed so far used to deduplicate identical blocks.
+pub struct BlockCutter<'a> {
+    data: &'a [u8],                                     // Concatenated strings being compressed
+    end_positions: &'a [usize],                         // Boundary positions of the strings
+    next_item: usize,                                   // Index in end_positions of the end of the next item
+    block_start: usize,                                 // Start of the current block
+    num_items_in_block: usize,                          // Number of items in the current block
+    current_block_size: usize,                          // Total size of the current block
+    item_start: usize,                                  // Start of the next item
+    unique_blocks: FxHashMap<(&'a [u8], u8), usize>,    // Index of the first block with each content and codec
 }
 
 /// Extended trait for block-based compression algorithms
@@ -98,12 +776,37 @@ pub trait BlockCompressor: Compressor {
     /// The size in bytes of each data block used for compression
     fn get_block_size(&self) -> usize;
 
+    /// Returns the policy used to cut blocks
+    /// 
+    /// # Returns
+    /// The block packing policy, cutting by size by default
+    fn get_block_packing(&self) -> BlockPacking {
+        BlockPacking::Bytes
+    }
+
+    /// Returns the checksum function used to verify blocks, if any
+    /// 
+    /// When enabled, the checksum of each uncompressed block is stored in its
+    /// metadata and verified whenever the block is decompressed.
+    /// 
+    /// # Returns
+    /// The checksum function, or `None` to disable verification (default)
+    fn get_checksum_algorithm(&self) -> Option<ChecksumAlgorithm> {
+        None
+    }
+
     /// Provides access to the entire compressed data
     /// 
     /// # Returns
     /// Byte slice containing all compressed blocks concatenated together
     fn get_compressed_data(&self) -> &[u8];
 
+    /// Returns mutable access to the compressed data
+    /// 
+    /// # Returns
+    /// Mutable vector containing all compressed blocks concatenated together
+    fn get_compressed_data_mut(&mut self) -> &mut Vec<u8>;
+
     /// Returns the metadata for all compressed blocks
     ///
     /// # Returns
@@ -148,12 +851,47 @@ pub trait BlockCompressor: Compressor {
     /// - `buffer`: Output buffer for the decompressed data
     fn decompress_block(&self, compressed_data: &[u8], uncompressed_size: usize, buffer: &mut [u8]);
 
+    /// Selects the codec used to compress a block
+    /// 
+    /// Compressors supporting several codecs override it, together with
+    /// `compress_block_with_codec` and `decompress_block_with_codec`. The codec
+    /// identifier is stored in the block metadata.
+    /// 
+    /// # Arguments
+    /// - `first_item`: Index of the first item of the block
+    /// - `num_items`: Number of items in the block
+    /// 
+    /// # Returns
+    /// Codec identifier, 0 by default
+    fn select_block_codec(&self, _first_item: usize, _num_items: usize) -> u8 {
+        0
+    }
+
+    /// Compresses a single block with the given codec (see `compress_block`)
+    /// 
+    /// The default implementation ignores the codec.
+    fn compress_block_with_codec(&mut self, block: &[u8], _codec: u8) -> usize {
+        self.compress_block(block)
+    }
+
+    /// Decompresses a single block compressed with the given codec (see `decompress_block`)
+    /// 
+    /// The default implementation ignores the codec.
+    fn decompress_block_with_codec(&self, compressed_data: &[u8], uncompressed_size: usize, _codec: u8, buffer: &mut [u8]) {
+        self.decompress_block(compressed_data, uncompressed_size, buffer);
+    }
+
     /// Decompresses a block to the internal cache for efficient repeated access
     /// 
     /// Decompresses the specified block and stores it in an internal cache
     /// for efficient repeated access to items within the block. Implements
     /// block-level caching to amortize decompression costs during sequential
-    /// or clustered random access patterns.
+    /// or clustered random access patterns. The cache must be sized using
+    /// `get_max_block_size`, as jumbo blocks exceed the block size.
+    /// 
+    /// Implementations verify the block (see `verify_block`) when they decompress
+    /// it, and skip both on cache hits, so that checksums cost once per decompression
+    /// rather than once per access.
     /// 
     /// # Arguments
     /// - `block_index`: Index of the block to decompress and cache
@@ -168,6 +906,9 @@ pub trait BlockCompressor: Compressor {
     /// Byte slice containing the cached decompressed block data
     fn get_block_cache(&self) -> &[u8];
 
+    /// Provides the scratch buffers of batch access (see `AccessScratch`)
+    fn get_access_scratch_mut(&mut self) -> &mut AccessScratch;
+
     /// Returns the total number of compressed blocks
     /// 
     /// # Returns
@@ -176,17 +917,200 @@ pub trait BlockCompressor: Compressor {
     fn get_num_blocks(&self) -> usize {
         self.get_blocks_metadata().len()
     }
+
+    /// Returns the range of the compressed data holding a block
+    /// 
+    /// Duplicate blocks share the range of the first identical block.
+    /// 
+    /// # Arguments
+    /// - `block_index`: Index of the block
+    #[inline(always)]
+    fn get_compressed_block_range(&self, block_index: usize) -> Range<usize> {
+        let block = &self.get_blocks_metadata()[block_index];
+        block.start_position..block.end_position
+    }
+
+    /// Reports the blocks stored as references to an identical earlier block
+    /// 
+    /// # Returns
+    /// Number of duplicate blocks and compressed bytes saved by not storing them
+    fn get_block_dedup_stats(&self) -> BlockDedupStats {
+        let mut stats = BlockDedupStats::default();
+        let mut stored_ranges = FxHashSet::default();
+        for block in self.get_blocks_metadata() {
+            if !stored_ranges.insert((block.start_position, block.end_position)) {
+                stats.duplicate_blocks += 1;
+                stats.saved_bytes += block.end_position - block.start_position;
+            }
+        }
+        stats
+    }
+
+    /// Orders blocks so that blocks accessed one after the other in a query trace are adjacent
+    /// 
+    /// Blocks are chained greedily: starting from the most accessed block not yet
+    /// placed, the chain repeatedly continues with the unplaced block most often
+    /// accessed right before or after the last one, and a new chain starts when
+    /// there is none. Blocks never accessed come last, in their original order.
+    /// 
+    /// # Arguments
+    /// - `queries`: Item IDs of the query trace, in access order
+    /// 
+    /// # Returns
+    /// Block indices in layout order (see `reorder_blocks`)
+    fn co_access_block_order(&self, queries: &[usize]) -> Vec<usize> {
+        let n_blocks = self.get_num_blocks();
+        let accessed_blocks: Vec<usize> = queries.iter().map(|&query| self.get_block_index(query)).collect();
+
+        let mut frequencies = vec![0usize; n_blocks];
+        for &block in accessed_blocks.iter() {
+            frequencies[block] += 1;
+        }
+        let mut co_accesses: FxHashMap<(usize, usize), usize> = FxHashMap::default();
+        for pair in accessed_blocks.windows(2) {
+            if pair[0] != pair[1] {
+                *co_accesses.entry((pair[0].min(pair[1]), pair[0].max(pair[1]))).or_insert(0) += 1;
+            }
+        }
+
+        // Neighbors of each block, by decreasing number of co-accesses
+        let mut neighbors: Vec<Vec<(usize, usize)>> = vec![Vec::new(); n_blocks];
+        for (&(a, b), &count) in co_accesses.iter() {
+            neighbors[a].push((count, b));
+            neighbors[b].push((count, a));
+        }
+        for block_neighbors in neighbors.iter_mut() {
+            block_neighbors.sort_unstable_by(|x, y| y.cmp(x));
+        }
+
+        let mut by_frequency: Vec<usize> = (0..n_blocks).collect();
+        by_frequency.sort_by_key(|&block| (Reverse(frequencies[block]), block));
+
+        let mut placed = vec![false; n_blocks];
+        let mut order = Vec::with_capacity(n_blocks);
+        for &seed in by_frequency.iter() {
+            let mut current = seed;
+            while !placed[current] {
+                placed[current] = true;
+                order.push(current);
+                if let Some(&(_, next)) = neighbors[current].iter().find(|&&(_, block)| !placed[block]) {
+                    current = next;
+                }
+            }
+        }
+        order
+    }
+
+    /// Moves the compressed blocks to the given layout order
+    /// 
+    /// Only the placement of the compressed data changes: blocks keep their index
+    /// and items, and their metadata is updated to the new ranges. Duplicate blocks
+    /// keep sharing their compressed data.
+    /// 
+    /// # Arguments
+    /// - `order`: Permutation of the block indices (e.g., `co_access_block_order`)
+    fn reorder_blocks(&mut self, order: &[usize]) {
+        assert_eq!(order.len(), self.get_num_blocks(), "Block order must be a permutation of the blocks");
+
+        let compressed_data = self.get_compressed_data();
+        let mut reordered = Vec::with_capacity(compressed_data.len());
+        let mut new_starts: FxHashMap<(usize, usize), usize> = FxHashMap::default();
+        for &block_index in order {
+            let range = self.get_compressed_block_range(block_index);
+            new_starts.entry((range.start, range.end)).or_insert_with(|| {
+                reordered.extend_from_slice(&compressed_data[range.clone()]);
+                reordered.len() - range.len()
+            });
+        }
+        assert_eq!(reordered.len(), compressed_data.len(), "Block order must cover every stored block");
+
+        for block in self.get_blocks_metadata_mut().iter_mut() {
+            let start_position = new_starts[&(block.start_position, block.end_position)];
+            block.end_position = start_position + (block.end_position - block.start_position);
+            block.start_position = start_position;
+        }
+        *self.get_compressed_data_mut() = reordered;
+    }
+
+    /// Returns the number of items in each block
+    /// 
+    /// Summarize with `MetricSummary::from_samples` to study the distribution of
+    /// items per block under different packing policies.
+    /// 
+    /// # Returns
+    /// Vector with the number of items of each block, in block order
+    fn get_items_per_block(&self) -> Vec<usize> {
+        let mut previous_psum = 0;
+        self.get_blocks_metadata()
+            .iter()
+            .map(|block| {
+                let num_items = block.num_items_psum - previous_psum;
+                previous_psum = block.num_items_psum;
+                num_items
+            })
+            .collect()
+    }
     
+    /// Reports the sizes and item count of each block (see `block_stats`)
+    /// 
+    /// Duplicate blocks report the compressed size of the data they share with the
+    /// first identical block (see `get_block_dedup_stats`).
+    /// 
+    /// # Returns
+    /// Statistics of each block, in block order
+    fn get_block_stats(&self) -> Vec<BlockStats> {
+        self.get_blocks_metadata()
+            .iter()
+            .zip(self.get_items_per_block())
+            .map(|(block, n_items)| BlockStats {
+                compressed_size: block.end_position - block.start_position,
+                uncompressed_size: block.uncompressed_size as usize,
+                n_items,
+            })
+            .collect()
+    }
+
+    /// Reports the limits of block-based compressors
+    /// 
+    /// Uncompressed block sizes are stored as 32-bit signed integers, and a string
+    /// longer than the block size forms a block of its own.
+    fn capacity(&self) -> Capacity {
+        Capacity { max_item_size: i32::MAX as usize, ..Capacity::UNLIMITED }
+    }
+
     /// Default implementation of compression for block-based algorithms
     /// 
     /// Divides the input data into blocks and compresses each block independently.
     /// Automatically handles block boundaries and maintains metadata for efficient
-    /// random access.
+    /// random access. Items larger than the block size are stored alone in a
+    /// dedicated jumbo block, which is never shared with other items.
+    /// 
+    /// Blocks identical to an earlier block compressed with the same codec are
+    /// stored once: their metadata references the compressed data of the first
+    /// occurrence, so random access is unchanged (see `get_block_dedup_stats`).
     /// 
     /// # Arguments
     /// - `data`: Raw byte array containing concatenated strings
     /// - `end_positions`: Boundary positions for individual strings (cumulative lengths)
     fn compress(&mut self, data: &[u8], end_positions: &[usize]) {
+        let mut cutter = self.start_blocks(data, end_positions);
+        self.compress_blocks(&mut cutter, usize::MAX);
+    }
+
+    /// Prepares the compression of the input dataset into blocks
+    /// 
+    /// Blocks are then compressed by `compress_blocks`, in as many calls as needed
+    /// (see `incremental`).
+    /// 
+    /// # Arguments
+    /// - `data`: Raw byte array containing concatenated strings
+    /// - `end_positions`: Boundary positions for individual strings (cumulative lengths)
+    /// 
+    /// # Returns
+    /// The position of the block compression, before the first item
+    fn start_blocks<'a>(&mut self, data: &'a [u8], end_positions: &'a [usize]) -> BlockCutter<'a> {
+        BlockCompressor::capacity(self).assert_fits(end_positions, self.name());
+
         // Copy end_positions to self.item_end_positions
         unsafe {
             let item_end_positions = self.get_item_end_positions_mut();
@@ -196,51 +1120,146 @@ pub trait BlockCompressor: Compressor {
             std::ptr::copy_nonoverlapping(src, dst, end_positions.len());
         }
 
+        BlockCutter {
+            data,
+            end_positions,
+            next_item: 1,
+            block_start: 0,
+            num_items_in_block: 0,
+            current_block_size: 0,
+            item_start: 0,
+            unique_blocks: FxHashMap::default(),
+        }
+    }
+
+    /// Compresses the next blocks of a block compression started by `start_blocks`
+    /// 
+    /// # Arguments
+    /// - `cutter`: Position of the block compression, advanced past the compressed blocks
+    /// - `max_blocks`: Maximum number of blocks to compress (at least 1)
+    /// 
+    /// # Returns
+    /// `Progress::Done` once the last block is compressed, `Progress::Continue` otherwise
+    fn compress_blocks(&mut self, cutter: &mut BlockCutter, max_blocks: usize) -> Progress {
         let block_size = self.get_block_size();
-        let mut block_start = 0;            // Start of the current block
-        let mut num_items_in_block = 0;     // Number of items in the current block
-        let mut current_block_size = 0;     // Total size of the current block
-        let mut item_start = 0;             // Start of the current item
+        let block_packing = self.get_block_packing();
+        let data = cutter.data;
+        let mut num_blocks = 0;
 
-        for &item_end in end_positions.iter().skip(1) {
-            let item_size = item_end - item_start;
+        while cutter.next_item < cutter.end_positions.len() {
+            let item_end = cutter.end_positions[cutter.next_item];
+            let item_size = item_end - cutter.item_start;
             
-            if current_block_size + item_size > block_size {
-                let block = &data[block_start..item_start];
-                let compressed_block_size = self.compress_block(block);
-
-                let end_position = self.get_blocks_metadata().last().map_or(0, |m| m.end_position) + compressed_block_size;
-                let num_items_psum = self.get_blocks_metadata().last().map_or(0, |meta| meta.num_items_psum) + num_items_in_block;
-
-                self.get_blocks_metadata_mut().push(BlockMetadata {
-                    end_position,
-                    num_items_psum,
-                    uncompressed_size: block.len() as i32,
-                });
-
-                block_start = item_start;
-                num_items_in_block = 0;
-                current_block_size = 0;
+            if cutter.num_items_in_block > 0 
+                && block_packing.should_cut(block_size, cutter.current_block_size, cutter.num_items_in_block, item_size) 
+            {
+                if num_blocks == max_blocks {
+                    return Progress::Continue;
+                }
+                self.append_block(&data[cutter.block_start..cutter.item_start], cutter.num_items_in_block, &mut cutter.unique_blocks);
+                num_blocks += 1;
+                cutter.block_start = cutter.item_start;
+                cutter.num_items_in_block = 0;
+                cutter.current_block_size = 0;
             }
 
-            current_block_size += item_size;
-            num_items_in_block += 1;
-            item_start = item_end;
+            cutter.current_block_size += item_size;
+            cutter.num_items_in_block += 1;
+            cutter.item_start = item_end;
+            cutter.next_item += 1;
         }
 
-        if num_items_in_block > 0 {
-            let block = &data[block_start..item_start];
-            let compressed_block_size = self.compress_block(block);
+        if cutter.num_items_in_block > 0 {
+            if num_blocks == max_blocks {
+                return Progress::Continue;
+            }
+            self.append_block(&data[cutter.block_start..cutter.item_start], cutter.num_items_in_block, &mut cutter.unique_blocks);
+            cutter.num_items_in_block = 0;
+        }
 
-            let end_position = self.get_blocks_metadata().last().map_or(0, |m| m.end_position) + compressed_block_size;
-            let num_items_psum = self.get_blocks_metadata().last().map_or(0, |meta| meta.num_items_psum) + num_items_in_block;  // Cumulative number of items
+        let stats = self.get_block_dedup_stats();
+        if stats.duplicate_blocks > 0 {
+            info!(
+                compressor = self.name(),
+                duplicate_blocks = stats.duplicate_blocks,
+                num_blocks = self.get_num_blocks(),
+                saved_bytes = stats.saved_bytes,
+                "Identical blocks deduplicated"
+            );
+        }
+        Progress::Done
+    }
 
-            self.get_blocks_metadata_mut().push(BlockMetadata {
-                end_position,
+    /// Compresses a block and records its metadata
+    /// 
+    /// A block identical to an earlier one with the same codec is not compressed
+    /// again, and references the compressed data of the earlier block.
+    /// 
+    /// # Arguments
+    /// - `block`: Uncompressed content of the block
+    /// - `num_items_in_block`: Number of items in the block
+    /// - `unique_blocks`: Index of the first block with each content and codec
+    fn append_block<'a>(&mut self, block: &'a [u8], num_items_in_block: usize, unique_blocks: &mut FxHashMap<(&'a [u8], u8), usize>) {
+        assert!(block.len() <= i32::MAX as usize, "Block of {} bytes exceeds the maximum block size", block.len());
+
+        let first_item = self.get_blocks_metadata().last().map_or(0, |meta| meta.num_items_psum);
+        let codec = self.select_block_codec(first_item, num_items_in_block);
+        let num_items_psum = first_item + num_items_in_block;  // Cumulative number of items
+        let block_index = self.get_blocks_metadata().len();
+
+        if let Some(&original) = unique_blocks.get(&(block, codec)) {
+            let original_metadata = &self.get_blocks_metadata()[original];
+            let metadata = BlockMetadata {
+                start_position: original_metadata.start_position,
+                end_position: original_metadata.end_position,
                 num_items_psum,
                 uncompressed_size: block.len() as i32,
-            });
+                checksum: original_metadata.checksum,
+                codec,
+            };
+            self.get_blocks_metadata_mut().push(metadata);
+            debug!(block_index, num_items = num_items_in_block, original, "Block deduplicated");
+            return;
         }
+        unique_blocks.insert((block, codec), block_index);
+
+        let checksum = self.get_checksum_algorithm().map_or(0, |algorithm| algorithm.checksum(block));
+        let start_position = self.get_compressed_data().len();
+        let compressed_block_size = self.compress_block_with_codec(block, codec);
+        let end_position = start_position + compressed_block_size;
+
+        self.get_blocks_metadata_mut().push(BlockMetadata {
+            start_position,
+            end_position,
+            num_items_psum,
+            uncompressed_size: block.len() as i32,
+            checksum,
+            codec,
+        });
+        debug!(
+            block_index,
+            num_items = num_items_in_block,
+            uncompressed_size = block.len(),
+            compressed_size = compressed_block_size,
+            codec,
+            "Block compressed"
+        );
+    }
+
+    /// Returns the largest uncompressed block size
+    /// 
+    /// Exceeds the block size when jumbo blocks are present; block caches must be
+    /// able to hold this many bytes.
+    /// 
+    /// # Returns
+    /// Maximum uncompressed size over all blocks, and at least the block size
+    fn get_max_block_size(&self) -> usize {
+        self.get_blocks_metadata()
+            .iter()
+            .map(|block| block.uncompressed_size as usize)
+            .max()
+            .unwrap_or(0)
+            .max(self.get_block_size())
     }
 
     /// Decompresses all blocks to reconstruct the original dataset
@@ -257,17 +1276,60 @@ pub trait BlockCompressor: Compressor {
         let mut total_size = 0;
 
         for (i, block_metadata) in self.get_blocks_metadata().iter().enumerate() {
-            let start = if i == 0 { 0 } else { self.get_blocks_metadata()[i - 1].end_position };
-            let end = block_metadata.end_position;
-
-            let compressed_data = &self.get_compressed_data()[start..end];
-            self.decompress_block(compressed_data, block_metadata.uncompressed_size as usize, buffer[total_size..].as_mut());
-            total_size += block_metadata.uncompressed_size as usize;
+            let compressed_data = &self.get_compressed_data()[self.get_compressed_block_range(i)];
+            let uncompressed_size = block_metadata.uncompressed_size as usize;
+            self.decompress_block_with_codec(compressed_data, uncompressed_size, block_metadata.codec, buffer[total_size..].as_mut());
+            self.verify_block(i, &buffer[total_size..total_size + uncompressed_size]);
+            total_size += uncompressed_size;
         }
 
         total_size
     }
 
+    /// Decompresses all blocks to a writer, one block at a time
+    /// 
+    /// Memory is bounded by the largest block (see `get_max_block_size`).
+    /// 
+    /// # Arguments
+    /// - `writer`: Destination of the decompressed data
+    /// 
+    /// # Returns
+    /// Number of bytes written, or the first error returned by the writer
+    fn decompress_to<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let mut block_buffer = vec![0; self.get_max_block_size() + BUFFER_PADDING];
+        let mut total_size = 0;
+
+        for (i, block_metadata) in self.get_blocks_metadata().iter().enumerate() {
+            let compressed_data = &self.get_compressed_data()[self.get_compressed_block_range(i)];
+            let uncompressed_size = block_metadata.uncompressed_size as usize;
+            self.decompress_block_with_codec(compressed_data, uncompressed_size, block_metadata.codec, &mut block_buffer);
+            self.verify_block(i, &block_buffer[..uncompressed_size]);
+            writer.write_all(&block_buffer[..uncompressed_size])?;
+            total_size += uncompressed_size;
+        }
+
+        writer.flush()?;
+        Ok(total_size)
+    }
+
+    /// Default space breakdown for block-based algorithms
+    /// 
+    /// Reports compressed blocks as payload, together with the item end positions
+    /// and block metadata used for random access. The block cache is excluded.
+    /// 
+    /// # Returns
+    /// Space usage of payload, item offsets and block metadata
+    fn space_breakdown(&self) -> SpaceBreakdown {
+        SpaceBreakdown {
+            payload: self.get_compressed_data().len(),
+            dictionary: 0,
+            item_offsets: std::mem::size_of_val(self.get_item_end_positions()),
+            block_metadata: self.get_blocks_metadata().len() * std::mem::size_of::<BlockMetadata>(),
+            id_map: 0,
+            checksums: 0,
+        }
+    }
+
     /// Retrieves a single string by index with optimized random access
     /// 
     /// Locates the block containing the requested string, decompresses only
@@ -298,6 +1360,199 @@ pub trait BlockCompressor: Compressor {
         item_size
     }
 
+    /// Retrieves a single string, attributing its latency to access stages
+    /// 
+    /// Block search and item delimiters are charged to lookup, block decompression
+    /// (a no-op on cache hits) and verification to decoding, and the extraction from
+    /// the block cache to copying.
+    /// 
+    /// # Arguments
+    /// * `index` - Zero-based index of the string to retrieve
+    /// * `buffer` - Output buffer for the decompressed string
+    /// * `timer` - Timer charged with the ticks of each stage
+    /// 
+    /// # Returns
+    /// Number of bytes written to the buffer
+    fn get_item_at_profiled(&mut self, index: usize, buffer: &mut [u8], timer: &mut StageTimer) -> usize {
+        let block_index = self.get_block_index(index);
+        let (item_start, item_end) = self.get_item_delimiters(block_index, index);
+        timer.lap(Stage::Lookup);
+
+        self.decompress_block_to_cache(block_index);
+        timer.lap(Stage::Decode);
+
+        let item_size = item_end - item_start;
+        buffer[..item_size].copy_from_slice(&self.get_block_cache()[item_start..item_end]);
+        timer.lap(Stage::Copy);
+        item_size
+    }
+
+    /// Retrieves a single string as a slice of the block cache, without copying it
+    /// 
+    /// The block holding the string is decompressed (and verified) as in `get_item_at`,
+    /// so that repeated accesses to the cached block cost a lookup only.
+    /// 
+    /// # Arguments
+    /// * `index` - Zero-based index of the string to retrieve
+    /// 
+    /// # Returns
+    /// The string, valid until the next access
+    #[inline(always)]
+    fn get_item_ref(&mut self, index: usize) -> Option<&[u8]> {
+        let block_index = self.get_block_index(index);
+        self.decompress_block_to_cache(block_index);
+
+        let (item_start, item_end) = self.get_item_delimiters(block_index, index);
+        Some(&self.get_block_cache()[item_start..item_end])
+    }
+
+    /// Retrieves a batch of strings, decompressing each involved block once
+    /// 
+    /// Equivalent to `get_items_scheduled` with prefetching enabled.
+    /// 
+    /// # Arguments
+    /// * `indices` - Zero-based indices of the strings to retrieve, in output order
+    /// * `buffer` - Output buffer for all strings
+    /// * `item_ends` - Receives the end position of each string
+    /// 
+    /// # Returns
+    /// Number of bytes written to the buffer
+    fn get_items_at(&mut self, indices: &[usize], buffer: &mut [u8], item_ends: &mut [usize]) -> usize {
+        self.get_items_scheduled(indices, buffer, item_ends, true)
+    }
+
+    /// Retrieves a batch of strings in block order, writing them in user order
+    /// 
+    /// Queries are grouped by block, so that each block is decompressed (and
+    /// verified) once per batch however many of its strings are requested. Output
+    /// positions are known in advance from the item lengths, so strings are written
+    /// directly at their place in user order. Optionally, the compressed bytes of
+    /// the next block are prefetched while the strings of the current one are copied.
+    /// 
+    /// # Arguments
+    /// * `indices` - Zero-based indices of the strings to retrieve, in output order
+    /// * `buffer` - Output buffer for all strings
+    /// * `item_ends` - Receives the end position of each string
+    /// * `prefetch` - Whether to prefetch the compressed data of the next block
+    /// 
+    /// # Returns
+    /// Number of bytes written to the buffer
+    fn get_items_scheduled(&mut self, indices: &[usize], buffer: &mut [u8], item_ends: &mut [usize], prefetch: bool) -> usize {
+        let mut size = 0;
+        for (&index, item_end) in indices.iter().zip(item_ends.iter_mut()) {
+            size += BlockCompressor::len_of_item(self, index);
+            *item_end = size;
+        }
+
+        // Query order sorted by block, keeping user order within each block
+        let mut order = std::mem::take(&mut self.get_access_scratch_mut().block_order);
+        order.clear();
+        order.extend(indices.iter().enumerate().map(|(i, &index)| (self.get_block_index(index), i)));
+        order.sort_unstable();
+
+        let mut group_start = 0;
+        while group_start < order.len() {
+            let block_index = order[group_start].0;
+            let group_end = group_start + order[group_start..].iter().take_while(|&&(block, _)| block == block_index).count();
+            if prefetch && group_end < order.len() {
+                let next_block = order[group_end].0;
+                prefetch_bytes(&self.get_compressed_data()[self.get_compressed_block_range(next_block)]);
+            }
+
+            self.decompress_block_to_cache(block_index);
+
+            for &(_, i) in order[group_start..group_end].iter() {
+                let (item_start, item_end) = self.get_item_delimiters(block_index, indices[i]);
+                let output_start = item_ends[i] - (item_end - item_start);
+                buffer[output_start..item_ends[i]].copy_from_slice(&self.get_block_cache()[item_start..item_end]);
+            }
+            group_start = group_end;
+        }
+
+        self.get_access_scratch_mut().block_order = order;
+
+        size
+    }
+
+    /// Visits a range of strings block by block (see `Compressor::scan_range`)
+    /// 
+    /// Each block overlapping the range is decompressed (and verified) once into the
+    /// block cache, and its strings are projected from there without copying.
+    /// 
+    /// # Arguments
+    /// * `start_index` - Index of the first string to visit
+    /// * `end_index` - Index past the last string to visit
+    /// * `projector` - Called with the index and the content of each string
+    fn scan_range<P: FnMut(usize, &[u8])>(&mut self, start_index: usize, end_index: usize, mut projector: P) {
+        let mut index = start_index;
+        while index < end_index {
+            let block_index = self.get_block_index(index);
+            self.decompress_block_to_cache(block_index);
+
+            let block_end = self.get_blocks_metadata()[block_index].num_items_psum.min(end_index);
+            for item_index in index..block_end {
+                let (item_start, item_end) = self.get_item_delimiters(block_index, item_index);
+                projector(item_index, &self.get_block_cache()[item_start..item_end]);
+            }
+            index = block_end;
+        }
+    }
+
+    /// Reports the length of a string from the item boundaries, without touching block data
+    /// 
+    /// # Arguments
+    /// * `index` - Zero-based index of the string
+    /// 
+    /// # Returns
+    /// Length of the string in bytes
+    #[inline(always)]
+    fn len_of_item(&self, index: usize) -> usize {
+        let item_positions = self.get_item_end_positions();
+        item_positions[index + 1] - item_positions[index]
+    }
+
+    /// Reports the length of the longest string from the item boundaries (see `Compressor::max_item_len`)
+    fn max_item_len(&self) -> usize {
+        self.get_item_end_positions().windows(2).map(|w| w[1] - w[0]).max().unwrap_or(0)
+    }
+
+    /// Finds the string holding a byte offset of the uncompressed data (see `Compressor::offset_to_item`)
+    fn offset_to_item(&self, offset: usize) -> Option<(usize, usize)> {
+        item_at_offset(self.get_item_end_positions(), offset)
+    }
+
+    /// Reports the position of a string within its block (see `Compressor::block_position`)
+    fn block_position(&self, index: usize) -> Option<BlockPosition> {
+        let block_index = self.get_block_index(index);
+        let first_item = if block_index == 0 { 0 } else { self.get_blocks_metadata()[block_index - 1].num_items_psum };
+        let block_size = self.get_blocks_metadata()[block_index].num_items_psum - first_item;
+        Some(BlockPosition::of(index - first_item, block_size))
+    }
+
+    /// Finds the block holding a byte offset of the uncompressed data
+    /// 
+    /// # Returns
+    /// Index of the block, or None if the offset is past the end of the data
+    fn offset_to_block(&self, offset: usize) -> Option<usize> {
+        BlockCompressor::offset_to_item(self, offset).map(|(index, _)| self.get_block_index(index))
+    }
+
+    /// Verifies a decompressed block against its stored checksum
+    /// 
+    /// Does nothing if checksums are disabled; panics on mismatch.
+    /// 
+    /// # Arguments
+    /// * `block_index` - Index of the block
+    /// * `block` - Decompressed content of the block
+    #[inline(always)]
+    fn verify_block(&self, block_index: usize, block: &[u8]) {
+        if let Some(algorithm) = self.get_checksum_algorithm() {
+            if algorithm.checksum(block) != self.get_blocks_metadata()[block_index].checksum {
+                panic!("Checksum mismatch in block {} for compressor: {}", block_index, self.name());
+            }
+        }
+    }
+
     /// Finds the block index containing the specified string
     /// 
     /// Uses binary search on cumulative item counts to efficiently locate
@@ -357,3 +1612,48 @@ pub trait BlockCompressor: Compressor {
         (start - adjustment, end - adjustment)
     }
 }
+
+/// Extended trait for dictionary-based compressors whose dictionary can be shared
+/// 
+/// Splits compression into dictionary training and parsing, so that a dictionary
+/// trained once over samples of several datasets (shards) can compress each of them
+/// independently. This models distributed stores deploying dictionaries fleet-wide.
+pub trait DictionaryCompressor: Compressor {
+    /// Trained dictionary, shared by the compressors of all shards
+    type Dictionary;
+
+    /// Trains a dictionary with the parameters of this compressor
+    /// 
+    /// The compressor itself is left untouched.
+    /// 
+    /// # Arguments
+    /// - `data`: Concatenated training strings (e.g., samples of every shard)
+    /// - `end_positions`: Boundary positions of the training strings
+    /// 
+    /// # Returns
+    /// Dictionary able to compress any string, including bytes absent from the training data
+    fn train_dictionary(&self, data: &[u8], end_positions: &[usize]) -> Self::Dictionary;
+
+    /// Compresses the input dataset with a trained dictionary instead of training one
+    /// 
+    /// The compressor keeps its own copy of the dictionary, which is reported in
+    /// `space_breakdown` as for regular compression.
+    /// 
+    /// # Arguments
+    /// - `data`: Concatenated string data as byte array
+    /// - `end_positions`: Boundary positions for individual strings (cumulative lengths)
+    /// - `dictionary`: Dictionary returned by `train_dictionary`
+    fn compress_with_dictionary(&mut self, data: &[u8], end_positions: &[usize], dictionary: &Self::Dictionary);
+
+    /// Reports the dictionary in use and how the compressed strings use it
+    /// 
+    /// Token frequencies and adjacencies are counted over the compressed strings, so
+    /// the compressor must hold compressed data.
+    fn dictionary_report(&self) -> DictionaryReport;
+
+    /// Summarizes the dictionary in use and how the compressed strings use it
+    /// 
+    /// Lighter than `dictionary_report`: tokens are neither copied nor paired. The
+    /// compressor must hold compressed data.
+    fn dictionary_stats(&self) -> DictionaryStats;
+}
@@ -0,0 +1,175 @@
+//! Compact string boundaries for the raw baseline
+//!
+//! The raw baseline stores one machine word per string boundary: on datasets of short
+//! strings, the offsets outweigh the strings themselves, and the compression rate of the
+//! baseline measures its offsets rather than the strings. `CodedOffsets` stores the
+//! boundaries either bit-packed, with the width of the largest one, or Elias-Fano coded,
+//! with about `2 + log2(data_size / n_strings)` bits per string, so that the baseline
+//! isolates the cost of copying strings and the metadata of each compressor can be read
+//! against it.
+//!
+//! Elias-Fano splits each boundary into low bits, stored packed, and high bits, stored in
+//! unary as the gaps between set bits of a bit vector. The position of every
+//! `SELECT_SAMPLE_RATE`-th set bit is kept, so that locating a boundary scans at most
+//! `SELECT_SAMPLE_RATE` set bits.
+
+use crate::bit_vector::{BitVector, UnaryIter};
+
+/// Set bits of the high part between two select samples
+const SELECT_SAMPLE_RATE: usize = 256;
+
+/// Storage of the string boundaries of the raw baseline
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum OffsetCoding {
+    Plain,      // One machine word per boundary
+    BitPacked,  // Fixed-width boundaries, as wide as the data size
+    EliasFano,  // Elias-Fano coded boundaries with sampled select
+}
+
+impl OffsetCoding {
+    /// Returns the coding identifier
+    pub fn name(&self) -> &'static str {
+        match self {
+            OffsetCoding::Plain => "plain",
+            OffsetCoding::BitPacked => "bit_packed",
+            OffsetCoding::EliasFano => "elias_fano",
+        }
+    }
+
+    /// Parses a coding from its identifier
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "plain" => Some(OffsetCoding::Plain),
+            "bit_packed" => Some(OffsetCoding::BitPacked),
+            "elias_fano" => Some(OffsetCoding::EliasFano),
+            _ => None,
+        }
+    }
+}
+
+/// String boundaries, bit-packed or Elias-Fano coded
+pub struct CodedOffsets {
+    coding: OffsetCoding,
+    n_offsets: usize,               // Boundaries, one more than the strings
+    width: usize,                   // Bits per packed boundary, or low bits per Elias-Fano boundary
+    values: BitVector,              // Packed boundaries, or Elias-Fano low bits
+    high_bits: BitVector,           // Elias-Fano high bits in unary (empty when bit-packed)
+    select_samples: Vec<usize>,     // Position of every SELECT_SAMPLE_RATE-th set bit of `high_bits`
+}
+
+impl CodedOffsets {
+    /// Codes the boundaries of a dataset
+    ///
+    /// # Arguments
+    /// - `end_positions`: Non-decreasing string boundary positions, starting with 0
+    /// - `coding`: Bit-packed or Elias-Fano coding
+    pub fn new(end_positions: &[usize], coding: OffsetCoding) -> Self {
+        assert!(coding != OffsetCoding::Plain, "Plain offsets are not coded");
+        let n_offsets = end_positions.len();
+        let universe = end_positions.last().copied().unwrap_or(0);
+
+        let width = match coding {
+            OffsetCoding::EliasFano if universe > n_offsets => (universe / n_offsets).ilog2() as usize,
+            OffsetCoding::EliasFano => 0,
+            _ => bit_width(universe),
+        };
+        let low_mask = if width == 64 { u64::MAX } else { (1u64 << width) - 1 };
+
+        let mut values = BitVector::with_capacity(n_offsets * width);
+        for &position in end_positions {
+            values.append_bits(position as u64 & low_mask, width);
+        }
+        values.shrink_to_fit();
+
+        let mut high_bits = BitVector::new();
+        let mut select_samples = Vec::new();
+        if coding == OffsetCoding::EliasFano {
+            high_bits = BitVector::with_zeroes((universe >> width) + n_offsets);
+            for (i, &position) in end_positions.iter().enumerate() {
+                let bit = (position >> width) + i;
+                high_bits.set(bit, true);
+                if i % SELECT_SAMPLE_RATE == 0 {
+                    select_samples.push(bit);
+                }
+            }
+            select_samples.shrink_to_fit();
+        }
+
+        CodedOffsets { coding, n_offsets, width, values, high_bits, select_samples }
+    }
+
+    /// Returns the boundary at the given position
+    #[inline(always)]
+    pub fn get(&self, index: usize) -> usize {
+        debug_assert!(index < self.n_offsets);
+        if self.coding == OffsetCoding::BitPacked {
+            return unsafe { self.values.get_bits_unchecked(index * self.width, self.width) as usize };
+        }
+        let mut cursor = self.high_cursor(index);
+        self.elias_fano_value(index, cursor.select1(index % SELECT_SAMPLE_RATE).unwrap())
+    }
+
+    /// Returns the byte range `[start, end)` of the given string
+    #[inline(always)]
+    pub fn bounds(&self, index: usize) -> (usize, usize) {
+        if self.coding == OffsetCoding::BitPacked {
+            return (self.get(index), self.get(index + 1));
+        }
+        // The end of a string is the next set bit after its start
+        let mut cursor = self.high_cursor(index);
+        let start = cursor.select1(index % SELECT_SAMPLE_RATE).unwrap();
+        let end = cursor.next1().unwrap();
+        (self.elias_fano_value(index, start), self.elias_fano_value(index + 1, end))
+    }
+
+    /// Finds the string holding a byte offset (see `Compressor::offset_to_item`)
+    pub fn find(&self, offset: usize) -> Option<(usize, usize)> {
+        if self.n_offsets < 2 || offset >= self.get(self.n_offsets - 1) {
+            return None;
+        }
+        // Last boundary at or before the offset, skipping empty strings
+        let (mut low, mut high) = (0, self.n_offsets - 1);
+        while high - low > 1 {
+            let middle = low + (high - low) / 2;
+            if self.get(middle) <= offset {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        Some((low, offset - self.get(low)))
+    }
+
+    /// Returns the number of boundaries
+    pub fn len(&self) -> usize {
+        self.n_offsets
+    }
+
+    /// Returns whether no boundary is stored
+    pub fn is_empty(&self) -> bool {
+        self.n_offsets == 0
+    }
+
+    /// Returns the space used by the boundaries and the select samples in bytes
+    pub fn size_bytes(&self) -> usize {
+        self.values.len().div_ceil(8) + self.high_bits.len().div_ceil(8) + self.select_samples.len() * std::mem::size_of::<usize>()
+    }
+
+    /// Positions a cursor on the high bits at the select sample preceding a boundary
+    #[inline(always)]
+    fn high_cursor(&self, index: usize) -> UnaryIter<'_> {
+        UnaryIter::new(&self.high_bits, self.select_samples[index / SELECT_SAMPLE_RATE])
+    }
+
+    /// Decodes an Elias-Fano boundary from the position of its set bit in the high bits
+    #[inline(always)]
+    fn elias_fano_value(&self, index: usize, high_position: usize) -> usize {
+        let low = unsafe { self.values.get_bits_unchecked(index * self.width, self.width) as usize };
+        ((high_position - index) << self.width) | low
+    }
+}
+
+/// Returns the number of bits needed to represent a value
+fn bit_width(value: usize) -> usize {
+    (usize::BITS - value.leading_zeros()) as usize
+}
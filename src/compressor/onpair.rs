@@ -1,32 +1,63 @@
-use super::Compressor;
+use super::{Capacity, Compressor, StreamBuffer, MAX_U32_OFFSET};
 use onpair_rs::OnPair;
+use std::io::{self, Write};
 
 /// OnPair compressor with unlimited token length
 /// 
 /// Core implementation of the OnPair algorithm supporting arbitrary-length tokens.
 pub struct OnPairCompressor {
     onpair: OnPair,
+    max_item_size: usize,   // Size of the longest string, bounding streaming writes
+    item_lengths: Vec<u32>, // Length of each string, not exposed by the library
 }
 
 impl Compressor for OnPairCompressor {
     fn new(data_size: usize, n_elements: usize) -> Self {
         let onpair = OnPair::with_capacity(data_size, n_elements);
-        OnPairCompressor { onpair }
+        OnPairCompressor { onpair, max_item_size: 0, item_lengths: Vec::new() }
     }
 
     fn compress(&mut self, data: &[u8], end_positions: &[usize]) {
+        self.capacity().assert_fits(end_positions, self.name());
         self.onpair.compress_bytes(data, end_positions);
+        self.item_lengths = end_positions.windows(2).map(|w| (w[1] - w[0]) as u32).collect();
+        self.max_item_size = self.item_lengths.iter().max().map_or(0, |&length| length as usize);
     }
 
     fn decompress(&self, buffer: &mut [u8]) -> usize {
         self.onpair.decompress_all(buffer)
     }
 
+    fn decompress_to<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        // The library decodes whole strings only, so strings are flushed one at a time
+        let mut stream = StreamBuffer::new(writer);
+        for index in 0..self.item_lengths.len() {
+            let size = self.onpair.decompress_string(index, stream.reserve(self.max_item_size)?);
+            stream.commit(size);
+        }
+        stream.finish()
+    }
+
     fn get_item_at(&mut self, index: usize, buffer: &mut [u8]) -> usize {
         self.onpair.decompress_string(index, buffer)
     }
 
+    fn len_of_item(&self, index: usize) -> usize {
+        self.item_lengths[index] as usize
+    }
+
+    fn max_item_len(&self) -> usize {
+        self.max_item_size
+    }
+
+    /// Reports the limits of the lengths kept for `len_of_item` (32-bit)
+    fn capacity(&self) -> Capacity {
+        Capacity { max_item_size: MAX_U32_OFFSET, ..Capacity::UNLIMITED }
+    }
+
     fn space_used_bytes(&self) -> usize {
+        // The library encodes the string boundaries itself: lengths kept for
+        // `len_of_item` are bookkeeping and are not reported
         self.onpair.space_used()
     }
 
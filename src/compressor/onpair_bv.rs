@@ -6,143 +6,1049 @@
 //!
 //! Demonstrates the trade-off between dictionary size and compressed representation
 //! overhead in token-based compression schemes.
+//!
+//! On datasets using a small byte alphabet (e.g., DNA, digits), the initial tokens can be
+//! restricted to the bytes actually used, optionally extended with all pairs of them
+//! (superalphabet symbols), leaving more token IDs for longer dictionary entries.
+//!
+//! Training merges tokens while scanning the corpus once, so that tokens merged early
+//! may become useless once longer tokens cover them. The iterated variant runs extra
+//! passes: each one parses the corpus with the current dictionary, drops the merged
+//! tokens the parse does not use, and resumes training with the freed token IDs. The
+//! final parse always uses the final dictionary.
+//!
+//! Datasets often repeat whole strings (e.g., enumerations, URLs). The optional string
+//! cache maps each distinct string to the tokens of its first occurrence, so that repeats
+//! are encoded by copying those tokens instead of running longest prefix matching again.
+//! Repeats are encoded with the same tokens either way, so the compressed output and the
+//! random access path are unchanged: the cache only speeds up parsing, and its hit rate
+//! is logged and exposed by `string_cache_hit_rate`.
+//!
+//! Noisy data parses into many single-byte tokens, each taking a full token ID. With
+//! literal inlining (see `with_literal_inlining`), every token of the stream starts with
+//! an escape bit, followed by the ID of a single-byte token in 8 bits if set, or by the
+//! ID of a merged token otherwise: single-byte tokens take 9 bits, merged tokens one
+//! bit more than without inlining, and merged tokens get all `2^bits_per_token` IDs
+//! instead of sharing them with the single-byte tokens. Tokens are then variable-width,
+//! so that strings are located by bit positions, and tokens preceding a sampled token
+//! within a string (see `set_item_sample_interval`) are decoded to be skipped. The share
+//! of single-byte tokens is logged and exposed by `literal_token_stats`, with or without
+//! inlining.
+//!
+//! Longest prefix matching runs on a compacted trie of the tokens (see `prefix_trie`)
+//! rather than on the matcher of `onpair_rs`: every merged token extends an existing
+//! token, so that the trie stores the shared prefixes once. The memory of the matcher
+//! is logged once the dictionary is built and exposed by `matcher_stats`.
+//!
+//! The training parameters (merge threshold, maximum token length, dictionary capacity
+//! and fraction and sampling strategy of the training strings) are set at runtime by `OnPairConfig`,
+//! so that parameter studies need no recompilation. OnPair and OnPair16 fix these
+//! parameters inside the `onpair_rs` library, and cannot be configured.
+//!
+//! The dictionary itself can be stored front-coded (`dictionary=front_coded`, see
+//! `coded_dictionary`), trading access time for space on datasets where the dictionary
+//! is a large share of the compressed size: random access decodes tokens through a cache
+//! of hot tokens, and the size reduction of the dictionary is logged after compression.
+//! Likewise, the token offsets of the strings can be sampled (see `set_offset_sample_rate`),
+//! so that an access scans the token counts following the closest sampled offset, and
+//! so can the token positions within long strings (see `set_item_sample_interval`), so
+//! that range reads start from the closest sampled token.
+//!
+//! Decoding copies tokens with fixed-size copies, reading and writing past short tokens
+//! (the dictionary is padded, output buffers have slack). Unbounded tokens take a 16-byte
+//! copy, followed by a variable-length copy for longer tokens. Tokens bounded to 32 or
+//! 64 bytes (`onpair_bv32`, `onpair_bv64`) are instead copied with a single copy of the
+//! bound, spanning two vector registers, so that decoding never branches on token
+//! lengths: unlike OnPair16, whose 16-byte bound keeps long repeated phrases split into
+//! many tokens, longer phrases remain single tokens.
 
-use crate::bit_vector::BitVector;
-use onpair_rs::lpm::LongestPrefixMatcher;
-use super::Compressor;
+use crate::bit_vector::{BitReader, BitVector};
+use crate::profiler::{Stage, StageTimer};
+use super::bpe::copy_spans;
+use super::coded_dictionary::{CodedDictionary, DictionaryCoding};
+use super::dictionary_report::{DictionaryReport, DictionaryStats};
+use super::incremental::{CompressionSteps, Progress};
+use super::intra_item_index::IntraItemIndex;
+use super::length_index::{self, LengthIndex};
+use super::prefix_trie::{MatcherStats, PrefixTrie, MAX_TOKEN_ID};
+use super::sampling::{self, Sampling};
+use super::token_offsets::{with_offsets, Offset, TokenOffsets};
+use super::{max_len_of_items, Compressor, DictionaryCompressor, ItemBoundaries, LiteralTokenStats, SpaceBreakdown, StreamBuffer, MAX_TOKEN_LENGTH};
 use rustc_hash::FxHashMap;
-use rand::seq::SliceRandom;
-use rand::thread_rng;
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::ops::Range;
+use tracing::{debug, info, info_span};
 
-/// Bits per token ID for space-optimized encoding
-const BITS_PER_TOKEN: usize = 13;
-const MAX_TOKEN_ID: usize = (1 << BITS_PER_TOKEN) - 1; 
+/// Default bits per token ID for space-optimized encoding
+pub const DEFAULT_BITS_PER_TOKEN: usize = 13;
+/// Training passes over the corpus of the iterated variant
+pub const ITERATED_PASSES: usize = 3;
 /// Optimization constant for memory copy operations
 const FAST_ACCESS_SIZE: usize = 16;
+/// Widest fixed-size token copy, used when tokens are bounded to 64 bytes
+const MAX_COPY_WIDTH: usize = 64;
+/// Evictions free at least `1 / EVICTION_SHARE` of the dictionary budget at once
+const EVICTION_SHARE: usize = 16;
+/// Strings parsed per unit of work of an incremental compression
+const PARSE_STEP_ITEMS: usize = 64 * 1024;
+/// Bits of the ID of an inlined single-byte token, after its escape bit
+const LITERAL_BITS: usize = 8;
+
+/// Training parameters of OnPairBV
+/// 
+/// The default configuration derives the merge threshold from the data size, bounds
+/// tokens to `MAX_TOKEN_LENGTH` bytes, uses `DEFAULT_BITS_PER_TOKEN` bits per token ID
+/// trains on every string (sampled uniformly at random when sampling) and stores the
+/// dictionary plain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OnPairConfig {
+    /// Pair frequency triggering a merge; `None` derives it from the size of the training data
+    pub threshold: Option<usize>,
+    /// Maximum length of merged tokens in bytes, between 2 and `MAX_TOKEN_LENGTH`
+    pub max_token_length: usize,
+    /// Bits per token ID, between 9 and 32: the dictionary holds up to `2^bits_per_token` tokens
+    pub bits_per_token: usize,
+    /// Fraction of the strings scanned by each training pass, in (0, 1]
+    pub sample_rate: f64,
+    /// Strategy picking the sampled strings (see `sampling`)
+    pub sampling: Sampling,
+    /// Storage of the dictionary after training (see `coded_dictionary`)
+    pub dictionary_coding: DictionaryCoding,
+    /// Maximum dictionary size in bytes, including token boundaries (see `merge_tokens`)
+    pub max_dictionary_size: Option<usize>,
+}
+
+impl Default for OnPairConfig {
+    fn default() -> Self {
+        OnPairConfig {
+            threshold: None,
+            max_token_length: MAX_TOKEN_LENGTH,
+            bits_per_token: DEFAULT_BITS_PER_TOKEN,
+            sample_rate: 1.0,
+            sampling: Sampling::Random,
+            dictionary_coding: DictionaryCoding::Plain,
+            max_dictionary_size: None,
+        }
+    }
+}
+
+impl OnPairConfig {
+    /// Parses a configuration from its command-line representation
+    /// 
+    /// The specification is a comma-separated list of `key=value` assignments, with keys
+    /// `threshold` (a number or `auto`), `max_length`, `bits`, `sample`, `sampling`
+    /// (`random` or `stratified`), `dictionary` (`plain` or `front_coded`) and
+    /// `max_dictionary` (bytes); unassigned parameters keep their default (e.g.,
+    /// `threshold=8,max_length=16`).
+    /// 
+    /// # Arguments
+    /// - `s`: Configuration specification
+    /// 
+    /// # Returns
+    /// The parsed configuration, or `None` if the specification is invalid
+    pub fn parse(s: &str) -> Option<Self> {
+        let mut config = OnPairConfig::default();
+        for assignment in s.split(',') {
+            let (key, value) = assignment.split_once('=')?;
+            match key {
+                "threshold" if value == "auto" => config.threshold = None,
+                "threshold" => config.threshold = Some(value.parse().ok().filter(|&threshold| threshold > 0)?),
+                "max_length" => config.max_token_length = value.parse().ok()?,
+                "bits" => config.bits_per_token = value.parse().ok()?,
+                "sample" => config.sample_rate = value.parse().ok()?,
+                "sampling" => config.sampling = Sampling::parse(value)?,
+                "dictionary" => config.dictionary_coding = DictionaryCoding::parse(value)?,
+                "max_dictionary" => config.max_dictionary_size = Some(value.parse().ok().filter(|&size| size > 0)?),
+                _ => return None,
+            }
+        }
+        config.is_valid().then_some(config)
+    }
+
+    /// Checks whether every parameter is within its range
+    pub fn is_valid(&self) -> bool {
+        (2..=MAX_TOKEN_LENGTH).contains(&self.max_token_length)
+            && (9..=32).contains(&self.bits_per_token)
+            && self.sample_rate > 0.0
+            && self.sample_rate <= 1.0
+    }
+}
+
+/// Initial tokens of the dictionary
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Alphabet {
+    Bytes,  // All 256 single-byte tokens
+    Dense,  // Single-byte tokens for the bytes used by the dataset only
+    Pairs,  // Dense, plus all pairs of used bytes if they fit in half of the token IDs
+}
 
 /// OnPair compressor with bit-vector token storage
 /// 
 /// OnPair variant that reduces per-token storage overhead through bit-level packing. 
 pub struct OnPairBVCompressor {
+    config: OnPairConfig,                   // Training parameters
+    alphabet: Alphabet,                     // Initial tokens of the dictionary
+    passes: usize,                          // Training passes over the corpus (see `refine`)
+    string_cache: bool,                     // Whether repeated strings skip longest prefix matching
+    string_cache_hit_rate: Option<f64>,     // Fraction of strings found in the cache by the last parse
+    inline_literals: bool,                  // Whether single-byte tokens are inlined in the token stream
+    n_literal_tokens: usize,                // Single-byte tokens, at the start of the dictionary
+    literal_token_stats: Option<LiteralTokenStats>, // Single-byte tokens of the last compression
+    matcher_stats: Option<MatcherStats>,    // Memory of the longest prefix matcher of the last compression
+    offset_sample_rate: Option<usize>,      // Distance between sampled string offsets, if sampled
+    item_sample_interval: Option<usize>,    // Decoded bytes between sampled tokens within strings, if sampled
+    name: String,                           // Display name, reflecting the configuration
     compressed_data: BitVector,             // Bit-packed token sequences
-    item_end_positions: Vec<usize>,         // Compressed string boundaries
-    dictionary: Vec<u8>,                    // Token definitions (variable length)
-    dictionary_end_positions: Vec<u32>,     // Token boundary positions in dictionary
+    item_end_positions: Vec<usize>,         // Compressed string boundaries, in stream positions (see `TokenCoding`)
+    sampled_offsets: Option<LengthIndex>,   // Sampled string boundaries, replacing the plain ones if configured
+    item_samples: Option<IntraItemIndex>,   // Sampled token positions within long strings, if configured
+    dictionary: Vec<u8>,                    // Token definitions (variable length), then MAX_COPY_WIDTH bytes of padding
+    dictionary_end_positions: TokenOffsets, // Token boundary positions in dictionary
+    coded_dictionary: Option<CodedDictionary>, // Front-coded dictionary, replacing the plain one if configured
+    copy_width: usize,                      // Bytes copied per token by the fast path (see `copy_width`)
 }
 
 impl Compressor for OnPairBVCompressor {
     fn new(data_size: usize, n_elements: usize) -> Self {
-        OnPairBVCompressor {
-            compressed_data: BitVector::with_capacity(data_size * BITS_PER_TOKEN),
-            item_end_positions: Vec::with_capacity(n_elements),
-            dictionary: Vec::with_capacity(2 * 1024 * 1024), // 2 MiB
-            dictionary_end_positions: Vec::with_capacity(1 << 16),
-        }
+        Self::with_parameters(data_size, n_elements, DEFAULT_BITS_PER_TOKEN, None)
     }
 
     fn compress(&mut self, data: &[u8], end_positions: &[usize]) {
-        let lpm = self.train(data, end_positions);
-        self.parse(data, end_positions, &lpm);
+        let lpm = self.prepare_parse(data, end_positions);
+        info_span!("parse").in_scope(|| self.parse(data, end_positions, &lpm));
+        self.finish_parse();
+    }
+
+    /// Trains the dictionary in a first unit, then parses `PARSE_STEP_ITEMS` strings per unit
+    fn compress_steps<'a>(&'a mut self, data: &'a [u8], end_positions: &'a [usize]) -> CompressionSteps<'a> {
+        let n_items = end_positions.len().saturating_sub(1);
+        let mut parse: Option<(PrefixTrie, StringCache<'a>)> = None;
+        let mut next_item = 0;
+        CompressionSteps::new(move || {
+            let Some((lpm, cache)) = parse.as_mut() else {
+                let lpm = self.prepare_parse(data, end_positions);
+                self.item_end_positions.push(0);
+                parse = Some((lpm, StringCache::default()));
+                return Progress::Continue;
+            };
+            let items = next_item..(next_item + PARSE_STEP_ITEMS).min(n_items);
+            next_item = items.end;
+            info_span!("parse").in_scope(|| self.parse_items(data, end_positions, items, lpm, cache));
+            if next_item < n_items {
+                return Progress::Continue;
+            }
+            self.report_string_cache(cache, n_items);
+            self.finish_parse();
+            Progress::Done
+        })
     }
 
     fn decompress(&self, buffer: &mut [u8]) -> usize {
-        let dict_ptr = self.dictionary.as_ptr();
-        let end_positions_ptr = self.dictionary_end_positions.as_ptr();
-        let mut size = 0;
+        if let Some(coded_dictionary) = &self.coded_dictionary {
+            let (dictionary, dictionary_end_positions) = decode_dictionary(coded_dictionary);
+            return with_offsets!(&dictionary_end_positions, offsets => self.decompress_with(&dictionary, offsets, buffer));
+        }
+        with_offsets!(&self.dictionary_end_positions, offsets => self.decompress_with(&self.dictionary, offsets, buffer))
+    }
 
-        for i in 0..self.compressed_data.len() / BITS_PER_TOKEN {
-            let offset = i * BITS_PER_TOKEN;
-            let token_id = unsafe { self.compressed_data.get_bits_unchecked(offset, BITS_PER_TOKEN) as usize };
+    fn decompress_to<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        if let Some(coded_dictionary) = &self.coded_dictionary {
+            let (dictionary, dictionary_end_positions) = decode_dictionary(coded_dictionary);
+            return with_offsets!(&dictionary_end_positions, offsets => self.decompress_to_with(&dictionary, offsets, writer));
+        }
+        with_offsets!(&self.dictionary_end_positions, offsets => self.decompress_to_with(&self.dictionary, offsets, writer))
+    }
 
-            unsafe {
-                let dict_start = *end_positions_ptr.add(token_id as usize) as usize;
-                let dict_end = *end_positions_ptr.add(token_id as usize + 1) as usize;
-                let length = dict_end - dict_start;
-
-                let mut src = dict_ptr.add(dict_start);
-                let mut dst = buffer.as_mut_ptr().add(size);
-                std::ptr::copy_nonoverlapping(src, dst, FAST_ACCESS_SIZE);
-
-                if length > FAST_ACCESS_SIZE {
-                    src = src.add(FAST_ACCESS_SIZE); 
-                    dst = dst.add(FAST_ACCESS_SIZE);
-                    std::ptr::copy_nonoverlapping(src, dst, length - FAST_ACCESS_SIZE);
-                }
+    fn get_item_at(&mut self, index: usize, buffer: &mut [u8]) -> usize {
+        let (item_start, item_end) = self.item_bounds(index);
+        let tokens = Tokens::new(&self.compressed_data, self.token_coding(), item_start, item_end);
+
+        if let Some(coded_dictionary) = &mut self.coded_dictionary {
+            let mut size = 0;
+            for token_id in tokens {
+                size += coded_dictionary.copy_token(token_id, &mut buffer[size..]);
+            }
+            return size;
+        }
+
+        with_offsets!(&self.dictionary_end_positions, offsets => copy_tokens(&self.dictionary, offsets, tokens, buffer, self.copy_width))
+    }
+
+    fn get_item_at_profiled(&mut self, index: usize, buffer: &mut [u8], timer: &mut StageTimer) -> usize {
+        let (item_start, item_end) = self.item_bounds(index);
+        let tokens = Tokens::new(&self.compressed_data, self.token_coding(), item_start, item_end);
+        timer.lap(Stage::Lookup);
 
-                size += length;
+        // Tokens of a front-coded dictionary are decoded straight to the output
+        if let Some(coded_dictionary) = &mut self.coded_dictionary {
+            let mut size = 0;
+            for token_id in tokens {
+                size += coded_dictionary.copy_token(token_id, &mut buffer[size..]);
             }
+            timer.lap(Stage::Decode);
+            return size;
         }
 
+        // Token IDs are unpacked and resolved to dictionary spans first, then copied
+        timer.spans.clear();
+        for token_id in tokens {
+            let span = self.dictionary_end_positions.span(token_id);
+            timer.spans.push((span.start, span.len()));
+        }
+        timer.lap(Stage::Decode);
+
+        let size = copy_spans(&self.dictionary, &timer.spans, buffer);
+        timer.lap(Stage::Copy);
         size
     }
 
-    fn get_item_at(&mut self, index: usize, buffer: &mut [u8]) -> usize {
-        let item_start = self.item_end_positions[index];
-        let item_end = self.item_end_positions[index + 1];
-        let dict_ptr = self.dictionary.as_ptr();
-        let end_positions_ptr = self.dictionary_end_positions.as_ptr();
-        let mut size = 0;
+    fn get_item_range(&mut self, index: usize, start: usize, end: usize, buffer: &mut [u8]) -> usize {
+        let (item_start, item_end) = self.item_bounds(index);
+        let (token_position, mut offset) = match &self.item_samples {
+            Some(item_samples) => item_samples.seek(index, start),
+            None => (0, 0),
+        };
+        let mut tokens = Tokens::new(&self.compressed_data, self.token_coding(), item_start, item_end);
+        tokens.skip_tokens(token_position);
 
-        for i in item_start..item_end {
-            let offset = i * BITS_PER_TOKEN;
-            let token_id = unsafe { self.compressed_data.get_bits_unchecked(offset, BITS_PER_TOKEN) as usize };
+        // Tokens ending before the range are skipped without copying their bytes, then
+        // tokens are copied from the one holding the first byte of the range
+        let mut size = 0;
+        while offset + size < end {
+            let Some(token_id) = tokens.next() else {
+                break;
+            };
+            let length = match &self.coded_dictionary {
+                Some(coded_dictionary) => coded_dictionary.token_length(token_id),
+                None => self.dictionary_end_positions.token_length(token_id),
+            };
+            if size == 0 && offset + length <= start {
+                offset += length;
+                continue;
+            }
 
-            unsafe {
-                let dict_start = *end_positions_ptr.add(token_id as usize) as usize;
-                let dict_end = *end_positions_ptr.add(token_id as usize + 1) as usize;
-                let length = dict_end - dict_start;
-
-                let mut src = dict_ptr.add(dict_start);
-                let mut dst = buffer.as_mut_ptr().add(size);
-                std::ptr::copy_nonoverlapping(src, dst, FAST_ACCESS_SIZE);
-
-                if length > FAST_ACCESS_SIZE {
-                    src = src.add(FAST_ACCESS_SIZE); 
-                    dst = dst.add(FAST_ACCESS_SIZE);
-                    std::ptr::copy_nonoverlapping(src, dst, length - FAST_ACCESS_SIZE);
+            match &mut self.coded_dictionary {
+                Some(coded_dictionary) => {
+                    coded_dictionary.copy_token(token_id, &mut buffer[size..]);
                 }
-
-                size += length;
+                None => unsafe {
+                    let dict_start = self.dictionary_end_positions.get(token_id);
+                    copy_token(self.dictionary.as_ptr().add(dict_start), buffer.as_mut_ptr().add(size), length, self.copy_width);
+                },
             }
+            size += length;
         }
 
-        size
+        // Bytes of the first token preceding the range are dropped
+        buffer.copy_within(start - offset..end - offset, 0);
+        end - start
+    }
+
+    fn len_of_item(&self, index: usize) -> usize {
+        // Sums the lengths of the item tokens, without copying their bytes
+        let (item_start, item_end) = self.item_bounds(index);
+        self.tokens(item_start, item_end).map(|token_id| self.token_length(token_id)).sum()
+    }
+
+    fn max_item_len(&self) -> usize {
+        max_len_of_items(self, self.item_end_positions().len().saturating_sub(1))
     }
 
     fn space_used_bytes(&self) -> usize {
-        (self.compressed_data.len() / 8) 
-        + self.dictionary.len() 
-        + (self.dictionary_end_positions.len() * std::mem::size_of::<u32>())
+        self.space_breakdown().total()
+    }
+
+    fn space_breakdown(&self) -> SpaceBreakdown {
+        SpaceBreakdown {
+            payload: self.compressed_data.len() / 8,
+            dictionary: self.dictionary_size(),
+            item_offsets: self.item_offsets_size() + self.item_samples.as_ref().map_or(0, IntraItemIndex::size_bytes),
+            block_metadata: 0,
+            id_map: 0,
+            checksums: 0,
+        }
+    }
+
+    fn item_boundaries(&self) -> ItemBoundaries {
+        match self.sampled_offsets {
+            Some(_) => ItemBoundaries::Sampled,
+            None => ItemBoundaries::Offsets,
+        }
     }
 
     fn name(&self) -> &str {
-        "OnPair BV"
+        &self.name
+    }
+}
+
+/// Dictionary trained by OnPairBV, with the matcher used to parse new data
+pub struct OnPairBVDictionary {
+    dictionary: Vec<u8>,                    // Token definitions (variable length)
+    dictionary_end_positions: TokenOffsets, // Token boundary positions in dictionary
+    n_literal_tokens: usize,                // Single-byte tokens, at the start of the dictionary
+    lpm: PrefixTrie,       // Longest prefix matcher over the tokens
+}
+
+impl DictionaryCompressor for OnPairBVCompressor {
+    type Dictionary = OnPairBVDictionary;
+
+    fn train_dictionary(&self, data: &[u8], end_positions: &[usize]) -> OnPairBVDictionary {
+        // All single-byte tokens are kept, as shards may use bytes absent from the training data
+        let mut trainer = Self::with_config(0, 0, self.config);
+        trainer.passes = self.passes;
+        trainer.inline_literals = self.inline_literals;
+        let lpm = trainer.build_dictionary(data, end_positions);
+
+        OnPairBVDictionary {
+            dictionary: trainer.dictionary,
+            dictionary_end_positions: trainer.dictionary_end_positions,
+            n_literal_tokens: trainer.n_literal_tokens,
+            lpm,
+        }
+    }
+
+    fn compress_with_dictionary(&mut self, data: &[u8], end_positions: &[usize], dictionary: &OnPairBVDictionary) {
+        self.alphabet = Alphabet::Bytes;
+        self.name = self.describe();
+        self.dictionary = dictionary.dictionary.clone();
+        self.dictionary_end_positions = dictionary.dictionary_end_positions.clone();
+        self.n_literal_tokens = dictionary.n_literal_tokens;
+        self.pad_dictionary();
+        self.report_matcher(&dictionary.lpm);
+        info_span!("parse").in_scope(|| self.parse(data, end_positions, &dictionary.lpm));
+        self.finish_parse();
+    }
+
+    fn dictionary_report(&self) -> DictionaryReport {
+        let item_end_positions = self.item_end_positions();
+        let items = item_end_positions.windows(2).map(|w| self.tokens(w[0], w[1]));
+        match &self.coded_dictionary {
+            Some(coded_dictionary) => {
+                let (dictionary, dictionary_end_positions) = coded_dictionary.decode_all();
+                with_offsets!(&dictionary_end_positions, offsets => DictionaryReport::build(self.name(), &dictionary, offsets, items))
+            }
+            None => with_offsets!(&self.dictionary_end_positions, offsets => DictionaryReport::build(self.name(), &self.dictionary, offsets, items)),
+        }
+    }
+
+    fn dictionary_stats(&self) -> DictionaryStats {
+        let item_end_positions = self.item_end_positions();
+        let items = item_end_positions.windows(2).map(|w| self.tokens(w[0], w[1]));
+        match &self.coded_dictionary {
+            Some(coded_dictionary) => with_offsets!(&coded_dictionary.decode_all().1, offsets => DictionaryStats::build(offsets, items)),
+            None => with_offsets!(&self.dictionary_end_positions, offsets => DictionaryStats::build(offsets, items)),
+        }
     }
 }
 
 impl OnPairBVCompressor {
-    fn train(&mut self, data: &[u8], end_positions: &[usize]) -> LongestPrefixMatcher<usize> {
+    /// Creates a compressor with custom token width and merge threshold
+    /// 
+    /// # Arguments
+    /// - `data_size`: Total size of input data in bytes
+    /// - `n_elements`: Number of individual strings in the dataset
+    /// - `bits_per_token`: Bits per token ID; the dictionary holds up to `2^bits_per_token` tokens
+    /// - `threshold`: Pair frequency triggering a merge; `None` derives it from the data size
+    pub fn with_parameters(data_size: usize, n_elements: usize, bits_per_token: usize, threshold: Option<usize>) -> Self {
+        Self::with_config(data_size, n_elements, OnPairConfig { bits_per_token, threshold, ..OnPairConfig::default() })
+    }
+
+    /// Creates a compressor with custom training parameters
+    /// 
+    /// # Arguments
+    /// - `data_size`: Total size of input data in bytes
+    /// - `n_elements`: Number of individual strings in the dataset
+    /// - `config`: Training parameters
+    pub fn with_config(data_size: usize, n_elements: usize, config: OnPairConfig) -> Self {
+        let mut compressor = OnPairBVCompressor {
+            config: OnPairConfig::default(),
+            alphabet: Alphabet::Bytes,
+            passes: 1,
+            string_cache: false,
+            string_cache_hit_rate: None,
+            inline_literals: false,
+            n_literal_tokens: 0,
+            literal_token_stats: None,
+            matcher_stats: None,
+            offset_sample_rate: None,
+            item_sample_interval: None,
+            name: String::new(),
+            compressed_data: BitVector::with_capacity(data_size * config.bits_per_token),
+            item_end_positions: Vec::with_capacity(n_elements),
+            sampled_offsets: None,
+            item_samples: None,
+            dictionary: Vec::with_capacity(2 * 1024 * 1024), // 2 MiB
+            dictionary_end_positions: TokenOffsets::with_capacity(1 << 16),
+            coded_dictionary: None,
+            copy_width: FAST_ACCESS_SIZE,
+        };
+        compressor.set_config(config);
+        compressor
+    }
+
+    /// Replaces the training parameters, keeping the alphabet, passes, string cache and literal inlining
+    /// 
+    /// Takes effect on the next compression.
+    pub fn set_config(&mut self, config: OnPairConfig) {
+        assert!(config.is_valid(), "Invalid OnPair configuration: {:?}", config);
+        self.config = config;
+        self.copy_width = copy_width(config.max_token_length);
+        self.name = self.describe();
+    }
+
+    /// Returns the training parameters
+    pub fn config(&self) -> OnPairConfig {
+        self.config
+    }
+
+    /// Creates a compressor with default parameters and the given initial tokens
+    ///
+    /// # Arguments
+    /// - `data_size`: Total size of input data in bytes
+    /// - `n_elements`: Number of individual strings in the dataset
+    /// - `alphabet`: Initial tokens of the dictionary
+    pub fn with_alphabet(data_size: usize, n_elements: usize, alphabet: Alphabet) -> Self {
+        let mut compressor = Self::new(data_size, n_elements);
+        compressor.alphabet = alphabet;
+        compressor.name = compressor.describe();
+        compressor
+    }
+
+    /// Creates a compressor with default parameters and extra training passes
+    ///
+    /// # Arguments
+    /// - `data_size`: Total size of input data in bytes
+    /// - `n_elements`: Number of individual strings in the dataset
+    /// - `passes`: Training passes over the corpus, at least 1 (see `refine`)
+    pub fn with_passes(data_size: usize, n_elements: usize, passes: usize) -> Self {
+        assert!(passes >= 1, "passes must be at least 1");
+        let mut compressor = Self::new(data_size, n_elements);
+        compressor.passes = passes;
+        compressor.name = compressor.describe();
+        compressor
+    }
+
+    /// Creates a compressor with default parameters, parsing repeated strings through the string cache
+    ///
+    /// # Arguments
+    /// - `data_size`: Total size of input data in bytes
+    /// - `n_elements`: Number of individual strings in the dataset
+    pub fn with_string_cache(data_size: usize, n_elements: usize) -> Self {
+        let mut compressor = Self::new(data_size, n_elements);
+        compressor.string_cache = true;
+        compressor.name = compressor.describe();
+        compressor
+    }
+
+    /// Creates a compressor with default parameters, inlining single-byte tokens in the token stream
+    ///
+    /// # Arguments
+    /// - `data_size`: Total size of input data in bytes
+    /// - `n_elements`: Number of individual strings in the dataset
+    pub fn with_literal_inlining(data_size: usize, n_elements: usize) -> Self {
+        let mut compressor = Self::new(data_size, n_elements);
+        compressor.inline_literals = true;
+        compressor.name = compressor.describe();
+        compressor
+    }
+
+    /// Creates a compressor with default parameters and tokens bounded to `max_token_length` bytes
+    ///
+    /// Bounds of 32 and 64 bytes enable the fixed-size copy of every token (see `copy_width`),
+    /// trading the compression of long repeated phrases for branch-free decoding.
+    ///
+    /// # Arguments
+    /// - `data_size`: Total size of input data in bytes
+    /// - `n_elements`: Number of individual strings in the dataset
+    /// - `max_token_length`: Maximum length of merged tokens in bytes, between 2 and `MAX_TOKEN_LENGTH`
+    pub fn with_max_token_length(data_size: usize, n_elements: usize, max_token_length: usize) -> Self {
+        Self::with_config(data_size, n_elements, OnPairConfig { max_token_length, ..OnPairConfig::default() })
+    }
+
+    /// Returns the fraction of strings found in the string cache by the last parse
+    ///
+    /// `None` if the string cache is disabled or no data was compressed yet.
+    pub fn string_cache_hit_rate(&self) -> Option<f64> {
+        self.string_cache_hit_rate
+    }
+
+    /// Returns the single-byte tokens of the last compression
+    ///
+    /// `None` if no data was compressed yet.
+    pub fn literal_token_stats(&self) -> Option<LiteralTokenStats> {
+        self.literal_token_stats
+    }
+
+    /// Returns the memory of the longest prefix matcher built by the last compression
+    ///
+    /// `None` if no data was compressed yet.
+    pub fn matcher_stats(&self) -> Option<MatcherStats> {
+        self.matcher_stats
+    }
+
+    /// Samples the token offsets of the strings
+    ///
+    /// The offset of every `sample_rate`-th string is stored, with the token count of
+    /// the strings in between (see `length_index`): an access scans up to
+    /// `sample_rate - 1` counts from the closest sample. Larger rates save space at the
+    /// cost of latency. Takes effect on the next compression.
+    ///
+    /// # Arguments
+    /// - `sample_rate`: Distance between sampled offsets, at least 1
+    pub fn set_offset_sample_rate(&mut self, sample_rate: usize) {
+        assert!(sample_rate >= 1, "sample_rate must be at least 1");
+        self.offset_sample_rate = Some(sample_rate);
+        self.name = self.describe();
+    }
+
+    /// Samples the token positions within long strings
+    ///
+    /// For every string longer than `sample_interval` bytes, the token holding every
+    /// `sample_interval`-th byte is stored with its offset (see `intra_item_index`), so
+    /// that `get_item_range` starts from the closest sampled token instead of the start
+    /// of the string. Takes effect on the next compression.
+    ///
+    /// # Arguments
+    /// - `sample_interval`: Decoded bytes between sampled tokens, at least 1
+    pub fn set_item_sample_interval(&mut self, sample_interval: usize) {
+        assert!(sample_interval >= 1, "sample_interval must be at least 1");
+        self.item_sample_interval = Some(sample_interval);
+        self.name = self.describe();
+    }
+
+    /// Decompresses all strings with the given dictionary (see `Compressor::decompress`)
+    ///
+    /// The dictionary must be padded (see `pad_dictionary`).
+    fn decompress_with<O: Offset>(&self, dictionary: &[u8], dictionary_end_positions: &[O], buffer: &mut [u8]) -> usize {
+        copy_tokens(dictionary, dictionary_end_positions, self.tokens(0, self.stream_position()), buffer, self.copy_width)
+    }
+
+    /// Streams all strings with the given dictionary (see `Compressor::decompress_to`)
+    ///
+    /// The dictionary must be padded (see `pad_dictionary`).
+    fn decompress_to_with<O: Offset, W: Write>(&self, dictionary: &[u8], dictionary_end_positions: &[O], writer: &mut W) -> io::Result<usize> {
+        let mut stream = StreamBuffer::new(writer);
+
+        for token_id in self.tokens(0, self.stream_position()) {
+            let dict_start = dictionary_end_positions[token_id].position();
+            let dict_end = dictionary_end_positions[token_id + 1].position();
+            let length = dict_end - dict_start;
+            let buffer = stream.reserve(length)?;
+
+            unsafe {
+                copy_token(dictionary.as_ptr().add(dict_start), buffer.as_mut_ptr(), length, self.copy_width);
+            }
+            stream.commit(length);
+        }
+        stream.finish()
+    }
+
+    /// Returns the positions of the first and past the last token of a string
+    #[inline(always)]
+    fn item_bounds(&self, index: usize) -> (usize, usize) {
+        match &self.sampled_offsets {
+            Some(sampled_offsets) => sampled_offsets.bounds(index),
+            None => (self.item_end_positions[index], self.item_end_positions[index + 1]),
+        }
+    }
+
+    /// Returns the token boundary positions of all strings, starting with 0
+    fn item_end_positions(&self) -> Cow<'_, [usize]> {
+        match &self.sampled_offsets {
+            Some(sampled_offsets) => Cow::Owned(sampled_offsets.end_positions()),
+            None => Cow::Borrowed(&self.item_end_positions),
+        }
+    }
+
+    /// Returns the size of the string offsets in bytes, sampled or plain
+    fn item_offsets_size(&self) -> usize {
+        match &self.sampled_offsets {
+            Some(sampled_offsets) => sampled_offsets.size_bytes(),
+            None => self.item_end_positions.len() * std::mem::size_of::<usize>(),
+        }
+    }
+
+    /// Returns the length in bytes of a token, from the front-coded or plain dictionary
+    #[inline(always)]
+    fn token_length(&self, token_id: usize) -> usize {
+        match &self.coded_dictionary {
+            Some(coded_dictionary) => coded_dictionary.token_length(token_id),
+            None => self.dictionary_end_positions.token_length(token_id),
+        }
+    }
+
+    /// Returns the layout of the token IDs in the token stream
+    #[inline(always)]
+    fn token_coding(&self) -> TokenCoding {
+        TokenCoding {
+            bits_per_token: self.config.bits_per_token,
+            literal_tokens: self.inline_literals.then_some(self.n_literal_tokens),
+        }
+    }
+
+    /// Returns the token IDs between two stream positions
+    #[inline(always)]
+    fn tokens(&self, start: usize, end: usize) -> Tokens<'_> {
+        Tokens::new(&self.compressed_data, self.token_coding(), start, end)
+    }
+
+    /// Returns the stream position past the last token
+    fn stream_position(&self) -> usize {
+        self.compressed_data.len() / self.token_coding().position_bits()
+    }
+
+    /// Returns the largest token ID: inlined single-byte tokens leave all `2^bits_per_token` IDs to merged tokens
+    ///
+    /// IDs are further bounded by the matcher (see `prefix_trie::MAX_TOKEN_ID`), which
+    /// only binds with 32-bit token IDs.
+    fn max_token_id(&self) -> usize {
+        let literal_ids = if self.inline_literals { self.n_literal_tokens } else { 0 };
+        (literal_ids + (1 << self.config.bits_per_token) - 1).min(MAX_TOKEN_ID)
+    }
+
+    /// Appends a token to the token stream
+    #[inline(always)]
+    fn push_token(&mut self, token_id: usize) {
+        let bits_per_token = self.config.bits_per_token;
+        if !self.inline_literals {
+            self.compressed_data.append_bits(token_id as u64, bits_per_token);
+        } else if token_id < self.n_literal_tokens {
+            self.compressed_data.append_bits(1 | ((token_id as u64) << 1), 1 + LITERAL_BITS);
+        } else {
+            self.compressed_data.append_bits(((token_id - self.n_literal_tokens) as u64) << 1, 1 + bits_per_token);
+        }
+    }
+
+    /// Appends a copy of the tokens between two stream positions to the token stream
+    fn repeat_tokens(&mut self, start: usize, end: usize) {
+        let position_bits = self.token_coding().position_bits();
+        let end = end * position_bits;
+        let mut offset = start * position_bits;
+        while offset < end {
+            let len = (end - offset).min(64);
+            let bits = unsafe { self.compressed_data.get_bits_unchecked(offset, len) };
+            self.compressed_data.append_bits(bits, len);
+            offset += len;
+        }
+    }
+
+    /// Counts the single-byte tokens of the token stream, and logs their share
+    fn count_literal_tokens(&mut self) {
+        let mut n_tokens = 0;
+        let mut single_byte_tokens = 0;
+        for token_id in self.tokens(0, self.stream_position()) {
+            n_tokens += 1;
+            single_byte_tokens += (token_id < self.n_literal_tokens) as usize;
+        }
+        let stats = LiteralTokenStats {
+            n_tokens,
+            single_byte_tokens,
+            inlined: self.inline_literals,
+            dictionary_tokens: self.dictionary_end_positions.len() - 1,
+        };
+        info!(n_tokens, single_byte_tokens, fraction = stats.single_byte_fraction(), inlined = stats.inlined, "Single-byte token statistics");
+        self.literal_token_stats = Some(stats);
+    }
+
+    /// Samples the token positions within long strings, if configured
+    ///
+    /// Runs before `sample_offsets`, while the plain string offsets are available.
+    fn sample_item_tokens(&mut self) {
+        let compressor = &*self;
+        let item_samples = self.item_sample_interval.map(|sample_interval| {
+            let items = compressor.item_end_positions.windows(2).map(move |w| {
+                compressor.tokens(w[0], w[1]).map(move |token_id| compressor.token_length(token_id))
+            });
+            IntraItemIndex::build(sample_interval, items)
+        });
+        self.item_samples = item_samples;
+    }
+
+    /// Replaces the plain string offsets with sampled ones, if configured
+    fn sample_offsets(&mut self) {
+        self.sampled_offsets = self
+            .offset_sample_rate
+            .map(|sample_rate| length_index::sample_item_offsets(&mut self.item_end_positions, sample_rate));
+    }
+
+    /// Returns the size of the dictionary in bytes, front-coded or plain (with its padding)
+    fn dictionary_size(&self) -> usize {
+        match &self.coded_dictionary {
+            Some(coded_dictionary) => coded_dictionary.space_used_bytes(),
+            None => self.dictionary.len() + self.dictionary_end_positions.size_bytes(),
+        }
+    }
+
+    /// Replaces the plain dictionary with its front-coded version, if configured
+    ///
+    /// Logs the size of the dictionary before and after coding.
+    fn encode_dictionary(&mut self) {
+        self.coded_dictionary = None;
+        if self.config.dictionary_coding != DictionaryCoding::FrontCoded {
+            return;
+        }
+        let plain_size = self.dictionary.len() - MAX_COPY_WIDTH + self.dictionary_end_positions.size_bytes();
+        let coded_dictionary = with_offsets!(&self.dictionary_end_positions, offsets => CodedDictionary::new(&self.dictionary, offsets));
+        let coded_size = coded_dictionary.space_used_bytes();
+        info!(plain_size, coded_size, reduction = 1.0 - coded_size as f64 / plain_size as f64, "Dictionary front-coded");
+
+        self.coded_dictionary = Some(coded_dictionary);
+        self.dictionary = Vec::new();
+        self.dictionary_end_positions = TokenOffsets::default();
+    }
+
+    /// Appends the padding read by fixed-size token copies past the last token
+    fn pad_dictionary(&mut self) {
+        self.dictionary.extend_from_slice(&[0; MAX_COPY_WIDTH]);
+    }
+
+    /// Returns the display name of the current configuration
+    fn describe(&self) -> String {
+        let mut options = Vec::new();
+        match self.alphabet {
+            Alphabet::Bytes => {}
+            Alphabet::Dense => options.push("dense alphabet"),
+            Alphabet::Pairs => options.push("pair alphabet"),
+        }
+        if self.passes > 1 {
+            options.push("iterated");
+        }
+        if self.string_cache {
+            options.push("string cache");
+        }
+        if self.inline_literals {
+            options.push("inline literals");
+        }
+        let sampled_offsets = self.offset_sample_rate.map(|sample_rate| format!("sampled offsets {}", sample_rate));
+        options.extend(sampled_offsets.as_deref());
+        let item_samples = self.item_sample_interval.map(|sample_interval| format!("item samples {}", sample_interval));
+        options.extend(item_samples.as_deref());
+
+        // Non-default training parameters
+        let default = OnPairConfig::default();
+        let mut parameters = Vec::new();
+        if let Some(threshold) = self.config.threshold {
+            parameters.push(format!("threshold {}", threshold));
+        }
+        if self.config.max_token_length != default.max_token_length {
+            parameters.push(format!("max length {}", self.config.max_token_length));
+        }
+        if self.config.bits_per_token != default.bits_per_token {
+            parameters.push(format!("{} bits", self.config.bits_per_token));
+        }
+        if self.config.sample_rate != default.sample_rate {
+            parameters.push(format!("sample {}", self.config.sample_rate));
+        }
+        if self.config.sampling != default.sampling {
+            parameters.push(format!("{} sampling", self.config.sampling.name()));
+        }
+        if self.config.dictionary_coding != default.dictionary_coding {
+            parameters.push(format!("{} dictionary", self.config.dictionary_coding.name()));
+        }
+        if let Some(max_dictionary_size) = self.config.max_dictionary_size {
+            parameters.push(format!("max dictionary {}", max_dictionary_size));
+        }
+        let options: Vec<&str> = options.into_iter().chain(parameters.iter().map(String::as_str)).collect();
+
+        if options.is_empty() {
+            String::from("OnPair BV")
+        } else {
+            format!("OnPair BV ({})", options.join(", "))
+        }
+    }
+
+    /// Returns the initial tokens of the dictionary for the given data
+    fn initial_tokens(&self, data: &[u8]) -> Vec<Vec<u8>> {
+        if self.alphabet == Alphabet::Bytes {
+            return (0..=255u8).map(|b| vec![b]).collect();
+        }
+
+        let mut used = [false; 256];
+        for &b in data {
+            used[b as usize] = true;
+        }
+        let symbols: Vec<u8> = (0..=255u8).filter(|&b| used[b as usize]).collect();
+
+        let mut tokens: Vec<Vec<u8>> = symbols.iter().map(|&b| vec![b]).collect();
+        let n_pairs = symbols.len() * symbols.len();
+        if self.alphabet == Alphabet::Pairs && tokens.len() + n_pairs <= (1 << self.config.bits_per_token) / 2 {
+            for &b1 in symbols.iter() {
+                for &b2 in symbols.iter() {
+                    tokens.push(vec![b1, b2]);
+                }
+            }
+        }
+        tokens
+    }
+
+    /// Builds the dictionary with the configured number of training passes
+    fn build_dictionary(&mut self, data: &[u8], end_positions: &[usize]) -> PrefixTrie {
+        let mut lpm = info_span!("train").in_scope(|| self.train(data, end_positions));
+        for pass in 2..=self.passes {
+            lpm = info_span!("refine", pass).in_scope(|| self.refine(data, end_positions, &lpm));
+        }
+        lpm
+    }
+
+    fn train(&mut self, data: &[u8], end_positions: &[usize]) -> PrefixTrie {
         self.dictionary_end_positions.push(0);
         
-        let mut frequency: FxHashMap<(usize, usize), usize> = FxHashMap::default();
-        let mut lpm = LongestPrefixMatcher::new();
-        let mut next_token_id = 256;
-    
-        // Initialize the dictionary with single-byte tokens
-        for i in 0..256 {
-            let token = vec![i as u8];
-            lpm.insert(&token, i);
-            self.dictionary.extend(&token);
-            self.dictionary_end_positions.push(self.dictionary.len() as u32);
+        let mut lpm = PrefixTrie::new();
+
+        // Initialize the dictionary with the alphabet tokens
+        let initial_tokens = self.initial_tokens(data);
+        for (i, token) in initial_tokens.iter().enumerate() {
+            lpm.insert(token, i);
+            self.dictionary.extend(token);
+            self.dictionary_end_positions.push(self.dictionary.len());
         }
+        // Single-byte tokens come first in every alphabet
+        self.n_literal_tokens = initial_tokens.iter().take_while(|token| token.len() == 1).count();
+
+        self.merge_tokens(data, end_positions, &mut lpm, initial_tokens.len(), initial_tokens.len());
+        lpm
+    }
+
+    /// Runs a further training pass over the corpus
+    ///
+    /// The corpus is parsed with the current dictionary, the merged tokens unused by
+    /// the parse are dropped (initial tokens are always kept, so that every string
+    /// remains parsable), and merging resumes with the freed token IDs.
+    ///
+    /// # Returns
+    /// Longest prefix matcher over the refined dictionary
+    fn refine(&mut self, data: &[u8], end_positions: &[usize], lpm: &PrefixTrie) -> PrefixTrie {
+        let n_tokens = self.dictionary_end_positions.len() - 1;
+        let mut used = vec![false; n_tokens];
+        for window in end_positions.windows(2) {
+            let mut pos = window[0];
+            while pos < window[1] {
+                let (token_id, length) = lpm.find_longest_match(&data[pos..window[1]]).unwrap();
+                used[token_id] = true;
+                pos += length;
+            }
+        }
+
+        let n_initial_tokens = self.initial_tokens(data).len();
+        let mut refined_lpm = self.retain_tokens(|token_id| token_id < n_initial_tokens || used[token_id]);
+        let n_kept_tokens = self.dictionary_end_positions.len() - 1;
+        debug!(n_tokens, n_dropped_tokens = n_tokens - n_kept_tokens, "Unused tokens dropped");
 
-        // Shuffle entries
-        let mut shuffled_indices: Vec<usize> = (0..end_positions.len()-1).collect();
-        shuffled_indices.shuffle(&mut thread_rng());
+        self.merge_tokens(data, end_positions, &mut refined_lpm, n_initial_tokens, n_kept_tokens);
+        refined_lpm
+    }
+
+    /// Rebuilds the dictionary with the selected tokens, in their original order
+    ///
+    /// # Arguments
+    /// - `keep`: Whether to keep the token with the given ID
+    ///
+    /// # Returns
+    /// Longest prefix matcher over the kept tokens, numbered consecutively
+    fn retain_tokens(&mut self, keep: impl Fn(usize) -> bool) -> PrefixTrie {
+        let n_tokens = self.dictionary_end_positions.len() - 1;
+        let dictionary = std::mem::take(&mut self.dictionary);
+        let dictionary_end_positions = std::mem::take(&mut self.dictionary_end_positions);
+        let mut lpm = PrefixTrie::new();
+        self.dictionary_end_positions.push(0);
+        for token_id in (0..n_tokens).filter(|&token_id| keep(token_id)) {
+            let token = &dictionary[dictionary_end_positions.span(token_id)];
+            lpm.insert(token, self.dictionary_end_positions.len() - 1);
+            self.dictionary.extend(token);
+            self.dictionary_end_positions.push(self.dictionary.len());
+        }
+        lpm
+    }
+
+    /// Evicts provisional tokens to make room for a new token within the dictionary budget
+    ///
+    /// Merged tokens matched fewer than `threshold` times since their merge are
+    /// provisional: they are evicted least-used first (oldest first among equals), until
+    /// the new token fits and at least `1 / EVICTION_SHARE` of the budget is freed, so
+    /// that the matcher is rebuilt once for many merges. Initial tokens and tokens
+    /// matched `threshold` times are never evicted.
+    ///
+    /// # Arguments
+    /// - `lpm`: Longest prefix matcher over the current dictionary, rebuilt after an eviction
+    /// - `uses`: Matches of each token since its merge, renumbered after an eviction
+    /// - `n_initial_tokens`: Number of initial tokens, at the start of the dictionary
+    /// - `threshold`: Matches confirming a merged token
+    /// - `token_length`: Length of the new token in bytes
+    /// - `budget`: Maximum dictionary size in bytes, including token boundaries
+    ///
+    /// # Returns
+    /// Whether the new token fits; if not, the dictionary is left unchanged
+    fn evict_tokens(
+        &mut self,
+        lpm: &mut PrefixTrie,
+        uses: &mut Vec<usize>,
+        n_initial_tokens: usize,
+        threshold: usize,
+        token_length: usize,
+        budget: usize,
+    ) -> bool {
+        let token_cost = |length: usize| length + self.dictionary_end_positions.offset_size();
+        let needed = (self.training_dictionary_size() + token_cost(token_length)).saturating_sub(budget);
+        let target = needed.max(budget / EVICTION_SHARE);
+
+        let mut provisional: Vec<usize> = (n_initial_tokens..uses.len()).filter(|&token_id| uses[token_id] < threshold).collect();
+        provisional.sort_by_key(|&token_id| (uses[token_id], token_id));
+        let mut evicted = vec![false; uses.len()];
+        let mut freed = 0;
+        for &token_id in provisional.iter() {
+            if freed >= target {
+                break;
+            }
+            evicted[token_id] = true;
+            freed += token_cost(self.dictionary_end_positions.token_length(token_id));
+        }
+        if freed < needed {
+            return false;
+        }
+
+        *lpm = self.retain_tokens(|token_id| !evicted[token_id]);
+        *uses = (0..uses.len()).filter(|&token_id| !evicted[token_id]).map(|token_id| uses[token_id]).collect();
+        true
+    }
+
+    /// Returns the size of the dictionary during training in bytes, including token boundaries
+    fn training_dictionary_size(&self) -> usize {
+        self.dictionary.len() + self.dictionary_end_positions.size_bytes()
+    }
+
+    /// Merges frequent pairs of adjacent tokens while scanning the corpus once
+    ///
+    /// Pairs whose merged token would exceed the maximum token length are never merged,
+    /// and only the sampled fraction of the strings is scanned.
+    ///
+    /// With a dictionary budget, a merge exceeding it first evicts provisional tokens
+    /// (see `evict_tokens`); if they do not free enough room, merging stops. Evictions
+    /// renumber the tokens, so that the pair frequencies gathered so far are reset and
+    /// the scan resumes from the next string.
+    ///
+    /// # Arguments
+    /// - `data`, `end_positions`: Training corpus
+    /// - `lpm`: Longest prefix matcher over the current dictionary, extended with the new tokens
+    /// - `n_initial_tokens`: Number of initial tokens, at the start of the dictionary
+    /// - `next_token_id`: First free token ID
+    fn merge_tokens(&mut self, data: &[u8], end_positions: &[usize], lpm: &mut PrefixTrie, n_initial_tokens: usize, mut next_token_id: usize) {
+        let max_token_id = self.max_token_id();
+        if next_token_id > max_token_id {
+            return;
+        }
+        let mut frequency: FxHashMap<(usize, usize), usize> = FxHashMap::default();
+
+        // Sampled entries, in random order
+        let shuffled_indices = sampling::sample_strings(end_positions.len() - 1, self.config.sample_rate, self.config.sampling);
 
         // Set the threshold for merging tokens
-        let data_size_mib = data.len() as f64 / (1024.0 * 1024.0);
-        let threshold = data_size_mib.log2().max(2.0) as usize;
+        let data_size_mib = data.len() as f64 * self.config.sample_rate / (1024.0 * 1024.0);
+        let threshold = self.config.threshold.unwrap_or(data_size_mib.log2().max(2.0) as usize);
+
+        // Matches of each token since its merge; tokens of earlier passes count as confirmed
+        let mut uses: Vec<usize> = vec![threshold; next_token_id];
+        let mut n_evictions = 0;
         
         // Iterate over entries
         'outer: for &index in shuffled_indices.iter() {
@@ -154,6 +1060,7 @@ impl OnPairBVCompressor {
             }
     
             let (match_token_id, match_length) = lpm.find_longest_match(&data[start..end]).unwrap();
+            uses[match_token_id] += 1;
             let mut previous_token_id = match_token_id;
             let mut previous_length = match_length;
 
@@ -162,21 +1069,36 @@ impl OnPairBVCompressor {
             while pos < end {
                 // Find the longest match
                 let (match_token_id, match_length) = lpm.find_longest_match(&data[pos..end]).unwrap();
+                uses[match_token_id] += 1;
     
                  // Update token frequency and possibly merge tokens
                 *frequency.entry((previous_token_id, match_token_id)).or_insert(0) += 1;
     
-                if frequency[&(previous_token_id, match_token_id)] >= threshold {
+                if frequency[&(previous_token_id, match_token_id)] >= threshold && previous_length + match_length <= self.config.max_token_length {
                     let merged_token = &data[pos - previous_length..pos + match_length];
+                    if let Some(budget) = self.config.max_dictionary_size {
+                        let dictionary_size = self.training_dictionary_size() + merged_token.len() + self.dictionary_end_positions.offset_size();
+                        if dictionary_size > budget {
+                            if !self.evict_tokens(lpm, &mut uses, n_initial_tokens, threshold, merged_token.len(), budget) {
+                                debug!("Dictionary budget reached");
+                                break 'outer;
+                            }
+                            n_evictions += 1;
+                            next_token_id = self.dictionary_end_positions.len() - 1;
+                            frequency.clear();
+                            continue 'outer;
+                        }
+                    }
                     lpm.insert(merged_token, next_token_id);
+                    uses.push(0);
                     self.dictionary.extend(merged_token);
-                    self.dictionary_end_positions.push(self.dictionary.len() as u32);
+                    self.dictionary_end_positions.push(self.dictionary.len());
                     
                     frequency.remove(&(previous_token_id, match_token_id));
                     previous_token_id = next_token_id;
                     previous_length = merged_token.len();
 
-                    if next_token_id == MAX_TOKEN_ID {
+                    if next_token_id == max_token_id {
                         break 'outer;
                     }
 
@@ -190,32 +1112,277 @@ impl OnPairBVCompressor {
                 pos += match_length;
             }
         }
+        if n_evictions > 0 {
+            debug!(n_evictions, "Provisional tokens evicted");
+        }
+    }
     
+    /// Builds the dictionary, padded for fixed-size token copies
+    fn prepare_parse(&mut self, data: &[u8], end_positions: &[usize]) -> PrefixTrie {
+        let lpm = self.build_dictionary(data, end_positions);
+        debug!(n_tokens = self.dictionary_end_positions.len() - 1, dictionary_size = self.dictionary.len(), "Dictionary built");
+        self.report_matcher(&lpm);
+        self.pad_dictionary();
         lpm
     }
-    
-    fn parse(&mut self, data: &[u8], end_positions: &[usize], lpm: &LongestPrefixMatcher<usize>) {
+
+    /// Encodes the dictionary and samples token positions once all strings are parsed
+    fn finish_parse(&mut self) {
+        self.count_literal_tokens();
+        self.encode_dictionary();
+        self.sample_item_tokens();
+        self.sample_offsets();
+    }
+
+    fn parse(&mut self, data: &[u8], end_positions: &[usize], lpm: &PrefixTrie) {
+        let n_items = end_positions.len().saturating_sub(1);
+        let mut cache = StringCache::default();
         self.item_end_positions.push(0);
+        self.parse_items(data, end_positions, 0..n_items, lpm, &mut cache);
+        self.report_string_cache(&cache, n_items);
+    }
 
-        for window in end_positions.windows(2) {
-            let start = window[0];
-            let end = window[1];
+    /// Parses a range of strings, following the strings already parsed
+    fn parse_items<'a>(&mut self, data: &'a [u8], end_positions: &[usize], items: Range<usize>, lpm: &PrefixTrie, cache: &mut StringCache<'a>) {
+        for index in items {
+            let start = end_positions[index];
+            let end = end_positions[index + 1];
 
             if start == end {
-                self.item_end_positions.push(self.compressed_data.len() / BITS_PER_TOKEN);
+                self.item_end_positions.push(self.stream_position());
                 continue;
             }
+
+            let first_token = self.stream_position();
+            if self.string_cache {
+                if let Some(&(cached_start, cached_end)) = cache.ranges.get(&data[start..end]) {
+                    // Repeated string: copy the tokens of its first occurrence
+                    self.repeat_tokens(cached_start, cached_end);
+                    cache.n_hits += 1;
+                    self.item_end_positions.push(self.stream_position());
+                    continue;
+                }
+            }
     
             let mut pos = start;
             while pos < end {
                 // Find the longest match
                 let (token_id, length) = lpm.find_longest_match(&data[pos..end]).unwrap();
-                let bits = token_id as u64;
-                self.compressed_data.append_bits(bits, BITS_PER_TOKEN); 
+                self.push_token(token_id);
                 pos += length;
             }
     
-            self.item_end_positions.push(self.compressed_data.len() / BITS_PER_TOKEN);
+            let last_token = self.stream_position();
+            if self.string_cache {
+                cache.ranges.insert(&data[start..end], (first_token, last_token));
+            }
+            self.item_end_positions.push(last_token);
+        }
+    }
+
+    /// Records the memory of the longest prefix matcher over the final dictionary
+    fn report_matcher(&mut self, lpm: &PrefixTrie) {
+        let stats = lpm.stats();
+        self.matcher_stats = Some(stats);
+        info!(
+            n_tokens = stats.n_tokens,
+            token_bytes = stats.token_bytes,
+            stored_bytes = stats.stored_bytes,
+            memory_bytes = stats.memory_bytes,
+            shared_fraction = stats.shared_fraction(),
+            "Longest prefix matcher built"
+        );
+    }
+
+    /// Records the hit rate of the string cache, once all strings are parsed
+    fn report_string_cache(&mut self, cache: &StringCache, n_strings: usize) {
+        if self.string_cache {
+            let n_hits = cache.n_hits;
+            let hit_rate = if n_strings == 0 { 0.0 } else { n_hits as f64 / n_strings as f64 };
+            self.string_cache_hit_rate = Some(hit_rate);
+            info!(n_hits, distinct_strings = cache.ranges.len(), hit_rate, "String cache statistics");
+        }
+    }
+}
+
+/// Layout of the token IDs in the token stream
+///
+/// Without inlining, token IDs take `bits_per_token` bits each, and stream positions
+/// count tokens. With inlined single-byte tokens, each token starts with an escape bit,
+/// followed by its ID in `LITERAL_BITS` bits if set, or by its ID minus the number of
+/// single-byte tokens in `bits_per_token` bits otherwise, and stream positions count bits.
+#[derive(Clone, Copy)]
+struct TokenCoding {
+    bits_per_token: usize,
+    literal_tokens: Option<usize>,  // Single-byte tokens, at the start of the dictionary, if inlined
+}
+
+impl TokenCoding {
+    /// Returns the bits per stream position
+    #[inline(always)]
+    fn position_bits(&self) -> usize {
+        match self.literal_tokens {
+            Some(_) => 1,
+            None => self.bits_per_token,
+        }
+    }
+}
+
+/// Token IDs between two stream positions of the token stream
+struct Tokens<'a> {
+    stream: &'a BitVector,
+    reader: BitReader<'a>,
+    end: usize,             // Bit position past the last token
+    coding: TokenCoding,
+}
+
+impl<'a> Tokens<'a> {
+    /// Reads the tokens from a stream position, ending before another one
+    fn new(stream: &'a BitVector, coding: TokenCoding, start: usize, end: usize) -> Self {
+        let position_bits = coding.position_bits();
+        Tokens { stream, reader: stream.reader(start * position_bits), end: end * position_bits, coding }
+    }
+
+    /// Skips the next tokens, decoding them only if tokens are variable-width
+    fn skip_tokens(&mut self, n: usize) {
+        match self.coding.literal_tokens {
+            Some(_) => {
+                for _ in 0..n {
+                    self.next();
+                }
+            }
+            None => self.reader = self.stream.reader(self.reader.pos() + n * self.coding.bits_per_token),
+        }
+    }
+}
+
+impl Iterator for Tokens<'_> {
+    type Item = usize;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<usize> {
+        if self.reader.pos() >= self.end {
+            return None;
+        }
+        // Tokens lie within the stream, ending at or before `end`
+        let token_id = unsafe {
+            match self.coding.literal_tokens {
+                None => self.reader.read_unchecked(self.coding.bits_per_token) as usize,
+                Some(_) if self.reader.read_unchecked(1) == 1 => self.reader.read_unchecked(LITERAL_BITS) as usize,
+                Some(literal_tokens) => self.reader.read_unchecked(self.coding.bits_per_token) as usize + literal_tokens,
+            }
+        };
+        Some(token_id)
+    }
+}
+
+/// Token range of the first occurrence of each parsed string, if the string cache is enabled
+#[derive(Default)]
+struct StringCache<'a> {
+    ranges: FxHashMap<&'a [u8], (usize, usize)>,
+    n_hits: usize,      // Strings found in the cache
+}
+
+/// Decodes a front-coded dictionary, padded for fixed-size token copies (see `pad_dictionary`)
+fn decode_dictionary(coded_dictionary: &CodedDictionary) -> (Vec<u8>, TokenOffsets) {
+    let (mut dictionary, dictionary_end_positions) = coded_dictionary.decode_all();
+    dictionary.extend_from_slice(&[0; MAX_COPY_WIDTH]);
+    (dictionary, dictionary_end_positions)
+}
+
+/// Returns the bytes copied per token by the fast path, for tokens of up to `max_token_length` bytes
+///
+/// Tokens bounded to 32 or 64 bytes are copied with a single fixed-size copy (two 16- or
+/// 32-byte registers), without branching on their length; longer bounds copy 16 bytes,
+/// then the rest of longer tokens.
+fn copy_width(max_token_length: usize) -> usize {
+    match max_token_length {
+        0..=16 => FAST_ACCESS_SIZE,
+        17..=32 => 32,
+        33..=MAX_COPY_WIDTH => MAX_COPY_WIDTH,
+        _ => FAST_ACCESS_SIZE,
+    }
+}
+
+/// Copies a sequence of tokens to the output, returning the number of bytes written
+///
+/// The dictionary must be padded (see `pad_dictionary`), and the output must have slack
+/// after the decompressed data (see `copy_token`).
+#[inline(always)]
+fn copy_tokens<O: Offset>(dictionary: &[u8], dictionary_end_positions: &[O], tokens: Tokens<'_>, buffer: &mut [u8], copy_width: usize) -> usize {
+    let dict_ptr = dictionary.as_ptr();
+    let end_positions_ptr = dictionary_end_positions.as_ptr();
+    let mut size = 0;
+
+    for token_id in tokens {
+        unsafe {
+            let dict_start = (*end_positions_ptr.add(token_id)).position();
+            let dict_end = (*end_positions_ptr.add(token_id + 1)).position();
+            let length = dict_end - dict_start;
+
+            copy_token(dict_ptr.add(dict_start), buffer.as_mut_ptr().add(size), length, copy_width);
+
+            size += length;
+        }
+    }
+
+    size
+}
+
+/// Copies a token to the output with fixed-size copies of `copy_width` bytes
+///
+/// # Safety
+/// `src` must be readable and `dst` writable for `max(length, copy_width)` bytes: the
+/// dictionary is padded, and output buffers have slack after the decompressed data.
+#[inline(always)]
+unsafe fn copy_token(src: *const u8, dst: *mut u8, length: usize, copy_width: usize) {
+    match copy_width {
+        32 => std::ptr::copy_nonoverlapping(src, dst, 32),
+        MAX_COPY_WIDTH => std::ptr::copy_nonoverlapping(src, dst, MAX_COPY_WIDTH),
+        _ => {
+            std::ptr::copy_nonoverlapping(src, dst, FAST_ACCESS_SIZE);
+            if length > FAST_ACCESS_SIZE {
+                std::ptr::copy_nonoverlapping(src.add(FAST_ACCESS_SIZE), dst.add(FAST_ACCESS_SIZE), length - FAST_ACCESS_SIZE);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::registry::AnyCompressor;
+    use crate::differential;
+
+    /// Returns strings of repeated words, so that training merges long tokens
+    fn words() -> (Vec<u8>, Vec<usize>) {
+        let mut data = Vec::new();
+        let mut end_positions = vec![0];
+        for i in 0..2000 {
+            data.extend_from_slice(format!("user-{} visited /page/{} at {}", i % 37, i % 101, i).as_bytes());
+            end_positions.push(data.len());
+        }
+        (data, end_positions)
+    }
+
+    #[test]
+    fn widened_dictionary_offsets_decode_the_same_strings() {
+        let (data, end_positions) = words();
+        for mut compressor in [OnPairBVCompressor::new(data.len(), end_positions.len() - 1), OnPairBVCompressor::with_literal_inlining(data.len(), end_positions.len() - 1)] {
+            compressor.compress(&data, &end_positions);
+            let narrow_size = compressor.space_used_bytes();
+
+            // Offsets widen on the first position past 4 GiB: rebuild them as if one had been pushed
+            let TokenOffsets::Narrow(offsets) = &compressor.dictionary_end_positions else {
+                panic!("dictionary offsets of a small dataset are widened");
+            };
+            compressor.dictionary_end_positions = TokenOffsets::Wide(offsets.iter().map(|&offset| offset as u64).collect());
+            let n_offsets = compressor.dictionary_end_positions.len();
+            assert_eq!(compressor.space_used_bytes(), narrow_size + n_offsets * (std::mem::size_of::<u64>() - std::mem::size_of::<u32>()));
+
+            let mut compressor = AnyCompressor::OnPairBV(Box::new(compressor));
+            let check = differential::check(&mut compressor, &data, &end_positions);
+            assert!(check.passed(), "{} returned different strings with 64-bit dictionary offsets", check.compressor_name);
         }
     }
 }
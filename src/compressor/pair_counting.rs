@@ -0,0 +1,211 @@
+//! Exact counting of token pairs in bounded memory
+//!
+//! Counting the pairs of adjacent tokens with a hash map takes memory proportional to
+//! the number of distinct pairs, up to 2^32 with 16-bit tokens, plus the overhead of the
+//! map. `ExternalPairCounter` bounds it instead by an external sort of the pair keys:
+//! pairs are buffered as 32-bit keys, and whenever the buffer is full it is sorted,
+//! collapsed into `(key, count)` entries and spilled to a temporary file as a sorted run.
+//! Merging the runs streams the exact count of every pair in key order, holding a single
+//! entry per run. Runs follow `std::env::temp_dir` and are removed once counted.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Identifier of the next spilled run, distinguishing the runs of concurrent counters
+static NEXT_RUN_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Pair of adjacent token IDs
+pub type Pair = (u16, u16);
+
+/// Counter of token pairs spilling sorted runs of pair keys to disk
+pub struct ExternalPairCounter {
+    buffer: Vec<u32>,       // Keys of the pairs pushed since the last spill
+    capacity: usize,        // Keys buffered before spilling
+    runs: Vec<PathBuf>,     // Sorted runs spilled so far
+}
+
+impl ExternalPairCounter {
+    /// Creates a counter buffering at most `memory_budget` bytes of pair keys
+    pub fn new(memory_budget: usize) -> Self {
+        let capacity = (memory_budget / std::mem::size_of::<u32>()).max(1);
+        ExternalPairCounter { buffer: Vec::with_capacity(capacity), capacity, runs: Vec::new() }
+    }
+
+    /// Counts one occurrence of a pair, spilling the buffered pairs if the buffer is full
+    pub fn push(&mut self, pair: Pair) -> io::Result<()> {
+        self.buffer.push(((pair.0 as u32) << 16) | pair.1 as u32);
+        if self.buffer.len() == self.capacity {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the number of runs spilled to disk so far
+    pub fn n_runs(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Returns the exact number of occurrences of every pair pushed, in increasing order of pairs
+    ///
+    /// The buffered pairs are counted in memory if no run was spilled, and spilled as
+    /// the last run otherwise.
+    pub fn counts(mut self) -> io::Result<PairCounts> {
+        let mut sources = Vec::new();
+        if self.runs.is_empty() {
+            self.buffer.sort_unstable();
+            sources.push(RunReader::Memory(collapse(&self.buffer).collect::<Vec<_>>().into_iter()));
+        } else {
+            if !self.buffer.is_empty() {
+                self.spill()?;
+            }
+            for path in self.runs.iter() {
+                sources.push(RunReader::File(BufReader::new(File::open(path)?)));
+            }
+        }
+
+        let mut heads = BinaryHeap::with_capacity(sources.len());
+        for (run, source) in sources.iter_mut().enumerate() {
+            if let Some((key, count)) = source.next_entry()? {
+                heads.push(Reverse((key, count, run)));
+            }
+        }
+        Ok(PairCounts { sources, heads, paths: std::mem::take(&mut self.runs) })
+    }
+
+    /// Sorts the buffered keys and writes them to a new run as `(key, count)` entries
+    fn spill(&mut self) -> io::Result<()> {
+        self.buffer.sort_unstable();
+        let id = NEXT_RUN_ID.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("pair_counts_{}_{}.bin", std::process::id(), id));
+        let file = File::create(&path)?;
+        self.runs.push(path);
+
+        let mut writer = BufWriter::new(file);
+        for (key, count) in collapse(&self.buffer) {
+            writer.write_all(&key.to_le_bytes())?;
+            writer.write_all(&count.to_le_bytes())?;
+        }
+        writer.flush()?;
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+impl Drop for ExternalPairCounter {
+    fn drop(&mut self) {
+        for path in self.runs.iter() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Exact counts of the pairs of an `ExternalPairCounter`, merged from its runs in key order
+pub struct PairCounts {
+    sources: Vec<RunReader>,
+    heads: BinaryHeap<Reverse<(u32, u32, usize)>>,  // Next entry of each run: key, count and run
+    paths: Vec<PathBuf>,                            // Spilled runs, removed once counted
+}
+
+impl Iterator for PairCounts {
+    type Item = io::Result<(Pair, usize)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((key, _, _)) = *self.heads.peek()?;
+        let mut total = 0;
+        // Sum the entries of the key across runs, advancing each run past it
+        while let Some(&Reverse((head_key, count, run))) = self.heads.peek() {
+            if head_key != key {
+                break;
+            }
+            self.heads.pop();
+            total += count as usize;
+            match self.sources[run].next_entry() {
+                Ok(Some((next_key, next_count))) => self.heads.push(Reverse((next_key, next_count, run))),
+                Ok(None) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        Some(Ok((((key >> 16) as u16, key as u16), total)))
+    }
+}
+
+impl Drop for PairCounts {
+    fn drop(&mut self) {
+        for path in self.paths.iter() {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Sorted run of `(key, count)` entries, in memory or spilled to a file
+enum RunReader {
+    Memory(std::vec::IntoIter<(u32, u32)>),
+    File(BufReader<File>),
+}
+
+impl RunReader {
+    /// Reads the next entry of the run, None once exhausted
+    fn next_entry(&mut self) -> io::Result<Option<(u32, u32)>> {
+        match self {
+            RunReader::Memory(entries) => Ok(entries.next()),
+            RunReader::File(reader) => {
+                let mut entry = [0u8; 8];
+                match reader.read_exact(&mut entry) {
+                    Ok(()) => Ok(Some((
+                        u32::from_le_bytes(entry[..4].try_into().unwrap()),
+                        u32::from_le_bytes(entry[4..].try_into().unwrap()),
+                    ))),
+                    Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+                    Err(e) => Err(e),
+                }
+            }
+        }
+    }
+}
+
+/// Collapses sorted keys into `(key, count)` entries
+fn collapse(keys: &[u32]) -> impl Iterator<Item = (u32, u32)> + '_ {
+    keys.chunk_by(|a, b| a == b).map(|run| (run[0], run.len() as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::BTreeMap;
+
+    fn count_all(mut counter: ExternalPairCounter, pairs: &[Pair]) -> (usize, Vec<(Pair, usize)>) {
+        for &pair in pairs {
+            counter.push(pair).unwrap();
+        }
+        let n_runs = counter.n_runs();
+        (n_runs, counter.counts().unwrap().collect::<io::Result<Vec<_>>>().unwrap())
+    }
+
+    #[test]
+    fn spilled_runs_count_the_same_pairs_as_memory() {
+        let mut rng = StdRng::seed_from_u64(1163);
+        let pairs: Vec<Pair> = (0..10_000).map(|_| (rng.gen_range(0..20), rng.gen_range(0..300))).collect();
+        let mut expected: BTreeMap<Pair, usize> = BTreeMap::new();
+        for &pair in &pairs {
+            *expected.entry(pair).or_default() += 1;
+        }
+        let expected: Vec<(Pair, usize)> = expected.into_iter().collect();
+
+        let (n_runs, in_memory) = count_all(ExternalPairCounter::new((pairs.len() + 1) * std::mem::size_of::<u32>()), &pairs);
+        assert_eq!(n_runs, 0);
+        assert_eq!(in_memory, expected);
+
+        // 37 keys per run: runs end mid-way through the occurrences of most pairs, and the
+        // last one is partial
+        let counter = ExternalPairCounter::new(37 * std::mem::size_of::<u32>());
+        let (n_runs, spilled) = count_all(counter, &pairs);
+        assert_eq!(n_runs, pairs.len().div_ceil(37) - 1);
+        assert_eq!(spilled, expected);
+    }
+}
@@ -0,0 +1,149 @@
+//! Vectorized common prefix length of byte strings
+//!
+//! Prefix comparisons (shared prefixes of consecutive strings in front coding, candidate
+//! tokens in longest prefix matching) are usually written as byte-by-byte loops or with
+//! `starts_with`, which stop at the first difference only after comparing whole slices.
+//! `common_prefix_length` compares 32 bytes at a time with AVX2 (if available at run time)
+//! and 16 bytes at a time with SSE2, locating the first differing byte of a chunk from the
+//! movemask of the byte-wise equality, and falls back to 8-byte words elsewhere.
+//!
+//! The longest prefix matchers of OnPair and OnPair16 are implemented by the external
+//! `onpair_rs` crate and compare tokens there; this module accelerates the prefix
+//! comparisons implemented in this crate, including the edge labels of the trie matching
+//! the tokens of OnPairBV (see `prefix_trie`).
+
+/// Returns the length of the longest common prefix of two byte strings
+#[inline]
+pub fn common_prefix_length(a: &[u8], b: &[u8]) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        let n = a.len().min(b.len());
+        if n >= 32 && std::is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 support was just checked
+            return unsafe { common_prefix_length_avx2(a, b) };
+        }
+        if n >= 16 {
+            // SAFETY: SSE2 is part of the x86_64 baseline
+            return unsafe { common_prefix_length_sse2(a, b) };
+        }
+    }
+    common_prefix_length_scalar(a, b)
+}
+
+/// Returns whether `prefix` is a prefix of `s` (vectorized `starts_with`)
+#[inline]
+pub fn starts_with(s: &[u8], prefix: &[u8]) -> bool {
+    prefix.len() <= s.len() && common_prefix_length(s, prefix) == prefix.len()
+}
+
+/// Portable implementation of `common_prefix_length`, comparing 8-byte words
+#[inline]
+pub fn common_prefix_length_scalar(a: &[u8], b: &[u8]) -> usize {
+    let n = a.len().min(b.len());
+    let mut i = 0;
+    while i + 8 <= n {
+        let x = u64::from_le_bytes(a[i..i + 8].try_into().unwrap());
+        let y = u64::from_le_bytes(b[i..i + 8].try_into().unwrap());
+        let difference = x ^ y;
+        if difference != 0 {
+            // Little-endian loading puts the first byte in the least significant bits
+            return i + difference.trailing_zeros() as usize / 8;
+        }
+        i += 8;
+    }
+    i + a[i..n].iter().zip(&b[i..n]).take_while(|(x, y)| x == y).count()
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn common_prefix_length_avx2(a: &[u8], b: &[u8]) -> usize {
+    use std::arch::x86_64::{__m256i, _mm256_cmpeq_epi8, _mm256_loadu_si256, _mm256_movemask_epi8};
+
+    let n = a.len().min(b.len());
+    let mut i = 0;
+    while i + 32 <= n {
+        let x = _mm256_loadu_si256(a.as_ptr().add(i) as *const __m256i);
+        let y = _mm256_loadu_si256(b.as_ptr().add(i) as *const __m256i);
+        let equal = _mm256_movemask_epi8(_mm256_cmpeq_epi8(x, y)) as u32;
+        if equal != u32::MAX {
+            return i + (!equal).trailing_zeros() as usize;
+        }
+        i += 32;
+    }
+    i + common_prefix_length_sse2(&a[i..n], &b[i..n])
+}
+
+#[cfg(target_arch = "x86_64")]
+#[inline]
+unsafe fn common_prefix_length_sse2(a: &[u8], b: &[u8]) -> usize {
+    use std::arch::x86_64::{__m128i, _mm_cmpeq_epi8, _mm_loadu_si128, _mm_movemask_epi8};
+
+    let n = a.len().min(b.len());
+    let mut i = 0;
+    while i + 16 <= n {
+        let x = _mm_loadu_si128(a.as_ptr().add(i) as *const __m128i);
+        let y = _mm_loadu_si128(b.as_ptr().add(i) as *const __m128i);
+        let equal = _mm_movemask_epi8(_mm_cmpeq_epi8(x, y)) as u32;
+        if equal != 0xffff {
+            return i + (!equal).trailing_zeros() as usize;
+        }
+        i += 16;
+    }
+    i + common_prefix_length_scalar(&a[i..n], &b[i..n])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a string of `length` bytes without repeats within 251 bytes
+    fn string(length: usize) -> Vec<u8> {
+        (0..length).map(|i| (i * 7 % 251) as u8).collect()
+    }
+
+    #[test]
+    fn equal_strings_of_every_length_match_the_scalar_path() {
+        for length in 0..=160 {
+            let a = string(length);
+            assert_eq!(common_prefix_length(&a, &a), length);
+            assert_eq!(common_prefix_length_scalar(&a, &a), length);
+            assert!(starts_with(&a, &a));
+        }
+    }
+
+    #[test]
+    fn every_mismatch_position_matches_the_scalar_path() {
+        for length in [1, 7, 8, 15, 16, 17, 31, 32, 33, 63, 64, 65, 100, 130] {
+            let a = string(length);
+            for mismatch in 0..length {
+                let mut b = a.clone();
+                b[mismatch] ^= 0x80;
+                assert_eq!(common_prefix_length(&a, &b), mismatch, "length {}", length);
+                assert_eq!(common_prefix_length_scalar(&a, &b), mismatch, "length {}", length);
+                assert_eq!(common_prefix_length(&b, &a), mismatch, "length {}", length);
+                assert!(!starts_with(&a, &b));
+            }
+        }
+    }
+
+    #[test]
+    fn unequal_lengths_stop_at_the_shorter_string() {
+        let long = string(150);
+        for length in 0..=long.len() {
+            let short = &long[..length];
+            assert_eq!(common_prefix_length(&long, short), length);
+            assert_eq!(common_prefix_length(short, &long), length);
+            assert_eq!(common_prefix_length_scalar(short, &long), length);
+            assert!(starts_with(&long, short));
+            assert_eq!(starts_with(short, &long), length == long.len());
+
+            // A mismatch past the end of the shorter string is not seen
+            let mut other = long.clone();
+            other[length.min(long.len() - 1)] ^= 1;
+            let expected = length.min(long.len() - 1);
+            assert_eq!(common_prefix_length(short, &other), expected.min(length));
+            assert_eq!(common_prefix_length(&long, &other), expected);
+            assert_eq!(common_prefix_length_scalar(&long, &other), expected);
+        }
+    }
+}
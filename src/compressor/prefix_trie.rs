@@ -0,0 +1,215 @@
+//! Longest prefix matching over tokens sharing their prefixes
+//!
+//! Dictionaries built by merging tokens are full of shared prefixes: every merged token
+//! extends an existing one, so that a matcher storing the bytes of each token
+//! independently stores the common prefixes once per token. `PrefixTrie` is a compacted
+//! trie (radix tree) instead: the bytes shared by several tokens are stored once, as the
+//! label of the edge leading to the node where they diverge, and each token only adds
+//! the tail bytes following its longest prefix already in the trie to a single byte
+//! arena. Splitting an edge splits the range of its label in place, so that inserted
+//! bytes are never copied nor moved.
+//!
+//! Children are found through a single hash map keyed by the parent node and the first
+//! byte of the edge, and edge labels are compared with the vectorized comparisons of
+//! `prefix_match`. Matching visits one node per branching point of the matched token,
+//! rather than one per byte.
+//! `MatcherStats` reports the memory of the trie against the bytes of the tokens it holds.
+
+use super::prefix_match::{common_prefix_length, starts_with};
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+/// Marks nodes ending no token
+const NO_VALUE: u32 = u32::MAX;
+/// Largest token ID held by the trie
+pub const MAX_TOKEN_ID: usize = NO_VALUE as usize - 1;
+
+/// Compacted trie mapping tokens to IDs, matching the longest token prefixing a string
+pub struct PrefixTrie {
+    arena: Vec<u8>,                         // Edge labels, each token tail stored once
+    nodes: Vec<Node>,                       // Nodes, the root first
+    children: FxHashMap<(u32, u8), u32>,    // Child of a node by the first byte of its edge
+    n_tokens: usize,                        // Tokens inserted
+    token_bytes: usize,                     // Bytes of the tokens inserted
+}
+
+/// Node of the trie, reached through an edge labeled by a range of the arena
+#[derive(Clone, Copy)]
+struct Node {
+    label_start: u64,   // Start of the edge label in the arena, which may exceed 4 GiB
+    label_length: u32,  // Length of the edge label (0 for the root)
+    value: u32,         // ID of the token ending at the node, or `NO_VALUE`
+}
+
+/// Memory of a longest prefix matcher, against the tokens it holds
+///
+/// Recorded by OnPairBV once its dictionary is built (see `OnPairBVCompressor::matcher_stats`).
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct MatcherStats {
+    pub n_tokens: usize,        // Tokens held by the matcher
+    pub token_bytes: usize,     // Bytes of the tokens, as stored by an unshared matcher
+    pub stored_bytes: usize,    // Bytes of the tokens stored by the matcher, shared prefixes once
+    pub memory_bytes: usize,    // Memory allocated by the matcher, nodes and index included
+}
+
+impl MatcherStats {
+    /// Returns the fraction of the token bytes saved by sharing prefixes
+    pub fn shared_fraction(&self) -> f64 {
+        if self.token_bytes == 0 { 0.0 } else { 1.0 - self.stored_bytes as f64 / self.token_bytes as f64 }
+    }
+}
+
+impl PrefixTrie {
+    /// Creates an empty trie
+    pub fn new() -> Self {
+        PrefixTrie {
+            arena: Vec::new(),
+            nodes: vec![Node { label_start: 0, label_length: 0, value: NO_VALUE }],
+            children: FxHashMap::default(),
+            n_tokens: 0,
+            token_bytes: 0,
+        }
+    }
+
+    /// Inserts a token, replacing the ID of an equal token inserted before
+    ///
+    /// # Arguments
+    /// - `token`: Bytes of the token, not empty
+    /// - `value`: ID of the token, at most `MAX_TOKEN_ID`
+    pub fn insert(&mut self, token: &[u8], value: usize) {
+        debug_assert!(!token.is_empty());
+        assert!(value <= MAX_TOKEN_ID, "Token ID {} exceeds the maximum of {} held by the prefix trie", value, MAX_TOKEN_ID);
+        let mut node = 0;
+        let mut pos = 0;
+        while pos < token.len() {
+            let Some(&child) = self.children.get(&(node, token[pos])) else {
+                // No edge continues the token: store its tail as a new leaf
+                let leaf = self.push_node(&token[pos..]);
+                self.children.insert((node, token[pos]), leaf);
+                node = leaf;
+                pos = token.len();
+                break;
+            };
+            let label = self.label(child);
+            let length = common_prefix_length(label, &token[pos..]);
+            if length < label.len() {
+                // The token leaves the edge (or ends) within its label: split the edge
+                let middle = self.split(node, child, length);
+                node = middle;
+                pos += length;
+                if pos < token.len() {
+                    let leaf = self.push_node(&token[pos..]);
+                    self.children.insert((middle, token[pos]), leaf);
+                    node = leaf;
+                    pos = token.len();
+                }
+                break;
+            }
+            node = child;
+            pos += length;
+        }
+        debug_assert_eq!(pos, token.len());
+
+        let entry = &mut self.nodes[node as usize].value;
+        if *entry == NO_VALUE {
+            self.n_tokens += 1;
+            self.token_bytes += token.len();
+        }
+        *entry = value as u32;
+    }
+
+    /// Finds the longest token prefixing a string
+    ///
+    /// # Returns
+    /// The ID and length of the longest token prefixing `s`, or `None` if no token does
+    #[inline]
+    pub fn find_longest_match(&self, s: &[u8]) -> Option<(usize, usize)> {
+        let mut longest = None;
+        let mut node = 0;
+        let mut pos = 0;
+        while pos < s.len() {
+            let Some(&child) = self.children.get(&(node, s[pos])) else {
+                break;
+            };
+            let label = self.label(child);
+            if !starts_with(&s[pos..], label) {
+                break;
+            }
+            node = child;
+            pos += label.len();
+            let value = self.nodes[node as usize].value;
+            if value != NO_VALUE {
+                longest = Some((value as usize, pos));
+            }
+        }
+        longest
+    }
+
+    /// Returns the number of tokens in the trie
+    pub fn len(&self) -> usize {
+        self.n_tokens
+    }
+
+    /// Checks whether the trie holds no token
+    pub fn is_empty(&self) -> bool {
+        self.n_tokens == 0
+    }
+
+    /// Returns the memory of the trie against the tokens it holds
+    pub fn stats(&self) -> MatcherStats {
+        // Hash map entries take their key, their value and a control byte
+        let entry_size = std::mem::size_of::<((u32, u8), u32)>() + 1;
+        MatcherStats {
+            n_tokens: self.n_tokens,
+            token_bytes: self.token_bytes,
+            stored_bytes: self.arena.len(),
+            memory_bytes: self.arena.capacity()
+                + self.nodes.capacity() * std::mem::size_of::<Node>()
+                + self.children.capacity() * entry_size,
+        }
+    }
+
+    /// Returns the label of the edge leading to a node
+    #[inline(always)]
+    fn label(&self, node: u32) -> &[u8] {
+        let node = self.nodes[node as usize];
+        let label_start = node.label_start as usize;
+        &self.arena[label_start..label_start + node.label_length as usize]
+    }
+
+    /// Appends a node without token, labeled by bytes appended to the arena
+    fn push_node(&mut self, label: &[u8]) -> u32 {
+        let node = self.next_node();
+        self.nodes.push(Node { label_start: self.arena.len() as u64, label_length: label.len() as u32, value: NO_VALUE });
+        self.arena.extend_from_slice(label);
+        node
+    }
+
+    /// Returns the index of the next node, panicking if it exceeds the 32-bit node indices
+    fn next_node(&self) -> u32 {
+        u32::try_from(self.nodes.len()).unwrap_or_else(|_| panic!("Prefix trie exceeds {} nodes, the maximum addressable by 32-bit indices", u32::MAX))
+    }
+
+    /// Splits the edge from `parent` to `child` after `length` bytes of its label
+    ///
+    /// # Returns
+    /// The node inserted between `parent` and `child`, labeled by the first `length` bytes
+    fn split(&mut self, parent: u32, child: u32, length: usize) -> u32 {
+        let Node { label_start, label_length, .. } = self.nodes[child as usize];
+        let first_byte = self.arena[label_start as usize];
+        let middle = self.next_node();
+        self.nodes.push(Node { label_start, label_length: length as u32, value: NO_VALUE });
+        self.nodes[child as usize].label_start = label_start + length as u64;
+        self.nodes[child as usize].label_length = label_length - length as u32;
+
+        self.children.insert((parent, first_byte), middle);
+        self.children.insert((middle, self.arena[(label_start as usize) + length]), child);
+        middle
+    }
+}
+
+impl Default for PrefixTrie {
+    fn default() -> Self {
+        Self::new()
+    }
+}
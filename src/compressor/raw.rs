@@ -3,30 +3,77 @@
 //! Provides a no-compression baseline for performance comparison. Simply stores
 //! data in its original form while maintaining the same interface as compressed
 //! algorithms.
+//!
+//! Data can be kept on the heap, or written to a temporary file and accessed either
+//! through a memory mapping (with configurable `madvise` hints) or through `O_DIRECT`
+//! reads bypassing the page cache. Disk-backed modes establish latency floors under
+//! different storage assumptions; the file location follows `std::env::temp_dir`
+//! (i.e., the `TMPDIR` environment variable).
+//!
+//! String boundaries can be kept bit-packed or Elias-Fano coded instead of one machine
+//! word each (see `offset_coding`), so that on short strings the baseline measures the
+//! cost of copying strings rather than the size of its offsets.
+
+use crate::compressor::{item_at_offset, max_len_of_items, Compressor, SpaceBreakdown};
+use crate::compressor::offset_coding::{CodedOffsets, OffsetCoding};
+use crate::profiler::{Stage, StageTimer};
+use std::io::{self, Write};
+use tracing::info;
+#[cfg(target_os = "linux")]
+use std::fs::{self, File, OpenOptions};
+#[cfg(target_os = "linux")]
+use std::os::unix::fs::OpenOptionsExt;
+#[cfg(target_os = "linux")]
+use std::os::unix::io::AsRawFd;
+#[cfg(target_os = "linux")]
+use std::path::PathBuf;
+#[cfg(target_os = "linux")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(target_os = "linux")]
+use tracing::warn;
+
+/// Alignment required by `O_DIRECT` reads (offset, length and buffer address)
+#[cfg(target_os = "linux")]
+const DIRECT_IO_ALIGNMENT: usize = 4096;
 
-use crate::compressor::Compressor;
+/// Counter used to generate unique names for backing files
+#[cfg(target_os = "linux")]
+static NEXT_FILE_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Storage location of the baseline data
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RawStorage {
+    Heap,               // In-memory vector
+    Mmap(MmapAdvice),   // Memory-mapped temporary file
+    Direct,             // Temporary file read with O_DIRECT
+}
+
+/// Access pattern hint passed to `madvise` for memory-mapped storage
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MmapAdvice {
+    Normal,     // MADV_NORMAL: default readahead
+    Random,     // MADV_RANDOM: disable readahead
+    Sequential, // MADV_SEQUENTIAL: aggressive readahead
+    WillNeed,   // MADV_WILLNEED: prefetch the whole mapping
+}
 
 /// Baseline compressor that stores data without compression
-/// 
+///
 /// Maintains original data layout while implementing the Compressor interface.
 /// Used as a performance baseline to measure compression algorithm trade-offs.
 pub struct RawCompressor {
-    compressed_data: Vec<u8>,   // Original uncompressed data
-    offsets: Vec<usize>,        // Boundary positions for random access
+    storage: RawStorage,
+    compressed_data: Vec<u8>,       // Original uncompressed data (heap storage)
+    offsets: Vec<usize>,            // Boundary positions for random access (plain coding)
+    offset_coding: OffsetCoding,
+    coded_offsets: Option<CodedOffsets>,    // Coded boundary positions, replacing `offsets`
+    #[cfg(target_os = "linux")]
+    file: Option<BackingFile>,      // Backing file (disk storage)
 }
 
 impl Compressor for RawCompressor {
     fn new(data_size: usize, n_elements: usize) -> Self {
-        let mut compressed_data = Vec::with_capacity(data_size);
-        compressed_data.resize(data_size, 0);
-        
-        let mut offsets = Vec::with_capacity(n_elements + 1);
-        offsets.resize(n_elements + 1, 0);
-        
-        Self {
-            compressed_data,
-            offsets,
-        }
+        Self::with_storage(data_size, n_elements, RawStorage::Heap)
     }
 
     fn compress(&mut self, data: &[u8], end_positions: &[usize]) {
@@ -36,6 +83,16 @@ impl Compressor for RawCompressor {
             let dst = self.offsets.as_mut_ptr();
             std::ptr::copy_nonoverlapping(src, dst, end_positions.len());
         }
+        self.code_offsets();
+
+        if self.storage != RawStorage::Heap {
+            #[cfg(target_os = "linux")]
+            {
+                let max_item_size = end_positions.windows(2).map(|w| w[1] - w[0]).max().unwrap_or(0);
+                self.file = Some(BackingFile::create(data, self.storage, max_item_size));
+            }
+            return;
+        }
 
         // Copy data unchanged
         unsafe {
@@ -46,6 +103,11 @@ impl Compressor for RawCompressor {
     }
 
     fn decompress(&self, buffer: &mut [u8]) -> usize {
+        #[cfg(target_os = "linux")]
+        if let Some(file) = self.file.as_ref() {
+            return file.read_all(buffer);
+        }
+
         unsafe {
             let src = self.compressed_data.as_ptr();
             let dst = buffer.as_mut_ptr();
@@ -55,13 +117,29 @@ impl Compressor for RawCompressor {
         self.compressed_data.len()
     }
 
+    fn decompress_to<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        #[cfg(target_os = "linux")]
+        if let Some(file) = self.file.as_ref() {
+            return file.write_to(writer);
+        }
+
+        writer.write_all(&self.compressed_data)?;
+        writer.flush()?;
+        Ok(self.compressed_data.len())
+    }
+
     #[inline(always)]
     fn get_item_at(&mut self, index: usize, buffer: &mut [u8]) -> usize {
         unsafe {
-            let start = self.offsets[index];
-            let end = self.offsets[index + 1];
+            let (start, end) = self.item_bounds(index);
             let item_size = end - start;
-            
+
+            #[cfg(target_os = "linux")]
+            if let Some(file) = self.file.as_mut() {
+                file.read_range(start, end, buffer);
+                return item_size;
+            }
+
             let src = self.compressed_data.as_ptr().add(start);
             let dst = buffer.as_mut_ptr();
             std::ptr::copy_nonoverlapping(src, dst, item_size);
@@ -70,11 +148,378 @@ impl Compressor for RawCompressor {
         }
     }
 
+    fn get_item_at_profiled(&mut self, index: usize, buffer: &mut [u8], timer: &mut StageTimer) -> usize {
+        let (start, end) = self.item_bounds(index);
+        timer.lap(Stage::Lookup);
+
+        // Raw data needs no decoding: reads from the backing file are charged to copying
+        #[cfg(target_os = "linux")]
+        if let Some(file) = self.file.as_mut() {
+            file.read_range(start, end, buffer);
+            timer.lap(Stage::Copy);
+            return end - start;
+        }
+
+        buffer[..end - start].copy_from_slice(&self.compressed_data[start..end]);
+        timer.lap(Stage::Copy);
+        end - start
+    }
+
+    #[inline(always)]
+    fn get_item_ref(&mut self, index: usize) -> Option<&[u8]> {
+        let (start, end) = self.item_bounds(index);
+
+        #[cfg(target_os = "linux")]
+        if let Some(file) = self.file.as_mut() {
+            return Some(file.range_ref(start, end));
+        }
+
+        Some(&self.compressed_data[start..end])
+    }
+
+    /// Projects the strings in place, without copying them
+    fn scan_range<P: FnMut(usize, &[u8])>(&mut self, start_index: usize, end_index: usize, mut projector: P) {
+        for index in start_index..end_index {
+            projector(index, self.get_item_ref(index).unwrap());
+        }
+    }
+
+    fn len_of_item(&self, index: usize) -> usize {
+        let (start, end) = self.item_bounds(index);
+        end - start
+    }
+
+    fn max_item_len(&self) -> usize {
+        let n_offsets = self.coded_offsets.as_ref().map_or(self.offsets.len(), CodedOffsets::len);
+        max_len_of_items(self, n_offsets.saturating_sub(1))
+    }
+
+    fn offset_to_item(&self, offset: usize) -> Option<(usize, usize)> {
+        match self.coded_offsets.as_ref() {
+            Some(coded_offsets) => coded_offsets.find(offset),
+            None => item_at_offset(&self.offsets, offset),
+        }
+    }
+
     fn space_used_bytes(&self) -> usize {
-        self.compressed_data.len()
+        self.space_breakdown().total()
+    }
+
+    fn space_breakdown(&self) -> SpaceBreakdown {
+        SpaceBreakdown {
+            payload: self.payload_size(),
+            item_offsets: self.item_offsets_size(),
+            ..SpaceBreakdown::default()
+        }
     }
-    
+
     fn name(&self) -> &str {
-        "Raw"
+        match (self.offset_coding, self.storage) {
+            (OffsetCoding::BitPacked, _) => "Raw (bit-packed offsets)",
+            (OffsetCoding::EliasFano, _) => "Raw (Elias-Fano offsets)",
+            (OffsetCoding::Plain, RawStorage::Heap) => "Raw",
+            (OffsetCoding::Plain, RawStorage::Mmap(_)) => "Raw (mmap)",
+            (OffsetCoding::Plain, RawStorage::Direct) => "Raw (direct)",
+        }
+    }
+}
+
+impl RawCompressor {
+    /// Creates a baseline compressor using the given storage location
+    ///
+    /// # Arguments
+    /// - `data_size`: Total size of input data in bytes
+    /// - `n_elements`: Number of individual strings in the dataset
+    /// - `storage`: Where the uncompressed data is kept
+    pub fn with_storage(data_size: usize, n_elements: usize, storage: RawStorage) -> Self {
+        #[cfg(not(target_os = "linux"))]
+        assert!(storage == RawStorage::Heap, "Disk-backed raw storage is only supported on Linux");
+
+        let compressed_data = match storage {
+            RawStorage::Heap => vec![0; data_size],
+            _ => Vec::new(),
+        };
+
+        Self {
+            storage,
+            compressed_data,
+            offsets: vec![0; n_elements + 1],
+            offset_coding: OffsetCoding::Plain,
+            coded_offsets: None,
+            #[cfg(target_os = "linux")]
+            file: None,
+        }
+    }
+
+    /// Creates an in-memory baseline compressor storing coded string boundaries
+    ///
+    /// # Arguments
+    /// - `data_size`: Total size of input data in bytes
+    /// - `n_elements`: Number of individual strings in the dataset
+    /// - `offset_coding`: Storage of the string boundaries (see `OffsetCoding`)
+    pub fn with_offset_coding(data_size: usize, n_elements: usize, offset_coding: OffsetCoding) -> Self {
+        Self { offset_coding, ..Self::with_storage(data_size, n_elements, RawStorage::Heap) }
+    }
+
+    /// Returns the byte range `[start, end)` of a string
+    #[inline(always)]
+    fn item_bounds(&self, index: usize) -> (usize, usize) {
+        match self.coded_offsets.as_ref() {
+            Some(coded_offsets) => coded_offsets.bounds(index),
+            None => (self.offsets[index], self.offsets[index + 1]),
+        }
+    }
+
+    /// Returns the size of the stored strings in bytes
+    fn payload_size(&self) -> usize {
+        match self.coded_offsets.as_ref() {
+            Some(coded_offsets) if !coded_offsets.is_empty() => coded_offsets.get(coded_offsets.len() - 1),
+            Some(_) => 0,
+            None => *self.offsets.last().unwrap_or(&0),
+        }
+    }
+
+    /// Returns the space used by the string boundaries in bytes
+    fn item_offsets_size(&self) -> usize {
+        match self.coded_offsets.as_ref() {
+            Some(coded_offsets) => coded_offsets.size_bytes(),
+            None => self.offsets.len() * std::mem::size_of::<usize>(),
+        }
+    }
+
+    /// Codes the copied string boundaries, if configured, replacing the plain ones
+    fn code_offsets(&mut self) {
+        if self.offset_coding == OffsetCoding::Plain {
+            return;
+        }
+        let plain_size = self.item_offsets_size();
+        let coded_offsets = CodedOffsets::new(&self.offsets, self.offset_coding);
+        let coded_size = coded_offsets.size_bytes();
+        info!(
+            coding = self.offset_coding.name(),
+            plain_size,
+            coded_size,
+            reduction = 1.0 - coded_size as f64 / plain_size.max(1) as f64,
+            "Item offsets coded"
+        );
+        self.offsets = Vec::new();
+        self.coded_offsets = Some(coded_offsets);
+    }
+}
+
+/// Temporary file holding the baseline data for disk-backed storage
+///
+/// The file is removed (and unmapped, if mapped) when dropped.
+#[cfg(target_os = "linux")]
+struct BackingFile {
+    path: PathBuf,
+    file: File,
+    size: usize,
+    mapping: *mut u8,       // Start of the memory mapping (null for direct reads)
+    scratch: Vec<u8>,       // Over-allocated buffer for aligned direct reads
+}
+
+// SAFETY: The mapping is read-only and owned by the backing file, which unmaps it only
+// when dropped; reads through `&self` never mutate shared state.
+#[cfg(target_os = "linux")]
+unsafe impl Send for BackingFile {}
+#[cfg(target_os = "linux")]
+unsafe impl Sync for BackingFile {}
+
+#[cfg(target_os = "linux")]
+impl BackingFile {
+    /// Writes `data` to a new temporary file and prepares it for access
+    fn create(data: &[u8], storage: RawStorage, max_item_size: usize) -> Self {
+        let id = NEXT_FILE_ID.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("raw_baseline_{}_{}.bin", std::process::id(), id));
+        fs::write(&path, data).expect("Failed to write raw baseline file");
+
+        match storage {
+            RawStorage::Mmap(advice) => {
+                let file = File::open(&path).expect("Failed to open raw baseline file");
+                let mapping = Self::map(&file, data.len(), advice);
+                BackingFile { path, file, size: data.len(), mapping, scratch: Vec::new() }
+            }
+            RawStorage::Direct => {
+                let file = Self::open_direct(&path);
+                // An item spans at most two partially covered aligned blocks, plus alignment slack
+                let scratch = vec![0; max_item_size + 3 * DIRECT_IO_ALIGNMENT];
+                BackingFile { path, file, size: data.len(), mapping: std::ptr::null_mut(), scratch }
+            }
+            RawStorage::Heap => unreachable!(),
+        }
+    }
+
+    /// Maps the file in memory and applies the access pattern hint
+    fn map(file: &File, size: usize, advice: MmapAdvice) -> *mut u8 {
+        if size == 0 {
+            return std::ptr::null_mut();
+        }
+
+        unsafe {
+            let ptr = libc::mmap(std::ptr::null_mut(), size, libc::PROT_READ, libc::MAP_SHARED, file.as_raw_fd(), 0);
+            assert!(ptr != libc::MAP_FAILED, "Failed to map raw baseline file");
+
+            let advice = match advice {
+                MmapAdvice::Normal => libc::MADV_NORMAL,
+                MmapAdvice::Random => libc::MADV_RANDOM,
+                MmapAdvice::Sequential => libc::MADV_SEQUENTIAL,
+                MmapAdvice::WillNeed => libc::MADV_WILLNEED,
+            };
+            if libc::madvise(ptr, size, advice) != 0 {
+                warn!("madvise failed on raw baseline mapping.");
+            }
+
+            ptr as *mut u8
+        }
+    }
+
+    /// Opens the file with O_DIRECT, falling back to buffered reads if unsupported
+    fn open_direct(path: &PathBuf) -> File {
+        match OpenOptions::new().read(true).custom_flags(libc::O_DIRECT).open(path) {
+            Ok(file) => file,
+            Err(_) => {
+                warn!(path = %path.display(), "O_DIRECT not supported. Falling back to buffered reads.");
+                File::open(path).expect("Failed to open raw baseline file")
+            }
+        }
+    }
+
+    /// Copies the whole file content to `buffer`
+    fn read_all(&self, buffer: &mut [u8]) -> usize {
+        if !self.mapping.is_null() {
+            unsafe { std::ptr::copy_nonoverlapping(self.mapping, buffer.as_mut_ptr(), self.size) };
+            return self.size;
+        }
+
+        // Aligned chunked reads through a local scratch buffer
+        const CHUNK_SIZE: usize = 1 << 20;
+        let mut scratch = vec![0u8; CHUNK_SIZE + DIRECT_IO_ALIGNMENT];
+        let base = scratch.as_ptr().align_offset(DIRECT_IO_ALIGNMENT);
+        let mut offset = 0;
+        while offset < self.size {
+            let read = pread(&self.file, &mut scratch[base..base + CHUNK_SIZE], offset);
+            // A truncated file would otherwise be read at the same offset forever
+            assert!(read > 0, "Raw baseline file truncated at byte {} of {}", offset, self.size);
+            let n = read.min(self.size - offset);
+            buffer[offset..offset + n].copy_from_slice(&scratch[base..base + n]);
+            offset += n;
+        }
+        self.size
+    }
+
+    /// Writes the whole file content to `writer`, in chunks for direct reads
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        if !self.mapping.is_null() {
+            writer.write_all(unsafe { std::slice::from_raw_parts(self.mapping, self.size) })?;
+            writer.flush()?;
+            return Ok(self.size);
+        }
+
+        const CHUNK_SIZE: usize = 1 << 20;
+        let mut scratch = vec![0u8; CHUNK_SIZE + DIRECT_IO_ALIGNMENT];
+        let base = scratch.as_ptr().align_offset(DIRECT_IO_ALIGNMENT);
+        let mut offset = 0;
+        while offset < self.size {
+            let read = pread(&self.file, &mut scratch[base..base + CHUNK_SIZE], offset);
+            if read == 0 {
+                let message = format!("Raw baseline file truncated at byte {} of {}", offset, self.size);
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, message));
+            }
+            let n = read.min(self.size - offset);
+            writer.write_all(&scratch[base..base + n])?;
+            offset += n;
+        }
+        writer.flush()?;
+        Ok(self.size)
+    }
+
+    /// Copies the byte range `[start, end)` of the file to `buffer`
+    #[inline(always)]
+    fn read_range(&mut self, start: usize, end: usize, buffer: &mut [u8]) {
+        let item_size = end - start;
+        buffer[..item_size].copy_from_slice(self.range_ref(start, end));
+    }
+
+    /// Returns the byte range `[start, end)` of the file, from the mapping or from the
+    /// scratch buffer of direct reads (valid until the next read)
+    #[inline(always)]
+    fn range_ref(&mut self, start: usize, end: usize) -> &[u8] {
+        let item_size = end - start;
+
+        if !self.mapping.is_null() {
+            return unsafe { std::slice::from_raw_parts(self.mapping.add(start), item_size) };
+        }
+
+        if item_size == 0 {
+            return &[];
+        }
+
+        // Widen the range to aligned boundaries as required by O_DIRECT
+        let aligned_start = start & !(DIRECT_IO_ALIGNMENT - 1);
+        let aligned_end = (end + DIRECT_IO_ALIGNMENT - 1) & !(DIRECT_IO_ALIGNMENT - 1);
+        let base = self.scratch.as_ptr().align_offset(DIRECT_IO_ALIGNMENT);
+        pread(&self.file, &mut self.scratch[base..base + aligned_end - aligned_start], aligned_start);
+
+        let skip = start - aligned_start;
+        &self.scratch[base + skip..base + skip + item_size]
+    }
+}
+
+/// Reads into `buffer` from the given file offset, returning the bytes read
+#[cfg(target_os = "linux")]
+#[inline(always)]
+fn pread(file: &File, buffer: &mut [u8], offset: usize) -> usize {
+    let read = unsafe {
+        libc::pread(file.as_raw_fd(), buffer.as_mut_ptr() as *mut libc::c_void, buffer.len(), offset as libc::off_t)
+    };
+    assert!(read >= 0, "Failed to read raw baseline file");
+    read as usize
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for BackingFile {
+    fn drop(&mut self) {
+        if !self.mapping.is_null() {
+            unsafe { libc::munmap(self.mapping as *mut libc::c_void, self.size) };
+        }
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn space_used_counts_the_coded_offsets() {
+        let data: Vec<u8> = (0..20_000u32).flat_map(|i| format!("string {}", i % 1000).into_bytes()).collect();
+        let end_positions: Vec<usize> = (0..=data.len()).step_by(10).collect();
+        let mut sizes = Vec::new();
+        for offset_coding in [OffsetCoding::Plain, OffsetCoding::BitPacked, OffsetCoding::EliasFano] {
+            let mut compressor = RawCompressor::with_offset_coding(data.len(), end_positions.len() - 1, offset_coding);
+            compressor.compress(&data, &end_positions);
+            let breakdown = compressor.space_breakdown();
+            assert_eq!(breakdown.payload, data.len());
+            assert_eq!(breakdown.item_offsets, compressor.item_offsets_size());
+            assert_eq!(compressor.space_used_bytes(), data.len() + breakdown.item_offsets);
+            sizes.push(compressor.space_used_bytes());
+        }
+        // Coded boundaries are smaller than machine words
+        assert!(sizes[1] < sizes[0] && sizes[2] < sizes[1], "{:?}", sizes);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn truncated_backing_files_fail_instead_of_spinning() {
+        let data = vec![7u8; 3 * DIRECT_IO_ALIGNMENT];
+        let file = BackingFile::create(&data, RawStorage::Direct, 16);
+        OpenOptions::new().write(true).open(&file.path).unwrap().set_len(DIRECT_IO_ALIGNMENT as u64).unwrap();
+
+        let error = file.write_to(&mut io::sink()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        let mut buffer = vec![0u8; data.len()];
+        let read = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| file.read_all(&mut buffer)));
+        assert!(read.is_err());
     }
 }
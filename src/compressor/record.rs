@@ -0,0 +1,201 @@
+//! Multi-column record storage on top of any compressor
+//!
+//! Stores datasets whose records carry several string columns (e.g., url and title)
+//! using either a column-store layout (one compressor per column) or a row-store
+//! layout (a single compressor over the fields of each record, stored contiguously).
+//! Both layouts provide record-level access fetching all columns for an index.
+
+use super::{Compressor, SpaceBreakdown};
+use std::io::{self, Write};
+
+/// Physical layout of the columns of a record dataset
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RecordLayout {
+    Columns,    // One compressor per column
+    Rows,       // One compressor over record-major interleaved fields
+}
+
+impl RecordLayout {
+    /// Returns the identifier of the layout used in result names
+    pub fn name(&self) -> &str {
+        match self {
+            RecordLayout::Columns => "columns",
+            RecordLayout::Rows => "rows",
+        }
+    }
+}
+
+/// Record store composing one or more compressors of the same kind
+///
+/// In the `Columns` layout, column `c` of record `i` is item `i` of compressor `c`.
+/// In the `Rows` layout, column `c` of record `i` is item `i * n_columns + c` of a
+/// single compressor, so that the fields of a record are adjacent.
+pub struct RecordStore<T: Compressor> {
+    layout: RecordLayout,
+    compressors: Vec<T>,    // One per column (Columns) or a single one (Rows)
+    n_columns: usize,
+}
+
+impl<T: Compressor> RecordStore<T> {
+    /// Creates a record store with pre-allocated compressors
+    ///
+    /// # Arguments
+    /// - `layout`: Physical layout of the columns
+    /// - `columns`: Concatenated data and boundary positions of each column
+    pub fn new(layout: RecordLayout, columns: &[(Vec<u8>, Vec<usize>)]) -> Self {
+        let compressors = match layout {
+            RecordLayout::Columns => columns
+                .iter()
+                .map(|(data, end_positions)| T::new(data.len(), end_positions.len() - 1))
+                .collect(),
+            RecordLayout::Rows => {
+                let data_size = columns.iter().map(|(data, _)| data.len()).sum();
+                let n_elements = columns.iter().map(|(_, end_positions)| end_positions.len() - 1).sum();
+                vec![T::new(data_size, n_elements)]
+            }
+        };
+
+        RecordStore {
+            layout,
+            compressors,
+            n_columns: columns.len(),
+        }
+    }
+
+    /// Compresses all columns according to the store layout
+    ///
+    /// # Arguments
+    /// - `columns`: Concatenated data and boundary positions of each column
+    pub fn compress(&mut self, columns: &[(Vec<u8>, Vec<usize>)]) {
+        match self.layout {
+            RecordLayout::Columns => {
+                for (compressor, (data, end_positions)) in self.compressors.iter_mut().zip(columns) {
+                    compressor.compress(data, end_positions);
+                }
+            }
+            RecordLayout::Rows => {
+                let (data, end_positions) = interleave_columns(columns);
+                self.compressors[0].compress(&data, &end_positions);
+            }
+        }
+    }
+
+    /// Decompresses the whole store to the provided buffer
+    ///
+    /// Data is written column-major for the `Columns` layout and record-major
+    /// for the `Rows` layout.
+    ///
+    /// # Returns
+    /// Number of bytes written to the output buffer
+    pub fn decompress(&self, buffer: &mut [u8]) -> usize {
+        let mut size = 0;
+        for compressor in self.compressors.iter() {
+            size += compressor.decompress(&mut buffer[size..]);
+        }
+        size
+    }
+
+    /// Decompresses the whole store to a writer (see `Compressor::decompress_to`)
+    ///
+    /// Data is written in the same order as `decompress`.
+    ///
+    /// # Returns
+    /// Number of bytes written, or the first error returned by the writer
+    pub fn decompress_to<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let mut size = 0;
+        for compressor in self.compressors.iter() {
+            size += compressor.decompress_to(writer)?;
+        }
+        Ok(size)
+    }
+
+    /// Retrieves all columns of a record
+    ///
+    /// Fields are written consecutively to `buffer`; `field_ends[c]` receives the
+    /// end position of column `c` within the buffer.
+    ///
+    /// # Arguments
+    /// - `index`: Zero-based index of the record
+    /// - `buffer`: Output buffer for the decompressed fields
+    /// - `field_ends`: Output end positions of each field (length `n_columns`)
+    ///
+    /// # Returns
+    /// Number of bytes written to the buffer
+    #[inline(always)]
+    pub fn get_record_at(&mut self, index: usize, buffer: &mut [u8], field_ends: &mut [usize]) -> usize {
+        debug_assert!(field_ends.len() >= self.n_columns);
+
+        let mut size = 0;
+        match self.layout {
+            RecordLayout::Columns => {
+                for (column, compressor) in self.compressors.iter_mut().enumerate() {
+                    size += compressor.get_item_at(index, &mut buffer[size..]);
+                    field_ends[column] = size;
+                }
+            }
+            RecordLayout::Rows => {
+                let first_item = index * self.n_columns;
+                for (column, field_end) in field_ends.iter_mut().take(self.n_columns).enumerate() {
+                    size += self.compressors[0].get_item_at(first_item + column, &mut buffer[size..]);
+                    *field_end = size;
+                }
+            }
+        }
+        size
+    }
+
+    /// Returns the number of columns of each record
+    pub fn n_columns(&self) -> usize {
+        self.n_columns
+    }
+
+    /// Returns the layout of the store
+    pub fn layout(&self) -> RecordLayout {
+        self.layout
+    }
+
+    /// Reports total memory usage of all underlying compressors
+    pub fn space_used_bytes(&self) -> usize {
+        self.compressors.iter().map(|c| c.space_used_bytes()).sum()
+    }
+
+    /// Reports memory usage of all underlying compressors split by category
+    pub fn space_breakdown(&self) -> SpaceBreakdown {
+        self.compressors.iter().map(|c| c.space_breakdown()).sum()
+    }
+
+    /// Returns the name of the underlying compression algorithm
+    pub fn name(&self) -> &str {
+        self.compressors[0].name()
+    }
+}
+
+/// Interleaves columns into a record-major collection
+///
+/// Column `c` of record `i` becomes item `i * n_columns + c` of the result.
+///
+/// # Arguments
+/// - `columns`: Concatenated data and boundary positions of each column
+///
+/// # Returns
+/// - `Vec<u8>`: Concatenated fields in record-major order
+/// - `Vec<usize>`: Boundary positions of the fields, starting with 0
+pub fn interleave_columns(columns: &[(Vec<u8>, Vec<usize>)]) -> (Vec<u8>, Vec<usize>) {
+    let data_size = columns.iter().map(|(data, _)| data.len()).sum();
+    let n_records = columns.first().map_or(0, |(_, end_positions)| end_positions.len() - 1);
+
+    let mut data = Vec::with_capacity(data_size);
+    let mut end_positions = Vec::with_capacity(n_records * columns.len() + 1);
+    end_positions.push(0);
+
+    for record in 0..n_records {
+        for (column_data, column_end_positions) in columns.iter() {
+            let start = column_end_positions[record];
+            let end = column_end_positions[record + 1];
+            data.extend_from_slice(&column_data[start..end]);
+            end_positions.push(data.len());
+        }
+    }
+
+    (data, end_positions)
+}
@@ -0,0 +1,606 @@
+//! Registry of the available compression algorithms
+//!
+//! Maps algorithm identifiers (as used on the command line) to compressor instances,
+//! so that binaries and language bindings share a single list of supported algorithms.
+//! Algorithms relying on third-party crates are behind cargo features; the registry
+//! lists every algorithm together with whether it is compiled in.
+
+use super::bpe::{BPECompressor, BPEConfig, BPETraining, DEFAULT_COUNTING_MEMORY, DEFAULT_MERGES_PER_PASS, DEFAULT_SAMPLE_RATE};
+use super::fm_index::FMIndexCompressor;
+use super::front_coding::FrontCodingCompressor;
+use super::fsst::FSSTCompressor;
+#[cfg(feature = "fsst-ffi")]
+use super::fsst_ffi::FSSTFfiCompressor;
+use super::lzw::{LZWCompressor, LZWDictionary};
+#[cfg(feature = "onpair")]
+use super::onpair::OnPairCompressor;
+#[cfg(feature = "onpair")]
+use super::onpair16::OnPair16Compressor;
+#[cfg(feature = "onpair")]
+use super::onpair_bv::{Alphabet, OnPairBVCompressor, OnPairConfig, ITERATED_PASSES};
+use super::terminated::TerminatedBPECompressor;
+use super::offset_coding::OffsetCoding;
+use super::raw::{MmapAdvice, RawCompressor, RawStorage};
+use super::sampling::Sampling;
+#[cfg(feature = "tiered")]
+use super::tiered::{BlockCodec, TieredCompressor};
+use super::block_stats::BlockStats;
+use super::dictionary_report::{DictionaryReport, DictionaryStats};
+use super::incremental::CompressionSteps;
+use super::{BlockSizeCalibration, Capacity, Compressor, DictionaryCompressor, ItemBoundaries, LiteralTokenStats, SpaceBreakdown};
+use super::prefix_trie::MatcherStats;
+use std::io::{self, Write};
+use crate::benchmark_utils::{self, BenchmarkOptions, BenchmarkResult, CompressionSplit, HoldoutMetrics, Phases, RunConditions};
+use crate::cost_model::{AccessStructure, CostModel};
+use crate::profiler::StageTimer;
+use tracing::info_span;
+
+/// Registered compression algorithm
+pub struct RegistryEntry {
+    pub name: &'static str,             // Algorithm identifier
+    pub feature: Option<&'static str>,  // Cargo feature providing the algorithm (None: always built)
+    pub available: bool,                // Whether the algorithm is compiled in
+}
+
+/// All registered compression algorithms
+pub const REGISTRY: [RegistryEntry; 30] = [
+    RegistryEntry { name: "raw", feature: None, available: true },
+    RegistryEntry { name: "raw_mmap", feature: None, available: true },
+    RegistryEntry { name: "raw_mmap_willneed", feature: None, available: true },
+    RegistryEntry { name: "raw_direct", feature: None, available: true },
+    RegistryEntry { name: "raw_bit_packed", feature: None, available: true },
+    RegistryEntry { name: "raw_elias_fano", feature: None, available: true },
+    RegistryEntry { name: "bpe", feature: None, available: true },
+    RegistryEntry { name: "bpe_sampled", feature: None, available: true },
+    RegistryEntry { name: "bpe_external", feature: None, available: true },
+    RegistryEntry { name: "bpe_terminated", feature: None, available: true },
+    RegistryEntry { name: "fm_index", feature: None, available: true },
+    RegistryEntry { name: "front_coding", feature: None, available: true },
+    RegistryEntry { name: "fsst_native", feature: None, available: true },
+    RegistryEntry { name: "fsst_ffi", feature: Some("fsst-ffi"), available: cfg!(feature = "fsst-ffi") },
+    RegistryEntry { name: "lzw", feature: None, available: true },
+    RegistryEntry { name: "lzw_per_item", feature: None, available: true },
+    RegistryEntry { name: "onpair", feature: Some("onpair"), available: cfg!(feature = "onpair") },
+    RegistryEntry { name: "onpair16", feature: Some("onpair"), available: cfg!(feature = "onpair") },
+    RegistryEntry { name: "onpair_bv", feature: Some("onpair"), available: cfg!(feature = "onpair") },
+    RegistryEntry { name: "onpair_bv_dense", feature: Some("onpair"), available: cfg!(feature = "onpair") },
+    RegistryEntry { name: "onpair_bv_pairs", feature: Some("onpair"), available: cfg!(feature = "onpair") },
+    RegistryEntry { name: "onpair_bv_iterated", feature: Some("onpair"), available: cfg!(feature = "onpair") },
+    RegistryEntry { name: "onpair_bv_cached", feature: Some("onpair"), available: cfg!(feature = "onpair") },
+    RegistryEntry { name: "onpair_bv_literals", feature: Some("onpair"), available: cfg!(feature = "onpair") },
+    RegistryEntry { name: "onpair_bv32", feature: Some("onpair"), available: cfg!(feature = "onpair") },
+    RegistryEntry { name: "onpair_bv64", feature: Some("onpair"), available: cfg!(feature = "onpair") },
+    RegistryEntry { name: "lz4_blocks", feature: Some("tiered"), available: cfg!(feature = "tiered") },
+    RegistryEntry { name: "zstd_blocks", feature: Some("tiered"), available: cfg!(feature = "tiered") },
+    RegistryEntry { name: "zstd_dict_blocks", feature: Some("tiered"), available: cfg!(feature = "tiered") },
+    RegistryEntry { name: "tiered", feature: Some("tiered"), available: cfg!(feature = "tiered") },
+];
+
+/// Looks up a registered algorithm by identifier
+pub fn lookup(name: &str) -> Option<&'static RegistryEntry> {
+    REGISTRY.iter().find(|entry| entry.name == name)
+}
+
+/// Returns the identifiers of the algorithms compiled in
+pub fn available_compressors() -> Vec<&'static str> {
+    REGISTRY.iter().filter(|entry| entry.available).map(|entry| entry.name).collect()
+}
+
+/// Returns an error message for an identifier that cannot be instantiated
+pub fn unavailable_message(name: &str) -> String {
+    match lookup(name) {
+        Some(entry) if entry.available => format!("Compressor '{}' is not supported in this mode", name),
+        Some(RegistryEntry { feature: Some(feature), .. }) => format!("Compressor '{}' requires the '{}' feature", name, feature),
+        _ => format!("Unknown compressor: {}", name),
+    }
+}
+
+/// Wrapper enum over all registered compression algorithms
+///
+/// Algorithms whose state is much larger than the others (e.g., inline symbol tables)
+/// are boxed, so that the enum stays small.
+pub enum AnyCompressor {
+    Raw(RawCompressor),
+    BPE(Box<BPECompressor>),
+    TerminatedBPE(TerminatedBPECompressor),
+    FMIndex(FMIndexCompressor),
+    FrontCoding(FrontCodingCompressor),
+    FSST(Box<FSSTCompressor>),
+    #[cfg(feature = "fsst-ffi")]
+    FSSTFfi(FSSTFfiCompressor),
+    LZW(LZWCompressor),
+    #[cfg(feature = "onpair")]
+    OnPair(OnPairCompressor),
+    #[cfg(feature = "onpair")]
+    OnPair16(OnPair16Compressor),
+    #[cfg(feature = "onpair")]
+    OnPairBV(Box<OnPairBVCompressor>),
+    #[cfg(feature = "tiered")]
+    Tiered(TieredCompressor),
+}
+
+// Registered compressors can be moved to and shared between threads (see `Compressor`)
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<AnyCompressor>();
+};
+
+/// Applies an expression to the compressor wrapped by an `AnyCompressor`
+///
+/// Method calls reach boxed compressors through auto-deref; with `mut`, boxed
+/// compressors are bound as a mutable reference to the compressor itself, so that
+/// `$c` can be passed to generic functions.
+macro_rules! dispatch {
+    ($any:expr, $c:ident => $e:expr) => {
+        dispatch!(@match $any, $c => $e, $c)
+    };
+    ($any:expr, mut $c:ident => $e:expr) => {
+        dispatch!(@match $any, $c => $e, &mut **$c)
+    };
+    (@match $any:expr, $c:ident => $e:expr, $unboxed:expr) => {
+        match $any {
+            AnyCompressor::Raw($c) => $e,
+            AnyCompressor::BPE($c) => {
+                let $c = $unboxed;
+                $e
+            }
+            AnyCompressor::TerminatedBPE($c) => $e,
+            AnyCompressor::FMIndex($c) => $e,
+            AnyCompressor::FrontCoding($c) => $e,
+            AnyCompressor::FSST($c) => {
+                let $c = $unboxed;
+                $e
+            }
+            #[cfg(feature = "fsst-ffi")]
+            AnyCompressor::FSSTFfi($c) => $e,
+            AnyCompressor::LZW($c) => $e,
+            #[cfg(feature = "onpair")]
+            AnyCompressor::OnPair($c) => $e,
+            #[cfg(feature = "onpair")]
+            AnyCompressor::OnPair16($c) => $e,
+            #[cfg(feature = "onpair")]
+            AnyCompressor::OnPairBV($c) => {
+                let $c = $unboxed;
+                $e
+            }
+            #[cfg(feature = "tiered")]
+            AnyCompressor::Tiered($c) => $e,
+        }
+    };
+}
+
+impl AnyCompressor {
+    /// Creates a compressor from its identifier
+    ///
+    /// # Arguments
+    /// - `name`: Algorithm identifier (one of `available_compressors()`)
+    /// - `data_size`: Total size of input data in bytes
+    /// - `n_elements`: Number of individual strings in the dataset
+    ///
+    /// # Returns
+    /// The compressor instance, or `None` if the identifier is unknown or not compiled in
+    pub fn create(name: &str, data_size: usize, n_elements: usize) -> Option<Self> {
+        let compressor = match name {
+            "raw" => AnyCompressor::Raw(RawCompressor::new(data_size, n_elements)),
+            "raw_mmap" => AnyCompressor::Raw(RawCompressor::with_storage(data_size, n_elements, RawStorage::Mmap(MmapAdvice::Random))),
+            "raw_mmap_willneed" => AnyCompressor::Raw(RawCompressor::with_storage(data_size, n_elements, RawStorage::Mmap(MmapAdvice::WillNeed))),
+            "raw_direct" => AnyCompressor::Raw(RawCompressor::with_storage(data_size, n_elements, RawStorage::Direct)),
+            "raw_bit_packed" => AnyCompressor::Raw(RawCompressor::with_offset_coding(data_size, n_elements, OffsetCoding::BitPacked)),
+            "raw_elias_fano" => AnyCompressor::Raw(RawCompressor::with_offset_coding(data_size, n_elements, OffsetCoding::EliasFano)),
+            "bpe" => AnyCompressor::BPE(Box::new(BPECompressor::new(data_size, n_elements))),
+            "bpe_sampled" => AnyCompressor::BPE(Box::new(BPECompressor::with_config(data_size, n_elements, BPEConfig { training: BPETraining::Sampled { sample_rate: DEFAULT_SAMPLE_RATE, sampling: Sampling::Random }, ..BPEConfig::default() }))),
+            "bpe_external" => AnyCompressor::BPE(Box::new(BPECompressor::with_config(data_size, n_elements, BPEConfig { training: BPETraining::External { memory_budget: DEFAULT_COUNTING_MEMORY, merges_per_pass: DEFAULT_MERGES_PER_PASS }, ..BPEConfig::default() }))),
+            "bpe_terminated" => AnyCompressor::TerminatedBPE(TerminatedBPECompressor::new(data_size, n_elements)),
+            "fm_index" => AnyCompressor::FMIndex(FMIndexCompressor::new(data_size, n_elements)),
+            "front_coding" => AnyCompressor::FrontCoding(FrontCodingCompressor::new(data_size, n_elements)),
+            "fsst_native" => AnyCompressor::FSST(Box::new(FSSTCompressor::new(data_size, n_elements))),
+            #[cfg(feature = "fsst-ffi")]
+            "fsst_ffi" => AnyCompressor::FSSTFfi(FSSTFfiCompressor::new(data_size, n_elements)),
+            "lzw" => AnyCompressor::LZW(LZWCompressor::new(data_size, n_elements)),
+            "lzw_per_item" => AnyCompressor::LZW(LZWCompressor::with_dictionary(data_size, n_elements, LZWDictionary::PerItem)),
+            #[cfg(feature = "onpair")]
+            "onpair" => AnyCompressor::OnPair(OnPairCompressor::new(data_size, n_elements)),
+            #[cfg(feature = "onpair")]
+            "onpair16" => AnyCompressor::OnPair16(OnPair16Compressor::new(data_size, n_elements)),
+            #[cfg(feature = "onpair")]
+            "onpair_bv" => AnyCompressor::OnPairBV(Box::new(OnPairBVCompressor::new(data_size, n_elements))),
+            #[cfg(feature = "onpair")]
+            "onpair_bv_dense" => AnyCompressor::OnPairBV(Box::new(OnPairBVCompressor::with_alphabet(data_size, n_elements, Alphabet::Dense))),
+            #[cfg(feature = "onpair")]
+            "onpair_bv_pairs" => AnyCompressor::OnPairBV(Box::new(OnPairBVCompressor::with_alphabet(data_size, n_elements, Alphabet::Pairs))),
+            #[cfg(feature = "onpair")]
+            "onpair_bv_iterated" => AnyCompressor::OnPairBV(Box::new(OnPairBVCompressor::with_passes(data_size, n_elements, ITERATED_PASSES))),
+            #[cfg(feature = "onpair")]
+            "onpair_bv_cached" => AnyCompressor::OnPairBV(Box::new(OnPairBVCompressor::with_string_cache(data_size, n_elements))),
+            #[cfg(feature = "onpair")]
+            "onpair_bv_literals" => AnyCompressor::OnPairBV(Box::new(OnPairBVCompressor::with_literal_inlining(data_size, n_elements))),
+            #[cfg(feature = "onpair")]
+            "onpair_bv32" => AnyCompressor::OnPairBV(Box::new(OnPairBVCompressor::with_max_token_length(data_size, n_elements, 32))),
+            #[cfg(feature = "onpair")]
+            "onpair_bv64" => AnyCompressor::OnPairBV(Box::new(OnPairBVCompressor::with_max_token_length(data_size, n_elements, 64))),
+            #[cfg(feature = "tiered")]
+            "lz4_blocks" => AnyCompressor::Tiered(TieredCompressor::with_codec(data_size, n_elements, BlockCodec::Lz4)),
+            #[cfg(feature = "tiered")]
+            "zstd_blocks" => AnyCompressor::Tiered(TieredCompressor::with_codec(data_size, n_elements, BlockCodec::Zstd)),
+            #[cfg(feature = "tiered")]
+            "zstd_dict_blocks" => AnyCompressor::Tiered(TieredCompressor::with_shared_dictionary(data_size, n_elements)),
+            #[cfg(feature = "tiered")]
+            "tiered" => AnyCompressor::Tiered(TieredCompressor::new(data_size, n_elements)),
+            _ => return None,
+        };
+        Some(compressor)
+    }
+
+    /// Replaces the training parameters of an OnPairBV compressor (see `OnPairBVCompressor::set_config`)
+    ///
+    /// # Returns
+    /// Whether the compressor accepts them: OnPair and OnPair16 fix their parameters
+    /// inside the library, and other algorithms have none
+    #[cfg(feature = "onpair")]
+    pub fn set_onpair_config(&mut self, config: OnPairConfig) -> bool {
+        match self {
+            AnyCompressor::OnPairBV(c) => {
+                c.set_config(config);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Lays out the compressed blocks by co-access in a query trace (see `TieredCompressor::set_layout_trace`)
+    ///
+    /// # Returns
+    /// Whether the compressor accepts it: only block compressors store blocks to reorder
+    #[cfg(feature = "tiered")]
+    pub fn set_layout_trace(&mut self, queries: Vec<usize>) -> bool {
+        match self {
+            AnyCompressor::Tiered(c) => {
+                c.set_layout_trace(queries);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Chooses the block size from a latency budget (see `TieredCompressor::set_latency_budget`)
+    ///
+    /// # Returns
+    /// Whether the compressor accepts it: only block compressors have a block size
+    #[cfg(feature = "tiered")]
+    pub fn set_latency_budget(&mut self, latency_budget: f64) -> bool {
+        match self {
+            AnyCompressor::Tiered(c) => {
+                c.set_latency_budget(latency_budget);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Samples the string offsets of a token-stream compressor (see `BPECompressor::set_offset_sample_rate`)
+    ///
+    /// # Returns
+    /// Whether the compressor accepts it: only BPE and OnPairBV store token offsets they can sample
+    pub fn set_offset_sample_rate(&mut self, sample_rate: usize) -> bool {
+        match self {
+            AnyCompressor::BPE(c) => {
+                c.set_offset_sample_rate(sample_rate);
+                true
+            }
+            #[cfg(feature = "onpair")]
+            AnyCompressor::OnPairBV(c) => {
+                c.set_offset_sample_rate(sample_rate);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Samples token positions within long strings (see `BPECompressor::set_item_sample_interval`)
+    ///
+    /// # Returns
+    /// Whether the compressor accepts it: only BPE and OnPairBV decode token streams they can sample
+    pub fn set_item_sample_interval(&mut self, sample_interval: usize) -> bool {
+        match self {
+            AnyCompressor::BPE(c) => {
+                c.set_item_sample_interval(sample_interval);
+                true
+            }
+            #[cfg(feature = "onpair")]
+            AnyCompressor::OnPairBV(c) => {
+                c.set_item_sample_interval(sample_interval);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the block size chosen for the latency budget (see `TieredCompressor::block_size_calibration`)
+    pub fn block_size_calibration(&self) -> Option<BlockSizeCalibration> {
+        match self {
+            #[cfg(feature = "tiered")]
+            AnyCompressor::Tiered(c) => c.block_size_calibration(),
+            _ => None,
+        }
+    }
+
+    /// Returns the single-byte tokens of the last compression (see `OnPairBVCompressor::literal_token_stats`)
+    ///
+    /// None for compressors whose token stream is not exposed.
+    pub fn literal_token_stats(&self) -> Option<LiteralTokenStats> {
+        match self {
+            #[cfg(feature = "onpair")]
+            AnyCompressor::OnPairBV(c) => c.literal_token_stats(),
+            _ => None,
+        }
+    }
+
+    /// Returns the memory of the longest prefix matcher (see `OnPairBVCompressor::matcher_stats`)
+    ///
+    /// None for compressors whose matcher is not built by this crate.
+    pub fn matcher_stats(&self) -> Option<MatcherStats> {
+        match self {
+            #[cfg(feature = "onpair")]
+            AnyCompressor::OnPairBV(c) => c.matcher_stats(),
+            _ => None,
+        }
+    }
+
+    /// Returns the sizes and item count of each block (see `BlockCompressor::get_block_stats`)
+    ///
+    /// None for compressors without blocks.
+    pub fn block_stats(&self) -> Option<Vec<BlockStats>> {
+        match self {
+            #[cfg(feature = "tiered")]
+            AnyCompressor::Tiered(c) => Some(super::BlockCompressor::get_block_stats(c)),
+            _ => None,
+        }
+    }
+
+    /// Compresses the input dataset (see `Compressor::compress`)
+    pub fn compress(&mut self, data: &[u8], end_positions: &[usize]) {
+        dispatch!(self, c => c.compress(data, end_positions))
+    }
+
+    /// Splits the compression of the input dataset into units of work (see `Compressor::compress_steps`)
+    pub fn compress_steps<'a>(&'a mut self, data: &'a [u8], end_positions: &'a [usize]) -> CompressionSteps<'a> {
+        dispatch!(self, c => c.compress_steps(data, end_positions))
+    }
+
+    /// Decompresses the entire dataset (see `Compressor::decompress`)
+    pub fn decompress(&self, buffer: &mut [u8]) -> usize {
+        dispatch!(self, c => c.decompress(buffer))
+    }
+
+    /// Decompresses the entire dataset to a writer (see `Compressor::decompress_to`)
+    pub fn decompress_to<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        dispatch!(self, c => c.decompress_to(writer))
+    }
+
+    /// Retrieves a single string by index (see `Compressor::get_item_at`)
+    #[inline(always)]
+    pub fn get_item_at(&mut self, index: usize, buffer: &mut [u8]) -> usize {
+        dispatch!(self, c => c.get_item_at(index, buffer))
+    }
+
+    /// Retrieves a single string, checking the buffer first (see `Compressor::try_get_item_at`)
+    pub fn try_get_item_at(&mut self, index: usize, buffer: &mut [u8]) -> Result<usize, String> {
+        dispatch!(self, c => c.try_get_item_at(index, buffer))
+    }
+
+    /// Retrieves a single string, attributing its latency to access stages (see `Compressor::get_item_at_profiled`)
+    pub fn get_item_at_profiled(&mut self, index: usize, buffer: &mut [u8], timer: &mut StageTimer) -> usize {
+        dispatch!(self, c => c.get_item_at_profiled(index, buffer, timer))
+    }
+
+    /// Retrieves a single string by index without copying it (see `Compressor::get_item_ref`)
+    #[inline(always)]
+    pub fn get_item_ref(&mut self, index: usize) -> Option<&[u8]> {
+        dispatch!(self, c => c.get_item_ref(index))
+    }
+
+    /// Retrieves a batch of strings by index (see `Compressor::get_items_at`)
+    pub fn get_items_at(&mut self, indices: &[usize], buffer: &mut [u8], item_ends: &mut [usize]) -> usize {
+        dispatch!(self, c => c.get_items_at(indices, buffer, item_ends))
+    }
+
+    /// Retrieves a byte range of a single string (see `Compressor::get_item_range`)
+    pub fn get_item_range(&mut self, index: usize, start: usize, end: usize, buffer: &mut [u8]) -> usize {
+        dispatch!(self, c => c.get_item_range(index, start, end, buffer))
+    }
+
+    /// Visits the strings of an index range, projecting each of them (see `Compressor::scan_range`)
+    pub fn scan_range<P: FnMut(usize, &[u8])>(&mut self, start_index: usize, end_index: usize, projector: P) {
+        dispatch!(self, c => c.scan_range(start_index, end_index, projector))
+    }
+
+    /// Reports the length of a single string (see `Compressor::len_of_item`)
+    pub fn len_of_item(&self, index: usize) -> usize {
+        dispatch!(self, c => c.len_of_item(index))
+    }
+
+    /// Reports the length of the longest string (see `Compressor::max_item_len`)
+    pub fn max_item_len(&self) -> usize {
+        dispatch!(self, c => c.max_item_len())
+    }
+
+    /// Finds the string holding a byte offset of the uncompressed data (see `Compressor::offset_to_item`)
+    pub fn offset_to_item(&self, offset: usize) -> Option<(usize, usize)> {
+        dispatch!(self, c => c.offset_to_item(offset))
+    }
+
+    /// Returns whether the algorithm keeps the boundaries of the uncompressed strings,
+    /// answering `offset_to_item` without an external index
+    pub fn has_offset_index(&self) -> bool {
+        match self {
+            AnyCompressor::Raw(_) | AnyCompressor::FMIndex(_) | AnyCompressor::LZW(_) => true,
+            #[cfg(feature = "tiered")]
+            AnyCompressor::Tiered(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Reports how the algorithm locates string boundaries (see `Compressor::item_boundaries`)
+    pub fn item_boundaries(&self) -> ItemBoundaries {
+        dispatch!(self, c => c.item_boundaries())
+    }
+
+    /// Reports the limits on the datasets it can represent (see `Compressor::capacity`)
+    pub fn capacity(&self) -> Capacity {
+        dispatch!(self, c => c.capacity())
+    }
+
+    /// Reports total memory usage (see `Compressor::space_used_bytes`)
+    pub fn space_used_bytes(&self) -> usize {
+        dispatch!(self, c => c.space_used_bytes())
+    }
+
+    /// Reports memory usage split by category (see `Compressor::space_breakdown`)
+    pub fn space_breakdown(&self) -> SpaceBreakdown {
+        dispatch!(self, c => c.space_breakdown())
+    }
+
+    /// Measures compression of held-out strings (see `benchmark_utils::evaluate_holdout`)
+    ///
+    /// # Returns
+    /// The metrics, or None for algorithms without a trainable dictionary (see `DictionaryCompressor`)
+    pub fn evaluate_holdout(&self, data: &[u8], end_positions: &[usize], holdout_fraction: f64) -> Option<HoldoutMetrics> {
+        match self {
+            AnyCompressor::BPE(c) => benchmark_utils::evaluate_holdout(&**c, data, end_positions, holdout_fraction),
+            #[cfg(feature = "onpair")]
+            AnyCompressor::OnPairBV(c) => benchmark_utils::evaluate_holdout(&**c, data, end_positions, holdout_fraction),
+            _ => None,
+        }
+    }
+
+    /// Measures compression split between training and parsing (see `benchmark_utils::measure_compression_split`)
+    ///
+    /// # Returns
+    /// The split, or None for algorithms without a trainable dictionary (see `DictionaryCompressor`)
+    pub fn measure_compression_split(&self, data: &[u8], end_positions: &[usize]) -> Option<CompressionSplit> {
+        match self {
+            AnyCompressor::BPE(c) => Some(benchmark_utils::measure_compression_split(&**c, data, end_positions)),
+            #[cfg(feature = "onpair")]
+            AnyCompressor::OnPairBV(c) => Some(benchmark_utils::measure_compression_split(&**c, data, end_positions)),
+            _ => None,
+        }
+    }
+
+    /// Reports the trained dictionary and its usage (see `DictionaryCompressor::dictionary_report`)
+    ///
+    /// # Returns
+    /// The report, or None for algorithms without a trainable dictionary (see `DictionaryCompressor`)
+    pub fn dictionary_report(&self) -> Option<DictionaryReport> {
+        match self {
+            AnyCompressor::BPE(c) => Some(c.dictionary_report()),
+            #[cfg(feature = "onpair")]
+            AnyCompressor::OnPairBV(c) => Some(c.dictionary_report()),
+            _ => None,
+        }
+    }
+
+    /// Summarizes the trained dictionary and its usage (see `DictionaryCompressor::dictionary_stats`)
+    ///
+    /// # Returns
+    /// The summary, or None for algorithms without a trainable dictionary (see `DictionaryCompressor`)
+    pub fn dictionary_stats(&self) -> Option<DictionaryStats> {
+        match self {
+            AnyCompressor::BPE(c) => Some(c.dictionary_stats()),
+            #[cfg(feature = "onpair")]
+            AnyCompressor::OnPairBV(c) => Some(c.dictionary_stats()),
+            _ => None,
+        }
+    }
+
+    /// Benchmarks the wrapped algorithm (see `benchmark_utils::benchmark_with_options`)
+    pub fn benchmark_with_options(
+        &mut self,
+        dataset_name: String,
+        data: &[u8],
+        end_positions: &[usize],
+        queries: &[usize],
+        options: &BenchmarkOptions
+    ) -> BenchmarkResult {
+        dispatch!(self, mut c => benchmark_utils::benchmark_with_options(c, dataset_name, data, end_positions, queries, options))
+    }
+
+    /// Benchmarks the wrapped algorithm on a dataset it has already compressed (see `benchmark_utils::benchmark_compressed`)
+    ///
+    /// Compression is not measured, whatever `options.phases`.
+    pub fn benchmark_compressed(
+        &mut self,
+        dataset_name: String,
+        data: &[u8],
+        end_positions: &[usize],
+        queries: &[usize],
+        options: &BenchmarkOptions
+    ) -> BenchmarkResult {
+        let _span = info_span!("benchmark", compressor = self.name(), dataset = %dataset_name).entered();
+        let conditions = RunConditions::start();
+        let options = BenchmarkOptions { phases: Phases { compress: false, ..options.phases }, ..*options };
+        let mut result = dispatch!(self, mut c => benchmark_utils::benchmark_compressed(c, dataset_name, data, end_positions, queries, &options, 0));
+        result.conditions = Some(conditions.finish());
+        result
+    }
+
+    /// Calibrates a latency model of the wrapped algorithm (see `CostModel::calibrate`)
+    pub fn calibrate_cost_model(&mut self, structure: AccessStructure, data: &[u8], end_positions: &[usize], queries: &[usize]) -> CostModel {
+        dispatch!(self, mut c => CostModel::calibrate(c, structure, data, end_positions, queries))
+    }
+
+    /// Returns the human-readable name of the wrapped algorithm
+    pub fn name(&self) -> &str {
+        dispatch!(self, c => c.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::differential;
+
+    /// Strings of a small dataset, different for every seed
+    fn dataset(seed: usize) -> (Vec<u8>, Vec<usize>) {
+        let mut data = Vec::new();
+        let mut end_positions = vec![0];
+        for index in 0..500 {
+            data.extend_from_slice(format!("dataset{}/item{}/{}", seed, index, "xyz".repeat((index * seed) % 7)).as_bytes());
+            end_positions.push(data.len());
+        }
+        (data, end_positions)
+    }
+
+    #[test]
+    fn compressors_built_in_parallel_threads_return_their_own_strings() {
+        let datasets: Vec<(Vec<u8>, Vec<usize>)> = (1..=4).map(dataset).collect();
+        let names = available_compressors();
+        // Each thread compresses its own dataset with every compressor at the same time as the others
+        let compressors: Vec<Vec<AnyCompressor>> = std::thread::scope(|scope| {
+            let workers: Vec<_> = datasets
+                .iter()
+                .map(|(data, end_positions)| {
+                    let names = &names;
+                    scope.spawn(move || {
+                        names
+                            .iter()
+                            .map(|name| {
+                                let mut compressor = AnyCompressor::create(name, data.len(), end_positions.len() - 1).unwrap();
+                                compressor.compress(data, end_positions);
+                                compressor
+                            })
+                            .collect()
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+        });
+
+        // Compressors moved back to this thread still return the strings of their dataset
+        for ((data, end_positions), compressors) in datasets.iter().zip(compressors) {
+            for mut compressor in compressors {
+                let check = differential::check(&mut compressor, data, end_positions);
+                assert!(check.passed(), "{} returned other strings after being built in another thread", check.compressor_name);
+            }
+        }
+    }
+}
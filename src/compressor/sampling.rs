@@ -0,0 +1,123 @@
+//! Sampling of training strings
+//!
+//! Dictionary trainers scanning a fraction of the strings pick them either uniformly at
+//! random or stratified by position: the collection is split into as many runs of
+//! consecutive strings as strings to sample, and one string is drawn from each run.
+//! Stratified samples cover every region of the collection, which matters for sorted or
+//! clustered datasets (e.g., logs grouped by service), where a uniform sample may miss
+//! small regions entirely. Samplers return string indices, string `i` spanning
+//! `end_positions[i]..end_positions[i + 1]` as everywhere else in the crate, in random
+//! order so that trainers sensitive to the scan order see no positional bias.
+
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng};
+
+/// Strategy picking the strings scanned by training
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Sampling {
+    Random,     // Uniformly random strings
+    Stratified, // One random string per run of consecutive strings
+}
+
+impl Sampling {
+    /// Returns the strategy identifier
+    pub fn name(&self) -> &'static str {
+        match self {
+            Sampling::Random => "random",
+            Sampling::Stratified => "stratified",
+        }
+    }
+
+    /// Parses a strategy from its identifier
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "random" => Some(Sampling::Random),
+            "stratified" => Some(Sampling::Stratified),
+            _ => None,
+        }
+    }
+}
+
+/// Samples a fraction of the strings with the given strategy
+///
+/// # Arguments
+/// - `n_items`: Number of strings
+/// - `sample_rate`: Fraction of the strings to sample, in (0, 1], rounded up
+/// - `sampling`: Sampling strategy
+///
+/// # Returns
+/// Indices of the sampled strings, distinct and in random order
+pub fn sample_strings(n_items: usize, sample_rate: f64, sampling: Sampling) -> Vec<usize> {
+    let n_sampled = ((n_items as f64 * sample_rate).ceil() as usize).min(n_items);
+    match sampling {
+        Sampling::Random => sample_random_strings(n_items, n_sampled),
+        Sampling::Stratified => sample_stratified_strings(n_items, n_sampled),
+    }
+}
+
+/// Samples `n_sampled` strings uniformly at random (at most `n_items`)
+pub fn sample_random_strings(n_items: usize, n_sampled: usize) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..n_items).collect();
+    indices.shuffle(&mut thread_rng());
+    indices.truncate(n_sampled);
+    indices
+}
+
+/// Samples one random string from each of `n_sampled` runs of consecutive strings (at most `n_items`)
+///
+/// Runs differ in length by at most one string.
+pub fn sample_stratified_strings(n_items: usize, n_sampled: usize) -> Vec<usize> {
+    let n_sampled = n_sampled.min(n_items);
+    let mut rng = thread_rng();
+    let mut indices: Vec<usize> = (0..n_sampled)
+        .map(|stratum| rng.gen_range(stratum * n_items / n_sampled..(stratum + 1) * n_items / n_sampled))
+        .collect();
+    indices.shuffle(&mut rng);
+    indices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sizes of collections and samples, including empty, full and oversized samples
+    const CASES: [(usize, usize); 9] = [(0, 0), (1, 1), (10, 0), (10, 3), (10, 10), (10, 25), (97, 13), (1000, 999), (1000, 1)];
+
+    #[test]
+    fn stratified_samples_draw_one_string_per_run() {
+        for (n_items, n_sampled) in CASES {
+            for _ in 0..20 {
+                let mut indices = sample_stratified_strings(n_items, n_sampled);
+                let n_runs = n_sampled.min(n_items);
+                assert_eq!(indices.len(), n_runs);
+                indices.sort_unstable();
+                for (run, &index) in indices.iter().enumerate() {
+                    let bounds = run * n_items / n_runs..(run + 1) * n_items / n_runs;
+                    assert!(bounds.contains(&index), "string {} outside run {:?} ({} of {})", index, bounds, n_sampled, n_items);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sampled_indices_address_strings_of_the_collection() {
+        // String i spans end_positions[i]..end_positions[i + 1], so indices stop one short of the boundaries
+        let end_positions: Vec<usize> = (0..=50).map(|i| i * 3).collect();
+        let n_items = end_positions.len() - 1;
+        for sampling in [Sampling::Random, Sampling::Stratified] {
+            for sample_rate in [0.01, 0.3, 1.0] {
+                let mut indices = sample_strings(n_items, sample_rate, sampling);
+                assert_eq!(indices.len(), (n_items as f64 * sample_rate).ceil() as usize);
+                assert!(indices.iter().all(|&index| index + 1 < end_positions.len()));
+                indices.sort_unstable();
+                indices.dedup();
+                assert_eq!(indices.len(), (n_items as f64 * sample_rate).ceil() as usize, "{} sample repeats strings", sampling.name());
+            }
+            assert_eq!(Sampling::parse(sampling.name()), Some(sampling));
+        }
+        // The whole collection, including its first and last strings
+        let mut indices = sample_strings(n_items, 1.0, Sampling::Stratified);
+        indices.sort_unstable();
+        assert_eq!(indices, (0..n_items).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,227 @@
+//! BPE with terminator symbols instead of string offsets
+//!
+//! Token-based compressors usually store the end position of every string in the token
+//! stream, one machine word per string: on collections of short strings, the offsets
+//! outweigh the compressed strings. `TerminatedBPECompressor` encodes strings with a BPE
+//! dictionary and closes each of them with a reserved terminator token, decoded as an
+//! empty token. Only the start of every `sample_rate`-th string is stored: a string is
+//! located by skipping terminators from the closest anchor, and its end is found while
+//! decoding it. The terminator costs one token per string instead of an offset.
+
+use super::bpe::{copy_spans, BPECompressor, FAST_ACCESS_SIZE, MAX_TOKENS};
+use super::{max_len_of_items, BlockPosition, Capacity, Compressor, ItemBoundaries, SpaceBreakdown, StreamBuffer, MAX_U32_OFFSET};
+use crate::profiler::{Stage, StageTimer};
+use std::io::{self, Write};
+
+/// Default distance between anchored strings
+pub const DEFAULT_SAMPLE_RATE: usize = 16;
+
+/// BPE compressor locating strings by terminator tokens
+pub struct TerminatedBPECompressor {
+    sample_rate: usize,
+    compressed_data: Vec<u16>,              // Token IDs, each string followed by the terminator
+    anchors: Vec<usize>,                    // Position in `compressed_data` of every `sample_rate`-th string
+    dictionary: Vec<u8>,                    // Token definitions, padded for overlapping copies
+    dictionary_end_positions: Vec<u32>,     // Token boundary positions in dictionary, the terminator last
+    terminator: u16,                        // Token ID closing every string
+    n_items: usize,
+}
+
+impl Compressor for TerminatedBPECompressor {
+    fn new(data_size: usize, n_elements: usize) -> Self {
+        Self::with_sample_rate(data_size, n_elements, DEFAULT_SAMPLE_RATE)
+    }
+
+    fn compress(&mut self, data: &[u8], end_positions: &[usize]) {
+        // One token ID is left to the terminator
+        let mut bpe = BPECompressor::with_max_tokens(data.len(), end_positions.len() - 1, MAX_TOKENS - 1);
+        bpe.compress(data, end_positions);
+        let (tokens, item_end_positions, mut dictionary, mut dictionary_end_positions) = bpe.into_parts();
+
+        // The terminator is an empty token following the trained ones
+        self.terminator = (dictionary_end_positions.len() - 1) as u16;
+        dictionary_end_positions.push(*dictionary_end_positions.last().unwrap());
+        dictionary.resize(dictionary.len() + FAST_ACCESS_SIZE, 0);
+
+        self.n_items = item_end_positions.len() - 1;
+        self.compressed_data.reserve(tokens.len() + self.n_items);
+        for (index, window) in item_end_positions.windows(2).enumerate() {
+            if index.is_multiple_of(self.sample_rate) {
+                self.anchors.push(self.compressed_data.len());
+            }
+            self.compressed_data.extend_from_slice(&tokens[window[0]..window[1]]);
+            self.compressed_data.push(self.terminator);
+        }
+        self.dictionary = dictionary;
+        self.dictionary_end_positions = dictionary_end_positions;
+    }
+
+    fn decompress(&self, buffer: &mut [u8]) -> usize {
+        // Terminators decode as empty tokens, without a branch
+        let mut size = 0;
+        for &token_id in self.compressed_data.iter() {
+            size += self.copy_token(token_id, &mut buffer[size..]);
+        }
+        size
+    }
+
+    fn decompress_to<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        let mut stream = StreamBuffer::new(writer);
+        for &token_id in self.compressed_data.iter() {
+            let (dict_start, length) = self.token_span(token_id);
+            stream.reserve(length)?[..length].copy_from_slice(&self.dictionary[dict_start..dict_start + length]);
+            stream.commit(length);
+        }
+        stream.finish()
+    }
+
+    fn get_item_at(&mut self, index: usize, buffer: &mut [u8]) -> usize {
+        let mut size = 0;
+        for &token_id in self.item_tokens(index) {
+            size += self.copy_token(token_id, &mut buffer[size..]);
+        }
+        size
+    }
+
+    fn get_item_at_profiled(&mut self, index: usize, buffer: &mut [u8], timer: &mut StageTimer) -> usize {
+        let start = self.item_start(index);
+        timer.lap(Stage::Lookup);
+
+        // Tokens are resolved to dictionary spans up to the terminator, then copied
+        timer.spans.clear();
+        for &token_id in self.compressed_data[start..].iter().take_while(|&&token_id| token_id != self.terminator) {
+            timer.spans.push(self.token_span(token_id));
+        }
+        timer.lap(Stage::Decode);
+
+        let size = copy_spans(&self.dictionary, &timer.spans, buffer);
+        timer.lap(Stage::Copy);
+        size
+    }
+
+    fn scan_range<P: FnMut(usize, &[u8])>(&mut self, start_index: usize, end_index: usize, mut projector: P) {
+        if start_index >= end_index {
+            return;
+        }
+        // Strings follow each other in the token stream: only the first one is located
+        let mut position = self.item_start(start_index);
+        let mut item = Vec::new();
+        for index in start_index..end_index {
+            item.clear();
+            while self.compressed_data[position] != self.terminator {
+                let (dict_start, length) = self.token_span(self.compressed_data[position]);
+                item.extend_from_slice(&self.dictionary[dict_start..dict_start + length]);
+                position += 1;
+            }
+            position += 1;
+            projector(index, &item);
+        }
+    }
+
+    fn len_of_item(&self, index: usize) -> usize {
+        self.item_tokens(index).iter().map(|&token_id| self.token_span(token_id).1).sum()
+    }
+
+    fn max_item_len(&self) -> usize {
+        max_len_of_items(self, self.n_items)
+    }
+
+    fn block_position(&self, index: usize) -> Option<BlockPosition> {
+        let first_item = index - index % self.sample_rate;
+        Some(BlockPosition::of(index - first_item, self.sample_rate.min(self.n_items - first_item)))
+    }
+
+    fn item_boundaries(&self) -> ItemBoundaries {
+        ItemBoundaries::Decoded
+    }
+
+    /// Reports the limits of BPE: training data positions are stored as 32-bit indices
+    fn capacity(&self) -> Capacity {
+        Capacity { max_data_size: MAX_U32_OFFSET, ..Capacity::UNLIMITED }
+    }
+
+    fn space_used_bytes(&self) -> usize {
+        self.space_breakdown().total()
+    }
+
+    fn space_breakdown(&self) -> SpaceBreakdown {
+        SpaceBreakdown {
+            payload: self.compressed_data.len() * std::mem::size_of::<u16>(),
+            dictionary: self.dictionary.len() - FAST_ACCESS_SIZE + self.dictionary_end_positions.len() * std::mem::size_of::<u32>(),
+            block_metadata: self.anchors.len() * std::mem::size_of::<usize>(),
+            ..SpaceBreakdown::default()
+        }
+    }
+
+    fn name(&self) -> &str {
+        "BPE (terminated)"
+    }
+}
+
+impl TerminatedBPECompressor {
+    /// Creates a compressor with a custom anchor sampling
+    ///
+    /// # Arguments
+    /// - `data_size`: Total size of input data in bytes
+    /// - `n_elements`: Number of individual strings in the dataset
+    /// - `sample_rate`: Distance between anchored strings: larger rates save space, at the
+    ///   cost of skipping up to `sample_rate - 1` strings per access
+    pub fn with_sample_rate(data_size: usize, n_elements: usize, sample_rate: usize) -> Self {
+        assert!(sample_rate >= 1, "sample_rate must be at least 1");
+        TerminatedBPECompressor {
+            sample_rate,
+            compressed_data: Vec::with_capacity(data_size / 2 + n_elements),
+            anchors: Vec::with_capacity(n_elements / sample_rate + 1),
+            dictionary: Vec::new(),
+            dictionary_end_positions: Vec::new(),
+            terminator: 0,
+            n_items: 0,
+        }
+    }
+
+    /// Returns the position of the first token of a string, skipping terminators from its anchor
+    #[inline(always)]
+    fn item_start(&self, index: usize) -> usize {
+        let mut position = self.anchors[index / self.sample_rate];
+        for _ in 0..index % self.sample_rate {
+            position += self.compressed_data[position..].iter().position(|&token_id| token_id == self.terminator).unwrap() + 1;
+        }
+        position
+    }
+
+    /// Returns the tokens of a string, without its terminator
+    #[inline(always)]
+    fn item_tokens(&self, index: usize) -> &[u16] {
+        let start = self.item_start(index);
+        let length = self.compressed_data[start..].iter().position(|&token_id| token_id == self.terminator).unwrap();
+        &self.compressed_data[start..start + length]
+    }
+
+    /// Returns the start and length of a token in the dictionary
+    #[inline(always)]
+    fn token_span(&self, token_id: u16) -> (usize, usize) {
+        let dict_start = self.dictionary_end_positions[token_id as usize] as usize;
+        (dict_start, self.dictionary_end_positions[token_id as usize + 1] as usize - dict_start)
+    }
+
+    /// Copies a token to the buffer, which must have `FAST_ACCESS_SIZE` bytes of padding
+    ///
+    /// # Returns
+    /// Length of the token
+    #[inline(always)]
+    fn copy_token(&self, token_id: u16, buffer: &mut [u8]) -> usize {
+        let (dict_start, length) = self.token_span(token_id);
+        unsafe {
+            let mut src = self.dictionary.as_ptr().add(dict_start);
+            let mut dst = buffer.as_mut_ptr();
+            std::ptr::copy_nonoverlapping(src, dst, FAST_ACCESS_SIZE);
+
+            if length > FAST_ACCESS_SIZE {
+                src = src.add(FAST_ACCESS_SIZE);
+                dst = dst.add(FAST_ACCESS_SIZE);
+                std::ptr::copy_nonoverlapping(src, dst, length - FAST_ACCESS_SIZE);
+            }
+        }
+        length
+    }
+}
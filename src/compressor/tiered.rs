@@ -0,0 +1,725 @@
+//! Hot/cold tiered block compression
+//!
+//! Splits the collection into blocks and compresses each block with either a fast
+//! codec (LZ4) or a high-ratio codec (Zstandard at level 19), depending on how often
+//! its items are expected to be accessed. Access frequencies come from a hint: a query
+//! trace, or the assumption that item popularity follows a Zipf distribution over item
+//! IDs. Blocks holding frequently accessed items stay fast to decompress, while rarely
+//! accessed blocks trade access latency for space. Single-codec configurations of the
+//! same compressor serve as baselines for the blended latency and compression rate.
+//!
+//! Blocks are compressed independently, so that redundancy across blocks is lost. The
+//! shared-dictionary configuration trains a Zstandard dictionary on a sample of the
+//! blocks and compresses every Zstandard block with it; the dictionary is stored with
+//! the block metadata and counted in the space used.
+//!
+//! Given a query trace (see `set_layout_trace`), the compressed blocks are laid out so
+//! that blocks accessed one after the other are adjacent in memory, improving cache
+//! and TLB locality of the compressed data without changing the blocks themselves.
+//!
+//! Given a latency budget (see `set_latency_budget`), the block size is chosen per
+//! dataset before compressing: candidate sizes (powers of two) are calibrated on
+//! sampled windows of the data, compressed with the slowest codec of the configuration,
+//! and the largest size whose mean single-block decompression time stays within the
+//! budget is used. Larger blocks compress better, so that the budget trades compression
+//! rate for a bound on the access latency.
+
+use super::{AccessScratch, BlockCompressor, BlockCutter, BlockMetadata, BlockPacking, BlockPosition, BlockSizeCalibration, Capacity, Compressor, SpaceBreakdown, DEFAULT_BLOCK_SIZE};
+use super::incremental::{CompressionSteps, Progress};
+use crate::profiler::StageTimer;
+use rand::seq::index;
+use rand::{thread_rng, Rng};
+use std::io::{self, Write};
+use std::time::Instant;
+use tracing::{info, warn};
+
+/// Zstandard compression level used for cold blocks
+pub const ZSTD_COLD_LEVEL: i32 = 19;
+/// Default share of the expected accesses served by hot blocks
+pub const DEFAULT_HOT_FRACTION: f64 = 0.9;
+/// Default exponent of the Zipf popularity assumption
+pub const DEFAULT_ZIPF_EXPONENT: f64 = 1.0;
+/// Maximum size of the shared Zstandard dictionary
+pub const ZSTD_DICTIONARY_SIZE: usize = 64 * 1024;
+/// Minimum ratio between the data size and the shared dictionary size, as recommended by Zstandard
+const DICTIONARY_SIZE_RATIO: usize = 100;
+/// Number of blocks sampled to train the shared Zstandard dictionary
+const DICTIONARY_SAMPLE_BLOCKS: usize = 128;
+/// Smallest block size calibrated for a latency budget
+pub const MIN_ADAPTIVE_BLOCK_SIZE: usize = 1024;
+/// Largest block size calibrated for a latency budget
+pub const MAX_ADAPTIVE_BLOCK_SIZE: usize = 1024 * 1024;
+/// Windows of the data sampled per calibrated block size
+const CALIBRATION_BLOCKS: usize = 8;
+/// Decompressions of each sampled window, of which the fastest is kept
+const CALIBRATION_REPETITIONS: usize = 5;
+/// Blocks compressed per unit of work of an incremental compression
+const BLOCKS_PER_STEP: usize = 64;
+
+/// Codec used to compress a block, stored in its metadata
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum BlockCodec {
+    Lz4 = 0,    // Fast decompression, for hot blocks
+    Zstd = 1,   // High compression rate, for cold blocks
+}
+
+impl BlockCodec {
+    /// Returns the codec with the given identifier
+    fn from_id(id: u8) -> Self {
+        match id {
+            0 => BlockCodec::Lz4,
+            1 => BlockCodec::Zstd,
+            _ => panic!("Unknown block codec: {}", id),
+        }
+    }
+
+    /// Returns the identifier of the codec used in compressor names
+    pub fn name(&self) -> &str {
+        match self {
+            BlockCodec::Lz4 => "LZ4",
+            BlockCodec::Zstd => "Zstd",
+        }
+    }
+}
+
+/// Expected access frequencies of the items
+pub enum AccessHint {
+    Zipf(f64),          // Popularity decreasing with the item ID, with the given exponent
+    Trace(Vec<usize>),  // Item IDs of a query trace
+}
+
+/// Assignment of codecs to blocks
+enum Tiering {
+    Single(BlockCodec),                             // All blocks use the same codec
+    Tiered { hint: AccessHint, hot_fraction: f64 }, // Hot blocks use LZ4, cold blocks Zstd
+}
+
+/// Block compressor choosing the codec of each block from access frequencies
+pub struct TieredCompressor {
+    tiering: Tiering,
+    block_size: usize,
+    block_packing: BlockPacking,            // Policy cutting the blocks (see `set_block_packing`)
+    compressed_data: Vec<u8>,               // Compressed blocks, concatenated
+    blocks_metadata: Vec<BlockMetadata>,
+    item_end_positions: Vec<usize>,
+    hot_items: Vec<bool>,                   // Whether each item is hot (during compression only)
+    block_cache: Vec<u8>,                   // Last decompressed block
+    cached_block: Option<usize>,            // Index of the block in the cache
+    access_scratch: AccessScratch,          // Scratch buffers of batch access
+    zstd_decompressor: zstd::bulk::Decompressor<'static>,
+    shared_dictionary: bool,                // Whether Zstd blocks are compressed with a trained dictionary
+    zstd_dictionary: Vec<u8>,               // Trained Zstd dictionary (empty if none)
+    zstd_compressor: Option<zstd::bulk::Compressor<'static>>,           // Dictionary compressor (during compression only)
+    zstd_decoder_dictionary: Option<zstd::dict::DecoderDictionary<'static>>, // Prepared dictionary for decompression
+    layout_trace: Option<Vec<usize>>,       // Query trace ordering the compressed blocks (during compression only)
+    latency_budget: Option<f64>,            // Decompression time allowed per block in nanoseconds, choosing the block size
+    block_size_calibration: Option<BlockSizeCalibration>, // Block size chosen for the latency budget by the last compression
+    name: String,
+}
+
+impl TieredCompressor {
+    /// Creates a compressor choosing the codec of each block from an access hint
+    ///
+    /// Hot items are the most frequently accessed ones, jointly covering
+    /// `DEFAULT_HOT_FRACTION` of the expected accesses; blocks holding at least
+    /// one hot item are compressed with LZ4, the others with Zstandard.
+    ///
+    /// # Arguments
+    /// - `data_size`: Total size of input data in bytes
+    /// - `n_elements`: Number of individual strings in the dataset
+    /// - `hint`: Expected access frequencies of the items
+    pub fn with_access_hint(data_size: usize, n_elements: usize, hint: AccessHint) -> Self {
+        let tiering = Tiering::Tiered { hint, hot_fraction: DEFAULT_HOT_FRACTION };
+        Self::with_tiering(data_size, n_elements, tiering, "Tiered (LZ4/Zstd)".to_string())
+    }
+
+    /// Creates a compressor using the same codec for all blocks
+    ///
+    /// # Arguments
+    /// - `data_size`: Total size of input data in bytes
+    /// - `n_elements`: Number of individual strings in the dataset
+    /// - `codec`: Codec of all blocks
+    pub fn with_codec(data_size: usize, n_elements: usize, codec: BlockCodec) -> Self {
+        let name = format!("{} blocks", codec.name());
+        Self::with_tiering(data_size, n_elements, Tiering::Single(codec), name)
+    }
+
+    /// Creates a compressor using Zstandard for all blocks, with a dictionary shared by all blocks
+    ///
+    /// The dictionary (up to `ZSTD_DICTIONARY_SIZE` bytes, and at most 1/`DICTIONARY_SIZE_RATIO`
+    /// of the data) is trained on the items of
+    /// `DICTIONARY_SAMPLE_BLOCKS` randomly sampled blocks when compressing.
+    ///
+    /// # Arguments
+    /// - `data_size`: Total size of input data in bytes
+    /// - `n_elements`: Number of individual strings in the dataset
+    pub fn with_shared_dictionary(data_size: usize, n_elements: usize) -> Self {
+        let name = format!("{} blocks (shared dictionary)", BlockCodec::Zstd.name());
+        let mut compressor = Self::with_tiering(data_size, n_elements, Tiering::Single(BlockCodec::Zstd), name);
+        compressor.shared_dictionary = true;
+        compressor
+    }
+
+    fn with_tiering(data_size: usize, n_elements: usize, tiering: Tiering, name: String) -> Self {
+        TieredCompressor {
+            tiering,
+            block_size: DEFAULT_BLOCK_SIZE,
+            block_packing: BlockPacking::Bytes,
+            compressed_data: Vec::with_capacity(data_size),
+            blocks_metadata: Vec::new(),
+            item_end_positions: Vec::with_capacity(n_elements + 1),
+            hot_items: Vec::new(),
+            block_cache: Vec::new(),
+            cached_block: None,
+            access_scratch: AccessScratch::default(),
+            zstd_decompressor: zstd::bulk::Decompressor::new().expect("Failed to create Zstd decompressor"),
+            shared_dictionary: false,
+            zstd_dictionary: Vec::new(),
+            zstd_compressor: None,
+            zstd_decoder_dictionary: None,
+            layout_trace: None,
+            latency_budget: None,
+            block_size_calibration: None,
+            name,
+        }
+    }
+
+    /// Lays out the compressed blocks by co-access in a query trace when compressing
+    ///
+    /// Blocks accessed one after the other in the trace are stored next to each
+    /// other (see `BlockCompressor::co_access_block_order`); block contents, codecs
+    /// and the compression rate are unchanged.
+    ///
+    /// # Arguments
+    /// - `queries`: Item IDs of the query trace, in access order
+    pub fn set_layout_trace(&mut self, queries: Vec<usize>) {
+        self.layout_trace = Some(queries);
+        self.name = format!("{} (co-access layout)", self.name);
+    }
+
+    /// Cuts blocks by number of items, or by both size and number of items, when compressing
+    ///
+    /// Blocks are cut by size by default (`BlockPacking::Bytes`).
+    ///
+    /// # Arguments
+    /// - `block_packing`: Policy cutting the blocks
+    pub fn set_block_packing(&mut self, block_packing: BlockPacking) {
+        self.block_packing = block_packing;
+        match block_packing {
+            BlockPacking::Bytes => {}
+            BlockPacking::Items(max_items) => self.name = format!("{} ({} items per block)", self.name, max_items),
+            BlockPacking::Hybrid(max_items) => self.name = format!("{} (at most {} items per block)", self.name, max_items),
+        }
+    }
+
+    /// Chooses the block size from a latency budget when compressing
+    ///
+    /// The largest power of two between `MIN_ADAPTIVE_BLOCK_SIZE` and
+    /// `MAX_ADAPTIVE_BLOCK_SIZE` whose calibrated single-block decompression time stays
+    /// within the budget is used (see `calibrate_block_size`).
+    ///
+    /// # Arguments
+    /// - `latency_budget`: Decompression time allowed per block in nanoseconds
+    pub fn set_latency_budget(&mut self, latency_budget: f64) {
+        assert!(latency_budget > 0.0, "latency_budget must be positive");
+        self.latency_budget = Some(latency_budget);
+        self.name = format!("{} (adaptive blocks)", self.name);
+    }
+
+    /// Returns the block size chosen for the latency budget by the last compression
+    ///
+    /// `None` without a latency budget or before compressing.
+    pub fn block_size_calibration(&self) -> Option<BlockSizeCalibration> {
+        self.block_size_calibration
+    }
+
+    /// Returns the slowest codec to decompress among those the configuration uses
+    fn slowest_codec(&self) -> BlockCodec {
+        match self.tiering {
+            Tiering::Single(codec) => codec,
+            Tiering::Tiered { .. } => BlockCodec::Zstd,
+        }
+    }
+
+    /// Calibrates the largest block size whose decompression time stays within a budget
+    ///
+    /// Candidate sizes are tried in increasing order, each on `CALIBRATION_BLOCKS`
+    /// random windows of the data (blocks are approximated by byte windows, ignoring
+    /// string boundaries), compressed with the slowest codec and without the shared
+    /// dictionary, which is trained afterwards for the chosen size. The fastest of
+    /// `CALIBRATION_REPETITIONS` decompressions of each window is kept, and windows
+    /// are averaged. Calibration stops at the first size exceeding the budget.
+    fn calibrate_block_size(&mut self, data: &[u8], latency_budget: f64) -> BlockSizeCalibration {
+        let codec = self.slowest_codec();
+        let mut rng = thread_rng();
+        let mut buffer = vec![0; MAX_ADAPTIVE_BLOCK_SIZE];
+        let mut calibration = BlockSizeCalibration {
+            latency_budget,
+            block_size: MIN_ADAPTIVE_BLOCK_SIZE,
+            block_latency: 0.0,
+            within_budget: false,
+        };
+
+        let mut block_size = MIN_ADAPTIVE_BLOCK_SIZE;
+        while block_size <= MAX_ADAPTIVE_BLOCK_SIZE {
+            let window_size = block_size.min(data.len());
+            let mut total_latency = 0.0;
+            for _ in 0..CALIBRATION_BLOCKS {
+                let start = rng.gen_range(0..=data.len() - window_size);
+                let window = &data[start..start + window_size];
+                let compressed = match codec {
+                    BlockCodec::Lz4 => lz4_flex::block::compress(window),
+                    BlockCodec::Zstd => zstd::bulk::compress(window, ZSTD_COLD_LEVEL).expect("Zstd compression failed"),
+                };
+
+                let mut fastest = u128::MAX;
+                for _ in 0..CALIBRATION_REPETITIONS {
+                    let output = &mut buffer[..window_size];
+                    let start = Instant::now();
+                    match codec {
+                        BlockCodec::Lz4 => {
+                            lz4_flex::block::decompress_into(&compressed, output).expect("Corrupted LZ4 block");
+                        }
+                        BlockCodec::Zstd => {
+                            self.zstd_decompressor.decompress_to_buffer(&compressed, output).expect("Corrupted Zstd block");
+                        }
+                    }
+                    fastest = fastest.min(start.elapsed().as_nanos());
+                }
+                total_latency += fastest as f64;
+            }
+
+            let block_latency = total_latency / CALIBRATION_BLOCKS as f64;
+            info!(block_size, block_latency, latency_budget, "Block size calibrated");
+            if block_latency > latency_budget && calibration.within_budget {
+                break;
+            }
+            calibration.block_size = block_size;
+            calibration.block_latency = block_latency;
+            calibration.within_budget = block_latency <= latency_budget;
+            if !calibration.within_budget || window_size == data.len() {
+                break;
+            }
+            block_size *= 2;
+        }
+
+        if !calibration.within_budget {
+            warn!(
+                block_size = calibration.block_size,
+                block_latency = calibration.block_latency,
+                latency_budget,
+                "No block size meets the latency budget, using the smallest one"
+            );
+        }
+        calibration
+    }
+
+    /// Trains the shared Zstd dictionary on the items of randomly sampled blocks
+    ///
+    /// Blocks are approximated by runs of consecutive items of about the block size.
+    /// If training fails (e.g., too little data), blocks are compressed without dictionary.
+    fn train_zstd_dictionary(&mut self, data: &[u8], end_positions: &[usize]) {
+        // First item of each block
+        let mut block_starts = vec![0];
+        for (item, &item_end) in end_positions.iter().enumerate().skip(1) {
+            let block_start = end_positions[*block_starts.last().unwrap()];
+            if item_end - block_start >= self.block_size && item < end_positions.len() - 1 {
+                block_starts.push(item);
+            }
+        }
+        block_starts.push(end_positions.len() - 1);
+
+        let n_blocks = block_starts.len() - 1;
+        let mut sampled = index::sample(&mut thread_rng(), n_blocks, n_blocks.min(DICTIONARY_SAMPLE_BLOCKS)).into_vec();
+        sampled.sort_unstable();
+        let samples: Vec<&[u8]> = sampled
+            .iter()
+            .flat_map(|&block| block_starts[block]..block_starts[block + 1])
+            .map(|item| &data[end_positions[item]..end_positions[item + 1]])
+            .filter(|sample| !sample.is_empty())
+            .collect();
+
+        let max_size = ZSTD_DICTIONARY_SIZE.min(data.len() / DICTIONARY_SIZE_RATIO);
+        match zstd::dict::from_samples(&samples, max_size) {
+            Ok(dictionary) => {
+                info!(n_sampled_blocks = sampled.len(), n_samples = samples.len(), dictionary_size = dictionary.len(), "Zstd dictionary trained");
+                self.zstd_compressor = Some(
+                    zstd::bulk::Compressor::with_dictionary(ZSTD_COLD_LEVEL, &dictionary).expect("Failed to create Zstd compressor")
+                );
+                self.zstd_decompressor.set_dictionary(&dictionary).expect("Failed to load Zstd dictionary");
+                self.zstd_decoder_dictionary = Some(zstd::dict::DecoderDictionary::copy(&dictionary));
+                self.zstd_dictionary = dictionary;
+            }
+            Err(e) => {
+                warn!(n_samples = samples.len(), "Failed to train Zstd dictionary, compressing without dictionary: {}", e);
+            }
+        }
+    }
+
+    /// Marks the items covering the given share of the expected accesses as hot
+    fn find_hot_items(hint: &AccessHint, n_items: usize, hot_fraction: f64) -> Vec<bool> {
+        let weights: Vec<f64> = match hint {
+            AccessHint::Zipf(exponent) => (0..n_items).map(|i| 1.0 / ((i + 1) as f64).powf(*exponent)).collect(),
+            AccessHint::Trace(queries) => {
+                let mut counts = vec![0.0; n_items];
+                for &query in queries {
+                    counts[query] += 1.0;
+                }
+                counts
+            }
+        };
+        let total_weight: f64 = weights.iter().sum();
+
+        let mut order: Vec<usize> = (0..n_items).collect();
+        order.sort_by(|&a, &b| weights[b].total_cmp(&weights[a]));
+
+        let mut hot_items = vec![false; n_items];
+        let mut covered_weight = 0.0;
+        for &item in order.iter() {
+            if covered_weight >= hot_fraction * total_weight || weights[item] == 0.0 {
+                break;
+            }
+            hot_items[item] = true;
+            covered_weight += weights[item];
+        }
+        hot_items
+    }
+
+    /// Prepares block compression: calibrates the block size, marks hot items and trains the shared dictionary
+    fn prepare_blocks(&mut self, data: &[u8], end_positions: &[usize]) {
+        if let Some(latency_budget) = self.latency_budget.filter(|_| !data.is_empty()) {
+            let calibration = self.calibrate_block_size(data, latency_budget);
+            self.block_size = calibration.block_size;
+            self.block_size_calibration = Some(calibration);
+        }
+        if let Tiering::Tiered { hint, hot_fraction } = &self.tiering {
+            self.hot_items = Self::find_hot_items(hint, end_positions.len() - 1, *hot_fraction);
+        }
+        if self.shared_dictionary {
+            self.train_zstd_dictionary(data, end_positions);
+        }
+    }
+
+    /// Completes block compression: lays out the blocks and releases compression state
+    fn finish_blocks(&mut self) {
+        if let Some(queries) = self.layout_trace.take() {
+            let order = self.co_access_block_order(&queries);
+            self.reorder_blocks(&order);
+            self.layout_trace = Some(queries);
+        }
+
+        self.hot_items = Vec::new();
+        self.zstd_compressor = None;
+        self.block_cache = vec![0; self.get_max_block_size()];
+        self.cached_block = None;
+
+        let hot_blocks = self.blocks_metadata.iter().filter(|block| block.codec == BlockCodec::Lz4 as u8).count();
+        info!(hot_blocks, cold_blocks = self.blocks_metadata.len() - hot_blocks, "Blocks compressed");
+    }
+}
+
+impl Compressor for TieredCompressor {
+    fn new(data_size: usize, n_elements: usize) -> Self {
+        Self::with_access_hint(data_size, n_elements, AccessHint::Zipf(DEFAULT_ZIPF_EXPONENT))
+    }
+
+    fn compress(&mut self, data: &[u8], end_positions: &[usize]) {
+        self.prepare_blocks(data, end_positions);
+        BlockCompressor::compress(self, data, end_positions);
+        self.finish_blocks();
+    }
+
+    /// Compresses the blocks in units of `BLOCKS_PER_STEP` blocks, after a unit of preparation
+    fn compress_steps<'a>(&'a mut self, data: &'a [u8], end_positions: &'a [usize]) -> CompressionSteps<'a> {
+        let mut cutter: Option<BlockCutter<'a>> = None;
+        CompressionSteps::new(move || {
+            let Some(position) = cutter.as_mut() else {
+                // Calibration and dictionary training form the first unit
+                self.prepare_blocks(data, end_positions);
+                cutter = Some(self.start_blocks(data, end_positions));
+                return Progress::Continue;
+            };
+            let progress = self.compress_blocks(position, BLOCKS_PER_STEP);
+            if progress == Progress::Done {
+                self.finish_blocks();
+            }
+            progress
+        })
+    }
+
+    fn decompress(&self, buffer: &mut [u8]) -> usize {
+        BlockCompressor::decompress(self, buffer)
+    }
+
+    fn decompress_to<W: Write>(&self, writer: &mut W) -> io::Result<usize> {
+        BlockCompressor::decompress_to(self, writer)
+    }
+
+    #[inline(always)]
+    fn get_item_at(&mut self, index: usize, buffer: &mut [u8]) -> usize {
+        BlockCompressor::get_item_at(self, index, buffer)
+    }
+
+    fn get_item_at_profiled(&mut self, index: usize, buffer: &mut [u8], timer: &mut StageTimer) -> usize {
+        BlockCompressor::get_item_at_profiled(self, index, buffer, timer)
+    }
+
+    #[inline(always)]
+    fn get_item_ref(&mut self, index: usize) -> Option<&[u8]> {
+        BlockCompressor::get_item_ref(self, index)
+    }
+
+    fn get_items_at(&mut self, indices: &[usize], buffer: &mut [u8], item_ends: &mut [usize]) -> usize {
+        BlockCompressor::get_items_at(self, indices, buffer, item_ends)
+    }
+
+    fn scan_range<P: FnMut(usize, &[u8])>(&mut self, start_index: usize, end_index: usize, projector: P) {
+        BlockCompressor::scan_range(self, start_index, end_index, projector)
+    }
+
+    fn len_of_item(&self, index: usize) -> usize {
+        BlockCompressor::len_of_item(self, index)
+    }
+
+    fn max_item_len(&self) -> usize {
+        BlockCompressor::max_item_len(self)
+    }
+
+    fn offset_to_item(&self, offset: usize) -> Option<(usize, usize)> {
+        BlockCompressor::offset_to_item(self, offset)
+    }
+
+    fn block_position(&self, index: usize) -> Option<BlockPosition> {
+        BlockCompressor::block_position(self, index)
+    }
+
+    fn capacity(&self) -> Capacity {
+        BlockCompressor::capacity(self)
+    }
+
+    fn space_used_bytes(&self) -> usize {
+        Compressor::space_breakdown(self).total()
+    }
+
+    fn space_breakdown(&self) -> SpaceBreakdown {
+        SpaceBreakdown {
+            dictionary: self.zstd_dictionary.len(),
+            ..BlockCompressor::space_breakdown(self)
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl BlockCompressor for TieredCompressor {
+    fn get_block_size(&self) -> usize {
+        self.block_size
+    }
+
+    fn get_block_packing(&self) -> BlockPacking {
+        self.block_packing
+    }
+
+    fn get_compressed_data(&self) -> &[u8] {
+        &self.compressed_data
+    }
+
+    fn get_compressed_data_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.compressed_data
+    }
+
+    fn get_blocks_metadata(&self) -> &Vec<BlockMetadata> {
+        &self.blocks_metadata
+    }
+
+    fn get_blocks_metadata_mut(&mut self) -> &mut Vec<BlockMetadata> {
+        &mut self.blocks_metadata
+    }
+
+    fn get_item_end_positions(&self) -> &[usize] {
+        &self.item_end_positions
+    }
+
+    fn get_item_end_positions_mut(&mut self) -> &mut Vec<usize> {
+        &mut self.item_end_positions
+    }
+
+    fn compress_block(&mut self, block: &[u8]) -> usize {
+        self.compress_block_with_codec(block, BlockCodec::Lz4 as u8)
+    }
+
+    fn decompress_block(&self, compressed_data: &[u8], uncompressed_size: usize, buffer: &mut [u8]) {
+        self.decompress_block_with_codec(compressed_data, uncompressed_size, BlockCodec::Lz4 as u8, buffer);
+    }
+
+    fn select_block_codec(&self, first_item: usize, num_items: usize) -> u8 {
+        let codec = match self.tiering {
+            Tiering::Single(codec) => codec,
+            Tiering::Tiered { .. } => {
+                if self.hot_items[first_item..first_item + num_items].contains(&true) {
+                    BlockCodec::Lz4
+                } else {
+                    BlockCodec::Zstd
+                }
+            }
+        };
+        codec as u8
+    }
+
+    fn compress_block_with_codec(&mut self, block: &[u8], codec: u8) -> usize {
+        let compressed = match BlockCodec::from_id(codec) {
+            BlockCodec::Lz4 => lz4_flex::block::compress(block),
+            BlockCodec::Zstd => match &mut self.zstd_compressor {
+                Some(compressor) => compressor.compress(block).expect("Zstd compression failed"),
+                None => zstd::bulk::compress(block, ZSTD_COLD_LEVEL).expect("Zstd compression failed"),
+            },
+        };
+        self.compressed_data.extend_from_slice(&compressed);
+        compressed.len()
+    }
+
+    fn decompress_block_with_codec(&self, compressed_data: &[u8], uncompressed_size: usize, codec: u8, buffer: &mut [u8]) {
+        let output = &mut buffer[..uncompressed_size];
+        let size = match BlockCodec::from_id(codec) {
+            BlockCodec::Lz4 => lz4_flex::block::decompress_into(compressed_data, output).expect("Corrupted LZ4 block"),
+            BlockCodec::Zstd => match &self.zstd_decoder_dictionary {
+                Some(dictionary) => zstd::bulk::Decompressor::with_prepared_dictionary(dictionary)
+                    .expect("Failed to create Zstd decompressor")
+                    .decompress_to_buffer(compressed_data, output)
+                    .expect("Corrupted Zstd block"),
+                None => zstd::bulk::decompress_to_buffer(compressed_data, output).expect("Corrupted Zstd block"),
+            },
+        };
+        debug_assert_eq!(size, uncompressed_size);
+    }
+
+    fn decompress_block_to_cache(&mut self, block_index: usize) {
+        if self.cached_block == Some(block_index) {
+            return;
+        }
+
+        let block = &self.blocks_metadata[block_index];
+        let compressed_data = &self.compressed_data[block.start_position..block.end_position];
+        let output = &mut self.block_cache[..block.uncompressed_size as usize];
+
+        // The Zstd context (and its dictionary, if any) is reused across accesses
+        match BlockCodec::from_id(block.codec) {
+            BlockCodec::Lz4 => {
+                lz4_flex::block::decompress_into(compressed_data, output).expect("Corrupted LZ4 block");
+            }
+            BlockCodec::Zstd => {
+                self.zstd_decompressor.decompress_to_buffer(compressed_data, output).expect("Corrupted Zstd block");
+            }
+        }
+        let uncompressed_size = block.uncompressed_size as usize;
+        self.verify_block(block_index, &self.block_cache[..uncompressed_size]);
+        self.cached_block = Some(block_index);
+    }
+
+    fn get_block_cache(&self) -> &[u8] {
+        &self.block_cache
+    }
+
+    fn get_access_scratch_mut(&mut self) -> &mut AccessScratch {
+        &mut self.access_scratch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Short strings around strings of several MiB, each far larger than a block
+    fn dataset_with_jumbo_strings() -> (Vec<u8>, Vec<usize>) {
+        let mut data = Vec::new();
+        let mut end_positions = vec![0];
+        for index in 0..600usize {
+            let length = match index {
+                100 => 3 << 20,
+                101 => 2 << 20,
+                400 => (5 << 20) + 17,
+                _ => 20 + index % 50,
+            };
+            data.extend((0..length).map(|i| b"jumbo strings span blocks "[(i * 7 + index) % 26]));
+            end_positions.push(data.len());
+        }
+        (data, end_positions)
+    }
+
+    #[test]
+    fn jumbo_strings_get_a_block_of_their_own() {
+        let (data, end_positions) = dataset_with_jumbo_strings();
+        for codec in [BlockCodec::Lz4, BlockCodec::Zstd] {
+            let mut compressor = TieredCompressor::with_codec(data.len(), end_positions.len() - 1, codec);
+            Compressor::compress(&mut compressor, &data, &end_positions);
+
+            let block_size = compressor.get_block_size();
+            let mut first_item = 0;
+            for block in compressor.get_blocks_metadata() {
+                let uncompressed_size = block.uncompressed_size as usize;
+                if uncompressed_size > block_size {
+                    assert_eq!(block.num_items_psum - first_item, 1, "{}: jumbo block shared with other strings", codec.name());
+                    assert_eq!(uncompressed_size, end_positions[first_item + 1] - end_positions[first_item]);
+                }
+                first_item = block.num_items_psum;
+            }
+            assert_eq!(compressor.get_max_block_size(), (5 << 20) + 17);
+
+            let mut buffer = vec![0u8; data.len() + 1024];
+            assert_eq!(Compressor::decompress(&compressor, &mut buffer), data.len());
+            assert!(buffer[..data.len()] == data[..], "{}: decompressed data differs", codec.name());
+            // Jumbo strings in both orders, and the short strings around them
+            for index in [400, 99, 100, 101, 102, 401, 100, 0, 599] {
+                let expected = &data[end_positions[index]..end_positions[index + 1]];
+                let size = Compressor::get_item_at(&mut compressor, index, &mut buffer);
+                assert!(buffer[..size] == *expected, "{}: string {} differs", codec.name(), index);
+            }
+        }
+    }
+
+    #[test]
+    fn item_packing_keeps_jumbo_strings_with_other_strings() {
+        let (data, end_positions) = dataset_with_jumbo_strings();
+        let items_per_block = 7;
+        let mut compressor = TieredCompressor::with_codec(data.len(), end_positions.len() - 1, BlockCodec::Lz4);
+        compressor.set_block_packing(BlockPacking::Items(items_per_block));
+        Compressor::compress(&mut compressor, &data, &end_positions);
+
+        let items = compressor.get_items_per_block();
+        let n_items = end_positions.len() - 1;
+        assert_eq!(items.len(), n_items.div_ceil(items_per_block));
+        assert!(items[..items.len() - 1].iter().all(|&n| n == items_per_block), "blocks cut around jumbo strings: {:?}", items);
+        assert_eq!(items[items.len() - 1], n_items - (items.len() - 1) * items_per_block);
+
+        let mut buffer = vec![0u8; data.len() + 1024];
+        for index in [99, 100, 101, 102, 400, 0, 599] {
+            let expected = &data[end_positions[index]..end_positions[index + 1]];
+            let size = Compressor::get_item_at(&mut compressor, index, &mut buffer);
+            assert!(buffer[..size] == *expected, "string {} differs", index);
+        }
+    }
+
+    #[test]
+    fn offset_to_block_finds_the_block_of_every_offset() {
+        let (data, end_positions) = dataset_with_jumbo_strings();
+        let mut compressor = TieredCompressor::with_codec(data.len(), end_positions.len() - 1, BlockCodec::Lz4);
+        Compressor::compress(&mut compressor, &data, &end_positions);
+
+        let mut first_item = 0;
+        for (block_index, block) in compressor.get_blocks_metadata().iter().enumerate() {
+            let (start, end) = (end_positions[first_item], end_positions[block.num_items_psum]);
+            // First, last and middle offsets of the block
+            for offset in [start, (start + end) / 2, end - 1].into_iter().filter(|&offset| offset < end) {
+                assert_eq!(compressor.offset_to_block(offset), Some(block_index), "offset {}", offset);
+                let (index, offset_in_item) = BlockCompressor::offset_to_item(&compressor, offset).unwrap();
+                assert!((first_item..block.num_items_psum).contains(&index));
+                assert_eq!(end_positions[index] + offset_in_item, offset);
+            }
+            first_item = block.num_items_psum;
+        }
+        assert_eq!(compressor.offset_to_block(data.len()), None);
+    }
+}
@@ -0,0 +1,178 @@
+//! Token boundaries of dictionaries, with offsets widened past 4 GiB
+//!
+//! Token compressors locate each token of their dictionary by its boundary positions.
+//! 32-bit offsets take half the space of 64-bit ones, but address at most
+//! `MAX_U32_OFFSET` bytes, while dictionaries with wide token IDs (e.g., OnPair BV with
+//! up to `2^32` tokens of up to `MAX_TOKEN_LENGTH` bytes) may grow past it. `TokenOffsets`
+//! stores 32-bit offsets, and widens all of them to 64 bits once the dictionary grows
+//! past `MAX_U32_OFFSET`: dictionaries of any size are supported, and the ones within
+//! 4 GiB keep compact offsets. Other positions that rarely exceed 4 GiB (e.g., sampled
+//! positions within strings, see `intra_item_index`) are widened the same way.
+//!
+//! Decoding loops dispatch on the width once rather than on every token (see
+//! `with_offsets!`), running code generic over the offset type (see `Offset`).
+
+use super::MAX_U32_OFFSET;
+use std::ops::Range;
+
+/// Unsigned integer storing a position
+pub trait Offset: Copy + Send + Sync + 'static {
+    /// Returns the position stored in the offset
+    fn position(self) -> usize;
+}
+
+impl Offset for u32 {
+    #[inline(always)]
+    fn position(self) -> usize {
+        self as usize
+    }
+}
+
+impl Offset for u64 {
+    #[inline(always)]
+    fn position(self) -> usize {
+        self as usize
+    }
+}
+
+/// Positions in 32-bit offsets, widened to 64 bits past `MAX_U32_OFFSET`
+///
+/// Dictionaries store their token boundary positions, starting with 0.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TokenOffsets {
+    Narrow(Vec<u32>),   // Positions of at most MAX_U32_OFFSET
+    Wide(Vec<u64>),     // Larger positions
+}
+
+/// Evaluates an expression on the offsets of a `TokenOffsets`, as a slice of `u32` or `u64`
+///
+/// The expression is compiled once per width, so that loops over tokens run without
+/// matching on the width.
+macro_rules! with_offsets {
+    ($offsets:expr, $slice:ident => $body:expr) => {
+        match $offsets {
+            $crate::compressor::token_offsets::TokenOffsets::Narrow(offsets) => {
+                let $slice: &[u32] = offsets;
+                $body
+            }
+            $crate::compressor::token_offsets::TokenOffsets::Wide(offsets) => {
+                let $slice: &[u64] = offsets;
+                $body
+            }
+        }
+    };
+}
+#[cfg_attr(not(feature = "onpair"), allow(unused_imports))]
+pub(crate) use with_offsets;
+
+impl Default for TokenOffsets {
+    fn default() -> Self {
+        TokenOffsets::Narrow(Vec::new())
+    }
+}
+
+impl TokenOffsets {
+    /// Creates empty offsets, with room for `capacity` narrow offsets
+    pub fn with_capacity(capacity: usize) -> Self {
+        TokenOffsets::Narrow(Vec::with_capacity(capacity))
+    }
+
+    /// Appends a position, widening all offsets if it exceeds `MAX_U32_OFFSET`
+    pub fn push(&mut self, position: usize) {
+        match self {
+            TokenOffsets::Narrow(offsets) if position <= MAX_U32_OFFSET => offsets.push(position as u32),
+            TokenOffsets::Narrow(offsets) => {
+                let mut wide: Vec<u64> = offsets.iter().map(|&offset| offset as u64).collect();
+                wide.push(position as u64);
+                *self = TokenOffsets::Wide(wide);
+            }
+            TokenOffsets::Wide(offsets) => offsets.push(position as u64),
+        }
+    }
+
+    /// Returns the number of positions (one more than the tokens, for token boundaries)
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        with_offsets!(self, offsets => offsets.len())
+    }
+
+    /// Checks whether no position is stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a position
+    #[inline(always)]
+    pub fn get(&self, index: usize) -> usize {
+        with_offsets!(self, offsets => offsets[index].position())
+    }
+
+    /// Returns the span of a token in the dictionary
+    #[inline(always)]
+    pub fn span(&self, token_id: usize) -> Range<usize> {
+        with_offsets!(self, offsets => offsets[token_id].position()..offsets[token_id + 1].position())
+    }
+
+    /// Returns the length of a token in bytes
+    #[inline(always)]
+    pub fn token_length(&self, token_id: usize) -> usize {
+        self.span(token_id).len()
+    }
+
+    /// Returns the size of an offset in bytes (4 until widened, then 8)
+    pub fn offset_size(&self) -> usize {
+        match self {
+            TokenOffsets::Narrow(_) => std::mem::size_of::<u32>(),
+            TokenOffsets::Wide(_) => std::mem::size_of::<u64>(),
+        }
+    }
+
+    /// Returns the size of the offsets in bytes
+    pub fn size_bytes(&self) -> usize {
+        self.len() * self.offset_size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets_stay_narrow_up_to_u32_max() {
+        let mut offsets = TokenOffsets::default();
+        for position in [0, 3, 10, MAX_U32_OFFSET] {
+            offsets.push(position);
+        }
+        assert!(matches!(offsets, TokenOffsets::Narrow(_)));
+        assert_eq!(offsets.len(), 4);
+        assert_eq!(offsets.span(1), 3..10);
+        assert_eq!(offsets.token_length(2), MAX_U32_OFFSET - 10);
+        assert_eq!(offsets.size_bytes(), 4 * std::mem::size_of::<u32>());
+    }
+
+    #[test]
+    fn offsets_widen_past_u32_max_and_keep_earlier_positions() {
+        let mut offsets = TokenOffsets::with_capacity(4);
+        for position in [0, 7, MAX_U32_OFFSET, MAX_U32_OFFSET + 5, 3 * MAX_U32_OFFSET] {
+            offsets.push(position);
+        }
+        assert!(matches!(offsets, TokenOffsets::Wide(_)));
+        assert_eq!(offsets.len(), 5);
+        assert_eq!(offsets.span(0), 0..7);
+        assert_eq!(offsets.span(2), MAX_U32_OFFSET..MAX_U32_OFFSET + 5);
+        assert_eq!(offsets.get(4), 3 * MAX_U32_OFFSET);
+        assert_eq!(offsets.token_length(3), 2 * MAX_U32_OFFSET - 5);
+        assert_eq!(offsets.size_bytes(), 5 * std::mem::size_of::<u64>());
+    }
+
+    #[test]
+    fn with_offsets_runs_on_either_width() {
+        let sum = |offsets: &TokenOffsets| with_offsets!(offsets, slice => slice.iter().map(|&offset| offset.position()).sum::<usize>());
+        let mut offsets = TokenOffsets::default();
+        offsets.push(0);
+        offsets.push(4);
+        assert_eq!(sum(&offsets), 4);
+        offsets.push(MAX_U32_OFFSET + 1);
+        assert_eq!(sum(&offsets), MAX_U32_OFFSET + 5);
+    }
+}
@@ -0,0 +1,209 @@
+//! Analytical model of random access latency
+//!
+//! Predicts the random access latency of a compressor on a dataset profile (number of
+//! strings and mean string length) without compressing or querying the dataset, for
+//! capacity planning. Each algorithm is modeled by the work of a single access, in
+//! units that depend on its structure (see `AccessStructure`):
+//!
+//! `latency = fixed_time + unit_time × units(profile)`
+//!
+//! where `units` is, e.g., the expected encoded size of a string for token streams
+//! (a proxy for the number of tokens decoded), or the size of a block for block
+//! compressors. The cost of a unit is calibrated from the full decompression of a
+//! sample of a dataset, which decodes every unit once; the fixed time (locating the
+//! string, cache misses) is what remains of the random access latency measured on
+//! the same sample. Predictions can be validated against measured benchmark results.
+
+use crate::compressor::{BlockPosition, Compressor, BUFFER_PADDING};
+use serde::{Serialize, Deserialize};
+use std::time::Instant;
+
+/// Structure of the work of a single access, determining its units
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AccessStructure {
+    Bytes,      // Work per byte of the string (copying, or per-character extraction)
+    Tokens,     // Work per encoded byte of the string, proportional to its tokens
+    Blocks,     // Decompression of the whole block of the string
+    Buckets,    // Decoding of the strings of the bucket up to the accessed one
+}
+
+impl AccessStructure {
+    /// Returns the structure of a registered algorithm (see `registry`)
+    ///
+    /// Wrapped variants (e.g., `_sorted`) add an ID lookup per access, which the
+    /// calibrated fixed time absorbs: they share the structure of the inner algorithm.
+    pub fn of(compressor_name: &str) -> Self {
+        match compressor_name {
+            name if name.starts_with("raw") || name.starts_with("fm_index") => AccessStructure::Bytes,
+            name if name.starts_with("front_coding") => AccessStructure::Buckets,
+            name if name.contains("_blocks") || name.starts_with("tiered") => AccessStructure::Blocks,
+            _ => AccessStructure::Tokens,
+        }
+    }
+
+    /// Returns the structure identifier
+    pub fn name(&self) -> &'static str {
+        match self {
+            AccessStructure::Bytes => "bytes",
+            AccessStructure::Tokens => "tokens",
+            AccessStructure::Blocks => "blocks",
+            AccessStructure::Buckets => "buckets",
+        }
+    }
+}
+
+/// Size of a dataset, actual or hypothetical
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct DatasetProfile {
+    pub n_items: usize,
+    pub mean_length: f64,                   // Mean string length in bytes
+}
+
+impl DatasetProfile {
+    /// Returns the profile of a dataset
+    ///
+    /// # Arguments
+    /// - `end_positions`: Boundary positions of the strings, starting with 0
+    pub fn of(end_positions: &[usize]) -> Self {
+        let n_items = end_positions.len().saturating_sub(1);
+        let mean_length = end_positions.last().map_or(0.0, |&size| size as f64 / n_items.max(1) as f64);
+        DatasetProfile { n_items, mean_length }
+    }
+
+    /// Parses a profile from its command-line representation
+    ///
+    /// The specification is a comma-separated list of `key=value` assignments with keys
+    /// `n_items` and `mean_length`, both required (e.g., `n_items=1000000,mean_length=24`).
+    ///
+    /// # Arguments
+    /// - `s`: Profile specification
+    ///
+    /// # Returns
+    /// The parsed profile, or `None` if the specification is invalid
+    pub fn parse(s: &str) -> Option<Self> {
+        let (mut n_items, mut mean_length) = (None, None);
+        for assignment in s.split(',') {
+            let (key, value) = assignment.split_once('=')?;
+            match key {
+                "n_items" => n_items = Some(value.parse().ok().filter(|&n_items: &usize| n_items > 0)?),
+                "mean_length" => mean_length = Some(value.parse().ok().filter(|&length: &f64| length.is_finite() && length >= 0.0)?),
+                _ => return None,
+            }
+        }
+        Some(DatasetProfile { n_items: n_items?, mean_length: mean_length? })
+    }
+
+    /// Returns the total size of the strings in bytes
+    pub fn data_size(&self) -> f64 {
+        self.n_items as f64 * self.mean_length
+    }
+}
+
+/// Calibrated latency model of a compressor
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct CostModel {
+    pub structure: AccessStructure,
+    pub fixed_time: f64,                    // Latency per access independent of the units in nanoseconds
+    pub unit_time: f64,                     // Latency per unit in nanoseconds
+    pub compression_rate: f64,              // Compression rate of the calibration sample, assumed for other profiles
+    pub group_items: f64,                   // Mean strings per block or bucket of the calibration sample (1 without groups)
+    pub group_bytes: f64,                   // Mean size of a block or bucket of the calibration sample in bytes
+}
+
+impl CostModel {
+    /// Calibrates the model of a compressor on the dataset it compressed
+    ///
+    /// Decompression and the random access queries are run once unmeasured, then
+    /// measured once. Strings are verified by length only: the benchmarks verify
+    /// contents, and the model needs timings.
+    ///
+    /// # Arguments
+    /// - `compressor`: Compressor holding the compressed calibration dataset
+    /// - `structure`: Structure of the work of an access of this compressor
+    /// - `data`: Concatenated strings of the calibration dataset
+    /// - `end_positions`: Boundary positions of the strings, starting with 0
+    /// - `queries`: Indices of the strings queried to measure random access latency
+    pub fn calibrate<T: Compressor>(
+        compressor: &mut T,
+        structure: AccessStructure,
+        data: &[u8],
+        end_positions: &[usize],
+        queries: &[usize]
+    ) -> Self {
+        let n_items = end_positions.len() - 1;
+        let profile = DatasetProfile::of(end_positions);
+        let compression_rate = data.len() as f64 / compressor.space_used_bytes().max(1) as f64;
+
+        // Groups are counted by their first string (see `Compressor::block_position`)
+        let n_groups = (0..n_items)
+            .filter(|&index| !matches!(compressor.block_position(index), Some(BlockPosition::Middle | BlockPosition::Last)))
+            .count()
+            .max(1);
+        let mut model = CostModel {
+            structure,
+            fixed_time: 0.0,
+            unit_time: 0.0,
+            compression_rate,
+            group_items: n_items as f64 / n_groups as f64,
+            group_bytes: data.len() as f64 / n_groups as f64,
+        };
+
+        let mut buffer = vec![0u8; data.len() + BUFFER_PADDING];
+        let mut decompression_time = 0;
+        for _ in 0..2 {
+            let start = Instant::now();
+            std::hint::black_box(compressor.decompress(&mut buffer));
+            decompression_time = start.elapsed().as_nanos();
+        }
+
+        let mut access_time = 0;
+        for _ in 0..2 {
+            access_time = 0;
+            for &query in queries {
+                let start = Instant::now();
+                let size = compressor.get_item_at(query, &mut buffer);
+                access_time += start.elapsed().as_nanos();
+                if size != end_positions[query + 1] - end_positions[query] {
+                    panic!("Length mismatch during calibration for compressor: {}", compressor.name());
+                }
+            }
+        }
+        let access_time = access_time as f64 / queries.len().max(1) as f64;
+
+        // Full decompression decodes every unit once
+        model.unit_time = decompression_time as f64 / model.decoded_units(&profile).max(1.0);
+        model.fixed_time = (access_time - model.unit_time * model.units(&profile)).max(0.0);
+        model
+    }
+
+    /// Returns the units of work of a single access on a profile
+    ///
+    /// Blocks keep their size in bytes (up to the size of the dataset); buckets keep
+    /// their number of strings, of which half are decoded on average.
+    pub fn units(&self, profile: &DatasetProfile) -> f64 {
+        match self.structure {
+            AccessStructure::Bytes => profile.mean_length,
+            AccessStructure::Tokens => profile.mean_length / self.compression_rate,
+            AccessStructure::Blocks => self.group_bytes.min(profile.data_size()),
+            AccessStructure::Buckets => (self.group_items + 1.0) / 2.0 * profile.mean_length,
+        }
+    }
+
+    /// Returns the units of work of decompressing a whole dataset of a profile
+    fn decoded_units(&self, profile: &DatasetProfile) -> f64 {
+        match self.structure {
+            AccessStructure::Tokens => profile.data_size() / self.compression_rate,
+            _ => profile.data_size(),
+        }
+    }
+
+    /// Predicts the random access latency on a profile, in nanoseconds
+    pub fn predict(&self, profile: &DatasetProfile) -> f64 {
+        self.fixed_time + self.unit_time * self.units(profile)
+    }
+}
+
+/// Returns the error of a prediction relative to the measured value (e.g., 0.1 for 10% too high)
+pub fn relative_error(predicted: f64, measured: f64) -> f64 {
+    (predicted - measured) / measured.max(f64::MIN_POSITIVE)
+}
@@ -0,0 +1,141 @@
+//! Orders of the strings of a dataset
+//!
+//! Compression depends on the order of the input: dictionary trainers scanning the
+//! strings once see recurring patterns earlier or later, block compressors find more
+//! redundancy within a block when similar strings are adjacent, and front coding relies
+//! on shared prefixes between neighbours. `InputOrder` names the orders compared by
+//! `benchmark_order`, and `permutation` computes them, so that the same strings can be
+//! compressed in each order:
+//! - `original`: the order of the dataset file
+//! - `sorted`: lexicographic order of the bytes
+//! - `shuffled`: uniformly random order, drawn from a seed
+//! - `clustered`: strings grouped by content similarity, groups in random order
+//!
+//! Clusters are built with a single MinHash over the byte trigrams of each string:
+//! strings sharing many trigrams likely share their smallest one, and land in the same
+//! cluster regardless of their prefixes, unlike with sorting. Strings keep their
+//! original order within a cluster.
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rustc_hash::FxHashMap;
+
+/// Order in which the strings of a dataset are compressed
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InputOrder {
+    Original,   // Order of the dataset file
+    Sorted,     // Lexicographic order
+    Shuffled,   // Random order
+    Clustered,  // Similar strings grouped together, groups in random order
+}
+
+impl InputOrder {
+    /// All orders, in reporting order
+    pub const ALL: [InputOrder; 4] = [InputOrder::Original, InputOrder::Sorted, InputOrder::Shuffled, InputOrder::Clustered];
+
+    /// Returns the order identifier
+    pub fn name(&self) -> &'static str {
+        match self {
+            InputOrder::Original => "original",
+            InputOrder::Sorted => "sorted",
+            InputOrder::Shuffled => "shuffled",
+            InputOrder::Clustered => "clustered",
+        }
+    }
+
+    /// Parses an order from its identifier
+    pub fn parse(s: &str) -> Option<Self> {
+        InputOrder::ALL.into_iter().find(|order| order.name() == s)
+    }
+}
+
+/// Computes the permutation of the strings of a dataset into an order
+///
+/// # Arguments
+/// - `data`: Concatenated strings
+/// - `end_positions`: Boundary positions of the strings, starting with 0
+/// - `order`: Order of the strings
+/// - `seed`: Seed of the random orders (shuffled strings and cluster order)
+///
+/// # Returns
+/// Index in the dataset of each string of the permuted dataset
+pub fn permutation(data: &[u8], end_positions: &[usize], order: InputOrder, seed: u64) -> Vec<usize> {
+    let item = |index: usize| &data[end_positions[index]..end_positions[index + 1]];
+    let mut indices: Vec<usize> = (0..end_positions.len() - 1).collect();
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    match order {
+        InputOrder::Original => {}
+        InputOrder::Sorted => indices.sort_by(|&a, &b| item(a).cmp(item(b))),
+        InputOrder::Shuffled => indices.shuffle(&mut rng),
+        InputOrder::Clustered => {
+            // Clusters are ranked randomly, strings keep their order within a cluster
+            let mut cluster_ranks: FxHashMap<u64, usize> = FxHashMap::default();
+            let keys: Vec<u64> = indices.iter().map(|&index| min_hash(item(index))).collect();
+            for &key in keys.iter() {
+                let n_clusters = cluster_ranks.len();
+                cluster_ranks.entry(key).or_insert(n_clusters);
+            }
+            let mut ranks: Vec<usize> = (0..cluster_ranks.len()).collect();
+            ranks.shuffle(&mut rng);
+            indices.sort_by_key(|&index| ranks[cluster_ranks[&keys[index]]]);
+        }
+    }
+    indices
+}
+
+/// Rearranges the strings of a dataset
+///
+/// # Arguments
+/// - `data`: Concatenated strings
+/// - `end_positions`: Boundary positions of the strings, starting with 0
+/// - `permutation`: Index in the dataset of each string of the output (see `permutation`)
+///
+/// # Returns
+/// Concatenated strings in the new order, and their boundary positions
+pub fn permute(data: &[u8], end_positions: &[usize], permutation: &[usize]) -> (Vec<u8>, Vec<usize>) {
+    let mut permuted_data = Vec::with_capacity(data.len());
+    let mut permuted_end_positions = Vec::with_capacity(end_positions.len());
+    permuted_end_positions.push(0);
+    for &index in permutation {
+        permuted_data.extend_from_slice(&data[end_positions[index]..end_positions[index + 1]]);
+        permuted_end_positions.push(permuted_data.len());
+    }
+    (permuted_data, permuted_end_positions)
+}
+
+/// Maps queries on the strings of a dataset to their positions in a permuted dataset
+///
+/// Queries then retrieve the same strings in every order, so that access latencies
+/// compare the layouts rather than the workloads.
+///
+/// # Arguments
+/// - `queries`: Indices of the queried strings in the dataset
+/// - `permutation`: Index in the dataset of each string of the permuted dataset
+pub fn permute_queries(queries: &[usize], permutation: &[usize]) -> Vec<usize> {
+    let mut positions = vec![0; permutation.len()];
+    for (position, &index) in permutation.iter().enumerate() {
+        positions[index] = position;
+    }
+    queries.iter().map(|&query| positions[query]).collect()
+}
+
+/// Returns the smallest hash of the byte trigrams of a string (of the whole string if shorter)
+fn min_hash(item: &[u8]) -> u64 {
+    if item.len() < 3 {
+        return mix(item.iter().fold(item.len() as u64, |hash, &byte| (hash << 8) | byte as u64));
+    }
+    item.windows(3)
+        .map(|trigram| mix(u64::from_le_bytes([trigram[0], trigram[1], trigram[2], 0, 0, 0, 0, 0]) | (1 << 32)))
+        .min()
+        .unwrap()
+}
+
+/// Scrambles the bits of a value (SplitMix64 finalizer)
+#[inline(always)]
+fn mix(mut value: u64) -> u64 {
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d049bb133111eb);
+    value ^ (value >> 31)
+}
@@ -0,0 +1,147 @@
+//! Cached statistics of datasets
+//!
+//! Every benchmark run reports properties of its dataset that do not depend on the
+//! algorithm: the empirical entropy and the smallest universal code of the string
+//! lengths (see `entropy` and `LengthIndex`). On large corpora, computing them takes
+//! longer than compressing with the fastest algorithms, and is repeated by every run of
+//! every algorithm. `DatasetStats::load_or_compute` stores them in a sidecar file next
+//! to the dataset (`<dataset>.stats.json`) on first use, and reads them back in later
+//! runs. The sidecar records a hash of the dataset contents: if the dataset changes,
+//! the hash no longer matches and the statistics are computed and stored again.
+
+use crate::compressor::length_index::{LengthCode, LengthIndex};
+use crate::entropy::dataset_entropy;
+use serde::{Serialize, Deserialize};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use xxhash_rust::xxh3::Xxh3;
+
+/// Version of the sidecar format, bumped whenever the statistics change
+const FORMAT_VERSION: u32 = 1;
+/// Extension appended to the dataset path to name its sidecar
+pub const SIDECAR_EXTENSION: &str = ".stats.json";
+/// Buckets of the length histogram, one per bit length of the string lengths
+pub const HISTOGRAM_BUCKETS: usize = 32;
+
+/// Properties of a dataset independent of the algorithm
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct DatasetStats {
+    pub n_items: usize,
+    pub data_size: usize,                   // Total size of the strings in bytes
+    pub max_item_size: usize,               // Size of the longest string in bytes
+    pub entropy: f64,                       // Empirical entropy in bits per byte (see `dataset_entropy`)
+    pub length_code: LengthCode,            // Smallest universal code of the string lengths
+    pub bits_per_length: f64,               // Average bits per string length with `length_code`
+    pub length_histogram: [usize; HISTOGRAM_BUCKETS], // Strings per bit length of their length (the last bucket holds longer ones)
+}
+
+/// Contents of a sidecar file
+#[derive(Serialize, Deserialize)]
+struct Sidecar {
+    format_version: u32,
+    dataset_hash: u64,  // Hash of the strings and their boundaries (see `dataset_hash`)
+    stats: DatasetStats,
+}
+
+impl DatasetStats {
+    /// Computes the statistics of a dataset
+    ///
+    /// # Arguments
+    /// - `data`: Concatenated strings
+    /// - `end_positions`: Boundary positions of the strings, starting with 0
+    pub fn compute(data: &[u8], end_positions: &[usize]) -> Self {
+        let mut length_histogram = [0; HISTOGRAM_BUCKETS];
+        let mut max_item_size = 0;
+        for window in end_positions.windows(2) {
+            let length = window[1] - window[0];
+            let bit_length = (usize::BITS - length.leading_zeros()) as usize;
+            length_histogram[bit_length.min(HISTOGRAM_BUCKETS - 1)] += 1;
+            max_item_size = max_item_size.max(length);
+        }
+        let length_index = LengthIndex::new(end_positions);
+
+        DatasetStats {
+            n_items: end_positions.len().saturating_sub(1),
+            data_size: data.len(),
+            max_item_size,
+            entropy: dataset_entropy(data, end_positions),
+            length_code: length_index.code(),
+            bits_per_length: length_index.bits_per_item(),
+            length_histogram,
+        }
+    }
+
+    /// Reads the statistics of a dataset from its sidecar, computing and storing them if
+    /// the sidecar is missing, outdated or unreadable
+    ///
+    /// Failing to write the sidecar (e.g., in a read-only directory) is not an error:
+    /// the statistics are computed again by the next run.
+    ///
+    /// # Arguments
+    /// - `dataset_path`: Path of the dataset file, next to which the sidecar is stored
+    /// - `data`: Concatenated strings loaded from the dataset
+    /// - `end_positions`: Boundary positions of the strings, starting with 0
+    pub fn load_or_compute(dataset_path: &Path, data: &[u8], end_positions: &[usize]) -> Self {
+        let path = sidecar_path(dataset_path);
+        let dataset_hash = dataset_hash(data, end_positions);
+        let cached = fs::read(&path)
+            .ok()
+            .and_then(|content| serde_json::from_slice::<Sidecar>(&content).ok())
+            .filter(|sidecar| sidecar.format_version == FORMAT_VERSION && sidecar.dataset_hash == dataset_hash);
+        if let Some(sidecar) = cached {
+            info!(sidecar = %path.display(), "Dataset statistics loaded");
+            return sidecar.stats;
+        }
+
+        let stats = Self::compute(data, end_positions);
+        let sidecar = Sidecar { format_version: FORMAT_VERSION, dataset_hash, stats };
+
+        // Written to a temporary file first, so that concurrent runs never read a partial sidecar
+        let mut temporary = path.clone().into_os_string();
+        temporary.push(format!(".{}.tmp", std::process::id()));
+        let written = fs::write(&temporary, serde_json::to_vec_pretty(&sidecar).unwrap()).and_then(|_| fs::rename(&temporary, &path));
+        match written {
+            Ok(()) => info!(sidecar = %path.display(), "Dataset statistics cached"),
+            Err(e) => {
+                let _ = fs::remove_file(&temporary);
+                warn!(sidecar = %path.display(), error = %e, "Failed to cache dataset statistics");
+            }
+        }
+        stats
+    }
+
+    /// Returns whether the statistics may describe a dataset, comparing its sizes
+    pub fn matches(&self, data: &[u8], end_positions: &[usize]) -> bool {
+        self.data_size == data.len() && self.n_items == end_positions.len().saturating_sub(1)
+    }
+}
+
+/// Returns the path of the sidecar of a dataset
+pub fn sidecar_path(dataset_path: &Path) -> PathBuf {
+    let mut path = OsString::from(dataset_path.as_os_str());
+    path.push(SIDECAR_EXTENSION);
+    PathBuf::from(path)
+}
+
+/// Returns whether a file is a sidecar, to be skipped when listing datasets
+pub fn is_sidecar(path: &Path) -> bool {
+    path.to_str().is_some_and(|path| path.ends_with(SIDECAR_EXTENSION))
+}
+
+/// Hashes the strings of a dataset and their boundaries
+///
+/// Hashing the loaded strings rather than the file makes the hash independent of the
+/// dataset format (JSON or binary) and of formatting changes that keep the strings.
+pub fn dataset_hash(data: &[u8], end_positions: &[usize]) -> u64 {
+    let mut hasher = Xxh3::new();
+    hasher.update(data);
+    let mut buffer = Vec::with_capacity(1024 * std::mem::size_of::<u64>());
+    for chunk in end_positions.chunks(1024) {
+        buffer.clear();
+        buffer.extend(chunk.iter().flat_map(|&position| (position as u64).to_le_bytes()));
+        hasher.update(&buffer);
+    }
+    hasher.digest()
+}
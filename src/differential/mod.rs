@@ -0,0 +1,234 @@
+//! Differential testing of compressors
+//!
+//! Every compressor must return the strings of a dataset unchanged, both by full
+//! decompression and by random access. The benchmark phases verify what they measure
+//! (the whole decompressed buffer, the queried strings), panicking on the first
+//! mismatch; differential testing instead checks every string of every compressor
+//! against a reference, the output of the `raw` baseline, and reports each divergence
+//! with the bytes around it rather than stopping at the first one.
+//!
+//! Compressors are also compared with each other: compressors returning the same
+//! bytes for every string form an agreement class (see `agreement_classes`), so that
+//! a bug shared by several compressors (e.g., in common decoding code) stands out from
+//! a bug of a single one, and so does a faulty reference.
+
+use crate::compressor::registry::AnyCompressor;
+use xxhash_rust::xxh3::xxh3_64;
+
+/// Bytes shown before and after a divergence
+pub const CONTEXT_BYTES: usize = 16;
+/// Slack past the longest string in the buffers handed to compressors
+const BUFFER_SLACK: usize = 1024;
+
+/// First byte where the output of a compressor differs from the reference
+#[derive(Clone, Debug)]
+pub struct Divergence {
+    pub item: Option<usize>,        // Index of the divergent string (None: full decompression)
+    pub offset: usize,              // Offset of the first differing byte (or of the end of the shorter output)
+    pub expected_length: usize,
+    pub found_length: usize,
+    pub expected_context: String,   // Reference bytes around the offset, ASCII-escaped
+    pub found_context: String,      // Compressor bytes around the offset, ASCII-escaped
+}
+
+impl Divergence {
+    /// Compares an output with the reference
+    ///
+    /// # Arguments
+    /// - `item`: Index of the compared string (None: full decompression)
+    /// - `expected`: Reference bytes
+    /// - `found`: Bytes returned by the compressor
+    ///
+    /// # Returns
+    /// The first divergence, or None if both are identical
+    pub fn find(item: Option<usize>, expected: &[u8], found: &[u8]) -> Option<Self> {
+        let offset = first_difference(expected, found)?;
+        Some(Divergence {
+            item,
+            offset,
+            expected_length: expected.len(),
+            found_length: found.len(),
+            expected_context: context(expected, offset),
+            found_context: context(found, offset),
+        })
+    }
+
+    /// Returns where the divergence occurs, e.g. "string 42, byte 7"
+    pub fn location(&self) -> String {
+        match self.item {
+            Some(item) => format!("string {}, byte {}", item, self.offset),
+            None => format!("decompressed byte {}", self.offset),
+        }
+    }
+}
+
+/// Outcome of the differential test of a compressor
+pub struct CompressorCheck {
+    pub compressor_name: String,
+    pub decompression: Option<Divergence>,  // First divergence of the full decompression, if any
+    pub divergent_items: usize,             // Strings returned differently by random access
+    pub first_item_divergence: Option<Divergence>, // First divergent string, if any
+    pub fingerprint: Vec<u64>,              // Hash of the full decompression, then of each string returned by random access
+}
+
+impl CompressorCheck {
+    /// Checks whether the compressor returned every string as the reference
+    pub fn passed(&self) -> bool {
+        self.decompression.is_none() && self.divergent_items == 0
+    }
+}
+
+/// Checks a compressor that has compressed the reference strings
+///
+/// Decompresses all strings, then retrieves each one by random access, comparing
+/// every output with the reference and reporting the first divergence of each
+/// kind along with the number of divergent strings.
+///
+/// # Arguments
+/// - `compressor`: Compressor holding the compressed strings
+/// - `reference`: Concatenated reference strings
+/// - `end_positions`: Boundary positions of the reference strings, starting with 0
+pub fn check(compressor: &mut AnyCompressor, reference: &[u8], end_positions: &[usize]) -> CompressorCheck {
+    let n_items = end_positions.len() - 1;
+    let max_length = end_positions.windows(2).map(|w| w[1] - w[0]).max().unwrap_or(0);
+    let mut fingerprint = Vec::with_capacity(n_items + 1);
+
+    // Outputs longer than the reference are clamped to the buffer, and still diverge
+    let mut buffer: Vec<u8> = vec![0; reference.len() + BUFFER_SLACK];
+    let size = compressor.decompress(&mut buffer).min(buffer.len());
+    let decompression = Divergence::find(None, reference, &buffer[..size]);
+    fingerprint.push(xxh3_64(&buffer[..size]));
+
+    buffer.truncate(max_length + BUFFER_SLACK);
+    let mut divergent_items = 0;
+    let mut first_item_divergence = None;
+    for index in 0..n_items {
+        let expected = &reference[end_positions[index]..end_positions[index + 1]];
+        let size = compressor.get_item_at(index, &mut buffer).min(buffer.len());
+        let found = &buffer[..size];
+        fingerprint.push(xxh3_64(found));
+        // The length reported without decoding must match the returned one
+        let reported_length = compressor.len_of_item(index);
+        let divergence = Divergence::find(Some(index), expected, found).or_else(|| {
+            (reported_length != size).then(|| Divergence {
+                item: Some(index),
+                offset: size,
+                expected_length: expected.len(),
+                found_length: reported_length,
+                expected_context: context(expected, size),
+                found_context: String::from("(length reported by len_of_item)"),
+            })
+        });
+        if let Some(divergence) = divergence {
+            divergent_items += 1;
+            if first_item_divergence.is_none() {
+                first_item_divergence = Some(divergence);
+            }
+        }
+    }
+
+    CompressorCheck {
+        compressor_name: compressor.name().to_string(),
+        decompression,
+        divergent_items,
+        first_item_divergence,
+        fingerprint,
+    }
+}
+
+/// Groups the compressors returning the same bytes for every string
+///
+/// # Returns
+/// Indices of the checks of each class, classes in order of their first check
+pub fn agreement_classes(checks: &[CompressorCheck]) -> Vec<Vec<usize>> {
+    let mut classes: Vec<Vec<usize>> = Vec::new();
+    for (index, check) in checks.iter().enumerate() {
+        match classes.iter_mut().find(|class| checks[class[0]].fingerprint == check.fingerprint) {
+            Some(class) => class.push(index),
+            None => classes.push(vec![index]),
+        }
+    }
+    classes
+}
+
+/// Returns the first string returned differently by two compressors
+///
+/// # Returns
+/// Index of the string, None if they agree on every string (full decompression aside)
+pub fn first_disagreement(a: &CompressorCheck, b: &CompressorCheck) -> Option<usize> {
+    a.fingerprint[1..].iter().zip(b.fingerprint[1..].iter()).position(|(a, b)| a != b)
+}
+
+/// Returns the offset of the first differing byte of two byte strings
+///
+/// The end of the shorter string counts as a difference if the other one is longer.
+fn first_difference(expected: &[u8], found: &[u8]) -> Option<usize> {
+    match expected.iter().zip(found.iter()).position(|(a, b)| a != b) {
+        Some(offset) => Some(offset),
+        None if expected.len() != found.len() => Some(expected.len().min(found.len())),
+        None => None,
+    }
+}
+
+/// Returns the bytes around an offset, ASCII-escaped, with the offset marked by `|`
+fn context(bytes: &[u8], offset: usize) -> String {
+    let offset = offset.min(bytes.len());
+    let before = &bytes[offset.saturating_sub(CONTEXT_BYTES)..offset];
+    let after = &bytes[offset..(offset + CONTEXT_BYTES).min(bytes.len())];
+    format!("{}|{}", before.escape_ascii(), after.escape_ascii())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::registry;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// Random binary strings: empty, runs of NUL and 0xFF bytes, repeated fragments and noise
+    fn binary_blobs(n_items: usize, seed: u64) -> (Vec<u8>, Vec<usize>) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let fragments: Vec<Vec<u8>> = (0..16).map(|_| (0..rng.gen_range(2..12)).map(|_| rng.gen::<u8>()).collect()).collect();
+        let mut data = Vec::new();
+        let mut end_positions = vec![0];
+        for index in 0..n_items {
+            match index % 4 {
+                0 => {}
+                1 => data.extend(std::iter::repeat_n(if rng.gen() { 0x00 } else { 0xFF }, rng.gen_range(1..64))),
+                2 => {
+                    for _ in 0..rng.gen_range(1..8) {
+                        data.extend_from_slice(&fragments[rng.gen_range(0..fragments.len())]);
+                    }
+                }
+                _ => data.extend((0..rng.gen_range(1..96)).map(|_| rng.gen::<u8>())),
+            }
+            end_positions.push(data.len());
+        }
+        (data, end_positions)
+    }
+
+    #[test]
+    fn every_compressor_returns_binary_strings_unchanged() {
+        let (data, end_positions) = binary_blobs(2000, 7);
+        for name in registry::available_compressors() {
+            let mut compressor = AnyCompressor::create(name, data.len(), end_positions.len() - 1).unwrap();
+            if compressor.capacity().check(&end_positions).is_err() {
+                continue;
+            }
+            compressor.compress(&data, &end_positions);
+            let result = check(&mut compressor, &data, &end_positions);
+            assert!(result.passed(), "{}: {:?}, {} divergent strings, first {:?}", name,
+                result.decompression.as_ref().map(Divergence::location),
+                result.divergent_items,
+                result.first_item_divergence.as_ref().map(Divergence::location));
+        }
+    }
+
+    #[test]
+    fn first_difference_counts_the_end_of_the_shorter_string() {
+        assert_eq!(first_difference(b"abc", b"abc"), None);
+        assert_eq!(first_difference(b"abc", b"abd"), Some(2));
+        assert_eq!(first_difference(b"abc", b"ab"), Some(2));
+        assert_eq!(first_difference(b"", b"\0"), Some(0));
+    }
+}
@@ -0,0 +1,281 @@
+//! Empirical entropy of string collections
+//!
+//! The order-k empirical entropy is the average number of bits per byte spent by an
+//! ideal model predicting each byte from the k bytes preceding it in the same string.
+//! It is a lower bound for any compressor using contexts of at most k bytes, and a
+//! measure of dataset difficulty independent of the algorithm.
+//!
+//! The exact computation counts every distinct context and (context, byte) pair, so
+//! that its memory grows with the dataset. `estimate_entropy` bounds memory instead:
+//! counts are kept in count-min sketches, and the information content of a reservoir
+//! sample of positions is averaged, with a confidence interval for the sampling error.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rustc_hash::FxHashMap;
+use tracing::info;
+
+/// Context order used to report the entropy of a dataset
+pub const DEFAULT_ORDER: usize = 3;
+/// Maximum supported context order
+pub const MAX_ORDER: usize = 7;
+/// Data size above which `dataset_entropy` estimates the entropy instead of computing it
+pub const EXACT_ENTROPY_LIMIT: usize = 256 << 20;
+/// Seed of the reservoir sampling, so that estimates are deterministic
+const SAMPLING_SEED: u64 = 42;
+
+/// Computes the order-k empirical entropy in bits per byte
+///
+/// Contexts do not cross string boundaries: the first bytes of a string are
+/// predicted from the shorter contexts available, which are distinct from any
+/// context of length k.
+///
+/// # Arguments
+/// - `data`: Concatenated input strings
+/// - `end_positions`: Boundary positions of the strings, starting with 0
+/// - `order`: Context length k, at most `MAX_ORDER`
+///
+/// # Returns
+/// Entropy in bits per byte (0 for empty datasets)
+pub fn empirical_entropy(data: &[u8], end_positions: &[usize], order: usize) -> f64 {
+    assert!(order <= MAX_ORDER, "order must be at most {}", MAX_ORDER);
+
+    // Contexts are packed as up to 7 bytes and their length in the most significant byte
+    let mut symbol_counts: FxHashMap<(u64, u8), u32> = FxHashMap::default();
+    let mut context_counts: FxHashMap<u64, u32> = FxHashMap::default();
+
+    for_each_context(data, end_positions, order, |key, byte| {
+        *symbol_counts.entry((key, byte)).or_insert(0) += 1;
+        *context_counts.entry(key).or_insert(0) += 1;
+    });
+
+    let n = end_positions.last().unwrap_or(&0) - end_positions.first().unwrap_or(&0);
+    if n == 0 {
+        return 0.0;
+    }
+
+    let bits: f64 = symbol_counts
+        .iter()
+        .map(|(&(key, _), &count)| {
+            let count = count as f64;
+            count * (context_counts[&key] as f64 / count).log2()
+        })
+        .sum();
+    bits / n as f64
+}
+
+/// Calls `f` with the packed context and the byte at every position of the strings
+///
+/// Contexts are packed as up to 7 bytes and their length in the most significant byte.
+#[inline(always)]
+fn for_each_context<F: FnMut(u64, u8)>(data: &[u8], end_positions: &[usize], order: usize, mut f: F) {
+    let mask = (1u64 << (8 * order)) - 1;
+    for window in end_positions.windows(2) {
+        let mut context = 0u64;
+        let mut length = 0u64;
+        for &byte in &data[window[0]..window[1]] {
+            f(context | (length << 56), byte);
+
+            context = ((context << 8) | byte as u64) & mask;
+            length = (length + 1).min(order as u64);
+        }
+    }
+}
+
+/// Memory and sampling parameters of `estimate_entropy`
+#[derive(Clone, Copy, Debug)]
+pub struct SketchConfig {
+    pub width: usize,       // Counters per sketch row, a power of two
+    pub depth: usize,       // Rows (independent hash functions) per sketch
+    pub sample_size: usize, // Positions in the reservoir sample
+}
+
+impl Default for SketchConfig {
+    /// 32 MiB of counters and a million sampled positions
+    fn default() -> Self {
+        SketchConfig { width: 1 << 20, depth: 4, sample_size: 1 << 20 }
+    }
+}
+
+/// Estimated empirical entropy, with its error bounds
+#[derive(Clone, Copy, Debug)]
+pub struct EntropyEstimate {
+    pub entropy: f64,       // Bits per byte
+    pub ci95: f64,          // Half-width of the 95% confidence interval of the sampling (0 if every position is sampled)
+    pub count_error: f64,   // Bound on the overestimation of each count, holding with probability 1 - e^-depth
+}
+
+/// Count-min sketch with conservative update
+///
+/// Estimates never underestimate a count, and exceed it by at most `e * n / width`
+/// with probability at least `1 - e^-depth`, `n` being the total of the added counts.
+struct CountMinSketch {
+    counters: Vec<u32>,     // Rows of `width` counters, concatenated
+    width: usize,
+    depth: usize,
+}
+
+impl CountMinSketch {
+    fn new(width: usize, depth: usize) -> Self {
+        assert!(width.is_power_of_two(), "Sketch width must be a power of two");
+        assert!(depth >= 1, "Sketch depth must be at least 1");
+        CountMinSketch { counters: vec![0; width * depth], width, depth }
+    }
+
+    /// Returns the counter of a key in a row
+    #[inline(always)]
+    fn slot(&self, row: usize, key: u64) -> usize {
+        row * self.width + (mix64(key ^ (row as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)) as usize & (self.width - 1))
+    }
+
+    /// Increments the count of a key, raising only its smallest counters
+    #[inline(always)]
+    fn add(&mut self, key: u64) {
+        let count = self.estimate(key).saturating_add(1);
+        for row in 0..self.depth {
+            let slot = self.slot(row, key);
+            self.counters[slot] = self.counters[slot].max(count);
+        }
+    }
+
+    /// Returns the estimated count of a key
+    #[inline(always)]
+    fn estimate(&self, key: u64) -> u32 {
+        (0..self.depth).map(|row| self.counters[self.slot(row, key)]).min().unwrap()
+    }
+}
+
+/// Estimates the order-k empirical entropy in bounded memory
+///
+/// The entropy is the average information content `log2(count(context) / count(context, byte))`
+/// of the positions of the dataset. Counts are accumulated in count-min sketches in a single
+/// pass, during which a reservoir sample of positions is drawn; the information content
+/// is then averaged over the sample. Memory depends on the configuration only.
+///
+/// # Arguments
+/// - `data`: Concatenated input strings
+/// - `end_positions`: Boundary positions of the strings, starting with 0
+/// - `order`: Context length k, at most `MAX_ORDER`
+/// - `config`: Sketch dimensions and sample size
+///
+/// # Returns
+/// The estimate and its error bounds (zero for empty datasets)
+pub fn estimate_entropy(data: &[u8], end_positions: &[usize], order: usize, config: &SketchConfig) -> EntropyEstimate {
+    assert!(order <= MAX_ORDER, "order must be at most {}", MAX_ORDER);
+    assert!(config.sample_size >= 1, "sample_size must be at least 1");
+
+    let mut context_counts = CountMinSketch::new(config.width, config.depth);
+    let mut symbol_counts = CountMinSketch::new(config.width, config.depth);
+    let mut reservoir = Reservoir::new(config.sample_size);
+    for_each_context(data, end_positions, order, |key, byte| {
+        let symbol_key = mix64(key) ^ byte as u64;
+        context_counts.add(key);
+        symbol_counts.add(symbol_key);
+        reservoir.offer((key, symbol_key));
+    });
+
+    let n = reservoir.n_offered;
+    if n == 0 {
+        return EntropyEstimate { entropy: 0.0, ci95: 0.0, count_error: 0.0 };
+    }
+
+    // Sketch collisions may leave a context count below the count of one of its bytes
+    let information: Vec<f64> = reservoir
+        .samples
+        .iter()
+        .map(|&(key, symbol_key)| {
+            let context_count = context_counts.estimate(key) as f64;
+            let symbol_count = symbol_counts.estimate(symbol_key) as f64;
+            (context_count / symbol_count).log2().max(0.0)
+        })
+        .collect();
+    let m = information.len();
+    let entropy = information.iter().sum::<f64>() / m as f64;
+    let ci95 = if m == n || m < 2 {
+        0.0
+    } else {
+        let variance = information.iter().map(|x| (x - entropy) * (x - entropy)).sum::<f64>() / (m - 1) as f64;
+        1.96 * (variance / m as f64).sqrt()
+    };
+    let count_error = std::f64::consts::E * n as f64 / config.width as f64;
+
+    EntropyEstimate { entropy, ci95, count_error }
+}
+
+/// Returns the order-`DEFAULT_ORDER` entropy of a dataset in bits per byte
+///
+/// The entropy is computed exactly up to `EXACT_ENTROPY_LIMIT` bytes, and estimated with
+/// the default sketch configuration beyond (see `estimate_entropy`).
+pub fn dataset_entropy(data: &[u8], end_positions: &[usize]) -> f64 {
+    if data.len() <= EXACT_ENTROPY_LIMIT {
+        return empirical_entropy(data, end_positions, DEFAULT_ORDER);
+    }
+
+    let estimate = estimate_entropy(data, end_positions, DEFAULT_ORDER, &SketchConfig::default());
+    info!(entropy = estimate.entropy, ci95 = estimate.ci95, count_error = estimate.count_error, "Entropy estimated");
+    estimate.entropy
+}
+
+/// Uniform sample of fixed size over a stream (Li's algorithm L)
+///
+/// Skips are drawn from a geometric distribution, so that the random generator is
+/// called once per accepted item instead of once per item.
+struct Reservoir<T> {
+    samples: Vec<T>,
+    capacity: usize,
+    n_offered: usize,
+    next: usize,        // Index of the next accepted item
+    w: f64,
+    rng: StdRng,
+}
+
+impl<T> Reservoir<T> {
+    fn new(capacity: usize) -> Self {
+        let mut rng = StdRng::seed_from_u64(SAMPLING_SEED);
+        let w = (rng.gen::<f64>().ln() / capacity as f64).exp();
+        Reservoir { samples: Vec::with_capacity(capacity), capacity, n_offered: 0, next: capacity, w, rng }
+    }
+
+    #[inline(always)]
+    fn offer(&mut self, item: T) {
+        if self.n_offered < self.capacity {
+            self.samples.push(item);
+        } else if self.n_offered == self.next {
+            let slot = self.rng.gen_range(0..self.capacity);
+            self.samples[slot] = item;
+            self.w *= (self.rng.gen::<f64>().ln() / self.capacity as f64).exp();
+            self.advance();
+        }
+        if self.n_offered + 1 == self.capacity {
+            self.advance();
+        }
+        self.n_offered += 1;
+    }
+
+    /// Draws the index of the next accepted item
+    fn advance(&mut self) {
+        let skip = (self.rng.gen::<f64>().ln() / (1.0 - self.w).ln()).floor();
+        self.next = self.n_offered + 1 + if skip.is_finite() { skip as usize } else { usize::MAX / 2 };
+    }
+}
+
+/// Mixes the bits of a key (SplitMix64 finalizer)
+#[inline(always)]
+fn mix64(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Returns how close a compressor gets to the entropy bound
+///
+/// # Arguments
+/// - `entropy`: Empirical entropy of the dataset in bits per byte
+/// - `compression_rate`: Achieved compression rate (original size over compressed size)
+///
+/// # Returns
+/// Entropy over the achieved bits per byte; 1 means the bound is reached, and values
+/// above 1 mean the compressor exploits longer contexts than the entropy order
+pub fn entropy_efficiency(entropy: f64, compression_rate: f64) -> f64 {
+    entropy * compression_rate / 8.0
+}
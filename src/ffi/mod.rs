@@ -0,0 +1,335 @@
+//! C ABI for embedding the compressors in non-Rust hosts
+//!
+//! Exposes the registered compression algorithms through `extern "C"` functions
+//! operating on opaque handles. Every function returns a `CbStatus` error code and
+//! writes its results through output pointers; panics are caught at the boundary
+//! and reported as `CB_STATUS_PANIC`. After a panic, the handle must only be destroyed.
+//!
+//! Output buffers must provide `CB_BUFFER_PADDING` bytes of slack beyond the data
+//! they receive, as decoders copy fixed-size chunks past the end of short items.
+//!
+//! The C header is generated at build time into `include/compression_benchmark.h`.
+
+use crate::compressor::registry::AnyCompressor;
+use std::ffi::{c_char, CStr};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// Extra bytes required at the end of every output buffer
+pub use crate::compressor::BUFFER_PADDING as CB_BUFFER_PADDING;
+
+/// Error codes returned by all FFI functions
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CbStatus {
+    Ok = 0,                 // Success
+    NullPointer = 1,        // A required pointer argument is null
+    UnknownCompressor = 2,  // The compressor identifier is not registered
+    InvalidArgument = 3,    // Input data is inconsistent with the handle or malformed
+    InvalidState = 4,       // Operation not allowed before/after compression
+    IndexOutOfBounds = 5,   // Item index is not smaller than the number of items
+    BufferTooSmall = 6,     // Output buffer cannot hold the data plus padding
+    Panic = 7,              // The compressor panicked
+}
+
+/// Opaque compressor handle
+///
+/// Keeps the dataset sizes given at creation to validate the compressed dataset and
+/// indices; item lengths for buffer checks come from the compressor.
+pub struct CbCompressor {
+    compressor: AnyCompressor,
+    data_size: usize,
+    n_elements: usize,
+    is_compressed: bool,
+}
+
+/// Runs an FFI function body, converting panics to `CbStatus::Panic`
+fn guard<F: FnOnce() -> CbStatus>(body: F) -> CbStatus {
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or(CbStatus::Panic)
+}
+
+/// Returns a static, NUL-terminated description of a status code
+#[no_mangle]
+pub extern "C" fn cb_status_message(status: CbStatus) -> *const c_char {
+    let message: &'static [u8] = match status {
+        CbStatus::Ok => b"ok\0",
+        CbStatus::NullPointer => b"null pointer argument\0",
+        CbStatus::UnknownCompressor => b"unknown compressor\0",
+        CbStatus::InvalidArgument => b"invalid argument\0",
+        CbStatus::InvalidState => b"invalid handle state\0",
+        CbStatus::IndexOutOfBounds => b"index out of bounds\0",
+        CbStatus::BufferTooSmall => b"buffer too small\0",
+        CbStatus::Panic => b"compressor panicked\0",
+    };
+    message.as_ptr() as *const c_char
+}
+
+/// Creates a compressor handle
+///
+/// # Arguments
+/// - `name`: NUL-terminated algorithm identifier (e.g., "onpair16")
+/// - `data_size`: Total size of the dataset in bytes
+/// - `n_elements`: Number of strings in the dataset
+/// - `out_handle`: Receives the new handle, to be released with `cb_compressor_destroy`
+///
+/// # Safety
+/// `name` must point to a valid NUL-terminated string and `out_handle` to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn cb_compressor_create(
+    name: *const c_char,
+    data_size: usize,
+    n_elements: usize,
+    out_handle: *mut *mut CbCompressor,
+) -> CbStatus {
+    guard(|| {
+        if name.is_null() || out_handle.is_null() {
+            return CbStatus::NullPointer;
+        }
+        let name = match CStr::from_ptr(name).to_str() {
+            Ok(name) => name,
+            Err(_) => return CbStatus::UnknownCompressor,
+        };
+        let compressor = match AnyCompressor::create(name, data_size, n_elements) {
+            Some(compressor) => compressor,
+            None => return CbStatus::UnknownCompressor,
+        };
+
+        let handle = CbCompressor { compressor, data_size, n_elements, is_compressed: false };
+        *out_handle = Box::into_raw(Box::new(handle));
+        CbStatus::Ok
+    })
+}
+
+/// Compresses a dataset into the handle
+///
+/// A handle compresses exactly one dataset, whose sizes must match those given at creation.
+///
+/// # Arguments
+/// - `handle`: Compressor handle
+/// - `data`: Concatenated strings (`data_len` bytes)
+/// - `end_positions`: Boundary positions of the strings, starting with 0 (`n_end_positions` entries)
+///
+/// # Safety
+/// `handle` must come from `cb_compressor_create`; `data` and `end_positions` must be
+/// valid for reads of the given lengths (`data` may be null for an empty dataset).
+#[no_mangle]
+pub unsafe extern "C" fn cb_compressor_compress(
+    handle: *mut CbCompressor,
+    data: *const u8,
+    data_len: usize,
+    end_positions: *const usize,
+    n_end_positions: usize,
+) -> CbStatus {
+    guard(|| {
+        if handle.is_null() || (data.is_null() && data_len > 0) || end_positions.is_null() {
+            return CbStatus::NullPointer;
+        }
+        let handle = &mut *handle;
+        if handle.is_compressed {
+            return CbStatus::InvalidState;
+        }
+
+        let data = if data_len == 0 { &[] } else { std::slice::from_raw_parts(data, data_len) };
+        let end_positions = std::slice::from_raw_parts(end_positions, n_end_positions);
+        let is_valid = data_len == handle.data_size
+            && n_end_positions == handle.n_elements + 1
+            && end_positions[0] == 0
+            && end_positions[handle.n_elements] == data_len
+            && end_positions.windows(2).all(|w| w[0] <= w[1]);
+        if !is_valid {
+            return CbStatus::InvalidArgument;
+        }
+
+        handle.compressor.compress(data, end_positions);
+        handle.is_compressed = true;
+        CbStatus::Ok
+    })
+}
+
+/// Reports the length of a string in bytes
+///
+/// # Safety
+/// `handle` must come from `cb_compressor_create` and `out_len` must point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn cb_compressor_item_len(
+    handle: *const CbCompressor,
+    index: usize,
+    out_len: *mut usize,
+) -> CbStatus {
+    guard(|| {
+        if handle.is_null() || out_len.is_null() {
+            return CbStatus::NullPointer;
+        }
+        let handle = &*handle;
+        if !handle.is_compressed {
+            return CbStatus::InvalidState;
+        }
+        if index >= handle.n_elements {
+            return CbStatus::IndexOutOfBounds;
+        }
+
+        *out_len = handle.compressor.len_of_item(index);
+        CbStatus::Ok
+    })
+}
+
+/// Retrieves a single string by index
+///
+/// # Arguments
+/// - `handle`: Compressor handle
+/// - `index`: Zero-based index of the string
+/// - `buffer`: Output buffer of `buffer_len` bytes (item length plus `CB_BUFFER_PADDING`)
+/// - `out_len`: Receives the number of bytes written
+///
+/// # Safety
+/// `handle` must come from `cb_compressor_create`, `buffer` must be valid for writes of
+/// `buffer_len` bytes and `out_len` must point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn cb_compressor_get_item_at(
+    handle: *mut CbCompressor,
+    index: usize,
+    buffer: *mut u8,
+    buffer_len: usize,
+    out_len: *mut usize,
+) -> CbStatus {
+    guard(|| {
+        if handle.is_null() || buffer.is_null() || out_len.is_null() {
+            return CbStatus::NullPointer;
+        }
+        let handle = &mut *handle;
+        if !handle.is_compressed {
+            return CbStatus::InvalidState;
+        }
+        if index >= handle.n_elements {
+            return CbStatus::IndexOutOfBounds;
+        }
+        let item_len = handle.compressor.len_of_item(index);
+        if buffer_len < item_len + CB_BUFFER_PADDING {
+            return CbStatus::BufferTooSmall;
+        }
+
+        let buffer = std::slice::from_raw_parts_mut(buffer, buffer_len);
+        *out_len = handle.compressor.get_item_at(index, buffer);
+        CbStatus::Ok
+    })
+}
+
+/// Decompresses the entire dataset
+///
+/// # Arguments
+/// - `handle`: Compressor handle
+/// - `buffer`: Output buffer of `buffer_len` bytes (data size plus `CB_BUFFER_PADDING`)
+/// - `out_len`: Receives the number of bytes written
+///
+/// # Safety
+/// `handle` must come from `cb_compressor_create`, `buffer` must be valid for writes of
+/// `buffer_len` bytes and `out_len` must point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn cb_compressor_decompress(
+    handle: *const CbCompressor,
+    buffer: *mut u8,
+    buffer_len: usize,
+    out_len: *mut usize,
+) -> CbStatus {
+    guard(|| {
+        if handle.is_null() || buffer.is_null() || out_len.is_null() {
+            return CbStatus::NullPointer;
+        }
+        let handle = &*handle;
+        if !handle.is_compressed {
+            return CbStatus::InvalidState;
+        }
+        if buffer_len < handle.data_size + CB_BUFFER_PADDING {
+            return CbStatus::BufferTooSmall;
+        }
+
+        let buffer = std::slice::from_raw_parts_mut(buffer, buffer_len);
+        *out_len = handle.compressor.decompress(buffer);
+        CbStatus::Ok
+    })
+}
+
+/// Reports the memory used by the compressed representation in bytes
+///
+/// # Safety
+/// `handle` must come from `cb_compressor_create` and `out_bytes` must point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn cb_compressor_space_used(
+    handle: *const CbCompressor,
+    out_bytes: *mut usize,
+) -> CbStatus {
+    guard(|| {
+        if handle.is_null() || out_bytes.is_null() {
+            return CbStatus::NullPointer;
+        }
+        let handle = &*handle;
+        if !handle.is_compressed {
+            return CbStatus::InvalidState;
+        }
+
+        *out_bytes = handle.compressor.space_used_bytes();
+        CbStatus::Ok
+    })
+}
+
+/// Releases a compressor handle; null handles are ignored
+///
+/// # Safety
+/// `handle` must come from `cb_compressor_create` and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn cb_compressor_destroy(handle: *mut CbCompressor) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+    use std::ptr;
+
+    /// Creates a handle of the raw compressor
+    fn create(data_size: usize, n_elements: usize) -> *mut CbCompressor {
+        let name = CString::new("raw").unwrap();
+        let mut handle = ptr::null_mut();
+        let status = unsafe { cb_compressor_create(name.as_ptr(), data_size, n_elements, &mut handle) };
+        assert_eq!(status, CbStatus::Ok);
+        handle
+    }
+
+    #[test]
+    fn null_data_is_accepted_for_an_empty_dataset() {
+        let handle = create(0, 0);
+        let end_positions = [0];
+        unsafe {
+            assert_eq!(cb_compressor_compress(handle, ptr::null(), 0, end_positions.as_ptr(), end_positions.len()), CbStatus::Ok);
+            cb_compressor_destroy(handle);
+        }
+    }
+
+    #[test]
+    fn null_data_is_rejected_for_a_nonempty_dataset() {
+        let handle = create(3, 1);
+        let end_positions = [0, 3];
+        unsafe {
+            assert_eq!(cb_compressor_compress(handle, ptr::null(), 3, end_positions.as_ptr(), end_positions.len()), CbStatus::NullPointer);
+            cb_compressor_destroy(handle);
+        }
+    }
+
+    #[test]
+    fn strings_are_returned_with_padded_buffers() {
+        let data = b"abcdefgh";
+        let end_positions = [0, 3, 3, 8];
+        let handle = create(data.len(), end_positions.len() - 1);
+        let mut buffer = vec![0; 5 + CB_BUFFER_PADDING];
+        let mut len = 0;
+        unsafe {
+            assert_eq!(cb_compressor_compress(handle, data.as_ptr(), data.len(), end_positions.as_ptr(), end_positions.len()), CbStatus::Ok);
+            assert_eq!(cb_compressor_get_item_at(handle, 2, buffer.as_mut_ptr(), buffer.len(), &mut len), CbStatus::Ok);
+            assert_eq!(&buffer[..len], b"defgh");
+            assert_eq!(cb_compressor_get_item_at(handle, 2, buffer.as_mut_ptr(), buffer.len() - 1, &mut len), CbStatus::BufferTooSmall);
+            cb_compressor_destroy(handle);
+        }
+    }
+}
@@ -7,4 +7,24 @@
 
 pub mod benchmark_utils;
 pub mod compressor;
-pub mod bit_vector;
\ No newline at end of file
+pub mod bit_vector;
+pub mod tuning;
+pub mod entropy;
+pub mod dataset_stats;
+pub mod logging;
+pub mod profiler;
+pub mod perf;
+pub mod phase_profile;
+pub mod alloc_counter;
+pub mod ranking;
+pub mod cost_model;
+pub mod store;
+pub mod bundle;
+pub mod dataset_order;
+pub mod stress;
+pub mod differential;
+pub mod cli;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "python")]
+pub mod python;
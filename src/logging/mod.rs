@@ -0,0 +1,74 @@
+//! Logging setup for the benchmark binaries
+//!
+//! Diagnostics are emitted through `tracing`: benchmark phases (compression, training,
+//! parsing, decompression, random access) are recorded as spans, progress and results
+//! as events, and per-block events are emitted at debug level. Logs are written to
+//! stderr, either as human-readable text or as one JSON object per line, so that
+//! benchmark tables on stdout are unaffected.
+
+use crate::benchmark_utils::take_option;
+use std::io::IsTerminal;
+use tracing::Level;
+
+/// Default maximum level of the emitted events
+pub const DEFAULT_LOG_LEVEL: Level = Level::INFO;
+
+/// Output format of the log records
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LogFormat {
+    Text,   // Human-readable lines
+    Json,   // One JSON object per line
+}
+
+impl LogFormat {
+    /// Parses a format identifier (`text` or `json`)
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(LogFormat::Text),
+            "json" => Some(LogFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Installs the global subscriber writing logs to stderr
+///
+/// Does nothing if a subscriber is already installed.
+///
+/// # Arguments
+/// - `level`: Maximum level of the emitted events
+/// - `format`: Output format of the log records
+pub fn init(level: Level, format: LogFormat) {
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_ansi(std::io::stderr().is_terminal())
+        .with_writer(std::io::stderr);
+    let _ = match format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder.json().try_init(),
+    };
+}
+
+/// Installs the global subscriber configured by command-line options
+///
+/// Removes `--log-level=<trace|debug|info|warn|error>` (default: info) and
+/// `--log-format=<text|json>` (default: text) from the arguments.
+///
+/// # Arguments
+/// - `args`: Command-line arguments
+///
+/// # Returns
+/// An error message if an option has an invalid value
+pub fn init_from_args(args: &mut Vec<String>) -> Result<(), String> {
+    let level = match take_option(args, "--log-level") {
+        Some(value) => value.parse::<Level>().map_err(|_| format!("Invalid log level '{}'.", value))?,
+        None => DEFAULT_LOG_LEVEL,
+    };
+    let format = match take_option(args, "--log-format") {
+        Some(value) => LogFormat::parse(&value).ok_or_else(|| format!("Invalid log format '{}'.", value))?,
+        None => LogFormat::Text,
+    };
+
+    init(level, format);
+    Ok(())
+}
@@ -0,0 +1,177 @@
+//! Hardware performance counters (Linux only)
+//!
+//! Wall-clock latencies tell which compressor is faster, but not why: a compressor may
+//! execute more instructions per access, miss the last-level cache more often (e.g.,
+//! touching a large dictionary or a whole block), or mispredict more branches. This
+//! module reads these events from the kernel's perf subsystem (`perf_event_open`),
+//! counting user-space events of the calling thread between `enable` and `disable`.
+//!
+//! Counters are unavailable on other platforms, and may be unavailable on Linux too
+//! (e.g., restricted by `perf_event_paranoid`, or not exposed by a virtual machine):
+//! events that cannot be opened are skipped, and `PerfCounters::open` returns `None`
+//! if none can.
+
+use std::fs::File;
+use std::io::Read;
+#[cfg(target_os = "linux")]
+use std::os::fd::{AsRawFd, FromRawFd};
+use tracing::debug;
+
+/// Hardware event counted around random access
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HardwareEvent {
+    Instructions,   // Instructions retired
+    LlcMisses,      // Last-level cache misses
+    BranchMisses,   // Mispredicted branches
+}
+
+impl HardwareEvent {
+    /// All events, in report order
+    pub const ALL: [HardwareEvent; 3] = [HardwareEvent::Instructions, HardwareEvent::LlcMisses, HardwareEvent::BranchMisses];
+
+    /// Returns the name of the event
+    pub fn name(&self) -> &'static str {
+        match self {
+            HardwareEvent::Instructions => "instructions",
+            HardwareEvent::LlcMisses => "llc_misses",
+            HardwareEvent::BranchMisses => "branch_misses",
+        }
+    }
+
+    /// Returns the generalized hardware event identifier (`PERF_COUNT_HW_*`)
+    #[cfg(target_os = "linux")]
+    fn config(&self) -> u64 {
+        match self {
+            HardwareEvent::Instructions => 1,   // PERF_COUNT_HW_INSTRUCTIONS
+            HardwareEvent::LlcMisses => 3,      // PERF_COUNT_HW_CACHE_MISSES
+            HardwareEvent::BranchMisses => 5,   // PERF_COUNT_HW_BRANCH_MISSES
+        }
+    }
+}
+
+/// Event counts read from the counters, `None` for events that could not be opened
+#[derive(Clone, Copy, Default, Debug)]
+pub struct HardwareCounts {
+    pub instructions: Option<u64>,
+    pub llc_misses: Option<u64>,
+    pub branch_misses: Option<u64>,
+}
+
+/// Set of hardware counters of the calling thread
+pub struct PerfCounters {
+    counters: Vec<(HardwareEvent, File)>,   // Opened events, with their perf file descriptors
+}
+
+impl PerfCounters {
+    /// Opens a disabled counter for each supported event
+    ///
+    /// # Returns
+    /// The counters, or `None` if no event can be counted on this machine
+    pub fn open() -> Option<Self> {
+        let counters: Vec<(HardwareEvent, File)> = HardwareEvent::ALL
+            .into_iter()
+            .filter_map(|event| match open_event(event) {
+                Some(file) => Some((event, file)),
+                None => {
+                    debug!(event = event.name(), "Hardware event unavailable");
+                    None
+                }
+            })
+            .collect();
+        (!counters.is_empty()).then_some(PerfCounters { counters })
+    }
+
+    /// Resets the counters to zero and starts counting
+    pub fn enable(&mut self) {
+        for (_, file) in &self.counters {
+            control(file, PERF_EVENT_IOC_RESET);
+            control(file, PERF_EVENT_IOC_ENABLE);
+        }
+    }
+
+    /// Stops counting
+    pub fn disable(&mut self) {
+        for (_, file) in &self.counters {
+            control(file, PERF_EVENT_IOC_DISABLE);
+        }
+    }
+
+    /// Returns the events counted between the last `enable` and `disable`
+    pub fn read(&mut self) -> HardwareCounts {
+        let mut counts = HardwareCounts::default();
+        for (event, file) in &mut self.counters {
+            let mut value = [0u8; 8];
+            file.read_exact(&mut value).expect("Failed to read hardware counter");
+            let value = Some(u64::from_ne_bytes(value));
+            match event {
+                HardwareEvent::Instructions => counts.instructions = value,
+                HardwareEvent::LlcMisses => counts.llc_misses = value,
+                HardwareEvent::BranchMisses => counts.branch_misses = value,
+            }
+        }
+        counts
+    }
+}
+
+/// `perf_event_attr` up to its first published version (`PERF_ATTR_SIZE_VER0`)
+#[cfg(target_os = "linux")]
+#[repr(C)]
+#[derive(Default)]
+struct PerfEventAttr {
+    event_type: u32,
+    size: u32,
+    config: u64,
+    sample_period: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    wakeup_events: u32,
+    bp_type: u32,
+    config1: u64,
+}
+
+/// Event type of generalized hardware events
+#[cfg(target_os = "linux")]
+const PERF_TYPE_HARDWARE: u32 = 0;
+/// `perf_event_attr` flags: start disabled, count user space only
+#[cfg(target_os = "linux")]
+const PERF_FLAGS: u64 = 1 | (1 << 5) | (1 << 6); // disabled | exclude_kernel | exclude_hv
+/// Close-on-exec flag of `perf_event_open`
+#[cfg(target_os = "linux")]
+const PERF_FLAG_FD_CLOEXEC: libc::c_ulong = 1 << 3;
+
+/// Counter control requests (`_IO('$', n)`)
+const PERF_EVENT_IOC_ENABLE: u64 = 0x2400;
+const PERF_EVENT_IOC_DISABLE: u64 = 0x2401;
+const PERF_EVENT_IOC_RESET: u64 = 0x2403;
+
+/// Opens a disabled counter of an event for the calling thread, on any CPU
+#[cfg(target_os = "linux")]
+fn open_event(event: HardwareEvent) -> Option<File> {
+    let attr = PerfEventAttr {
+        event_type: PERF_TYPE_HARDWARE,
+        size: std::mem::size_of::<PerfEventAttr>() as u32,
+        config: event.config(),
+        flags: PERF_FLAGS,
+        ..Default::default()
+    };
+    let fd = unsafe {
+        libc::syscall(libc::SYS_perf_event_open, &attr as *const PerfEventAttr, 0, -1, -1, PERF_FLAG_FD_CLOEXEC)
+    };
+    (fd >= 0).then(|| unsafe { File::from_raw_fd(fd as libc::c_int) })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_event(_event: HardwareEvent) -> Option<File> {
+    None
+}
+
+/// Sends a control request to a counter
+#[cfg(target_os = "linux")]
+fn control(file: &File, request: u64) {
+    let result = unsafe { libc::ioctl(file.as_raw_fd(), request as _, 0) };
+    assert!(result == 0, "Failed to control hardware counter");
+}
+
+#[cfg(not(target_os = "linux"))]
+fn control(_file: &File, _request: u64) {}
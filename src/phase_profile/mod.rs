@@ -0,0 +1,136 @@
+//! Sampling profiles of benchmark phases (`pprof` feature)
+//!
+//! Latencies and hardware counters tell how fast a compressor is, not where its time
+//! goes. Once enabled with an output directory (`enable`), every phase entered with
+//! `enter` (compression, decompression and random access, around the measured runs and
+//! their warm-up runs) samples the call stacks of the process with pprof-rs, and writes
+//! its profile when the phase ends, both as a flamegraph (`.svg`) and as a pprof
+//! protobuf (`.pb`, readable by `go tool pprof` or speedscope), named after the
+//! dataset, the compressor and the phase. Samples also cover the unmeasured verification
+//! of the phase, and sampling slows the phase down: profiled runs are meant for finding
+//! hotspots, not for reporting measurements.
+//!
+//! Without the feature, `enable` fails and `enter` does nothing.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+#[cfg(feature = "pprof")]
+use std::fs::File;
+#[cfg(feature = "pprof")]
+use tracing::{info, warn};
+
+/// Stack samples per second of a phase profile (prime, to avoid sampling in lockstep with periodic work)
+pub const SAMPLING_FREQUENCY: i32 = 997;
+
+/// Directory receiving the profiles, once enabled
+static OUTPUT_DIRECTORY: OnceLock<PathBuf> = OnceLock::new();
+
+/// Enables the profiles of the phases entered from now on
+///
+/// # Arguments
+/// - `directory`: Directory receiving the profiles, created if missing
+///
+/// # Returns
+/// An error message if the `pprof` feature is not compiled in, the directory cannot be
+/// created, or the profiles are already enabled
+pub fn enable(directory: &Path) -> Result<(), String> {
+    if !cfg!(feature = "pprof") {
+        return Err("Phase profiles require the 'pprof' feature".to_string());
+    }
+    fs::create_dir_all(directory).map_err(|e| format!("Failed to create '{}': {}", directory.display(), e))?;
+    OUTPUT_DIRECTORY
+        .set(directory.to_path_buf())
+        .map_err(|_| "Phase profiles are already enabled".to_string())
+}
+
+/// Profile of a phase, written when dropped (nothing if profiles are disabled)
+pub struct PhaseProfile {
+    #[cfg(feature = "pprof")]
+    active: Option<(pprof::ProfilerGuard<'static>, PathBuf)>, // Sampler, and path of the profile without extension
+}
+
+impl PhaseProfile {
+    /// Ends the phase, writing its profile
+    pub fn finish(self) {}
+}
+
+/// Starts the profile of a phase, if profiles are enabled
+///
+/// # Arguments
+/// - `dataset_name`: Name of the dataset
+/// - `compressor_name`: Display name of the compressor
+/// - `phase`: Name of the phase (e.g., `random_access`)
+pub fn enter(dataset_name: &str, compressor_name: &str, phase: &str) -> PhaseProfile {
+    #[cfg(feature = "pprof")]
+    {
+        let active = OUTPUT_DIRECTORY.get().and_then(|directory| {
+            let name = format!("{}.{}.{}", file_name_part(dataset_name), file_name_part(compressor_name), phase);
+            let guard = pprof::ProfilerGuardBuilder::default()
+                .frequency(SAMPLING_FREQUENCY)
+                .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+                .build();
+            match guard {
+                Ok(guard) => Some((guard, directory.join(name))),
+                Err(e) => {
+                    warn!(phase, error = %e, "Failed to start the phase profile");
+                    None
+                }
+            }
+        });
+        PhaseProfile { active }
+    }
+    #[cfg(not(feature = "pprof"))]
+    {
+        let _ = (dataset_name, compressor_name, phase);
+        PhaseProfile {}
+    }
+}
+
+#[cfg(feature = "pprof")]
+impl Drop for PhaseProfile {
+    fn drop(&mut self) {
+        let Some((guard, path)) = self.active.take() else {
+            return;
+        };
+        match write_profile(&guard, &path) {
+            Ok(()) => info!(profile = %path.display(), "Phase profile written"),
+            Err(message) => warn!(profile = %path.display(), error = %message, "Failed to write the phase profile"),
+        }
+    }
+}
+
+/// Writes the samples of a phase as a flamegraph and as a pprof protobuf
+///
+/// # Arguments
+/// - `guard`: Sampler of the phase
+/// - `path`: Path of the profile, without extension
+#[cfg(feature = "pprof")]
+fn write_profile(guard: &pprof::ProfilerGuard, path: &Path) -> Result<(), String> {
+    use pprof::protos::Message;
+
+    let report = guard.report().build().map_err(|e| e.to_string())?;
+    let flamegraph = File::create(with_suffix(path, "svg")).map_err(|e| e.to_string())?;
+    report.flamegraph(flamegraph).map_err(|e| e.to_string())?;
+
+    let profile = report.pprof().map_err(|e| e.to_string())?;
+    let mut content = Vec::new();
+    profile.encode(&mut content).map_err(|e| e.to_string())?;
+    fs::write(with_suffix(path, "pb"), content).map_err(|e| e.to_string())
+}
+
+/// Appends an extension to a path whose name contains dots
+#[cfg(feature = "pprof")]
+fn with_suffix(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+/// Replaces the characters of a name that are unsafe in file names (e.g., the spaces
+/// and parentheses of compressor names) with underscores
+#[cfg(feature = "pprof")]
+fn file_name_part(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' { c } else { '_' }).collect()
+}
@@ -0,0 +1,101 @@
+//! Latency attribution of random access
+//!
+//! The instrumented access path (`Compressor::get_item_at_profiled`) splits each
+//! access into stages: locating the string (binary search over blocks or offsets),
+//! decoding it (block decompression or token decoding) and copying it into the output
+//! buffer. Stages are timed with the timestamp counter where available, so that the
+//! instrumentation costs a few cycles per stage; averages over many queries tell
+//! whether a compressor is bound by search, decoding or copying on a dataset.
+
+/// Number of stages of a random access
+pub const N_STAGES: usize = 3;
+
+/// Stage of a random access
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stage {
+    Lookup,     // Locating the string: item offsets, block search, ID mapping
+    Decode,     // Producing its bytes: block decompression, token decoding, verification
+    Copy,       // Writing its bytes to the output buffer
+}
+
+impl Stage {
+    /// All stages, in access order
+    pub const ALL: [Stage; N_STAGES] = [Stage::Lookup, Stage::Decode, Stage::Copy];
+
+    /// Returns the name of the stage
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Lookup => "lookup",
+            Stage::Decode => "decode",
+            Stage::Copy => "copy",
+        }
+    }
+}
+
+/// Accumulates the ticks spent in each stage over many accesses
+///
+/// The caller starts the timer before each access, and the compressor laps it at the
+/// end of each stage, charging the ticks elapsed since the previous lap to that stage.
+pub struct StageTimer {
+    last: u64,                      // Timestamp of the last start or lap
+    ticks: [u64; N_STAGES],         // Ticks charged to each stage
+    pub spans: Vec<(usize, usize)>, // Scratch (offset, length) pairs of decoded tokens, reused across accesses
+}
+
+impl Default for StageTimer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StageTimer {
+    /// Creates a timer with no ticks charged
+    pub fn new() -> Self {
+        StageTimer { last: 0, ticks: [0; N_STAGES], spans: Vec::new() }
+    }
+
+    /// Marks the beginning of an access
+    #[inline(always)]
+    pub fn start(&mut self) {
+        self.last = timestamp();
+    }
+
+    /// Charges the ticks elapsed since the previous start or lap to a stage
+    #[inline(always)]
+    pub fn lap(&mut self, stage: Stage) {
+        let now = timestamp();
+        self.ticks[stage as usize] += now.wrapping_sub(self.last);
+        self.last = now;
+    }
+
+    /// Returns the ticks charged to a stage
+    pub fn ticks(&self, stage: Stage) -> u64 {
+        self.ticks[stage as usize]
+    }
+
+    /// Clears the ticks charged to every stage
+    pub fn reset(&mut self) {
+        self.ticks = [0; N_STAGES];
+    }
+}
+
+/// Reads a monotonic timestamp in ticks
+///
+/// Ticks are cycles of the timestamp counter on x86_64, read as by the `tsc` timer
+/// (see `clock`), and nanoseconds since the first call elsewhere. Convert them to
+/// time by calibrating against a wall clock over a long enough interval.
+#[inline(always)]
+pub fn timestamp() -> u64 {
+    #[cfg(target_arch = "x86_64")]
+    {
+        crate::benchmark_utils::clock::read_tsc()
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        use std::sync::OnceLock;
+        use std::time::Instant;
+
+        static EPOCH: OnceLock<Instant> = OnceLock::new();
+        EPOCH.get_or_init(Instant::now).elapsed().as_nanos() as u64
+    }
+}
@@ -0,0 +1,288 @@
+//! Python bindings for dataset loading and benchmark execution
+//!
+//! Exposes the dataset loaders, the compressor registry and the benchmark protocol
+//! as a Python extension module. Results are returned as flat dictionaries (one per
+//! benchmark run), so that a list of results converts directly to a pandas DataFrame:
+//!
+//! ```python
+//! import pandas as pd
+//! import compression_benchmark_rs as cb
+//!
+//! df = pd.DataFrame([cb.benchmark("data/urls.json", c) for c in cb.compressors()])
+//! ```
+
+// The `#[pyfunction]` expansion converts `PyResult` errors into themselves
+#![allow(clippy::useless_conversion)]
+
+use crate::benchmark_utils::{self, BenchmarkOptions, BenchmarkResult, BenchmarkSummary, QueryDistribution, QueryPolicy, DEFAULT_N_QUERIES};
+use crate::compressor::registry::{self, AnyCompressor};
+use crate::logging::{self, LogFormat};
+use crate::ranking;
+use pyo3::exceptions::{PyFileNotFoundError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+use std::path::Path;
+use std::time::Duration;
+use tracing::Level;
+
+/// Returns the identifiers of the compression algorithms compiled in
+#[pyfunction]
+fn compressors() -> Vec<&'static str> {
+    registry::available_compressors()
+}
+
+/// Loads a dataset as concatenated bytes and item boundary positions (starting with 0)
+#[pyfunction]
+fn load_dataset<'py>(py: Python<'py>, path: &str) -> PyResult<(Bound<'py, PyBytes>, Vec<usize>)> {
+    let path = check_file(path)?;
+    let (data, end_positions) = py.allow_threads(|| benchmark_utils::load_dataset(path));
+    Ok((PyBytes::new_bound(py, &data), end_positions))
+}
+
+/// Benchmarks a compressor on a dataset, returning the result as a flat dictionary
+///
+/// With `warmup_iterations` > 0, the result also reports first-run measurements
+/// (`first_run_*`), taken before the unmeasured warm-up runs. With `streaming`,
+/// decompression writes to a sink with bounded memory. With `batch_size`, random
+/// access is also measured in batches (`batch_*`). With `scan_length`, scans of that many
+/// consecutive strings are also measured (`scan_*`). With `profile_stages`, random access
+/// latency is split into lookup, decoding and copying (`*_time`). With `phase_timeout`
+/// (seconds), a phase exceeding it cancels the run, and `timed_out` names that phase.
+/// With `zero_copy`, random access is also measured without copying, for compressors
+/// supporting it (`zero_copy_*`). With `perf_counters`, hardware events of random access
+/// are counted per query where the machine exposes them (`instructions`, `llc_misses`,
+/// `branch_misses`). With `plain_baselines`, the queries are also issued to uncompressed
+/// in-memory stores (`plain_*`). With `validation` (a fraction in (0, 1]), that fraction
+/// of the strings is retrieved and checked one by one, including UTF-8 validity
+/// (`validated_*`).
+#[pyfunction]
+#[allow(clippy::too_many_arguments)]
+#[pyo3(signature = (dataset_path, compressor, n_queries = DEFAULT_N_QUERIES, warmup_iterations = 0, streaming = false, batch_size = None, scan_length = None, profile_stages = false, phase_timeout = None, zero_copy = false, perf_counters = false, plain_baselines = false, validation = None))]
+fn benchmark<'py>(
+    py: Python<'py>,
+    dataset_path: &str,
+    compressor: &str,
+    n_queries: usize,
+    warmup_iterations: usize,
+    streaming: bool,
+    batch_size: Option<usize>,
+    scan_length: Option<usize>,
+    profile_stages: bool,
+    phase_timeout: Option<f64>,
+    zero_copy: bool,
+    perf_counters: bool,
+    plain_baselines: bool,
+    validation: Option<f64>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let path = check_file(dataset_path)?;
+    let dataset_name = path.file_name().unwrap().to_string_lossy().to_string();
+    let phase_timeout = match phase_timeout {
+        Some(seconds) if seconds.is_finite() && seconds > 0.0 => Some(Duration::from_secs_f64(seconds)),
+        Some(_) => return Err(PyValueError::new_err("phase_timeout must be a positive number of seconds")),
+        None => None,
+    };
+    if validation.is_some_and(|fraction| !(fraction > 0.0 && fraction <= 1.0)) {
+        return Err(PyValueError::new_err("validation must be a fraction in (0, 1]"));
+    }
+
+    let result = py.allow_threads(|| {
+        let (data, end_positions) = benchmark_utils::load_dataset(path);
+        let n_elements = end_positions.len() - 1;
+        let policy = QueryPolicy::Fixed(n_queries);
+        let queries = benchmark_utils::generate_queries(policy, n_elements, QueryDistribution::Uniform, false);
+
+        let mut compressor = AnyCompressor::create(compressor, data.len(), n_elements)?;
+        let options = BenchmarkOptions { warmup_iterations, streaming, batch_size, scan_length, profile_stages, phase_timeout, zero_copy, perf_counters, plain_baselines, validation, ..Default::default() };
+        let mut result = compressor.benchmark_with_options(dataset_name, &data, &end_positions, &queries, &options);
+        result.query_policy = Some(policy.describe(false));
+        Some(result)
+    });
+
+    match result {
+        Some(result) => result_to_dict(py, &result),
+        None => Err(PyValueError::new_err(registry::unavailable_message(compressor))),
+    }
+}
+
+/// Reads the results of a JSON results file as a list of flat dictionaries
+#[pyfunction]
+fn read_results<'py>(py: Python<'py>, path: &str) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    let results = load_results(path)?;
+    results.iter().map(|result| result_to_dict(py, result)).collect()
+}
+
+/// Summarizes a JSON results file as a list of flat dictionaries (one per dataset and compressor)
+#[pyfunction]
+fn summarize_results<'py>(py: Python<'py>, path: &str) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    let results = load_results(path)?;
+    let summaries = benchmark_utils::summarize_benchmark_results(&results);
+    summaries.iter().map(|summary| summary_to_dict(py, summary)).collect()
+}
+
+/// Ranks the compressors of a JSON results file relative to a baseline (see `ranking`)
+///
+/// Returns a list of flat dictionaries, one per dataset and compressor, with the rank,
+/// score and normalized metrics of the compressor on the dataset.
+#[pyfunction]
+#[pyo3(signature = (path, baseline = ranking::DEFAULT_BASELINE))]
+fn rank_results<'py>(py: Python<'py>, path: &str, baseline: &str) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    let results = load_results(path)?;
+    let report = ranking::rank_summaries(&benchmark_utils::summarize_benchmark_results(&results), baseline);
+    let mut dicts = Vec::new();
+    for dataset in &report.datasets {
+        for score in &dataset.scores {
+            let dict = PyDict::new_bound(py);
+            dict.set_item("dataset_name", &dataset.dataset_name)?;
+            dict.set_item("compressor_name", &score.compressor_name)?;
+            dict.set_item("rank", score.rank)?;
+            dict.set_item("score", score.score)?;
+            dict.set_item("compression_factor", score.compression_factor)?;
+            dict.set_item("access_factor", score.access_factor)?;
+            dict.set_item("decompression_factor", score.decompression_factor)?;
+            dicts.push(dict);
+        }
+    }
+    Ok(dicts)
+}
+
+/// Returns the path if it refers to an existing file
+fn check_file(path: &str) -> PyResult<&Path> {
+    let path = Path::new(path);
+    if !path.is_file() {
+        return Err(PyFileNotFoundError::new_err(format!("'{}' is not a file", path.display())));
+    }
+    Ok(path)
+}
+
+/// Reads the results of a JSON or JSON Lines results file, in any supported schema
+fn load_results(path: &str) -> PyResult<Vec<BenchmarkResult>> {
+    benchmark_utils::load_benchmark_results(check_file(path)?).map_err(PyValueError::new_err)
+}
+
+/// Converts a benchmark result to a flat dictionary
+fn result_to_dict<'py>(py: Python<'py>, result: &BenchmarkResult) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    dict.set_item("dataset_name", &result.dataset_name)?;
+    dict.set_item("compressor_name", &result.compressor_name)?;
+    dict.set_item("compression_rate", result.compression_rate)?;
+    dict.set_item("compression_speed", result.compression_speed)?;
+    dict.set_item("decompression_speed", result.decompression_speed)?;
+    dict.set_item("average_random_access_time", result.average_random_access_time)?;
+    dict.set_item("item_access_rate", result.access_throughput.map(|t| t.item_rate))?;
+    dict.set_item("byte_access_throughput", result.access_throughput.map(|t| t.byte_throughput))?;
+    dict.set_item("space_payload", result.space_breakdown.payload)?;
+    dict.set_item("space_dictionary", result.space_breakdown.dictionary)?;
+    dict.set_item("space_item_offsets", result.space_breakdown.item_offsets)?;
+    dict.set_item("space_block_metadata", result.space_breakdown.block_metadata)?;
+    dict.set_item("space_id_map", result.space_breakdown.id_map)?;
+    dict.set_item("space_checksums", result.space_breakdown.checksums)?;
+    dict.set_item("dedup_ratio", result.dedup_ratio)?;
+    dict.set_item("entropy", result.entropy)?;
+    dict.set_item("entropy_efficiency", result.entropy_efficiency())?;
+    dict.set_item("n_queries", result.n_queries)?;
+    dict.set_item("query_policy", &result.query_policy)?;
+    dict.set_item("input_order", &result.input_order)?;
+    dict.set_item("first_run_decompression_speed", result.first_run.map(|f| f.decompression_speed))?;
+    dict.set_item("first_run_average_random_access_time", result.first_run.map(|f| f.average_random_access_time))?;
+    dict.set_item("batch_size", result.batch_access.map(|b| b.batch_size))?;
+    dict.set_item("batch_access_time", result.batch_access.map(|b| b.average_access_time))?;
+    dict.set_item("batch_speedup", result.batch_access.map(|b| b.speedup))?;
+    dict.set_item("scan_length", result.scan.map(|s| s.scan_length))?;
+    dict.set_item("scan_throughput", result.scan.map(|s| s.throughput))?;
+    dict.set_item("scan_item_time", result.scan.map(|s| s.item_time))?;
+    dict.set_item("scan_speedup", result.scan.map(|s| s.speedup))?;
+    dict.set_item("validated_items", result.validation.map(|v| v.n_items))?;
+    dict.set_item("validated_utf8_items", result.validation.map(|v| v.n_utf8_items))?;
+    dict.set_item("lookup_time", result.access_stages.map(|s| s.lookup_time))?;
+    dict.set_item("decode_time", result.access_stages.map(|s| s.decode_time))?;
+    dict.set_item("copy_time", result.access_stages.map(|s| s.copy_time))?;
+    dict.set_item("zero_copy_access_time", result.zero_copy_access.map(|z| z.average_access_time))?;
+    dict.set_item("zero_copy_overhead", result.zero_copy_access.map(|z| z.copy_overhead))?;
+    dict.set_item("plain_vec_access_time", result.plain_access.map(|p| p.vec_access_time))?;
+    dict.set_item("plain_hash_map_access_time", result.plain_access.map(|p| p.hash_map_access_time))?;
+    dict.set_item("plain_slowdown", result.plain_access.map(|p| p.slowdown))?;
+    dict.set_item("instructions", result.hardware_counters.and_then(|h| h.instructions))?;
+    dict.set_item("llc_misses", result.hardware_counters.and_then(|h| h.llc_misses))?;
+    dict.set_item("branch_misses", result.hardware_counters.and_then(|h| h.branch_misses))?;
+    dict.set_item("timed_out", &result.timed_out)?;
+    dict.set_item("shared_dictionary_n_shards", result.shared_dictionary.map(|s| s.n_shards))?;
+    dict.set_item("shared_dictionary_size", result.shared_dictionary.map(|s| s.dictionary_size))?;
+    dict.set_item("shared_dictionary_amortized_size", result.shared_dictionary.map(|s| s.amortized_dictionary_size))?;
+    dict.set_item("block_min_ratio", result.block_stats.map(|b| b.min_ratio))?;
+    dict.set_item("block_median_ratio", result.block_stats.map(|b| b.median_ratio))?;
+    dict.set_item("block_max_ratio", result.block_stats.map(|b| b.max_ratio))?;
+    dict.set_item("single_byte_token_fraction", result.literal_tokens.map(|l| l.single_byte_fraction()))?;
+    dict.set_item("literals_inlined", result.literal_tokens.map(|l| l.inlined))?;
+    dict.set_item("training_time", result.compression_split.map(|c| c.training_time))?;
+    dict.set_item("parse_time", result.compression_split.map(|c| c.parse_time))?;
+    Ok(dict)
+}
+
+/// Converts a benchmark summary to a flat dictionary (`<metric>_mean`, `<metric>_stddev`, `<metric>_ci95`)
+fn summary_to_dict<'py>(py: Python<'py>, summary: &BenchmarkSummary) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    dict.set_item("dataset_name", &summary.dataset_name)?;
+    dict.set_item("compressor_name", &summary.compressor_name)?;
+    dict.set_item("n_iterations", summary.n_iterations)?;
+    dict.set_item("n_outliers", summary.n_outliers)?;
+
+    // Metrics of phases not measured (see `Phases`) are None
+    let metrics = [
+        ("compression_rate", Some(summary.compression_rate)),
+        ("compression_speed", summary.compression_speed),
+        ("decompression_speed", summary.decompression_speed),
+        ("average_random_access_time", summary.average_random_access_time),
+    ];
+    for (name, metric) in metrics {
+        dict.set_item(format!("{}_mean", name), metric.map(|m| m.mean))?;
+        dict.set_item(format!("{}_stddev", name), metric.map(|m| m.stddev))?;
+        dict.set_item(format!("{}_ci95", name), metric.map(|m| m.ci95))?;
+    }
+
+    dict.set_item("space_payload", summary.space_breakdown.payload)?;
+    dict.set_item("space_dictionary", summary.space_breakdown.dictionary)?;
+    dict.set_item("space_item_offsets", summary.space_breakdown.item_offsets)?;
+    dict.set_item("space_block_metadata", summary.space_breakdown.block_metadata)?;
+    dict.set_item("space_id_map", summary.space_breakdown.id_map)?;
+    dict.set_item("space_checksums", summary.space_breakdown.checksums)?;
+    dict.set_item("dedup_ratio", summary.dedup_ratio)?;
+    dict.set_item("entropy", summary.entropy)?;
+    dict.set_item("entropy_efficiency", summary.entropy_efficiency())?;
+    if let Some(first_run) = &summary.first_run {
+        let first_run_metrics = [
+            ("first_run_decompression_speed", &first_run.decompression_speed),
+            ("first_run_average_random_access_time", &first_run.average_random_access_time),
+        ];
+        for (name, metric) in first_run_metrics {
+            dict.set_item(format!("{}_mean", name), metric.mean)?;
+            dict.set_item(format!("{}_stddev", name), metric.stddev)?;
+            dict.set_item(format!("{}_ci95", name), metric.ci95)?;
+        }
+    }
+    if let Some(access_throughput) = &summary.access_throughput {
+        let throughput_metrics = [
+            ("item_access_rate", &access_throughput.item_rate),
+            ("byte_access_throughput", &access_throughput.byte_throughput),
+        ];
+        for (name, metric) in throughput_metrics {
+            dict.set_item(format!("{}_mean", name), metric.mean)?;
+            dict.set_item(format!("{}_stddev", name), metric.stddev)?;
+            dict.set_item(format!("{}_ci95", name), metric.ci95)?;
+        }
+    }
+    Ok(dict)
+}
+
+/// Python module definition
+#[pymodule]
+fn compression_benchmark_rs(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    // Surface warnings of the benchmark protocol on stderr
+    logging::init(Level::WARN, LogFormat::Text);
+    m.add_function(wrap_pyfunction!(compressors, m)?)?;
+    m.add_function(wrap_pyfunction!(load_dataset, m)?)?;
+    m.add_function(wrap_pyfunction!(benchmark, m)?)?;
+    m.add_function(wrap_pyfunction!(read_results, m)?)?;
+    m.add_function(wrap_pyfunction!(summarize_results, m)?)?;
+    m.add_function(wrap_pyfunction!(rank_results, m)?)?;
+    Ok(())
+}
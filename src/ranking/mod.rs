@@ -0,0 +1,189 @@
+//! Normalized scoring and ranking of compressors
+//!
+//! Compressors trade compression rate against random access latency and
+//! decompression speed, so that comparing them requires cross-referencing many
+//! table rows. This module scores each compressor on each dataset relative to a
+//! baseline (by default the uncompressed `Raw`): every metric is turned into a
+//! speedup-like factor (higher is better, 1 for the baseline), and the score is the
+//! geometric mean of the factors. Compressors are ranked per dataset, and an overall
+//! leaderboard aggregates their scores across datasets with a geometric mean.
+
+use crate::benchmark_utils::{write_file_atomically, BenchmarkSummary};
+use prettytable::{row, Table};
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tracing::warn;
+
+/// Compressor against which metrics are normalized by default
+pub const DEFAULT_BASELINE: &str = "Raw";
+
+/// Normalized score of a compressor on a dataset
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DatasetScore {
+    pub compressor_name: String,
+    pub rank: usize,                        // 1 for the highest score on the dataset
+    pub score: f64,                         // Geometric mean of the normalized metrics
+    pub compression_factor: f64,            // Compression rate over the baseline's
+    pub access_factor: f64,                 // Baseline random access time over the compressor's
+    pub decompression_factor: f64,          // Decompression speed over the baseline's
+}
+
+/// Ranking of the compressors evaluated on a dataset
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DatasetRanking {
+    pub dataset_name: String,
+    pub scores: Vec<DatasetScore>,          // Sorted by rank
+}
+
+/// Overall standing of a compressor across datasets
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LeaderboardEntry {
+    pub compressor_name: String,
+    pub n_datasets: usize,                  // Datasets on which the compressor was ranked
+    pub score: f64,                         // Geometric mean of the dataset scores
+    pub average_rank: f64,
+    pub wins: usize,                        // Datasets on which the compressor ranked first
+}
+
+/// Per-dataset rankings and overall leaderboard
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RankingReport {
+    pub baseline: String,
+    pub datasets: Vec<DatasetRanking>,
+    pub leaderboard: Vec<LeaderboardEntry>, // Sorted by decreasing score
+}
+
+/// Ranks compressors per dataset and across datasets
+///
+/// Datasets on which the baseline was not evaluated cannot be normalized and are
+/// skipped with a warning, as are summaries missing decompression or random access
+/// measurements (runs skipping these phases, see `Phases`).
+///
+/// # Arguments
+/// - `summaries`: Aggregated results (see `summarize_benchmark_results`)
+/// - `baseline`: Name of the compressor used for normalization, as reported in results
+///
+/// # Returns
+/// Rankings of every normalizable dataset, and the leaderboard of their compressors
+pub fn rank_summaries(summaries: &[BenchmarkSummary], baseline: &str) -> RankingReport {
+    let mut by_dataset: BTreeMap<&str, Vec<&BenchmarkSummary>> = BTreeMap::new();
+    for summary in summaries {
+        if summary.decompression_speed.is_none() || summary.average_random_access_time.is_none() {
+            warn!(compressor = summary.compressor_name.as_str(), dataset = summary.dataset_name.as_str(), "Decompression or random access not measured, skipping in ranking");
+            continue;
+        }
+        by_dataset.entry(&summary.dataset_name).or_default().push(summary);
+    }
+
+    let mut datasets = Vec::with_capacity(by_dataset.len());
+    for (dataset_name, group) in by_dataset {
+        let Some(reference) = group.iter().find(|s| s.compressor_name == baseline) else {
+            warn!(dataset = dataset_name, baseline, "Baseline not evaluated, skipping dataset in ranking");
+            continue;
+        };
+
+        let mut scores: Vec<DatasetScore> = group
+            .iter()
+            .map(|summary| {
+                let compression_factor = summary.compression_rate.mean / reference.compression_rate.mean;
+                // Summaries without these measurements are skipped above
+                let access_time = |summary: &BenchmarkSummary| summary.average_random_access_time.unwrap().mean.max(1.0);
+                let decompression_speed = |summary: &BenchmarkSummary| summary.decompression_speed.unwrap().mean;
+                let access_factor = access_time(reference) / access_time(summary);
+                let decompression_factor = decompression_speed(summary) / decompression_speed(reference);
+                DatasetScore {
+                    compressor_name: summary.compressor_name.clone(),
+                    rank: 0,
+                    score: (compression_factor * access_factor * decompression_factor).cbrt(),
+                    compression_factor,
+                    access_factor,
+                    decompression_factor,
+                }
+            })
+            .collect();
+        scores.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.compressor_name.cmp(&b.compressor_name)));
+        for (i, score) in scores.iter_mut().enumerate() {
+            score.rank = i + 1;
+        }
+
+        datasets.push(DatasetRanking { dataset_name: dataset_name.to_string(), scores });
+    }
+
+    // Aggregate the dataset scores of each compressor
+    let mut by_compressor: BTreeMap<&str, Vec<&DatasetScore>> = BTreeMap::new();
+    for ranking in &datasets {
+        for score in &ranking.scores {
+            by_compressor.entry(&score.compressor_name).or_default().push(score);
+        }
+    }
+    let mut leaderboard: Vec<LeaderboardEntry> = by_compressor
+        .into_iter()
+        .map(|(compressor_name, scores)| {
+            let n_datasets = scores.len();
+            LeaderboardEntry {
+                compressor_name: compressor_name.to_string(),
+                n_datasets,
+                score: (scores.iter().map(|s| s.score.ln()).sum::<f64>() / n_datasets as f64).exp(),
+                average_rank: scores.iter().map(|s| s.rank as f64).sum::<f64>() / n_datasets as f64,
+                wins: scores.iter().filter(|s| s.rank == 1).count(),
+            }
+        })
+        .collect();
+    leaderboard.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.compressor_name.cmp(&b.compressor_name)));
+
+    RankingReport { baseline: baseline.to_string(), datasets, leaderboard }
+}
+
+/// Writes a ranking report to a JSON file
+pub fn write_ranking_report(report: &RankingReport, output_path: &Path) {
+    let json = serde_json::to_string_pretty(report).expect("Failed to serialize ranking");
+    write_file_atomically(output_path, json.as_bytes()).expect("Failed to write ranking to file");
+}
+
+/// Prints the ranking of each dataset, followed by the overall leaderboard
+///
+/// # Arguments
+/// - `report`: Rankings to display
+pub fn print_ranking_report(report: &RankingReport) {
+    for ranking in &report.datasets {
+        let mut table = Table::new();
+        table.add_row(row![
+            "Rank",
+            "Compressor",
+            "Score",
+            "Comp. Rate (x)",
+            "Random Access (x)",
+            "Decomp. Speed (x)"
+        ]);
+        for score in &ranking.scores {
+            table.add_row(row![
+                format!("{}", score.rank),
+                &score.compressor_name,
+                format!("{:.3}", score.score),
+                format!("{:.3}", score.compression_factor),
+                format!("{:.3}", score.access_factor),
+                format!("{:.3}", score.decompression_factor),
+            ]);
+        }
+
+        println!("\nRanking for Dataset: {} (relative to {})", ranking.dataset_name, report.baseline);
+        table.printstd();
+    }
+
+    let mut table = Table::new();
+    table.add_row(row!["Rank", "Compressor", "Score", "Avg. Rank", "Wins", "Datasets"]);
+    for (i, entry) in report.leaderboard.iter().enumerate() {
+        table.add_row(row![
+            format!("{}", i + 1),
+            &entry.compressor_name,
+            format!("{:.3}", entry.score),
+            format!("{:.2}", entry.average_rank),
+            format!("{}", entry.wins),
+            format!("{}", entry.n_datasets),
+        ]);
+    }
+
+    println!("\nOverall leaderboard (relative to {}):", report.baseline);
+    table.printstd();
+}
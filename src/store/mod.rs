@@ -0,0 +1,329 @@
+//! Compressed string store for embedding in other applications
+//!
+//! The `Compressor` trait exposes the protocol followed by the benchmarks: buffers
+//! sized by the caller with padding, string boundaries as end positions, compressors
+//! created by identifier through the registry. `CompressedStringStore` hides these
+//! details behind a collection-like interface: it is built from an iterator of strings,
+//! returns strings into growable buffers, and can be saved to and loaded from a file.
+//! See `examples/kv_service.rs` for a store embedded in a key-value service.
+//!
+//! In sorted mode, the store is an order-preserving string dictionary (e.g., for columns
+//! of a database encoded by string index): strings are sorted and deduplicated, so that
+//! the index of a string is its rank, and `locate` and `range` map strings and string
+//! bounds to indices. Front coding (`StoreConfig::sorted`) answers them by binary search
+//! on its bucket headers; other algorithms by binary search on their strings.
+//!
+//! `offset_to_item` maps byte offsets of the concatenated strings to strings, for callers
+//! addressing the data by byte ranges. Algorithms keeping string boundaries answer it
+//! directly; for the others (e.g., dictionary compressors, which know string lengths
+//! only), the store keeps a compact index of the boundaries (see `LengthIndex`).
+//!
+//! `UpdatableStore` (see `updatable`) extends the store with appends, deletions and
+//! replacements, patching the collection with new segments instead of compressing it again.
+//!
+//! Compressors define no serialized form (OnPair and OnPair16 are implemented by an
+//! external library), so that a saved store holds its algorithm and its strings, and
+//! loading it compresses them again. `benchmark_individual --from-store` measures the
+//! compressor of a loaded store, without compressing the dataset itself.
+
+pub mod updatable;
+
+use crate::compressor::length_index::LengthIndex;
+use crate::compressor::registry::{self, AnyCompressor};
+use crate::compressor::BUFFER_PADDING;
+use serde::{Serialize, Deserialize};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::ops::Range;
+use std::path::Path;
+
+/// Algorithm used by default, the fastest OnPair variant if compiled in
+pub const DEFAULT_ALGORITHM: &str = if cfg!(feature = "onpair") { "onpair16" } else { "bpe" };
+/// Version of the file format written by `save`
+const FORMAT_VERSION: u32 = 2;
+
+/// Configuration of a compressed string store
+#[derive(Debug, Clone)]
+pub struct StoreConfig {
+    /// Compression algorithm identifier (see `registry::available_compressors`)
+    pub algorithm: String,
+    /// Whether strings are sorted and deduplicated, enabling `locate` and `range`
+    pub sorted: bool,
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        StoreConfig { algorithm: DEFAULT_ALGORITHM.to_string(), sorted: false }
+    }
+}
+
+impl StoreConfig {
+    /// Returns the configuration of an order-preserving dictionary with front coding
+    pub fn sorted() -> Self {
+        StoreConfig { algorithm: "front_coding".to_string(), sorted: true }
+    }
+}
+
+/// Contents of a saved store
+#[derive(Serialize, Deserialize)]
+struct SavedStore {
+    format_version: u32,
+    algorithm: String,
+    sorted: bool,
+    data: Vec<u8>,              // Concatenated strings
+    end_positions: Vec<usize>,  // String boundaries, starting with 0
+}
+
+/// Immutable collection of compressed strings with random access by index
+pub struct CompressedStringStore {
+    compressor: AnyCompressor,
+    algorithm: String,      // Algorithm identifier, as configured
+    sorted: bool,           // Whether strings are sorted and distinct
+    data_size: usize,       // Total size of the strings in bytes
+    n_items: usize,
+    offset_index: Option<LengthIndex>,  // String boundaries, for algorithms not keeping them
+    search_buffer: Vec<u8>, // Strings compared by binary searches, reused across searches
+}
+
+impl CompressedStringStore {
+    /// Compresses a collection of strings
+    ///
+    /// # Arguments
+    /// - `items`: Strings, in index order (in sorted mode, in any order)
+    /// - `config`: Configuration of the store
+    ///
+    /// # Returns
+    /// The store, or an error if the algorithm is not available or does not support the strings
+    pub fn build<'a, I: IntoIterator<Item = &'a [u8]>>(items: I, config: &StoreConfig) -> Result<Self, String> {
+        let mut items: Vec<&[u8]> = items.into_iter().collect();
+        if config.sorted {
+            items.sort_unstable();
+            items.dedup();
+        }
+
+        let mut data = Vec::new();
+        let mut end_positions = vec![0];
+        for item in items {
+            data.extend_from_slice(item);
+            end_positions.push(data.len());
+        }
+        Self::from_parts(&config.algorithm, config.sorted, &data, &end_positions)
+    }
+
+    /// Compresses concatenated strings with the given algorithm
+    fn from_parts(algorithm: &str, sorted: bool, data: &[u8], end_positions: &[usize]) -> Result<Self, String> {
+        let n_items = end_positions.len() - 1;
+        let mut compressor = AnyCompressor::create(algorithm, data.len(), n_items)
+            .ok_or_else(|| registry::unavailable_message(algorithm))?;
+        compressor.capacity().check(end_positions)?;
+        compressor.compress(data, end_positions);
+        let offset_index = (!compressor.has_offset_index()).then(|| LengthIndex::new(end_positions));
+
+        Ok(CompressedStringStore { compressor, algorithm: algorithm.to_string(), sorted, data_size: data.len(), n_items, offset_index, search_buffer: Vec::new() })
+    }
+
+    /// Retrieves a string by index
+    ///
+    /// # Arguments
+    /// - `index`: Zero-based index of the string (below `len()`)
+    /// - `buffer`: Receives the string, replacing its contents; reusing the buffer across
+    ///   calls avoids allocations
+    ///
+    /// # Returns
+    /// Length of the string in bytes
+    pub fn get(&mut self, index: usize, buffer: &mut Vec<u8>) -> usize {
+        assert!(index < self.n_items, "Index {} out of bounds for a store of {} strings", index, self.n_items);
+        buffer.resize(self.compressor.len_of_item(index) + BUFFER_PADDING, 0);
+        let size = self.compressor.get_item_at(index, buffer);
+        buffer.truncate(size);
+        size
+    }
+
+    /// Returns the index of a string (sorted mode only)
+    ///
+    /// # Returns
+    /// The index of the string, or `None` if it is not stored
+    pub fn locate(&mut self, string: &[u8]) -> Option<usize> {
+        let (index, found) = self.lower_bound(string);
+        found.then_some(index)
+    }
+
+    /// Returns the indices of the strings `s` such that `lower <= s < upper` (sorted mode only)
+    pub fn range(&mut self, lower: &[u8], upper: &[u8]) -> Range<usize> {
+        let start = self.lower_bound(lower).0;
+        let end = self.lower_bound(upper).0.max(start);
+        start..end
+    }
+
+    /// Returns the number of strings smaller than `string`, and whether the next one equals it
+    fn lower_bound(&mut self, string: &[u8]) -> (usize, bool) {
+        assert!(self.sorted, "Order-preserving search requires a sorted store");
+        if let AnyCompressor::FrontCoding(compressor) = &self.compressor {
+            return compressor.lower_bound(string);
+        }
+
+        let mut buffer = std::mem::take(&mut self.search_buffer);
+        let (mut low, mut high) = (0, self.n_items);
+        while low < high {
+            let middle = (low + high) / 2;
+            self.get(middle, &mut buffer);
+            if buffer.as_slice() < string {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        let found = low < self.n_items && {
+            self.get(low, &mut buffer);
+            buffer == string
+        };
+        self.search_buffer = buffer;
+        (low, found)
+    }
+
+    /// Finds the string holding a byte offset of the concatenated strings
+    ///
+    /// # Returns
+    /// Index of the string and offset within it (empty strings hold no offset), or None
+    /// if the offset is past the end of the data
+    pub fn offset_to_item(&self, offset: usize) -> Option<(usize, usize)> {
+        match &self.offset_index {
+            Some(index) => index.offset_to_item(offset),
+            None => self.compressor.offset_to_item(offset),
+        }
+    }
+
+    /// Returns whether strings are sorted and deduplicated, enabling `locate` and `range`
+    pub fn is_sorted(&self) -> bool {
+        self.sorted
+    }
+
+    /// Returns the length of a string in bytes, without decompressing it
+    pub fn item_len(&self, index: usize) -> usize {
+        self.compressor.len_of_item(index)
+    }
+
+    /// Returns the number of strings
+    pub fn len(&self) -> usize {
+        self.n_items
+    }
+
+    /// Returns whether the store holds no string
+    pub fn is_empty(&self) -> bool {
+        self.n_items == 0
+    }
+
+    /// Returns the total size of the strings in bytes, uncompressed
+    pub fn data_size(&self) -> usize {
+        self.data_size
+    }
+
+    /// Returns the memory used by the compressed representation and the offset index in bytes
+    /// (see `Compressor::space_used_bytes`)
+    pub fn memory_usage(&self) -> usize {
+        self.compressor.space_used_bytes() + self.offset_index.as_ref().map_or(0, |index| index.size_bytes())
+    }
+
+    /// Returns the algorithm identifier
+    pub fn algorithm(&self) -> &str {
+        &self.algorithm
+    }
+
+    /// Returns the human-readable name of the algorithm
+    pub fn name(&self) -> &str {
+        self.compressor.name()
+    }
+
+    /// Decompresses all strings
+    ///
+    /// # Returns
+    /// The concatenated strings, and their boundaries starting with 0
+    pub fn strings(&self) -> (Vec<u8>, Vec<usize>) {
+        let mut data = vec![0; self.data_size + BUFFER_PADDING];
+        let size = self.compressor.decompress(&mut data);
+        data.truncate(size);
+
+        let mut end_positions = Vec::with_capacity(self.n_items + 1);
+        end_positions.push(0);
+        for index in 0..self.n_items {
+            end_positions.push(end_positions[index] + self.compressor.len_of_item(index));
+        }
+        (data, end_positions)
+    }
+
+    /// Returns the compressor holding the strings, dropping the offset index
+    pub fn into_compressor(self) -> AnyCompressor {
+        self.compressor
+    }
+
+    /// Saves the store to a file
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let (data, end_positions) = self.strings();
+        let saved = SavedStore { format_version: FORMAT_VERSION, algorithm: self.algorithm.clone(), sorted: self.sorted, data, end_positions };
+        let writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(writer, &saved).map_err(io::Error::other)
+    }
+
+    /// Loads a store saved by `save`, compressing its strings again
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let saved: SavedStore = bincode::deserialize_from(reader).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if saved.format_version != FORMAT_VERSION {
+            let message = format!("Unsupported store format version {}", saved.format_version);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+
+        let is_valid = saved.end_positions.first() == Some(&0)
+            && saved.end_positions.last() == Some(&saved.data.len())
+            && saved.end_positions.windows(2).all(|w| w[0] <= w[1]);
+        if !is_valid {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Invalid string boundaries"));
+        }
+        Self::from_parts(&saved.algorithm, saved.sorted, &saved.data, &saved.end_positions)
+            .map_err(|message| io::Error::new(io::ErrorKind::InvalidInput, message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Strings of varied lengths, with runs of empty strings holding no offset
+    fn items() -> Vec<Vec<u8>> {
+        (0..3000usize)
+            .map(|index| match index % 7 {
+                0 | 1 => Vec::new(),
+                _ => format!("item-{}-{}", index % 97, "x".repeat(index % 40)).into_bytes(),
+            })
+            .collect()
+    }
+
+    /// Finds the string holding an offset by a linear scan
+    fn linear_offset_to_item(items: &[Vec<u8>], offset: usize) -> Option<(usize, usize)> {
+        let mut start = 0;
+        for (index, item) in items.iter().enumerate() {
+            if offset < start + item.len() {
+                return Some((index, offset - start));
+            }
+            start += item.len();
+        }
+        None
+    }
+
+    #[test]
+    fn offset_to_item_matches_a_linear_scan_for_every_algorithm() {
+        let items = items();
+        let data_size: usize = items.iter().map(|item| item.len()).sum();
+        for algorithm in registry::available_compressors() {
+            let config = StoreConfig { algorithm: algorithm.to_string(), sorted: false };
+            let store = CompressedStringStore::build(items.iter().map(|item| item.as_slice()), &config).unwrap();
+            for offset in 0..data_size + 3 {
+                assert_eq!(
+                    store.offset_to_item(offset),
+                    linear_offset_to_item(&items, offset),
+                    "{}: offset {}", algorithm, offset
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,192 @@
+//! Compressed string store supporting appends, deletions and replacements
+//!
+//! Compressed representations are immutable: changing a string means compressing the
+//! whole collection again. `UpdatableStore` instead patches the collection: new and
+//! replacing strings are compressed into a new segment, and the copies they supersede
+//! are tombstoned, staying in their segment until it holds no live string anymore.
+//! Strings keep their index across updates, resolved to a segment and a position within
+//! it. Patching is cheaper than compressing again, at the cost of the space of dead
+//! copies and of segments compressed with less context; `compact` compresses the live
+//! strings again into a single segment.
+
+use super::{CompressedStringStore, StoreConfig};
+use std::ops::Range;
+
+/// Segment of the locations of deleted strings
+const DELETED: u32 = u32::MAX;
+
+/// Segment of compressed strings, released once all its strings are dead
+struct Segment {
+    store: Option<CompressedStringStore>,
+    live_items: usize,  // Strings of the segment not superseded or deleted
+}
+
+/// Collection of compressed strings with random access by index, updated in place
+pub struct UpdatableStore {
+    config: StoreConfig,
+    segments: Vec<Segment>,
+    locations: Vec<(u32, u32)>,   // Segment and position within it of each string
+    dead_items: usize,          // Superseded or deleted copies held by live segments
+}
+
+impl UpdatableStore {
+    /// Compresses a collection of strings into the first segment
+    ///
+    /// # Arguments
+    /// - `items`: Strings, in index order
+    /// - `config`: Configuration of the segments, which must not be sorted
+    ///
+    /// # Returns
+    /// The store, or an error if the configuration is sorted or its algorithm cannot
+    /// compress the strings
+    pub fn build<'a, I: IntoIterator<Item = &'a [u8]>>(items: I, config: &StoreConfig) -> Result<Self, String> {
+        if config.sorted {
+            return Err("Updatable stores keep strings at their index, sorted mode is not supported".to_string());
+        }
+        let mut store = UpdatableStore { config: config.clone(), segments: Vec::new(), locations: Vec::new(), dead_items: 0 };
+        store.append(items)?;
+        Ok(store)
+    }
+
+    /// Appends strings, compressed into a new segment
+    ///
+    /// # Returns
+    /// Indices of the appended strings
+    pub fn append<'a, I: IntoIterator<Item = &'a [u8]>>(&mut self, items: I) -> Result<Range<usize>, String> {
+        let items: Vec<&[u8]> = items.into_iter().collect();
+        let start = self.locations.len();
+        if let Some(segment) = self.push_segment(&items)? {
+            self.locations.extend((0..items.len() as u32).map(|position| (segment, position)));
+        }
+        Ok(start..self.locations.len())
+    }
+
+    /// Replaces strings with new values, compressed into a new segment
+    ///
+    /// The replaced copies are tombstoned; a string replaced more than once in the
+    /// same call takes its last value.
+    ///
+    /// # Arguments
+    /// - `updates`: Index (below `len()`) and new value of each replaced string
+    pub fn replace<'a, I: IntoIterator<Item = (usize, &'a [u8])>>(&mut self, updates: I) -> Result<(), String> {
+        let (indices, values): (Vec<usize>, Vec<&[u8]>) = updates.into_iter().unzip();
+        for &index in indices.iter() {
+            assert!(index < self.locations.len(), "Index {} out of bounds for a store of {} strings", index, self.locations.len());
+        }
+        let Some(segment) = self.push_segment(&values)? else {
+            return Ok(());
+        };
+        for (position, index) in indices.into_iter().enumerate() {
+            self.tombstone(index);
+            self.locations[index] = (segment, position as u32);
+        }
+        Ok(())
+    }
+
+    /// Deletes a string, keeping the indices of the others
+    pub fn delete(&mut self, index: usize) {
+        assert!(index < self.locations.len(), "Index {} out of bounds for a store of {} strings", index, self.locations.len());
+        self.tombstone(index);
+        self.locations[index] = (DELETED, 0);
+    }
+
+    /// Compresses the live strings again into a single segment, dropping dead copies
+    pub fn compact(&mut self) -> Result<(), String> {
+        let mut buffer = Vec::new();
+        let mut data = Vec::new();
+        let mut end_positions = vec![0];
+        let mut live = Vec::new();
+        for index in 0..self.locations.len() {
+            if !self.is_deleted(index) {
+                self.get(index, &mut buffer);
+                data.extend_from_slice(&buffer);
+                end_positions.push(data.len());
+                live.push(index);
+            }
+        }
+
+        // The current segments are kept until the live strings are compressed successfully
+        let store = if live.is_empty() {
+            None
+        } else {
+            Some(CompressedStringStore::build(end_positions.windows(2).map(|w| &data[w[0]..w[1]]), &self.config)?)
+        };
+        self.segments = store.into_iter().map(|store| Segment { live_items: store.len(), store: Some(store) }).collect();
+        self.dead_items = 0;
+        for (position, index) in live.into_iter().enumerate() {
+            self.locations[index] = (0, position as u32);
+        }
+        Ok(())
+    }
+
+    /// Compresses strings into a new segment, initially without referencing strings
+    ///
+    /// # Returns
+    /// The segment, or None if there are no strings
+    fn push_segment(&mut self, items: &[&[u8]]) -> Result<Option<u32>, String> {
+        if items.is_empty() {
+            return Ok(None);
+        }
+        let store = CompressedStringStore::build(items.iter().copied(), &self.config)?;
+        let segment = self.segments.len() as u32;
+        assert!(segment < DELETED, "Too many segments");
+        self.segments.push(Segment { live_items: store.len(), store: Some(store) });
+        Ok(Some(segment))
+    }
+
+    /// Marks the current copy of a string as dead, releasing its segment if it was the last live one
+    fn tombstone(&mut self, index: usize) {
+        let (segment, _) = self.locations[index];
+        if segment == DELETED {
+            return;
+        }
+        let segment = &mut self.segments[segment as usize];
+        segment.live_items -= 1;
+        if segment.live_items == 0 {
+            self.dead_items -= segment.store.as_ref().unwrap().len() - 1;
+            segment.store = None;
+        } else {
+            self.dead_items += 1;
+        }
+    }
+
+    /// Retrieves a string by index (see `CompressedStringStore::get`)
+    ///
+    /// Panics if the string was deleted.
+    pub fn get(&mut self, index: usize, buffer: &mut Vec<u8>) -> usize {
+        let (segment, position) = self.locations[index];
+        assert!(segment != DELETED, "String {} was deleted", index);
+        self.segments[segment as usize].store.as_mut().unwrap().get(position as usize, buffer)
+    }
+
+    /// Returns whether a string was deleted
+    pub fn is_deleted(&self, index: usize) -> bool {
+        self.locations[index].0 == DELETED
+    }
+
+    /// Returns the number of strings, including deleted ones
+    pub fn len(&self) -> usize {
+        self.locations.len()
+    }
+
+    /// Returns whether the store holds no string
+    pub fn is_empty(&self) -> bool {
+        self.locations.is_empty()
+    }
+
+    /// Returns the number of segments holding live strings
+    pub fn n_segments(&self) -> usize {
+        self.segments.iter().filter(|segment| segment.store.is_some()).count()
+    }
+
+    /// Returns the number of dead copies still stored in live segments
+    pub fn dead_items(&self) -> usize {
+        self.dead_items
+    }
+
+    /// Returns the memory used by the live segments and the string locations in bytes
+    pub fn memory_usage(&self) -> usize {
+        let segments: usize = self.segments.iter().filter_map(|segment| segment.store.as_ref()).map(|store| store.memory_usage()).sum();
+        segments + std::mem::size_of_val(self.locations.as_slice())
+    }
+}
@@ -0,0 +1,146 @@
+//! Pathological string classes
+//!
+//! Generators of the datasets written by `generate_stress`, one per class of strings
+//! on which compressors tend to degrade:
+//! - long strings: a few strings of tens of MiB of text, far larger than a block, on
+//!   which training scans huge strings
+//! - runs: strings of a single repeated byte, of every power-of-two length, on which
+//!   pair merging produces ever longer tokens
+//! - unique strings: a de Bruijn sequence of order 2 over all byte values, cut into
+//!   strings: no substring of two or more bytes occurs twice in the whole dataset, so
+//!   that dictionary-based compressors find nothing to merge
+//!
+//! Every generator is deterministic for given arguments and random generator state.
+
+use rand::rngs::StdRng;
+use rand::Rng;
+
+/// Number of long strings
+pub const N_LONG_ITEMS: usize = 2;
+/// Number of distinct words of the text of long strings
+const VOCABULARY_SIZE: usize = 5000;
+/// Length of the longest run of a repeated byte written by `generate_stress`
+pub const MAX_RUN_LENGTH: usize = 1 << 20;
+/// Bytes repeated by the runs
+pub const RUN_BYTES: [u8; 4] = [b'a', b'0', b' ', 0];
+/// Length of the strings cut from the de Bruijn sequence
+pub const UNIQUE_ITEM_LENGTH: usize = 64;
+
+/// Generates `N_LONG_ITEMS` strings of `item_size` bytes of random words
+pub fn long_items(rng: &mut StdRng, item_size: usize) -> (Vec<u8>, Vec<usize>) {
+    let vocabulary: Vec<Vec<u8>> = (0..VOCABULARY_SIZE)
+        .map(|_| (0..rng.gen_range(2..10)).map(|_| rng.gen_range(b'a'..=b'z')).collect())
+        .collect();
+
+    let mut data: Vec<u8> = Vec::with_capacity(N_LONG_ITEMS * item_size);
+    let mut end_positions = vec![0];
+    for _ in 0..N_LONG_ITEMS {
+        let item_end = data.len() + item_size;
+        while data.len() < item_end {
+            data.extend_from_slice(&vocabulary[rng.gen_range(0..VOCABULARY_SIZE)]);
+            data.push(b' ');
+        }
+        data.truncate(item_end);
+        end_positions.push(data.len());
+    }
+    (data, end_positions)
+}
+
+/// Generates runs of each byte of `RUN_BYTES`, of every power-of-two length up to `max_run_length`
+pub fn run_items(max_run_length: usize) -> (Vec<u8>, Vec<usize>) {
+    let mut data: Vec<u8> = Vec::new();
+    let mut end_positions = vec![0];
+    let mut length = 1;
+    while length <= max_run_length {
+        for &byte in RUN_BYTES.iter() {
+            data.resize(data.len() + length, byte);
+            end_positions.push(data.len());
+        }
+        length *= 2;
+    }
+    (data, end_positions)
+}
+
+/// Cuts the de Bruijn sequence of order 2 over all byte values into strings
+pub fn unique_items() -> (Vec<u8>, Vec<usize>) {
+    let data = de_bruijn(256, 2);
+    let end_positions = (0..=data.len()).step_by(UNIQUE_ITEM_LENGTH).collect();
+    (data, end_positions)
+}
+
+/// Returns the de Bruijn sequence of order `n` over `k` symbols
+///
+/// Every sequence of `n` symbols occurs exactly once in the cyclic sequence, of length `k^n`
+/// (Fredricksen, Kessler and Maiorana's algorithm, concatenating Lyndon words in order).
+fn de_bruijn(k: usize, n: usize) -> Vec<u8> {
+    fn lyndon_words(t: usize, p: usize, k: usize, n: usize, a: &mut [usize], sequence: &mut Vec<u8>) {
+        if t > n {
+            if n.is_multiple_of(p) {
+                sequence.extend(a[1..=p].iter().map(|&symbol| symbol as u8));
+            }
+            return;
+        }
+        a[t] = a[t - p];
+        lyndon_words(t + 1, p, k, n, a, sequence);
+        for symbol in a[t - p] + 1..k {
+            a[t] = symbol;
+            lyndon_words(t + 1, t, k, n, a, sequence);
+        }
+    }
+
+    let mut a = vec![0; n + 1];
+    let mut sequence = Vec::with_capacity(k.pow(n as u32));
+    lyndon_words(1, 1, k, n, &mut a, &mut sequence);
+    sequence
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compressor::registry::{self, AnyCompressor};
+    use crate::compressor::MAX_TOKEN_LENGTH;
+    use crate::differential;
+    use rand::SeedableRng;
+    use rustc_hash::FxHashSet;
+
+    /// Checks that a compressor returns every string, with tokens within the length bound
+    fn check_class(name: &str, data: &[u8], end_positions: &[usize]) {
+        let mut compressor = AnyCompressor::create(name, data.len(), end_positions.len() - 1).unwrap();
+        if compressor.capacity().check(end_positions).is_err() {
+            return;
+        }
+        compressor.compress(data, end_positions);
+        let check = differential::check(&mut compressor, data, end_positions);
+        assert!(check.passed(), "{} returned different strings", name);
+        if let Some(stats) = compressor.dictionary_stats() {
+            assert!(stats.max_token_length <= MAX_TOKEN_LENGTH, "{} merged a token of {} bytes", name, stats.max_token_length);
+        }
+    }
+
+    #[test]
+    fn unique_items_repeat_no_pair_of_bytes() {
+        let (data, end_positions) = unique_items();
+        assert_eq!(data.len(), 1 << 16);
+        assert_eq!(end_positions.len() - 1, data.len() / UNIQUE_ITEM_LENGTH);
+        let pairs: FxHashSet<&[u8]> = data.windows(2).collect();
+        assert_eq!(pairs.len(), data.len() - 1);
+    }
+
+    #[test]
+    fn every_compressor_handles_runs_and_unique_strings() {
+        let classes = [run_items(1 << 16), unique_items()];
+        for name in registry::available_compressors() {
+            for (data, end_positions) in classes.iter() {
+                check_class(name, data, end_positions);
+            }
+        }
+    }
+
+    #[test]
+    fn block_compressors_handle_strings_of_tens_of_mib() {
+        let (data, end_positions) = long_items(&mut StdRng::seed_from_u64(1112), 16 << 20);
+        for name in ["raw", "lz4_blocks"].into_iter().filter(|name| registry::lookup(name).is_some_and(|entry| entry.available)) {
+            check_class(name, &data, &end_positions);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! Objective functions for compressor parameter tuning
+//!
+//! This module defines the objectives used to rank the configurations evaluated
+//! during a parameter sweep, including constrained objectives such as minimizing
+//! random access latency subject to a minimum compression rate.
+
+use crate::benchmark_utils::BenchmarkResult;
+use serde::{Serialize, Deserialize};
+
+/// Outcome of the evaluation of a single parameter configuration
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Trial {
+    pub parameters: String,         // Human-readable parameter assignment (e.g., "threshold=8")
+    pub result: BenchmarkResult,    // Metrics measured with these parameters
+}
+
+/// Optimization goal for selecting the best configuration
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Objective {
+    MaxCompressionRate,                         // Maximize compression rate
+    MinAccessTime,                              // Minimize average random access time
+    MaxCompressionSpeed,                        // Maximize compression throughput
+    MinAccessTimeWithRate(f64),                 // Minimize access time subject to rate >= X
+    MaxCompressionRateWithAccessTime(u128),     // Maximize rate subject to access time <= N ns
+}
+
+impl Objective {
+    /// Parses an objective from its command-line representation
+    /// 
+    /// Accepted forms: `max_ratio`, `min_latency`, `max_speed`,
+    /// `min_latency:ratio>=X` and `max_ratio:latency<=N`.
+    /// 
+    /// # Arguments
+    /// - `s`: Objective specification
+    /// 
+    /// # Returns
+    /// The parsed objective, or `None` if the specification is invalid
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "max_ratio" => return Some(Objective::MaxCompressionRate),
+            "min_latency" => return Some(Objective::MinAccessTime),
+            "max_speed" => return Some(Objective::MaxCompressionSpeed),
+            _ => {}
+        }
+
+        if let Some(rate) = s.strip_prefix("min_latency:ratio>=") {
+            return rate.parse().ok().map(Objective::MinAccessTimeWithRate);
+        }
+        if let Some(time) = s.strip_prefix("max_ratio:latency<=") {
+            return time.parse().ok().map(Objective::MaxCompressionRateWithAccessTime);
+        }
+        None
+    }
+
+    /// Checks whether a result satisfies the objective constraints
+    pub fn is_feasible(&self, result: &BenchmarkResult) -> bool {
+        match *self {
+            Objective::MinAccessTimeWithRate(rate) => result.compression_rate >= rate,
+            Objective::MaxCompressionRateWithAccessTime(time) => result.average_random_access_time.is_some_and(|access_time| access_time <= time),
+            _ => true,
+        }
+    }
+
+    /// Scores a result under the objective (higher is better)
+    /// 
+    /// Results without the scored metric (phases not measured) score lowest.
+    pub fn score(&self, result: &BenchmarkResult) -> f64 {
+        match *self {
+            Objective::MaxCompressionRate | Objective::MaxCompressionRateWithAccessTime(_) => result.compression_rate,
+            Objective::MinAccessTime | Objective::MinAccessTimeWithRate(_) => {
+                result.average_random_access_time.map_or(f64::NEG_INFINITY, |access_time| -(access_time as f64))
+            }
+            Objective::MaxCompressionSpeed => result.compression_speed.unwrap_or(f64::NEG_INFINITY),
+        }
+    }
+
+    /// Selects the best feasible trial
+    /// 
+    /// # Arguments
+    /// - `trials`: Evaluated configurations
+    /// 
+    /// # Returns
+    /// The feasible trial with the highest score, or `None` if no trial is feasible
+    pub fn best<'a>(&self, trials: &'a [Trial]) -> Option<&'a Trial> {
+        trials
+            .iter()
+            .filter(|trial| self.is_feasible(&trial.result))
+            .max_by(|a, b| self.score(&a.result).total_cmp(&self.score(&b.result)))
+    }
+}